@@ -1,9 +1,19 @@
-use std::{collections::HashMap, path::PathBuf};
-
 fn main() {
-    let library = HashMap::from([("lucide".to_string(), PathBuf::from(lucide_slint::lib()))]);
-    let config = slint_build::CompilerConfiguration::new().with_library_paths(library);
+    // 只有界面需要编译 Slint 文件；关闭 gui feature 作为库使用时跳过
+    #[cfg(feature = "gui")]
+    {
+        use std::{collections::HashMap, path::PathBuf};
+
+        let library = HashMap::from([("lucide".to_string(), PathBuf::from(lucide_slint::lib()))]);
+        // 界面文字的译文与命令行共用 lang 目录，以中文原文为键，不区分上下文
+        println!("cargo:rerun-if-changed=lang");
+        let config = slint_build::CompilerConfiguration::new()
+            .with_library_paths(library)
+            .with_bundled_translations("lang")
+            .with_default_translation_context(slint_build::DefaultTranslationContext::None);
 
-    // Specify your Slint code entry here
-    slint_build::compile_with_config("ui/app_window.slint", config).expect("Slint build failed");
+        // Specify your Slint code entry here
+        slint_build::compile_with_config("ui/app_window.slint", config)
+            .expect("Slint build failed");
+    }
 }
@@ -0,0 +1,73 @@
+/*
+ * mir2_library C 接口
+ *
+ * 手写的声明，与 src/ffi.rs 的导出函数和常量一致，修改导出函数时同步修改
+ * （src/ffi.rs 的测试会检查）。函数说明见 src/ffi.rs。
+ */
+
+#ifndef MIR2_LIBRARY_H
+#define MIR2_LIBRARY_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+/* 成功 */
+#define MIR2_OK 0
+
+/* 空帧（没有像素数据，缓冲区未写入） */
+#define MIR2_EMPTY_FRAME 1
+
+/* 失败，原因见 mir2_library_last_error */
+#define MIR2_ERROR -1
+
+/* 缓冲区不足以容纳整帧 */
+#define MIR2_BUFFER_TOO_SMALL -2
+
+/* 参数为空指针或索引超出范围 */
+#define MIR2_INVALID_ARGUMENT -3
+
+/* 已打开的库（不透明） */
+typedef struct Mir2Library Mir2Library;
+
+/* 帧信息 */
+typedef struct Mir2FrameInfo {
+  /* 宽度（空帧为 0） */
+  int32_t width;
+  /* 高度（空帧为 0） */
+  int32_t height;
+  /* X 偏移 */
+  int32_t x;
+  /* Y 偏移 */
+  int32_t y;
+} Mir2FrameInfo;
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* 打开库文件（UTF-8 路径），失败时返回 NULL */
+Mir2Library *mir2_library_open(const char *path);
+
+/* 释放 mir2_library_open 返回的库，NULL 时什么也不做 */
+void mir2_library_free(Mir2Library *library);
+
+/* 帧数，NULL 时返回 0 */
+size_t mir2_library_count(const Mir2Library *library);
+
+/* 读取帧的尺寸和偏移 */
+int32_t mir2_library_frame_info(Mir2Library *library, size_t index, Mir2FrameInfo *info);
+
+/* 把帧解码为 RGBA8 写入 buffer（至少 width * height * 4 字节，自上而下逐行） */
+int32_t mir2_library_decode_frame(Mir2Library *library,
+                                  size_t index,
+                                  uint8_t *buffer,
+                                  size_t buffer_len);
+
+/* 本线程最近一次失败的原因（UTF-8），下一次失败前一直有效 */
+const char *mir2_library_last_error(void);
+
+#ifdef __cplusplus
+}  /* extern "C" */
+#endif
+
+#endif  /* MIR2_LIBRARY_H */
@@ -0,0 +1,413 @@
+//! 编辑器操作表
+//!
+//! 界面的命令面板（Ctrl+P）和命令行的 `actions` 命令共用这里的操作列表：每个操作有
+//! 固定的标识（如 `export-range`）、显示名称和对应的命令行用法。界面按标识分派，
+//! 工具栏按钮和命令面板执行的是 `AppWindow` 中同一个 `run_action`。
+//!
+//! 命令面板按模糊匹配排序：输入的字符依次出现在名称或标识中即匹配，连续的字符和
+//! 词首的字符得分更高。
+
+use crate::i18n::tr;
+
+/// 编辑器操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditorAction {
+    /// 打开文件
+    OpenFile,
+    /// 打开 URL
+    OpenUrl,
+    /// 打开项目
+    OpenProject,
+    /// 打开地图
+    OpenMap,
+    /// 快速预览（仅读取文件头）
+    QuickPreview,
+    /// 保存文件
+    SaveFile,
+    /// 另存为
+    SaveAs,
+    /// 导出当前帧为 PNG
+    ExportPng,
+    /// 导出一段帧为 PNG
+    ExportRange,
+    /// 导出所选帧
+    ExportSelected,
+    /// 导出视频
+    ExportVideo,
+    /// 动画序列
+    Animations,
+    /// 播放或停止动画
+    PlayAnimation,
+    /// 图块拼接预览
+    Tiles,
+    /// 跳转到指定帧
+    GotoFrame,
+    /// 切换基础层和遮罩层
+    ToggleMask,
+    /// 显示或隐藏阴影
+    ToggleShadow,
+    /// 洋葱皮
+    ToggleOnion,
+    /// 地图格预览
+    TogglePlacement,
+    /// 像素检查
+    ToggleInspector,
+    /// 切换预览背景
+    TogglePreviewBackground,
+    /// 查找相似帧
+    FindSimilar,
+    /// 体积分析
+    LibraryStats,
+    /// 替换图像
+    ReplaceImage,
+    /// 导入遮罩
+    ImportMask,
+    /// 按文件名从文件夹批量替换和追加帧
+    ImportFolder,
+    /// 删除当前帧
+    DeleteFrame,
+    /// 撤销删除
+    UndoDelete,
+    /// 锁定或解锁当前帧
+    ToggleLock,
+    /// 添加或删除当前帧的书签
+    ToggleBookmark,
+    /// 跳到下一个书签
+    NextBookmark,
+    /// 跳到上一个书签
+    PreviousBookmark,
+    /// 仅追加模式
+    ToggleAppendOnly,
+    /// 导出 PNG 时保留调色板
+    ToggleIndexedPng,
+    /// 复制帧
+    CopyFrame,
+    /// 粘贴帧
+    PasteFrame,
+    /// 复制图像到系统剪贴板
+    CopyImage,
+    /// 统一帧尺寸
+    Normalize,
+    /// 填充画布
+    PadCanvas,
+    /// 按内容下边缘对齐一段帧的脚底
+    AlignBaseline,
+    /// 删除全部空帧
+    RemoveEmpty,
+    /// 删除末尾的空帧
+    RemoveTrailingEmpty,
+    /// 导出索引对照表
+    ExportRemap,
+    /// 与另一个库对比
+    Compare,
+    /// 资源目录完整性自检
+    SelfTest,
+    /// 格式说明
+    FormatDoc,
+    /// 展开或收起问题面板
+    ToggleProblems,
+    /// 切换缩略图网格视图
+    ToggleThumbnailGrid,
+    /// 运行 Rhai 脚本
+    RunScript,
+    /// 设置
+    Settings,
+    /// 快捷键
+    Shortcuts,
+}
+
+impl EditorAction {
+    /// 全部操作（命令面板未输入时的顺序）
+    pub const ALL: [EditorAction; 51] = [
+        EditorAction::OpenFile,
+        EditorAction::OpenUrl,
+        EditorAction::OpenProject,
+        EditorAction::OpenMap,
+        EditorAction::QuickPreview,
+        EditorAction::SaveFile,
+        EditorAction::SaveAs,
+        EditorAction::ExportPng,
+        EditorAction::ExportRange,
+        EditorAction::ExportSelected,
+        EditorAction::ExportVideo,
+        EditorAction::Animations,
+        EditorAction::PlayAnimation,
+        EditorAction::Tiles,
+        EditorAction::GotoFrame,
+        EditorAction::ToggleMask,
+        EditorAction::ToggleShadow,
+        EditorAction::ToggleOnion,
+        EditorAction::TogglePlacement,
+        EditorAction::ToggleInspector,
+        EditorAction::TogglePreviewBackground,
+        EditorAction::FindSimilar,
+        EditorAction::LibraryStats,
+        EditorAction::ReplaceImage,
+        EditorAction::ImportMask,
+        EditorAction::ImportFolder,
+        EditorAction::DeleteFrame,
+        EditorAction::UndoDelete,
+        EditorAction::ToggleLock,
+        EditorAction::ToggleBookmark,
+        EditorAction::NextBookmark,
+        EditorAction::PreviousBookmark,
+        EditorAction::ToggleAppendOnly,
+        EditorAction::ToggleIndexedPng,
+        EditorAction::CopyFrame,
+        EditorAction::PasteFrame,
+        EditorAction::CopyImage,
+        EditorAction::Normalize,
+        EditorAction::PadCanvas,
+        EditorAction::AlignBaseline,
+        EditorAction::RemoveEmpty,
+        EditorAction::RemoveTrailingEmpty,
+        EditorAction::ExportRemap,
+        EditorAction::Compare,
+        EditorAction::SelfTest,
+        EditorAction::FormatDoc,
+        EditorAction::ToggleProblems,
+        EditorAction::ToggleThumbnailGrid,
+        EditorAction::RunScript,
+        EditorAction::Settings,
+        EditorAction::Shortcuts,
+    ];
+
+    /// 标识（界面分派和 `actions` 命令中使用）
+    pub fn id(self) -> &'static str {
+        match self {
+            EditorAction::OpenFile => "open-file",
+            EditorAction::OpenUrl => "open-url",
+            EditorAction::OpenProject => "open-project",
+            EditorAction::OpenMap => "open-map",
+            EditorAction::QuickPreview => "quick-preview",
+            EditorAction::SaveFile => "save-file",
+            EditorAction::SaveAs => "save-as",
+            EditorAction::ExportPng => "export-png",
+            EditorAction::ExportRange => "export-range",
+            EditorAction::ExportSelected => "export-selected",
+            EditorAction::ExportVideo => "export-video",
+            EditorAction::Animations => "animations",
+            EditorAction::PlayAnimation => "play-animation",
+            EditorAction::Tiles => "tiles",
+            EditorAction::GotoFrame => "goto-frame",
+            EditorAction::ToggleMask => "toggle-mask",
+            EditorAction::ToggleShadow => "toggle-shadow",
+            EditorAction::ToggleOnion => "toggle-onion",
+            EditorAction::TogglePlacement => "toggle-placement",
+            EditorAction::ToggleInspector => "toggle-inspector",
+            EditorAction::TogglePreviewBackground => "toggle-preview-bg",
+            EditorAction::FindSimilar => "find-similar",
+            EditorAction::LibraryStats => "library-stats",
+            EditorAction::ReplaceImage => "replace-image",
+            EditorAction::ImportMask => "import-mask",
+            EditorAction::ImportFolder => "import-folder",
+            EditorAction::DeleteFrame => "delete-frame",
+            EditorAction::UndoDelete => "undo-delete",
+            EditorAction::ToggleLock => "toggle-lock",
+            EditorAction::ToggleBookmark => "toggle-bookmark",
+            EditorAction::NextBookmark => "next-bookmark",
+            EditorAction::PreviousBookmark => "previous-bookmark",
+            EditorAction::ToggleAppendOnly => "toggle-append-only",
+            EditorAction::ToggleIndexedPng => "toggle-indexed-png",
+            EditorAction::CopyFrame => "copy-frame",
+            EditorAction::PasteFrame => "paste-frame",
+            EditorAction::CopyImage => "copy-image",
+            EditorAction::Normalize => "normalize",
+            EditorAction::PadCanvas => "pad-canvas",
+            EditorAction::AlignBaseline => "align-baseline",
+            EditorAction::RemoveEmpty => "remove-empty",
+            EditorAction::RemoveTrailingEmpty => "remove-trailing-empty",
+            EditorAction::ExportRemap => "export-remap",
+            EditorAction::Compare => "compare",
+            EditorAction::SelfTest => "self-test",
+            EditorAction::FormatDoc => "format-doc",
+            EditorAction::ToggleProblems => "toggle-problems",
+            EditorAction::ToggleThumbnailGrid => "toggle-thumbnail-grid",
+            EditorAction::RunScript => "run-script",
+            EditorAction::Settings => "settings",
+            EditorAction::Shortcuts => "shortcuts",
+        }
+    }
+
+    /// 按标识查找
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|action| action.id() == id)
+    }
+
+    /// 显示名称
+    pub fn label(self) -> &'static str {
+        match self {
+            EditorAction::OpenFile => tr!("打开文件"),
+            EditorAction::OpenUrl => tr!("打开 URL"),
+            EditorAction::OpenProject => tr!("打开项目"),
+            EditorAction::OpenMap => tr!("打开地图"),
+            EditorAction::QuickPreview => tr!("快速预览（仅读取文件头）"),
+            EditorAction::SaveFile => tr!("保存文件"),
+            EditorAction::SaveAs => tr!("另存为"),
+            EditorAction::ExportPng => tr!("导出图像"),
+            EditorAction::ExportRange => tr!("导出一段帧为 PNG"),
+            EditorAction::ExportSelected => tr!("导出所选帧"),
+            EditorAction::ExportVideo => tr!("导出视频"),
+            EditorAction::Animations => tr!("动画序列"),
+            EditorAction::PlayAnimation => tr!("播放 / 停止动画"),
+            EditorAction::Tiles => tr!("图块拼接预览"),
+            EditorAction::GotoFrame => tr!("跳转到帧"),
+            EditorAction::ToggleMask => tr!("切换遮罩层"),
+            EditorAction::ToggleShadow => tr!("显示 / 隐藏阴影"),
+            EditorAction::ToggleOnion => tr!("显示 / 隐藏洋葱皮"),
+            EditorAction::TogglePlacement => tr!("地图格预览（按偏移摆放）"),
+            EditorAction::ToggleInspector => tr!("像素检查"),
+            EditorAction::TogglePreviewBackground => tr!("切换预览背景"),
+            EditorAction::FindSimilar => tr!("查找相似帧 (选择 PNG)"),
+            EditorAction::LibraryStats => tr!("体积分析..."),
+            EditorAction::ReplaceImage => tr!("替换图像"),
+            EditorAction::ImportMask => tr!("导入遮罩"),
+            EditorAction::ImportFolder => tr!("从文件夹导入..."),
+            EditorAction::DeleteFrame => tr!("删除图像"),
+            EditorAction::UndoDelete => tr!("撤销删除"),
+            EditorAction::ToggleLock => tr!("锁定 / 解锁帧"),
+            EditorAction::ToggleBookmark => tr!("添加 / 删除书签"),
+            EditorAction::NextBookmark => tr!("下一个书签"),
+            EditorAction::PreviousBookmark => tr!("上一个书签"),
+            EditorAction::ToggleAppendOnly => tr!("切换仅追加模式"),
+            EditorAction::ToggleIndexedPng => tr!("切换导出 PNG 时保留调色板"),
+            EditorAction::CopyFrame => tr!("复制帧"),
+            EditorAction::PasteFrame => tr!("粘贴帧"),
+            EditorAction::CopyImage => tr!("复制图像到系统剪贴板"),
+            EditorAction::Normalize => tr!("统一帧尺寸"),
+            EditorAction::PadCanvas => tr!("填充画布"),
+            EditorAction::AlignBaseline => tr!("基线对齐..."),
+            EditorAction::RemoveEmpty => tr!("删除全部空帧"),
+            EditorAction::RemoveTrailingEmpty => tr!("删除末尾的空帧"),
+            EditorAction::ExportRemap => tr!("导出索引对照表..."),
+            EditorAction::Compare => tr!("与另一个库对比"),
+            EditorAction::SelfTest => tr!("资源目录完整性自检"),
+            EditorAction::FormatDoc => tr!("格式说明"),
+            EditorAction::ToggleProblems => tr!("问题面板"),
+            EditorAction::ToggleThumbnailGrid => tr!("缩略图网格视图"),
+            EditorAction::RunScript => tr!("运行脚本"),
+            EditorAction::Settings => tr!("设置"),
+            EditorAction::Shortcuts => tr!("快捷键"),
+        }
+    }
+
+    /// 对应的命令行用法，只能在界面中执行的操作为 `None`
+    pub fn cli(self) -> Option<&'static str> {
+        let usage = match self {
+            EditorAction::OpenFile => "open <库文件>",
+            EditorAction::OpenUrl => "open <URL> [--refresh]",
+            EditorAction::OpenProject => "project <项目.lep>",
+            EditorAction::OpenMap => "map <地图.map> [<输出.png>]",
+            EditorAction::QuickPreview => "inventory <库文件>",
+            EditorAction::ExportPng => "export-frame <库文件> <帧> <输出文件>",
+            EditorAction::ExportRange | EditorAction::ExportSelected => {
+                "export <库文件> <输出目录> [--range 起-止]"
+            }
+            EditorAction::ExportVideo => "video <库文件> <输出.webm|.mp4>",
+            EditorAction::Animations => "anim <库文件>",
+            EditorAction::PlayAnimation => "export-gif <库文件> <输出.gif>",
+            EditorAction::Tiles => "tiles <库文件> <输出.png>",
+            EditorAction::ImportFolder => "import-dir <目录> <库文件>",
+            EditorAction::FindSimilar => "similar <目标.png|库文件#帧> <库文件...>",
+            EditorAction::LibraryStats => "stats <库文件> --analyze",
+            EditorAction::ToggleIndexedPng => "export <库文件> <输出目录> --indexed",
+            EditorAction::ToggleLock => "lock / unlock <库文件> [--range 起-止]",
+            EditorAction::ToggleBookmark => "project <项目.lep> bookmark <库文件> <帧> [说明...]",
+            EditorAction::Normalize => "normalize <库文件.Lib> [--range 起-止]",
+            EditorAction::PadCanvas => "pad <库文件.Lib> --size 宽x高",
+            EditorAction::AlignBaseline => "align <库文件.Lib> [--range 起-止] [--apply]",
+            EditorAction::RemoveEmpty => "compact <库文件.Lib>",
+            EditorAction::RemoveTrailingEmpty => "compact <库文件.Lib> --trailing",
+            EditorAction::ExportRemap => "compact <库文件.Lib> --remap 对照表.csv",
+            EditorAction::SelfTest => "self-test [目录...]",
+            EditorAction::FormatDoc => "spec <格式|库文件>",
+            EditorAction::RunScript => "script <脚本.rhai> [参数...]",
+            _ => return None,
+        };
+        Some(usage)
+    }
+}
+
+/// 模糊匹配得分：`query` 的字符（忽略大小写和空白）依次出现在 `text` 中时返回得分，
+/// 越大越好；否则返回 `None`
+///
+/// 每个匹配的字符得 1 分，与上一个匹配的字符相连再加 4 分，位于词首再加 3 分；
+/// 第一个匹配字符之前每跳过一个字符扣 1 分（最多扣 5 分）。
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i32> {
+    let mut query = query
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .peekable();
+    let mut score = 0;
+    let mut previous: Option<char> = None;
+    let mut last_match: Option<usize> = None;
+    for (position, c) in text.chars().flat_map(char::to_lowercase).enumerate() {
+        let Some(&wanted) = query.peek() else {
+            break;
+        };
+        if c == wanted {
+            query.next();
+            score += 1;
+            match last_match {
+                Some(last) if last + 1 == position => score += 4,
+                None => score -= position.min(5) as i32,
+                _ => {}
+            }
+            if previous.is_none_or(|p| !p.is_alphanumeric() || (p.is_ascii() != c.is_ascii())) {
+                score += 3;
+            }
+            last_match = Some(position);
+        }
+        previous = Some(c);
+    }
+    query.peek().is_none().then_some(score)
+}
+
+/// 按名称和标识模糊查找操作，得分高的在前；`query` 为空时返回全部操作
+pub fn search(query: &str) -> Vec<EditorAction> {
+    let mut scored: Vec<(i32, EditorAction)> = EditorAction::ALL
+        .iter()
+        .filter_map(|action| {
+            let score = [action.label(), action.id()]
+                .into_iter()
+                .filter_map(|text| fuzzy_score(query, text))
+                .max()?;
+            Some((score, *action))
+        })
+        .collect();
+    // 稳定排序：得分相同时保持操作表中的顺序
+    scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+    scored.into_iter().map(|(_, action)| action).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry_and_fuzzy_search() {
+        // 标识不重复，且界面的 run_action 能分派每个操作
+        let window = include_str!("../ui/app_window.slint");
+        for action in EditorAction::ALL {
+            assert_eq!(EditorAction::from_id(action.id()), Some(action));
+            assert!(
+                window.contains(&format!("id == \"{}\"", action.id())),
+                "界面未分派 {}",
+                action.id()
+            );
+        }
+
+        assert!(fuzzy_score("", "打开文件").is_some());
+        assert!(fuzzy_score("exr", "export-range").is_some());
+        assert!(fuzzy_score("rex", "export-range").is_none());
+        assert!(fuzzy_score("exp", "export-png") > fuzzy_score("exp", "open-project"));
+
+        assert_eq!(search("").len(), EditorAction::ALL.len());
+        assert_eq!(search("run script")[0], EditorAction::RunScript);
+        assert_eq!(search("toggle-mask")[0], EditorAction::ToggleMask);
+        assert_eq!(search("遮罩")[0], EditorAction::ToggleMask);
+        assert_eq!(search("导出帧")[0], EditorAction::ExportRange);
+        assert!(search("qqq").is_empty());
+    }
+}
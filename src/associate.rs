@@ -0,0 +1,124 @@
+//! Windows 文件关联（`associate` 命令）
+//!
+//! 在当前用户的注册表中登记编辑器，资源管理器中右键库文件或项目文件时，“打开方式”
+//! 列表里会出现编辑器，双击时把文件路径作为参数传给编辑器，界面启动后直接打开。
+//! 只加入 `OpenWithProgids`，不改动各扩展名的默认程序：`.lib` 同时是 C/C++ 的静态库，
+//! 默认程序由用户在“打开方式”中自己选择。
+//!
+//! 登记写在 `HKEY_CURRENT_USER\Software\Classes` 下，不需要管理员权限。内容先生成
+//! `.reg` 脚本，可以直接导入（Windows 上由 `reg import` 完成），也可以写出文件检查后
+//! 双击导入，或分发给其他机器。
+
+use mir2_library::error::{LibraryError, Result};
+use std::path::Path;
+
+/// 登记的文件类型标识
+pub const PROG_ID: &str = "Mir2LibraryEditor.Library";
+
+/// 加入“打开方式”的扩展名（库文件和项目文件）
+pub const EXTENSIONS: [&str; 5] = ["wil", "wzl", "lib", "wtl", "lep"];
+
+/// 注册表中的类注册位置
+const CLASSES_KEY: &str = r"HKEY_CURRENT_USER\Software\Classes";
+
+/// 生成登记（`remove` 为真时为取消登记）的 `.reg` 脚本，`exe` 为编辑器的路径
+pub fn reg_script(exe: &Path, remove: bool) -> String {
+    let mut lines = vec![
+        "Windows Registry Editor Version 5.00".to_string(),
+        String::new(),
+    ];
+    if remove {
+        lines.push(format!(r"[-{}\{}]", CLASSES_KEY, PROG_ID));
+        lines.push(String::new());
+        for ext in EXTENSIONS {
+            lines.push(format!(r"[{}\.{}\OpenWithProgids]", CLASSES_KEY, ext));
+            lines.push(format!("\"{}\"=-", PROG_ID));
+            lines.push(String::new());
+        }
+        return lines.join("\r\n");
+    }
+
+    let exe = exe.display().to_string();
+    let command = format!("\"{}\" \"%1\"", exe);
+    lines.push(format!(r"[{}\{}]", CLASSES_KEY, PROG_ID));
+    lines.push(format!("@={}", reg_string("传奇资源库")));
+    lines.push(String::new());
+    lines.push(format!(r"[{}\{}\DefaultIcon]", CLASSES_KEY, PROG_ID));
+    lines.push(format!("@={}", reg_string(&format!("{},0", exe))));
+    lines.push(String::new());
+    lines.push(format!(r"[{}\{}\shell\open\command]", CLASSES_KEY, PROG_ID));
+    lines.push(format!("@={}", reg_string(&command)));
+    lines.push(String::new());
+    for ext in EXTENSIONS {
+        lines.push(format!(r"[{}\.{}\OpenWithProgids]", CLASSES_KEY, ext));
+        lines.push(format!("\"{}\"=\"\"", PROG_ID));
+        lines.push(String::new());
+    }
+    lines.join("\r\n")
+}
+
+/// `.reg` 中的字符串值：加引号，反斜杠和引号转义
+fn reg_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', r"\\").replace('"', "\\\""))
+}
+
+/// 把 `.reg` 脚本编码为带 BOM 的 UTF-16LE（“Version 5.00”脚本的编码，路径中可以有中文）
+pub fn encode_reg(script: &str) -> Vec<u8> {
+    let mut data = vec![0xFF, 0xFE];
+    data.extend(script.encode_utf16().flat_map(u16::to_le_bytes));
+    data
+}
+
+/// 写出 `.reg` 脚本
+pub fn write_reg(path: &Path, exe: &Path, remove: bool) -> Result<()> {
+    std::fs::write(path, encode_reg(&reg_script(exe, remove)))?;
+    Ok(())
+}
+
+/// 导入登记（或取消登记）到当前用户的注册表，仅 Windows
+pub fn register(exe: &Path, remove: bool) -> Result<()> {
+    if !cfg!(windows) {
+        return Err(LibraryError::Unsupported(
+            "文件关联".to_string(),
+            "只能在 Windows 上登记，其他系统可以用 --reg 写出脚本",
+        ));
+    }
+    let script = std::env::temp_dir().join(format!("library_editor_{}.reg", std::process::id()));
+    write_reg(&script, exe, remove)?;
+    let status = std::process::Command::new("reg")
+        .arg("import")
+        .arg(&script)
+        .status();
+    let _ = std::fs::remove_file(&script);
+    if !status?.success() {
+        return Err(std::io::Error::other("reg import 执行失败").into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reg_script() {
+        let exe = Path::new(r#"C:\工具\library_editor.exe"#);
+        let script = reg_script(exe, false);
+        assert!(script.starts_with("Windows Registry Editor Version 5.00\r\n"));
+        assert!(script.contains(r#"@="\"C:\\工具\\library_editor.exe\" \"%1\"""#));
+        assert!(script.contains(
+            "[HKEY_CURRENT_USER\\Software\\Classes\\.lib\\OpenWithProgids]\r\n\"Mir2LibraryEditor.Library\"=\"\""
+        ));
+
+        // 取消登记只删除自己的条目
+        let script = reg_script(exe, true);
+        assert!(
+            script.contains(r"[-HKEY_CURRENT_USER\Software\Classes\Mir2LibraryEditor.Library]")
+        );
+        assert!(script.contains("\"Mir2LibraryEditor.Library\"=-"));
+        assert!(!script.contains("library_editor.exe"));
+
+        let data = encode_reg("W");
+        assert_eq!(data, [0xFF, 0xFE, b'W', 0]);
+    }
+}
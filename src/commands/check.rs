@@ -0,0 +1,87 @@
+//! 批量检查命令：`self-test`
+
+use super::report;
+use crate::i18n::tr;
+use crate::{locale, progress_bar};
+use mir2_library::{error, formats};
+
+use error::Result;
+use std::path::{Path, PathBuf};
+
+/// 检查资源目录下全部库的完整性，列出有问题的文件，可另存为 CSV 报告
+pub(super) fn self_test(dirs: &[&str], csv: Option<&Path>) -> Result<()> {
+    let dirs: Vec<PathBuf> = if dirs.is_empty() {
+        formats::integrity::configured_dirs()
+    } else {
+        dirs.iter().map(PathBuf::from).collect()
+    };
+    if dirs.is_empty() {
+        return Err(error::LibraryError::ParseError(format!(
+            "未指定资源目录（命令行参数或环境变量 {}）",
+            formats::integrity::ASSET_DIRS_ENV
+        )));
+    }
+
+    let total = dirs
+        .iter()
+        .map(|dir| formats::integrity::find_libraries(dir).len())
+        .sum();
+    let bar = progress_bar::new(total, tr!("检查"));
+    let reports = formats::integrity::scan_with(&dirs, |_| bar.inc(1));
+    bar.finish_and_clear();
+    let problems: Vec<_> = reports.iter().filter(|r| !r.is_ok()).collect();
+    // 按显示宽度对齐路径列（中文文件名每个字占两列）
+    let paths: Vec<String> = problems
+        .iter()
+        .map(|r| r.path.display().to_string())
+        .collect();
+    let width = paths.iter().map(|p| locale::display_width(p)).max();
+    for (report, path) in problems.iter().zip(&paths) {
+        report!(
+            "{}",
+            tr!(
+                "{}  {} {} 帧",
+                locale::pad(path, width.unwrap_or(0)),
+                locale::pad(report.format.unwrap_or(tr!("未知格式")), 16),
+                locale::count(report.frames as u64)
+            )
+        );
+        for problem in &report.problems {
+            report!("  {}", problem);
+        }
+    }
+    report!(
+        "{}",
+        tr!(
+            "已检查 {} 个库，{} 个有问题",
+            locale::count(reports.len() as u64),
+            locale::count(problems.len() as u64)
+        )
+    );
+
+    if let Some(csv) = csv {
+        let mut rows = vec![vec![
+            "文件".to_string(),
+            "格式".to_string(),
+            "帧数".to_string(),
+            "问题".to_string(),
+        ]];
+        rows.extend(reports.iter().map(|report| {
+            vec![
+                report.path.display().to_string(),
+                report.format.unwrap_or_default().to_string(),
+                report.frames.to_string(),
+                report.problems.join("；"),
+            ]
+        }));
+        locale::write_csv(csv, &rows)?;
+        report!("{}", tr!("报告已写入 {}", format!("{:?}", csv)));
+    }
+    if !problems.is_empty() {
+        return Err(error::LibraryError::PartialFailure(
+            problems.len(),
+            reports.len(),
+        ));
+    }
+    Ok(())
+}
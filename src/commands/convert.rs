@@ -0,0 +1,82 @@
+//! 批量转换命令：`convert-dir`
+
+use super::report;
+use crate::i18n::tr;
+use crate::{locale, progress_bar};
+use mir2_library::{error, formats};
+
+use error::Result;
+use formats::mlibrary_v2::EncodeOptions;
+use std::path::Path;
+
+/// 把目录下的全部库并行转换为 .Lib，输出每个文件的结果
+pub(super) fn convert_dir(
+    src: &Path,
+    dst: &Path,
+    to: Option<&str>,
+    jobs: Option<&str>,
+    encode: EncodeOptions,
+) -> Result<()> {
+    if let Some(to) = to.filter(|to| !to.eq_ignore_ascii_case("lib")) {
+        return Err(error::LibraryError::ParseError(format!(
+            "不支持的转换目标: {}（只能转换为 lib）",
+            to
+        )));
+    }
+    let jobs = match jobs {
+        Some(text) => text
+            .parse::<usize>()
+            .ok()
+            .filter(|&jobs| jobs > 0)
+            .ok_or_else(|| error::LibraryError::ParseError(format!("无效的线程数: {}", text)))?,
+        None => std::thread::available_parallelism().map_or(1, |n| n.get()),
+    };
+
+    let started = std::time::Instant::now();
+    let bar = progress_bar::new(formats::integrity::find_libraries(src).len(), tr!("转换"));
+    let results = formats::convert::convert_dir(src, dst, encode, jobs, |conversion| {
+        tracing::debug!("已完成 {:?}", conversion.source);
+        bar.inc(1);
+    })?;
+    bar.finish_and_clear();
+
+    // 按显示宽度对齐路径列（中文文件名每个字占两列）
+    let paths: Vec<String> = results
+        .iter()
+        .map(|c| {
+            let relative = c.source.strip_prefix(src).unwrap_or(&c.source);
+            relative.display().to_string()
+        })
+        .collect();
+    let width = paths.iter().map(|p| locale::display_width(p)).max();
+    for (conversion, path) in results.iter().zip(&paths) {
+        let path = locale::pad(path, width.unwrap_or(0));
+        match &conversion.result {
+            Ok(converted) => report!(
+                "{}",
+                tr!(
+                    "{}  {} {} 帧",
+                    path,
+                    locale::pad(&converted.format, 16),
+                    locale::count(converted.frames as u64)
+                )
+            ),
+            Err(e) => report!("{}", tr!("{}  失败: {}", path, e)),
+        }
+    }
+    let failed = results.iter().filter(|c| c.result.is_err()).count();
+    report!(
+        "{}",
+        tr!(
+            "已转换 {} 个库到 {}，{} 个失败，用时 {} 秒",
+            locale::count((results.len() - failed) as u64),
+            format!("{:?}", dst),
+            locale::count(failed as u64),
+            format!("{:.1}", started.elapsed().as_secs_f64())
+        )
+    );
+    if failed > 0 {
+        return Err(error::LibraryError::PartialFailure(failed, results.len()));
+    }
+    Ok(())
+}
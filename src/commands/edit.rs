@@ -0,0 +1,373 @@
+//! 修改库的命令：`strip-masks`、`recompress`、`compact`、`normalize`、`align`、`pad`、
+//! `lock`、`unlock`
+
+use super::{check_codec_level, format_indices, parse_compression_level, parse_range, report};
+use crate::i18n::tr;
+use crate::locale;
+use mir2_library::{error, formats, image};
+
+use error::Result;
+use formats::LibraryLoader;
+use formats::mlibrary_v2::Codec;
+use formats::remap::IndexRemap;
+use image::baseline;
+use image::compression::CompressionLevel;
+use std::path::Path;
+
+/// 去掉帧的遮罩层并保存，报告受影响的帧索引
+pub(super) fn strip_masks(lib_path: &Path, range: Option<&str>) -> Result<()> {
+    let (info, mut loader) = LibraryLoader::load(lib_path)?;
+    let range = match range {
+        Some(text) => parse_range(text)?,
+        None => 0..info.image_count,
+    };
+
+    let affected = loader.strip_masks(range.clone())?;
+    if affected.is_empty() {
+        report!(
+            "{}",
+            tr!(
+                "{} 的 {} 范围内没有带遮罩的帧",
+                info.file_name,
+                format!("{:?}", range)
+            )
+        );
+        return Ok(());
+    }
+
+    loader.save()?;
+    report!(
+        "{}",
+        tr!(
+            "已去除 {} 帧的遮罩层 ({}): {}",
+            affected.len(),
+            info.file_name,
+            format_indices(&affected)
+        )
+    );
+    Ok(())
+}
+
+/// 重新压缩全部帧并保存，报告文件大小的变化
+pub(super) fn recompress_library(
+    lib_path: &Path,
+    level: Option<&str>,
+    codec: Option<&str>,
+) -> Result<()> {
+    let explicit_level = level.is_some();
+    let level = match level {
+        Some(text) => parse_compression_level(text)?,
+        None => CompressionLevel::BEST,
+    };
+    let codec = match codec {
+        Some(text) => Some(match text.trim().to_ascii_lowercase().as_str() {
+            "gzip" => Codec::Gzip,
+            "zstd" => Codec::Zstd,
+            _ => {
+                return Err(error::LibraryError::ParseError(format!(
+                    "无效的压缩方式: {}",
+                    text
+                )));
+            }
+        }),
+        None => None,
+    };
+    // 未指定 --level 时按默认级别，对 zstd 同样有效
+    if let Some(codec) = codec
+        && explicit_level
+    {
+        check_codec_level(codec, level, "--level")?;
+    }
+
+    let before = std::fs::metadata(lib_path)?.len();
+    let (info, mut loader) = LibraryLoader::load(lib_path)?;
+    loader.recompress(level, codec)?;
+    let after = std::fs::metadata(lib_path)?.len();
+    report!(
+        "{}",
+        tr!(
+            "已按 {} 级重新压缩 {} ({} 帧): {} -> {} ({}%)",
+            level.get(),
+            info.file_name,
+            locale::count(info.image_count as u64),
+            locale::size(before),
+            locale::size(after),
+            format!(
+                "{:+.1}",
+                (after as f64 - before as f64) * 100.0 / before.max(1) as f64
+            )
+        )
+    );
+    Ok(())
+}
+
+/// 删除空帧并保存，报告删除的帧和前移的索引段
+pub(super) fn remove_empty_frames(
+    lib_path: &Path,
+    trailing_only: bool,
+    remap_path: Option<&Path>,
+) -> Result<()> {
+    let (info, mut loader) = LibraryLoader::load(lib_path)?;
+    let remap = loader.remove_empty_frames(trailing_only)?;
+    let removed = remap.removed();
+    if removed.is_empty() {
+        report!("{}", tr!("{} 中没有可删除的空帧", info.file_name));
+        return Ok(());
+    }
+
+    loader.save()?;
+    report!(
+        "{}",
+        tr!(
+            "已删除 {} 个空帧 ({}): {}",
+            removed.len(),
+            info.file_name,
+            format_indices(&removed)
+        )
+    );
+    for segment in remap.shifted() {
+        report!(
+            "  #{}-{} -> #{}-{}",
+            segment.old_start,
+            segment.old_start + segment.len - 1,
+            segment.new_start,
+            segment.new_start + segment.len - 1
+        );
+    }
+    if let Some(remap_path) = remap_path {
+        write_remap(remap_path, &remap)?;
+        report!(
+            "{}",
+            tr!("索引对照表已写入 {}", format!("{:?}", remap_path))
+        );
+    }
+    Ok(())
+}
+
+/// 写出索引对照表：扩展名为 .json 时写 JSON，否则写 CSV
+pub(crate) fn write_remap(path: &Path, remap: &IndexRemap) -> Result<()> {
+    if path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"))
+    {
+        std::fs::write(path, remap.to_json()?)?;
+    } else {
+        locale::write_csv(path, &remap.csv_rows())?;
+    }
+    Ok(())
+}
+
+/// 将范围内的帧填充到相同尺寸并保存
+pub(super) fn normalize_frames(lib_path: &Path, range: Option<&str>) -> Result<()> {
+    let (info, mut loader) = LibraryLoader::load(lib_path)?;
+    let range = match range {
+        Some(text) => parse_range(text)?,
+        None => 0..info.image_count,
+    };
+
+    let Some(report) = loader.normalize_frames(range.clone())? else {
+        report!(
+            "{}",
+            tr!(
+                "{} 的 {} 范围内没有非空帧",
+                info.file_name,
+                format!("{:?}", range)
+            )
+        );
+        return Ok(());
+    };
+    if report.frames.is_empty() {
+        report!(
+            "{}",
+            tr!(
+                "{} 的帧已是统一尺寸 {}x{}",
+                info.file_name,
+                report.width,
+                report.height
+            )
+        );
+        return Ok(());
+    }
+
+    loader.save()?;
+    report!(
+        "{}",
+        tr!(
+            "已将 {} 帧统一为 {}x{}，偏移 ({}, {}) ({}): {}",
+            report.frames.len(),
+            report.width,
+            report.height,
+            report.x,
+            report.y,
+            info.file_name,
+            format_indices(&report.frames)
+        )
+    );
+    Ok(())
+}
+
+/// 按内容下边缘对齐一段帧的脚底：列出偏移修正，可导出对照图，`apply` 时写入并保存
+pub(super) fn align_frames(
+    lib_path: &Path,
+    range: Option<&str>,
+    horizontal: bool,
+    preview: Option<&Path>,
+    apply: bool,
+) -> Result<()> {
+    let (info, mut loader) = LibraryLoader::load(lib_path)?;
+    let range = match range {
+        Some(text) => parse_range(text)?,
+        None => 0..info.image_count,
+    };
+
+    let frames = loader.placed_range(range.clone())?;
+    let Some(plan) = baseline::plan(&frames, horizontal) else {
+        report!(
+            "{}",
+            tr!(
+                "{} 的 {} 范围内没有非空帧",
+                info.file_name,
+                format!("{:?}", range)
+            )
+        );
+        return Ok(());
+    };
+    report!("{}", tr!("基准基线 y = {}", plan.baseline));
+    for alignment in &plan.frames {
+        report!(
+            "  #{}: ({:+}, {:+})",
+            alignment.index,
+            alignment.dx,
+            alignment.dy
+        );
+    }
+    if let Some(path) = preview
+        && let Some(image) = baseline::preview(&frames, &plan)
+    {
+        image.save(path)?;
+        report!("{}", tr!("对照图 -> {}", format!("{:?}", path)));
+    }
+    if plan.frames.is_empty() {
+        report!("{}", tr!("{} 的帧已对齐", info.file_name));
+        return Ok(());
+    }
+    if !apply {
+        report!(
+            "{}",
+            tr!("{} 帧需要修正，加 --apply 写入", plan.frames.len())
+        );
+        return Ok(());
+    }
+
+    let shifted = loader.apply_alignment(&plan)?;
+    loader.save()?;
+    report!(
+        "{}",
+        tr!(
+            "已对齐 {} 帧 ({}): {}",
+            shifted.len(),
+            info.file_name,
+            format_indices(&shifted)
+        )
+    );
+    Ok(())
+}
+
+/// 将范围内的帧填充到统一的画布尺寸并保存
+pub(super) fn pad_frames(
+    lib_path: &Path,
+    size: Option<&str>,
+    anchor: Option<&str>,
+    range: Option<&str>,
+) -> Result<()> {
+    let size =
+        size.ok_or_else(|| error::LibraryError::ParseError("缺少 --size 宽x高".to_string()))?;
+    let (width, height) = parse_size(size)?;
+    let anchor = match anchor {
+        Some(text) => image::canvas::Anchor::parse(text)
+            .ok_or_else(|| error::LibraryError::ParseError(format!("无效的锚点: {}", text)))?,
+        None => image::canvas::Anchor::default(),
+    };
+
+    let (info, mut loader) = LibraryLoader::load(lib_path)?;
+    let range = match range {
+        Some(text) => parse_range(text)?,
+        None => 0..info.image_count,
+    };
+    let indices: Vec<usize> = (range.start..range.end.min(info.image_count)).collect();
+
+    let report = loader.pad_frames(&indices, width, height, anchor)?;
+    if !report.oversized.is_empty() {
+        report!(
+            "{}",
+            tr!(
+                "{} 帧比 {}x{} 大，未处理: {}",
+                report.oversized.len(),
+                width,
+                height,
+                format_indices(&report.oversized)
+            )
+        );
+    }
+    if report.frames.is_empty() {
+        report!("{}", tr!("{} 中没有需要填充的帧", info.file_name));
+        return Ok(());
+    }
+
+    loader.save()?;
+    report!(
+        "{}",
+        tr!(
+            "已将 {} 帧填充到 {}x{}，锚点 {} ({}): {}",
+            report.frames.len(),
+            width,
+            height,
+            anchor.name(),
+            info.file_name,
+            format_indices(&report.frames)
+        )
+    );
+    Ok(())
+}
+
+/// 解析尺寸：`宽x高`
+fn parse_size(text: &str) -> Result<(u32, u32)> {
+    let invalid = || error::LibraryError::ParseError(format!("无效的尺寸: {}", text));
+    let (width, height) = text.split_once(['x', 'X', '*']).ok_or_else(invalid)?;
+    let width = width.trim().parse().map_err(|_| invalid())?;
+    let height = height.trim().parse().map_err(|_| invalid())?;
+    Ok((width, height))
+}
+
+/// 锁定或解锁范围内的帧（写入库旁的锁定记录）
+pub(super) fn lock_frames(lib_path: &Path, range: Option<&str>, locked: bool) -> Result<()> {
+    let (info, mut loader) = LibraryLoader::load(lib_path)?;
+    let range = match range {
+        Some(text) => parse_range(text)?,
+        None if locked => {
+            let frames: Vec<usize> = loader.locks().iter().collect();
+            report!(
+                "{}",
+                tr!(
+                    "{} 已锁定 {} 帧: {}",
+                    info.file_name,
+                    frames.len(),
+                    format_indices(&frames)
+                )
+            );
+            return Ok(());
+        }
+        None => 0..info.image_count,
+    };
+
+    let indices: Vec<usize> = (range.start..range.end.min(info.image_count)).collect();
+    let changed = loader.set_locked(&indices, locked)?;
+    let (file_name, total) = (&info.file_name, loader.locks().len());
+    let text = if locked {
+        tr!("已锁定 {} 帧 ({})，共锁定 {} 帧", changed, file_name, total)
+    } else {
+        tr!("已解锁 {} 帧 ({})，共锁定 {} 帧", changed, file_name, total)
+    };
+    report!("{}", text);
+    Ok(())
+}
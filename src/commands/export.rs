@@ -0,0 +1,386 @@
+//! 导出命令：`export`、`export-frame`、`export-zip`、`video`、`export-gif`、`tiles`、
+//! `contact-sheet`
+
+use super::{option_value, parse_fps, parse_range, report};
+use crate::i18n::tr;
+use crate::progress_bar;
+use mir2_library::{error, formats, image};
+
+use error::Result;
+use formats::LibraryLoader;
+use formats::animation::AnimationSet;
+use formats::metadata::Manifest;
+use image::export::{ExportFormat, ExportOptions};
+use mir2_library::progress::Progress;
+use std::path::Path;
+
+/// 导出所有帧为 PNG，可选写出元数据 JSON
+pub(super) fn export_frames(
+    lib_path: &Path,
+    out_dir: &Path,
+    metadata: Option<&Path>,
+    indexed: bool,
+) -> Result<()> {
+    let (info, mut loader) = LibraryLoader::load(lib_path)?;
+    std::fs::create_dir_all(out_dir)?;
+
+    let bar = progress_bar::new(info.image_count, tr!("导出"));
+    let manifest = Manifest::collect_with_progress(
+        &mut loader,
+        "",
+        "mask_",
+        |name, img| {
+            img.save(out_dir.join(name))?;
+            Ok(())
+        },
+        |progress| {
+            if let Progress::FrameDecoded { done, .. } = progress {
+                bar.set_position(done as u64);
+            }
+        },
+    )?;
+    bar.finish_and_clear();
+
+    // 8 位调色板帧改写为索引色 PNG，文件名不变
+    if indexed {
+        let mut rewritten = 0;
+        for frame in &manifest.frames {
+            if let Some(name) = &frame.image
+                && loader.export_indexed_png(frame.index, &out_dir.join(name))?
+            {
+                rewritten += 1;
+            }
+        }
+        report!("{}", tr!("{} 帧写为索引色 PNG", rewritten));
+    }
+
+    if let Some(path) = metadata {
+        manifest.save(path)?;
+        report!("{}", tr!("元数据已写入 {}", format!("{:?}", path)));
+    }
+
+    report!(
+        "{}",
+        tr!(
+            "已导出 {} ({}) -> {}: {} 帧",
+            info.file_name,
+            info.format_name(),
+            format!("{:?}", out_dir),
+            manifest.frames.len()
+        )
+    );
+    Ok(())
+}
+
+/// 导出一段帧为 PNG（只有图像，文件名为帧索引）
+pub(super) fn export_range(
+    lib_path: &Path,
+    out_dir: &Path,
+    range: Option<&str>,
+    indexed: bool,
+) -> Result<()> {
+    let (info, mut loader) = LibraryLoader::load(lib_path)?;
+    let range = match range {
+        Some(text) => parse_range(text)?,
+        None => 0..info.image_count,
+    };
+    let frames: Vec<usize> = range
+        .clone()
+        .take_while(|i| *i < info.image_count)
+        .collect();
+    let written = loader.export_pngs(&frames, out_dir, indexed)?;
+    report!(
+        "{}",
+        tr!(
+            "已导出 {} 的 {} 范围 -> {}: {} 帧",
+            info.file_name,
+            format!("{:?}", range),
+            format!("{:?}", out_dir),
+            written
+        )
+    );
+    Ok(())
+}
+
+/// 按格式导出单帧
+pub(super) fn export_frame(
+    lib_path: &Path,
+    index: &str,
+    out: &Path,
+    args: &[String],
+) -> Result<()> {
+    let index: usize = index
+        .parse()
+        .map_err(|_| error::LibraryError::ParseError(format!("无效的帧索引: {}", index)))?;
+    let format = match option_value(args, "--format") {
+        Some(name) => ExportFormat::from_name(name),
+        None => ExportFormat::from_path(out),
+    }
+    .ok_or_else(|| error::LibraryError::ParseError(format!("无法确定导出格式: {:?}", out)))?;
+
+    let mut options = ExportOptions {
+        keep_alpha: !args.iter().any(|a| a == "--no-alpha"),
+        ..ExportOptions::default()
+    };
+    if let Some(text) = option_value(args, "--background") {
+        options.background = image::parse_hex_color(text)
+            .ok_or_else(|| error::LibraryError::ParseError(format!("无效的颜色: {}", text)))?;
+    }
+    if let Some(text) = option_value(args, "--quality") {
+        options.jpeg_quality = text
+            .trim()
+            .parse()
+            .ok()
+            .filter(|quality| (1..=100).contains(quality))
+            .ok_or_else(|| error::LibraryError::ParseError(format!("无效的质量: {}", text)))?;
+    }
+
+    let (info, mut loader) = LibraryLoader::load(lib_path)?;
+    loader.export_image(index, out, format, &options)?;
+    report!(
+        "{}",
+        tr!(
+            "已导出 {} 的第 {} 帧 -> {} ({})",
+            info.file_name,
+            index,
+            format!("{:?}", out),
+            format.name()
+        )
+    );
+    Ok(())
+}
+
+/// 导出所有帧为带阴影的 PNG（渲染结果，不写元数据）
+pub(super) fn export_with_shadow(
+    lib_path: &Path,
+    out_dir: &Path,
+    metadata: Option<&Path>,
+) -> Result<()> {
+    if metadata.is_some() {
+        return Err(error::LibraryError::ParseError(
+            "--shadow 导出的是渲染结果，不能与 --metadata 同时使用".to_string(),
+        ));
+    }
+
+    let (info, mut loader) = LibraryLoader::load(lib_path)?;
+    std::fs::create_dir_all(out_dir)?;
+
+    let bar = progress_bar::new(info.image_count, tr!("导出"));
+    let mut written = 0;
+    for index in 0..info.image_count {
+        if let Some(img) = loader.get_with_shadow(index)? {
+            img.save(out_dir.join(format!("{:05}.png", index)))?;
+            written += 1;
+        }
+        bar.inc(1);
+    }
+    bar.finish_and_clear();
+
+    report!(
+        "{}",
+        tr!(
+            "已导出 {} ({}) 带阴影 -> {}: {} 帧",
+            info.file_name,
+            info.format_name(),
+            format!("{:?}", out_dir),
+            written
+        )
+    );
+    Ok(())
+}
+
+/// 导出库为 ZIP 交换格式
+pub(super) fn export_zip(lib_path: &Path, zip_path: &Path) -> Result<()> {
+    let (info, mut loader) = LibraryLoader::load(lib_path)?;
+    let count = formats::zip_archive::export_zip(&mut loader, zip_path)?;
+    report!(
+        "{}",
+        tr!(
+            "已导出 {} ({}) -> {}: {} 帧",
+            info.file_name,
+            info.format_name(),
+            format!("{:?}", zip_path),
+            count
+        )
+    );
+    Ok(())
+}
+
+/// 将范围内的帧导出为视频
+pub(super) fn export_video(
+    lib_path: &Path,
+    out: &Path,
+    range: Option<&str>,
+    fps: Option<&str>,
+    background: Option<&str>,
+) -> Result<()> {
+    let mut options = image::video::VideoOptions::default();
+    if let Some(text) = fps {
+        options.fps = parse_fps(text)?;
+    }
+    if let Some(text) = background {
+        options.background = image::parse_hex_color(text)
+            .ok_or_else(|| error::LibraryError::ParseError(format!("无效的颜色: {}", text)))?;
+    }
+
+    let (info, mut loader) = LibraryLoader::load(lib_path)?;
+    let range = match range {
+        Some(text) => parse_range(text)?,
+        None => 0..info.image_count,
+    };
+    let indices: Vec<usize> = (range.start..range.end.min(info.image_count)).collect();
+
+    let frames = loader.placed_frames(&indices)?;
+    let written = image::video::export(&frames, out, options)?;
+    report!(
+        "{}",
+        tr!(
+            "已导出 {} ({}) -> {}: {} 帧, {} FPS",
+            info.file_name,
+            info.format_name(),
+            format!("{:?}", out),
+            written,
+            options.fps
+        )
+    );
+    Ok(())
+}
+
+/// 将命名动画序列或范围内的帧导出为 GIF
+pub(super) fn export_gif(
+    lib_path: &Path,
+    out: &Path,
+    sequence: Option<&str>,
+    range: Option<&str>,
+    fps: Option<&str>,
+) -> Result<()> {
+    let (info, mut loader) = LibraryLoader::load(lib_path)?;
+    let (range, default_fps) = match (sequence, range) {
+        (Some(_), Some(_)) => {
+            return Err(error::LibraryError::ParseError(
+                "--sequence 和 --range 只能指定一个".to_string(),
+            ));
+        }
+        (Some(name), None) => {
+            let animations = AnimationSet::load(&info.base_path)?;
+            let sequence = animations.require(name)?;
+            (sequence.frames(), sequence.fps)
+        }
+        (None, Some(text)) => (parse_range(text)?, image::video::DEFAULT_FPS),
+        (None, None) => (0..info.image_count, image::video::DEFAULT_FPS),
+    };
+    let fps = match fps {
+        Some(text) => parse_fps(text)?,
+        None => default_fps,
+    };
+    let indices: Vec<usize> = (range.start..range.end.min(info.image_count)).collect();
+
+    let frames = loader.placed_frames(&indices)?;
+    let written = image::gif::export(&frames, out, fps)?;
+    report!(
+        "{}",
+        tr!(
+            "已导出 {} ({}) -> {}: {} 帧, {} FPS",
+            info.file_name,
+            info.format_name(),
+            format!("{:?}", out),
+            written,
+            fps
+        )
+    );
+    Ok(())
+}
+
+/// 把范围内的地图图块拼成一张网格图
+pub(super) fn export_tiles(
+    lib_path: &Path,
+    out: &Path,
+    range: Option<&str>,
+    columns: Option<&str>,
+) -> Result<()> {
+    let columns = match columns {
+        Some(text) => text
+            .trim()
+            .parse()
+            .ok()
+            .filter(|&columns| columns > 0)
+            .ok_or_else(|| error::LibraryError::ParseError(format!("无效的列数: {}", text)))?,
+        None => image::tiles::DEFAULT_COLUMNS,
+    };
+    let (info, mut loader) = LibraryLoader::load(lib_path)?;
+    let range = match range {
+        Some(text) => parse_range(text)?,
+        None => 0..info.image_count,
+    };
+
+    let grid = loader
+        .tile_grid(range.clone(), columns)?
+        .ok_or_else(|| error::LibraryError::ParseError(format!("{:?} 范围内没有图块", range)))?;
+    grid.save(out)?;
+    report!(
+        "{}",
+        tr!(
+            "已拼接 {} ({}) -> {}: {}x{}，每行 {} 块",
+            info.file_name,
+            info.format_name(),
+            format!("{:?}", out),
+            grid.width(),
+            grid.height(),
+            columns
+        )
+    );
+    Ok(())
+}
+
+/// 把范围内的帧排成带帧号的联系表
+pub(super) fn export_contact_sheet(
+    lib_path: &Path,
+    out: &Path,
+    range: Option<&str>,
+    columns: Option<&str>,
+    cell: Option<&str>,
+) -> Result<()> {
+    let positive = |text: &str, what: &str| {
+        text.trim()
+            .parse::<u32>()
+            .ok()
+            .filter(|&value| value > 0)
+            .ok_or_else(|| error::LibraryError::ParseError(format!("无效的{}: {}", what, text)))
+    };
+    let columns = match columns {
+        Some(text) => positive(text, "列数")?,
+        None => image::contact_sheet::DEFAULT_COLUMNS,
+    };
+    let cell = match cell {
+        Some(text) => positive(text, "格子边长")?,
+        None => image::contact_sheet::DEFAULT_CELL,
+    };
+    let (info, mut loader) = LibraryLoader::load(lib_path)?;
+    let range = match range {
+        Some(text) => parse_range(text)?,
+        None => 0..info.image_count,
+    };
+    let count = range.end.min(info.image_count).saturating_sub(range.start);
+    if count == 0 {
+        return Err(error::LibraryError::ParseError(format!(
+            "{:?} 范围内没有帧（共 {} 帧）",
+            range, info.image_count
+        )));
+    }
+
+    let sheet = loader.contact_sheet(range, columns, cell)?;
+    sheet.save(out)?;
+    report!(
+        "{}",
+        tr!(
+            "已生成联系表 {} ({}) -> {}: {} 帧，{}x{}",
+            info.file_name,
+            info.format_name(),
+            format!("{:?}", out),
+            count,
+            sheet.width(),
+            sheet.height()
+        )
+    );
+    Ok(())
+}
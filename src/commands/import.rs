@@ -0,0 +1,136 @@
+//! 导入命令：`pack`、`import-zip`、`import-dir`
+
+use super::{format_indices, report};
+use crate::i18n::tr;
+use mir2_library::{error, formats, image};
+
+use error::Result;
+use formats::LibraryLoader;
+use formats::folder_import::{self, ImportPlan};
+use formats::metadata::Manifest;
+use formats::mlibrary_v2::EncodeOptions;
+use std::path::Path;
+
+/// 将 PNG 目录打包为 .Lib，有元数据时按其恢复帧属性
+pub(super) fn pack_frames(
+    dir: &Path,
+    lib_path: &Path,
+    metadata: Option<&Path>,
+    key: image::chroma_key::ChromaKey,
+    encode: EncodeOptions,
+) -> Result<()> {
+    let manifest = match metadata {
+        Some(path) => Manifest::load(path)?,
+        None => {
+            // 没有元数据时按文件名顺序打包目录下的 PNG（遮罩图除外）；
+            // 非 UTF-8 的文件名（如 GBK 编码的中文名）转换后找不到原文件，跳过并提示
+            let mut names: Vec<String> = std::fs::read_dir(dir)?
+                .filter_map(|e| e.ok())
+                .filter_map(|e| {
+                    e.file_name()
+                        .into_string()
+                        .inspect_err(|name| tracing::warn!("跳过非 UTF-8 文件名: {:?}", name))
+                        .ok()
+                })
+                .filter(|n| n.to_lowercase().ends_with(".png") && !n.starts_with("mask_"))
+                .collect();
+            names.sort();
+            Manifest::from_images(&names)
+        }
+    };
+
+    let library = manifest.rebuild(lib_path, encode, |name| {
+        let path = dir.join(name);
+        if !path.exists() {
+            return Err(error::LibraryError::FileNotFound(
+                path.display().to_string(),
+            ));
+        }
+        let mut image = ::image::open(&path)?.to_rgba8();
+        key.apply(&mut image);
+        Ok(image)
+    })?;
+
+    report!(
+        "{}",
+        tr!(
+            "已打包 {} -> {}: {} 帧",
+            format!("{:?}", dir),
+            format!("{:?}", lib_path),
+            library.count()
+        )
+    );
+    Ok(())
+}
+
+/// 从 ZIP 交换格式重建库
+pub(super) fn import_zip(
+    zip_path: &Path,
+    lib_path: &Path,
+    key: image::chroma_key::ChromaKey,
+    encode: EncodeOptions,
+) -> Result<()> {
+    let library = formats::zip_archive::import_zip(zip_path, lib_path, key, encode)?;
+    report!(
+        "{}",
+        tr!(
+            "已导入 {} -> {}: {} 帧",
+            format!("{:?}", zip_path),
+            format!("{:?}", lib_path),
+            library.count()
+        )
+    );
+    Ok(())
+}
+
+/// 按文件名把文件夹中的 PNG 替换或追加到已有的库并保存
+pub(super) fn import_dir(
+    dir: &Path,
+    lib_path: &Path,
+    key: image::chroma_key::ChromaKey,
+    encode: EncodeOptions,
+    dry_run: bool,
+) -> Result<()> {
+    let plan = ImportPlan::scan(dir)?;
+    for (index, name) in &plan.replace {
+        report!("{}", tr!("  {} -> 第 {} 帧", name, index));
+    }
+    for name in &plan.append {
+        report!("{}", tr!("  {} -> 追加", name));
+    }
+    if !plan.skipped.is_empty() {
+        report!("{}", tr!("不导入: {}", plan.skipped.join(", ")));
+    }
+    if plan.is_empty() {
+        report!("{}", tr!("{} 中没有要导入的 PNG", format!("{:?}", dir)));
+        return Ok(());
+    }
+    if dry_run {
+        report!(
+            "{}",
+            tr!(
+                "预演: 将替换 {} 帧、追加 {} 帧，未写入",
+                plan.replace.len(),
+                plan.append.len()
+            )
+        );
+        return Ok(());
+    }
+
+    let (info, mut loader) = LibraryLoader::load(lib_path)?;
+    let report = folder_import::apply(&mut loader, dir, &plan, key, encode)?;
+    loader.save()?;
+    report!(
+        "{}",
+        tr!(
+            "已从 {} 更新 {}: 替换 {} 帧 ({})，追加 {} 帧 ({})",
+            format!("{:?}", dir),
+            info.file_name,
+            report.replaced.len(),
+            format_indices(&report.replaced),
+            report.appended.len(),
+            format_indices(&report.appended)
+        )
+    );
+    Ok(())
+}
@@ -0,0 +1,123 @@
+//! 地图命令：`map`
+
+use super::report;
+use crate::i18n::tr;
+use mir2_library::{error, formats};
+
+use error::Result;
+use formats::map::{MapFile, MapLibraries, MapRegion};
+use std::path::Path;
+
+/// 地图的资源目录（未指定时按地图位置推断）
+fn map_libraries(map_path: &Path, data: Option<&str>) -> MapLibraries {
+    match data {
+        Some(dir) => MapLibraries::new(Path::new(dir)),
+        None => MapLibraries::new(&formats::map::default_data_dir(map_path)),
+    }
+}
+
+/// 显示地图尺寸、用到的资源库和缺少的库
+pub(super) fn show_map(map_path: &Path, data: Option<&str>) -> Result<()> {
+    let map = MapFile::load(map_path)?;
+    let libraries = map_libraries(map_path, data);
+    report!(
+        "{}",
+        tr!(
+            "地图 {}: {}x{} 格，不可行走 {} 格",
+            format!("{:?}", map_path),
+            map.width,
+            map.height,
+            map.blocked_count()
+        )
+    );
+    let objects: Vec<String> = map
+        .object_areas()
+        .into_iter()
+        .map(formats::map::objects_library_name)
+        .collect();
+    report!(
+        "{}",
+        tr!(
+            "  物件库: {}",
+            if objects.is_empty() {
+                tr!("无").to_string()
+            } else {
+                objects.join("、")
+            }
+        )
+    );
+    report!(
+        "{}",
+        tr!("  资源目录: {}", format!("{:?}", libraries.dir()))
+    );
+    let missing = libraries.missing(&map);
+    if !missing.is_empty() {
+        report!("{}", tr!("  缺少: {}", missing.join("、")));
+    }
+    Ok(())
+}
+
+/// 渲染地图的一块区域并保存为 PNG
+pub(super) fn render_map(
+    map_path: &Path,
+    out: &Path,
+    data: Option<&str>,
+    region: Option<&str>,
+) -> Result<()> {
+    let map = MapFile::load(map_path)?;
+    let mut libraries = map_libraries(map_path, data);
+    let region = match region {
+        Some(text) => parse_map_region(text)?,
+        None => MapRegion {
+            x: 0,
+            y: 0,
+            width: map.width,
+            height: map.height,
+        },
+    }
+    .clamp_to(&map);
+    let missing = libraries.missing(&map);
+    if !missing.is_empty() {
+        tracing::warn!(
+            "资源目录 {:?} 中缺少: {}",
+            libraries.dir(),
+            missing.join("、")
+        );
+    }
+
+    let canvas = formats::map::render(&map, &mut libraries, region);
+    canvas.save(out)?;
+    report!(
+        "{}",
+        tr!(
+            "已渲染 {} ({},{} 起 {}x{} 格) -> {}: {}x{}",
+            format!("{:?}", map_path),
+            region.x,
+            region.y,
+            region.width,
+            region.height,
+            format!("{:?}", out),
+            canvas.width(),
+            canvas.height()
+        )
+    );
+    Ok(())
+}
+
+/// 解析 `x,y,宽,高` 形式的地图区域
+fn parse_map_region(text: &str) -> Result<MapRegion> {
+    let invalid = || error::LibraryError::ParseError(format!("无效的地图区域: {}", text));
+    let values = text
+        .split(',')
+        .map(|v| v.trim().parse::<u32>().map_err(|_| invalid()))
+        .collect::<Result<Vec<_>>>()?;
+    let [x, y, width, height] = values[..] else {
+        return Err(invalid());
+    };
+    Ok(MapRegion {
+        x,
+        y,
+        width,
+        height,
+    })
+}
@@ -0,0 +1,763 @@
+//! 命令行子命令
+//!
+//! 解析参数后按位置参数分派到各子命令，子命令按功能分在本目录的各个模块中。
+
+mod check;
+mod convert;
+mod edit;
+mod export;
+mod import;
+mod map;
+mod project;
+mod search;
+mod stats;
+mod tools;
+
+use crate::i18n::{self, tr};
+use crate::{locale, progress_bar, script};
+use mir2_library::{error, formats, image};
+
+use check::self_test;
+use convert::convert_dir;
+#[cfg(feature = "gui")]
+pub(crate) use edit::write_remap;
+use edit::{
+    align_frames, lock_frames, normalize_frames, pad_frames, recompress_library,
+    remove_empty_frames, strip_masks,
+};
+use error::Result;
+use export::{
+    export_contact_sheet, export_frame, export_frames, export_gif, export_range, export_tiles,
+    export_video, export_with_shadow, export_zip,
+};
+use formats::filter::FrameFilter;
+use formats::mlibrary_v2::{Codec, EncodeOptions};
+use image::compression::CompressionLevel;
+use import::{import_dir, import_zip, pack_frames};
+use map::{render_map, show_map};
+use project::{
+    describe_sequence, edit_animations, edit_project, parse_sequence, show_animations, show_project,
+};
+use search::{find_frames, find_similar};
+use stats::{analyze_library, library_inventory, library_stats, list_frames, open_library};
+use std::path::Path;
+use tools::{associate_files, format_spec, list_actions, serve_assets};
+use tracing::info;
+
+/// 命令结果的日志目标：`--quiet` 只压低其它日志，这一目标照常输出到控制台
+pub(crate) const RESULT_TARGET: &str = "library_editor::result";
+
+/// 输出命令结果（用法同 `info!`），`--quiet` 时照常显示
+macro_rules! report {
+    ($($arg:tt)*) => {
+        tracing::info!(target: $crate::commands::RESULT_TARGET, $($arg)*)
+    };
+}
+pub(crate) use report;
+
+/// 运行 CLI 模式
+pub(crate) fn run_cli(args: Vec<String>) -> Result<()> {
+    // 初始化日志 - 同时输出到控制台和文件
+    let quiet = args.iter().any(|a| a == "--quiet");
+    crate::init_logging(quiet, args.iter().any(|a| a == "--verbose"));
+    progress_bar::set_quiet(quiet);
+    // 数字格式与界面共用偏好设置文件
+    locale::set(locale::load(&locale::settings_path()));
+
+    info!("Library Editor CLI 模式启动中...");
+
+    if args.iter().any(|a| a == "--watch") {
+        return watch_command(&args);
+    }
+    run_command(&args)
+}
+
+/// 执行子命令，没有匹配的子命令时显示帮助
+fn run_command(args: &[String]) -> Result<()> {
+    // 子命令
+    let positional = positional_args(args);
+    let metadata = option_value(args, "--metadata").map(Path::new);
+    let shadow = args.iter().any(|a| a == "--shadow");
+    let indexed = args.iter().any(|a| a == "--indexed");
+    let refresh = args.iter().any(|a| a == "--refresh");
+    // 导入 PNG 时的透明色，默认纯黑透明
+    let key = parse_key(option_value(args, "--key"));
+    // 把不透明的纯黑像素写成近黑色，避免客户端按黑色透明抠掉
+    let encode = EncodeOptions {
+        preserve_black: args.iter().any(|a| a == "--preserve-black"),
+        wide_offsets: args.iter().any(|a| a == "--wide-offsets"),
+        codec: if args.iter().any(|a| a == "--zstd") {
+            Codec::Zstd
+        } else {
+            Codec::Gzip
+        },
+        level: option_value(args, "--compression")
+            .map(parse_compression_level)
+            .transpose()?,
+    };
+    if let Some(level) = encode.level {
+        check_codec_level(encode.codec, level, "--compression")?;
+    }
+    // 保存库时保留的旧版本份数
+    if let Some(text) = option_value(args, "--backups") {
+        let count = text
+            .trim()
+            .parse::<usize>()
+            .map_err(|_| error::LibraryError::ParseError(format!("无效的备份份数: {}", text)))?;
+        formats::atomic_file::set_backup_count(count);
+    }
+    // 空帧的占位图，默认与 GUI 相同
+    if let Some(text) = option_value(args, "--placeholder") {
+        let policy = text
+            .parse::<image::PlaceholderPolicy>()
+            .map_err(error::LibraryError::ParseError)?;
+        image::placeholder::set_loader_default(policy);
+    }
+    // 只读命令的库文件可以是 http(s) 地址，先下载到本地缓存
+    let remote = |lib: &str| formats::remote::resolve(lib, refresh);
+    match positional.as_slice() {
+        ["open", lib] => return open_library(&remote(lib)?),
+        ["export", lib, out] if option_value(args, "--range").is_some() => {
+            let range = option_value(args, "--range");
+            return export_range(&remote(lib)?, Path::new(out), range, indexed);
+        }
+        ["export", lib, out] if shadow => {
+            return export_with_shadow(&remote(lib)?, Path::new(out), metadata);
+        }
+        ["export", lib, out] => {
+            return export_frames(&remote(lib)?, Path::new(out), metadata, indexed);
+        }
+        ["pack", dir, out] => {
+            return pack_frames(Path::new(dir), Path::new(out), metadata, key?, encode);
+        }
+        ["export-frame", lib, index, out] => {
+            return export_frame(&remote(lib)?, index, Path::new(out), args);
+        }
+        ["export-zip", lib, out] => return export_zip(&remote(lib)?, Path::new(out)),
+        ["import-zip", zip, out] => {
+            return import_zip(Path::new(zip), Path::new(out), key?, encode);
+        }
+        ["import-dir", dir, lib] => {
+            let dry_run = args.iter().any(|a| a == "--dry-run");
+            return import_dir(Path::new(dir), Path::new(lib), key?, encode, dry_run);
+        }
+        ["compact", lib] => {
+            return remove_empty_frames(
+                Path::new(lib),
+                args.iter().any(|a| a == "--trailing"),
+                option_value(args, "--remap").map(Path::new),
+            );
+        }
+        ["recompress", lib] => {
+            return recompress_library(
+                Path::new(lib),
+                option_value(args, "--level"),
+                option_value(args, "--codec"),
+            );
+        }
+        ["strip-masks", lib] => {
+            return strip_masks(Path::new(lib), option_value(args, "--range"));
+        }
+        ["video", lib, out] => {
+            return export_video(
+                &remote(lib)?,
+                Path::new(out),
+                option_value(args, "--range"),
+                option_value(args, "--fps"),
+                option_value(args, "--background"),
+            );
+        }
+        ["stats", lib] if args.iter().any(|a| a == "--analyze") => {
+            return analyze_library(&remote(lib)?, option_value(args, "--limit"));
+        }
+        ["stats", lib] => return library_stats(&remote(lib)?),
+        ["inventory", lib] => return library_inventory(&remote(lib)?),
+        ["list", lib] => {
+            return list_frames(
+                &remote(lib)?,
+                option_value(args, "--range"),
+                option_value(args, "--csv").map(Path::new),
+            );
+        }
+        ["normalize", lib] => {
+            return normalize_frames(Path::new(lib), option_value(args, "--range"));
+        }
+        ["align", lib] => {
+            return align_frames(
+                Path::new(lib),
+                option_value(args, "--range"),
+                args.iter().any(|a| a == "--horizontal"),
+                option_value(args, "--preview").map(Path::new),
+                args.iter().any(|a| a == "--apply"),
+            );
+        }
+        ["pad", lib] => {
+            return pad_frames(
+                Path::new(lib),
+                option_value(args, "--size"),
+                option_value(args, "--anchor"),
+                option_value(args, "--range"),
+            );
+        }
+        ["lock", lib] => return lock_frames(Path::new(lib), option_value(args, "--range"), true),
+        ["unlock", lib] => {
+            return lock_frames(Path::new(lib), option_value(args, "--range"), false);
+        }
+        ["self-test", dirs @ ..] => {
+            return self_test(dirs, option_value(args, "--csv").map(Path::new));
+        }
+        ["spec", target] => return format_spec(target),
+        ["actions", words @ ..] => return list_actions(&words.join(" ")),
+        ["associate"] => {
+            return associate_files(
+                option_value(args, "--reg").map(Path::new),
+                args.iter().any(|a| a == "--remove"),
+            );
+        }
+        ["project", lep] => return show_project(Path::new(lep)),
+        ["project", lep, "add", lib] => {
+            return edit_project(Path::new(lep), |project| {
+                let library = project.library_mut(Path::new(lib));
+                if let Some(note) = option_value(args, "--note") {
+                    library.notes = note.to_string();
+                }
+                Ok(tr!("已加入 {}", format!("{:?}", library.path)))
+            });
+        }
+        ["project", lep, "bookmark", lib, frame, label @ ..] => {
+            let index = parse_index(frame)?;
+            return edit_project(Path::new(lep), |project| {
+                project
+                    .library_mut(Path::new(lib))
+                    .bookmarks
+                    .set(index, &label.join(" "));
+                Ok(tr!("已添加书签: 第 {} 帧", index))
+            });
+        }
+        ["project", lep, "sequence", lib, name] => {
+            let sequence = parse_sequence(args, name)?;
+            return edit_project(Path::new(lep), |project| {
+                let text = tr!("已保存{}", describe_sequence(&sequence));
+                project
+                    .library_mut(Path::new(lib))
+                    .sequences
+                    .set(sequence)?;
+                Ok(text)
+            });
+        }
+        ["anim", lib] => return show_animations(Path::new(lib)),
+        ["anim", lib, "set", name] => {
+            let sequence = parse_sequence(args, name)?;
+            return edit_animations(Path::new(lib), |animations| {
+                let text = tr!("已保存{}", describe_sequence(&sequence));
+                animations.set(sequence)?;
+                Ok(text)
+            });
+        }
+        ["anim", lib, "remove", name] => {
+            return edit_animations(Path::new(lib), |animations| {
+                if !animations.remove(name) {
+                    animations.require(name)?;
+                }
+                Ok(tr!("已删除动画序列 {}", name))
+            });
+        }
+        ["tiles", lib, out] => {
+            return export_tiles(
+                &remote(lib)?,
+                Path::new(out),
+                option_value(args, "--range"),
+                option_value(args, "--columns"),
+            );
+        }
+        ["contact-sheet", lib, out] => {
+            return export_contact_sheet(
+                &remote(lib)?,
+                Path::new(out),
+                option_value(args, "--range"),
+                option_value(args, "--cols").or(option_value(args, "--columns")),
+                option_value(args, "--cell"),
+            );
+        }
+        ["map", map] => return show_map(Path::new(map), option_value(args, "--data")),
+        ["map", map, out] => {
+            return render_map(
+                Path::new(map),
+                Path::new(out),
+                option_value(args, "--data"),
+                option_value(args, "--region"),
+            );
+        }
+        ["similar", target, libs @ ..] if !libs.is_empty() => {
+            let libs = libs
+                .iter()
+                .map(|lib| remote(lib))
+                .collect::<Result<Vec<_>>>()?;
+            return find_similar(target, &libs, option_value(args, "--limit"));
+        }
+        ["find", lib] => {
+            let filter = FrameFilter {
+                width: FrameFilter::parse_range(option_value(args, "--width").unwrap_or(""))?,
+                height: FrameFilter::parse_range(option_value(args, "--height").unwrap_or(""))?,
+                empty: args.iter().any(|a| a == "--empty"),
+                mask: args.iter().any(|a| a == "--mask"),
+                shadow,
+            };
+            return find_frames(&remote(lib)?, &filter);
+        }
+        ["convert-dir", src, dst] => {
+            return convert_dir(
+                Path::new(src),
+                Path::new(dst),
+                option_value(args, "--to"),
+                option_value(args, "--jobs"),
+                encode,
+            );
+        }
+        ["script", file, script_args @ ..] => {
+            return script::run_file(Path::new(file), script_args, encode);
+        }
+        ["serve", dirs @ ..] if dirs.len() <= 1 => {
+            return serve_assets(dirs.first().copied(), option_value(args, "--port"));
+        }
+        ["export-gif", lib, out] => {
+            return export_gif(
+                &remote(lib)?,
+                Path::new(out),
+                option_value(args, "--sequence"),
+                option_value(args, "--range"),
+                option_value(args, "--fps"),
+            );
+        }
+        _ => {}
+    }
+
+    // 每行单独翻译，译文自行对齐各列
+    print_help(&[
+        "支持格式: MLibrary V1/V2, WeMade, WTL",
+        "",
+        "使用方法:",
+        "  library_editor.exe [选项] <文件路径>",
+        "",
+        "选项:",
+        "  --no-gui, --cli    强制使用 CLI 模式 (当前默认为 GUI)",
+        "  --help, -h         显示帮助信息",
+        "  --quiet            不显示进度条，控制台只输出命令结果、警告和错误",
+        "  --verbose          控制台输出调试信息",
+        "  --backups <份数>   保存库时保留旧版本为 .bak1、.bak2……（默认不保留）",
+        "  --placeholder <策略>",
+        "                     按帧导出（export --range、export-frame 等）时空帧的占位图：",
+        "                     checker（棋盘格，默认，与 GUI 相同）、transparent、missing，",
+        "                     none 跳过空帧；完整的 export 仍在元数据中记为空帧",
+        "  --lang <语言>      界面和命令行的语言：zh（中文）或 en（英文），默认按系统语言",
+        "  --watch            执行 export、export-zip、export-gif、tiles、video 或 pack 后",
+        "                     继续监视输入的库文件（pack 为 PNG 目录），变化时重新执行",
+        "",
+        "退出码:",
+        "  0 成功，1 其他错误，2 批量操作部分失败，3 格式不支持，4 文件损坏，5 读写错误",
+        "",
+        "命令:",
+        "  open <库文件|URL> [--refresh]     打开库并显示格式和帧数",
+        "                                    URL 连同索引文件下载到本地缓存后打开，",
+        "                                    --refresh 忽略缓存重新下载；",
+        "                                    导出类命令与 stats、inventory 也可用 URL",
+        "  export <库文件> <输出目录> [--metadata 元数据.json] [--shadow]",
+        "                                    导出所有帧为 PNG，可附带帧属性 JSON",
+        "                                    --shadow 在帧下方绘制阴影（渲染结果，不可再打包）",
+        "                                    --range 起-止 只导出这段帧的图像，不写元数据",
+        "                                    --indexed 8 位调色板帧写成带原调色板的索引色 PNG，",
+        "                                    外部工具编辑后再导入不必重新量化",
+        "  pack <PNG目录> <输出.Lib> [--metadata 元数据.json] [--key 透明色] [--preserve-black]",
+        "                                    [--wide-offsets] [--zstd] [--compression 级别]",
+        "                                    将 PNG 打包为 .Lib，按 JSON 恢复偏移/阴影/遮罩",
+        "                                    --key 透明色: alpha（保留 Alpha）、black（默认）、",
+        "                                    magenta 或 #RRGGBB[:容差]，import-zip 同样适用",
+        "                                    --preserve-black 把不透明的纯黑写成 (1,1,1)，",
+        "                                    避免客户端按黑色透明抠掉，import-zip 同样适用",
+        "                                    --wide-offsets 保存为 64 位偏移的 V2.1，可超过",
+        "                                    4 GB（客户端不支持），import-zip、convert-dir、",
+        "                                    script 同样适用",
+        "                                    --zstd 帧数据用 zstd 压缩，文件更小、读取更快，",
+        "                                    但只有编辑器能读；交给客户端前用不带 --zstd 的",
+        "                                    convert-dir 转回 GZip",
+        "                                    --compression 压缩级别（0-9，默认 6，9 最小），",
+        "                                    import-zip、convert-dir、script 同样适用；",
+        "                                    zstd 不区分级别，与 --zstd 同用时只接受 0（不压缩）",
+        "  export-frame <库文件> <帧> <输出文件> [--format 格式] [--background #RRGGBB]",
+        "                                    [--quality 质量] [--no-alpha]",
+        "                                    导出单帧，格式按扩展名判断（png、bmp、tga、jpg），",
+        "                                    --format bmp8 / png8 输出使用库调色板的 8 位 BMP /",
+        "                                    索引色 PNG（仅 V0、加密 WIL 和 WZL 的 8 位帧）；",
+        "                                    BMP、JPEG 和 --no-alpha 的 TGA 按",
+        "                                    --background（默认黑色）去掉透明，--quality 为",
+        "                                    JPEG 质量（1-100，默认 90）",
+        "  export-zip <库文件> <输出.zip>    导出为 ZIP 交换格式 (PNG + manifest.json)",
+        "  import-zip <输入.zip> <输出.Lib> [--key 透明色] [--preserve-black]",
+        "                                    [--wide-offsets] [--zstd]",
+        "                                    从 ZIP 交换格式重建 .Lib 库",
+        "  import-dir <目录> <库文件> [--key 透明色] [--preserve-black] [--dry-run]",
+        "                                    按文件名更新已有的库：00017.png 替换第 17 帧，",
+        "                                    append_*.png 按文件名顺序追加；目录中的",
+        "                                    mapping.csv（每行 文件名,帧索引|append|skip）",
+        "                                    可改写去向；--dry-run 只列出计划，不写入",
+        "  video <库文件> <输出.webm|.mp4> [--range 起-止] [--fps 帧率] [--background #RRGGBB]",
+        "                                    将一段帧导出为短视频（需要 ffmpeg，默认 10 FPS）",
+        "  export-gif <库文件> <输出.gif> [--sequence 名称 | --range 起-止] [--fps 帧率]",
+        "                                    将动画序列或一段帧导出为循环播放的 GIF",
+        "  tiles <库文件> <输出.png> [--range 起-止] [--columns 列数]",
+        "                                    把地图图块（Tiles、SmTiles）无缝拼成一张网格图，",
+        "                                    便于检查接缝；默认每行 8 块",
+        "  contact-sheet <库文件> <输出.png> [--range 起-止] [--cols 列数] [--cell 边长]",
+        "                                    把一段帧的缩略图排成标有帧号的网格图，便于写文档",
+        "                                    和审查资源；默认每行 10 帧，格子边长 96 像素",
+        "  map <地图.map> [--data 资源目录]  显示地图尺寸、用到的资源库和缺少的库",
+        "  map <地图.map> <输出.png> [--data 资源目录] [--region x,y,宽,高]",
+        "                                    用 Tiles、SmTiles、Objects 库渲染地图（单位为格，",
+        "                                    每边最多 128 格）；资源目录默认为地图目录旁的 Data",
+        "  similar <目标.png|库文件#帧> <库文件...> [--limit 个数]",
+        "                                    按感知哈希在各库中查找与目标最相似的帧",
+        "                                    （默认列出 10 个）",
+        "  find <库文件> [--width 范围] [--height 范围] [--empty] [--mask] [--shadow]",
+        "                                    列出满足全部条件的帧：宽高范围（如 16-64、32-）、",
+        "                                    空帧、带遮罩层、阴影值不为 0",
+        "  convert-dir <来源目录> <输出目录> [--to lib] [--jobs 线程数] [--preserve-black]",
+        "                                    [--wide-offsets] [--zstd]",
+        "                                    把目录下的全部库按原目录结构转换为 .Lib，",
+        "                                    多个文件并行转换（默认按 CPU 核数），最后列出",
+        "                                    每个文件的结果；有文件失败时退出码为 2",
+        "  serve [资源目录] [--port 端口]    在本机提供只读 HTTP 接口：/libs 列出库，",
+        "                                    /lib/<库名>/info 帧数和偏移，",
+        "                                    /lib/<库名>/frame/<帧>.png 取帧图像；",
+        "                                    不带目录时使用 LIBRARY_EDITOR_ASSET_DIRS",
+        "                                    中的第一个目录，默认端口 8765",
+        "  script <脚本.rhai> [参数...] [--preserve-black] [--wide-offsets] [--zstd]",
+        "                                    运行 Rhai 脚本批量处理库，参数在脚本中为 ARGS；",
+        "                                    可用 open、create、frame、replace、append、",
+        "                                    shift_offsets、save、save_as 等",
+        "  compact <库文件.Lib> [--trailing] [--remap 对照表.csv]",
+        "                                    删除全部空帧并保存，列出索引的变化；",
+        "                                    --trailing 只删除末尾的空帧，其余索引不变；",
+        "                                    --remap 写出索引对照表（.json 为 JSON，",
+        "                                    否则为 CSV）",
+        "  recompress <库文件.Lib> [--level 级别] [--codec gzip|zstd]",
+        "                                    解压后按级别（0-9，默认 9）重新压缩全部帧并保存，",
+        "                                    像素和帧属性不变；--codec 同时改变压缩方式，",
+        "                                    --codec zstd 时 --level 只接受 0（不压缩）",
+        "  strip-masks <库文件.Lib> [--range 起-止]",
+        "                                    去掉遮罩层并保存，列出受影响的帧",
+        "  stats <库文件> [--analyze] [--limit 个数]",
+        "                                    仅读取文件头，统计帧数、空帧和数据大小分布；",
+        "                                    --analyze 逐帧解码，对比压缩前后的大小，列出最大的",
+        "                                    帧（默认 10 个）和尺寸分布，估算裁边、去重和 zstd",
+        "                                    各能省下多少",
+        "  inventory <库文件>                仅读取文件头，列出每帧的尺寸和偏移",
+        "  list <库文件> [--range 起-止] [--csv 帧表.csv]",
+        "                                    仅读取文件头，以表格列出每帧的索引、尺寸、偏移、",
+        "                                    阴影、遮罩和压缩后的数据大小；--csv 写入文件",
+        "  normalize <库文件.Lib> [--range 起-止]",
+        "                                    将范围内的帧填充到相同尺寸并统一偏移，便于图集打包",
+        "  align <库文件.Lib> [--range 起-止] [--horizontal] [--preview 对照图.png] [--apply]",
+        "                                    按内容下边缘对齐脚底：以范围内的中位数为基准列出各帧",
+        "                                    的偏移修正，--horizontal 同时对齐内容的水平中心，",
+        "                                    --preview 导出对齐前后的对照图，--apply 写入并保存",
+        "  pad <库文件.Lib> --size 宽x高 [--anchor 锚点] [--range 起-止]",
+        "                                    将帧填充到统一的画布尺寸并调整偏移，绘制位置不变；",
+        "                                    锚点: top-left、top、center、bottom（默认）等",
+        "  lock <库文件> [--range 起-止]     锁定帧，替换、删除等修改会跳过它们；",
+        "                                    不带 --range 时列出已锁定的帧",
+        "  unlock <库文件> [--range 起-止]   解锁帧，不带 --range 时全部解锁",
+        "  associate [--remove] [--reg 输出.reg]",
+        "                                    在当前用户的注册表中登记编辑器（仅 Windows），",
+        "                                    资源管理器中库文件和 .lep 的“打开方式”列出编辑器；",
+        "                                    --remove 取消登记，--reg 只写出注册表脚本",
+        "  actions [关键字]                  列出编辑器操作（与界面的命令面板相同）及对应的命令，",
+        "                                    带关键字时按模糊匹配筛选",
+        "  spec <格式|库文件>                显示格式的二进制结构，传入库文件时标出读到的字段值",
+        "                                    格式: wzl、lib、wil、wtl 或格式名",
+        "  project <项目.lep>                列出项目中的库、书签、动画序列和备注",
+        "  project <项目.lep> add <库文件> [--note 备注]",
+        "                                    把库加入项目（项目文件不存在时新建）",
+        "  project <项目.lep> bookmark <库文件> <帧> [说明...]",
+        "                                    添加或修改书签",
+        "  project <项目.lep> sequence <库文件> <名称> --range 起-止 [--fps 帧率]",
+        "                                    保存命名动画序列，如 attack --range 360-369",
+        "  anim <库文件>                     列出库的动画序列（保存在 <库名>.anim.json）",
+        "  anim <库文件> set <名称> --range 起-止 [--fps 帧率]",
+        "                                    添加或修改动画序列",
+        "  anim <库文件> remove <名称>       删除动画序列",
+        "  self-test [目录...] [--csv 报告.csv]",
+        "                                    检查目录下全部库的文件头和索引，列出有问题的文件；",
+        "                                    不带目录时检查 LIBRARY_EDITOR_ASSET_DIRS 中的目录",
+        "                                    --csv 写出全部库的检查结果（UTF-8 带 BOM）；",
+        "                                    有问题时退出码为 2",
+        "",
+        "支持格式:",
+        "  - .wzl/.wzx (MLibrary V1)",
+        "  - .Lib (MLibrary V2)",
+        "  - .wil/.wix (WeMade Library)",
+        "  - .wtl (WTL Library)",
+        "  - .D (加密 WIL)",
+        "  - .wil/.wzl (传奇3 变体，自动识别)",
+        "",
+        "注意: 程序默认使用 GUI 模式",
+        "      (gui feature 当前已默认启用)",
+        "      library_editor <库文件|项目.lep> 启动界面并直接打开该文件",
+    ]);
+
+    // 显示传入的文件参数
+    if args.len() > 1 {
+        let file_args: Vec<_> = args
+            .iter()
+            .filter(|a| !a.starts_with("--") && !a.starts_with('-'))
+            .collect();
+
+        if !file_args.is_empty() {
+            report!("");
+            report!("{}", tr!("传入的文件:"));
+            for file in file_args {
+                report!("  - {}", file);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// 逐行输出帮助信息（按当前语言翻译）
+fn print_help(lines: &[&str]) {
+    for line in lines {
+        report!("{}", i18n::translate(line));
+    }
+}
+
+/// 执行命令后监视输入文件，变化时重新执行，直到进程被中止
+fn watch_command(args: &[String]) -> Result<()> {
+    let mut watcher = match positional_args(args).as_slice() {
+        [
+            "export" | "export-zip" | "export-gif" | "tiles" | "video",
+            lib,
+            _,
+        ] => formats::watch::FileWatcher::library(Path::new(lib))?,
+        ["pack", dir, _] => formats::watch::FileWatcher::directory(Path::new(dir))?,
+        _ => {
+            return Err(error::LibraryError::ParseError(
+                "--watch 只能用于 export、export-zip、export-gif、tiles、video 和 pack 命令"
+                    .to_string(),
+            ));
+        }
+    };
+
+    loop {
+        // 单次执行失败（如文件正在写入）不退出，等下一次变化
+        if let Err(e) = run_command(args) {
+            tracing::error!("执行失败: {}", e);
+        }
+        info!("正在监视变化，按 Ctrl+C 退出");
+        let changed = watcher.wait();
+        if changed.is_empty() {
+            return Ok(());
+        }
+        let names: Vec<String> = changed
+            .iter()
+            .filter_map(|path| path.file_name())
+            .map(|name| name.to_string_lossy().into_owned())
+            .collect();
+        info!("检测到变化: {}，重新执行", names.join(", "));
+    }
+}
+
+/// 需要跟随参数值的选项
+const VALUE_OPTIONS: [&str; 32] = [
+    "--metadata",
+    "--csv",
+    "--key",
+    "--range",
+    "--size",
+    "--anchor",
+    "--fps",
+    "--background",
+    "--note",
+    "--sequence",
+    "--columns",
+    "--data",
+    "--region",
+    "--limit",
+    "--width",
+    "--height",
+    "--remap",
+    "--port",
+    "--to",
+    "--jobs",
+    "--backups",
+    "--placeholder",
+    "--lang",
+    "--format",
+    "--quality",
+    "--preview",
+    "--compression",
+    "--level",
+    "--codec",
+    "--reg",
+    "--cols",
+    "--cell",
+];
+
+/// 收集位置参数（跳过程序名、选项及选项的参数值）
+pub(crate) fn positional_args(args: &[String]) -> Vec<&str> {
+    let mut positional = Vec::new();
+    let mut iter = args.iter().skip(1);
+    while let Some(arg) = iter.next() {
+        if VALUE_OPTIONS.contains(&arg.as_str()) {
+            iter.next();
+        } else if !arg.starts_with('-') {
+            positional.push(arg.as_str());
+        }
+    }
+    positional
+}
+
+/// 获取选项的参数值
+pub(crate) fn option_value<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|a| a == name)
+        .and_then(|i| args.get(i + 1))
+        .map(String::as_str)
+}
+
+/// 解析 `--key` 透明色，未指定时为纯黑透明
+fn parse_key(text: Option<&str>) -> Result<image::chroma_key::ChromaKey> {
+    match text {
+        Some(text) => image::chroma_key::ChromaKey::parse(text)
+            .ok_or_else(|| error::LibraryError::ParseError(format!("无效的透明色: {}", text))),
+        None => Ok(image::chroma_key::ChromaKey::default()),
+    }
+}
+
+/// 解析 `--compression` 压缩级别（0-9）
+fn parse_compression_level(text: &str) -> Result<CompressionLevel> {
+    text.trim()
+        .parse()
+        .ok()
+        .and_then(CompressionLevel::new)
+        .ok_or_else(|| error::LibraryError::ParseError(format!("无效的压缩级别: {}", text)))
+}
+
+/// 检查指定的压缩级别对压缩方式是否有效：zstd 只接受 0 级，其他级别会被忽略，直接拒绝
+fn check_codec_level(codec: Codec, level: CompressionLevel, option: &str) -> Result<()> {
+    if codec.supports_level(level) {
+        return Ok(());
+    }
+    Err(error::LibraryError::ParseError(format!(
+        "zstd 不区分压缩级别，只接受 {} 0（不压缩）: {}",
+        option,
+        level.get()
+    )))
+}
+
+/// 解析帧率
+fn parse_fps(text: &str) -> Result<u32> {
+    text.trim()
+        .parse()
+        .ok()
+        .filter(|fps| image::video::FPS_RANGE.contains(fps))
+        .ok_or_else(|| error::LibraryError::ParseError(format!("无效的帧率: {}", text)))
+}
+
+/// 解析帧范围：`起-止`（含两端）、`起-`（到末尾）或单个索引
+fn parse_range(text: &str) -> Result<std::ops::Range<usize>> {
+    let invalid = || error::LibraryError::ParseError(format!("无效的帧范围: {}", text));
+    let parse = |s: &str| s.trim().parse::<usize>().map_err(|_| invalid());
+
+    match text.split_once('-') {
+        Some((start, "")) => Ok(parse(start)?..usize::MAX),
+        Some((start, end)) => {
+            let (start, end) = (parse(start)?, parse(end)?);
+            if start > end {
+                return Err(invalid());
+            }
+            Ok(start..end.checked_add(1).ok_or_else(invalid)?)
+        }
+        None => {
+            let index = parse(text)?;
+            Ok(index..index.checked_add(1).ok_or_else(invalid)?)
+        }
+    }
+}
+
+/// 解析帧索引
+fn parse_index(text: &str) -> Result<usize> {
+    text.trim()
+        .parse()
+        .map_err(|_| error::LibraryError::ParseError(format!("无效的帧索引: {}", text)))
+}
+
+/// 将有序索引列表压缩为 `1, 3-5, 9` 形式
+fn format_indices(indices: &[usize]) -> String {
+    let mut parts: Vec<String> = Vec::new();
+    let mut iter = indices.iter().copied().peekable();
+    while let Some(start) = iter.next() {
+        let mut end = start;
+        while iter.peek() == Some(&(end + 1)) {
+            end = iter.next().unwrap_or(end);
+        }
+        parts.push(if start == end {
+            start.to_string()
+        } else {
+            format!("{}-{}", start, end)
+        });
+    }
+    parts.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_range_and_format_indices() {
+        assert_eq!(parse_range("3-5").unwrap(), 3..6);
+        assert_eq!(parse_range("7").unwrap(), 7..8);
+        assert_eq!(parse_range("2-").unwrap(), 2..usize::MAX);
+        assert!(parse_range("5-3").is_err());
+        let max = usize::MAX.to_string();
+        assert!(parse_range(&format!("0-{}", max)).is_err());
+        assert!(parse_range(&max).is_err());
+        assert_eq!(format_indices(&[1, 3, 4, 5, 9]), "1, 3-5, 9");
+    }
+
+    #[test]
+    fn test_zstd_rejects_compression_levels() {
+        let level = |n| CompressionLevel::new(n).unwrap();
+        assert!(check_codec_level(Codec::Gzip, level(9), "--compression").is_ok());
+        assert!(check_codec_level(Codec::Zstd, level(0), "--compression").is_ok());
+        assert!(check_codec_level(Codec::Zstd, level(9), "--level").is_err());
+    }
+
+    #[cfg(feature = "gui")]
+    #[test]
+    fn test_export_empty_frame_matches_gui_placeholder() {
+        use crate::gui::preferences::Preferences;
+        use formats::mlibrary_v2::{MImage, MLibraryV2};
+        use mir2_library::image::PlaceholderPolicy;
+
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let mut library = MLibraryV2::new(dir.join("Hum")).unwrap();
+        library.add_image(&MImage::new());
+        library.save().unwrap();
+
+        // 不带 --placeholder 时与 GUI 默认设置下的显示相同
+        let lib = dir.join("Hum.Lib");
+        let out = dir.join("out");
+        let args: Vec<String> = ["library_editor", "export"]
+            .into_iter()
+            .map(String::from)
+            .chain([lib, out.clone()].map(|p| p.display().to_string()))
+            .chain(["--range", "0"].map(String::from))
+            .collect();
+        run_command(&args).unwrap();
+        let exported = ::image::open(out.join("00000.png")).unwrap().to_rgba8();
+        let gui = PlaceholderPolicy::from_index(Preferences::default().placeholder_policy);
+        assert_eq!(Some(exported), gui.render());
+    }
+}
@@ -0,0 +1,131 @@
+//! 项目和动画序列命令：`project`、`anim`
+
+use super::{option_value, parse_fps, parse_range, report};
+use crate::i18n::tr;
+use mir2_library::{error, formats, image};
+
+use error::Result;
+use formats::animation::{AnimationSet, Sequence};
+use formats::project::Project;
+use std::path::Path;
+
+/// 列出项目中的库、书签、动画序列和备注
+pub(super) fn show_project(lep: &Path) -> Result<()> {
+    let project = Project::load(lep)?;
+    report!(
+        "{}",
+        tr!(
+            "项目 {}: {} 个库",
+            format!("{:?}", lep),
+            project.libraries.len()
+        )
+    );
+    if !project.notes.is_empty() {
+        report!("{}", tr!("  备注: {}", project.notes));
+    }
+    for library in &project.libraries {
+        let missing = if library.path.exists() {
+            ""
+        } else {
+            tr!("（文件不存在）")
+        };
+        report!("{}{}", library.path.display(), missing);
+        if !library.notes.is_empty() {
+            report!("{}", tr!("  备注: {}", library.notes));
+        }
+        for sequence in library.sequences.iter() {
+            report!("  {}", describe_sequence(sequence));
+        }
+        for bookmark in library.bookmarks.iter() {
+            report!(
+                "{}",
+                tr!("  书签 第 {} 帧: {}", bookmark.index, bookmark.label)
+            );
+        }
+    }
+    Ok(())
+}
+
+/// 修改项目文件（不存在时新建）并写回
+pub(super) fn edit_project(
+    lep: &Path,
+    edit: impl FnOnce(&mut Project) -> Result<String>,
+) -> Result<()> {
+    let mut project = if lep.exists() {
+        Project::load(lep)?
+    } else {
+        Project::default()
+    };
+    let message = edit(&mut project)?;
+    project.save(lep)?;
+    report!("{} ({:?})", message, lep);
+    Ok(())
+}
+
+/// 列出库的动画序列
+pub(super) fn show_animations(lib_path: &Path) -> Result<()> {
+    let base = lib_path.with_extension("");
+    let animations = AnimationSet::load(&base)?;
+    if animations.is_empty() {
+        report!("{}", tr!("{} 没有动画序列", format!("{:?}", lib_path)));
+        return Ok(());
+    }
+    report!(
+        "{}",
+        tr!(
+            "{}: {} 个动画序列",
+            format!("{:?}", lib_path),
+            animations.len()
+        )
+    );
+    for sequence in animations.iter() {
+        report!("  {}", describe_sequence(sequence));
+    }
+    Ok(())
+}
+
+/// 修改库的动画序列并写回 `<库名>.anim.json`
+pub(super) fn edit_animations(
+    lib_path: &Path,
+    edit: impl FnOnce(&mut AnimationSet) -> Result<String>,
+) -> Result<()> {
+    let base = lib_path.with_extension("");
+    let mut animations = AnimationSet::load(&base)?;
+    let message = edit(&mut animations)?;
+    animations.save(&base)?;
+    report!("{} ({:?})", message, AnimationSet::path(&base));
+    Ok(())
+}
+
+/// 由 `--range` 和 `--fps` 构造命名动画序列
+pub(super) fn parse_sequence(args: &[String], name: &str) -> Result<Sequence> {
+    let range = parse_range(option_value(args, "--range").ok_or_else(|| {
+        error::LibraryError::ParseError("动画序列需要 --range 起-止".to_string())
+    })?)?;
+    if range.end == usize::MAX {
+        return Err(error::LibraryError::ParseError(
+            "动画序列需要指定结束帧".to_string(),
+        ));
+    }
+    let fps = match option_value(args, "--fps") {
+        Some(text) => parse_fps(text)?,
+        None => image::video::DEFAULT_FPS,
+    };
+    Ok(Sequence {
+        name: name.to_string(),
+        start: range.start,
+        count: range.len(),
+        fps,
+    })
+}
+
+/// 动画序列的说明文字
+pub(super) fn describe_sequence(sequence: &Sequence) -> String {
+    tr!(
+        "动画序列 {}: 第 {}-{} 帧，{} FPS",
+        sequence.name,
+        sequence.start,
+        sequence.frames().end.saturating_sub(1),
+        sequence.fps
+    )
+}
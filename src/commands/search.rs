@@ -0,0 +1,87 @@
+//! 查找帧的命令：`similar`、`find`
+
+use super::{format_indices, parse_index, report};
+use crate::i18n::tr;
+use crate::locale;
+use mir2_library::{error, formats};
+
+use error::Result;
+use formats::LibraryLoader;
+use formats::filter::FrameFilter;
+use formats::similar::SimilarityIndex;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// 相似帧查找默认列出的个数
+const SIMILAR_LIMIT: usize = 10;
+
+/// 在若干库中查找与目标图像最相似的帧，目标为 PNG 或 `库文件#帧`
+pub(super) fn find_similar(target: &str, libs: &[PathBuf], limit: Option<&str>) -> Result<()> {
+    let limit = match limit {
+        Some(text) => text
+            .trim()
+            .parse()
+            .ok()
+            .filter(|&limit| limit > 0)
+            .ok_or_else(|| error::LibraryError::ParseError(format!("无效的个数: {}", text)))?,
+        None => SIMILAR_LIMIT,
+    };
+    let image = match target.rsplit_once('#') {
+        Some((lib, frame)) if Path::new(lib).is_file() => {
+            let index = parse_index(frame)?;
+            let (_, mut loader) = LibraryLoader::load(Path::new(lib))?;
+            loader.get_frame(index)?.ok_or_else(|| {
+                error::LibraryError::ParseError(format!("{} 第 {} 帧是空帧", lib, index))
+            })?
+        }
+        _ => Arc::new(::image::open(target)?.to_rgba8()),
+    };
+
+    let mut matches = Vec::new();
+    for lib in libs {
+        let (info, mut loader) = LibraryLoader::load(lib)?;
+        let index = SimilarityIndex::build(
+            &mut loader,
+            &mir2_library::progress::CancelToken::new(),
+            |_| {},
+        )?;
+        report!("{}", tr!("{}: {} 帧非空", info.file_name, index.len()));
+        matches.extend(
+            index
+                .search(&image, limit)
+                .into_iter()
+                .map(|m| (info.file_name.clone(), m)),
+        );
+    }
+    matches.sort_by_key(|(_, m)| m.distance);
+    matches.truncate(limit);
+
+    report!("{}", tr!("与 {} 最相似的帧:", target));
+    for (file_name, m) in &matches {
+        report!(
+            "{}",
+            tr!("  {} #{}: 距离 {}", file_name, m.index, m.distance)
+        );
+    }
+    Ok(())
+}
+
+/// 列出满足筛选条件的帧
+pub(super) fn find_frames(lib_path: &Path, filter: &FrameFilter) -> Result<()> {
+    let (info, mut loader) = LibraryLoader::load(lib_path)?;
+    let found = loader.find_frames(|frame| filter.matches(frame));
+    if found.is_empty() {
+        report!("{}", tr!("{} 中没有满足条件的帧", info.file_name));
+        return Ok(());
+    }
+    report!(
+        "{}",
+        tr!(
+            "{} 中有 {} 帧满足条件: {}",
+            info.file_name,
+            locale::count(found.len() as u64),
+            format_indices(&found)
+        )
+    );
+    Ok(())
+}
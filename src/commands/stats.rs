@@ -0,0 +1,303 @@
+//! 读取库信息的命令：`open`、`stats`、`inventory`、`list`
+
+use super::{format_indices, parse_range, report};
+use crate::i18n::tr;
+use crate::locale;
+use mir2_library::{error, formats};
+
+use error::Result;
+use formats::LibraryLoader;
+use formats::analyze::{self, LibraryStats};
+use std::path::Path;
+
+/// 打开库（远程库已下载到缓存），输出格式、帧数和本地路径
+pub(super) fn open_library(lib_path: &Path) -> Result<()> {
+    let (info, _loader) = LibraryLoader::load(lib_path)?;
+    report!("{} ({})", info.file_name, info.format_name());
+    report!(
+        "{}",
+        tr!("  帧数: {}", locale::count(info.image_count as u64))
+    );
+    // 空帧数取自文件头，读不到文件头的格式不显示
+    if let Ok(header) = LibraryLoader::open_header(lib_path) {
+        report!(
+            "{}",
+            tr!("  空帧: {}", locale::count(header.empty_count() as u64))
+        );
+    }
+    report!("{}", tr!("  本地路径: {}", format!("{:?}", lib_path)));
+    Ok(())
+}
+
+/// `stats` 命令中数据大小直方图的区间数
+const STATS_HISTOGRAM_BINS: usize = 10;
+
+/// 仅读取文件头，输出库的统计信息
+pub(super) fn library_stats(lib_path: &Path) -> Result<()> {
+    let header = LibraryLoader::open_header(lib_path)?;
+    let (max_width, max_height) = header.max_size();
+
+    report!("{} ({})", header.info.file_name, header.info.format_name());
+    report!(
+        "{}",
+        tr!("  帧数: {}", locale::count(header.frames.len() as u64))
+    );
+    report!(
+        "{}",
+        tr!("  空帧: {}", locale::count(header.empty_count() as u64))
+    );
+    report!(
+        "{}",
+        tr!("  带遮罩: {}", locale::count(header.mask_count() as u64))
+    );
+    report!("{}", tr!("  最大尺寸: {}x{}", max_width, max_height));
+    report!(
+        "{}",
+        tr!("  像素数据: {}", locale::size(header.payload_bytes()))
+    );
+
+    let histogram = header.size_histogram(STATS_HISTOGRAM_BINS);
+    if histogram.bins.is_empty() {
+        return Ok(());
+    }
+    report!(
+        "{}",
+        tr!("  数据大小中位数: {}", locale::size(histogram.median))
+    );
+    let peak = histogram.peak().max(1);
+    for bin in &histogram.bins {
+        report!(
+            "    {:>11} - {:<11} {:<20} {}",
+            locale::count(bin.min),
+            locale::count(bin.max),
+            "#".repeat((bin.frames.len() * 20).div_ceil(peak)),
+            bin.frames.len()
+        );
+    }
+    if !histogram.outliers.is_empty() {
+        report!(
+            "{}",
+            tr!(
+                "  异常大的帧 (超过 {}): {}",
+                locale::size(histogram.fence),
+                format_indices(&histogram.outliers)
+            )
+        );
+    }
+    Ok(())
+}
+
+/// `stats --analyze` 默认列出的最大帧个数
+const ANALYZE_LIMIT: usize = 10;
+
+/// 逐帧解码，输出体积分析：压缩前后的大小、最大的帧、尺寸分布和估计可省的空间
+pub(super) fn analyze_library(lib_path: &Path, limit: Option<&str>) -> Result<()> {
+    let limit = match limit {
+        Some(text) => text
+            .trim()
+            .parse()
+            .ok()
+            .filter(|&limit| limit > 0)
+            .ok_or_else(|| error::LibraryError::ParseError(format!("无效的个数: {}", text)))?,
+        None => ANALYZE_LIMIT,
+    };
+    let header = LibraryLoader::open_header(lib_path)?;
+    let (info, mut loader) = LibraryLoader::load(lib_path)?;
+    let stats = LibraryStats::collect(
+        &header,
+        &mut loader,
+        &mir2_library::progress::CancelToken::new(),
+        |_| {},
+    )?;
+
+    let (stored, raw) = (stats.stored_bytes(), stats.raw_bytes());
+    report!("{} ({})", info.file_name, info.format_name());
+    report!(
+        "{}",
+        tr!(
+            "  非空帧: {}，文件中 {}，解码后 {} ({}%)",
+            locale::count(stats.frames.len() as u64),
+            locale::size(stored),
+            locale::size(raw),
+            format!("{:.1}", stored as f64 * 100.0 / raw.max(1) as f64)
+        )
+    );
+
+    report!("{}", tr!("  最大的 {} 帧:", limit.min(stats.frames.len())));
+    for frame in stats.largest(limit) {
+        report!(
+            "    #{:<6} {:>5}x{:<5} {:>11} ({:.1}%)",
+            frame.index,
+            frame.width,
+            frame.height,
+            locale::size(frame.stored),
+            frame.ratio() * 100.0
+        );
+    }
+
+    report!("{}", tr!("  尺寸分布（长边）:"));
+    let peak = stats.dimensions.iter().copied().max().unwrap_or(0).max(1);
+    for (bin, &count) in stats.dimensions.iter().enumerate() {
+        let label = match analyze::DIMENSION_LIMITS.get(bin) {
+            Some(limit) => format!("<= {}", limit),
+            None => format!("> {}", analyze::DIMENSION_LIMITS[bin - 1]),
+        };
+        report!(
+            "    {:<8} {:<20} {}",
+            label,
+            "#".repeat((count * 20).div_ceil(peak)),
+            count
+        );
+    }
+
+    if !stats.duplicates.is_empty() {
+        let groups: Vec<String> = stats
+            .duplicates
+            .iter()
+            .map(|group| format_indices(group))
+            .collect();
+        report!(
+            "{}",
+            tr!(
+                "  重复帧 ({} 组): {}",
+                stats.duplicates.len(),
+                groups.join("; ")
+            )
+        );
+    }
+    report!("{}", tr!("  估计可省（分别估算，不可相加）:"));
+    report!(
+        "{}",
+        tr!("    裁掉透明边: {}", locale::size(stats.savings.trim))
+    );
+    report!(
+        "{}",
+        tr!("    去掉重复帧: {}", locale::size(stats.savings.dedup))
+    );
+    report!(
+        "{}",
+        tr!("    改用 zstd:  {}", locale::size(stats.savings.zstd))
+    );
+    Ok(())
+}
+
+/// 仅读取文件头，逐帧列出尺寸、偏移和数据大小
+pub(super) fn library_inventory(lib_path: &Path) -> Result<()> {
+    let header = LibraryLoader::open_header(lib_path)?;
+
+    report!(
+        "{}",
+        tr!(
+            "{} ({}, {} 帧)",
+            header.info.file_name,
+            header.info.format_name(),
+            locale::count(header.frames.len() as u64)
+        )
+    );
+    for frame in &header.frames {
+        if frame.is_empty() {
+            report!("{}", tr!("  #{} 空帧", format!("{:<6}", frame.index)));
+            continue;
+        }
+        report!(
+            "{}",
+            tr!(
+                "  #{} {}x{} ({}, {}) {} 字节{}",
+                format!("{:<6}", frame.index),
+                frame.width,
+                frame.height,
+                frame.x,
+                frame.y,
+                locale::count(frame.data_length),
+                if frame.has_mask { tr!(" [遮罩]") } else { "" }
+            )
+        );
+    }
+    Ok(())
+}
+
+/// 仅读取文件头，以表格列出帧的尺寸、偏移、阴影、遮罩和数据大小，或写入 CSV
+pub(super) fn list_frames(lib_path: &Path, range: Option<&str>, csv: Option<&Path>) -> Result<()> {
+    let header = LibraryLoader::open_header(lib_path)?;
+    let range = match range {
+        Some(text) => parse_range(text)?,
+        None => 0..header.frames.len(),
+    };
+    let frames: Vec<_> = header
+        .frames
+        .iter()
+        .filter(|frame| range.contains(&frame.index))
+        .collect();
+
+    // 表格中的数据大小带千位分隔符，CSV 中按原样写出
+    let row = |frame: &formats::header::FrameHeader, size: String| {
+        vec![
+            frame.index.to_string(),
+            frame.width.to_string(),
+            frame.height.to_string(),
+            frame.x.to_string(),
+            frame.y.to_string(),
+            frame.shadow.to_string(),
+            if frame.has_mask { "有" } else { "" }.to_string(),
+            size,
+        ]
+    };
+    let titles = ["索引", "宽", "高", "X", "Y", "阴影", "遮罩", "数据大小"];
+    let titles = titles.map(String::from).to_vec();
+
+    if let Some(csv) = csv {
+        let mut rows = vec![titles];
+        rows.extend(
+            frames
+                .iter()
+                .map(|frame| row(frame, frame.data_length.to_string())),
+        );
+        locale::write_csv(csv, &rows)?;
+        report!(
+            "{}",
+            tr!(
+                "{} 帧的信息已写入 {}",
+                locale::count(frames.len() as u64),
+                format!("{:?}", csv)
+            )
+        );
+        return Ok(());
+    }
+
+    let mut rows = vec![titles];
+    rows.extend(
+        frames
+            .iter()
+            .map(|frame| row(frame, locale::count(frame.data_length))),
+    );
+    // 各列按显示宽度右对齐
+    let widths: Vec<usize> = (0..rows[0].len())
+        .map(|column| {
+            rows.iter()
+                .map(|row| locale::display_width(&row[column]))
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    report!(
+        "{}",
+        tr!(
+            "{} ({}, {} 帧)",
+            header.info.file_name,
+            header.info.format_name(),
+            locale::count(header.frames.len() as u64)
+        )
+    );
+    for row in &rows {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, &width)| {
+                let fill = width.saturating_sub(locale::display_width(cell));
+                format!("{}{}", " ".repeat(fill), cell)
+            })
+            .collect();
+        report!("  {}", cells.join("  "));
+    }
+    Ok(())
+}
@@ -0,0 +1,108 @@
+//! 其他命令：`serve`、`associate`、`spec`、`actions`
+
+use super::report;
+use crate::i18n::tr;
+use crate::{actions, associate, serve};
+use mir2_library::{error, formats};
+
+use error::Result;
+use formats::LibraryLoader;
+use std::path::{Path, PathBuf};
+
+/// 在本机端口上提供资源目录中库的帧图像和信息
+pub(super) fn serve_assets(dir: Option<&str>, port: Option<&str>) -> Result<()> {
+    let Some(root) = dir
+        .map(PathBuf::from)
+        .or_else(|| formats::integrity::configured_dirs().into_iter().next())
+    else {
+        return Err(error::LibraryError::ParseError(format!(
+            "未指定资源目录（命令行参数或环境变量 {}）",
+            formats::integrity::ASSET_DIRS_ENV
+        )));
+    };
+    let port = match port {
+        Some(text) => text
+            .parse::<u16>()
+            .map_err(|_| error::LibraryError::ParseError(format!("无效的端口: {}", text)))?,
+        None => serve::DEFAULT_PORT,
+    };
+
+    let server = serve::AssetServer::new(&root)?;
+    serve::run(server, &format!("127.0.0.1:{}", port))
+}
+
+/// 登记或取消文件关联；指定 `reg` 时只写出注册表脚本
+pub(super) fn associate_files(reg: Option<&Path>, remove: bool) -> Result<()> {
+    let exe = std::env::current_exe()?;
+    if let Some(reg) = reg {
+        associate::write_reg(reg, &exe, remove)?;
+        report!("{}", tr!("已写出注册表脚本: {}", reg.display()));
+        return Ok(());
+    }
+    associate::register(&exe, remove)?;
+    if remove {
+        report!("{}", tr!("已取消文件关联"));
+    } else {
+        report!("{}", tr!("已登记文件关联: {}", exe.display()));
+        report!(
+            "{}",
+            tr!("  扩展名: .{}", associate::EXTENSIONS.join(", ."))
+        );
+    }
+    Ok(())
+}
+
+/// 显示格式说明；传入库文件时在字段后标出文件中的实际值
+pub(super) fn format_spec(target: &str) -> Result<()> {
+    let path = Path::new(target);
+    let (spec, values) = if path.is_file() {
+        let lib_type = LibraryLoader::detect_library_type(path)?;
+        let spec = formats::spec::spec_for(lib_type).ok_or_else(|| {
+            error::LibraryError::ParseError(format!("{} 没有格式说明", lib_type.name()))
+        })?;
+        (spec, formats::spec::annotate(path, lib_type)?)
+    } else {
+        let spec = formats::spec::find(target)
+            .ok_or_else(|| error::LibraryError::ParseError(format!("未知的格式: {}", target)))?;
+        (spec, Default::default())
+    };
+
+    report!("{} ({})", spec.name, spec.files);
+    report!("  {}", spec.summary);
+    for section in spec.sections {
+        report!("");
+        report!("  {}", section.title);
+        for field in section.fields {
+            let value = values
+                .get(field.id)
+                .map(|v| format!(" = {}", v))
+                .unwrap_or_default();
+            report!(
+                "    {:<6} {:<12} {}{}",
+                field.offset,
+                field.kind,
+                field.description,
+                value
+            );
+        }
+    }
+    Ok(())
+}
+
+/// 列出编辑器操作及对应的命令行用法
+pub(super) fn list_actions(query: &str) -> Result<()> {
+    let found = actions::search(query);
+    if found.is_empty() {
+        report!("{}", tr!("没有与 {} 匹配的操作", format!("{:?}", query)));
+        return Ok(());
+    }
+    for action in found {
+        report!(
+            "  {:<24} {:<28} {}",
+            action.id(),
+            action.label(),
+            action.cli().unwrap_or("-")
+        );
+    }
+    Ok(())
+}
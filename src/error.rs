@@ -5,35 +5,227 @@ use thiserror::Error;
 /// 库编辑器错误类型
 #[derive(Error, Debug)]
 pub enum LibraryError {
+    /// 文件读写失败
     #[error("IO 错误: {0}")]
     Io(#[from] std::io::Error),
 
+    /// 图片编解码失败
     #[error("图片解码错误: {0}")]
     ImageDecode(#[from] image::ImageError),
 
+    /// ZIP 归档读写失败
+    #[error("ZIP 归档错误: {0}")]
+    Archive(#[from] zip::result::ZipError),
+
+    /// JSON 序列化失败（清单、偏好设置等）
+    #[error("JSON 错误: {0}")]
+    Json(#[from] serde_json::Error),
+
+    /// 界面错误
     #[error("GUI 错误: {0}")]
     Gui(String),
 
+    /// 像素数据压缩或解压失败
     #[error("压缩/解压缩错误: {0}")]
     Compression(String),
 
+    /// 文件不是可识别的库格式
     #[error("无效的文件格式")]
     InvalidFormat,
 
+    /// 库的版本号不受支持
     #[error("不支持的版本: {0}")]
     UnsupportedVersion(i32),
 
+    /// 帧索引超出范围
     #[error("索引超出范围: {0}")]
     IndexOutOfBounds(usize),
 
+    /// 文件（或伴随的索引文件）不存在
     #[error("文件未找到: {0}")]
     FileNotFound(String),
 
+    /// 帧数据无法解码
     #[error("无效的图片数据")]
     InvalidImageData,
 
+    /// 格式不支持某项操作（格式名、操作说明）
+    #[error("{0} 不支持{1}")]
+    Unsupported(String, &'static str),
+
+    /// 文件中声明的长度或帧数为负数或超出解析上限
+    #[error("数据长度无效: {0}")]
+    InvalidLength(String),
+
+    /// 库超过 32 位偏移能表示的大小（需要的字节数）
+    #[error("库大小 {0} 字节超过 4 GB，需要保存为 64 位偏移的 V2.1 格式")]
+    OffsetOverflow(u64),
+
+    /// 参数或文件内容无法解析
     #[error("解析错误: {0}")]
     ParseError(String),
+
+    /// 远程库下载失败
+    #[error("下载失败: {0}")]
+    Download(String),
+
+    /// 帧已锁定，拒绝修改
+    #[error("第 {0} 帧已锁定")]
+    FrameLocked(usize),
+
+    /// 仅追加模式下拒绝会移动已有帧索引的操作
+    #[error("仅追加模式下不能{0}")]
+    AppendOnly(&'static str),
+
+    /// 以只读方式打开，拒绝修改和保存
+    #[error("以只读方式打开，不能{0}")]
+    ReadOnly(&'static str),
+
+    /// 库正被其他程序以读写方式打开
+    #[error("库正被其他程序编辑: {0}")]
+    LibraryInUse(String),
+
+    /// 库文件在写入恢复日志后被改动过，不能按日志恢复
+    #[error("库文件在上次编辑后已改变，不能恢复未保存的修改: {0}")]
+    StaleJournal(String),
+
+    /// 调用外部编码器（ffmpeg）失败
+    #[error("视频编码失败: {0}")]
+    Encoder(String),
+
+    /// 脚本解析或运行出错
+    #[error("脚本错误: {0}")]
+    Script(String),
+
+    /// 操作被用户取消
+    #[error("已取消")]
+    Cancelled,
+
+    /// 批量操作中有部分项目失败（失败数、总数），其余已完成
+    #[error("{0}/{1} 项失败")]
+    PartialFailure(usize, usize),
 }
 
+/// 错误类别
+///
+/// 命令行以此作为进程退出码（成功为 0），批处理脚本据此区分失败原因，
+/// 不必解析错误信息。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    /// 其他错误（参数无效、帧已锁定等）
+    Other = 1,
+    /// 批量操作中部分项目失败
+    PartialFailure = 2,
+    /// 格式或版本不受支持
+    Unsupported = 3,
+    /// 文件损坏或数据无法解码
+    Corrupt = 4,
+    /// 文件读写失败或文件不存在
+    Io = 5,
+}
+
+impl ErrorCode {
+    /// 进程退出码
+    pub fn exit_code(self) -> u8 {
+        self as u8
+    }
+
+    /// 类别名称
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Other => "其他错误",
+            Self::PartialFailure => "部分失败",
+            Self::Unsupported => "格式不支持",
+            Self::Corrupt => "文件损坏",
+            Self::Io => "读写错误",
+        }
+    }
+}
+
+impl LibraryError {
+    /// 错误所属的类别
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            // 文件被截断或内容错乱时读取会提前遇到文件尾，按损坏处理而不是读写失败
+            Self::Io(e)
+                if matches!(
+                    e.kind(),
+                    std::io::ErrorKind::UnexpectedEof | std::io::ErrorKind::InvalidData
+                ) =>
+            {
+                ErrorCode::Corrupt
+            }
+            Self::Io(_) | Self::FileNotFound(_) | Self::Download(_) | Self::LibraryInUse(_) => {
+                ErrorCode::Io
+            }
+            Self::InvalidFormat
+            | Self::UnsupportedVersion(_)
+            | Self::Unsupported(..)
+            | Self::OffsetOverflow(_) => ErrorCode::Unsupported,
+            Self::ImageDecode(_)
+            | Self::Archive(_)
+            | Self::Json(_)
+            | Self::Compression(_)
+            | Self::InvalidImageData
+            | Self::InvalidLength(_) => ErrorCode::Corrupt,
+            Self::PartialFailure(..) => ErrorCode::PartialFailure,
+            Self::Gui(_)
+            | Self::IndexOutOfBounds(_)
+            | Self::ParseError(_)
+            | Self::FrameLocked(_)
+            | Self::AppendOnly(_)
+            | Self::ReadOnly(_)
+            | Self::StaleJournal(_)
+            | Self::Encoder(_)
+            | Self::Script(_)
+            | Self::Cancelled => ErrorCode::Other,
+        }
+    }
+}
+
+/// 库操作的结果类型
 pub type Result<T> = std::result::Result<T, LibraryError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_codes() {
+        let missing = std::io::Error::from(std::io::ErrorKind::NotFound);
+        assert_eq!(LibraryError::from(missing).code(), ErrorCode::Io);
+        assert_eq!(
+            LibraryError::UnsupportedVersion(9).code(),
+            ErrorCode::Unsupported
+        );
+        assert_eq!(LibraryError::InvalidImageData.code(), ErrorCode::Corrupt);
+        assert_eq!(LibraryError::PartialFailure(1, 3).code().exit_code(), 2);
+        assert_eq!(LibraryError::Cancelled.code().exit_code(), 1);
+    }
+
+    #[test]
+    fn test_truncated_library_is_corrupt() {
+        use crate::formats::mlibrary_v2::MImage;
+        use crate::formats::{LibraryLoader, MLibraryV2, companion_path};
+
+        let eof = std::io::Error::from(std::io::ErrorKind::UnexpectedEof);
+        assert_eq!(LibraryError::from(eof).code(), ErrorCode::Corrupt);
+
+        let temp = tempfile::tempdir().unwrap();
+        let base = temp.path().join("Truncated");
+        let mut library = MLibraryV2::new(base.clone()).unwrap();
+        let pixels = image::RgbaImage::from_pixel(4, 4, image::Rgba([1, 2, 3, 255]));
+        for _ in 0..3 {
+            library.add_image(&MImage::from_image(&pixels, 0, 0));
+        }
+        library.save().unwrap();
+
+        let path = companion_path(&base, ".Lib");
+        let data = std::fs::read(&path).unwrap();
+        std::fs::write(&path, &data[..12]).unwrap();
+        let error = LibraryLoader::load(&path).err().unwrap();
+        assert!(matches!(error, LibraryError::Io(_)), "{error:?}");
+        assert_eq!(error.code(), ErrorCode::Corrupt);
+        assert_eq!(error.code().exit_code(), 4);
+    }
+}
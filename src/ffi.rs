@@ -0,0 +1,328 @@
+//! C 接口（`capi` feature）
+//!
+//! 供 C / C++ / C# 工具复用库解析：打开库、查询帧数和帧信息、把帧解码到调用方提供的
+//! 缓冲区、释放资源。声明见手写的 `include/mir2_library.h`，修改导出函数或常量时同步
+//! 修改，测试会检查两边一致。编译动态库：
+//!
+//! ```text
+//! cargo rustc --lib --release --no-default-features --features capi --crate-type cdylib
+//! ```
+//!
+//! 约定：
+//! - 路径为 UTF-8 编码的 C 字符串
+//! - 帧解码为 RGBA8，自上而下逐行排列，每行 `width * 4` 字节，无行间填充
+//! - 返回负数表示失败，详细原因由 [`mir2_library_last_error`] 取得（按线程保存）
+//! - 同一个库句柄不能在多个线程中同时使用
+
+use crate::error::LibraryError;
+use crate::formats::LibraryLoader;
+use std::cell::RefCell;
+use std::ffi::{CStr, CString, c_char};
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::path::Path;
+
+/// 成功
+pub const MIR2_OK: i32 = 0;
+/// 空帧（没有像素数据，缓冲区未写入）
+pub const MIR2_EMPTY_FRAME: i32 = 1;
+/// 失败，原因见 [`mir2_library_last_error`]
+pub const MIR2_ERROR: i32 = -1;
+/// 缓冲区不足以容纳整帧
+pub const MIR2_BUFFER_TOO_SMALL: i32 = -2;
+/// 参数为空指针或索引超出范围
+pub const MIR2_INVALID_ARGUMENT: i32 = -3;
+
+/// 已打开的库（对 C 不透明）
+pub struct Mir2Library {
+    loader: LibraryLoader,
+}
+
+/// 帧信息
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Mir2FrameInfo {
+    /// 宽度（空帧为 0）
+    pub width: i32,
+    /// 高度（空帧为 0）
+    pub height: i32,
+    /// X 偏移
+    pub x: i32,
+    /// Y 偏移
+    pub y: i32,
+}
+
+thread_local! {
+    /// 本线程最近一次失败的原因
+    static LAST_ERROR: RefCell<CString> = RefCell::new(CString::default());
+}
+
+/// 记录失败原因
+fn set_last_error(message: impl Into<String>) {
+    // 消息中不会出现 NUL，万一出现则截断
+    let mut bytes = message.into().into_bytes();
+    if let Some(nul) = bytes.iter().position(|&b| b == 0) {
+        bytes.truncate(nul);
+    }
+    let message = CString::new(bytes).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = message);
+}
+
+/// 执行调用并把错误和 panic 转换为状态码（panic 不能跨越 C 边界）
+fn guard(f: impl FnOnce() -> Result<i32, (i32, String)>) -> i32 {
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(status)) => status,
+        Ok(Err((status, message))) => {
+            set_last_error(message);
+            status
+        }
+        Err(_) => {
+            set_last_error("内部错误（panic）");
+            MIR2_ERROR
+        }
+    }
+}
+
+/// 库错误对应的状态码和消息
+fn library_error(e: LibraryError) -> (i32, String) {
+    let status = match e {
+        LibraryError::IndexOutOfBounds(_) => MIR2_INVALID_ARGUMENT,
+        _ => MIR2_ERROR,
+    };
+    (status, e.to_string())
+}
+
+/// 打开库文件，失败时返回空指针
+///
+/// # Safety
+///
+/// `path` 必须是有效的、以 NUL 结尾的 C 字符串（或空指针）。
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mir2_library_open(path: *const c_char) -> *mut Mir2Library {
+    let mut library = None;
+    guard(|| {
+        if path.is_null() {
+            return Err((MIR2_INVALID_ARGUMENT, "路径为空".to_string()));
+        }
+        // SAFETY: 调用方保证 path 为有效的 C 字符串
+        let path = unsafe { CStr::from_ptr(path) }
+            .to_str()
+            .map_err(|_| (MIR2_INVALID_ARGUMENT, "路径不是有效的 UTF-8".to_string()))?;
+        // 加载器对不存在的 .Lib 会当作新建的空库，C 接口只打开已有文件
+        let path = Path::new(path);
+        if !path.is_file() {
+            return Err(library_error(LibraryError::FileNotFound(
+                path.display().to_string(),
+            )));
+        }
+        let (_, loader) = LibraryLoader::load(path).map_err(library_error)?;
+        library = Some(Box::new(Mir2Library { loader }));
+        Ok(MIR2_OK)
+    });
+    library.map_or(std::ptr::null_mut(), Box::into_raw)
+}
+
+/// 释放 [`mir2_library_open`] 返回的库，空指针时什么也不做
+///
+/// # Safety
+///
+/// `library` 必须来自 [`mir2_library_open`] 且未被释放过。
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mir2_library_free(library: *mut Mir2Library) {
+    if !library.is_null() {
+        // SAFETY: 调用方保证指针来自 Box::into_raw 且只释放一次
+        drop(unsafe { Box::from_raw(library) });
+    }
+}
+
+/// 帧数，空指针时返回 0
+///
+/// # Safety
+///
+/// `library` 必须是有效的库句柄（或空指针）。
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mir2_library_count(library: *const Mir2Library) -> usize {
+    // SAFETY: 调用方保证指针有效
+    unsafe { library.as_ref() }.map_or(0, |library| library.loader.image_count())
+}
+
+/// 读取帧的尺寸和偏移
+///
+/// # Safety
+///
+/// `library` 必须是有效的库句柄，`info` 必须指向可写的 [`Mir2FrameInfo`]。
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mir2_library_frame_info(
+    library: *mut Mir2Library,
+    index: usize,
+    info: *mut Mir2FrameInfo,
+) -> i32 {
+    guard(|| {
+        // SAFETY: 调用方保证指针有效
+        let (Some(library), Some(info)) = (unsafe { library.as_mut() }, unsafe { info.as_mut() })
+        else {
+            return Err((MIR2_INVALID_ARGUMENT, "库句柄或输出参数为空".to_string()));
+        };
+        check_index(library, index)?;
+        let image = library
+            .loader
+            .get_image_info(index)
+            .map_err(library_error)?;
+        *info = Mir2FrameInfo {
+            width: image.width,
+            height: image.height,
+            x: image.x,
+            y: image.y,
+        };
+        Ok(MIR2_OK)
+    })
+}
+
+/// 把帧解码为 RGBA8 写入 `buffer`（至少 `width * height * 4` 字节）
+///
+/// 空帧返回 [`MIR2_EMPTY_FRAME`]，缓冲区不足返回 [`MIR2_BUFFER_TOO_SMALL`]。
+///
+/// # Safety
+///
+/// `library` 必须是有效的库句柄，`buffer` 必须指向至少 `buffer_len` 字节的可写内存。
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mir2_library_decode_frame(
+    library: *mut Mir2Library,
+    index: usize,
+    buffer: *mut u8,
+    buffer_len: usize,
+) -> i32 {
+    guard(|| {
+        // SAFETY: 调用方保证指针有效
+        let Some(library) = (unsafe { library.as_mut() }) else {
+            return Err((MIR2_INVALID_ARGUMENT, "库句柄为空".to_string()));
+        };
+        if buffer.is_null() {
+            return Err((MIR2_INVALID_ARGUMENT, "缓冲区为空".to_string()));
+        }
+        check_index(library, index)?;
+        let Some(frame) = library.loader.get_frame(index).map_err(library_error)? else {
+            return Ok(MIR2_EMPTY_FRAME);
+        };
+        let pixels = frame.as_raw();
+        if buffer_len < pixels.len() {
+            return Err((
+                MIR2_BUFFER_TOO_SMALL,
+                format!("缓冲区 {} 字节，需要 {} 字节", buffer_len, pixels.len()),
+            ));
+        }
+        // SAFETY: 调用方保证 buffer 至少有 buffer_len 字节，且已检查 buffer_len 足够
+        unsafe { std::ptr::copy_nonoverlapping(pixels.as_ptr(), buffer, pixels.len()) };
+        Ok(MIR2_OK)
+    })
+}
+
+/// 本线程最近一次失败的原因（UTF-8），下一次失败前一直有效；没有失败时为空字符串
+#[unsafe(no_mangle)]
+pub extern "C" fn mir2_library_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ptr())
+}
+
+/// 检查帧索引
+fn check_index(library: &Mir2Library, index: usize) -> Result<(), (i32, String)> {
+    let count = library.loader.image_count();
+    if index >= count {
+        return Err((
+            MIR2_INVALID_ARGUMENT,
+            format!("帧索引 {} 超出范围（共 {} 帧）", index, count),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::mlibrary_v2::{MImage, MLibraryV2};
+
+    #[test]
+    fn test_open_decode_and_free() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let frame = image::RgbaImage::from_pixel(4, 3, image::Rgba([200, 10, 10, 255]));
+        let mut source = MLibraryV2::new(dir.join("Hum")).unwrap();
+        source.add_image(&MImage::from_image(&frame, -2, 5));
+        source.add_image(&MImage::new());
+        source.save().unwrap();
+
+        let path = CString::new(dir.join("Hum.Lib").to_str().unwrap()).unwrap();
+        let missing = CString::new(dir.join("missing.Lib").to_str().unwrap()).unwrap();
+        unsafe {
+            assert!(mir2_library_open(missing.as_ptr()).is_null());
+            assert!(!CStr::from_ptr(mir2_library_last_error()).is_empty());
+
+            let library = mir2_library_open(path.as_ptr());
+            assert!(!library.is_null());
+            assert_eq!(mir2_library_count(library), 2);
+
+            let mut info = Mir2FrameInfo::default();
+            assert_eq!(mir2_library_frame_info(library, 0, &mut info), MIR2_OK);
+            assert_eq!(
+                info,
+                Mir2FrameInfo {
+                    width: 4,
+                    height: 3,
+                    x: -2,
+                    y: 5
+                }
+            );
+
+            let mut buffer = vec![0u8; 4 * 3 * 4];
+            assert_eq!(
+                mir2_library_decode_frame(library, 0, buffer.as_mut_ptr(), 8),
+                MIR2_BUFFER_TOO_SMALL
+            );
+            assert_eq!(
+                mir2_library_decode_frame(library, 0, buffer.as_mut_ptr(), buffer.len()),
+                MIR2_OK
+            );
+            assert_eq!(buffer, frame.as_raw().as_slice());
+            assert_eq!(
+                mir2_library_decode_frame(library, 1, buffer.as_mut_ptr(), buffer.len()),
+                MIR2_EMPTY_FRAME
+            );
+            assert_eq!(
+                mir2_library_frame_info(library, 2, &mut info),
+                MIR2_INVALID_ARGUMENT
+            );
+            mir2_library_free(library);
+        }
+
+        // 头文件与导出函数、常量保持一致
+        let header = include_str!("../include/mir2_library.h");
+        for (name, value) in [
+            ("MIR2_OK", MIR2_OK),
+            ("MIR2_EMPTY_FRAME", MIR2_EMPTY_FRAME),
+            ("MIR2_ERROR", MIR2_ERROR),
+            ("MIR2_BUFFER_TOO_SMALL", MIR2_BUFFER_TOO_SMALL),
+            ("MIR2_INVALID_ARGUMENT", MIR2_INVALID_ARGUMENT),
+        ] {
+            let define = format!("#define {} {}", name, value);
+            assert!(header.contains(&define), "头文件缺少 {}", define);
+        }
+        // 函数签名改动时这里和头文件要一起改
+        let declarations = [
+            "Mir2Library *mir2_library_open(const char *path);",
+            "void mir2_library_free(Mir2Library *library);",
+            "size_t mir2_library_count(const Mir2Library *library);",
+            "int32_t mir2_library_frame_info(Mir2Library *library, size_t index, Mir2FrameInfo *info);",
+            "int32_t mir2_library_decode_frame(Mir2Library *library, size_t index, uint8_t *buffer, size_t buffer_len);",
+            "const char *mir2_library_last_error(void);",
+            "int32_t width; int32_t height; int32_t x; int32_t y; } Mir2FrameInfo;",
+        ];
+        // 去掉注释并合并空白后比较，不受换行和对齐影响
+        let code = header
+            .lines()
+            .map(|line| line.split("/*").next().unwrap_or_default())
+            .collect::<Vec<_>>()
+            .join(" ");
+        let code = code.split_whitespace().collect::<Vec<_>>().join(" ");
+        for declaration in declarations {
+            assert!(code.contains(declaration), "头文件缺少 {}", declaration);
+        }
+    }
+}
@@ -0,0 +1,226 @@
+//! 库的体积分析 (stats --analyze)
+//!
+//! 客户端补丁越打越大时，先要知道空间花在了哪里。这里逐帧解码，对比文件中的数据
+//! 大小与解码后的 RGBA 大小，列出最大的帧和帧尺寸的分布，并估算三种瘦身手段各能
+//! 省下多少：
+//! - 裁掉透明边：按裁剪后面积占原面积的比例折算数据大小，全透明的帧按整帧计；
+//! - 去重：像素和尺寸完全相同的帧只保留一份；
+//! - zstd：把解码后的像素用 zstd 压缩（即 V2 的 `--zstd`），与当前数据大小比较。
+//!
+//! 三项分别估算，不能简单相加；实际能省多少还取决于目标格式。
+
+use crate::error::{LibraryError, Result};
+use crate::formats::LibraryLoader;
+use crate::formats::header::LibraryHeader;
+use crate::formats::mlibrary_v2::Codec;
+use crate::image::baseline::content_bounds;
+use crate::progress::{CancelToken, Progress};
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::Arc;
+
+/// 尺寸分布的区间上限（帧的长边，像素，含），超过最后一个的归入末尾区间
+pub const DIMENSION_LIMITS: [u32; 7] = [16, 32, 64, 128, 256, 512, 1024];
+
+/// 单帧的大小
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameSize {
+    /// 帧索引
+    pub index: usize,
+    /// 宽度
+    pub width: u32,
+    /// 高度
+    pub height: u32,
+    /// 文件中的数据大小（字节，含遮罩层）
+    pub stored: u64,
+    /// 解码后的 RGBA 大小（字节）
+    pub raw: u64,
+}
+
+impl FrameSize {
+    /// 压缩率（文件中的大小 / 解码后的大小）
+    pub fn ratio(&self) -> f64 {
+        self.stored as f64 / self.raw.max(1) as f64
+    }
+}
+
+/// 估计可以省下的字节数
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Savings {
+    /// 裁掉透明边
+    pub trim: u64,
+    /// 去掉重复帧
+    pub dedup: u64,
+    /// 改用 zstd 压缩
+    pub zstd: u64,
+}
+
+/// 库的体积分析结果
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LibraryStats {
+    /// 非空帧的大小（按索引）
+    pub frames: Vec<FrameSize>,
+    /// 按长边统计的帧数，与 [`DIMENSION_LIMITS`] 对应，最后多一个更大的区间
+    pub dimensions: Vec<usize>,
+    /// 像素完全相同的帧（每组按索引升序，至少两帧）
+    pub duplicates: Vec<Vec<usize>>,
+    /// 估计可以省下的字节数
+    pub savings: Savings,
+}
+
+impl LibraryStats {
+    /// 逐帧解码并统计，每帧之后报告进度并检查取消；解码失败的帧跳过
+    ///
+    /// 数据大小取自 `header`（已保存文件的帧头），像素取自 `loader`。
+    pub fn collect(
+        header: &LibraryHeader,
+        loader: &mut LibraryLoader,
+        cancel: &CancelToken,
+        progress: impl FnMut(Progress),
+    ) -> Result<Self> {
+        Self::collect_with(header, cancel, progress, |index| loader.get_frame(index))
+    }
+
+    /// 同 [`collect`](Self::collect)，像素逐帧由 `frame` 取得
+    ///
+    /// 供后台线程使用：每取一帧才锁一次共享的加载器。`frame` 返回
+    /// [`LibraryError::Cancelled`] 时中止统计，其它错误只跳过该帧。
+    pub fn collect_with(
+        header: &LibraryHeader,
+        cancel: &CancelToken,
+        mut progress: impl FnMut(Progress),
+        mut frame: impl FnMut(usize) -> Result<Option<Arc<image::RgbaImage>>>,
+    ) -> Result<Self> {
+        let total = header.frames.len();
+        let mut stats = LibraryStats {
+            dimensions: vec![0; DIMENSION_LIMITS.len() + 1],
+            ..LibraryStats::default()
+        };
+        let mut groups: HashMap<u64, Vec<usize>> = HashMap::new();
+
+        for (done, entry) in header.frames.iter().enumerate() {
+            cancel.check()?;
+            let decoded = if entry.is_empty() {
+                None
+            } else {
+                match frame(entry.index) {
+                    Err(e @ LibraryError::Cancelled) => return Err(e),
+                    decoded => decoded
+                        .inspect_err(|e| tracing::debug!("分析第 {} 帧失败: {:?}", entry.index, e))
+                        .ok()
+                        .flatten(),
+                }
+            };
+            if let Some(image) = decoded {
+                let (width, height) = image.dimensions();
+                let size = FrameSize {
+                    index: entry.index,
+                    width,
+                    height,
+                    stored: entry.data_length,
+                    raw: image.as_raw().len() as u64,
+                };
+                let side = width.max(height);
+                let bin = DIMENSION_LIMITS.partition_point(|&limit| limit < side);
+                stats.dimensions[bin] += 1;
+
+                let area = width as u64 * height as u64;
+                let kept = content_bounds(&image)
+                    .map_or(0, |(l, t, r, b)| (r - l) as u64 * (b - t) as u64);
+                stats.savings.trim += size.stored - size.stored * kept / area.max(1);
+
+                let zstd = Codec::Zstd.compress(image.as_raw()).len() as u64;
+                stats.savings.zstd += size.stored.saturating_sub(zstd);
+
+                let mut hasher = DefaultHasher::new();
+                (width, height, image.as_raw()).hash(&mut hasher);
+                groups.entry(hasher.finish()).or_default().push(size.index);
+                stats.frames.push(size);
+            }
+            progress(Progress::FrameDecoded {
+                done: done + 1,
+                total,
+            });
+        }
+
+        let stored: HashMap<usize, u64> =
+            stats.frames.iter().map(|f| (f.index, f.stored)).collect();
+        stats.duplicates = groups.into_values().filter(|g| g.len() > 1).collect();
+        stats.duplicates.sort();
+        stats.savings.dedup = stats
+            .duplicates
+            .iter()
+            .flat_map(|group| &group[1..])
+            .map(|index| stored[index])
+            .sum();
+        tracing::debug!(
+            "体积分析: {} 帧，{} 组重复，估计可省 {:?}",
+            stats.frames.len(),
+            stats.duplicates.len(),
+            stats.savings
+        );
+        Ok(stats)
+    }
+
+    /// 文件中的数据总大小
+    pub fn stored_bytes(&self) -> u64 {
+        self.frames.iter().map(|f| f.stored).sum()
+    }
+
+    /// 解码后的总大小
+    pub fn raw_bytes(&self) -> u64 {
+        self.frames.iter().map(|f| f.raw).sum()
+    }
+
+    /// 数据最大的 `count` 帧（从大到小，大小相同时按索引）
+    pub fn largest(&self, count: usize) -> Vec<FrameSize> {
+        let mut frames = self.frames.clone();
+        frames.sort_by_key(|f| (std::cmp::Reverse(f.stored), f.index));
+        frames.truncate(count);
+        frames
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::mlibrary_v2::{MImage, MLibraryV2};
+    use image::{Rgba, RgbaImage};
+
+    #[test]
+    fn test_library_stats() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let mut library = MLibraryV2::new(dir.join("Hum")).unwrap();
+        // 0: 带透明边的 40x20，1 和 2: 相同的 8x8，3: 空帧
+        let mut bordered = RgbaImage::new(40, 20);
+        for x in 10..30 {
+            for y in 5..15 {
+                bordered.put_pixel(x, y, Rgba([(x * 7) as u8, y as u8, 90, 255]));
+            }
+        }
+        let square = RgbaImage::from_pixel(8, 8, Rgba([200, 10, 10, 255]));
+        library.add_image(&MImage::from_image(&bordered, 0, 0));
+        library.add_image(&MImage::from_image(&square, 0, 0));
+        library.add_image(&MImage::from_image(&square, 3, 3));
+        library.add_image(&MImage::from_image(&RgbaImage::new(0, 0), 0, 0));
+        library.save().unwrap();
+
+        let path = dir.join("Hum.Lib");
+        let header = LibraryLoader::open_header(&path).unwrap();
+        let (_, mut loader) = LibraryLoader::load(&path).unwrap();
+        let mut reported = 0;
+        let stats =
+            LibraryStats::collect(&header, &mut loader, &CancelToken::new(), |_| reported += 1)
+                .unwrap();
+
+        assert_eq!(reported, 4);
+        assert_eq!(stats.frames.len(), 3);
+        assert_eq!(stats.frames[0].raw, 40 * 20 * 4);
+        assert_eq!(stats.dimensions, vec![2, 0, 1, 0, 0, 0, 0, 0]);
+        assert_eq!(stats.duplicates, vec![vec![1, 2]]);
+        assert_eq!(stats.savings.dedup, stats.frames[2].stored);
+        assert!(stats.savings.trim >= stats.frames[0].stored / 2);
+        assert_eq!(stats.largest(1)[0].index, 0);
+    }
+}
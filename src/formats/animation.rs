@@ -0,0 +1,186 @@
+//! 命名动画序列
+//!
+//! 一个库中的动作（站立、行走、攻击……）按连续的帧排列，把“攻击 = 第 360-369 帧”
+//! 这样的划分记为命名序列，导出 GIF、预览时按名称引用。序列保存在库文件旁的
+//! `<库名>.anim.json` 中，与锁定记录一样随库分发；项目文件中也按同样的格式记录。
+
+use crate::error::{LibraryError, Result};
+use crate::formats::companion_path;
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+/// 动画序列文件的扩展名（拼接在库的基础路径后）
+pub const ANIMATIONS_EXTENSION: &str = ".anim.json";
+
+/// 命名的动画序列
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Sequence {
+    /// 名称（如“攻击”）
+    pub name: String,
+    /// 起始帧
+    pub start: usize,
+    /// 帧数
+    pub count: usize,
+    /// 播放帧率
+    pub fps: u32,
+}
+
+impl Default for Sequence {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            start: 0,
+            count: 0,
+            fps: crate::image::video::DEFAULT_FPS,
+        }
+    }
+}
+
+impl Sequence {
+    /// 序列包含的帧索引
+    pub fn frames(&self) -> Range<usize> {
+        self.start..self.start + self.count
+    }
+}
+
+/// 一个库的动画序列（按添加顺序）
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct AnimationSet {
+    sequences: Vec<Sequence>,
+}
+
+impl AnimationSet {
+    /// 库（基础路径，不含扩展名）对应的动画序列文件
+    pub fn path(base: &Path) -> PathBuf {
+        companion_path(base, ANIMATIONS_EXTENSION)
+    }
+
+    /// 读取库的动画序列，文件不存在时返回空集合
+    pub fn load(base: &Path) -> Result<Self> {
+        let path = Self::path(base);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let set: Self = serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+        tracing::debug!("读取动画序列 {:?}: {} 个", path, set.len());
+        Ok(set)
+    }
+
+    /// 写出动画序列，没有序列时删除文件
+    pub fn save(&self, base: &Path) -> Result<()> {
+        let path = Self::path(base);
+        if self.is_empty() {
+            if path.exists() {
+                std::fs::remove_file(&path)?;
+            }
+            return Ok(());
+        }
+        std::fs::write(&path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// 按名称查找序列
+    pub fn get(&self, name: &str) -> Option<&Sequence> {
+        self.sequences.iter().find(|s| s.name == name)
+    }
+
+    /// 按名称查找序列，找不到时返回列出可用名称的错误
+    pub fn require(&self, name: &str) -> Result<&Sequence> {
+        self.get(name).ok_or_else(|| {
+            let names: Vec<&str> = self.iter().map(|s| s.name.as_str()).collect();
+            LibraryError::ParseError(format!(
+                "没有名为 {} 的动画序列（已有: {}）",
+                name,
+                if names.is_empty() {
+                    "无".to_string()
+                } else {
+                    names.join("、")
+                }
+            ))
+        })
+    }
+
+    /// 添加序列，已有同名序列时替换；名称为空或没有帧时返回错误
+    pub fn set(&mut self, sequence: Sequence) -> Result<()> {
+        if sequence.name.trim().is_empty() {
+            return Err(LibraryError::ParseError("动画序列名称不能为空".to_string()));
+        }
+        if sequence.count == 0 {
+            return Err(LibraryError::ParseError(format!(
+                "动画序列 {} 没有帧",
+                sequence.name
+            )));
+        }
+        match self.sequences.iter_mut().find(|s| s.name == sequence.name) {
+            Some(existing) => *existing = sequence,
+            None => self.sequences.push(sequence),
+        }
+        Ok(())
+    }
+
+    /// 删除序列，返回是否存在
+    pub fn remove(&mut self, name: &str) -> bool {
+        let before = self.sequences.len();
+        self.sequences.retain(|s| s.name != name);
+        self.sequences.len() != before
+    }
+
+    /// 全部序列（按添加顺序）
+    pub fn iter(&self) -> impl Iterator<Item = &Sequence> + '_ {
+        self.sequences.iter()
+    }
+
+    /// 序列个数
+    pub fn len(&self) -> usize {
+        self.sequences.len()
+    }
+
+    /// 是否没有序列
+    pub fn is_empty(&self) -> bool {
+        self.sequences.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_animation_set_edit_and_roundtrip() {
+        let sequence = |name: &str, start, count| Sequence {
+            name: name.to_string(),
+            start,
+            count,
+            fps: 8,
+        };
+        let mut set = AnimationSet::default();
+        set.set(sequence("站立", 0, 4)).unwrap();
+        set.set(sequence("攻击", 360, 10)).unwrap();
+        set.set(sequence("攻击", 360, 9)).unwrap();
+        assert!(set.set(sequence(" ", 0, 1)).is_err());
+        assert!(set.set(sequence("空", 5, 0)).is_err());
+        assert_eq!(set.len(), 2);
+        assert_eq!(set.require("攻击").unwrap().frames(), 360..369);
+        assert!(
+            set.require("行走")
+                .unwrap_err()
+                .to_string()
+                .contains("站立、攻击")
+        );
+
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let base = dir.join("Mon23");
+        set.save(&base).unwrap();
+        assert_eq!(AnimationSet::load(&base).unwrap(), set);
+
+        assert!(set.remove("站立"));
+        assert!(!set.remove("站立"));
+        assert!(set.remove("攻击"));
+        set.save(&base).unwrap();
+        assert!(!AnimationSet::path(&base).exists());
+    }
+}
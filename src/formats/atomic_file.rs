@@ -0,0 +1,199 @@
+//! 原子保存
+//!
+//! 直接 `File::create` 覆盖库文件时，写到一半崩溃或断电会留下截断的文件，整个库
+//! 无法再打开。各格式的写入器改为先写同目录下的临时文件，写完并落盘后再改名替换
+//! 目标文件：任何时刻目标文件要么是旧版本，要么是完整的新版本。
+//!
+//! 可选保留被替换的旧版本作为备份（`<文件名>.bak1` 最新，`.bak2` 次之……），
+//! 默认份数对整个进程生效，用 [`set_backup_count`] 设置，默认不保留；单个文件可以用
+//! [`AtomicFile::with_backups`] 另行指定。
+
+use crate::error::Result;
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// 保留的备份份数
+static BACKUP_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// 当前保留的备份份数
+pub fn backup_count() -> usize {
+    BACKUP_COUNT.load(Ordering::Relaxed)
+}
+
+/// 设置保存时保留的备份份数，0 表示不保留
+pub fn set_backup_count(count: usize) {
+    BACKUP_COUNT.store(count, Ordering::Relaxed);
+}
+
+/// 第 `n` 份备份的路径（从 1 开始，1 为最新）
+pub fn backup_path(target: &Path, n: usize) -> PathBuf {
+    with_suffix(target, &format!(".bak{}", n))
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// 写入中的文件，[`commit`](Self::commit) 后才替换目标文件
+///
+/// 未提交就丢弃（写入出错提前返回）时删除临时文件，目标文件保持不变。
+pub struct AtomicFile {
+    target: PathBuf,
+    temp: PathBuf,
+    writer: Option<BufWriter<File>>,
+    /// 提交时保留的备份份数
+    backups: usize,
+    /// 临时文件已落盘
+    synced: bool,
+}
+
+impl AtomicFile {
+    /// 在目标文件旁创建临时文件
+    pub fn create(target: &Path) -> Result<Self> {
+        let temp = with_suffix(target, ".tmp");
+        let writer = BufWriter::new(File::create(&temp)?);
+        Ok(Self {
+            target: target.to_path_buf(),
+            temp,
+            writer: Some(writer),
+            backups: backup_count(),
+            synced: false,
+        })
+    }
+
+    /// 提交时保留 `count` 份备份，不使用进程的默认份数
+    pub fn with_backups(mut self, count: usize) -> Self {
+        self.backups = count;
+        self
+    }
+
+    /// 把写入的内容落盘，目标文件暂不替换
+    ///
+    /// 需要同时替换几个文件时，先把它们全部落盘再依次提交，缩短文件之间不一致的时间。
+    pub fn sync(&mut self) -> Result<()> {
+        if let Some(writer) = self.writer.as_mut() {
+            writer.flush()?;
+            writer.get_ref().sync_all()?;
+        }
+        self.synced = true;
+        Ok(())
+    }
+
+    /// 写入完成：落盘、备份旧文件，再用临时文件替换目标文件
+    ///
+    /// 失败时删除临时文件，目标文件（和已有备份）保持不变。
+    pub fn commit(mut self) -> Result<()> {
+        let result = self.replace_target();
+        if result.is_err() {
+            let _ = std::fs::remove_file(&self.temp);
+        }
+        result
+    }
+
+    fn replace_target(&mut self) -> Result<()> {
+        if !self.synced {
+            self.sync()?;
+        }
+        // 关闭临时文件，改名后不再写入
+        self.writer = None;
+        if self.backups > 0 && self.target.exists() {
+            rotate_backups(&self.target, self.backups)?;
+        }
+        std::fs::rename(&self.temp, &self.target)?;
+        tracing::debug!("已保存 {:?}", self.target);
+        Ok(())
+    }
+}
+
+impl Write for AtomicFile {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.writer
+            .as_mut()
+            .expect("提交前写入器一直存在")
+            .write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.writer.as_mut().expect("提交前写入器一直存在").flush()
+    }
+}
+
+impl Drop for AtomicFile {
+    fn drop(&mut self) {
+        if self.writer.take().is_some() {
+            let _ = std::fs::remove_file(&self.temp);
+        }
+    }
+}
+
+/// 原子地写入整个文件
+pub fn write(target: &Path, data: &[u8]) -> Result<()> {
+    let mut file = AtomicFile::create(target)?;
+    file.write_all(data)?;
+    file.commit()
+}
+
+/// 依次后移已有备份（最旧的一份被覆盖），再把目标文件保留为第 1 份备份
+///
+/// 用硬链接保留（文件系统不支持时复制），目标文件在被替换前一直完整。
+fn rotate_backups(target: &Path, count: usize) -> Result<()> {
+    for n in (1..count).rev() {
+        let older = backup_path(target, n);
+        if older.exists() {
+            std::fs::rename(&older, backup_path(target, n + 1))?;
+        }
+    }
+    let newest = backup_path(target, 1);
+    if newest.exists() {
+        std::fs::remove_file(&newest)?;
+    }
+    if std::fs::hard_link(target, &newest).is_err() {
+        std::fs::copy(target, &newest)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_commit_replaces_and_keeps_backups() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let target = dir.join("Hum.Lib");
+
+        // 未提交时目标文件不变，临时文件被删除
+        std::fs::write(&target, b"v1").unwrap();
+        {
+            let mut file = AtomicFile::create(&target).unwrap();
+            file.write_all(b"partial").unwrap();
+        }
+        assert_eq!(std::fs::read(&target).unwrap(), b"v1");
+        assert!(!with_suffix(&target, ".tmp").exists());
+
+        // 已落盘但未提交同样不替换
+        {
+            let mut file = AtomicFile::create(&target).unwrap();
+            file.write_all(b"staged").unwrap();
+            file.sync().unwrap();
+        }
+        assert_eq!(std::fs::read(&target).unwrap(), b"v1");
+        assert!(!with_suffix(&target, ".tmp").exists());
+
+        for version in [&b"v2"[..], b"v3", b"v4"] {
+            let mut file = AtomicFile::create(&target).unwrap().with_backups(2);
+            file.write_all(version).unwrap();
+            file.commit().unwrap();
+        }
+        assert_eq!(std::fs::read(&target).unwrap(), b"v4");
+        assert_eq!(std::fs::read(backup_path(&target, 1)).unwrap(), b"v3");
+        assert_eq!(std::fs::read(backup_path(&target, 2)).unwrap(), b"v2");
+        assert!(!backup_path(&target, 3).exists());
+    }
+}
@@ -0,0 +1,217 @@
+//! 帧书签
+//!
+//! 整理资源时给关键帧加上说明，如“boss 死亡开始”，之后可以在书签列表中点击或按
+//! 快捷键在书签之间跳转。书签保存在库文件旁的 `<库名>.bookmarks.json` 中，库加入
+//! 项目后也同步写入项目文件（`.lep`），两处使用同一结构。
+
+use crate::error::Result;
+use crate::formats::companion_path;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// 书签文件的扩展名（拼接在库的基础路径后）
+pub const BOOKMARKS_EXTENSION: &str = ".bookmarks.json";
+
+/// 帧书签
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Bookmark {
+    /// 帧索引
+    pub index: usize,
+    /// 说明
+    pub label: String,
+}
+
+/// 一个库的书签（按帧索引排序，每帧最多一个）
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Bookmarks {
+    items: Vec<Bookmark>,
+}
+
+impl Bookmarks {
+    /// 库（基础路径，不含扩展名）对应的书签文件
+    pub fn path(base: &Path) -> PathBuf {
+        companion_path(base, BOOKMARKS_EXTENSION)
+    }
+
+    /// 读取书签，文件不存在时返回空书签
+    pub fn load(base: &Path) -> Result<Self> {
+        let path = Self::path(base);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let mut bookmarks: Self = serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+        bookmarks.items.sort_by_key(|b| b.index);
+        bookmarks.items.dedup_by_key(|b| b.index);
+        tracing::debug!("读取书签 {:?}: {} 个", path, bookmarks.len());
+        Ok(bookmarks)
+    }
+
+    /// 写出书签，没有书签时删除书签文件
+    pub fn save(&self, base: &Path) -> Result<()> {
+        let path = Self::path(base);
+        if self.is_empty() {
+            if path.exists() {
+                std::fs::remove_file(&path)?;
+            }
+            return Ok(());
+        }
+        std::fs::write(&path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// 添加或更新书签，返回是否有改动
+    pub fn set(&mut self, index: usize, label: &str) -> bool {
+        match self.items.binary_search_by_key(&index, |b| b.index) {
+            Ok(pos) if self.items[pos].label == label => false,
+            Ok(pos) => {
+                self.items[pos].label = label.to_string();
+                true
+            }
+            Err(pos) => {
+                let label = label.to_string();
+                self.items.insert(pos, Bookmark { index, label });
+                true
+            }
+        }
+    }
+
+    /// 删除第 `index` 帧的书签，返回是否有书签
+    pub fn remove(&mut self, index: usize) -> bool {
+        match self.items.binary_search_by_key(&index, |b| b.index) {
+            Ok(pos) => {
+                self.items.remove(pos);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// 第 `index` 帧的书签
+    pub fn get(&self, index: usize) -> Option<&Bookmark> {
+        self.items
+            .binary_search_by_key(&index, |b| b.index)
+            .ok()
+            .map(|pos| &self.items[pos])
+    }
+
+    /// 全部书签（按帧索引）
+    pub fn iter(&self) -> impl Iterator<Item = &Bookmark> {
+        self.items.iter()
+    }
+
+    /// 书签数
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// 是否没有书签
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// `index` 之后的下一个书签，到末尾时回到第一个
+    pub fn next(&self, index: usize) -> Option<&Bookmark> {
+        self.items
+            .iter()
+            .find(|b| b.index > index)
+            .or(self.items.first())
+    }
+
+    /// `index` 之前的上一个书签，到开头时回到最后一个
+    pub fn previous(&self, index: usize) -> Option<&Bookmark> {
+        self.items
+            .iter()
+            .rev()
+            .find(|b| b.index < index)
+            .or(self.items.last())
+    }
+
+    /// 加入 `other` 中本身没有的帧的书签，返回加入的个数
+    pub fn merge(&mut self, other: &Bookmarks) -> usize {
+        let missing: Vec<Bookmark> = other
+            .iter()
+            .filter(|b| self.get(b.index).is_none())
+            .cloned()
+            .collect();
+        for bookmark in &missing {
+            self.set(bookmark.index, &bookmark.label);
+        }
+        missing.len()
+    }
+
+    /// 删除第 `index` 帧后，该帧的书签随之删除，其后的书签前移
+    pub fn removed(&mut self, index: usize) {
+        self.items.retain(|b| b.index != index);
+        for bookmark in &mut self.items {
+            if bookmark.index > index {
+                bookmark.index -= 1;
+            }
+        }
+    }
+
+    /// 在 `index` 处插入一帧后，其后的书签后移
+    pub fn inserted(&mut self, index: usize) {
+        for bookmark in &mut self.items {
+            if bookmark.index >= index {
+                bookmark.index += 1;
+            }
+        }
+    }
+}
+
+impl FromIterator<Bookmark> for Bookmarks {
+    fn from_iter<I: IntoIterator<Item = Bookmark>>(iter: I) -> Self {
+        let mut bookmarks = Self::default();
+        for bookmark in iter {
+            bookmarks.set(bookmark.index, &bookmark.label);
+        }
+        bookmarks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bookmarks_navigate_follow_removal_and_roundtrip() {
+        let mut bookmarks = Bookmarks::default();
+        assert!(bookmarks.set(40, "boss 死亡开始"));
+        assert!(bookmarks.set(8, "攻击"));
+        assert!(!bookmarks.set(8, "攻击"));
+        assert!(bookmarks.set(8, "攻击开始"));
+        assert_eq!(bookmarks.get(8).unwrap().label, "攻击开始");
+
+        // 前后跳转，到两端时循环
+        assert_eq!(bookmarks.next(8).unwrap().index, 40);
+        assert_eq!(bookmarks.next(40).unwrap().index, 8);
+        assert_eq!(bookmarks.previous(20).unwrap().index, 8);
+        assert_eq!(bookmarks.previous(8).unwrap().index, 40);
+        assert!(Bookmarks::default().next(0).is_none());
+
+        // 删除帧时书签跟着前移，被删的帧的书签一起去掉
+        bookmarks.removed(8);
+        assert_eq!(bookmarks.iter().map(|b| b.index).collect::<Vec<_>>(), [39]);
+        bookmarks.inserted(0);
+        assert_eq!(bookmarks.get(40).unwrap().label, "boss 死亡开始");
+
+        let mut other = Bookmarks::default();
+        other.set(40, "另一个说明");
+        other.set(3, "站立");
+        assert_eq!(bookmarks.merge(&other), 1);
+        assert_eq!(bookmarks.get(40).unwrap().label, "boss 死亡开始");
+
+        let temp = tempfile::tempdir().unwrap();
+        let base = temp.path().join("bookmarks");
+        bookmarks.save(&base).unwrap();
+        assert_eq!(Bookmarks::load(&base).unwrap(), bookmarks);
+
+        // 全部删除后删除书签文件
+        assert!(bookmarks.remove(3) && bookmarks.remove(40));
+        assert!(!bookmarks.remove(40));
+        bookmarks.save(&base).unwrap();
+        assert!(!Bookmarks::path(&base).exists());
+    }
+}
@@ -0,0 +1,124 @@
+//! 帧剪贴板
+//!
+//! 复制一帧的完整内容（图像、绘制偏移、阴影和遮罩层），可以粘贴到同一个库的
+//! 其他索引，也可以粘贴到另一个打开的库。复制可以来自任意格式，粘贴只支持可写的库。
+
+use crate::formats::mlibrary_v2::{EncodeOptions, MImage};
+use image::RgbaImage;
+
+/// 复制的遮罩层
+#[derive(Debug, Clone, PartialEq)]
+pub struct MaskClip {
+    /// 遮罩图像
+    pub image: RgbaImage,
+    /// 遮罩的 X 偏移
+    pub x: i16,
+    /// 遮罩的 Y 偏移
+    pub y: i16,
+}
+
+/// 复制的一帧
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameClip {
+    /// 帧图像（与预览方向一致）
+    pub image: RgbaImage,
+    /// X 偏移
+    pub x: i16,
+    /// Y 偏移
+    pub y: i16,
+    /// 阴影类型
+    pub shadow: u8,
+    /// 阴影 X 偏移
+    pub shadow_x: i16,
+    /// 阴影 Y 偏移
+    pub shadow_y: i16,
+    /// 遮罩层（没有遮罩时为 `None`）
+    pub mask: Option<MaskClip>,
+}
+
+impl FrameClip {
+    /// 只有图像和偏移的帧
+    pub fn new(image: RgbaImage, x: i16, y: i16) -> Self {
+        Self {
+            image,
+            x,
+            y,
+            shadow: 0,
+            shadow_x: 0,
+            shadow_y: 0,
+            mask: None,
+        }
+    }
+
+    /// 转换为可写入 V2 库的帧
+    pub fn to_mimage(&self) -> MImage {
+        self.to_mimage_with(EncodeOptions::default())
+    }
+
+    /// 转换为可写入 V2 库的帧，像素按 `options` 编码
+    pub fn to_mimage_with(&self, options: EncodeOptions) -> MImage {
+        let mut frame = MImage::from_image_with(&self.image, self.x, self.y, options);
+        frame.shadow = self.shadow;
+        frame.shadow_x = self.shadow_x;
+        frame.shadow_y = self.shadow_y;
+        if let Some(mask) = &self.mask {
+            frame.set_mask(&mask.image, mask.x, mask.y);
+        }
+        frame
+    }
+
+    /// 状态栏中的简要描述
+    pub fn describe(&self) -> String {
+        format!(
+            "{} x {}，偏移 ({}, {}){}",
+            self.image.width(),
+            self.image.height(),
+            self.x,
+            self.y,
+            if self.mask.is_some() {
+                "，含遮罩"
+            } else {
+                ""
+            }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::{LibraryLoader, MLibraryV2};
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_copy_paste_between_libraries() {
+        let red = RgbaImage::from_pixel(3, 2, image::Rgba([255, 0, 0, 255]));
+        let mask = RgbaImage::from_pixel(2, 2, image::Rgba([0, 0, 255, 255]));
+        let mut frame = MImage::from_image(&red, 7, -4);
+        frame.shadow = 2;
+        frame.shadow_x = 3;
+        frame.shadow_y = 1;
+        frame.set_mask(&mask, -1, 5);
+
+        let mut source = MLibraryV2::new(PathBuf::from("clip_source_missing")).unwrap();
+        source.add_image(&frame);
+        let (_, mut source) = LibraryLoader::from_v2(source, "source.Lib");
+        let clip = source.copy_frame(0).unwrap().unwrap();
+        assert_eq!((clip.x, clip.y, clip.shadow), (7, -4, 2));
+        assert_eq!(clip.mask.as_ref().map(|m| (m.x, m.y)), Some((-1, 5)));
+
+        let blank = RgbaImage::from_pixel(1, 1, image::Rgba([1, 1, 1, 255]));
+        let mut target = MLibraryV2::new(PathBuf::from("clip_target_missing")).unwrap();
+        target.add_image(&MImage::from_image(&blank, 0, 0));
+        let (_, mut target) = LibraryLoader::from_v2(target, "target.Lib");
+
+        // 粘贴到已有帧时整帧替换，超出末尾时追加
+        assert_eq!(target.paste_frame(&clip, Some(0)).unwrap(), 0);
+        assert_eq!(target.paste_frame(&clip, Some(9)).unwrap(), 1);
+        assert_eq!(target.image_count(), 2);
+        for index in 0..2 {
+            assert_eq!(target.copy_frame(index).unwrap().as_ref(), Some(&clip));
+        }
+        assert!(target.copy_frame(2).unwrap().is_none());
+    }
+}
@@ -0,0 +1,245 @@
+//! 格式转换
+//!
+//! 把任意可读格式的库逐帧解码后重新编码为 MLibrary V2（.Lib），保留偏移、阴影和
+//! 遮罩层，空帧仍为空帧，帧索引不变。整个客户端有上千个库、几个 GB，
+//! [`convert_dir`] 按目录结构把全部库转换到输出目录，文件之间互不依赖，
+//! 用 `jobs` 个线程并行处理（需要 `cli` feature）；单个文件失败只记入结果，不影响其他文件。
+
+#[cfg(feature = "cli")]
+use crate::error::LibraryError;
+use crate::error::Result;
+use crate::formats::LibraryLoader;
+#[cfg(feature = "cli")]
+use crate::formats::integrity::find_libraries;
+use crate::formats::mlibrary_v2::{EncodeOptions, MImage, MLibraryV2};
+#[cfg(feature = "cli")]
+use rayon::prelude::*;
+#[cfg(feature = "cli")]
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// 转换成功的库
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Converted {
+    /// 来源格式名
+    pub format: String,
+    /// 帧数
+    pub frames: usize,
+}
+
+/// 一个文件的转换结果
+#[derive(Debug)]
+pub struct Conversion {
+    /// 来源库文件
+    pub source: PathBuf,
+    /// 输出的 .Lib 文件
+    pub output: PathBuf,
+    /// 转换结果
+    pub result: Result<Converted>,
+}
+
+/// 把已加载的库转换为 V2 并保存到 `output`（.Lib 文件路径），返回写入的库
+pub fn to_v2(
+    loader: &mut LibraryLoader,
+    output: &Path,
+    options: EncodeOptions,
+) -> Result<MLibraryV2> {
+    let mut library = MLibraryV2::new(output.with_extension(""))?;
+    library.images.clear();
+    library.count = 0;
+    library.wide_offsets = options.wide_offsets;
+    library.codec = options.codec;
+    library.level = options.level;
+
+    for index in 0..loader.image_count() {
+        let image = match loader.copy_frame(index)? {
+            Some(frame) => frame.to_mimage_with(options),
+            None => {
+                let info = loader.get_image_info(index)?;
+                let mut empty = MImage::new();
+                empty.x = info.x as i16;
+                empty.y = info.y as i16;
+                empty
+            }
+        };
+        library.add_image(&image);
+    }
+
+    library.save()?;
+    Ok(library)
+}
+
+/// 把 `src` 下的全部库按相同的目录结构转换为 `dst` 下的 .Lib，按 `jobs` 个线程并行
+///
+/// 每个文件完成后调用 `done`（调用顺序不固定），结果按来源路径排列。
+/// 不同格式的同名库（如 `Hum.wil` 与 `Hum.wzl`）只转换第一个，其余记为失败。
+#[cfg(feature = "cli")]
+pub fn convert_dir(
+    src: &Path,
+    dst: &Path,
+    options: EncodeOptions,
+    jobs: usize,
+    done: impl Fn(&Conversion) + Sync,
+) -> Result<Vec<Conversion>> {
+    if !src.is_dir() {
+        return Err(LibraryError::FileNotFound(src.display().to_string()));
+    }
+    if dst.canonicalize().ok() == Some(src.canonicalize()?) {
+        return Err(LibraryError::ParseError(
+            "输出目录不能与来源目录相同".to_string(),
+        ));
+    }
+
+    let mut outputs = HashSet::new();
+    let tasks: Vec<(PathBuf, PathBuf, bool)> = find_libraries(src)
+        .into_iter()
+        .filter_map(|source| {
+            let relative = source.strip_prefix(src).ok()?.to_path_buf();
+            let output = dst.join(relative).with_extension("Lib");
+            let unique = outputs.insert(output.to_string_lossy().to_lowercase());
+            Some((source, output, unique))
+        })
+        .collect();
+    tracing::info!("批量转换: {} 个库, {} 个线程", tasks.len(), jobs);
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(jobs)
+        .build()
+        .map_err(std::io::Error::other)?;
+    let results = pool.install(|| {
+        tasks
+            .into_par_iter()
+            .map(|(source, output, unique)| {
+                let result = if unique {
+                    convert_file(&source, &output, options)
+                } else {
+                    Err(LibraryError::ParseError(format!(
+                        "输出文件 {:?} 与其他库重名，已跳过",
+                        output
+                    )))
+                };
+                if let Err(e) = &result {
+                    tracing::warn!("转换 {:?} 失败: {}", source, e);
+                }
+                let conversion = Conversion {
+                    source,
+                    output,
+                    result,
+                };
+                done(&conversion);
+                conversion
+            })
+            .collect()
+    });
+    Ok(results)
+}
+
+/// 转换单个文件，按需创建输出目录
+#[cfg(feature = "cli")]
+fn convert_file(source: &Path, output: &Path, options: EncodeOptions) -> Result<Converted> {
+    let (info, mut loader) = LibraryLoader::load(source)?;
+    if let Some(parent) = output.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let library = to_v2(&mut loader, output, options)?;
+    Ok(Converted {
+        format: info.format_name(),
+        frames: library.count(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::companion_path;
+    use crate::formats::mlibrary_v0::{self, MLibraryV0};
+    use crate::formats::mlibrary_v1::{self, MLibraryV1};
+    use image::{Rgba, RgbaImage};
+
+    #[test]
+    #[cfg(feature = "cli")]
+    fn test_convert_dir() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        let (src, dst) = (root.join("client"), root.join("out"));
+        std::fs::create_dir_all(src.join("Data")).unwrap();
+        let mut library = MLibraryV2::new(src.join("Data").join("Hum")).unwrap();
+        let mut frame = MImage::from_image(&RgbaImage::new(3, 2), 5, -4);
+        frame.shadow = 2;
+        library.add_image(&frame);
+        library.add_image(&MImage::new());
+        library.save().unwrap();
+        std::fs::write(src.join("Data").join("Broken.wzl"), b"not a library").unwrap();
+
+        let finished = AtomicUsize::new(0);
+        let results = convert_dir(&src, &dst, EncodeOptions::default(), 2, |_| {
+            finished.fetch_add(1, Ordering::SeqCst);
+        })
+        .unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(finished.load(Ordering::SeqCst), 2);
+        assert!(results[0].result.is_err());
+
+        let converted = results[1].result.as_ref().unwrap();
+        assert_eq!(converted.frames, 2);
+        let (_, mut loader) = LibraryLoader::load(&dst.join("Data").join("Hum.Lib")).unwrap();
+        assert_eq!(loader.image_count(), 2);
+        let info = loader.get_image_info(0).unwrap();
+        assert_eq!((info.width, info.x, info.y), (3, 5, -4));
+        assert!(loader.get_image_info(1).unwrap().is_empty());
+
+        assert!(convert_dir(&src, &src, EncodeOptions::default(), 1, |_| {}).is_err());
+    }
+
+    #[test]
+    fn test_conversions_keep_orientation() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        // 5x3（奇数宽度）：顶行红、底行蓝，中间一行只有最左一个红点
+        let (red, blue) = (Rgba([255, 0, 0, 255]), Rgba([0, 0, 255, 255]));
+        let mut sprite = RgbaImage::new(5, 3);
+        for x in 0..5 {
+            sprite.put_pixel(x, 0, red);
+            sprite.put_pixel(x, 2, blue);
+        }
+        sprite.put_pixel(0, 1, red);
+
+        // 同一张图写成 WIL、WZL 和 .Lib
+        let mut palette = [[0u8; 4]; 256];
+        palette[1] = [0, 0, 255, 255];
+        palette[2] = [255, 0, 0, 255];
+        let mut wil = MLibraryV0::new(root.join("Wil")).unwrap();
+        wil.set_palette(palette);
+        wil.add_image(&mlibrary_v0::MImage::from_image(&sprite, 0, 0, &palette));
+        wil.save().unwrap();
+        let mut wzl = MLibraryV1::empty(root.join("Wzl"));
+        wzl.add_image(&mlibrary_v1::MImage::from_image(&sprite, 0, 0));
+        wzl.save().unwrap();
+        let mut lib = MLibraryV2::new(root.join("Lib")).unwrap();
+        lib.add_image(&MImage::from_image(&sprite, 0, 0));
+        lib.save().unwrap();
+
+        for (name, ext) in [("Wil", ".wil"), ("Wzl", ".wzl"), ("Lib", ".Lib")] {
+            let (_, mut loader) =
+                LibraryLoader::load(&companion_path(&root.join(name), ext)).unwrap();
+            assert_eq!(
+                *loader.get_frame(0).unwrap().unwrap(),
+                sprite,
+                "{} 解码",
+                name
+            );
+
+            let output = root.join(format!("{}.converted.Lib", name));
+            to_v2(&mut loader, &output, EncodeOptions::default()).unwrap();
+            let (_, mut converted) = LibraryLoader::load(&output).unwrap();
+            assert_eq!(
+                *converted.get_frame(0).unwrap().unwrap(),
+                sprite,
+                "{} 转换",
+                name
+            );
+        }
+    }
+}
@@ -0,0 +1,140 @@
+//! 打开模式与编辑锁
+//!
+//! 两个编辑器（或编辑器与其他工具）同时写同一个库会互相覆盖，甚至写坏文件。以读写
+//! 方式编辑时在库文件旁的 `<库名>.lock` 上加建议锁（advisory lock），持有期间其他
+//! 遵守约定的程序无法再以读写方式打开；只读方式不加锁，但拒绝一切修改和保存。
+//! 锁随持有它的加载器释放（进程崩溃时由系统释放）。`.lock` 文件本身留在原处，
+//! 占用与否只看文件上的锁：删除文件会让另一个进程在新文件上加锁，两边都以为自己独占。
+
+use crate::error::{LibraryError, Result};
+use crate::formats::companion_path;
+use std::fs::{File, TryLockError};
+use std::io::Write;
+use std::path::Path;
+
+/// 锁文件的扩展名（拼接在库的基础路径后）
+pub const LOCK_EXTENSION: &str = ".lock";
+
+/// 打开模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OpenMode {
+    /// 只读：不加锁，拒绝修改和保存
+    ReadOnly,
+    /// 读写：编辑和保存前需要取得编辑锁
+    #[default]
+    ReadWrite,
+}
+
+/// 持有中的编辑锁，丢弃时释放
+///
+/// 释放的只是文件上的锁，`.lock` 文件有意留在库旁（原因见模块说明），下次加锁时复用；
+/// 库的 `.lock` 文件随时可以在没有编辑器打开它时手动删除。
+#[derive(Debug)]
+pub struct EditLock {
+    _file: File,
+}
+
+impl EditLock {
+    /// 为库（基础路径，不含扩展名）加锁，已被其他程序持有时返回
+    /// [`LibraryError::LibraryInUse`]
+    ///
+    /// 文件系统不支持加锁时（如浏览器中）返回 `Ok(None)`，不加锁继续。
+    pub fn acquire(base: &Path) -> Result<Option<Self>> {
+        let path = companion_path(base, LOCK_EXTENSION);
+        let mut file = File::options()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)?;
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                let holder = std::fs::read_to_string(&path).unwrap_or_default();
+                return Err(LibraryError::LibraryInUse(format!(
+                    "{} ({})",
+                    path.display(),
+                    holder.trim()
+                )));
+            }
+            Err(TryLockError::Error(e)) if e.kind() == std::io::ErrorKind::Unsupported => {
+                tracing::debug!("文件系统不支持加锁，跳过编辑锁: {:?}", path);
+                return Ok(None);
+            }
+            Err(TryLockError::Error(e)) => return Err(e.into()),
+        }
+
+        // 记下持有者，便于在提示中说明是谁占用了库
+        file.set_len(0)?;
+        write!(file, "pid {}", std::process::id())?;
+        tracing::debug!("取得编辑锁: {:?}", path);
+        Ok(Some(Self { _file: file }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::LibraryLoader;
+    use crate::formats::mlibrary_v2::{MImage, MLibraryV2};
+
+    #[test]
+    fn test_second_writer_is_refused() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let mut library = MLibraryV2::new(dir.join("Hum")).unwrap();
+        let frame = image::RgbaImage::from_pixel(2, 2, image::Rgba([9, 9, 9, 255]));
+        library.add_image(&MImage::from_image(&frame, 0, 0));
+        library.save().unwrap();
+        let path = dir.join("Hum.Lib");
+
+        let (_, mut first) = LibraryLoader::load(&path).unwrap();
+        first.set_open_mode(OpenMode::ReadWrite).unwrap();
+        assert!(first.holds_edit_lock());
+
+        let (_, mut second) = LibraryLoader::load(&path).unwrap();
+        assert!(matches!(
+            second.set_open_mode(OpenMode::ReadWrite),
+            Err(LibraryError::LibraryInUse(_))
+        ));
+        assert!(matches!(second.save(), Err(LibraryError::LibraryInUse(_))));
+        second.set_open_mode(OpenMode::ReadOnly).unwrap();
+        assert!(matches!(second.save(), Err(LibraryError::ReadOnly(_))));
+        assert!(matches!(
+            second.remove_image(0),
+            Err(LibraryError::ReadOnly(_))
+        ));
+
+        // 第一个编辑器关闭后即可加锁
+        drop(first);
+        second.set_open_mode(OpenMode::ReadWrite).unwrap();
+        second.save().unwrap();
+    }
+
+    #[test]
+    fn test_read_only_loader_keeps_trash() {
+        let temp = tempfile::tempdir().unwrap();
+        let mut library = MLibraryV2::new(temp.path().join("Hum")).unwrap();
+        let frame = image::RgbaImage::from_pixel(2, 2, image::Rgba([9, 9, 9, 255]));
+        library.add_image(&MImage::from_image(&frame, 0, 0));
+        library.add_image(&MImage::from_image(&frame, 1, 1));
+        library.save().unwrap();
+
+        let (_, mut loader) = LibraryLoader::load(&temp.path().join("Hum.Lib")).unwrap();
+        loader.set_open_mode(OpenMode::ReadWrite).unwrap();
+        loader.remove_image(0).unwrap();
+        loader.set_open_mode(OpenMode::ReadOnly).unwrap();
+
+        // 只读时回收站中的帧不能放回，记录保留
+        assert!(matches!(
+            loader.undo_remove(),
+            Err(LibraryError::ReadOnly(_))
+        ));
+        assert!(matches!(
+            loader.restore_image(0),
+            Err(LibraryError::ReadOnly(_))
+        ));
+        assert_eq!(loader.trash().len(), 1);
+        assert_eq!(loader.image_count(), 1);
+    }
+}
@@ -0,0 +1,181 @@
+//! 未保存的修改记录
+//!
+//! 记录每一帧对应已保存文件中的哪一帧（来源索引），新追加的帧没有来源，
+//! 被修改的帧保留来源但另作标记。删除和恢复帧时来源随之移动，界面据此标出已编辑的帧，
+//! 把未修改的帧对应回文件头中的信息（如是否为空帧），并生成索引对照表。保存后清空。
+
+/// 单帧的来源
+#[derive(Debug, Clone, Copy, Default)]
+struct Slot {
+    /// 在已保存文件中的索引
+    source: Option<usize>,
+    /// 内容是否被修改过
+    modified: bool,
+}
+
+/// 未保存的修改记录
+#[derive(Debug, Default)]
+pub struct EditLog {
+    /// 每一帧的来源（`None` 表示还没有修改，所有帧都是原样）
+    ///
+    /// 超出长度的帧是之后追加的，同样视为没有来源。
+    slots: Option<Vec<Slot>>,
+    /// 已保存文件中的帧数（第一次修改时的帧数）
+    saved_count: usize,
+    /// 回收站中各条记录的来源（与回收站记录一一对应）
+    removed: Vec<Slot>,
+    /// 修改次数（每次修改前递增，用于判断修改记录是否变化）
+    revision: u64,
+}
+
+/// 单帧的来源：(在已保存文件中的索引, 内容是否被修改过)
+pub type SlotState = (Option<usize>, bool);
+
+impl EditLog {
+    /// 修改前调用：第一次修改时按当前帧数建立来源表
+    pub fn track(&mut self, count: usize) {
+        self.revision += 1;
+        if self.slots.is_none() {
+            self.saved_count = count;
+        }
+        self.slots.get_or_insert_with(|| {
+            (0..count)
+                .map(|index| Slot {
+                    source: Some(index),
+                    modified: false,
+                })
+                .collect()
+        });
+    }
+
+    /// 帧被修改
+    pub fn modified(&mut self, index: usize) {
+        if let Some(slot) = self.slots.as_mut().and_then(|s| s.get_mut(index)) {
+            slot.modified = true;
+        }
+    }
+
+    /// 帧被删除（移入回收站）
+    pub fn removed(&mut self, index: usize) {
+        let slot = match self.slots.as_mut() {
+            Some(slots) if index < slots.len() => slots.remove(index),
+            _ => Slot::default(),
+        };
+        self.removed.push(slot);
+    }
+
+    /// 回收站第 `pos` 条记录恢复到 `index`
+    pub fn restored(&mut self, pos: usize, index: usize) {
+        let slot = if pos < self.removed.len() {
+            self.removed.remove(pos)
+        } else {
+            Slot::default()
+        };
+        if let Some(slots) = self.slots.as_mut() {
+            // 插回位置在追加的帧之间时，先补齐这些帧
+            if slots.len() < index {
+                slots.resize(index, Slot::default());
+            }
+            slots.insert(index, slot);
+        }
+    }
+
+    /// 保存后清空
+    pub fn clear(&mut self) {
+        self.slots = None;
+        self.removed.clear();
+    }
+
+    /// 是否有未保存的修改
+    pub fn is_modified(&self) -> bool {
+        self.slots.is_some()
+    }
+
+    /// 第 `index` 帧在已保存文件中的索引，被修改或新追加的帧返回 `None`
+    pub fn origin(&self, index: usize) -> Option<usize> {
+        match &self.slots {
+            None => Some(index),
+            Some(slots) => slots
+                .get(index)
+                .filter(|slot| !slot.modified)
+                .and_then(|slot| slot.source),
+        }
+    }
+
+    /// 第 `index` 帧在已保存文件中的位置（不论内容是否修改过），新追加的帧返回 `None`
+    pub fn source(&self, index: usize) -> Option<usize> {
+        match &self.slots {
+            None => Some(index),
+            Some(slots) => slots.get(index).and_then(|slot| slot.source),
+        }
+    }
+
+    /// 已保存文件中的帧数，还没有修改时为 `None`
+    pub fn saved_count(&self) -> Option<usize> {
+        self.slots.as_ref().map(|_| self.saved_count)
+    }
+
+    /// 修改次数，两次取值相同说明期间没有新的修改
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// 当前 `count` 帧的来源和回收站记录的来源（用于写入恢复日志）
+    pub fn slot_states(&self, count: usize) -> (Vec<SlotState>, Vec<SlotState>) {
+        let slot = |index: usize| match &self.slots {
+            None => Slot {
+                source: Some(index),
+                modified: false,
+            },
+            Some(slots) => slots.get(index).copied().unwrap_or_default(),
+        };
+        let frames = (0..count).map(slot).map(|s| (s.source, s.modified));
+        let removed = self.removed.iter().map(|s| (s.source, s.modified));
+        (frames.collect(), removed.collect())
+    }
+
+    /// 按恢复日志中的来源重建修改记录
+    pub fn restore(&mut self, saved_count: usize, frames: &[SlotState], removed: &[SlotState]) {
+        let slot = |&(source, modified): &SlotState| Slot { source, modified };
+        self.revision += 1;
+        self.saved_count = saved_count;
+        self.slots = Some(frames.iter().map(slot).collect());
+        self.removed = removed.iter().map(slot).collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_origins_follow_edits() {
+        let mut edits = EditLog::default();
+        assert_eq!(edits.origin(3), Some(3));
+
+        edits.track(5);
+        edits.modified(1);
+        // 追加第 5 帧，然后删除第 2 帧：后面的帧前移
+        edits.track(6);
+        edits.removed(2);
+        let origins: Vec<_> = (0..5).map(|i| edits.origin(i)).collect();
+        assert_eq!(origins, vec![Some(0), None, Some(3), Some(4), None]);
+
+        // 恢复后回到原来的来源
+        edits.restored(0, 2);
+        let origins: Vec<_> = (0..6).map(|i| edits.origin(i)).collect();
+        assert_eq!(
+            origins,
+            vec![Some(0), None, Some(2), Some(3), Some(4), None]
+        );
+
+        // 被修改的帧仍在原位置
+        assert_eq!(edits.source(1), Some(1));
+        assert_eq!(edits.source(5), None);
+        assert_eq!(edits.saved_count(), Some(5));
+
+        edits.clear();
+        assert!(!edits.is_modified());
+        assert_eq!(edits.origin(1), Some(1));
+    }
+}
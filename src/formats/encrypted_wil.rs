@@ -0,0 +1,350 @@
+//! 加密 WIL 格式解析 (.D / 加密 .wil)
+//! 盛大 3.55 之后的官方客户端对数据文件做了逐字节异或混淆，
+//! 解密后的布局与 WeMade .wil/.wix 完全相同（见 `mlibrary_v0`）。
+//!
+//! 支持的密钥方案：
+//! - 循环异或：固定长度的密钥重复使用
+//! - 滚动异或：第 i 个字节的密钥为 `seed + step * i`（按 u8 回绕）
+//!
+//! 密钥可以显式指定，也可以通过已知明文文件头（`#WEMADE` / `#INDX`）自动推导。
+//! 解密按文件绝对偏移进行，无需把整个数据文件读入内存。
+
+use crate::error::{LibraryError, Result};
+use crate::formats::header::FrameHeader;
+use crate::formats::limits;
+use crate::formats::mlibrary_v0::MImage;
+use crate::formats::source::{ReadSeek, Source};
+use crate::image::rows::RowLayout;
+use byteorder::{ByteOrder, LittleEndian};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::PathBuf;
+
+/// WIL 数据文件的已知明文头
+const WIL_MAGIC: &[u8] = b"#WEMADE Entertainment inc.";
+/// WIX 索引文件的已知明文头
+const WIX_MAGIC: &[u8] = b"#INDX v1.0-WEMADE Entertainment inc.";
+/// 调色板在数据文件中的偏移量
+const PALETTE_OFFSET: u64 = 56;
+/// 调色板大小（256色 * 4字节）
+const PALETTE_SIZE: usize = 1024;
+/// WIX 中图像数量的偏移量
+const WIX_COUNT_OFFSET: usize = 44;
+/// WIX 中索引数组的偏移量
+const WIX_INDEX_OFFSET: usize = 48;
+/// 自动推导时允许的最大循环密钥长度
+const MAX_KEY_LEN: usize = 16;
+/// 按顺序尝试的数据文件扩展名
+const DATA_EXTENSIONS: [&str; 3] = [".D", ".d", ".wil"];
+
+/// 异或密钥方案
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CipherScheme {
+    /// 循环异或密钥
+    Xor(Vec<u8>),
+    /// 滚动异或：key[i] = seed + step * i
+    Rolling {
+        /// 初始密钥字节
+        seed: u8,
+        /// 每字节递增量
+        step: u8,
+    },
+}
+
+impl CipherScheme {
+    /// 获取文件绝对偏移处的密钥字节
+    fn key_at(&self, offset: u64) -> u8 {
+        match self {
+            CipherScheme::Xor(key) if key.is_empty() => 0,
+            CipherScheme::Xor(key) => key[(offset % key.len() as u64) as usize],
+            CipherScheme::Rolling { seed, step } => {
+                seed.wrapping_add(step.wrapping_mul(offset as u8))
+            }
+        }
+    }
+
+    /// 原地解密（或加密，异或是对称的）从 `offset` 开始的数据
+    pub fn apply(&self, offset: u64, data: &mut [u8]) {
+        for (i, byte) in data.iter_mut().enumerate() {
+            *byte ^= self.key_at(offset + i as u64);
+        }
+    }
+
+    /// 根据文件开头的密文和已知明文推导密钥
+    ///
+    /// 明文未加密（推导出的密钥全为 0）或无法识别方案时返回 `None`。
+    pub fn derive(cipher: &[u8], known: &[u8]) -> Option<Self> {
+        let len = cipher.len().min(known.len());
+        if len < 8 {
+            return None;
+        }
+
+        let key: Vec<u8> = cipher[..len]
+            .iter()
+            .zip(&known[..len])
+            .map(|(c, p)| c ^ p)
+            .collect();
+
+        if key.iter().all(|&k| k == 0) {
+            return None;
+        }
+
+        // 优先尝试最短的循环密钥（至少重复一次才可信）
+        for period in 1..=MAX_KEY_LEN.min(len / 2) {
+            if key.iter().enumerate().all(|(i, &k)| k == key[i % period]) {
+                return Some(CipherScheme::Xor(key[..period].to_vec()));
+            }
+        }
+
+        let seed = key[0];
+        let step = key[1].wrapping_sub(key[0]);
+        let rolling = CipherScheme::Rolling { seed, step };
+        if key
+            .iter()
+            .enumerate()
+            .all(|(i, &k)| k == rolling.key_at(i as u64))
+        {
+            return Some(rolling);
+        }
+
+        None
+    }
+}
+
+/// 检测数据文件（扩展名为 `ext` 的伴随文件）是否为加密的 WIL（文件头不是明文且能推导出密钥）
+pub fn sniff(source: &Source, ext: &str) -> Option<CipherScheme> {
+    let mut header = vec![0u8; WIL_MAGIC.len()];
+    source.open(ext).ok()?.read_exact(&mut header).ok()?;
+
+    if header.starts_with(b"#WEMADE") || header.starts_with(b"#INDX") {
+        return None;
+    }
+
+    CipherScheme::derive(&header, WIL_MAGIC)
+}
+
+/// 加密 WIL 库 - 用于处理 .D / 加密 .wil 文件
+pub struct EncryptedWilLibrary {
+    /// 文件路径（不带扩展名）
+    pub file_name: PathBuf,
+    /// 图像列表
+    pub images: Vec<Option<MImage>>,
+    /// 索引列表（解密后数据文件中的偏移量）
+    pub index_list: Vec<u32>,
+    /// 图像计数
+    pub count: usize,
+    /// 数据文件的密钥方案
+    pub scheme: CipherScheme,
+    /// 数据文件读取器
+    reader: Box<dyn ReadSeek>,
+    /// 调色板（256色 BGRA）
+    palette: [[u8; 4]; 256],
+}
+
+impl EncryptedWilLibrary {
+    /// 创建新的加密 WIL 实例，密钥从文件头自动推导
+    pub fn new(file_name: PathBuf) -> Result<Self> {
+        let source = Source::Path(file_name.clone());
+        Self::open(file_name, source, None)
+    }
+
+    /// 使用指定的密钥方案创建实例
+    pub fn with_scheme(file_name: PathBuf, scheme: CipherScheme) -> Result<Self> {
+        let source = Source::Path(file_name.clone());
+        Self::open(file_name, source, Some(scheme))
+    }
+
+    /// 从指定来源读取的实例，密钥从文件头自动推导
+    pub fn from_source(file_name: PathBuf, source: Source) -> Result<Self> {
+        Self::open(file_name, source, None)
+    }
+
+    fn open(file_name: PathBuf, source: Source, scheme: Option<CipherScheme>) -> Result<Self> {
+        let data_ext = DATA_EXTENSIONS
+            .into_iter()
+            .find(|ext| source.exists(ext))
+            .ok_or_else(|| LibraryError::FileNotFound(source.describe(".D")))?;
+
+        if !source.exists(".wix") {
+            return Err(LibraryError::FileNotFound(source.describe(".wix")));
+        }
+
+        let scheme = match scheme {
+            Some(s) => s,
+            None => sniff(&source, data_ext).ok_or_else(|| {
+                tracing::error!("无法从文件头推导密钥: {}", source.describe(data_ext));
+                LibraryError::ParseError("无法识别的加密方案".to_string())
+            })?,
+        };
+        tracing::debug!("加密 WIL 密钥方案: {:?}", scheme);
+
+        let index_list = Self::read_index(&source)?;
+
+        let mut library = Self {
+            file_name,
+            images: vec![None; index_list.len()],
+            count: index_list.len(),
+            index_list,
+            scheme,
+            reader: source.open(data_ext)?,
+            palette: [[0u8; 4]; 256],
+        };
+
+        let mut palette = [0u8; PALETTE_SIZE];
+        library.read_decrypted(PALETTE_OFFSET, &mut palette)?;
+        for (color, chunk) in library.palette.iter_mut().zip(palette.chunks_exact(4)) {
+            color.copy_from_slice(chunk);
+        }
+
+        tracing::info!(
+            "加载加密 WIL: {:?} ({} 张图像)",
+            library.file_name,
+            library.count
+        );
+        Ok(library)
+    }
+
+    /// 读取 WIX 索引文件（明文或加密均可）
+    fn read_index(source: &Source) -> Result<Vec<u32>> {
+        let mut data = source.read(".wix")?;
+
+        if !data.starts_with(b"#INDX")
+            && let Some(scheme) = CipherScheme::derive(&data, WIX_MAGIC)
+        {
+            tracing::debug!("WIX 索引文件已加密: {:?}", scheme);
+            scheme.apply(0, &mut data);
+        }
+
+        if data.len() < WIX_INDEX_OFFSET {
+            return Err(LibraryError::InvalidFormat);
+        }
+
+        let declared = LittleEndian::read_u32(&data[WIX_COUNT_OFFSET..]) as usize;
+        let available = (data.len() - WIX_INDEX_OFFSET) / 4;
+        let count = declared.min(available);
+
+        Ok(data[WIX_INDEX_OFFSET..WIX_INDEX_OFFSET + count * 4]
+            .chunks_exact(4)
+            .map(LittleEndian::read_u32)
+            .collect())
+    }
+
+    /// 从数据文件的绝对偏移读取并解密
+    fn read_decrypted(&mut self, offset: u64, buf: &mut [u8]) -> Result<()> {
+        self.reader.seek(SeekFrom::Start(offset))?;
+        self.reader.read_exact(buf)?;
+        self.scheme.apply(offset, buf);
+        Ok(())
+    }
+
+    /// 检查并加载指定索引的图像
+    pub fn check_image(&mut self, index: usize) -> Result<()> {
+        if index >= self.images.len() {
+            return Err(LibraryError::IndexOutOfBounds(index));
+        }
+
+        if self.images[index].is_none() {
+            self.load_image(index)?;
+        }
+
+        Ok(())
+    }
+
+    /// 加载指定索引的图像
+    fn load_image(&mut self, index: usize) -> Result<()> {
+        let offset = self.index_list[index] as u64;
+
+        let mut header = [0u8; 8];
+        self.read_decrypted(offset, &mut header)?;
+
+        let mut image = MImage::new();
+        image.width = LittleEndian::read_u16(&header[0..2]);
+        image.height = LittleEndian::read_u16(&header[2..4]);
+        image.flag = LittleEndian::read_u32(&header[4..8]);
+
+        let data_size = image.layout().len() as i64;
+        let mut fbytes = vec![0u8; limits::check_length(data_size, "帧数据")?];
+        self.read_decrypted(offset + 8, &mut fbytes)?;
+        image.fbytes = fbytes;
+
+        image.decode_with_palette(&self.palette)?;
+        self.images[index] = Some(image);
+        Ok(())
+    }
+
+    /// 只解密每帧的 8 字节头部，跳过像素数据
+    pub fn frame_headers(&mut self) -> Result<Vec<FrameHeader>> {
+        let mut headers = Vec::with_capacity(self.index_list.len());
+        for index in 0..self.index_list.len() {
+            let mut header = [0u8; 4];
+            self.read_decrypted(self.index_list[index] as u64, &mut header)?;
+            let width = LittleEndian::read_u16(&header[0..2]) as i32;
+            let height = LittleEndian::read_u16(&header[2..4]) as i32;
+            headers.push(FrameHeader {
+                width,
+                height,
+                data_offset: self.index_list[index] as u64 + 8,
+                data_length: RowLayout::new(width as u32, height as u32, 8).len() as u64,
+                ..FrameHeader::empty(index)
+            });
+        }
+
+        Ok(headers)
+    }
+
+    /// 获取指定索引的图像
+    pub fn get_image(&mut self, index: usize) -> Result<&MImage> {
+        self.check_image(index)?;
+
+        self.images[index]
+            .as_ref()
+            .ok_or(LibraryError::IndexOutOfBounds(index))
+    }
+
+    /// 释放指定帧，下次访问时从文件重新读取（不在文件索引中的帧不释放）
+    pub fn release_image(&mut self, index: usize) {
+        if index < self.index_list.len()
+            && let Some(image) = self.images.get_mut(index)
+        {
+            *image = None;
+        }
+    }
+
+    /// 获取图像计数
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// 获取调色板
+    pub fn get_palette(&self) -> &[[u8; 4]; 256] {
+        &self.palette
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_derive_repeating_xor() {
+        let scheme = CipherScheme::Xor(vec![0x5A, 0xA5, 0x3C]);
+        let mut cipher = WIL_MAGIC.to_vec();
+        scheme.apply(0, &mut cipher);
+
+        assert_eq!(CipherScheme::derive(&cipher, WIL_MAGIC), Some(scheme));
+    }
+
+    #[test]
+    fn test_derive_rolling_and_plaintext() {
+        let scheme = CipherScheme::Rolling { seed: 0x11, step: 7 };
+        let mut cipher = WIX_MAGIC.to_vec();
+        scheme.apply(0, &mut cipher);
+
+        assert_eq!(CipherScheme::derive(&cipher, WIX_MAGIC), Some(scheme.clone()));
+        assert_eq!(CipherScheme::derive(WIX_MAGIC, WIX_MAGIC), None);
+
+        // 任意偏移处解密应与整体解密一致
+        let mut tail = cipher[10..].to_vec();
+        scheme.apply(10, &mut tail);
+        assert_eq!(&tail[..], &WIX_MAGIC[10..]);
+    }
+}
@@ -0,0 +1,151 @@
+//! 按帧属性筛选
+//!
+//! 用宽高范围、空帧、遮罩层和阴影等条件筛选帧，条件之间为“且”的关系。
+//! 配合 [`LibraryLoader::find_frames`](crate::formats::LibraryLoader::find_frames)
+//! 使用，供 GUI 的筛选栏和 `find` 命令缩小要查看的帧。
+
+use crate::error::{LibraryError, Result};
+use crate::formats::{ImageInfo, ShadowInfo};
+use std::ops::RangeInclusive;
+
+/// 帧属性筛选条件（全部为空时所有帧都匹配）
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FrameFilter {
+    /// 宽度范围（含两端）
+    pub width: Option<RangeInclusive<i32>>,
+    /// 高度范围（含两端）
+    pub height: Option<RangeInclusive<i32>>,
+    /// 只要空帧（宽或高为 0）
+    pub empty: bool,
+    /// 只要带遮罩层的帧
+    pub mask: bool,
+    /// 只要阴影值不为 0 的帧
+    pub shadow: bool,
+}
+
+impl FrameFilter {
+    /// 解析尺寸范围：`32`、`16-64`、`16-`（至少 16）或 `-64`（至多 64），空文本表示不限
+    pub fn parse_range(text: &str) -> Result<Option<RangeInclusive<i32>>> {
+        let text = text.trim();
+        if text.is_empty() {
+            return Ok(None);
+        }
+        let invalid = || LibraryError::ParseError(format!("无效的尺寸范围: {}", text));
+        let parse = |s: &str, default: i32| match s.trim() {
+            "" => Ok(default),
+            s => s.parse::<i32>().map_err(|_| invalid()),
+        };
+
+        let (min, max) = match text.split_once('-') {
+            Some((min, max)) => (parse(min, 0)?, parse(max, i32::MAX)?),
+            None => {
+                let size = parse(text, 0)?;
+                (size, size)
+            }
+        };
+        if min > max {
+            return Err(invalid());
+        }
+        Ok(Some(min..=max))
+    }
+
+    /// 是否没有任何条件
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// 帧是否满足全部条件
+    pub fn matches(&self, info: &ImageInfo) -> bool {
+        let in_range = |range: &Option<RangeInclusive<i32>>, value| {
+            range.as_ref().is_none_or(|r| r.contains(&value))
+        };
+        let shadow = match info.has_mask {
+            ShadowInfo::None => 0,
+            ShadowInfo::Simple { shadow, .. } | ShadowInfo::Mask { shadow, .. } => shadow,
+        };
+
+        in_range(&self.width, info.width)
+            && in_range(&self.height, info.height)
+            && (!self.empty || info.is_empty())
+            && (!self.mask || matches!(info.has_mask, ShadowInfo::Mask { .. }))
+            && (!self.shadow || shadow != 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::LibraryLoader;
+    use crate::formats::mlibrary_v2::{MImage, MLibraryV2};
+    use image::RgbaImage;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_filter_frames() {
+        assert_eq!(FrameFilter::parse_range(" ").unwrap(), None);
+        assert_eq!(FrameFilter::parse_range("32").unwrap(), Some(32..=32));
+        assert_eq!(FrameFilter::parse_range("16-64").unwrap(), Some(16..=64));
+        assert_eq!(
+            FrameFilter::parse_range("16-").unwrap(),
+            Some(16..=i32::MAX)
+        );
+        assert_eq!(FrameFilter::parse_range("-64").unwrap(), Some(0..=64));
+        assert!(FrameFilter::parse_range("64-16").is_err());
+        assert!(FrameFilter::parse_range("abc").is_err());
+
+        let frame = |width, height, has_mask| ImageInfo {
+            index: 0,
+            width,
+            height,
+            x: 0,
+            y: 0,
+            has_mask,
+        };
+        let plain = frame(48, 32, ShadowInfo::None);
+        let shadowed = frame(
+            20,
+            80,
+            ShadowInfo::Simple {
+                shadow: 1,
+                shadow_x: 0,
+                shadow_y: 0,
+            },
+        );
+        let empty = frame(0, 0, ShadowInfo::None);
+
+        assert!(FrameFilter::default().is_empty());
+        assert!(FrameFilter::default().matches(&plain));
+
+        let tall = FrameFilter {
+            height: Some(64..=100),
+            ..Default::default()
+        };
+        assert!(!tall.matches(&plain) && tall.matches(&shadowed));
+
+        let with_shadow = FrameFilter {
+            shadow: true,
+            ..Default::default()
+        };
+        assert!(!with_shadow.matches(&plain) && with_shadow.matches(&shadowed));
+
+        let blank = FrameFilter {
+            empty: true,
+            ..Default::default()
+        };
+        assert!(blank.matches(&empty) && !blank.matches(&plain));
+
+        let masked = FrameFilter {
+            mask: true,
+            ..Default::default()
+        };
+        assert!(!masked.matches(&shadowed));
+
+        let mut source = MLibraryV2::new(PathBuf::from("filter_missing")).unwrap();
+        source.add_image(&MImage::from_image(&RgbaImage::new(48, 32), 0, 0));
+        source.add_image(&MImage::new());
+        source.add_image(&MImage::from_image(&RgbaImage::new(20, 80), 0, 0));
+        let (_, mut loader) = LibraryLoader::from_v2(source, "filter.Lib");
+        assert_eq!(loader.find_frames(|info| tall.matches(info)), vec![2]);
+        assert_eq!(loader.find_frames(|info| blank.matches(info)), vec![1]);
+    }
+}
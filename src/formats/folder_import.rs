@@ -0,0 +1,298 @@
+//! 按文件名从文件夹批量更新帧 (import-dir)
+//!
+//! 美术把改好的帧放进一个文件夹交回，管理员一次导入即可精确更新：
+//! - `00017.png`（文件名为数字）替换第 17 帧，保留原帧的偏移、阴影和遮罩层；
+//! - `append_*.png` 按文件名顺序追加到末尾；
+//! - 文件夹中的 `mapping.csv` 可改写文件的去向，每行为 `文件名,目标`，目标为帧索引、
+//!   `append` 或 `skip`；以 `#` 开头的行和 `file,target` 表头忽略。
+//!
+//! 其它 PNG 和 `mask_` 开头的遮罩图不导入。导入前先检查全部规则，有冲突时不改动库。
+
+use crate::error::{LibraryError, Result};
+use crate::formats::LibraryLoader;
+use crate::formats::mlibrary_v2::EncodeOptions;
+use crate::image::chroma_key::ChromaKey;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// 映射文件名
+pub const MAPPING_FILE: &str = "mapping.csv";
+
+/// 追加帧的文件名前缀
+const APPEND_PREFIX: &str = "append_";
+
+/// 文件的去向
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    /// 替换指定帧
+    Frame(usize),
+    /// 追加到末尾
+    Append,
+    /// 不导入
+    Skip,
+}
+
+impl Target {
+    /// 按文件名规则判断去向：数字文件名替换对应帧，`append_` 开头追加，其它不导入
+    pub fn from_file_name(name: &str) -> Self {
+        let Some(stem) = png_stem(name) else {
+            return Target::Skip;
+        };
+        if !stem.is_empty() && stem.bytes().all(|b| b.is_ascii_digit()) {
+            return stem.parse().map_or(Target::Skip, Target::Frame);
+        }
+        if stem.starts_with(APPEND_PREFIX) {
+            return Target::Append;
+        }
+        Target::Skip
+    }
+
+    /// 解析映射文件中的目标
+    fn parse(text: &str) -> Option<Self> {
+        match text.trim().to_ascii_lowercase().as_str() {
+            "append" => Some(Target::Append),
+            "skip" | "" => Some(Target::Skip),
+            index => index.parse().ok().map(Target::Frame),
+        }
+    }
+}
+
+/// 去掉 `.png` 扩展名（不区分大小写），不是 PNG 时返回 `None`
+fn png_stem(name: &str) -> Option<&str> {
+    let split = name.len().checked_sub(4)?;
+    let (stem, ext) = (name.get(..split)?, name.get(split..)?);
+    ext.eq_ignore_ascii_case(".png").then_some(stem)
+}
+
+/// 导入计划：要替换的帧（按帧索引）和要追加的文件（按文件名）
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportPlan {
+    /// 帧索引 → 文件名
+    pub replace: BTreeMap<usize, String>,
+    /// 追加的文件名
+    pub append: Vec<String>,
+    /// 不导入的 PNG
+    pub skipped: Vec<String>,
+}
+
+impl ImportPlan {
+    /// 扫描文件夹中的 PNG 和映射文件
+    pub fn scan(dir: &Path) -> Result<Self> {
+        let names: Vec<String> = std::fs::read_dir(dir)?
+            .filter_map(|e| e.ok())
+            .filter_map(|e| {
+                e.file_name()
+                    .into_string()
+                    .inspect_err(|name| tracing::warn!("跳过非 UTF-8 文件名: {:?}", name))
+                    .ok()
+            })
+            .filter(|n| png_stem(n).is_some() && !n.starts_with("mask_"))
+            .collect();
+        let mapping_path = dir.join(MAPPING_FILE);
+        let mapping = match mapping_path.exists() {
+            true => Some(std::fs::read_to_string(&mapping_path)?),
+            false => None,
+        };
+        Self::from_names(names, mapping.as_deref())
+    }
+
+    /// 由文件名和映射文件内容建立计划
+    ///
+    /// 映射中的文件不存在、目标无法解析或多个文件替换同一帧时返回错误。
+    pub fn from_names(mut names: Vec<String>, mapping: Option<&str>) -> Result<Self> {
+        names.sort();
+        let mut targets: BTreeMap<String, Target> = names
+            .iter()
+            .map(|name| (name.clone(), Target::from_file_name(name)))
+            .collect();
+
+        // 记事本和 Excel 保存的 UTF-8 文件开头带 BOM，不去掉时表头会被当成数据行
+        let mapping = mapping.unwrap_or_default();
+        let mapping = mapping.strip_prefix('\u{feff}').unwrap_or(mapping);
+        for (line_no, line) in mapping.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (file, target) = line.split_once(',').unwrap_or((line, ""));
+            let file = file.trim();
+            if line_no == 0 && file.eq_ignore_ascii_case("file") {
+                continue;
+            }
+            let target = Target::parse(target).ok_or_else(|| {
+                LibraryError::ParseError(format!(
+                    "{} 第 {} 行: 无效的目标: {}",
+                    MAPPING_FILE,
+                    line_no + 1,
+                    target.trim()
+                ))
+            })?;
+            match targets.get_mut(file) {
+                Some(slot) => *slot = target,
+                None => {
+                    return Err(LibraryError::FileNotFound(format!(
+                        "{} 第 {} 行: {}",
+                        MAPPING_FILE,
+                        line_no + 1,
+                        file
+                    )));
+                }
+            }
+        }
+
+        let mut plan = ImportPlan::default();
+        for name in names {
+            match targets[&name] {
+                Target::Frame(index) => {
+                    if let Some(other) = plan.replace.insert(index, name.clone()) {
+                        return Err(LibraryError::ParseError(format!(
+                            "{} 和 {} 都替换第 {} 帧",
+                            other, name, index
+                        )));
+                    }
+                }
+                Target::Append => plan.append.push(name),
+                Target::Skip => plan.skipped.push(name),
+            }
+        }
+        Ok(plan)
+    }
+
+    /// 计划是否为空
+    pub fn is_empty(&self) -> bool {
+        self.replace.is_empty() && self.append.is_empty()
+    }
+}
+
+/// 导入结果
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportReport {
+    /// 替换的帧
+    pub replaced: Vec<usize>,
+    /// 追加的帧
+    pub appended: Vec<usize>,
+}
+
+/// 按计划把文件夹中的图像写入库（V2 和 WZL 可写），需另行保存
+///
+/// 先解码全部图像，替换的帧超出帧数或有图像无法解码时不写入任何帧；
+/// 落在锁定帧上的图像跳过。
+pub fn apply(
+    loader: &mut LibraryLoader,
+    dir: &Path,
+    plan: &ImportPlan,
+    key: ChromaKey,
+    options: EncodeOptions,
+) -> Result<ImportReport> {
+    let count = loader.image_count();
+    if let Some((&index, name)) = plan.replace.iter().find(|&(&index, _)| index >= count) {
+        return Err(LibraryError::ParseError(format!(
+            "{} 替换第 {} 帧，超出帧数 {}",
+            name, index, count
+        )));
+    }
+
+    let load = |name: &String| -> Result<image::RgbaImage> {
+        let mut image = image::open(dir.join(name))?.to_rgba8();
+        key.apply(&mut image);
+        Ok(image)
+    };
+
+    let replacements = plan
+        .replace
+        .iter()
+        .map(|(&index, name)| Ok((index, load(name)?)))
+        .collect::<Result<Vec<_>>>()?;
+    let images = plan.append.iter().map(load).collect::<Result<Vec<_>>>()?;
+
+    let mut report = ImportReport::default();
+    for (index, image) in replacements {
+        report
+            .replaced
+            .extend(loader.put_frames(&[image], Some(index), options)?);
+    }
+    report.appended = loader.put_frames(&images, None, options)?;
+    tracing::debug!(
+        "从 {:?} 导入: 替换 {} 帧，追加 {} 帧",
+        dir,
+        report.replaced.len(),
+        report.appended.len()
+    );
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::mlibrary_v2::{MImage, MLibraryV2};
+
+    #[test]
+    fn test_import_folder_by_name() {
+        let names = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(Target::from_file_name("00017.PNG"), Target::Frame(17));
+        assert_eq!(Target::from_file_name("append_sword.png"), Target::Append);
+        assert_eq!(Target::from_file_name("notes.png"), Target::Skip);
+        assert_eq!(Target::from_file_name("00017.bmp"), Target::Skip);
+
+        // 映射文件改写去向
+        let mapping = "file,target\n# 备注\nnotes.png,3\n00001.png,skip\n";
+        let plan = ImportPlan::from_names(
+            names(&["00001.png", "append_b.png", "append_a.png", "notes.png"]),
+            Some(mapping),
+        )
+        .unwrap();
+        assert_eq!(plan.replace.get(&3).map(String::as_str), Some("notes.png"));
+        assert_eq!(plan.replace.len(), 1);
+        assert_eq!(plan.append, names(&["append_a.png", "append_b.png"]));
+        assert_eq!(plan.skipped, names(&["00001.png"]));
+
+        // 冲突和映射错误
+        assert!(ImportPlan::from_names(names(&["1.png", "001.png"]), None).is_err());
+        assert!(ImportPlan::from_names(names(&["1.png"]), Some("2.png,append")).is_err());
+        assert!(ImportPlan::from_names(names(&["1.png"]), Some("1.png,later")).is_err());
+
+        // 带 BOM 的映射文件表头同样忽略
+        let plan =
+            ImportPlan::from_names(names(&["a.png"]), Some("\u{feff}File,Target\na.png,2\n"))
+                .unwrap();
+        assert_eq!(plan.replace.get(&2).map(String::as_str), Some("a.png"));
+
+        // 写入库：替换第 1 帧、追加一帧，超出帧数时不改动
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let mut library = MLibraryV2::new(dir.join("Hum")).unwrap();
+        for shade in [10, 20] {
+            let frame = image::RgbaImage::from_pixel(2, 2, image::Rgba([shade, 0, 0, 255]));
+            library.add_image(&MImage::from_image(&frame, 4, 5));
+        }
+        library.save().unwrap();
+        let blue = image::RgbaImage::from_pixel(3, 2, image::Rgba([0, 0, 99, 255]));
+        blue.save(dir.join("00001.png")).unwrap();
+        blue.save(dir.join("append_x.png")).unwrap();
+        blue.save(dir.join("00009.png")).unwrap();
+        std::fs::write(dir.join(MAPPING_FILE), "00009.png,skip\n").unwrap();
+
+        let (_, mut loader) = LibraryLoader::load(&dir.join("Hum.Lib")).unwrap();
+        let plan = ImportPlan::scan(dir).unwrap();
+        let options = EncodeOptions::default();
+        let report = apply(&mut loader, dir, &plan, ChromaKey::Alpha, options).unwrap();
+        assert_eq!(report.replaced, vec![1]);
+        assert_eq!(report.appended, vec![2]);
+        assert_eq!(loader.image_count(), 3);
+        let info = loader.get_image_info(1).unwrap();
+        assert_eq!((info.width, info.height, info.x, info.y), (3, 2, 4, 5));
+
+        let plan = ImportPlan::from_names(names(&["00009.png"]), None).unwrap();
+        assert!(apply(&mut loader, dir, &plan, ChromaKey::Alpha, options).is_err());
+        assert_eq!(loader.image_count(), 3);
+
+        // 后面的文件无法解码时前面的替换也不写入
+        let red = image::RgbaImage::from_pixel(5, 5, image::Rgba([200, 0, 0, 255]));
+        red.save(dir.join("00000.png")).unwrap();
+        std::fs::write(dir.join("00002.png"), b"not a png").unwrap();
+        let plan = ImportPlan::from_names(names(&["00000.png", "00002.png"]), None).unwrap();
+        assert!(apply(&mut loader, dir, &plan, ChromaKey::Alpha, options).is_err());
+        let info = loader.get_image_info(0).unwrap();
+        assert_eq!((info.width, info.height), (2, 2));
+    }
+}
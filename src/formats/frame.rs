@@ -0,0 +1,215 @@
+//! 帧的公共部分
+//!
+//! WZL (V1) 与 .Lib (V2) 的帧除像素数据的编码外完全相同：尺寸、偏移、阴影、遮罩层，
+//! 以及解码后的图像和预览图。这些字段放在 [`FrameData`] 中，两种 `MImage` 通过
+//! `Deref` 直接访问，只各自保留像素数据并实现 [`FrameCodec`]。预览、图像信息、
+//! 格式之间的转码等通用处理只写一次。WIL (V0) 的帧头字段与此不同，不在此列。
+
+use crate::formats::ShadowInfo;
+use crate::image::rows::RowOrder;
+use crate::image::{PREVIEW_SIZE, letterbox};
+use image::RgbaImage;
+use std::ops::{Deref, DerefMut};
+use std::sync::Arc;
+
+/// 各格式帧共有的字段
+#[derive(Debug, Clone, Default)]
+pub struct FrameData {
+    /// 图像宽度
+    pub width: i16,
+    /// 图像高度
+    pub height: i16,
+    /// X 偏移
+    pub x: i16,
+    /// Y 偏移
+    pub y: i16,
+    /// 阴影 X 偏移
+    pub shadow_x: i16,
+    /// 阴影 Y 偏移
+    pub shadow_y: i16,
+    /// 阴影值
+    pub shadow: u8,
+    /// 图像纹理是否有效
+    pub texture_valid: bool,
+    /// 解码后的图像（取帧时共享，不复制像素）
+    pub image: Option<Arc<RgbaImage>>,
+    /// 预览图 (64x64)
+    pub preview: Option<RgbaImage>,
+
+    // Layer 2 (Mask)
+    /// 是否有遮罩层
+    pub has_mask: bool,
+    /// 遮罩宽度
+    pub mask_width: i16,
+    /// 遮罩高度
+    pub mask_height: i16,
+    /// 遮罩 X 偏移
+    pub mask_x: i16,
+    /// 遮罩 Y 偏移
+    pub mask_y: i16,
+    /// 遮罩数据
+    pub mask_fbytes: Vec<u8>,
+    /// 遮罩图像
+    pub mask_image: Option<RgbaImage>,
+}
+
+impl FrameData {
+    /// 创建预览图（64x64，等比缩小后居中，不裁剪）
+    pub fn create_preview(&mut self) {
+        if let Some(ref image) = self.image {
+            self.preview = Some(letterbox(image, PREVIEW_SIZE));
+        }
+    }
+
+    /// 获取预览图
+    pub fn get_preview(&mut self) -> Option<&RgbaImage> {
+        if self.preview.is_none() {
+            self.create_preview();
+        }
+        self.preview.as_ref()
+    }
+
+    /// 释放解码后的图像，下次访问时从像素数据重新解码
+    pub fn release_texture(&mut self) {
+        self.image = None;
+        self.mask_image = None;
+        self.preview = None;
+        self.texture_valid = false;
+    }
+
+    /// 去掉遮罩层，返回原先是否带遮罩
+    pub fn clear_mask(&mut self) -> bool {
+        let had_mask = self.has_mask;
+        self.has_mask = false;
+        self.mask_width = 0;
+        self.mask_height = 0;
+        self.mask_x = 0;
+        self.mask_y = 0;
+        self.mask_fbytes.clear();
+        self.mask_image = None;
+        had_mask
+    }
+
+    /// 阴影和遮罩属性
+    pub fn properties(&self) -> FrameProperties {
+        FrameProperties::from(&self.shadow_info())
+    }
+
+    /// 阴影参数和遮罩层的信息
+    pub fn shadow_info(&self) -> ShadowInfo {
+        if self.has_mask {
+            ShadowInfo::Mask {
+                shadow: self.shadow,
+                shadow_x: self.shadow_x,
+                shadow_y: self.shadow_y,
+                mask_width: self.mask_width,
+                mask_height: self.mask_height,
+                mask_x: self.mask_x,
+                mask_y: self.mask_y,
+            }
+        } else {
+            ShadowInfo::Simple {
+                shadow: self.shadow,
+                shadow_x: self.shadow_x,
+                shadow_y: self.shadow_y,
+            }
+        }
+    }
+}
+
+/// 帧的阴影和遮罩属性（属性面板中可编辑的部分）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FrameProperties {
+    /// 阴影值（0-127，最高位在文件中用作遮罩标志）
+    pub shadow: u8,
+    /// 阴影 X 偏移
+    pub shadow_x: i16,
+    /// 阴影 Y 偏移
+    pub shadow_y: i16,
+    /// 是否有遮罩层
+    pub has_mask: bool,
+    /// 遮罩 X 偏移
+    pub mask_x: i16,
+    /// 遮罩 Y 偏移
+    pub mask_y: i16,
+}
+
+impl From<&ShadowInfo> for FrameProperties {
+    fn from(info: &ShadowInfo) -> Self {
+        match *info {
+            ShadowInfo::None => Self::default(),
+            ShadowInfo::Simple {
+                shadow,
+                shadow_x,
+                shadow_y,
+            } => Self {
+                shadow,
+                shadow_x,
+                shadow_y,
+                ..Self::default()
+            },
+            ShadowInfo::Mask {
+                shadow,
+                shadow_x,
+                shadow_y,
+                mask_x,
+                mask_y,
+                ..
+            } => Self {
+                shadow,
+                shadow_x,
+                shadow_y,
+                has_mask: true,
+                mask_x,
+                mask_y,
+            },
+        }
+    }
+}
+
+/// 一种格式的帧编码：公共字段在 [`FrameData`] 中，实现者只负责像素数据
+pub trait FrameCodec: Deref<Target = FrameData> + DerefMut + Default {
+    /// 像素行在文件中的存储顺序
+    const ROW_ORDER: RowOrder;
+
+    /// 把 RGBA 图像编码为本格式的帧，偏移为 (`x`, `y`)
+    fn encode(image: &RgbaImage, x: i16, y: i16) -> Self;
+
+    /// 把另一种格式的帧按本格式重新编码，保留偏移；没有解码后的图像时得到空帧
+    fn transcode(frame: &FrameData) -> Self {
+        match frame.image {
+            Some(ref image) => Self::encode(image, frame.x, frame.y),
+            None => Self::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::{mlibrary_v1, mlibrary_v2};
+    use image::Rgba;
+
+    #[test]
+    fn test_transcode_between_formats() {
+        let mut image = RgbaImage::from_pixel(4, 2, Rgba([0, 0, 0, 0]));
+        image.put_pixel(1, 0, Rgba([255, 0, 0, 255]));
+        let source = mlibrary_v2::MImage::encode(&image, 3, -5);
+
+        let wzl = mlibrary_v1::MImage::transcode(&source);
+        assert_eq!((wzl.width, wzl.height, wzl.x, wzl.y), (4, 2, 3, -5));
+        assert!(wzl.bo16bit);
+        let decoded = wzl.image.as_deref().unwrap();
+        assert_eq!(decoded.get_pixel(1, 0).0, [255, 0, 0, 255]);
+        assert_eq!(decoded.get_pixel(0, 1).0[3], 0);
+        assert!(matches!(wzl.shadow_info(), ShadowInfo::Simple { .. }));
+
+        // 预览图对两种格式的帧一致
+        let mut lib = mlibrary_v2::MImage::transcode(&wzl);
+        let mut wzl = wzl;
+        assert_eq!(lib.get_preview().cloned(), wzl.get_preview().cloned());
+        lib.release_texture();
+        assert!(lib.image.is_none() && lib.preview.is_none());
+        assert!(mlibrary_v1::MImage::transcode(&lib).fbytes.is_empty());
+    }
+}
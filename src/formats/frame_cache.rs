@@ -0,0 +1,150 @@
+//! 解码帧的内存预算
+//!
+//! 各后端把解码后的帧图像保存在内存中，默认一直保留到库关闭。打开大库或长时间浏览时
+//! 可用 [`CachePolicy`] 限制保留的帧数或字节数，超出时释放最久未访问的帧，下次访问时
+//! 再从压缩数据或文件重新解码。已交给调用方的图像（`Arc`）不受释放影响。
+//!
+//! 访问顺序按帧索引记录；删除、插入帧后索引移动，记录可能对应到相邻的帧上，
+//! 只影响先释放哪一帧，不影响取到的图像。
+
+use std::collections::{BTreeMap, HashMap};
+
+/// 解码帧的保留上限，0 表示不限制
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CachePolicy {
+    /// 最多保留的帧数
+    pub max_frames: usize,
+    /// 最多保留的像素字节数
+    pub max_bytes: usize,
+}
+
+impl CachePolicy {
+    /// 不限制（默认）
+    pub const UNLIMITED: Self = Self {
+        max_frames: 0,
+        max_bytes: 0,
+    };
+
+    /// 最多保留 `megabytes` MiB 像素数据
+    pub fn megabytes(megabytes: usize) -> Self {
+        Self {
+            max_frames: 0,
+            max_bytes: megabytes << 20,
+        }
+    }
+
+    /// 是否不限制
+    pub fn is_unlimited(&self) -> bool {
+        self.max_frames == 0 && self.max_bytes == 0
+    }
+
+    fn exceeded(&self, frames: usize, bytes: usize) -> bool {
+        (self.max_frames > 0 && frames > self.max_frames)
+            || (self.max_bytes > 0 && bytes > self.max_bytes)
+    }
+}
+
+/// 按最近访问顺序记录已解码的帧
+#[derive(Debug, Default)]
+pub(crate) struct FrameCache {
+    policy: CachePolicy,
+    /// 递增的访问计数
+    tick: u64,
+    /// 帧索引 -> (最近访问计数, 字节数)
+    entries: HashMap<usize, (u64, usize)>,
+    /// 最近访问计数 -> 帧索引（最早的在前）
+    order: BTreeMap<u64, usize>,
+    bytes: usize,
+}
+
+impl FrameCache {
+    pub(crate) fn policy(&self) -> CachePolicy {
+        self.policy
+    }
+
+    /// 设置上限，返回需要释放的帧
+    pub(crate) fn set_policy(&mut self, policy: CachePolicy) -> Vec<usize> {
+        self.policy = policy;
+        if policy.is_unlimited() {
+            self.clear();
+            return Vec::new();
+        }
+        self.evict(None)
+    }
+
+    /// 是否记录了这一帧
+    pub(crate) fn contains(&self, index: usize) -> bool {
+        self.entries.contains_key(&index)
+    }
+
+    /// 记录一次访问，返回需要释放的帧（不含刚访问的这一帧）
+    pub(crate) fn touch(&mut self, index: usize, bytes: usize) -> Vec<usize> {
+        if self.policy.is_unlimited() {
+            return Vec::new();
+        }
+        self.tick += 1;
+        if let Some((tick, old_bytes)) = self.entries.insert(index, (self.tick, bytes)) {
+            self.order.remove(&tick);
+            self.bytes -= old_bytes;
+        }
+        self.order.insert(self.tick, index);
+        self.bytes += bytes;
+        self.evict(Some(index))
+    }
+
+    /// 清空记录（库关闭或改为不限制时）
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+        self.bytes = 0;
+    }
+
+    fn evict(&mut self, keep: Option<usize>) -> Vec<usize> {
+        let mut evicted = Vec::new();
+        while self.policy.exceeded(self.entries.len(), self.bytes) {
+            let Some((&tick, &index)) = self.order.iter().next() else {
+                break;
+            };
+            if Some(index) == keep {
+                break;
+            }
+            self.order.remove(&tick);
+            if let Some((_, bytes)) = self.entries.remove(&index) {
+                self.bytes -= bytes;
+            }
+            evicted.push(index);
+        }
+        evicted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let mut cache = FrameCache::default();
+        assert!(cache.touch(0, 100).is_empty());
+        assert!(!cache.contains(0), "不限制时不记录");
+
+        cache.set_policy(CachePolicy {
+            max_frames: 2,
+            max_bytes: 250,
+        });
+        assert!(cache.touch(0, 100).is_empty());
+        assert!(cache.touch(1, 100).is_empty());
+        assert!(cache.touch(0, 100).is_empty());
+        // 帧数超限，释放最久未访问的 1
+        assert_eq!(cache.touch(2, 100), [1]);
+        // 字节数超限
+        assert_eq!(cache.touch(3, 200), [0, 2]);
+        // 单帧超过上限时仍保留刚访问的帧
+        assert_eq!(cache.touch(4, 1000), [3]);
+        assert!(cache.contains(4));
+
+        assert!(cache.set_policy(CachePolicy::megabytes(1)).is_empty());
+        assert!(cache.set_policy(CachePolicy::UNLIMITED).is_empty());
+        assert!(!cache.contains(4));
+    }
+}
@@ -0,0 +1,258 @@
+//! 仅读取文件头的轻量打开模式
+//!
+//! 只读取索引和每帧的头部字段（尺寸、偏移、数据长度），不读取也不解码像素数据，
+//! 供统计、清点命令和 GUI 的快速预览使用。大库也能在瞬间打开。
+//! 每帧的数据大小还可以汇总成分布直方图，找出压缩后异常大的帧。
+
+use crate::formats::LibraryInfo;
+
+/// 单帧的头部信息
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameHeader {
+    /// 帧索引
+    pub index: usize,
+    /// 宽度
+    pub width: i32,
+    /// 高度
+    pub height: i32,
+    /// X偏移
+    pub x: i32,
+    /// Y偏移
+    pub y: i32,
+    /// 像素数据在数据文件中的起始位置
+    pub data_offset: u64,
+    /// 文件中像素数据（含遮罩层）占用的字节数
+    pub data_length: u64,
+    /// 是否带遮罩层
+    pub has_mask: bool,
+    /// 阴影值（格式不记录阴影时为 0）
+    pub shadow: u8,
+}
+
+impl FrameHeader {
+    /// 空帧（没有像素数据的占位帧）
+    pub fn empty(index: usize) -> Self {
+        Self {
+            index,
+            width: 0,
+            height: 0,
+            x: 0,
+            y: 0,
+            data_offset: 0,
+            data_length: 0,
+            has_mask: false,
+            shadow: 0,
+        }
+    }
+
+    /// 是否为空帧
+    pub fn is_empty(&self) -> bool {
+        self.width <= 0 || self.height <= 0
+    }
+}
+
+/// 仅读取文件头得到的库概要
+#[derive(Debug, Clone)]
+pub struct LibraryHeader {
+    /// 库信息
+    pub info: LibraryInfo,
+    /// 每帧的头部信息
+    pub frames: Vec<FrameHeader>,
+}
+
+impl LibraryHeader {
+    /// 空帧数量
+    pub fn empty_count(&self) -> usize {
+        self.frames.iter().filter(|f| f.is_empty()).count()
+    }
+
+    /// 带遮罩层的帧数量
+    pub fn mask_count(&self) -> usize {
+        self.frames.iter().filter(|f| f.has_mask).count()
+    }
+
+    /// 最大帧宽高（分别取最大值）
+    pub fn max_size(&self) -> (i32, i32) {
+        self.frames
+            .iter()
+            .fold((0, 0), |(w, h), f| (w.max(f.width), h.max(f.height)))
+    }
+
+    /// 像素数据总字节数
+    pub fn payload_bytes(&self) -> u64 {
+        self.frames.iter().map(|f| f.data_length).sum()
+    }
+
+    /// 非空帧数据大小的分布直方图，分为最多 `bins` 个等宽区间
+    pub fn size_histogram(&self, bins: usize) -> SizeHistogram {
+        let frames: Vec<&FrameHeader> = self.frames.iter().filter(|f| !f.is_empty()).collect();
+        let mut sizes: Vec<u64> = frames.iter().map(|f| f.data_length).collect();
+        sizes.sort_unstable();
+        let (Some(&min), Some(&max)) = (sizes.first(), sizes.last()) else {
+            return SizeHistogram::default();
+        };
+
+        // 区间宽度向上取整，保证最大值落在最后一个区间内；取值范围很窄时减少区间数
+        let bins = (bins.max(1) as u64).min(max - min + 1);
+        let width = (max - min) / bins + 1;
+        let mut histogram = SizeHistogram {
+            bins: (0..bins)
+                .map(|i| SizeBin {
+                    min: min + i * width,
+                    max: min + (i + 1) * width - 1,
+                    frames: Vec::new(),
+                })
+                .collect(),
+            median: sizes[sizes.len() / 2],
+            ..SizeHistogram::default()
+        };
+
+        // 四分位距的 3 倍以外视为异常（Tukey 远离群值）
+        let q1 = sizes[sizes.len() / 4];
+        let q3 = sizes[sizes.len() * 3 / 4];
+        histogram.fence = q3 + (q3 - q1) * OUTLIER_IQR_FACTOR;
+
+        for frame in frames {
+            let bin = ((frame.data_length - min) / width) as usize;
+            histogram.bins[bin].frames.push(frame.index);
+            if frame.data_length > histogram.fence {
+                histogram.outliers.push(frame.index);
+            }
+        }
+        histogram
+    }
+}
+
+/// 超过第三四分位数多少倍四分位距的帧视为异常
+const OUTLIER_IQR_FACTOR: u64 = 3;
+
+/// 直方图的一个区间
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SizeBin {
+    /// 区间下限（字节，含）
+    pub min: u64,
+    /// 区间上限（字节，含）
+    pub max: u64,
+    /// 落在区间内的帧索引（升序）
+    pub frames: Vec<usize>,
+}
+
+/// 每帧数据大小的分布
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SizeHistogram {
+    /// 等宽区间（从小到大）
+    pub bins: Vec<SizeBin>,
+    /// 数据大小的中位数
+    pub median: u64,
+    /// 异常阈值：数据大小超过它的帧视为异常
+    pub fence: u64,
+    /// 异常大的帧索引（升序）
+    pub outliers: Vec<usize>,
+}
+
+impl SizeHistogram {
+    /// 帧数最多的区间的帧数
+    pub fn peak(&self) -> usize {
+        self.bins.iter().map(|b| b.frames.len()).max().unwrap_or(0)
+    }
+
+    /// 区间内位于 `current` 之后的下一帧，到末尾后从头开始
+    pub fn next_in_bin(&self, bin: usize, current: Option<usize>) -> Option<usize> {
+        next_after(&self.bins.get(bin)?.frames, current)
+    }
+
+    /// 位于 `current` 之后的下一个异常帧，到末尾后从头开始
+    pub fn next_outlier(&self, current: Option<usize>) -> Option<usize> {
+        next_after(&self.outliers, current)
+    }
+}
+
+/// 升序索引列表中大于 `current` 的第一个，没有时回到第一个
+fn next_after(indices: &[usize], current: Option<usize>) -> Option<usize> {
+    let start = current.map_or(0, |c| indices.partition_point(|&i| i <= c));
+    indices.get(start).or(indices.first()).copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::LibraryType;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_header_summary() {
+        let frame = |index, width, height, data_length, has_mask| FrameHeader {
+            index,
+            width,
+            height,
+            data_length,
+            has_mask,
+            ..FrameHeader::empty(index)
+        };
+        let header = LibraryHeader {
+            info: LibraryInfo::new(PathBuf::from("Hum"), "Hum.Lib".into(), LibraryType::MLV2, 3),
+            frames: vec![
+                frame(0, 48, 80, 1200, true),
+                FrameHeader::empty(1),
+                frame(2, 96, 32, 800, false),
+            ],
+        };
+
+        assert_eq!(header.empty_count(), 1);
+        assert_eq!(header.mask_count(), 1);
+        assert_eq!(header.max_size(), (96, 80));
+        assert_eq!(header.payload_bytes(), 2000);
+    }
+
+    #[test]
+    fn test_size_histogram_outliers() {
+        let mut frames: Vec<FrameHeader> = (0..20)
+            .map(|index| FrameHeader {
+                width: 32,
+                height: 32,
+                data_length: 1000 + index as u64 * 10,
+                ..FrameHeader::empty(index)
+            })
+            .collect();
+        frames[7].data_length = 50_000;
+        frames[13].data_length = 40_000;
+        frames.push(FrameHeader::empty(20));
+        let header = LibraryHeader {
+            info: LibraryInfo::new(
+                PathBuf::from("Hum"),
+                "Hum.Lib".into(),
+                LibraryType::MLV2,
+                21,
+            ),
+            frames,
+        };
+
+        let histogram = header.size_histogram(8);
+        assert_eq!(histogram.bins.len(), 8);
+        assert_eq!(histogram.bins[0].min, 1000);
+        assert!(histogram.bins[7].max >= 50_000);
+        // 空帧不计入
+        assert_eq!(
+            histogram.bins.iter().map(|b| b.frames.len()).sum::<usize>(),
+            20
+        );
+        assert_eq!(histogram.peak(), 18);
+        assert_eq!(histogram.outliers, vec![7, 13]);
+        assert_eq!(histogram.bins[7].frames, vec![7]);
+
+        assert_eq!(histogram.next_outlier(None), Some(7));
+        assert_eq!(histogram.next_outlier(Some(7)), Some(13));
+        assert_eq!(histogram.next_outlier(Some(13)), Some(7));
+        assert_eq!(histogram.next_in_bin(0, Some(5)), Some(6));
+        assert_eq!(histogram.next_in_bin(9, None), None);
+
+        assert_eq!(
+            LibraryHeader {
+                frames: Vec::new(),
+                ..header
+            }
+            .size_histogram(8),
+            SizeHistogram::default()
+        );
+    }
+}
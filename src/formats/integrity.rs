@@ -0,0 +1,203 @@
+//! 资源目录完整性自检
+//!
+//! 扫描游戏客户端的资源目录，逐个只读取库的文件头，检查头部能否解析、每帧的
+//! 数据是否落在文件范围内，列出有问题的文件。用于在打开之前尽早发现损坏或
+//! 下载不完整的客户端。不解码任何像素数据，整个客户端目录也能很快检查完。
+
+use crate::formats::LibraryLoader;
+use crate::formats::header::FrameHeader;
+use std::path::{Path, PathBuf};
+
+/// 指定资源目录的环境变量（多个目录按系统路径分隔符分隔）
+pub const ASSET_DIRS_ENV: &str = "LIBRARY_EDITOR_ASSET_DIRS";
+
+/// 参与检查的库数据文件扩展名（索引文件随数据文件一起检查）
+pub const LIBRARY_EXTENSIONS: [&str; 4] = ["wzl", "lib", "wil", "d"];
+
+/// 每个文件最多列出的问题帧数
+const MAX_FRAME_PROBLEMS: usize = 5;
+
+/// 单个库文件的检查结果
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileReport {
+    /// 库数据文件
+    pub path: PathBuf,
+    /// 识别出的格式名（文件头无法解析时为空）
+    pub format: Option<&'static str>,
+    /// 帧数
+    pub frames: usize,
+    /// 发现的问题
+    pub problems: Vec<String>,
+}
+
+impl FileReport {
+    /// 是否没有发现问题
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// 环境变量中配置的资源目录
+pub fn configured_dirs() -> Vec<PathBuf> {
+    std::env::var_os(ASSET_DIRS_ENV)
+        .map(|dirs| std::env::split_paths(&dirs).collect())
+        .unwrap_or_default()
+}
+
+/// 是否为参与检查的库数据文件（扩展名不区分大小写）
+pub fn is_library_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| {
+            LIBRARY_EXTENSIONS
+                .iter()
+                .any(|e| ext.eq_ignore_ascii_case(e))
+        })
+}
+
+/// 递归列出目录下的库数据文件（按路径排序）
+pub fn find_libraries(dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                tracing::warn!("无法读取目录 {:?}: {}", dir, e);
+                continue;
+            }
+        };
+        for path in entries.flatten().map(|entry| entry.path()) {
+            if path.is_dir() {
+                pending.push(path);
+            } else if is_library_file(&path) {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+/// 检查单个库：文件头能否解析，每帧的数据是否超出文件末尾
+pub fn check_file(path: &Path) -> FileReport {
+    let mut report = FileReport {
+        path: path.to_path_buf(),
+        format: None,
+        frames: 0,
+        problems: Vec::new(),
+    };
+    let header = match LibraryLoader::open_header(path) {
+        Ok(header) => header,
+        Err(e) => {
+            report.problems.push(format!("无法解析文件头: {}", e));
+            return report;
+        }
+    };
+    report.format = Some(header.info.library_type.name());
+    report.frames = header.frames.len();
+
+    let file_len = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    let bad: Vec<(&FrameHeader, &str)> = header
+        .frames
+        .iter()
+        .filter_map(|frame| frame_problem(frame, file_len).map(|problem| (frame, problem)))
+        .collect();
+    for (frame, problem) in bad.iter().take(MAX_FRAME_PROBLEMS) {
+        report
+            .problems
+            .push(format!("第 {} 帧{}", frame.index, problem));
+    }
+    if bad.len() > MAX_FRAME_PROBLEMS {
+        report
+            .problems
+            .push(format!("另有 {} 帧有问题", bad.len() - MAX_FRAME_PROBLEMS));
+    }
+    report
+}
+
+/// 单帧头部的问题
+fn frame_problem(frame: &FrameHeader, file_len: u64) -> Option<&'static str> {
+    if frame.width < 0 || frame.height < 0 {
+        return Some("尺寸为负数");
+    }
+    if frame.is_empty() {
+        return None;
+    }
+    if frame.data_offset > file_len {
+        return Some("的索引超出文件末尾");
+    }
+    if frame.data_offset + frame.data_length > file_len {
+        return Some("的数据被截断");
+    }
+    None
+}
+
+/// 检查若干目录下的全部库，按路径顺序返回每个文件的结果
+pub fn scan(dirs: &[PathBuf]) -> Vec<FileReport> {
+    scan_with(dirs, |_| {})
+}
+
+/// 同 [`scan`]，每检查完一个文件调用 `done`（用于显示进度）
+pub fn scan_with(dirs: &[PathBuf], mut done: impl FnMut(&FileReport)) -> Vec<FileReport> {
+    let reports: Vec<FileReport> = dirs
+        .iter()
+        .flat_map(|dir| find_libraries(dir))
+        .map(|path| {
+            let report = check_file(&path);
+            done(&report);
+            report
+        })
+        .collect();
+    let problems = reports.iter().filter(|r| !r.is_ok()).count();
+    tracing::info!(
+        "完整性自检: 检查 {} 个库，{} 个有问题",
+        reports.len(),
+        problems
+    );
+    reports
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::MLibraryV2;
+    use crate::formats::mlibrary_v2::MImage;
+
+    #[test]
+    fn test_scan_reports_truncated_library() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        std::fs::create_dir_all(dir.join("Data")).unwrap();
+
+        let pixels = image::RgbaImage::from_pixel(8, 8, image::Rgba([200, 10, 10, 255]));
+        for name in ["Hum", "Weapon"] {
+            let mut library = MLibraryV2::new(dir.join("Data").join(name)).unwrap();
+            library.add_image(&MImage::from_image(&pixels, 0, 0));
+            library.add_image(&MImage::from_image(&pixels, 1, 1));
+            library.save().unwrap();
+        }
+        // 截掉最后一帧的一部分数据
+        let weapon = dir.join("Data").join("Weapon.Lib");
+        let len = std::fs::metadata(&weapon).unwrap().len();
+        let file = std::fs::OpenOptions::new()
+            .write(true)
+            .open(&weapon)
+            .unwrap();
+        file.set_len(len - 4).unwrap();
+        std::fs::write(dir.join("Broken.wzl"), b"not a library").unwrap();
+        std::fs::write(dir.join("readme.txt"), b"").unwrap();
+
+        let reports = scan(&[dir.to_path_buf()]);
+        let names: Vec<_> = reports
+            .iter()
+            .map(|r| r.path.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(names, ["Broken.wzl", "Hum.Lib", "Weapon.Lib"]);
+
+        assert!(!reports[0].is_ok());
+        assert!(reports[1].is_ok());
+        assert_eq!(reports[1].frames, 2);
+        assert_eq!(reports[2].problems, ["第 1 帧的数据被截断"]);
+    }
+}
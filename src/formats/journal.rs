@@ -0,0 +1,344 @@
+//! 崩溃恢复日志
+//!
+//! 有未保存的修改时，编辑器定期把修改写入缓存目录中的恢复日志：每一帧对应已保存文件
+//! 中的哪一帧、被修改或新追加的帧的完整数据、回收站（撤销删除用）和锁定的帧。程序崩溃后
+//! 下次启动时可以重新打开原库并按日志恢复这些修改；保存或正常关闭后删除日志。
+//!
+//! 日志记下了写入时库文件的大小和修改时间，库文件之后被改动过时拒绝恢复，
+//! 以免把修改套到另一个版本的库上。
+
+use crate::error::{LibraryError, Result};
+use crate::formats::atomic_file;
+use crate::formats::mlibrary_v2::{MImage, MLibraryV2};
+use crate::formats::{limits, remote};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::fs::File;
+use std::io::{BufReader, Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 日志文件标识
+const MAGIC: &[u8; 4] = b"MJNL";
+
+/// 日志格式版本
+const VERSION: u32 = 1;
+
+/// 日志文件的扩展名
+pub const JOURNAL_EXTENSION: &str = "journal";
+
+/// 恢复日志所在的目录（缓存目录下的 `journal`）
+pub fn journal_dir() -> PathBuf {
+    remote::cache_root().join("journal")
+}
+
+/// 库在 `dir` 中对应的恢复日志文件：`<库名>-<路径哈希>.journal`
+pub fn journal_path(dir: &Path, library: &Path) -> PathBuf {
+    let library = library
+        .canonicalize()
+        .unwrap_or_else(|_| library.to_path_buf());
+    let name = library.file_stem().unwrap_or_default().to_string_lossy();
+    let hash = fnv1a(library.as_os_str().as_encoded_bytes());
+    dir.join(format!("{}-{:016x}.{}", name, hash, JOURNAL_EXTENSION))
+}
+
+/// FNV-1a 哈希（结果不随编译器版本变化，日志文件名在升级后保持不变）
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// 库文件的大小和修改时间，用于判断写入日志后库文件是否被改动
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileStamp {
+    /// 文件大小
+    pub len: u64,
+    /// 修改时间（UNIX 纪元起的纳秒数）
+    pub modified: u64,
+}
+
+impl FileStamp {
+    /// 读取文件当前的大小和修改时间
+    pub fn of(path: &Path) -> Result<Self> {
+        let metadata = std::fs::metadata(path)?;
+        let modified = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64);
+        Ok(Self {
+            len: metadata.len(),
+            modified,
+        })
+    }
+}
+
+/// 日志中的一帧
+#[derive(Debug, Clone)]
+pub struct JournalFrame {
+    /// 在已保存文件中的索引（新追加的帧为 `None`）
+    pub source: Option<usize>,
+    /// 被修改或新追加的帧的数据；未修改的帧为 `None`，恢复时从库文件读取
+    pub image: Option<MImage>,
+}
+
+/// 恢复日志
+#[derive(Debug, Clone)]
+pub struct Journal {
+    /// 库文件（规范化后的路径）
+    pub library: PathBuf,
+    /// 写入日志时库文件的大小和修改时间
+    pub stamp: FileStamp,
+    /// 已保存文件中的帧数
+    pub saved_count: usize,
+    /// 当前的每一帧
+    pub frames: Vec<JournalFrame>,
+    /// 回收站中的帧（删除时所在的索引和帧），按删除顺序排列
+    pub trash: Vec<(usize, JournalFrame)>,
+    /// 锁定的帧
+    pub locked: Vec<usize>,
+}
+
+impl Journal {
+    /// 被修改或新追加的帧数
+    pub fn edited_frames(&self) -> usize {
+        self.frames
+            .iter()
+            .filter(|frame| frame.image.is_some())
+            .count()
+    }
+
+    /// 写入日志文件（先写临时文件再替换，写到一半崩溃时保留上一次的日志）
+    pub fn write(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut data = Vec::new();
+        data.write_all(MAGIC)?;
+        data.write_u32::<LittleEndian>(VERSION)?;
+        write_path(&mut data, &self.library)?;
+        data.write_u64::<LittleEndian>(self.stamp.len)?;
+        data.write_u64::<LittleEndian>(self.stamp.modified)?;
+        data.write_u32::<LittleEndian>(self.saved_count as u32)?;
+
+        data.write_u32::<LittleEndian>(self.frames.len() as u32)?;
+        for frame in &self.frames {
+            write_frame(&mut data, frame)?;
+        }
+        data.write_u32::<LittleEndian>(self.trash.len() as u32)?;
+        for (index, frame) in &self.trash {
+            data.write_u32::<LittleEndian>(*index as u32)?;
+            write_frame(&mut data, frame)?;
+        }
+        data.write_u32::<LittleEndian>(self.locked.len() as u32)?;
+        for &index in &self.locked {
+            data.write_u32::<LittleEndian>(index as u32)?;
+        }
+
+        atomic_file::write(path, &data)?;
+        tracing::debug!(
+            "已写入恢复日志: {:?}（{} 帧有修改）",
+            path,
+            self.edited_frames()
+        );
+        Ok(())
+    }
+
+    /// 读取日志文件
+    pub fn read(path: &Path) -> Result<Self> {
+        let mut reader = Cursor::new(std::fs::read(path)?);
+        let library = read_header(&mut reader)?;
+        let stamp = FileStamp {
+            len: reader.read_u64::<LittleEndian>()?,
+            modified: reader.read_u64::<LittleEndian>()?,
+        };
+        let saved_count = reader.read_u32::<LittleEndian>()? as usize;
+
+        let count = limits::check_count(reader.read_u32::<LittleEndian>()? as usize)?;
+        let frames = (0..count)
+            .map(|_| read_frame(&mut reader))
+            .collect::<Result<Vec<_>>>()?;
+        let count = limits::check_count(reader.read_u32::<LittleEndian>()? as usize)?;
+        let mut trash = Vec::with_capacity(count);
+        for _ in 0..count {
+            let index = reader.read_u32::<LittleEndian>()? as usize;
+            trash.push((index, read_frame(&mut reader)?));
+        }
+        let count = limits::check_count(reader.read_u32::<LittleEndian>()? as usize)?;
+        let locked = (0..count)
+            .map(|_| Ok(reader.read_u32::<LittleEndian>()? as usize))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            library,
+            stamp,
+            saved_count,
+            frames,
+            trash,
+            locked,
+        })
+    }
+}
+
+/// 缓存目录中尚未处理的恢复日志
+#[derive(Debug, Clone)]
+pub struct PendingJournal {
+    /// 日志文件
+    pub path: PathBuf,
+    /// 日志对应的库文件
+    pub library: PathBuf,
+    /// 日志最后写入的时间
+    pub saved_at: SystemTime,
+}
+
+/// 列出 `dir` 中的恢复日志（最近写入的在前），只读取日志开头的库路径
+pub fn pending(dir: &Path) -> Vec<PendingJournal> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut journals: Vec<_> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case(JOURNAL_EXTENSION))
+        })
+        .filter_map(|path| {
+            let library = File::open(&path)
+                .map_err(LibraryError::from)
+                .and_then(|file| read_header(&mut BufReader::new(file)));
+            match library {
+                Ok(library) => Some(PendingJournal {
+                    saved_at: std::fs::metadata(&path)
+                        .and_then(|m| m.modified())
+                        .unwrap_or(UNIX_EPOCH),
+                    path,
+                    library,
+                }),
+                Err(e) => {
+                    tracing::warn!("忽略无法读取的恢复日志 {:?}: {}", path, e);
+                    None
+                }
+            }
+        })
+        .collect();
+    journals.sort_by_key(|journal| std::cmp::Reverse(journal.saved_at));
+    journals
+}
+
+/// 删除库的恢复日志（不存在时忽略）
+pub fn discard(path: &Path) {
+    match std::fs::remove_file(path) {
+        Ok(()) => tracing::debug!("已删除恢复日志: {:?}", path),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => tracing::warn!("删除恢复日志 {:?} 失败: {}", path, e),
+    }
+}
+
+/// 检查文件标识和版本，返回库路径
+fn read_header(reader: &mut impl Read) -> Result<PathBuf> {
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+    let version = reader.read_u32::<LittleEndian>()?;
+    if &magic != MAGIC || version != VERSION {
+        return Err(LibraryError::InvalidFormat);
+    }
+    let len = limits::check_length(reader.read_u32::<LittleEndian>()? as i64, "日志路径")?;
+    let mut path = vec![0u8; len];
+    reader.read_exact(&mut path)?;
+    Ok(PathBuf::from(String::from_utf8_lossy(&path).into_owned()))
+}
+
+fn write_path(writer: &mut impl Write, path: &Path) -> Result<()> {
+    let path = path.to_string_lossy();
+    writer.write_u32::<LittleEndian>(path.len() as u32)?;
+    writer.write_all(path.as_bytes())?;
+    Ok(())
+}
+
+fn write_frame(writer: &mut Vec<u8>, frame: &JournalFrame) -> Result<()> {
+    writer.write_i64::<LittleEndian>(frame.source.map_or(-1, |source| source as i64))?;
+    match &frame.image {
+        Some(image) => {
+            writer.write_u8(1)?;
+            image.save(writer)?;
+        }
+        None => writer.write_u8(0)?,
+    }
+    Ok(())
+}
+
+fn read_frame(reader: &mut Cursor<Vec<u8>>) -> Result<JournalFrame> {
+    let source = usize::try_from(reader.read_i64::<LittleEndian>()?).ok();
+    let image = match reader.read_u8()? {
+        0 => None,
+        _ => Some(MLibraryV2::read_mimage(reader)?),
+    };
+    Ok(JournalFrame { source, image })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::LibraryLoader;
+
+    #[test]
+    fn test_restore_pending_edits() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let mut library = MLibraryV2::new(dir.join("Hum")).unwrap();
+        for shade in [10, 20, 30] {
+            let frame = image::RgbaImage::from_pixel(2, 2, image::Rgba([shade, 0, 0, 255]));
+            library.add_image(&MImage::from_image(&frame, 0, 0));
+        }
+        library.save().unwrap();
+        let path = dir.join("Hum.Lib");
+
+        // 删除第 0 帧、修改第 1 帧、追加一帧、锁定第 0 帧
+        let (_, mut loader) = LibraryLoader::load(&path).unwrap();
+        let blue = image::RgbaImage::from_pixel(3, 2, image::Rgba([0, 0, 99, 255]));
+        loader.remove_image(0).unwrap();
+        loader
+            .put_frames(std::slice::from_ref(&blue), Some(1), Default::default())
+            .unwrap();
+        loader
+            .put_frames(&[blue], None, Default::default())
+            .unwrap();
+        loader.set_locked(&[0], true).unwrap();
+        let journal_file = journal_path(dir, &path);
+        loader
+            .journal()
+            .unwrap()
+            .unwrap()
+            .write(&journal_file)
+            .unwrap();
+        drop(loader);
+
+        let found = pending(dir);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].library, path.canonicalize().unwrap());
+
+        let (_, mut restored) = LibraryLoader::load(&path).unwrap();
+        restored
+            .apply_journal(Journal::read(&journal_file).unwrap())
+            .unwrap();
+        assert!(restored.is_modified());
+        assert_eq!(restored.image_count(), 3);
+        assert_eq!(restored.frame_origin(0), Some(1));
+        assert_eq!(restored.frame_origin(1), None);
+        assert_eq!(restored.get_frame(1).unwrap().unwrap().width(), 3);
+        assert!(restored.is_locked(0));
+        assert_eq!(restored.undo_remove().unwrap(), Some(0));
+        assert_eq!(
+            restored.get_frame(0).unwrap().unwrap().get_pixel(0, 0)[0],
+            10
+        );
+
+        // 库文件在写入日志后被改动时拒绝恢复
+        restored.save().unwrap();
+        let (_, mut changed) = LibraryLoader::load(&path).unwrap();
+        let journal = Journal::read(&journal_file).unwrap();
+        assert!(changed.apply_journal(journal).is_err());
+        discard(&journal_file);
+        assert!(pending(dir).is_empty());
+    }
+}
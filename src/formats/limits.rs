@@ -0,0 +1,108 @@
+//! 解析文件时的分配上限
+//!
+//! 帧头部和索引头部中的长度、帧数直接决定读取时分配多少内存。损坏或恶意构造的
+//! 文件可能声明负数或几 GB 的长度，不加检查就会分配失败使整个进程中止。读取器在
+//! 分配之前用 [`check_length`]、[`check_count`] 检查，解压用 [`read_to_end`] 限制
+//! 输出大小，超出 [`ParseLimits`] 时返回 [`LibraryError::InvalidLength`]，只有这一帧
+//! （或这个库）读取失败。上限对整个进程生效，默认值足够容纳正常的客户端资源，
+//! 可用 [`set_limits`] 调整。
+
+use crate::error::{LibraryError, Result};
+use std::io::Read;
+use std::sync::RwLock;
+
+/// 解析时的分配上限
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseLimits {
+    /// 单帧数据（压缩前或解压后）的最大字节数
+    pub max_frame_bytes: usize,
+    /// 单个库的最大帧数
+    pub max_frames: usize,
+}
+
+impl ParseLimits {
+    /// 默认上限：单帧 64 MiB，每库 100 万帧
+    pub const DEFAULT: Self = Self {
+        max_frame_bytes: 64 << 20,
+        max_frames: 1 << 20,
+    };
+}
+
+impl Default for ParseLimits {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+static LIMITS: RwLock<ParseLimits> = RwLock::new(ParseLimits::DEFAULT);
+
+/// 当前生效的上限
+pub fn limits() -> ParseLimits {
+    *LIMITS.read().unwrap_or_else(|e| e.into_inner())
+}
+
+/// 设置上限，之后的读取生效
+pub fn set_limits(limits: ParseLimits) {
+    *LIMITS.write().unwrap_or_else(|e| e.into_inner()) = limits;
+}
+
+/// 检查文件中声明的数据长度，返回可以安全分配的字节数
+pub fn check_length(length: i64, what: &str) -> Result<usize> {
+    let max = limits().max_frame_bytes;
+    match usize::try_from(length) {
+        Ok(length) if length <= max => Ok(length),
+        Ok(_) => Err(LibraryError::InvalidLength(format!(
+            "{}长度 {} 超过上限 {}",
+            what, length, max
+        ))),
+        Err(_) => Err(LibraryError::InvalidLength(format!(
+            "{}长度为负数 ({})",
+            what, length
+        ))),
+    }
+}
+
+/// 检查文件中声明的帧数
+pub fn check_count(count: usize) -> Result<usize> {
+    let max = limits().max_frames;
+    if count > max {
+        return Err(LibraryError::InvalidLength(format!(
+            "帧数 {} 超过上限 {}",
+            count, max
+        )));
+    }
+    Ok(count)
+}
+
+/// 读取（解压）全部数据，超过单帧上限时报错，避免压缩炸弹耗尽内存
+pub fn read_to_end(reader: impl Read, what: &str) -> Result<Vec<u8>> {
+    let max = limits().max_frame_bytes;
+    let mut data = Vec::new();
+    reader.take(max as u64 + 1).read_to_end(&mut data)?;
+    if data.len() > max {
+        return Err(LibraryError::InvalidLength(format!(
+            "{}解压后超过上限 {}",
+            what, max
+        )));
+    }
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_length() {
+        assert_eq!(check_length(16, "数据").unwrap(), 16);
+        assert!(matches!(
+            check_length(-1, "数据"),
+            Err(LibraryError::InvalidLength(message)) if message.contains("负数")
+        ));
+        let max = limits().max_frame_bytes as i64;
+        assert!(check_length(max, "数据").is_ok());
+        assert!(check_length(max + 1, "数据").is_err());
+        assert!(check_count(limits().max_frames + 1).is_err());
+        assert_eq!(read_to_end(&[1u8, 2, 3][..], "数据").unwrap(), [1, 2, 3]);
+    }
+}
@@ -0,0 +1,177 @@
+//! 帧锁定（写保护）
+//!
+//! 多人协作编辑同一个库时，把已定稿的帧标记为锁定：替换、删除、清空等修改
+//! 操作会跳过这些帧并给出警告。锁定记录保存在库文件旁的 `<库名>.locks.json`
+//! 中，可以随库一起提交和分发。
+
+use crate::error::Result;
+use crate::formats::companion_path;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+/// 锁定记录文件的扩展名（拼接在库的基础路径后）
+pub const LOCKS_EXTENSION: &str = ".locks.json";
+
+/// 锁定的帧
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FrameLocks {
+    /// 锁定的帧索引
+    frames: BTreeSet<usize>,
+}
+
+impl FrameLocks {
+    /// 库（基础路径，不含扩展名）对应的锁定记录文件
+    pub fn path(base: &Path) -> PathBuf {
+        companion_path(base, LOCKS_EXTENSION)
+    }
+
+    /// 读取锁定记录，文件不存在时返回空记录
+    pub fn load(base: &Path) -> Result<Self> {
+        let path = Self::path(base);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let locks: Self = serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+        tracing::debug!("读取锁定记录 {:?}: {} 帧", path, locks.len());
+        Ok(locks)
+    }
+
+    /// 写出锁定记录，没有锁定的帧时删除记录文件
+    pub fn save(&self, base: &Path) -> Result<()> {
+        let path = Self::path(base);
+        if self.is_empty() {
+            if path.exists() {
+                std::fs::remove_file(&path)?;
+            }
+            return Ok(());
+        }
+        std::fs::write(&path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// 第 `index` 帧是否锁定
+    pub fn is_locked(&self, index: usize) -> bool {
+        self.frames.contains(&index)
+    }
+
+    /// 锁定或解锁一帧，返回状态是否改变
+    pub fn set(&mut self, index: usize, locked: bool) -> bool {
+        if locked {
+            self.frames.insert(index)
+        } else {
+            self.frames.remove(&index)
+        }
+    }
+
+    /// 锁定的帧索引（从小到大）
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.frames.iter().copied()
+    }
+
+    /// 锁定的帧数
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// 是否没有锁定的帧
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// 把索引分成可修改的和锁定的两组（各自保持原顺序）
+    pub fn split(&self, indices: &[usize]) -> (Vec<usize>, Vec<usize>) {
+        indices.iter().partition(|&&index| !self.is_locked(index))
+    }
+
+    /// 删除第 `index` 帧后，其后的锁定前移
+    pub fn removed(&mut self, index: usize) {
+        self.frames = self
+            .iter()
+            .filter(|&i| i != index)
+            .map(|i| if i > index { i - 1 } else { i })
+            .collect();
+    }
+
+    /// 在 `index` 处插入一帧后，其后的锁定后移
+    pub fn inserted(&mut self, index: usize) {
+        self.frames = self
+            .iter()
+            .map(|i| if i >= index { i + 1 } else { i })
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locks_follow_removal_and_roundtrip() {
+        let mut locks = FrameLocks::default();
+        assert!(locks.set(2, true));
+        assert!(locks.set(5, true));
+        assert!(!locks.set(5, true));
+
+        let (open, locked) = locks.split(&[1, 2, 3, 5]);
+        assert_eq!((open, locked), (vec![1, 3], vec![2, 5]));
+
+        // 删除第 3 帧后第 5 帧前移，恢复后回到原处
+        locks.removed(3);
+        assert_eq!(locks.iter().collect::<Vec<_>>(), vec![2, 4]);
+        locks.inserted(3);
+        assert_eq!(locks.iter().collect::<Vec<_>>(), vec![2, 5]);
+
+        let temp = tempfile::tempdir().unwrap();
+        let base = temp.path().join("frame_locks_roundtrip");
+        locks.save(&base).unwrap();
+        assert_eq!(FrameLocks::load(&base).unwrap(), locks);
+
+        // 全部解锁后删除记录文件
+        locks.set(2, false);
+        locks.set(5, false);
+        locks.save(&base).unwrap();
+        assert!(!FrameLocks::path(&base).exists());
+        assert!(FrameLocks::load(&base).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_loader_skips_locked_frames() {
+        use crate::error::LibraryError;
+        use crate::formats::mlibrary_v2::MImage;
+        use crate::formats::{LibraryLoader, MLibraryV2};
+
+        let pixel = image::RgbaImage::from_pixel(1, 1, image::Rgba([9, 9, 9, 255]));
+        let temp = tempfile::tempdir().unwrap();
+        let base = temp.path().join("frame_locks_loader");
+        let mut library = MLibraryV2::new(base.clone()).unwrap();
+        for _ in 0..4 {
+            library.add_image(&MImage::from_image(&pixel, 0, 0));
+        }
+        let (_, mut loader) = LibraryLoader::from_v2(library, "locks.Lib");
+        assert_eq!(loader.set_locked(&[1], true).unwrap(), 1);
+
+        let frame = MImage::from_image(&pixel, 1, 1);
+        assert!(matches!(
+            loader.replace_image(1, &frame),
+            Err(LibraryError::FrameLocked(1))
+        ));
+        assert_eq!(loader.shift_offsets(&[0, 1], 1, 1).unwrap(), vec![0]);
+
+        // 删除时跳过锁定帧，锁定随帧前移
+        assert_eq!(loader.remove_images(&[0, 1, 2]).unwrap(), 2);
+        assert_eq!(loader.image_count(), 2);
+        assert!(loader.is_locked(0));
+        assert!(matches!(
+            loader.normalize_frames(0..2),
+            Err(LibraryError::FrameLocked(0))
+        ));
+
+        loader.undo_remove().unwrap();
+        loader.undo_remove().unwrap();
+        assert!(loader.is_locked(1));
+
+        loader.set_locked(&[1], false).unwrap();
+        FrameLocks::default().save(&base).unwrap();
+    }
+}
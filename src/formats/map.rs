@@ -0,0 +1,450 @@
+//! 传奇2 地图文件（`.map`）
+//!
+//! 文件结构：52 字节文件头（开头为 u16 宽、u16 高，单位为格），随后按列优先
+//! （先 x 后 y）存放每格 12 字节的记录：
+//!
+//! | 偏移 | 类型 | 说明 |
+//! |------|------|------|
+//! | 0  | u16 | 地面大块（Tiles，96×64，只在偶数行列出现），最高位为不可行走 |
+//! | 2  | u16 | 地面小块（SmTiles，48×32） |
+//! | 4  | u16 | 物件（Objects），最高位为不可行走 |
+//! | 6  | u8  | 门编号（最高位表示有门） |
+//! | 7  | u8  | 门状态偏移 |
+//! | 8  | u8  | 物件动画帧数（最高位为混合绘制） |
+//! | 9  | u8  | 物件动画间隔 |
+//! | 10 | u8  | 物件所在的库：0 为 Objects，n 为 Objects{n+1} |
+//! | 11 | u8  | 光源 |
+//!
+//! 图像编号从 1 开始，0 表示该层为空。渲染时使用已支持的库格式读取
+//! Tiles / SmTiles / Objects 库，按客户端的顺序绘制：先地面大块、地面小块和
+//! 地面形状的物件，再逐行绘制底边对齐到格子底部的物件。
+
+use crate::error::{LibraryError, Result};
+use crate::formats::{LibraryLoader, find_companion};
+use crate::image::placement::{CELL_HEIGHT, CELL_WIDTH};
+use image::RgbaImage;
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// 地图文件的扩展名
+pub const MAP_EXTENSION: &str = "map";
+
+/// 文件头字节数
+pub const HEADER_SIZE: usize = 52;
+
+/// 每格记录的字节数
+pub const CELL_SIZE: usize = 12;
+
+/// 一次最多渲染的格数（每边），避免整张大地图占用过多内存
+pub const MAX_RENDER_CELLS: u32 = 128;
+
+/// 按库名查找地图资源库时依次尝试的扩展名
+const LIBRARY_EXTENSIONS: [&str; 3] = [".wil", ".wzl", ".Lib"];
+
+/// 物件可能比格子高，向区域下方多读的行数（这些行中的高物件会伸进区域）
+const OBJECT_OVERSCAN_ROWS: u32 = 12;
+
+/// 缓存的解码帧超过此数量时清空缓存（平移查看大地图时内存不会无限增长）
+const FRAME_CACHE_LIMIT: usize = 4096;
+
+/// 不可行走标记位
+const BLOCKED: u16 = 0x8000;
+
+/// 地图上的一格
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MapCell {
+    /// 地面大块编号（含不可行走标记）
+    pub back: u16,
+    /// 地面小块编号
+    pub middle: u16,
+    /// 物件编号（含不可行走标记）
+    pub front: u16,
+    /// 门编号
+    pub door_index: u8,
+    /// 门状态偏移
+    pub door_offset: u8,
+    /// 物件动画帧数
+    pub animation_frames: u8,
+    /// 物件动画间隔
+    pub animation_tick: u8,
+    /// 物件所在库的序号
+    pub area: u8,
+    /// 光源
+    pub light: u8,
+}
+
+impl MapCell {
+    /// 由 12 字节的记录解析
+    fn parse(bytes: &[u8]) -> Self {
+        let u16_at = |i: usize| u16::from_le_bytes([bytes[i], bytes[i + 1]]);
+        Self {
+            back: u16_at(0),
+            middle: u16_at(2),
+            front: u16_at(4),
+            door_index: bytes[6],
+            door_offset: bytes[7],
+            animation_frames: bytes[8],
+            animation_tick: bytes[9],
+            area: bytes[10],
+            light: bytes[11],
+        }
+    }
+
+    /// 地面大块在 Tiles 库中的帧索引
+    pub fn back_index(&self) -> Option<usize> {
+        ((self.back & !BLOCKED) as usize).checked_sub(1)
+    }
+
+    /// 地面小块在 SmTiles 库中的帧索引
+    pub fn middle_index(&self) -> Option<usize> {
+        ((self.middle & !BLOCKED) as usize).checked_sub(1)
+    }
+
+    /// 物件在 Objects 库中的帧索引
+    pub fn front_index(&self) -> Option<usize> {
+        ((self.front & !BLOCKED) as usize).checked_sub(1)
+    }
+
+    /// 是否可以行走
+    pub fn walkable(&self) -> bool {
+        self.back & BLOCKED == 0 && self.front & BLOCKED == 0
+    }
+}
+
+/// 地图文件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MapFile {
+    /// 宽（格）
+    pub width: u32,
+    /// 高（格）
+    pub height: u32,
+    /// 按列优先存放的格子
+    cells: Vec<MapCell>,
+}
+
+impl MapFile {
+    /// 读取地图文件
+    pub fn load(path: &Path) -> Result<Self> {
+        let map = Self::parse(&std::fs::read(path)?)?;
+        tracing::debug!("读取地图 {:?}: {}x{} 格", path, map.width, map.height);
+        Ok(map)
+    }
+
+    /// 由文件内容解析
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        if data.len() < HEADER_SIZE {
+            return Err(LibraryError::InvalidFormat);
+        }
+        let width = u16::from_le_bytes([data[0], data[1]]) as u32;
+        let height = u16::from_le_bytes([data[2], data[3]]) as u32;
+        let count = width as usize * height as usize;
+        let body = &data[HEADER_SIZE..];
+        if count == 0 || body.len() < count * CELL_SIZE {
+            return Err(LibraryError::ParseError(format!(
+                "地图 {}x{} 需要 {} 字节的格子数据，文件中只有 {} 字节（可能是不支持的地图版本）",
+                width,
+                height,
+                count * CELL_SIZE,
+                body.len()
+            )));
+        }
+        let cells = body
+            .chunks_exact(CELL_SIZE)
+            .take(count)
+            .map(MapCell::parse)
+            .collect();
+        Ok(Self {
+            width,
+            height,
+            cells,
+        })
+    }
+
+    /// 取一格，超出地图时返回 `None`
+    pub fn cell(&self, x: u32, y: u32) -> Option<&MapCell> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        self.cells.get((x * self.height + y) as usize)
+    }
+
+    /// 地图用到的物件库序号
+    pub fn object_areas(&self) -> BTreeSet<u8> {
+        self.cells
+            .iter()
+            .filter(|cell| cell.front_index().is_some())
+            .map(|cell| cell.area)
+            .collect()
+    }
+
+    /// 不可行走的格数
+    pub fn blocked_count(&self) -> usize {
+        self.cells.iter().filter(|cell| !cell.walkable()).count()
+    }
+}
+
+/// 物件库序号对应的库名：0 为 Objects，n 为 Objects{n+1}
+pub fn objects_library_name(area: u8) -> String {
+    match area {
+        0 => "Objects".to_string(),
+        n => format!("Objects{}", n as u32 + 1),
+    }
+}
+
+/// 地图的默认资源目录：地图所在目录旁的 `Data`（客户端的 `Map` 与 `Data` 并列），
+/// 不存在时为地图所在目录
+pub fn default_data_dir(map_path: &Path) -> PathBuf {
+    let dir = map_path.parent().unwrap_or(Path::new(""));
+    dir.parent()
+        .into_iter()
+        .flat_map(|parent| ["Data", "data"].map(|name| parent.join(name)))
+        .find(|data| data.is_dir())
+        .unwrap_or_else(|| dir.to_path_buf())
+}
+
+/// 地图图层
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Layer {
+    Tiles,
+    SmallTiles,
+    Objects(u8),
+}
+
+/// 渲染地图所需的资源库（按需打开，缺少的库对应图层不绘制）
+pub struct MapLibraries {
+    dir: PathBuf,
+    loaders: HashMap<Layer, Option<LibraryLoader>>,
+    frames: HashMap<(Layer, usize), Option<Arc<RgbaImage>>>,
+}
+
+impl MapLibraries {
+    /// 在资源目录中查找地图资源库
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            loaders: HashMap::new(),
+            frames: HashMap::new(),
+        }
+    }
+
+    /// 资源目录
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// 资源目录中名为 `name` 的库文件（扩展名和大小写不限）
+    pub fn find(&self, name: &str) -> Option<PathBuf> {
+        let base = self.dir.join(name);
+        LIBRARY_EXTENSIONS
+            .iter()
+            .map(|ext| find_companion(&base, ext))
+            .find(|path| path.is_file())
+    }
+
+    /// 地图需要、但资源目录中找不到的库名
+    pub fn missing(&self, map: &MapFile) -> Vec<String> {
+        let mut names = vec!["Tiles".to_string(), "SmTiles".to_string()];
+        names.extend(map.object_areas().into_iter().map(objects_library_name));
+        names
+            .into_iter()
+            .filter(|name| self.find(name).is_none())
+            .collect()
+    }
+
+    /// 取某层的帧（解码结果缓存），库不存在或帧为空时返回 `None`
+    fn frame(&mut self, layer: Layer, index: usize) -> Option<&RgbaImage> {
+        if !self.frames.contains_key(&(layer, index)) {
+            let frame = self.decode(layer, index);
+            self.frames.insert((layer, index), frame);
+        }
+        self.frames[&(layer, index)].as_deref()
+    }
+
+    fn decode(&mut self, layer: Layer, index: usize) -> Option<Arc<RgbaImage>> {
+        if !self.loaders.contains_key(&layer) {
+            let name = match layer {
+                Layer::Tiles => "Tiles".to_string(),
+                Layer::SmallTiles => "SmTiles".to_string(),
+                Layer::Objects(area) => objects_library_name(area),
+            };
+            let loader = self.find(&name).and_then(|path| {
+                LibraryLoader::load(&path)
+                    .map(|(_, loader)| loader)
+                    .map_err(|e| tracing::warn!("打开地图资源库 {:?} 失败: {:?}", path, e))
+                    .ok()
+            });
+            if loader.is_none() {
+                tracing::warn!("资源目录 {:?} 中没有可用的 {}", self.dir, name);
+            }
+            self.loaders.insert(layer, loader);
+        }
+        let loader = self.loaders.get_mut(&layer)?.as_mut()?;
+        loader
+            .get_frame(index)
+            .map_err(|e| tracing::debug!("地图资源 {:?} 第 {} 帧解码失败: {:?}", layer, index, e))
+            .ok()
+            .flatten()
+    }
+}
+
+/// 渲染区域（单位为格）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MapRegion {
+    /// 左上角 x
+    pub x: u32,
+    /// 左上角 y
+    pub y: u32,
+    /// 宽
+    pub width: u32,
+    /// 高
+    pub height: u32,
+}
+
+impl MapRegion {
+    /// 收缩到地图范围内，宽高不超过 [`MAX_RENDER_CELLS`]
+    pub fn clamp_to(self, map: &MapFile) -> Self {
+        let x = self.x.min(map.width.saturating_sub(1));
+        let y = self.y.min(map.height.saturating_sub(1));
+        Self {
+            x,
+            y,
+            width: self.width.min(map.width - x).clamp(1, MAX_RENDER_CELLS),
+            height: self.height.min(map.height - y).clamp(1, MAX_RENDER_CELLS),
+        }
+    }
+}
+
+/// 渲染地图的一块区域，返回透明底的画布
+///
+/// 区域会先收缩到地图范围内（见 [`MapRegion::clamp_to`]）。
+pub fn render(map: &MapFile, libraries: &mut MapLibraries, region: MapRegion) -> RgbaImage {
+    let region = region.clamp_to(map);
+    if libraries.frames.len() > FRAME_CACHE_LIMIT {
+        libraries.frames.clear();
+    }
+    let (cw, ch) = (CELL_WIDTH as i64, CELL_HEIGHT as i64);
+    let mut canvas = RgbaImage::new(region.width * CELL_WIDTH, region.height * CELL_HEIGHT);
+    let position = |x: u32, y: u32| {
+        (
+            (x as i64 - region.x as i64) * cw,
+            (y as i64 - region.y as i64) * ch,
+        )
+    };
+    let is_floor = |image: &RgbaImage| {
+        let size = image.dimensions();
+        size == (CELL_WIDTH, CELL_HEIGHT) || size == (CELL_WIDTH * 2, CELL_HEIGHT * 2)
+    };
+
+    // 地面：大块从区域左上方的偶数格开始，覆盖 2×2 格
+    let xs = region.x - region.x % 2..region.x + region.width;
+    let ys = region.y - region.y % 2..region.y + region.height;
+    for x in xs.clone().step_by(2) {
+        for y in ys.clone().step_by(2) {
+            let Some(index) = map.cell(x, y).and_then(MapCell::back_index) else {
+                continue;
+            };
+            if let Some(tile) = libraries.frame(Layer::Tiles, index) {
+                let (px, py) = position(x, y);
+                image::imageops::overlay(&mut canvas, tile, px, py);
+            }
+        }
+    }
+    let cells = |xs: std::ops::Range<u32>, ys: std::ops::Range<u32>| {
+        ys.flat_map(move |y| xs.clone().map(move |x| (x, y)))
+    };
+    for (x, y) in cells(xs.clone(), ys.clone()) {
+        let Some(cell) = map.cell(x, y) else {
+            continue;
+        };
+        let (px, py) = position(x, y);
+        if let Some(index) = cell.middle_index()
+            && let Some(tile) = libraries.frame(Layer::SmallTiles, index)
+        {
+            image::imageops::overlay(&mut canvas, tile, px, py);
+        }
+        if let Some(index) = cell.front_index()
+            && let Some(object) = libraries.frame(Layer::Objects(cell.area), index)
+            && is_floor(object)
+        {
+            image::imageops::overlay(&mut canvas, object, px, py);
+        }
+    }
+
+    // 物件：逐行绘制，底边对齐到格子底部，下方几行的高物件会伸进区域
+    let rows = region.y..(region.y + region.height + OBJECT_OVERSCAN_ROWS).min(map.height);
+    for (x, y) in cells(region.x..region.x + region.width, rows) {
+        let Some(cell) = map.cell(x, y) else {
+            continue;
+        };
+        if let Some(index) = cell.front_index()
+            && let Some(object) = libraries.frame(Layer::Objects(cell.area), index)
+            && !is_floor(object)
+        {
+            let (px, py) = position(x, y);
+            image::imageops::overlay(&mut canvas, object, px, py + ch - object.height() as i64);
+        }
+    }
+    canvas
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::mlibrary_v2::{MImage, MLibraryV2};
+    use image::Rgba;
+
+    #[test]
+    fn test_parse_and_render_map() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let library = |name: &str, frame: RgbaImage| {
+            let mut library = MLibraryV2::new(dir.join(name)).unwrap();
+            library.add_image(&MImage::from_image(&frame, 0, 0));
+            library.save().unwrap();
+        };
+        let red = Rgba([200, 0, 0, 255]);
+        let blue = Rgba([0, 0, 200, 255]);
+        library("Tiles", RgbaImage::from_pixel(96, 64, red));
+        library("Objects2", RgbaImage::from_pixel(20, 80, blue));
+
+        // 2×2 格：(0,0) 地面大块 1，(1,1) 第二个物件库的物件 1 且不可行走
+        let mut data = vec![0u8; HEADER_SIZE];
+        data[0] = 2;
+        data[2] = 2;
+        for (x, y) in [(0, 0), (0, 1), (1, 0), (1, 1)] {
+            let mut cell = [0u8; CELL_SIZE];
+            if (x, y) == (0, 0) {
+                cell[0] = 1;
+            }
+            if (x, y) == (1, 1) {
+                cell[4..6].copy_from_slice(&(1 | BLOCKED).to_le_bytes());
+                cell[10] = 1;
+            }
+            data.extend_from_slice(&cell);
+        }
+        let map = MapFile::parse(&data).unwrap();
+        assert_eq!((map.width, map.height), (2, 2));
+        assert_eq!(map.cell(1, 1).unwrap().front_index(), Some(0));
+        assert!(!map.cell(1, 1).unwrap().walkable());
+        assert_eq!(map.blocked_count(), 1);
+        assert_eq!(map.object_areas(), BTreeSet::from([1]));
+        assert!(MapFile::parse(&data[..data.len() - 1]).is_err());
+
+        let mut libraries = MapLibraries::new(dir);
+        assert_eq!(libraries.missing(&map), vec!["SmTiles".to_string()]);
+        let region = MapRegion {
+            x: 0,
+            y: 0,
+            width: 10,
+            height: 10,
+        };
+        let canvas = render(&map, &mut libraries, region);
+        assert_eq!(canvas.dimensions(), (96, 64));
+        assert_eq!(*canvas.get_pixel(10, 10), red);
+        // 物件底边对齐到 (1,1) 格底部，向上伸出格子
+        assert_eq!(*canvas.get_pixel(50, 63), blue);
+        assert_eq!(*canvas.get_pixel(50, 0), blue);
+        assert_eq!(*canvas.get_pixel(70, 63), red);
+    }
+}
@@ -0,0 +1,316 @@
+//! 帧元数据清单 (JSON)
+//!
+//! 记录每一帧的偏移、阴影和遮罩几何信息，使帧属性在 PNG 往返中不丢失。
+//! `export --metadata` 输出的旁路文件、`pack --metadata` 读取的文件以及
+//! ZIP 交换格式中的 `manifest.json` 使用同一结构。
+
+use crate::error::{LibraryError, Result};
+use crate::formats::mlibrary_v2::{EncodeOptions, MImage, MLibraryV2};
+use crate::formats::{LibraryLoader, ShadowInfo};
+use crate::progress::Progress;
+use image::RgbaImage;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Arc;
+
+/// 当前清单版本
+pub const MANIFEST_VERSION: u32 = 1;
+
+/// 帧元数据清单
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    /// 清单版本
+    pub version: u32,
+    /// 来源库格式名称
+    pub source_format: String,
+    /// 来源库文件名
+    pub source_file: String,
+    /// 调色板（BGRA，仅调色板格式）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub palette: Option<Vec<[u8; 4]>>,
+    /// 逐帧属性
+    pub frames: Vec<FrameEntry>,
+}
+
+/// 单帧属性
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FrameEntry {
+    /// 帧索引
+    pub index: usize,
+    /// 帧图像的相对路径（空帧为 `None`）
+    #[serde(default)]
+    pub image: Option<String>,
+    /// 宽度
+    pub width: i32,
+    /// 高度
+    pub height: i32,
+    /// X偏移
+    pub x: i16,
+    /// Y偏移
+    pub y: i16,
+    /// 阴影类型
+    #[serde(default)]
+    pub shadow: u8,
+    /// 阴影X偏移
+    #[serde(default)]
+    pub shadow_x: i16,
+    /// 阴影Y偏移
+    #[serde(default)]
+    pub shadow_y: i16,
+    /// 遮罩层
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mask: Option<MaskEntry>,
+}
+
+/// 遮罩层属性
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaskEntry {
+    /// 遮罩图像的相对路径
+    pub image: Option<String>,
+    /// 遮罩宽度
+    pub width: i16,
+    /// 遮罩高度
+    pub height: i16,
+    /// 遮罩X偏移
+    pub x: i16,
+    /// 遮罩Y偏移
+    pub y: i16,
+}
+
+impl Manifest {
+    /// 遍历已加载库的所有帧，生成清单
+    ///
+    /// 帧图像命名为 `{frame_prefix}00000.png`，遮罩为 `{mask_prefix}00000.png`，
+    /// 每张图像连同其名称交给 `sink` 写出。
+    pub fn collect<F>(
+        loader: &mut LibraryLoader,
+        frame_prefix: &str,
+        mask_prefix: &str,
+        sink: F,
+    ) -> Result<Self>
+    where
+        F: FnMut(&str, &RgbaImage) -> Result<()>,
+    {
+        Self::collect_with_progress(loader, frame_prefix, mask_prefix, sink, |_| {})
+    }
+
+    /// 同 [`collect`](Self::collect)，每处理完一帧以 [`Progress::FrameDecoded`] 报告进度
+    pub fn collect_with_progress<F>(
+        loader: &mut LibraryLoader,
+        frame_prefix: &str,
+        mask_prefix: &str,
+        mut sink: F,
+        mut progress: impl FnMut(Progress),
+    ) -> Result<Self>
+    where
+        F: FnMut(&str, &RgbaImage) -> Result<()>,
+    {
+        let info = loader
+            .info()
+            .cloned()
+            .ok_or_else(|| LibraryError::ParseError("导出元数据时异常：库未加载".to_string()))?;
+
+        let mut frames = Vec::with_capacity(info.image_count);
+        for index in 0..info.image_count {
+            let image_info = loader.get_image_info(index)?;
+            let mut entry = FrameEntry {
+                index,
+                width: image_info.width,
+                height: image_info.height,
+                x: image_info.x as i16,
+                y: image_info.y as i16,
+                ..Default::default()
+            };
+
+            match image_info.has_mask {
+                ShadowInfo::None => {}
+                ShadowInfo::Simple {
+                    shadow,
+                    shadow_x,
+                    shadow_y,
+                } => {
+                    entry.shadow = shadow;
+                    entry.shadow_x = shadow_x;
+                    entry.shadow_y = shadow_y;
+                }
+                ShadowInfo::Mask {
+                    shadow,
+                    shadow_x,
+                    shadow_y,
+                    mask_width,
+                    mask_height,
+                    mask_x,
+                    mask_y,
+                } => {
+                    entry.shadow = shadow;
+                    entry.shadow_x = shadow_x;
+                    entry.shadow_y = shadow_y;
+
+                    let mask_name = match loader.get_mask(index)? {
+                        Some(mask) => {
+                            let name = format!("{}{:05}.png", mask_prefix, index);
+                            sink(&name, &mask)?;
+                            Some(name)
+                        }
+                        None => None,
+                    };
+                    entry.mask = Some(MaskEntry {
+                        image: mask_name,
+                        width: mask_width,
+                        height: mask_height,
+                        x: mask_x,
+                        y: mask_y,
+                    });
+                }
+            }
+
+            if let Some(frame) = loader.get_frame(index)? {
+                let name = format!("{}{:05}.png", frame_prefix, index);
+                sink(&name, &frame)?;
+                entry.image = Some(name);
+            }
+
+            frames.push(entry);
+            progress(Progress::FrameDecoded {
+                done: index + 1,
+                total: info.image_count,
+            });
+        }
+
+        Ok(Self {
+            version: MANIFEST_VERSION,
+            source_format: info.format_name(),
+            source_file: info.file_name.clone(),
+            palette: loader.palette().map(|p| p.to_vec()),
+            frames,
+        })
+    }
+
+    /// 为一组没有元数据的图像生成清单（偏移全部为 0）
+    pub fn from_images(names: &[String]) -> Self {
+        let frames = names
+            .iter()
+            .enumerate()
+            .map(|(index, name)| FrameEntry {
+                index,
+                image: Some(name.clone()),
+                ..Default::default()
+            })
+            .collect();
+
+        Self {
+            version: MANIFEST_VERSION,
+            source_format: "PNG".to_string(),
+            source_file: String::new(),
+            palette: None,
+            frames,
+        }
+    }
+
+    /// 从 JSON 数据解析并校验版本
+    pub fn from_json(data: &[u8]) -> Result<Self> {
+        let manifest: Manifest = serde_json::from_slice(data)?;
+        if manifest.version > MANIFEST_VERSION {
+            return Err(LibraryError::UnsupportedVersion(manifest.version as i32));
+        }
+        Ok(manifest)
+    }
+
+    /// 序列化为 JSON
+    pub fn to_json(&self) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec_pretty(self)?)
+    }
+
+    /// 读取 JSON 文件
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Err(LibraryError::FileNotFound(path.display().to_string()));
+        }
+        Self::from_json(&std::fs::read(path)?)
+    }
+
+    /// 写入 JSON 文件
+    pub fn save(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, self.to_json()?)?;
+        Ok(())
+    }
+
+    /// 按清单重建 MLibrary V2 库并保存，`output` 为目标 .Lib 文件路径
+    ///
+    /// `source` 根据清单中的相对路径读取图像，帧像素按 `options` 编码。
+    pub fn rebuild<F>(
+        &self,
+        output: &Path,
+        options: EncodeOptions,
+        mut source: F,
+    ) -> Result<MLibraryV2>
+    where
+        F: FnMut(&str) -> Result<RgbaImage>,
+    {
+        let mut library = MLibraryV2::new(output.with_extension(""))?;
+        library.images.clear();
+        library.count = 0;
+        library.wide_offsets = options.wide_offsets;
+        library.codec = options.codec;
+        library.level = options.level;
+
+        let mut frames: Vec<&FrameEntry> = self.frames.iter().collect();
+        frames.sort_by_key(|f| f.index);
+
+        for entry in frames {
+            let mut image = match entry.image {
+                Some(ref name) => {
+                    MImage::from_image_with(&source(name)?, entry.x, entry.y, options)
+                }
+                None => {
+                    let mut empty = MImage::new();
+                    empty.x = entry.x;
+                    empty.y = entry.y;
+                    empty
+                }
+            };
+            image.shadow = entry.shadow;
+            image.shadow_x = entry.shadow_x;
+            image.shadow_y = entry.shadow_y;
+
+            if let Some(ref mask) = entry.mask
+                && let Some(ref name) = mask.image
+            {
+                // 复用 from_image 完成遮罩层的像素压缩
+                let mask_layer = MImage::from_image(&source(name)?, 0, 0);
+                image.has_mask = true;
+                image.mask_width = mask.width;
+                image.mask_height = mask.height;
+                image.mask_x = mask.x;
+                image.mask_y = mask.y;
+                image.mask_fbytes = mask_layer.fbytes;
+                image.mask_image = mask_layer.frame.image.map(Arc::unwrap_or_clone);
+            }
+
+            library.add_image(&image);
+        }
+
+        library.save()?;
+        Ok(library)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_roundtrip_and_version_check() {
+        let mut manifest = Manifest::from_images(&["a.png".to_string(), "b.png".to_string()]);
+        manifest.frames[1].x = -12;
+        manifest.frames[1].shadow_y = 4;
+
+        let parsed = Manifest::from_json(&manifest.to_json().unwrap()).unwrap();
+        assert_eq!(parsed.frames.len(), 2);
+        assert_eq!(parsed.frames[1].image.as_deref(), Some("b.png"));
+        assert_eq!((parsed.frames[1].x, parsed.frames[1].shadow_y), (-12, 4));
+
+        manifest.version = MANIFEST_VERSION + 1;
+        assert!(Manifest::from_json(&manifest.to_json().unwrap()).is_err());
+    }
+}
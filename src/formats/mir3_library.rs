@@ -0,0 +1,431 @@
+//! Mir3 库格式解析 (.wil/.wix, .wzl/.wzx)
+//! 这是传奇3使用的库文件格式，与传奇2同名但头部和帧压缩方式不同
+//!
+//! 索引文件（.wix / .wzx）结构：
+//! - 文件头：20字节，以 "#INDX v2.0" 开头
+//! - 图片数量：偏移 20，4字节，小端序
+//! - 版本标记：偏移 26，2字节，新版为 0xB13A，此时索引数组从偏移 28 开始，否则从 24 开始
+//! - 图像位置数组：每个图像4字节
+//!
+//! WIL 帧结构（17字节头部 + RLE 数据）：
+//! - 宽度、高度、X、Y：各2字节
+//! - 阴影类型：1字节
+//! - 阴影 X、阴影 Y：各2字节
+//! - 数据长度：4字节（以 u16 字为单位）
+//!
+//! WZL 帧结构（与 MLibrary V1 相同的16字节头部）：
+//! - 标识(1) + 保留(3) + 宽、高、X、Y(各2) + 压缩长度(4)
+//! - 数据为 Zlib 压缩后的 RLE 数据
+//!
+//! RLE 数据按行从上到下存储，每行以 u16 行长度（字数）开头，随后是若干命令：
+//! - 0xC0 n：跳过 n 个透明像素
+//! - 0xC1 n：随后 n 个不透明 RGB565 像素
+//! - 0xC2 n / 0xC3 n：随后 n 个半透明 RGB565 像素
+
+use crate::error::{LibraryError, Result};
+use crate::formats::header::FrameHeader;
+use crate::formats::limits;
+use crate::formats::mlibrary_v2::MImage;
+use crate::formats::source::{ReadSeek, Source};
+use crate::image::rgb565_to_rgba;
+use crate::image::rows::RowOrder;
+use byteorder::{LittleEndian, ReadBytesExt};
+use flate2::read::ZlibDecoder;
+use image::{Rgba, RgbaImage};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Mir3 索引文件头标识
+const MIR3_INDEX_MAGIC: &[u8] = b"#INDX v2.0";
+/// 新版索引头的版本标记
+const MIR3_VERSION_MARKER: u16 = 0xB13A;
+/// 图片数量在索引文件中的偏移量
+const COUNT_OFFSET: u64 = 20;
+/// 版本标记在索引文件中的偏移量
+const MARKER_OFFSET: u64 = 26;
+
+/// 跳过透明像素
+const CMD_SKIP: u16 = 0xC0;
+/// 不透明像素
+const CMD_COPY: u16 = 0xC1;
+/// 半透明像素
+const CMD_BLEND: u16 = 0xC2;
+/// 半透明像素（阴影）
+const CMD_BLEND_SHADOW: u16 = 0xC3;
+/// 半透明像素的 Alpha 值
+const BLEND_ALPHA: u8 = 128;
+/// RLE 像素行的存储顺序
+pub const ROW_ORDER: RowOrder = RowOrder::TopDown;
+
+/// Mir3 库的存储容器
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mir3Container {
+    /// .wil/.wix，帧数据为未压缩的 RLE
+    Wil,
+    /// .wzl/.wzx，帧数据为 Zlib 压缩的 RLE
+    Wzl,
+}
+
+impl Mir3Container {
+    /// 数据文件扩展名
+    pub fn data_extension(self) -> &'static str {
+        match self {
+            Mir3Container::Wil => ".wil",
+            Mir3Container::Wzl => ".wzl",
+        }
+    }
+
+    /// 索引文件扩展名
+    pub fn index_extension(self) -> &'static str {
+        match self {
+            Mir3Container::Wil => ".wix",
+            Mir3Container::Wzl => ".wzx",
+        }
+    }
+}
+
+/// 检测来源中的库是否为 Mir3 格式，返回其容器类型
+pub fn sniff(source: &Source) -> Option<Mir3Container> {
+    [Mir3Container::Wil, Mir3Container::Wzl]
+        .into_iter()
+        .find(|container| {
+            let mut header = [0u8; 10];
+            source
+                .open(container.index_extension())
+                .is_ok_and(|mut f| f.read_exact(&mut header).is_ok())
+                && header.starts_with(MIR3_INDEX_MAGIC)
+        })
+}
+
+/// Mir3 Library - 用于处理传奇3的 .wil/.wzl 文件
+pub struct Mir3Library {
+    /// 文件路径（不带扩展名）
+    pub file_name: PathBuf,
+    /// 存储容器类型
+    pub container: Mir3Container,
+    /// 图像列表
+    pub images: Vec<Option<MImage>>,
+    /// 索引列表
+    pub index_list: Vec<u32>,
+    /// 图像计数
+    pub count: usize,
+    /// 数据文件读取器
+    reader: Box<dyn ReadSeek>,
+}
+
+impl Mir3Library {
+    /// 创建新的 Mir3 Library 实例
+    pub fn new(file_name: PathBuf, container: Mir3Container) -> Result<Self> {
+        let source = Source::Path(file_name.clone());
+        Self::from_source(file_name, source, container)
+    }
+
+    /// 从指定来源读取的实例
+    pub fn from_source(
+        file_name: PathBuf,
+        source: Source,
+        container: Mir3Container,
+    ) -> Result<Self> {
+        for ext in [container.index_extension(), container.data_extension()] {
+            if !source.exists(ext) {
+                return Err(LibraryError::FileNotFound(source.describe(ext)));
+            }
+        }
+
+        let index_list = Self::load_index_file(source.open(container.index_extension())?)?;
+
+        tracing::info!(
+            "加载 Mir3 Library: {:?} ({} 张图像)",
+            file_name,
+            index_list.len()
+        );
+
+        Ok(Self {
+            file_name,
+            container,
+            images: vec![None; index_list.len()],
+            count: index_list.len(),
+            index_list,
+            reader: source.open(container.data_extension())?,
+        })
+    }
+
+    /// 加载索引文件
+    fn load_index_file(mut reader: Box<dyn ReadSeek>) -> Result<Vec<u32>> {
+        let mut magic = [0u8; 10];
+        reader.read_exact(&mut magic)?;
+        if !magic.starts_with(MIR3_INDEX_MAGIC) {
+            return Err(LibraryError::InvalidFormat);
+        }
+
+        reader.seek(SeekFrom::Start(COUNT_OFFSET))?;
+        let count = reader.read_u32::<LittleEndian>()? as usize;
+
+        reader.seek(SeekFrom::Start(MARKER_OFFSET))?;
+        let header_size = if reader.read_u16::<LittleEndian>()? == MIR3_VERSION_MARKER {
+            28
+        } else {
+            24
+        };
+
+        reader.seek(SeekFrom::Start(header_size))?;
+        let mut index_list = Vec::with_capacity(count.min(1 << 20));
+        for i in 0..count {
+            match reader.read_u32::<LittleEndian>() {
+                Ok(offset) => index_list.push(offset),
+                Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                    tracing::warn!("Mir3 索引在第 {} 项处提前结束（声明 {} 项）", i, count);
+                    break;
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        Ok(index_list)
+    }
+
+    /// 检查并加载指定索引的图像
+    pub fn check_image(&mut self, index: usize) -> Result<()> {
+        if index >= self.images.len() {
+            return Err(LibraryError::IndexOutOfBounds(index));
+        }
+
+        if self.images[index].is_none() {
+            self.load_image(index)?;
+        }
+
+        Ok(())
+    }
+
+    /// 加载指定索引的图像
+    fn load_image(&mut self, index: usize) -> Result<()> {
+        let offset = self.index_list[index] as u64;
+        self.reader.seek(SeekFrom::Start(offset))?;
+
+        let image = match self.container {
+            Mir3Container::Wil => Self::read_wil_frame(&mut *self.reader)?,
+            Mir3Container::Wzl => Self::read_wzl_frame(&mut *self.reader)?,
+        };
+
+        self.images[index] = Some(image);
+        Ok(())
+    }
+
+    /// 只读取每帧的头部，跳过 RLE 数据
+    pub fn frame_headers(&mut self) -> Result<Vec<FrameHeader>> {
+        let mut headers = Vec::with_capacity(self.index_list.len());
+        for (index, &offset) in self.index_list.iter().enumerate() {
+            let reader = &mut self.reader;
+            reader.seek(SeekFrom::Start(offset as u64))?;
+            if self.container == Mir3Container::Wzl {
+                // 标识和保留字节
+                reader.read_exact(&mut [0u8; 4])?;
+            }
+
+            let width = reader.read_i16::<LittleEndian>()? as i32;
+            let height = reader.read_i16::<LittleEndian>()? as i32;
+            let x = reader.read_i16::<LittleEndian>()? as i32;
+            let y = reader.read_i16::<LittleEndian>()? as i32;
+            let (shadow, data_length) = match self.container {
+                Mir3Container::Wil => {
+                    // 阴影类型之后跳过阴影偏移，长度以 u16 字为单位
+                    let shadow = reader.read_u8()?;
+                    reader.read_exact(&mut [0u8; 4])?;
+                    (shadow, reader.read_i32::<LittleEndian>()?.max(0) as u64 * 2)
+                }
+                Mir3Container::Wzl => (0, reader.read_i32::<LittleEndian>()?.max(0) as u64),
+            };
+
+            headers.push(FrameHeader {
+                index,
+                width,
+                height,
+                x,
+                y,
+                data_offset: reader.stream_position()?,
+                data_length,
+                has_mask: false,
+                shadow,
+            });
+        }
+
+        Ok(headers)
+    }
+
+    /// 读取 WIL 帧
+    fn read_wil_frame(reader: &mut dyn ReadSeek) -> Result<MImage> {
+        let mut img = MImage::new();
+        img.width = reader.read_i16::<LittleEndian>()?;
+        img.height = reader.read_i16::<LittleEndian>()?;
+        img.x = reader.read_i16::<LittleEndian>()?;
+        img.y = reader.read_i16::<LittleEndian>()?;
+        img.shadow = reader.read_u8()?;
+        img.shadow_x = reader.read_i16::<LittleEndian>()?;
+        img.shadow_y = reader.read_i16::<LittleEndian>()?;
+        let words = reader.read_i32::<LittleEndian>()?;
+
+        if img.width <= 0 || img.height <= 0 || words <= 0 {
+            return Ok(img);
+        }
+
+        let mut data = vec![0u8; limits::check_length(words as i64 * 2, "帧数据")?];
+        reader.read_exact(&mut data)?;
+
+        img.image = Some(Arc::new(decode_rle(
+            &data,
+            img.width as u32,
+            img.height as u32,
+        )?));
+        img.length = data.len() as i32;
+        img.fbytes = data;
+        img.texture_valid = true;
+        Ok(img)
+    }
+
+    /// 读取 WZL 帧
+    fn read_wzl_frame(reader: &mut dyn ReadSeek) -> Result<MImage> {
+        let _flag = reader.read_u8()?;
+        let mut reserved = [0u8; 3];
+        reader.read_exact(&mut reserved)?;
+
+        let mut img = MImage::new();
+        img.width = reader.read_i16::<LittleEndian>()?;
+        img.height = reader.read_i16::<LittleEndian>()?;
+        img.x = reader.read_i16::<LittleEndian>()?;
+        img.y = reader.read_i16::<LittleEndian>()?;
+        let n_size = reader.read_i32::<LittleEndian>()?;
+
+        if img.width <= 0 || img.height <= 0 || n_size <= 0 {
+            return Ok(img);
+        }
+
+        let mut compressed = vec![0u8; limits::check_length(n_size as i64, "压缩数据")?];
+        reader.read_exact(&mut compressed)?;
+
+        let data = limits::read_to_end(ZlibDecoder::new(&compressed[..]), "帧数据")?;
+
+        img.image = Some(Arc::new(decode_rle(
+            &data,
+            img.width as u32,
+            img.height as u32,
+        )?));
+        img.length = compressed.len() as i32;
+        img.fbytes = compressed;
+        img.texture_valid = true;
+        Ok(img)
+    }
+
+    /// 获取指定索引的图像
+    pub fn get_image(&mut self, index: usize) -> Result<&MImage> {
+        self.check_image(index)?;
+
+        self.images[index]
+            .as_ref()
+            .ok_or(LibraryError::IndexOutOfBounds(index))
+    }
+
+    /// 释放指定帧，下次访问时从文件重新读取（不在文件索引中的帧不释放）
+    pub fn release_image(&mut self, index: usize) {
+        if index < self.index_list.len()
+            && let Some(image) = self.images.get_mut(index)
+        {
+            *image = None;
+        }
+    }
+
+    /// 获取预览图（与缓存共享，不复制像素）
+    pub fn get_preview(&mut self, index: usize) -> Result<Option<Arc<RgbaImage>>> {
+        self.check_image(index)?;
+
+        Ok(self.images[index]
+            .as_ref()
+            .and_then(|img| img.image.clone()))
+    }
+
+    /// 获取图像计数
+    pub fn count(&self) -> usize {
+        self.count
+    }
+}
+
+/// 解码 Mir3 行 RLE 数据
+fn decode_rle(data: &[u8], width: u32, height: u32) -> Result<RgbaImage> {
+    let words: Vec<u16> = data
+        .chunks_exact(2)
+        .map(|c| u16::from_le_bytes([c[0], c[1]]))
+        .collect();
+
+    let mut img = RgbaImage::new(width, height);
+    let mut pos = 0usize;
+    let corrupt = || LibraryError::ParseError("Mir3 RLE 数据损坏".to_string());
+
+    for row in 0..height {
+        let y = ROW_ORDER.map(row, height);
+        let row_len = *words.get(pos).ok_or_else(corrupt)? as usize;
+        pos += 1;
+        let row_end = pos + row_len;
+        if row_end > words.len() {
+            return Err(corrupt());
+        }
+
+        let mut x = 0u32;
+        while pos < row_end {
+            let cmd = words[pos];
+            let n = *words.get(pos + 1).ok_or_else(corrupt)? as usize;
+            pos += 2;
+
+            match cmd {
+                CMD_SKIP => x += n as u32,
+                CMD_COPY | CMD_BLEND | CMD_BLEND_SHADOW => {
+                    if pos + n > row_end {
+                        return Err(corrupt());
+                    }
+                    let alpha = if cmd == CMD_COPY { 255 } else { BLEND_ALPHA };
+                    for &color in &words[pos..pos + n] {
+                        if x < width {
+                            let [r, g, b, _] = rgb565_to_rgba(color);
+                            img.put_pixel(x, y, Rgba([r, g, b, alpha]));
+                        }
+                        x += 1;
+                    }
+                    pos += n;
+                }
+                _ => {
+                    return Err(LibraryError::ParseError(format!(
+                        "未知的 Mir3 RLE 命令: {:#06x}",
+                        cmd
+                    )));
+                }
+            }
+        }
+    }
+
+    Ok(img)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(words: &[u16]) -> Vec<u8> {
+        words.iter().flat_map(|w| w.to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn test_decode_rle_rows() {
+        // 第一行：跳过1个像素，复制1个白色像素；第二行：1个半透明红色像素
+        let data = encode(&[5, CMD_SKIP, 1, CMD_COPY, 1, 0xFFFF, 3, CMD_BLEND, 1, 0xF800]);
+        let img = decode_rle(&data, 2, 2).unwrap();
+
+        assert_eq!(img.get_pixel(0, 0).0[3], 0);
+        assert_eq!(img.get_pixel(1, 0).0, [255, 255, 255, 255]);
+        assert_eq!(img.get_pixel(0, 1).0, [255, 0, 0, BLEND_ALPHA]);
+    }
+
+    #[test]
+    fn test_decode_rle_rejects_truncated() {
+        let data = encode(&[5, CMD_COPY, 3, 0xFFFF]);
+        assert!(decode_rle(&data, 4, 1).is_err());
+    }
+}
@@ -9,20 +9,31 @@
 //!
 //! WIL 文件（数据文件）结构：
 //! - 文件头：44字节
-//! - 控制信息：偏移 44-55，12字节（可忽略）
+//! - 控制信息：偏移 44-55，12字节（颜色数、调色板字节数、版本标志）
 //! - 调色板：偏移 56-1079，1024字节（256色 BGRA）
 //! - 图像数据：从偏移 1080 开始
+//!
+//! 多调色板变体：控制信息中的调色板字节数为 N × 1024（N > 1）时，
+//! 偏移 56 起依次存放 N 个调色板，之后是 N 个 4 字节的分段起始帧索引（升序，第一个为 0），
+//! 每个调色板作用于从其起始帧到下一分段之前的帧。
 //!   - 宽度：2字节
 //!   - 高度：2字节
 //!   - 固定标识：4字节
-//!   - 像素数据：宽度 × 高度 字节（8-bit 调色板索引）
+//!   - 像素数据：8-bit 调色板索引，每行补齐到 4 字节，共 行跨度 × 高度 字节
 
 use crate::error::{LibraryError, Result};
+use crate::formats::atomic_file::AtomicFile;
+use crate::formats::find_companion;
+use crate::formats::header::FrameHeader;
+use crate::formats::limits;
+use crate::formats::source::{ReadSeek, Source};
+use crate::image::rows::{RowLayout, RowOrder};
+use crate::image::{PREVIEW_SIZE, letterbox};
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use image::{Rgba, RgbaImage};
-use std::fs::File;
-use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
-use std::path::Path;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
 
 /// WIX 文件头标识 (44字节)
 const WIX_HEADER: [u8; 44] = *b"#INDX v1.0-WEMADE Entertainment inc.\0\0\0\0\0\0\0\0";
@@ -34,11 +45,26 @@ const IMAGE_DATA_OFFSET: u64 = 1080;
 const PALETTE_OFFSET: u64 = 56;
 /// 调色板大小（256色 * 4字节）
 const PALETTE_SIZE: usize = 1024;
+/// 控制信息中调色板字节数字段的偏移
+const PALETTE_BYTES_OFFSET: u64 = 48;
+/// 多调色板变体最多支持的分段数（超出视为普通单调色板文件）
+const MAX_PALETTE_SECTIONS: usize = 64;
+/// WIL 像素行的存储顺序
+pub const ROW_ORDER: RowOrder = RowOrder::BottomUp;
+
+/// 多调色板变体中的一个分段：从 `first` 帧起使用的调色板
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PaletteSection {
+    /// 分段起始帧索引
+    pub first: usize,
+    /// 调色板（256色 BGRA）
+    pub palette: [[u8; 4]; 256],
+}
 
 /// WeMade Library - 用于处理 .wil/.wix 文件
 pub struct MLibraryV0 {
     /// 文件基础路径（不含扩展名）
-    pub file_name: String,
+    pub file_name: PathBuf,
     /// 图像列表
     pub images: Vec<Option<MImage>>,
     /// 索引列表（存储每个图像在 WIL 文件中的偏移量）
@@ -49,8 +75,14 @@ pub struct MLibraryV0 {
     initialized: bool,
     /// 是否加载图像
     pub load: bool,
-    /// 调色板（256色 BGRA）
+    /// 调色板（256色 BGRA；多调色板变体中为第一个分段的调色板）
     palette: [[u8; 4]; 256],
+    /// 多调色板变体的各分段（按起始帧升序，单调色板文件为空）
+    sections: Vec<PaletteSection>,
+    /// 手动指定的索引数组起始偏移（`None` 为自动检测）
+    index_offset: Option<u64>,
+    /// 读取索引和帧数据的来源（保存时总是写入 `file_name`）
+    source: Source,
 }
 
 /// WeMade Library 的 MImage 结构
@@ -70,8 +102,8 @@ pub struct MImage {
     pub fbytes: Vec<u8>,
     /// 纹理是否有效
     pub texture_valid: bool,
-    /// 解码后的 RGBA 图像
-    pub image: Option<RgbaImage>,
+    /// 解码后的 RGBA 图像（取帧时共享，不复制像素）
+    pub image: Option<Arc<RgbaImage>>,
     /// 预览图 (64x64)
     pub preview: Option<RgbaImage>,
 }
@@ -97,15 +129,20 @@ impl MImage {
         let width = img.width() as u16;
         let height = img.height() as u16;
 
-        // 将 RGBA 像素转换为调色板索引
-        let mut fbytes = Vec::with_capacity((width * height) as usize);
-
-        for pixel in img.pixels() {
-            let [r, g, b, a] = pixel.0;
-            // 查找最接近的调色板颜色
-            let index = find_closest_palette_color(r, g, b, a, palette);
-            fbytes.push(index);
-        }
+        // 将 RGBA 像素转换为调色板索引（查找最接近的调色板颜色），按存储行序逐行补齐
+        let layout = RowLayout::new(width as u32, height as u32, 8);
+        let indices: Vec<Vec<u8>> = (0..layout.height)
+            .map(|row| {
+                let y = ROW_ORDER.map(row, layout.height);
+                (0..layout.width)
+                    .map(|x| {
+                        let [r, g, b, a] = img.get_pixel(x, y).0;
+                        find_closest_palette_color(r, g, b, a, palette)
+                    })
+                    .collect()
+            })
+            .collect();
+        let fbytes = layout.pack(indices.iter().map(Vec::as_slice));
 
         Self {
             width,
@@ -115,7 +152,7 @@ impl MImage {
             flag: 0xFFD40007,
             fbytes,
             texture_valid: true,
-            image: Some(img.clone()),
+            image: Some(Arc::new(img.clone())),
             preview: None,
         }
     }
@@ -126,9 +163,9 @@ impl MImage {
             return Err(LibraryError::InvalidImageData);
         }
 
-        let width = self.width as u32;
-        let height = self.height as u32;
-        let expected_size = (width * height) as usize;
+        let layout = self.layout();
+        let (width, height) = (layout.width, layout.height);
+        let expected_size = layout.len();
 
         if self.fbytes.len() != expected_size {
             tracing::warn!(
@@ -142,10 +179,9 @@ impl MImage {
 
         let mut rgba_img = RgbaImage::new(width, height);
 
-        for y in 0..height {
-            for x in 0..width {
-                let idx = (y * width + x) as usize;
-                let palette_idx = self.fbytes[idx] as usize;
+        for (y, row) in (0..height).zip(layout.rows(&self.fbytes)) {
+            for (x, &palette_idx) in (0..width).zip(row) {
+                let palette_idx = palette_idx as usize;
                 let color = palette[palette_idx];
 
                 // 调色板格式: BGRA
@@ -161,37 +197,36 @@ impl MImage {
                     (r, g, b, if a == 0 { 255 } else { a })
                 };
 
-                // 图像需要垂直翻转
-                rgba_img.put_pixel(x, height - 1 - y, Rgba([r, g, b, a]));
+                rgba_img.put_pixel(x, ROW_ORDER.map(y, height), Rgba([r, g, b, a]));
             }
         }
 
-        self.image = Some(rgba_img);
+        self.image = Some(Arc::new(rgba_img));
         self.texture_valid = true;
         Ok(())
     }
 
-    /// 创建预览图 (64x64)
-    pub fn create_preview(&mut self) {
-        if let Some(ref image) = self.image {
-            use image::imageops;
-
-            let w = std::cmp::min(image.width(), 64);
-            let h = std::cmp::min(image.height(), 64);
-
-            let resized = imageops::resize(image, w, h, imageops::FilterType::Triangle);
+    /// 取图像坐标处像素的调色板索引
+    ///
+    /// 像素数据按自下而上的行存储，这里的 `y` 与解码后图像的行方向一致。
+    pub fn palette_index(&self, x: u32, y: u32) -> Option<u8> {
+        let (width, height) = (self.width as u32, self.height as u32);
+        if x >= width || y >= height {
+            return None;
+        }
+        let row = self.layout().row(ROW_ORDER.map(y, height));
+        self.fbytes.get(row.start + x as usize).copied()
+    }
 
-            let preview = RgbaImage::from_fn(64, 64, |x, y| {
-                let offset_x = (64 - w) / 2;
-                let offset_y = (64 - h) / 2;
-                if x >= offset_x && x < offset_x + w && y >= offset_y && y < offset_y + h {
-                    *resized.get_pixel(x - offset_x, y - offset_y)
-                } else {
-                    Rgba([0, 0, 0, 0])
-                }
-            });
+    /// 像素数据的行布局（每像素 1 字节，每行补齐到 4 字节）
+    pub fn layout(&self) -> RowLayout {
+        RowLayout::new(self.width as u32, self.height as u32, 8)
+    }
 
-            self.preview = Some(preview);
+    /// 创建预览图（64x64，等比缩小后居中，不裁剪）
+    pub fn create_preview(&mut self) {
+        if let Some(ref image) = self.image {
+            self.preview = Some(letterbox(image, PREVIEW_SIZE));
         }
     }
 
@@ -251,7 +286,22 @@ fn find_closest_palette_color(r: u8, g: u8, b: u8, a: u8, palette: &[[u8; 4]; 25
 
 impl MLibraryV0 {
     /// 创建新的 WeMade Library 实例
-    pub fn new(file_name: String) -> Result<Self> {
+    pub fn new(file_name: PathBuf) -> Result<Self> {
+        Self::with_index_offset(file_name, None)
+    }
+
+    /// 创建实例并手动指定 WIX 中索引数组的起始偏移，跳过文件头检测
+    pub fn with_index_offset(file_name: PathBuf, index_offset: Option<u64>) -> Result<Self> {
+        let source = Source::Path(file_name.clone());
+        Self::from_source(file_name, source, index_offset)
+    }
+
+    /// 从指定来源读取的实例（如内存中的 `.wix`、`.wil` 内容）
+    pub fn from_source(
+        file_name: PathBuf,
+        source: Source,
+        index_offset: Option<u64>,
+    ) -> Result<Self> {
         let mut library = Self {
             file_name,
             images: Vec::new(),
@@ -260,6 +310,9 @@ impl MLibraryV0 {
             initialized: false,
             load: true,
             palette: [[0u8; 4]; 256],
+            sections: Vec::new(),
+            index_offset,
+            source,
         };
 
         library.initialize()?;
@@ -270,48 +323,46 @@ impl MLibraryV0 {
     pub fn initialize(&mut self) -> Result<()> {
         self.initialized = true;
 
-        let wix_path = format!("{}.wix", self.file_name);
-        let wil_path = format!("{}.wil", self.file_name);
-
         // 检查文件是否存在
-        if !Path::new(&wix_path).exists() || !Path::new(&wil_path).exists() {
-            tracing::debug!("WIL/WIX 文件不存在: {}", self.file_name);
+        if !self.source.exists(".wix") || !self.source.exists(".wil") {
+            tracing::debug!("WIL/WIX 文件不存在: {:?}", self.file_name);
             return Ok(());
         }
 
         // 读取 WIX 索引文件
-        self.read_wix_file(&wix_path)?;
+        self.read_wix_file()?;
 
         // 读取 WIL 文件的调色板
-        self.read_palette(&wil_path)?;
+        self.read_palette()?;
 
         // 初始化图像列表
         self.images = vec![None; self.count];
 
         tracing::info!(
-            "加载 WeMade Library: {} ({} 张图像)",
+            "加载 WeMade Library: {:?} ({} 张图像)",
             self.file_name,
             self.count
         );
+        if !self.sections.is_empty() {
+            tracing::info!("  多调色板变体: {} 个分段", self.sections.len());
+        }
 
         Ok(())
     }
 
     /// 读取 WIX 索引文件
-    fn read_wix_file(&mut self, wix_path: &str) -> Result<()> {
-        tracing::debug!("读取 WIX 文件: {}", wix_path);
+    fn read_wix_file(&mut self) -> Result<()> {
+        let wix_name = self.source.describe(".wix");
+        tracing::debug!("读取 WIX 文件: {}", wix_name);
 
-        let file = File::open(wix_path).map_err(|e| {
-            tracing::error!("无法打开 WIX 文件: {} - {}", wix_path, e);
+        let mut reader = self.source.open(".wix").map_err(|e| {
+            tracing::error!("无法打开 WIX 文件: {} - {}", wix_name, e);
             e
         })?;
 
-        let metadata = file.metadata()?;
-        let file_size = metadata.len();
+        let file_size = self.source.len(".wix")?;
         tracing::debug!("WIX 文件大小: {} 字节", file_size);
 
-        let mut reader = BufReader::new(file);
-
         // 读取文件头（最多读取 52 字节用于分析）
         let mut header = [0u8; 52];
         if let Err(e) = reader.read_exact(&mut header) {
@@ -330,7 +381,11 @@ impl MLibraryV0 {
         );
 
         // 尝试检测文件格式版本
-        let (count, header_size) = if header.starts_with(b"#INDX v1.0-WEMADE") {
+        let (count, header_size) = if let Some(offset) = self.index_offset {
+            // 手动指定偏移，数量按文件大小计算
+            tracing::debug!("使用手动指定的索引偏移: {}", offset);
+            (0, offset.min(file_size) as usize)
+        } else if header.starts_with(b"#INDX v1.0-WEMADE") {
             // 标准格式: 44字节文件头 + 4字节数量 + 4字节数据起始位置
             tracing::debug!("检测到标准 WeMade 格式 (#INDX v1.0)");
             reader.seek(SeekFrom::Start(44))?;
@@ -396,7 +451,7 @@ impl MLibraryV0 {
             (count, header_size)
         };
 
-        self.count = count;
+        self.count = limits::check_count(count)?;
 
         // 读取图像偏移量数组
         self.index_list.clear();
@@ -432,18 +487,16 @@ impl MLibraryV0 {
     }
 
     /// 读取 WIL 文件的调色板
-    fn read_palette(&mut self, wil_path: &str) -> Result<()> {
-        tracing::debug!("读取 WIL 文件调色板: {}", wil_path);
+    fn read_palette(&mut self) -> Result<()> {
+        let wil_name = self.source.describe(".wil");
+        tracing::debug!("读取 WIL 文件调色板: {}", wil_name);
 
-        let file = File::open(wil_path).map_err(|e| {
-            tracing::error!("无法打开 WIL 文件: {} - {}", wil_path, e);
+        let mut reader = self.source.open(".wil").map_err(|e| {
+            tracing::error!("无法打开 WIL 文件: {} - {}", wil_name, e);
             e
         })?;
 
-        let metadata = file.metadata()?;
-        tracing::debug!("WIL 文件大小: {} 字节", metadata.len());
-
-        let mut reader = BufReader::new(file);
+        tracing::debug!("WIL 文件大小: {} 字节", self.source.len(".wil")?);
 
         // 读取并验证文件头（44字节）
         let mut header = [0u8; 44];
@@ -462,22 +515,46 @@ impl MLibraryV0 {
             );
         }
 
-        // 跳过控制信息（12字节），定位到调色板位置
+        // 控制信息中的调色板字节数决定是否为多调色板变体
+        reader.seek(SeekFrom::Start(PALETTE_BYTES_OFFSET))?;
+        let palette_bytes = reader.read_u32::<LittleEndian>()? as usize;
+        let section_count = palette_bytes / PALETTE_SIZE;
+
+        // 定位到调色板位置
         reader.seek(SeekFrom::Start(PALETTE_OFFSET))?;
         tracing::debug!("定位到调色板位置: {} 字节", PALETTE_OFFSET);
 
         // 读取调色板（256色 * 4字节 = 1024字节）
-        for (i, color) in self.palette.iter_mut().enumerate() {
-            reader.read_exact(color).map_err(|e| {
-                tracing::error!("读取调色板第 {} 色失败: {}", i, e);
-                e
-            })?;
+        self.palette = read_palette_block(&mut reader)?;
+        self.sections.clear();
+
+        if palette_bytes.is_multiple_of(PALETTE_SIZE)
+            && (2..=MAX_PALETTE_SECTIONS).contains(&section_count)
+        {
+            match read_palette_sections(&mut reader, self.palette, section_count) {
+                Ok(sections) => self.sections = sections,
+                Err(e) => tracing::warn!("多调色板分段表无效，按单调色板处理: {}", e),
+            }
         }
 
         tracing::debug!("调色板读取完成");
         Ok(())
     }
 
+    /// 获取指定帧使用的调色板
+    pub fn palette_for(&self, index: usize) -> &[[u8; 4]; 256] {
+        self.sections
+            .iter()
+            .rev()
+            .find(|section| section.first <= index)
+            .map_or(&self.palette, |section| &section.palette)
+    }
+
+    /// 多调色板变体的分段（单调色板文件为空）
+    pub fn palette_sections(&self) -> &[PaletteSection] {
+        &self.sections
+    }
+
     /// 关闭库
     pub fn close(&mut self) {
         self.initialized = false;
@@ -506,26 +583,48 @@ impl MLibraryV0 {
             return Err(LibraryError::IndexOutOfBounds(index));
         }
 
-        let wil_path = format!("{}.wil", self.file_name);
-        let file = File::open(&wil_path)?;
-        let mut reader = BufReader::new(file);
+        let mut reader = self.source.open(".wil")?;
 
         // 获取图像在 WIL 文件中的偏移量
         let offset = self.index_list[index] as u64;
         reader.seek(SeekFrom::Start(offset))?;
 
         // 读取图像数据
-        let mut image = self.read_wil_image(&mut reader)?;
+        let mut image = self.read_wil_image(&mut *reader)?;
 
-        // 使用调色板解码图像
-        image.decode_with_palette(&self.palette)?;
+        // 使用该帧所在分段的调色板解码图像
+        image.decode_with_palette(self.palette_for(index))?;
 
         self.images[index] = Some(image);
         Ok(())
     }
 
+    /// 只读取每帧的宽高，跳过像素数据（WIL 帧不含绘制偏移）
+    pub fn frame_headers(&self) -> Result<Vec<FrameHeader>> {
+        if self.index_list.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut reader = self.source.open(".wil")?;
+
+        let mut headers = Vec::with_capacity(self.index_list.len());
+        for (index, &offset) in self.index_list.iter().enumerate() {
+            reader.seek(SeekFrom::Start(offset as u64))?;
+            let width = reader.read_u16::<LittleEndian>()? as i32;
+            let height = reader.read_u16::<LittleEndian>()? as i32;
+            headers.push(FrameHeader {
+                width,
+                height,
+                data_offset: offset as u64 + 8,
+                data_length: RowLayout::new(width as u32, height as u32, 8).len() as u64,
+                ..FrameHeader::empty(index)
+            });
+        }
+
+        Ok(headers)
+    }
+
     /// 从 WIL 文件读取图像数据
-    fn read_wil_image(&self, reader: &mut BufReader<File>) -> Result<MImage> {
+    fn read_wil_image(&self, reader: &mut dyn ReadSeek) -> Result<MImage> {
         // 读取宽度（2字节）
         let width = reader.read_u16::<LittleEndian>()?;
         // 读取高度（2字节）
@@ -533,9 +632,9 @@ impl MLibraryV0 {
         // 读取固定标识（4字节）
         let flag = reader.read_u32::<LittleEndian>()?;
 
-        // 读取像素数据（宽度 × 高度 字节）
-        let data_size = (width as usize) * (height as usize);
-        let mut fbytes = vec![0u8; data_size];
+        // 读取像素数据（每行补齐到 4 字节）
+        let data_size = RowLayout::new(width as u32, height as u32, 8).len() as i64;
+        let mut fbytes = vec![0u8; limits::check_length(data_size, "帧数据")?];
         reader.read_exact(&mut fbytes)?;
 
         let mut img = MImage::new();
@@ -556,6 +655,15 @@ impl MLibraryV0 {
             .ok_or_else(|| LibraryError::IndexOutOfBounds(index))
     }
 
+    /// 释放指定帧，下次访问时从文件重新读取（不在文件索引中的帧不释放）
+    pub fn release_image(&mut self, index: usize) {
+        if index < self.index_list.len()
+            && let Some(image) = self.images.get_mut(index)
+        {
+            *image = None;
+        }
+    }
+
     /// 添加新图像
     pub fn add_image(&mut self, image: &MImage) {
         self.count += 1;
@@ -600,12 +708,20 @@ impl MLibraryV0 {
 
     /// 保存库文件
     pub fn save(&self) -> Result<()> {
-        let wix_path = format!("{}.wix", self.file_name);
-        let wil_path = format!("{}.wil", self.file_name);
+        let wix_path = find_companion(&self.file_name, ".wix");
+        let wil_path = find_companion(&self.file_name, ".wil");
 
         // 计算 WIL 文件中图像数据的起始偏移量
         // = 文件头(44) + 控制信息(12) + 调色板(1024) = 1080
-        let base_offset = IMAGE_DATA_OFFSET as u32;
+        // 多调色板变体还要加上其余调色板和分段表
+        let extra_sections = self.sections.len().saturating_sub(1);
+        let base_offset = IMAGE_DATA_OFFSET as u32
+            + (extra_sections * PALETTE_SIZE) as u32
+            + if self.sections.is_empty() {
+                0
+            } else {
+                (self.sections.len() * 4) as u32
+            };
 
         // 构建图像数据流和索引列表
         let mut data_stream = Vec::new();
@@ -619,8 +735,7 @@ impl MLibraryV0 {
 
         // 写入 WIX 索引文件
         {
-            let file = File::create(&wix_path)?;
-            let mut writer = BufWriter::new(file);
+            let mut writer = AtomicFile::create(&wix_path)?;
 
             // 写入文件头（44字节）
             writer.write_all(&WIX_HEADER)?;
@@ -640,13 +755,12 @@ impl MLibraryV0 {
                 writer.write_u32::<LittleEndian>(*index)?;
             }
 
-            writer.flush()?;
+            writer.commit()?;
         }
 
         // 写入 WIL 数据文件
         {
-            let file = File::create(&wil_path)?;
-            let mut writer = BufWriter::new(file);
+            let mut writer = AtomicFile::create(&wil_path)?;
 
             // 写入文件头（44字节）
             writer.write_all(&WIL_HEADER)?;
@@ -656,21 +770,37 @@ impl MLibraryV0 {
                 writer.write_all(&vec![0u8; header_pad])?;
             }
 
-            // 写入控制信息（12字节，填充0）
-            writer.write_all(&[0u8; 12])?;
+            // 写入控制信息（12字节）：单调色板填充0，多调色板写入调色板总字节数
+            if self.sections.is_empty() {
+                writer.write_all(&[0u8; 12])?;
 
-            // 写入调色板（1024字节）
-            for color in &self.palette {
-                writer.write_all(color)?;
+                // 写入调色板（1024字节）
+                for color in &self.palette {
+                    writer.write_all(color)?;
+                }
+            } else {
+                writer.write_u32::<LittleEndian>(256)?;
+                writer.write_u32::<LittleEndian>((self.sections.len() * PALETTE_SIZE) as u32)?;
+                writer.write_u32::<LittleEndian>(0)?;
+
+                // 依次写入各分段的调色板和分段表
+                for section in &self.sections {
+                    for color in &section.palette {
+                        writer.write_all(color)?;
+                    }
+                }
+                for section in &self.sections {
+                    writer.write_u32::<LittleEndian>(section.first as u32)?;
+                }
             }
 
             // 写入图像数据
             writer.write_all(&data_stream)?;
 
-            writer.flush()?;
+            writer.commit()?;
         }
 
-        tracing::info!("保存 WeMade Library 完成: {}", self.file_name);
+        tracing::info!("保存 WeMade Library 完成: {:?}", self.file_name);
         Ok(())
     }
 
@@ -684,10 +814,51 @@ impl MLibraryV0 {
         &self.palette
     }
 
-    /// 设置调色板
+    /// 设置调色板（作用于全部帧，覆盖多调色板变体的分段）
     pub fn set_palette(&mut self, palette: [[u8; 4]; 256]) {
         self.palette = palette;
+        self.sections.clear();
+    }
+}
+
+/// 读取一个调色板（256色 * 4字节）
+fn read_palette_block(reader: &mut impl Read) -> Result<[[u8; 4]; 256]> {
+    let mut palette = [[0u8; 4]; 256];
+    for (i, color) in palette.iter_mut().enumerate() {
+        reader.read_exact(color).map_err(|e| {
+            tracing::error!("读取调色板第 {} 色失败: {}", i, e);
+            e
+        })?;
     }
+    Ok(palette)
+}
+
+/// 读取多调色板变体的其余调色板和分段表（读取位置紧接第一个调色板之后）
+fn read_palette_sections(
+    reader: &mut impl Read,
+    first: [[u8; 4]; 256],
+    count: usize,
+) -> Result<Vec<PaletteSection>> {
+    let mut palettes = vec![first];
+    for _ in 1..count {
+        palettes.push(read_palette_block(reader)?);
+    }
+
+    let mut sections = Vec::with_capacity(count);
+    for palette in palettes {
+        let first = reader.read_u32::<LittleEndian>()? as usize;
+        let ascending = sections
+            .last()
+            .map_or(first == 0, |prev: &PaletteSection| first > prev.first);
+        if !ascending {
+            return Err(LibraryError::ParseError(format!(
+                "调色板分段起始帧不是从 0 开始的升序: {}",
+                first
+            )));
+        }
+        sections.push(PaletteSection { first, palette });
+    }
+    Ok(sections)
 }
 
 #[cfg(test)]
@@ -696,10 +867,74 @@ mod tests {
 
     #[test]
     fn test_create_library() {
-        let lib = MLibraryV0::new("nonexistent_file".to_string());
+        let lib = MLibraryV0::new(PathBuf::from("nonexistent_file"));
         assert!(lib.is_ok()); // 文件不存在时应该返回 Ok
     }
 
+    #[test]
+    fn test_palette_index_flips_rows() {
+        let mut img = MImage::new();
+        img.width = 2;
+        img.height = 2;
+        img.fbytes = vec![1, 2, 0, 0, 3, 4, 0, 0];
+
+        // 第一行存储的是图像底行，每行补齐到 4 字节
+        assert_eq!(img.palette_index(0, 0), Some(3));
+        assert_eq!(img.palette_index(1, 1), Some(2));
+        assert_eq!(img.palette_index(2, 0), None);
+    }
+
+    #[test]
+    fn test_multi_palette_sections_roundtrip() {
+        let temp = tempfile::tempdir().unwrap();
+        let base = temp.path().join("multi_palette");
+        let palette = |color: u8| {
+            let mut palette = [[0u8; 4]; 256];
+            palette[1] = [color, color, color, 255];
+            palette
+        };
+
+        let mut lib = MLibraryV0::new(base.clone()).unwrap();
+        for _ in 0..3 {
+            let mut img = MImage::new();
+            img.width = 1;
+            img.height = 1;
+            img.fbytes = vec![1, 0, 0, 0];
+            lib.add_image(&img);
+        }
+        lib.palette = palette(10);
+        lib.sections = vec![
+            PaletteSection {
+                first: 0,
+                palette: palette(10),
+            },
+            PaletteSection {
+                first: 2,
+                palette: palette(200),
+            },
+        ];
+        lib.save().unwrap();
+
+        let mut lib = MLibraryV0::new(base.clone()).unwrap();
+        assert_eq!(lib.palette_sections().len(), 2);
+        let pixel = |lib: &mut MLibraryV0, index| {
+            lib.get_image(index)
+                .unwrap()
+                .image
+                .as_ref()
+                .unwrap()
+                .get_pixel(0, 0)
+                .0
+        };
+        assert_eq!(pixel(&mut lib, 1), [10, 10, 10, 255]);
+        assert_eq!(pixel(&mut lib, 2), [200, 200, 200, 255]);
+
+        // 外部调色板覆盖全部分段
+        lib.set_palette(palette(50));
+        assert!(lib.palette_sections().is_empty());
+        assert_eq!(lib.palette_for(2)[1], [50, 50, 50, 255]);
+    }
+
     #[test]
     fn test_mimage_creation() {
         let img = MImage::new();
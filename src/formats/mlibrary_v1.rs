@@ -2,19 +2,53 @@
 //! 这是传奇2使用的库文件格式
 
 use crate::error::{LibraryError, Result};
-use crate::image::compression::{compress_gzip, decompress_gzip};
-use crate::image::{Color, DEFAULT_PALETTE};
+use crate::formats::atomic_file::AtomicFile;
+use crate::formats::find_companion;
+use crate::formats::frame::{FrameCodec, FrameData};
+use crate::formats::header::FrameHeader;
+use crate::formats::limits;
+use crate::formats::source::{ReadSeek, Source};
+use crate::image::compression::{CompressionLevel, compress_zlib_with, decompress_gzip};
+use crate::image::rows::{RowLayout, RowOrder};
+use crate::image::{
+    Color, DEFAULT_PALETTE, convert_16bit_to_32bit_with_alpha, rgb_to_rgb565, rgb565_to_rgba,
+};
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use flate2::read::ZlibDecoder;
 use image::{Rgba, RgbaImage};
-use std::fs::File;
-use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
-use std::path::Path;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::ops::{Deref, DerefMut};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// 帧记录的像素格式：8 位调色板索引（读取时 5、6 以外的值都按 8 位处理）
+const FORMAT_8BIT: u8 = 3;
+/// 帧记录的像素格式：16 位 RGB565
+const FORMAT_16BIT: u8 = 5;
+/// 帧记录的像素格式：16 位 RGB565，行数据之后附 4 位 alpha 平面（特效库常用）
+const FORMAT_16BIT_ALPHA: u8 = 6;
+
+/// WZL 像素行（含 alpha 平面）的存储顺序
+pub const ROW_ORDER: RowOrder = RowOrder::BottomUp;
+
+/// 没有 alpha 平面的 16 位帧中代替不透明纯黑的颜色（RGB565 各分量为 1）
+const NEAR_BLACK: u16 = 0x0821;
+
+/// 帧的行数据和 alpha 平面（每像素 4 位）的布局，没有 alpha 平面时平面为 0 行
+fn frame_layout(width: i16, height: i16, bo16bit: bool, has_alpha: bool) -> (RowLayout, RowLayout) {
+    let (width, height) = (width.max(0) as u32, height.max(0) as u32);
+    let bits = if bo16bit { 16 } else { 8 };
+    let alpha_rows = if bo16bit && has_alpha { height } else { 0 };
+    (
+        RowLayout::new(width, height, bits),
+        RowLayout::new(width, alpha_rows, 4),
+    )
+}
 
 /// MLibrary V1 - 用于处理 .wzl/.wzx 文件
 pub struct MLibraryV1 {
-    /// 文件名（不带扩展名）
-    pub file_name: String,
+    /// 文件路径（不带扩展名）
+    pub file_name: PathBuf,
     /// 图像列表
     pub images: Vec<Option<MImage>>,
     /// 索引列表
@@ -27,16 +61,31 @@ pub struct MLibraryV1 {
     pub load: bool,
     /// 调色板
     palette: [Color; 256],
+    /// 保存时帧数据的 zlib 压缩级别
+    pub level: CompressionLevel,
+    /// 读取索引和帧数据的来源（保存时总是写入 `file_name`）
+    source: Source,
     /// WZL 文件读取器（全局存放，避免重复打开文件）
-    wzl_reader: Option<BufReader<File>>,
+    wzl_reader: Option<Box<dyn ReadSeek>>,
 }
 
 impl MLibraryV1 {
-    const LIB_VERSION: i32 = 1;
     const WZX_HEADER_SIZE: u64 = 48;
+    /// 保存时写出的 WZL 文件头大小
+    const WZL_HEADER_SIZE: u64 = 64;
+    /// 保存时写在文件头开头的标识
+    const TITLE: &'static [u8] = b"www.shandagames.com";
+    /// WZX 头部中声明图像数量的偏移量
+    const WZX_COUNT_OFFSET: usize = 44;
 
     /// 创建新的 MLibrary V1 实例
-    pub fn new(file_name: String) -> Result<Self> {
+    pub fn new(file_name: PathBuf) -> Result<Self> {
+        let source = Source::Path(file_name.clone());
+        Self::from_source(file_name, source)
+    }
+
+    /// 从指定来源读取的实例（如内存中的 `.wzx`、`.wzl` 内容）
+    pub fn from_source(file_name: PathBuf, source: Source) -> Result<Self> {
         let mut library = Self {
             file_name,
             images: Vec::new(),
@@ -45,6 +94,8 @@ impl MLibraryV1 {
             initialized: false,
             load: true,
             palette: DEFAULT_PALETTE,
+            level: CompressionLevel::default(),
+            source,
             wzl_reader: None,
         };
 
@@ -56,26 +107,20 @@ impl MLibraryV1 {
     pub fn initialize(&mut self) -> Result<()> {
         self.initialized = true;
 
-        let wzx_path = format!("{}.wzx", self.file_name);
-        let wzl_path = format!("{}.wzl", self.file_name);
-
-        if !Path::new(&wzx_path).exists() {
-            return Err(LibraryError::FileNotFound(wzx_path));
-        }
-
-        if !Path::new(&wzl_path).exists() {
-            return Err(LibraryError::FileNotFound(wzl_path));
+        for ext in [".wzx", ".wzl"] {
+            if !self.source.exists(ext) {
+                return Err(LibraryError::FileNotFound(self.source.describe(ext)));
+            }
         }
 
         // 读取索引文件 (.wzx)
-        self.load_index_file(&wzx_path)?;
+        self.load_index_file()?;
 
         // 初始化图像列表
         self.images = vec![None; self.index_list.len()];
 
         // 打开 WZL 文件并全局存放（初始化后读取，完成后关闭）
-        let wzl_file = File::open(&wzl_path)?;
-        self.wzl_reader = Some(BufReader::new(wzl_file));
+        self.wzl_reader = Some(self.source.open(".wzl")?);
 
         // 初始化时检查所有图像
         // for i in 0..self.index_list.len() {
@@ -85,24 +130,52 @@ impl MLibraryV1 {
     }
 
     /// 加载索引文件
-    fn load_index_file(&mut self, path: &str) -> Result<()> {
-        let file = File::open(path)?;
-        let mut reader = BufReader::new(file);
-
-        // 跳过 48 字节的头部
-        reader.seek(SeekFrom::Start(Self::WZX_HEADER_SIZE))?;
-
-        // 读取所有索引
-        self.index_list.clear();
-        while let Ok(index) = reader.read_u32::<LittleEndian>() {
-            self.index_list.push(index);
-            self.images.push(None);
-        }
+    fn load_index_file(&mut self) -> Result<()> {
+        let data = self.source.read(".wzx")?;
 
+        self.index_list = Self::parse_index(&data);
+        self.images = vec![None; self.index_list.len()];
         self.count = self.index_list.len();
         Ok(())
     }
 
+    /// 解析 WZX 索引数据
+    ///
+    /// 头部声明了图像数量时只读取声明的数量，与实际条目数不符时给出警告；
+    /// 未声明（为 0）时读取到文件末尾。
+    fn parse_index(data: &[u8]) -> Vec<u32> {
+        let header_size = Self::WZX_HEADER_SIZE as usize;
+        if data.len() < header_size {
+            tracing::warn!("WZX 文件过短: {} 字节", data.len());
+            return Vec::new();
+        }
+
+        let available = (data.len() - header_size) / 4;
+        let declared = (&data[Self::WZX_COUNT_OFFSET..header_size])
+            .read_i32::<LittleEndian>()
+            .unwrap_or(0);
+
+        let count = if declared > 0 {
+            let declared = declared as usize;
+            if declared != available {
+                tracing::warn!(
+                    "WZX 声明的图像数 {} 与实际索引条目数 {} 不一致，按 {} 读取",
+                    declared,
+                    available,
+                    declared.min(available)
+                );
+            }
+            declared.min(available)
+        } else {
+            available
+        };
+
+        data[header_size..header_size + count * 4]
+            .chunks_exact(4)
+            .map(|mut chunk| chunk.read_u32::<LittleEndian>().unwrap_or(0))
+            .collect()
+    }
+
     /// 检查并加载指定索引的图像
     pub fn check_image(&mut self, index: usize) -> Result<()> {
         if !self.initialized {
@@ -127,7 +200,7 @@ impl MLibraryV1 {
         // 使用全局存储的文件流
         if let Some(ref mut reader) = self.wzl_reader {
             reader.seek(SeekFrom::Start(offset))?;
-            let image = Self::read_mimage(&self.palette, reader, offset)?;
+            let image = Self::read_mimage(&self.palette, &mut **reader, offset)?;
             self.images[index] = Some(image);
         } else {
             return Err(LibraryError::FileNotFound(
@@ -138,10 +211,66 @@ impl MLibraryV1 {
         Ok(())
     }
 
+    /// 只读取每帧的 16 字节头部，跳过像素数据
+    pub fn frame_headers(&mut self) -> Result<Vec<FrameHeader>> {
+        let Some(ref mut reader) = self.wzl_reader else {
+            return Err(LibraryError::FileNotFound(
+                "WZL reader not initialized".to_string(),
+            ));
+        };
+
+        let mut headers = Vec::with_capacity(self.index_list.len());
+        for (index, &offset) in self.index_list.iter().enumerate() {
+            // 偏移为 0 表示空帧
+            if offset == 0 {
+                headers.push(FrameHeader::empty(index));
+                continue;
+            }
+
+            reader.seek(SeekFrom::Start(offset as u64))?;
+            let flag = reader.read_u8()?;
+            reader.read_exact(&mut [0u8; 3])?;
+            let width = reader.read_i16::<LittleEndian>()? as i32;
+            let height = reader.read_i16::<LittleEndian>()? as i32;
+            let x = reader.read_i16::<LittleEndian>()? as i32;
+            let y = reader.read_i16::<LittleEndian>()? as i32;
+            let n_size = reader.read_i32::<LittleEndian>()?;
+
+            // 与完整读取一致：面积过小的帧视为空帧
+            if width * height < 4 {
+                headers.push(FrameHeader::empty(index));
+                continue;
+            }
+
+            let data_length = match n_size {
+                1.. => n_size as u64,
+                _ => {
+                    let bo16bit = flag == FORMAT_16BIT || flag == FORMAT_16BIT_ALPHA;
+                    let has_alpha = flag == FORMAT_16BIT_ALPHA;
+                    let (rows, alpha) =
+                        frame_layout(width as i16, height as i16, bo16bit, has_alpha);
+                    (rows.len() + alpha.len()) as u64
+                }
+            };
+            headers.push(FrameHeader {
+                index,
+                width,
+                height,
+                x,
+                y,
+                data_offset: offset as u64 + 16,
+                data_length,
+                ..FrameHeader::empty(index)
+            });
+        }
+
+        Ok(headers)
+    }
+
     /// 读取 MImage 数据
     fn read_mimage(
         palette: &[Color; 256],
-        reader: &mut BufReader<File>,
+        reader: &mut dyn ReadSeek,
         offset: u64,
     ) -> Result<MImage> {
         reader.seek(SeekFrom::Start(offset))?;
@@ -154,7 +283,8 @@ impl MLibraryV1 {
             return Ok(MImage::new());
         }
 
-        let bo16bit = flag == 5;
+        let bo16bit = flag == FORMAT_16BIT || flag == FORMAT_16BIT_ALPHA;
+        let has_alpha = flag == FORMAT_16BIT_ALPHA;
 
         // 跳过 3 字节
         reader.read_u8()?;
@@ -178,26 +308,20 @@ impl MLibraryV1 {
 
         // 读取图像数据
         let bytes = if n_size == 0 {
-            // 未压缩 - 直接读取原始数据
-            // 使用 i32 避免两个 i16 相乘溢出
-            let size = if bo16bit {
-                ((width as i32) * (height as i32) * 2) as usize
-            } else {
-                ((width as i32) * (height as i32)) as usize
-            };
-            let mut buf = vec![0u8; size];
+            // 未压缩 - 直接读取原始数据（对齐后的行数据加 alpha 平面）
+            let (rows, alpha) = frame_layout(width, height, bo16bit, has_alpha);
+            let size = (rows.len() + alpha.len()) as i64;
+            let mut buf = vec![0u8; limits::check_length(size, "帧数据")?];
             reader.read_exact(&mut buf)?;
             buf
         } else {
             // Zlib 压缩
-            let mut compressed_data = vec![0u8; n_size as usize];
+            let mut compressed_data =
+                vec![0u8; limits::check_length(n_size as i64, "压缩数据")?];
             reader.read_exact(&mut compressed_data)?;
 
             // 解压
-            let mut decoder = ZlibDecoder::new(&compressed_data[..]);
-            let mut decompressed = Vec::new();
-            decoder.read_to_end(&mut decompressed)?;
-            decompressed
+            limits::read_to_end(ZlibDecoder::new(&compressed_data[..]), "帧数据")?
         };
 
         // 创建图像
@@ -206,80 +330,65 @@ impl MLibraryV1 {
         img.height = height;
         img.x = x;
         img.y = y;
+        img.bo16bit = bo16bit;
+        img.has_alpha = has_alpha;
         img.fbytes = bytes.clone();
 
         // 将原始字节数据转换为图像
-        Self::convert_bytes_to_image(palette, &mut img, &bytes, bo16bit)?;
+        Self::convert_bytes_to_image(palette, &mut img, &bytes)?;
 
         Ok(img)
     }
 
     /// 将字节数据转换为图像
+    ///
+    /// 带 alpha 平面的 16 位帧按平面中的透明度解码，不再把纯黑当作透明；alpha 平面
+    /// 不完整时缺少的像素仍按纯黑透明处理。
     fn convert_bytes_to_image(
         palette: &[Color; 256],
         img: &mut MImage,
         bytes: &[u8],
-        bo16bit: bool,
     ) -> Result<()> {
-        let width = img.width as u32;
-        let height = img.height as u32;
-
+        let (layout, alpha_layout) = img.layout();
+        let (width, height) = (layout.width, layout.height);
         if width == 0 || height == 0 {
             return Err(LibraryError::InvalidImageData);
         }
 
         let mut rgba_img = RgbaImage::new(width, height);
-        let mut idx = 0;
-
-        // 计算每行字节数 (需要4字节对齐)
-        let row_bytes = if bo16bit { width * 2 } else { width };
-        let aligned_row_bytes = row_bytes.div_ceil(4) * 4;
-
-        for y in (0..height).rev() {
-            for x in 0..width {
-                if idx >= bytes.len() {
-                    break;
+        // alpha 平面紧接在行数据之后，行顺序相同（自下而上）
+        let alpha_data = bytes.get(layout.len()..).unwrap_or_default();
+        let mut alpha_rows = alpha_layout.rows(alpha_data);
+
+        for (row, pixels) in layout.rows(bytes).enumerate() {
+            let y = ROW_ORDER.map(row as u32, height);
+            let alpha = alpha_rows.next().unwrap_or_default();
+            if img.bo16bit {
+                // 16位颜色格式 (RGB565)
+                for (x, color) in pixels.chunks_exact(2).enumerate() {
+                    let color = u16::from_le_bytes([color[0], color[1]]);
+                    let pixel = match alpha.get(x / 2) {
+                        Some(&alpha_byte) => {
+                            let argb = convert_16bit_to_32bit_with_alpha(color, alpha_byte, x);
+                            let [b, g, r, a] = argb.to_le_bytes();
+                            [r, g, b, a]
+                        }
+                        // 如果全黑则透明
+                        None if color == 0 => [0, 0, 0, 0],
+                        None => rgb565_to_rgba(color),
+                    };
+                    rgba_img.put_pixel(x as u32, y, Rgba(pixel));
+                }
+            } else {
+                // 8位索引颜色
+                for (x, &index) in pixels.iter().enumerate() {
+                    let color = &palette[index as usize];
+                    rgba_img.put_pixel(x as u32, y, Rgba([color.r, color.g, color.b, color.a]));
                 }
-
-                let pixel = if bo16bit {
-                    // 16位颜色格式 (RGB565)
-                    let b1 = bytes[idx] as u16;
-                    let b2 = bytes[idx + 1] as u16;
-                    let color = (b2 << 8) | b1;
-                    idx += 2;
-
-                    // RGB565 转 RGB888
-                    let r = ((color & 0xF800) >> 8) as u8;
-                    let g = ((color & 0x07E0) >> 3) as u8;
-                    let b = ((color & 0x001F) << 3) as u8;
-
-                    // 如果全黑则透明
-                    if r == 0 && g == 0 && b == 0 {
-                        [0, 0, 0, 0]
-                    } else {
-                        [r, g, b, 255]
-                    }
-                } else {
-                    // 8位索引颜色
-                    let palette_idx = bytes[idx] as usize;
-                    idx += 1;
-
-                    if palette_idx < palette.len() {
-                        let color = &palette[palette_idx];
-                        [color.r, color.g, color.b, color.a]
-                    } else {
-                        [0, 0, 0, 0]
-                    }
-                };
-
-                rgba_img.put_pixel(x, y, Rgba(pixel));
             }
-
-            // 跳过对齐填充字节
-            idx += (aligned_row_bytes - row_bytes) as usize;
         }
 
-        img.image = Some(rgba_img);
+        img.image = Some(Arc::new(rgba_img));
         img.texture_valid = true;
         Ok(())
     }
@@ -293,12 +402,37 @@ impl MLibraryV1 {
             .ok_or_else(|| LibraryError::IndexOutOfBounds(index))
     }
 
-    /// 获取预览图
-    pub fn get_preview(&mut self, index: usize) -> Result<Option<&RgbaImage>> {
+    /// 释放指定帧，下次访问时从文件重新读取（不在文件索引中的帧不释放）
+    pub fn release_image(&mut self, index: usize) {
+        if index < self.index_list.len()
+            && let Some(image) = self.images.get_mut(index)
+        {
+            *image = None;
+        }
+    }
+
+    /// 新建空库（保存时写入 `file_name` 对应的 .wzx 和 .wzl）
+    pub fn empty(file_name: PathBuf) -> Self {
+        Self {
+            source: Source::Path(file_name.clone()),
+            file_name,
+            images: Vec::new(),
+            index_list: Vec::new(),
+            count: 0,
+            initialized: true,
+            load: true,
+            palette: DEFAULT_PALETTE,
+            level: CompressionLevel::default(),
+            wzl_reader: None,
+        }
+    }
+
+    /// 获取预览图（与缓存共享，不复制像素）
+    pub fn get_preview(&mut self, index: usize) -> Result<Option<Arc<RgbaImage>>> {
         self.check_image(index)?;
 
         if let Some(ref img) = self.images[index] {
-            Ok(img.image.as_ref())
+            Ok(img.image.clone())
         } else {
             Ok(None)
         }
@@ -346,81 +480,107 @@ impl MLibraryV1 {
         Ok(())
     }
 
-    /// 保存库文件
-    pub fn save(&self) -> Result<()> {
-        let wzl_path = format!("{}.wzl", self.file_name);
-
-        // 使用内存流计算索引
-        let mut data_stream = Vec::new();
-        let mut index_list: Vec<u32> = Vec::new();
-
-        let offset = 8 + (self.images.len() * 4) as u32;
-
-        for img in self.images.iter().flatten() {
-            // 当前位置 + 初始偏移
-            let current_offset = data_stream.len() as u32 + offset;
-            index_list.push(current_offset);
-
-            // 写入图像数据
-            self.write_mimage_data(img, &mut data_stream)?;
+    /// 保存库文件（.wzx 索引和 .wzl 数据，结构与读取时相同）
+    ///
+    /// 尚未读取的帧先全部读入，帧数据按 [`level`](Self::level) 压缩。WZL 帧记录没有
+    /// 遮罩层和阴影字段，带有这些信息的帧保存时去掉并给出警告。
+    pub fn save(&mut self) -> Result<()> {
+        for index in 0..self.images.len() {
+            self.check_image(index)?;
         }
 
-        // 写入 .wzl 文件
-        let file = File::create(&wzl_path)?;
-        let mut writer = BufWriter::new(file);
-
-        // 写入文件头
-        writer.write_i32::<LittleEndian>(Self::LIB_VERSION)?;
-        writer.write_i32::<LittleEndian>(self.images.len() as i32)?;
+        let mut data = Self::file_header(Self::WZL_HEADER_SIZE, self.images.len());
+        let mut index_list = Vec::with_capacity(self.images.len());
+        let mut stripped = Vec::new();
+        for (index, image) in self.images.iter().enumerate() {
+            let image = image
+                .as_ref()
+                .ok_or(LibraryError::IndexOutOfBounds(index))?;
+            if image.is_empty() {
+                index_list.push(0);
+                continue;
+            }
+            if image.has_mask || image.shadow != 0 {
+                stripped.push(index);
+            }
+            index_list.push(data.len() as u32);
+            Self::write_frame(index, image, self.level, &mut data)?;
+        }
+        if !stripped.is_empty() {
+            tracing::warn!(
+                frame = stripped[0],
+                "WZL 不支持遮罩层和阴影，已去掉 {} 帧的遮罩层/阴影（第一帧为 #{}）",
+                stripped.len(),
+                stripped[0]
+            );
+        }
 
-        // 写入索引列表
-        for index in &index_list {
-            writer.write_i32::<LittleEndian>(*index as i32)?;
+        let mut index = Self::file_header(Self::WZX_HEADER_SIZE, index_list.len());
+        for &offset in &index_list {
+            index.write_u32::<LittleEndian>(offset)?;
         }
 
-        // 写入图像数据
-        writer.write_all(&data_stream)?;
-        writer.flush()?;
+        // 两个文件都写完并落盘后才替换：先替换数据再替换索引。两次改名之间崩溃会留下新的
+        // .wzl 和旧的 .wzx，偏移对不上；开启备份时可用同一次保存留下的 .bak1 恢复旧的一对
+        let mut data_file = AtomicFile::create(&find_companion(&self.file_name, ".wzl"))?;
+        data_file.write_all(&data)?;
+        data_file.sync()?;
+        let mut index_file = AtomicFile::create(&find_companion(&self.file_name, ".wzx"))?;
+        index_file.write_all(&index)?;
+        index_file.sync()?;
+
+        // 先关闭正在读取的数据文件再覆盖
+        self.wzl_reader = None;
+        data_file.commit()?;
+        index_file.commit()?;
 
+        self.source = Source::Path(self.file_name.clone());
+        self.wzl_reader = Some(self.source.open(".wzl")?);
+        self.count = index_list.len();
+        self.index_list = index_list;
         Ok(())
     }
 
-    /// 写入 MImage 数据 (匹配 C# 的 MImage.Save 方法)
-    fn write_mimage_data(&self, image: &MImage, writer: &mut Vec<u8>) -> Result<()> {
-        use byteorder::{LittleEndian, WriteBytesExt};
+    /// 文件头：标识加上位于 [`WZX_COUNT_OFFSET`](Self::WZX_COUNT_OFFSET) 的图像数量
+    fn file_header(size: u64, count: usize) -> Vec<u8> {
+        let mut header = vec![0u8; size as usize];
+        header[..Self::TITLE.len()].copy_from_slice(Self::TITLE);
+        header[Self::WZX_COUNT_OFFSET..Self::WZX_COUNT_OFFSET + 4]
+            .copy_from_slice(&(count as i32).to_le_bytes());
+        header
+    }
 
-        // 写入基础信息 (14字节: Width + Height + X + Y + ShadowX + ShadowY)
+    /// 写入一帧：16 字节头部加 zlib 压缩的行数据（与 `read_mimage` 对应）
+    fn write_frame(
+        index: usize,
+        image: &MImage,
+        level: CompressionLevel,
+        writer: &mut Vec<u8>,
+    ) -> Result<()> {
+        let (rows, alpha) = image.layout();
+        let expected = rows.len() + alpha.len();
+        if image.fbytes.len() != expected {
+            return Err(LibraryError::ParseError(format!(
+                "第 {} 帧的像素数据为 {} 字节，与 WZL 行数据的 {} 字节不符",
+                index,
+                image.fbytes.len(),
+                expected
+            )));
+        }
+        let compressed = compress_zlib_with(&image.fbytes, level)?;
+
+        writer.write_u8(match (image.bo16bit, image.has_alpha) {
+            (true, true) => FORMAT_16BIT_ALPHA,
+            (true, false) => FORMAT_16BIT,
+            (false, _) => FORMAT_8BIT,
+        })?;
+        writer.write_all(&[0u8; 3])?;
         writer.write_i16::<LittleEndian>(image.width)?;
         writer.write_i16::<LittleEndian>(image.height)?;
         writer.write_i16::<LittleEndian>(image.x)?;
         writer.write_i16::<LittleEndian>(image.y)?;
-        writer.write_i16::<LittleEndian>(image.shadow_x)?;
-        writer.write_i16::<LittleEndian>(image.shadow_y)?;
-
-        // 写入 Shadow 字节 (1字节) - 最高位表示是否有 Mask
-        let shadow_byte = if image.has_mask {
-            image.shadow | 0x80
-        } else {
-            image.shadow
-        };
-        writer.write_u8(shadow_byte)?;
-
-        // 写入数据长度 (4字节)
-        writer.write_i32::<LittleEndian>(image.fbytes.len() as i32)?;
-
-        // 写入图像数据
-        writer.extend_from_slice(&image.fbytes);
-
-        // 如果有 Mask 层，写入 Mask 数据
-        if image.has_mask {
-            writer.write_i16::<LittleEndian>(image.mask_width)?;
-            writer.write_i16::<LittleEndian>(image.mask_height)?;
-            writer.write_i16::<LittleEndian>(image.mask_x)?;
-            writer.write_i16::<LittleEndian>(image.mask_y)?;
-            writer.write_i32::<LittleEndian>(image.mask_fbytes.len() as i32)?;
-            writer.extend_from_slice(&image.mask_fbytes);
-        }
-
+        writer.write_i32::<LittleEndian>(compressed.len() as i32)?;
+        writer.write_all(&compressed)?;
         Ok(())
     }
 
@@ -429,6 +589,11 @@ impl MLibraryV1 {
         self.count
     }
 
+    /// 获取 8 位帧使用的调色板
+    pub fn get_palette(&self) -> &[Color; 256] {
+        &self.palette
+    }
+
     /// 手动关闭 WZL 文件流
     pub fn close(&mut self) {
         self.wzl_reader = None;
@@ -443,138 +608,130 @@ impl Drop for MLibraryV1 {
 }
 
 /// MImage - 传奇2库文件中的图像结构
-#[derive(Debug, Clone)]
+///
+/// 尺寸、偏移、阴影和遮罩层等公共字段在 [`FrameData`] 中，可直接访问。
+#[derive(Debug, Clone, Default)]
 pub struct MImage {
-    /// 图像宽度
-    pub width: i16,
-    /// 图像高度
-    pub height: i16,
-    /// X 偏移
-    pub x: i16,
-    /// Y 偏移
-    pub y: i16,
-    /// 阴影 X 偏移
-    pub shadow_x: i16,
-    /// 阴影 Y 偏移
-    pub shadow_y: i16,
-    /// 阴影值
-    pub shadow: u8,
-    /// 压缩后的图像数据
+    /// 各格式共有的字段
+    pub frame: FrameData,
+    /// 像素为 16 位 RGB565（否则为 8 位调色板索引）
+    pub bo16bit: bool,
+    /// 16 位帧的行数据之后附有 4 位 alpha 平面
+    pub has_alpha: bool,
+    /// 像素数据（读取自 WZL 时为解压后的行数据，每行按 4 字节对齐；有 alpha 平面时
+    /// 紧接其后）
     pub fbytes: Vec<u8>,
-    /// 图像纹理是否有效
-    pub texture_valid: bool,
-    /// 解码后的图像
-    pub image: Option<RgbaImage>,
-    /// 预览图 (64x64)
-    pub preview: Option<RgbaImage>,
-
-    // Layer 2 (Mask)
-    /// 是否有遮罩层
-    pub has_mask: bool,
-    /// 遮罩宽度
-    pub mask_width: i16,
-    /// 遮罩高度
-    pub mask_height: i16,
-    /// 遮罩 X 偏移
-    pub mask_x: i16,
-    /// 遮罩 Y 偏移
-    pub mask_y: i16,
-    /// 遮罩数据
-    pub mask_fbytes: Vec<u8>,
-    /// 遮罩图像
-    pub mask_image: Option<RgbaImage>,
+}
+
+impl Deref for MImage {
+    type Target = FrameData;
+
+    fn deref(&self) -> &FrameData {
+        &self.frame
+    }
+}
+
+impl DerefMut for MImage {
+    fn deref_mut(&mut self) -> &mut FrameData {
+        &mut self.frame
+    }
+}
+
+impl FrameCodec for MImage {
+    const ROW_ORDER: RowOrder = ROW_ORDER;
+
+    fn encode(image: &RgbaImage, x: i16, y: i16) -> Self {
+        Self::from_image(image, x, y)
+    }
 }
 
 impl MImage {
     /// 创建一个新的空白图像
     pub fn new() -> Self {
-        Self {
-            width: 0,
-            height: 0,
-            x: 0,
-            y: 0,
-            shadow_x: 0,
-            shadow_y: 0,
-            shadow: 0,
-            fbytes: Vec::new(),
-            texture_valid: false,
-            image: None,
-            preview: None,
-            has_mask: false,
-            mask_width: 0,
-            mask_height: 0,
-            mask_x: 0,
-            mask_y: 0,
-            mask_fbytes: Vec::new(),
-            mask_image: None,
-        }
+        Self::default()
     }
 
-    /// 从图像数据创建 MImage
+    /// 把 RGBA 图像编码为 16 位帧（与 `read_mimage` 的解码对应）
+    ///
+    /// 有半透明像素时附 4 位 alpha 平面，否则按客户端的纯黑透明写出，见
+    /// [`from_image_16bit`](Self::from_image_16bit)。
     pub fn from_image(image: &RgbaImage, x: i16, y: i16) -> Self {
-        let width = image.width() as i16;
-        let height = image.height() as i16;
-
-        // 固定图像大小为4的倍数
-        let fixed_width = width + (4 - width % 4) % 4;
-        let fixed_height = height + (4 - height % 4) % 4;
-
-        let mut fixed_image = image.clone();
-
-        if fixed_width != width || fixed_height != height {
-            // 需要调整大小
-            fixed_image = RgbaImage::from_fn(fixed_width as u32, fixed_height as u32, |x, y| {
-                if x < image.width() && y < image.height() {
-                    *image.get_pixel(x, y)
-                } else {
-                    Rgba([0, 0, 0, 0])
-                }
-            });
+        let with_alpha = image.pixels().any(|p| p[3] != 0 && p[3] != 255);
+        Self::from_image_16bit(image, x, y, with_alpha)
+    }
+
+    /// 把 RGBA 图像编码为 16 位帧，`with_alpha` 指定是否附 alpha 平面
+    ///
+    /// 像素取最接近的 RGB565，行数据自下而上、每行按 4 字节对齐。没有 alpha 平面时
+    /// alpha 低于一半的像素写成纯黑（客户端按纯黑透明），不透明像素落到纯黑时改写成
+    /// 最暗的非零色，避免被抠掉。
+    pub fn from_image_16bit(image: &RgbaImage, x: i16, y: i16, with_alpha: bool) -> Self {
+        let mut frame = MImage::new();
+        let (width, height) = image.dimensions();
+        (frame.width, frame.height, frame.x, frame.y) = (width as i16, height as i16, x, y);
+        frame.bo16bit = true;
+        frame.has_alpha = with_alpha;
+        if width == 0 || height == 0 {
+            return frame;
         }
 
-        let pixels = Self::convert_image_to_bytes(&fixed_image);
-        let fbytes = compress_gzip(&pixels).unwrap_or_default();
+        let (layout, alpha_layout) = frame.layout();
+        let mut bytes = vec![0u8; layout.len() + alpha_layout.len()];
+        let (rows, alpha) = bytes.split_at_mut(layout.len());
+        for (x, y, pixel) in image.enumerate_pixels() {
+            let [r, g, b, a] = pixel.0;
+            let color = match (with_alpha, a) {
+                (_, 0) | (false, 0..=127) => 0,
+                (true, _) => rgb_to_rgb565(r, g, b),
+                (false, _) => match rgb_to_rgb565(r, g, b) {
+                    0 => NEAR_BLACK,
+                    color => color,
+                },
+            };
+            // 按存储行序换算
+            let row = ROW_ORDER.map(y, height);
+            let at = layout.row(row).start + x as usize * 2;
+            rows[at..at + 2].copy_from_slice(&color.to_le_bytes());
+            if with_alpha {
+                // 每字节两个像素，偶数列在高 4 位
+                let level = ((a as u32 * 15 + 127) / 255) as u8;
+                let shift = if x % 2 == 0 { 4 } else { 0 };
+                alpha[alpha_layout.row(row).start + x as usize / 2] |= level << shift;
+            }
+        }
 
-        Self {
-            width: fixed_width,
-            height: fixed_height,
-            x,
-            y,
-            shadow_x: 0,
-            shadow_y: 0,
-            shadow: 0,
-            fbytes,
-            texture_valid: true,
-            image: Some(fixed_image),
-            preview: None,
-            has_mask: false,
-            mask_width: 0,
-            mask_height: 0,
-            mask_x: 0,
-            mask_y: 0,
-            mask_fbytes: Vec::new(),
-            mask_image: None,
+        // 按读取时的方式解码一次，预览与保存后重新打开的结果一致
+        if let Err(e) = MLibraryV1::convert_bytes_to_image(&DEFAULT_PALETTE, &mut frame, &bytes) {
+            tracing::warn!("解码编码后的 16 位帧失败: {:?}", e);
         }
+        frame.fbytes = bytes;
+        frame
+    }
+
+    /// 是否为空帧（与读取时一致：面积过小或没有像素数据）
+    pub fn is_empty(&self) -> bool {
+        (self.width as i32) * (self.height as i32) < 4 || self.fbytes.is_empty()
     }
 
-    /// 将图像转换为字节数组
-    fn convert_image_to_bytes(image: &RgbaImage) -> Vec<u8> {
-        let mut pixels = Vec::with_capacity((image.width() * image.height() * 4) as usize);
+    /// 行数据和 alpha 平面的布局，没有 alpha 平面时平面为 0 行
+    pub fn layout(&self) -> (RowLayout, RowLayout) {
+        frame_layout(self.width, self.height, self.bo16bit, self.has_alpha)
+    }
 
-        for pixel in image.pixels() {
-            let [r, g, b, a] = pixel.0;
-            // 交换 R 和 B (BGR 格式)
-            pixels.push(b);
-            pixels.push(g);
-            pixels.push(r);
-            pixels.push(if r == 0 && g == 0 && b == 0 { 0 } else { a });
+    /// 获取 8 位帧中像素的调色板索引（行方向与解码后的图像一致）
+    ///
+    /// 16 位帧或坐标越界时返回 `None`。
+    pub fn palette_index(&self, x: u32, y: u32) -> Option<u8> {
+        let (width, height) = (self.width.max(0) as u32, self.height.max(0) as u32);
+        if self.bo16bit || x >= width || y >= height {
+            return None;
         }
-
-        pixels
+        let row = self.layout().0.row(ROW_ORDER.map(y, height));
+        self.fbytes.get(row.start + x as usize).copied()
     }
 
-    /// 从字节数组创建图像
-    pub fn create_texture(&mut self, data: &[u8]) -> Result<()> {
+    /// 从 GZip 压缩的 BGRA 像素创建图像，行按 `order` 存储
+    pub fn create_texture(&mut self, data: &[u8], order: RowOrder) -> Result<()> {
         if self.width <= 0 || self.height <= 0 {
             return Err(LibraryError::InvalidImageData);
         }
@@ -600,62 +757,171 @@ impl MImage {
                     let g = decompressed[idx + 1];
                     let r = decompressed[idx + 2];
                     let a = decompressed[idx + 3];
-                    img.put_pixel(x, height - 1 - y, Rgba([r, g, b, a]));
+                    img.put_pixel(x, order.map(y, height), Rgba([r, g, b, a]));
                 }
             }
         }
 
-        self.image = Some(img);
+        self.image = Some(Arc::new(img));
         self.texture_valid = true;
         Ok(())
     }
+}
 
-    /// 创建预览图 (64x64)
-    pub fn create_preview(&mut self) {
-        if let Some(ref image) = self.image {
-            use image::imageops;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-            let w = std::cmp::min(image.width(), 64);
-            let h = std::cmp::min(image.height(), 64);
+    #[test]
+    fn test_create_library() {
+        let lib = MLibraryV1::new(PathBuf::from("test"));
+        assert!(lib.is_err()); // 文件不存在
+    }
 
-            let resized = imageops::resize(image, w, h, imageops::FilterType::Triangle);
+    #[test]
+    fn test_parse_index_respects_declared_count() {
+        let mut data = vec![0u8; MLibraryV1::WZX_HEADER_SIZE as usize];
+        data[MLibraryV1::WZX_COUNT_OFFSET] = 2;
+        for offset in [100u32, 200, 300] {
+            data.extend_from_slice(&offset.to_le_bytes());
+        }
+        // 末尾多出的半个条目应被忽略
+        data.extend_from_slice(&[0xFF, 0xFF]);
 
-            let preview = RgbaImage::from_fn(64, 64, |x, y| {
-                let offset_x = (64 - w) / 2;
-                let offset_y = (64 - h) / 2;
-                if x >= offset_x && x < offset_x + w && y >= offset_y && y < offset_y + h {
-                    *resized.get_pixel(x - offset_x, y - offset_y)
-                } else {
-                    Rgba([0, 0, 0, 0])
-                }
-            });
+        assert_eq!(MLibraryV1::parse_index(&data), vec![100, 200]);
 
-            self.preview = Some(preview);
-        }
+        // 未声明数量时读取到文件末尾
+        data[MLibraryV1::WZX_COUNT_OFFSET] = 0;
+        assert_eq!(MLibraryV1::parse_index(&data), vec![100, 200, 300]);
     }
 
-    /// 获取预览图
-    pub fn get_preview(&mut self) -> Option<&RgbaImage> {
-        if self.preview.is_none() {
-            self.create_preview();
-        }
-        self.preview.as_ref()
+    #[test]
+    fn test_save_round_trip() {
+        let temp = tempfile::tempdir().unwrap();
+        let base = temp.path().join("v1_round_trip");
+        let mut lib = MLibraryV1::empty(base.clone());
+        // 8 位帧：宽 3，每行补齐到 4 字节
+        let mut indexed = MImage::new();
+        (indexed.width, indexed.height, indexed.x, indexed.y) = (3, 2, -4, 7);
+        indexed.fbytes = vec![1, 2, 3, 0, 4, 5, 6, 0];
+        lib.add_image(&indexed);
+        lib.add_image(&MImage::new());
+        // 16 位帧带遮罩层和阴影，保存时去掉
+        let mut rgb = MImage::new();
+        (rgb.width, rgb.height, rgb.bo16bit, rgb.shadow) = (2, 2, true, 2);
+        rgb.fbytes = vec![0x00, 0xF8, 0xE0, 0x07, 0x1F, 0x00, 0xFF, 0xFF];
+        rgb.has_mask = true;
+        rgb.mask_fbytes = vec![1; 4];
+        lib.add_image(&rgb);
+        lib.save().unwrap();
+
+        let mut opened = MLibraryV1::new(base.clone()).unwrap();
+        assert_eq!(opened.count(), 3);
+        let frame = opened.get_image(0).unwrap();
+        assert_eq!((frame.x, frame.y, frame.bo16bit), (-4, 7, false));
+        assert_eq!(frame.fbytes, indexed.fbytes);
+        assert_eq!(frame.palette_index(0, 1), Some(1));
+        assert_eq!(frame.palette_index(2, 0), Some(6));
+        assert_eq!(frame.palette_index(3, 0), None);
+        assert!(opened.get_image(1).unwrap().is_empty());
+        let frame = opened.get_image(2).unwrap().clone();
+        assert!(frame.bo16bit && !frame.has_mask && frame.shadow == 0);
+        assert_eq!(frame.palette_index(0, 0), None);
+        assert_eq!(frame.fbytes, rgb.fbytes);
+        // 行数据自下而上：第一行在图像底部；RGB565 的分量按位复制展开到 0-255
+        let golden = RgbaImage::from_raw(
+            2,
+            2,
+            vec![
+                0, 0, 255, 255, 255, 255, 255, 255, 255, 0, 0, 255, 0, 255, 0, 255,
+            ],
+        )
+        .unwrap();
+        assert_eq!(frame.image.as_deref(), Some(&golden));
+
+        // 再次保存得到相同的文件
+        let wzl = find_companion(&base, ".wzl");
+        let saved = std::fs::read(&wzl).unwrap();
+        opened.save().unwrap();
+        assert_eq!(std::fs::read(&wzl).unwrap(), saved);
     }
-}
 
-impl Default for MImage {
-    fn default() -> Self {
-        Self::new()
+    #[test]
+    fn test_decode_alpha_plane() {
+        let temp = tempfile::tempdir().unwrap();
+        let base = temp.path().join("v1_alpha");
+        let mut lib = MLibraryV1::empty(base.clone());
+        // 2x2 的 16 位帧：行数据（每行 4 字节）之后是 alpha 平面（每行 4 字节，每像素 4 位）
+        let mut effect = MImage::new();
+        (effect.width, effect.height, effect.bo16bit) = (2, 2, true);
+        effect.has_alpha = true;
+        // 底行：黑、红；顶行：绿、蓝
+        effect.fbytes = vec![0x00, 0x00, 0x00, 0xF8, 0xE0, 0x07, 0x1F, 0x00];
+        // 底行 alpha: F、8；顶行 alpha: 0、F
+        effect.fbytes.extend([0xF8, 0, 0, 0, 0x0F, 0, 0, 0]);
+        assert_eq!(effect.layout().1.len(), 8);
+        lib.add_image(&effect);
+        lib.save().unwrap();
+
+        let mut opened = MLibraryV1::new(base.clone()).unwrap();
+        let frame = opened.get_image(0).unwrap().clone();
+        assert!(frame.bo16bit && frame.has_alpha);
+        assert_eq!(frame.fbytes, effect.fbytes);
+        let image = frame.frame.image.unwrap();
+        // 有 alpha 平面时纯黑不再当作透明
+        assert_eq!(image.get_pixel(0, 1).0, [0, 0, 0, 255]);
+        assert_eq!(image.get_pixel(1, 1).0, [255, 0, 0, 136]);
+        assert_eq!(image.get_pixel(0, 0).0[3], 0);
+        assert_eq!(image.get_pixel(1, 0).0, [0, 0, 255, 255]);
+
+        let headers = opened.frame_headers().unwrap();
+        assert!(!headers[0].is_empty());
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
 
     #[test]
-    fn test_create_library() {
-        let lib = MLibraryV1::new("test".to_string());
-        assert!(lib.is_err()); // 文件不存在
+    fn test_encode_16bit_frames() {
+        let temp = tempfile::tempdir().unwrap();
+        let base = temp.path().join("v1_encode");
+        // 3x2：奇数宽度要补齐行，含纯黑、透明和接近透明的像素
+        let mut opaque = RgbaImage::new(3, 2);
+        opaque.put_pixel(0, 0, Rgba([255, 0, 0, 255]));
+        opaque.put_pixel(1, 0, Rgba([0, 0, 0, 255]));
+        opaque.put_pixel(2, 0, Rgba([0, 255, 0, 100]));
+        opaque.put_pixel(0, 1, Rgba([10, 20, 250, 255]));
+        let encoded = MImage::from_image_16bit(&opaque, 4, -7, false);
+        assert!(encoded.bo16bit && !encoded.has_alpha);
+        assert!(MImage::from_image(&opaque, 4, -7).has_alpha);
+        assert_eq!(encoded.fbytes.len(), 8 * 2);
+        // 顶行存在后面：红色、最暗的非零色、透明
+        assert_eq!(&encoded.fbytes[8..14], &[0x00, 0xF8, 0x21, 0x08, 0, 0]);
+
+        let mut faded = RgbaImage::from_pixel(4, 1, Rgba([255, 255, 255, 255]));
+        faded.put_pixel(1, 0, Rgba([255, 255, 255, 128]));
+        faded.put_pixel(2, 0, Rgba([0, 0, 0, 0]));
+        let effect = MImage::from_image(&faded, 0, 0);
+        assert!(effect.has_alpha);
+        assert_eq!(&effect.fbytes[8..10], &[0xF8, 0x0F]);
+
+        let mut lib = MLibraryV1::empty(base.clone());
+        lib.add_image(&encoded);
+        lib.add_image(&effect);
+        lib.add_image(&MImage::from_image(&RgbaImage::new(0, 0), 0, 0));
+        lib.save().unwrap();
+
+        let mut opened = MLibraryV1::new(base.clone()).unwrap();
+        let frame = opened.get_image(0).unwrap().clone();
+        assert_eq!((frame.x, frame.y), (4, -7));
+        let image = frame.frame.image.unwrap();
+        assert_eq!(image.get_pixel(0, 0).0, [255, 0, 0, 255]);
+        assert_eq!(image.get_pixel(1, 0).0, [8, 4, 8, 255]);
+        assert_eq!(image.get_pixel(2, 0).0[3], 0);
+        assert_eq!(image.get_pixel(0, 1).0, [8, 20, 247, 255]);
+        assert_eq!(encoded.image.as_deref(), Some(&*image));
+
+        let image = opened.get_image(1).unwrap().image.clone().unwrap();
+        assert_eq!(image.get_pixel(0, 0).0, [255, 255, 255, 255]);
+        assert_eq!(image.get_pixel(1, 0).0, [255, 255, 255, 136]);
+        assert_eq!(image.get_pixel(2, 0).0[3], 0);
+        assert!(opened.get_image(2).unwrap().is_empty());
     }
 }
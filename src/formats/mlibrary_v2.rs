@@ -1,105 +1,225 @@
 //! MLibrary V2 格式解析 (.Lib)
 //! 这是传奇2使用的自定义库文件格式
+//!
+//! 索引中的帧偏移为 32 位，文件不能超过 4 GB。合并后的私服资源可能更大，
+//! 此时保存为 V2.1：版本号带 [`MLibraryV2::FLAG_WIDE_OFFSETS`] 标志，索引为 64 位偏移。
+//! 游戏客户端只认 V2，不需要时不要使用。
+//!
+//! 帧数据默认用 GZip 压缩。编辑器自己使用的库可以改用 zstd（[`Codec::Zstd`]，版本号带
+//! [`MLibraryV2::FLAG_ZSTD`] 标志），文件更小、解码更快；交给游戏客户端的库保存为
+//! GZip 时，zstd 帧会重新压缩。保存时还可以指定压缩级别（[`MLibraryV2::level`]），
+//! 全部帧按该级别重新压缩。
 
 use crate::error::{LibraryError, Result};
+use crate::formats::atomic_file::AtomicFile;
+use crate::formats::find_companion;
+use crate::formats::frame::{FrameCodec, FrameData, FrameProperties};
+use crate::formats::header::FrameHeader;
+use crate::formats::limits;
+use crate::formats::source::{ReadSeek, Source};
+use crate::image::canvas::{self, Anchor};
+use crate::image::compression::CompressionLevel;
+use crate::image::rows::RowOrder;
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
-use flate2::Compression;
 use flate2::read::GzDecoder;
 use flate2::write::GzEncoder;
 use image::{Rgba, RgbaImage};
-use std::fs::File;
-use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
-use std::path::Path;
+use std::borrow::Cow;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::ops::{Deref, DerefMut, Range};
+use std::path::PathBuf;
+use std::sync::Arc;
 
 /// MLibrary V2 - 用于处理 .Lib 文件
 pub struct MLibraryV2 {
-    /// 文件名
-    pub file_name: String,
+    /// 文件路径（不带扩展名）
+    pub file_name: PathBuf,
     /// 图像列表
     pub images: Vec<Option<MImage>>,
     /// 索引列表
-    pub index_list: Vec<u32>,
+    pub index_list: Vec<u64>,
     /// 图像计数
     pub count: usize,
+    /// 保存为 V2.1（64 位偏移）；读取 V2.1 文件时自动设置
+    pub wide_offsets: bool,
+    /// 保存时帧数据的压缩方式；读取时按文件头设置
+    pub codec: Codec,
+    /// 保存时的压缩级别；设置后全部帧按该级别重新压缩，`None` 时已压缩的帧原样写入
+    pub level: Option<CompressionLevel>,
     /// 是否已初始化
     initialized: bool,
     /// 是否加载图像
     pub load: bool,
+    /// 读取帧数据的来源（保存时总是写入 `file_name`）
+    source: Source,
 }
 
-/// MLibrary V2 的 MImage 结构
-#[derive(Debug, Clone)]
-pub struct MImage {
-    /// 图像宽度
-    pub width: i16,
-    /// 图像高度
-    pub height: i16,
-    /// X 偏移
+/// 统一帧尺寸的结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NormalizeReport {
+    /// 统一后的 X 偏移
     pub x: i16,
-    /// Y 偏移
+    /// 统一后的 Y 偏移
     pub y: i16,
-    /// 阴影 X 偏移
-    pub shadow_x: i16,
-    /// 阴影 Y 偏移
-    pub shadow_y: i16,
-    /// 阴影值
-    pub shadow: u8,
+    /// 统一后的宽度
+    pub width: i16,
+    /// 统一后的高度
+    pub height: i16,
+    /// 实际被修改的帧索引
+    pub frames: Vec<usize>,
+}
+
+/// 填充画布的结果
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PadReport {
+    /// 实际被修改的帧索引
+    pub frames: Vec<usize>,
+    /// 比画布大、未处理的帧索引
+    pub oversized: Vec<usize>,
+}
+
+/// .Lib 像素行的存储顺序（与解码后的图像相同，像素原样读写）
+pub const ROW_ORDER: RowOrder = RowOrder::TopDown;
+
+/// 近黑色：保留黑色像素时代替纯黑写入
+pub const NEAR_BLACK: [u8; 3] = [1, 1, 1];
+
+/// 像素编码和保存格式选项
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EncodeOptions {
+    /// 把不透明的纯黑像素写成 [`NEAR_BLACK`]
+    ///
+    /// 游戏客户端绘制时把 RGB 全为 0 的像素当作透明，即使 Alpha 不为 0；
+    /// 描边、瞳孔等本该显示的黑色会被抠掉。写成 (1,1,1) 肉眼看不出差别，客户端照常绘制。
+    pub preserve_black: bool,
+    /// 新建的库保存为 V2.1（64 位偏移），可超过 4 GB
+    pub wide_offsets: bool,
+    /// 帧数据的压缩方式
+    pub codec: Codec,
+    /// 压缩级别（`None` 为默认级别）；新建的库保存时全部帧按该级别重新压缩
+    pub level: Option<CompressionLevel>,
+}
+
+/// 帧数据的压缩方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Codec {
+    /// GZip，游戏客户端只认这种
+    #[default]
+    Gzip,
+    /// zstd，只有编辑器能读
+    Zstd,
+}
+
+impl Codec {
+    /// zstd 帧的魔数
+    const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+
+    /// 按数据开头的魔数判断压缩方式
+    pub fn detect(data: &[u8]) -> Self {
+        if data.starts_with(&Self::ZSTD_MAGIC) {
+            Self::Zstd
+        } else {
+            Self::Gzip
+        }
+    }
+
+    /// 按默认级别压缩
+    pub fn compress(self, data: &[u8]) -> Vec<u8> {
+        self.compress_with(data, CompressionLevel::default())
+    }
+
+    /// 按指定级别压缩
+    ///
+    /// ruzstd 目前只实现了最快的一级，zstd 除 0 级（不压缩）外都按最快级别压缩。
+    pub fn compress_with(self, data: &[u8], level: CompressionLevel) -> Vec<u8> {
+        match self {
+            Self::Gzip => {
+                let mut compressed = Vec::new();
+                {
+                    let mut encoder = GzEncoder::new(&mut compressed, level.flate());
+                    encoder.write_all(data).unwrap();
+                }
+                compressed
+            }
+            Self::Zstd => {
+                let zstd_level = match level.get() {
+                    0 => ruzstd::encoding::CompressionLevel::Uncompressed,
+                    _ => ruzstd::encoding::CompressionLevel::Fastest,
+                };
+                ruzstd::encoding::compress_to_vec(data, zstd_level)
+            }
+        }
+    }
+
+    /// 能否按指定级别压缩：zstd 只区分 0 级（不压缩）和最快一级，其余级别没有效果
+    pub fn supports_level(self, level: CompressionLevel) -> bool {
+        match self {
+            Self::Gzip => true,
+            Self::Zstd => level.get() == 0,
+        }
+    }
+
+    /// 解压，压缩方式按数据本身判断
+    pub fn decompress(data: &[u8]) -> Result<Vec<u8>> {
+        match Self::detect(data) {
+            Self::Gzip => limits::read_to_end(GzDecoder::new(data), "像素数据"),
+            Self::Zstd => {
+                let decoder = ruzstd::decoding::StreamingDecoder::new(data)
+                    .map_err(|e| LibraryError::Compression(e.to_string()))?;
+                limits::read_to_end(decoder, "像素数据")
+            }
+        }
+    }
+}
+
+/// MLibrary V2 的 MImage 结构
+///
+/// 尺寸、偏移、阴影和遮罩层等公共字段在 [`FrameData`] 中，可直接访问。
+#[derive(Debug, Clone, Default)]
+pub struct MImage {
+    /// 各格式共有的字段
+    pub frame: FrameData,
     /// 数据长度
     pub length: i32,
-    /// 压缩后的图像数据 (GZip)
+    /// 压缩后的图像数据（GZip 或 zstd）
     pub fbytes: Vec<u8>,
-    /// 纹理是否有效
-    pub texture_valid: bool,
-    /// 解码后的图像
-    pub image: Option<RgbaImage>,
-    /// 预览图 (64x64)
-    pub preview: Option<RgbaImage>,
-
-    // Layer 2 (Mask)
-    /// 是否有遮罩层
-    pub has_mask: bool,
-    /// 遮罩宽度
-    pub mask_width: i16,
-    /// 遮罩高度
-    pub mask_height: i16,
-    /// 遮罩 X 偏移
-    pub mask_x: i16,
-    /// 遮罩 Y 偏移
-    pub mask_y: i16,
-    /// 遮罩数据
-    pub mask_fbytes: Vec<u8>,
-    /// 遮罩图像
-    pub mask_image: Option<RgbaImage>,
+}
+
+impl Deref for MImage {
+    type Target = FrameData;
+
+    fn deref(&self) -> &FrameData {
+        &self.frame
+    }
+}
+
+impl DerefMut for MImage {
+    fn deref_mut(&mut self) -> &mut FrameData {
+        &mut self.frame
+    }
+}
+
+impl FrameCodec for MImage {
+    const ROW_ORDER: RowOrder = ROW_ORDER;
+
+    fn encode(image: &RgbaImage, x: i16, y: i16) -> Self {
+        Self::from_image(image, x, y)
+    }
 }
 
 impl MImage {
     /// 创建新的空白图像
     pub fn new() -> Self {
-        Self {
-            width: 0,
-            height: 0,
-            x: 0,
-            y: 0,
-            shadow_x: 0,
-            shadow_y: 0,
-            shadow: 0,
-            length: 0,
-            fbytes: Vec::new(),
-            texture_valid: false,
-            image: None,
-            preview: None,
-            has_mask: false,
-            mask_width: 0,
-            mask_height: 0,
-            mask_x: 0,
-            mask_y: 0,
-            mask_fbytes: Vec::new(),
-            mask_image: None,
-        }
+        Self::default()
     }
 
     /// 从位图创建 MImage
     pub fn from_image(img: &RgbaImage, x: i16, y: i16) -> Self {
+        Self::from_image_with(img, x, y, EncodeOptions::default())
+    }
+
+    /// 按编码选项从位图创建 MImage
+    pub fn from_image_with(img: &RgbaImage, x: i16, y: i16, options: EncodeOptions) -> Self {
         let width = img.width() as i16;
         let height = img.height() as i16;
 
@@ -108,11 +228,13 @@ impl MImage {
         result.height = height;
         result.x = x;
         result.y = y;
-        result.image = Some(img.clone());
+        result.image = Some(Arc::new(img.clone()));
 
         // 转换为字节数组并压缩
-        let pixels = Self::convert_bitmap_to_array(img);
-        result.fbytes = Self::compress(&pixels);
+        let pixels = Self::convert_bitmap_to_array(img, options);
+        result.fbytes = options
+            .codec
+            .compress_with(&pixels, options.level.unwrap_or_default());
         result.length = result.fbytes.len() as i32;
         result.texture_valid = true;
 
@@ -127,46 +249,104 @@ impl MImage {
         result.mask_height = mask_img.height() as i16;
         result.mask_image = Some(mask_img.clone());
 
-        let mask_pixels = Self::convert_bitmap_to_array(mask_img);
-        result.mask_fbytes = Self::compress(&mask_pixels);
+        let mask_pixels = Self::convert_bitmap_to_array(mask_img, EncodeOptions::default());
+        result.mask_fbytes = Codec::Gzip.compress(&mask_pixels);
 
         result
     }
 
-    /// 将图像转换为字节数组
-    fn convert_bitmap_to_array(img: &RgbaImage) -> Vec<u8> {
-        let mut pixels = Vec::with_capacity((img.width() * img.height() * 4) as usize);
+    /// 替换像素数据和偏移，保留阴影与遮罩层
+    pub fn set_pixels(&mut self, img: &RgbaImage, x: i16, y: i16, options: EncodeOptions) {
+        let fresh = Self::from_image_with(img, x, y, options);
+        self.width = fresh.width;
+        self.height = fresh.height;
+        self.x = fresh.x;
+        self.y = fresh.y;
+        self.length = fresh.length;
+        self.fbytes = fresh.fbytes;
+        self.image = fresh.frame.image;
+        self.texture_valid = true;
+        self.preview = None;
+    }
 
-        for pixel in img.pixels() {
-            let [r, g, b, a] = pixel.0;
-            pixels.push(r);
-            pixels.push(g);
-            pixels.push(b);
-            // 黑色像素设为透明
-            pixels.push(if r == 0 && g == 0 && b == 0 { 0 } else { a });
+    /// 设置遮罩层（替换已有遮罩），`x`/`y` 为遮罩的绘制偏移
+    pub fn set_mask(&mut self, mask_img: &RgbaImage, x: i16, y: i16) {
+        self.has_mask = true;
+        self.mask_width = mask_img.width() as i16;
+        self.mask_height = mask_img.height() as i16;
+        self.mask_x = x;
+        self.mask_y = y;
+        self.mask_fbytes = Codec::Gzip.compress(&Self::convert_bitmap_to_array(
+            mask_img,
+            EncodeOptions::default(),
+        ));
+        self.mask_image = Some(mask_img.clone());
+    }
+
+    /// 修改阴影和遮罩属性，返回是否有改动
+    ///
+    /// 阴影值只取低 7 位。关闭遮罩时去掉遮罩层；开启时若还没有遮罩层，创建与帧同尺寸的
+    /// 全透明遮罩层，之后可以再导入遮罩图像。
+    pub fn set_properties(&mut self, properties: FrameProperties) -> bool {
+        let properties = FrameProperties {
+            shadow: properties.shadow & 0x7F,
+            ..properties
+        };
+        if properties == self.properties() {
+            return false;
         }
+        self.shadow = properties.shadow;
+        self.shadow_x = properties.shadow_x;
+        self.shadow_y = properties.shadow_y;
+        if !properties.has_mask {
+            self.clear_mask();
+        } else if self.has_mask {
+            self.mask_x = properties.mask_x;
+            self.mask_y = properties.mask_y;
+        } else {
+            let blank = RgbaImage::new(self.width.max(0) as u32, self.height.max(0) as u32);
+            self.set_mask(&blank, properties.mask_x, properties.mask_y);
+        }
+        true
+    }
 
+    /// 将图像转换为字节数组
+    fn convert_bitmap_to_array(img: &RgbaImage, options: EncodeOptions) -> Vec<u8> {
+        // 按 Alpha 原样写入；哪些颜色算透明由导入时的 ChromaKey 决定。行序与
+        // ROW_ORDER 相同（自上而下），不需要换算
+        let mut pixels = img.as_raw().clone();
+        if options.preserve_black {
+            for pixel in pixels.chunks_exact_mut(4) {
+                if pixel[3] != 0 && pixel[..3] == [0, 0, 0] {
+                    pixel[..3].copy_from_slice(&NEAR_BLACK);
+                }
+            }
+        }
         pixels
     }
 
-    /// GZip 压缩
-    fn compress(data: &[u8]) -> Vec<u8> {
-        use std::io::Write;
-
-        let mut compressed = Vec::new();
-        {
-            let mut encoder = GzEncoder::new(&mut compressed, Compression::default());
-            encoder.write_all(data).unwrap();
+    /// 按 `codec` 重新压缩帧数据和遮罩数据
+    ///
+    /// 指定 `level` 时全部重新压缩；否则已是该压缩方式的数据不变，其余按默认级别压缩。
+    pub fn recompress(&mut self, codec: Codec, level: Option<CompressionLevel>) -> Result<()> {
+        let stale =
+            |data: &[u8]| !data.is_empty() && (level.is_some() || Codec::detect(data) != codec);
+        let level = level.unwrap_or_default();
+        if stale(&self.fbytes) {
+            self.fbytes = codec.compress_with(&Codec::decompress(&self.fbytes)?, level);
+            self.length = self.fbytes.len() as i32;
+        }
+        if stale(&self.mask_fbytes) {
+            self.mask_fbytes = codec.compress_with(&Codec::decompress(&self.mask_fbytes)?, level);
         }
-        compressed
+        Ok(())
     }
 
-    /// GZip 解压
-    fn decompress(data: &[u8]) -> Result<Vec<u8>> {
-        let mut decoder = GzDecoder::new(data);
-        let mut decompressed = Vec::new();
-        decoder.read_to_end(&mut decompressed)?;
-        Ok(decompressed)
+    /// 帧数据和遮罩数据都已是 `codec` 压缩
+    fn is_compressed_with(&self, codec: Codec) -> bool {
+        [&self.fbytes, &self.mask_fbytes]
+            .iter()
+            .all(|data| data.is_empty() || Codec::detect(data) == codec)
     }
 
     /// 创建纹理
@@ -183,7 +363,7 @@ impl MImage {
         }
 
         // 解压数据
-        let decompressed = Self::decompress(&self.fbytes)?;
+        let decompressed = Codec::decompress(&self.fbytes)?;
 
         let mut rgba_img = RgbaImage::new(width, height);
 
@@ -195,12 +375,12 @@ impl MImage {
                     let g = decompressed[idx + 1];
                     let b = decompressed[idx + 2];
                     let a = decompressed[idx + 3];
-                    rgba_img.put_pixel(x, y, Rgba([r, g, b, a]));
+                    rgba_img.put_pixel(x, ROW_ORDER.map(y, height), Rgba([r, g, b, a]));
                 }
             }
         }
 
-        self.image = Some(rgba_img);
+        self.image = Some(Arc::new(rgba_img));
         self.texture_valid = true;
 
         // 如果有遮罩，创建遮罩图像
@@ -209,7 +389,7 @@ impl MImage {
             let mask_height = self.mask_height as u32;
 
             if mask_width > 0 && mask_height > 0 {
-                let mask_decompressed = Self::decompress(&self.mask_fbytes)?;
+                let mask_decompressed = Codec::decompress(&self.mask_fbytes)?;
 
                 let mut mask_img = RgbaImage::new(mask_width, mask_height);
 
@@ -221,7 +401,11 @@ impl MImage {
                             let g = mask_decompressed[idx + 1];
                             let b = mask_decompressed[idx + 2];
                             let a = mask_decompressed[idx + 3];
-                            mask_img.put_pixel(x, y, Rgba([r, g, b, a]));
+                            mask_img.put_pixel(
+                                x,
+                                ROW_ORDER.map(y, mask_height),
+                                Rgba([r, g, b, a]),
+                            );
                         }
                     }
                 }
@@ -233,42 +417,18 @@ impl MImage {
         Ok(())
     }
 
-    /// 创建预览图 (64x64)
-    pub fn create_preview(&mut self) {
-        if let Some(ref image) = self.image {
-            let preview = RgbaImage::from_fn(64, 64, |px, py| {
-                let w = std::cmp::min(self.width as u32, 64);
-                let h = std::cmp::min(self.height as u32, 64);
-                let offset_x = (64 - w) / 2;
-                let offset_y = (64 - h) / 2;
-
-                if px >= offset_x && px < offset_x + w && py >= offset_y && py < offset_y + h {
-                    let x = px - offset_x;
-                    let y = py - offset_y;
-                    if x < image.width() && y < image.height() {
-                        *image.get_pixel(x, y)
-                    } else {
-                        Rgba([0, 0, 0, 0])
-                    }
-                } else {
-                    Rgba([0, 0, 0, 0])
-                }
-            });
-
-            self.preview = Some(preview);
-        }
-    }
-
-    /// 获取预览图
-    pub fn get_preview(&mut self) -> Option<&RgbaImage> {
-        if self.preview.is_none() {
-            self.create_preview();
-        }
-        self.preview.as_ref()
+    /// 保存后占用的字节数
+    pub fn saved_len(&self) -> u64 {
+        let mask = if self.has_mask {
+            12 + self.mask_fbytes.len() as u64
+        } else {
+            0
+        };
+        17 + self.fbytes.len() as u64 + mask
     }
 
     /// 保存图像数据
-    pub fn save(&self, writer: &mut Vec<u8>) -> Result<()> {
+    pub fn save(&self, writer: &mut impl Write) -> Result<()> {
         writer.write_i16::<LittleEndian>(self.width)?;
         writer.write_i16::<LittleEndian>(self.height)?;
         writer.write_i16::<LittleEndian>(self.x)?;
@@ -284,7 +444,7 @@ impl MImage {
         writer.write_u8(shadow_byte)?;
 
         writer.write_i32::<LittleEndian>(self.length)?;
-        writer.extend_from_slice(&self.fbytes);
+        writer.write_all(&self.fbytes)?;
 
         if self.has_mask {
             writer.write_i16::<LittleEndian>(self.mask_width)?;
@@ -292,53 +452,88 @@ impl MImage {
             writer.write_i16::<LittleEndian>(self.mask_x)?;
             writer.write_i16::<LittleEndian>(self.mask_y)?;
             writer.write_i32::<LittleEndian>(self.mask_fbytes.len() as i32)?;
-            writer.extend_from_slice(&self.mask_fbytes);
+            writer.write_all(&self.mask_fbytes)?;
         }
 
         Ok(())
     }
 }
 
-impl Default for MImage {
-    fn default() -> Self {
-        Self::new()
-    }
-}
-
 impl MLibraryV2 {
+    /// 写入的库版本号
     pub const LIB_VERSION: i32 = 2;
 
+    /// 版本号中的标志位：索引为 64 位偏移（V2.1）
+    pub const FLAG_WIDE_OFFSETS: i32 = 0x100;
+
+    /// 版本号中的标志位：帧数据用 zstd 压缩
+    pub const FLAG_ZSTD: i32 = 0x200;
+
+    /// 版本号中可识别的标志位
+    const KNOWN_FLAGS: i32 = Self::FLAG_WIDE_OFFSETS | Self::FLAG_ZSTD;
+
     /// 创建新的 MLibrary V2 实例
-    pub fn new(file_name: String) -> Result<Self> {
+    pub fn new(file_name: PathBuf) -> Result<Self> {
+        let source = Source::Path(file_name.clone());
+        Self::from_source(file_name, source)
+    }
+
+    /// 从指定来源读取的实例（如内存中的 `.Lib` 内容）
+    pub fn from_source(file_name: PathBuf, source: Source) -> Result<Self> {
         let mut library = Self {
             file_name,
             images: Vec::new(),
             index_list: Vec::new(),
             count: 0,
+            wide_offsets: false,
+            codec: Codec::Gzip,
+            level: None,
             initialized: false,
             load: true,
+            source,
         };
 
         library.initialize()?;
         Ok(library)
     }
 
-    /// 初始化库
+    /// 初始化库：只读取索引，帧在首次访问时读取并解码
     pub fn initialize(&mut self) -> Result<()> {
         self.initialized = true;
+        self.read_index()
+    }
+
+    /// 立即读取并解码全部帧（需要一次性处理整个库的工具使用）
+    pub fn preload(&mut self) -> Result<()> {
+        for index in 0..self.images.len() {
+            self.check_image(index)?;
+        }
+        Ok(())
+    }
 
-        let lib_path = format!("{}.Lib", self.file_name);
+    /// 读取尚未加载的帧（不解码），保存前调用，之后不再依赖原文件
+    fn read_unloaded(&mut self) -> Result<()> {
+        for index in 0..self.images.len() {
+            if self.images[index].is_none() {
+                self.load_image(index)?;
+            }
+        }
+        Ok(())
+    }
 
-        if !Path::new(&lib_path).exists() {
+    /// 读取版本号和索引列表
+    fn read_index(&mut self) -> Result<()> {
+        if !self.source.exists(".Lib") {
             return Ok(()); // 文件不存在时直接返回
         }
 
-        let file = File::open(&lib_path)?;
-        let mut reader = BufReader::new(file);
+        let mut reader = self.source.open(".Lib")?;
 
         // 读取版本号
         let current_version = reader.read_i32::<LittleEndian>()?;
-        if current_version != Self::LIB_VERSION {
+        if current_version & 0xFF != Self::LIB_VERSION
+            || current_version & !0xFF & !Self::KNOWN_FLAGS != 0
+        {
             tracing::error!(
                 "Wrong version, expecting lib version: {} found version: {}",
                 Self::LIB_VERSION,
@@ -347,25 +542,74 @@ impl MLibraryV2 {
             return Err(LibraryError::UnsupportedVersion(current_version));
         }
 
+        self.wide_offsets = current_version & Self::FLAG_WIDE_OFFSETS != 0;
+        self.codec = if current_version & Self::FLAG_ZSTD != 0 {
+            Codec::Zstd
+        } else {
+            Codec::Gzip
+        };
+
         // 读取图像计数
-        self.count = reader.read_i32::<LittleEndian>()? as usize;
+        self.count = limits::check_count(reader.read_i32::<LittleEndian>()? as usize)?;
 
         // 读取索引列表
         self.index_list.clear();
         for _ in 0..self.count {
-            let index = reader.read_u32::<LittleEndian>()?;
+            let index = if self.wide_offsets {
+                reader.read_u64::<LittleEndian>()?
+            } else {
+                reader.read_u32::<LittleEndian>()? as u64
+            };
             self.index_list.push(index);
         }
 
         // 初始化图像列表
         self.images = vec![None; self.count];
 
-        // 加载所有图像
-        for i in 0..self.count {
-            self.check_image(i)?;
+        Ok(())
+    }
+
+    /// 只读取每帧的头部信息，跳过像素数据
+    pub fn frame_headers(&self) -> Result<Vec<FrameHeader>> {
+        if !self.source.exists(".Lib") {
+            return Ok(Vec::new());
         }
+        let mut reader = self.source.open(".Lib")?;
+
+        let mut headers = Vec::with_capacity(self.index_list.len());
+        for (index, &offset) in self.index_list.iter().enumerate() {
+            reader.seek(SeekFrom::Start(offset))?;
+            let width = reader.read_i16::<LittleEndian>()?;
+            let height = reader.read_i16::<LittleEndian>()?;
+            let x = reader.read_i16::<LittleEndian>()?;
+            let y = reader.read_i16::<LittleEndian>()?;
+            // 跳过阴影偏移
+            reader.read_exact(&mut [0u8; 4])?;
+            let shadow = reader.read_u8()?;
+            let length = reader.read_i32::<LittleEndian>()?;
+            let has_mask = (shadow >> 7) == 1;
+
+            let mut data_length = length.max(0) as u64;
+            if has_mask {
+                // 跳过基础层数据和遮罩层的尺寸、偏移
+                reader.seek(SeekFrom::Current(length as i64 + 8))?;
+                data_length += reader.read_i32::<LittleEndian>()?.max(0) as u64;
+            }
 
-        Ok(())
+            headers.push(FrameHeader {
+                index,
+                width: width as i32,
+                height: height as i32,
+                x: x as i32,
+                y: y as i32,
+                data_offset: offset + 17,
+                data_length,
+                has_mask,
+                shadow: shadow & 0x7F,
+            });
+        }
+
+        Ok(headers)
     }
 
     /// 关闭库
@@ -391,8 +635,11 @@ impl MLibraryV2 {
             return Ok(());
         }
 
+        // 空帧（宽或高为 0）没有像素数据，无需创建纹理
         if let Some(ref mut img) = self.images[index]
             && !img.texture_valid
+            && img.width > 0
+            && img.height > 0
         {
             img.create_texture()?;
         }
@@ -402,21 +649,19 @@ impl MLibraryV2 {
 
     /// 加载指定索引的图像
     fn load_image(&mut self, index: usize) -> Result<()> {
-        let lib_path = format!("{}.Lib", self.file_name);
-        let file = File::open(&lib_path)?;
-        let mut reader = BufReader::new(file);
+        let mut reader = self.source.open(".Lib")?;
 
-        let offset = self.index_list[index] as u64;
+        let offset = self.index_list[index];
         reader.seek(SeekFrom::Start(offset))?;
 
-        let image = Self::read_mimage(&mut reader)?;
+        let image = Self::read_mimage(&mut *reader)?;
         self.images[index] = Some(image);
 
         Ok(())
     }
 
     /// 读取 MImage 数据
-    fn read_mimage(reader: &mut BufReader<File>) -> Result<MImage> {
+    pub(crate) fn read_mimage(reader: &mut dyn ReadSeek) -> Result<MImage> {
         // 读取 Layer 1
         let width = reader.read_i16::<LittleEndian>()?;
         let height = reader.read_i16::<LittleEndian>()?;
@@ -427,7 +672,7 @@ impl MLibraryV2 {
         let shadow = reader.read_u8()?;
         let length = reader.read_i32::<LittleEndian>()?;
 
-        let mut fbytes = vec![0u8; length as usize];
+        let mut fbytes = vec![0u8; limits::check_length(length as i64, "帧数据")?];
         reader.read_exact(&mut fbytes)?;
 
         // 检查是否有 Layer 2 (Mask)
@@ -452,7 +697,7 @@ impl MLibraryV2 {
             img.mask_y = reader.read_i16::<LittleEndian>()?;
             let mask_length = reader.read_i32::<LittleEndian>()?;
 
-            img.mask_fbytes = vec![0u8; mask_length as usize];
+            img.mask_fbytes = vec![0u8; limits::check_length(mask_length as i64, "遮罩数据")?];
             reader.read_exact(&mut img.mask_fbytes)?;
         }
 
@@ -468,17 +713,31 @@ impl MLibraryV2 {
             .ok_or_else(|| LibraryError::IndexOutOfBounds(index))
     }
 
-    /// 获取预览图
-    pub fn get_preview(&mut self, index: usize) -> Result<Option<&RgbaImage>> {
+    /// 释放指定帧解码后的图像，保留压缩数据，下次访问时重新解码
+    pub fn release_image(&mut self, index: usize) {
+        if let Some(Some(image)) = self.images.get_mut(index) {
+            image.release_texture();
+        }
+    }
+
+    /// 获取预览图（与缓存共享，不复制像素）
+    pub fn get_preview(&mut self, index: usize) -> Result<Option<Arc<RgbaImage>>> {
         self.check_image(index)?;
 
         if let Some(ref img) = self.images[index] {
-            Ok(img.image.as_ref())
+            Ok(img.image.clone())
         } else {
             Ok(None)
         }
     }
 
+    /// 整体替换所有帧（帧都在内存中，不再按原文件的偏移读取）
+    pub fn set_images(&mut self, images: Vec<MImage>) {
+        self.count = images.len();
+        self.images = images.into_iter().map(Some).collect();
+        self.index_list.clear();
+    }
+
     /// 添加新图像
     pub fn add_image(&mut self, image: &MImage) {
         self.count += 1;
@@ -492,7 +751,7 @@ impl MLibraryV2 {
         new_image.mask_width = mask_image.width;
         new_image.mask_height = mask_image.height;
         new_image.mask_fbytes = mask_image.fbytes.clone();
-        new_image.mask_image = mask_image.image.clone();
+        new_image.mask_image = mask_image.image.as_deref().cloned();
 
         self.count += 1;
         self.images.push(Some(new_image));
@@ -507,6 +766,189 @@ impl MLibraryV2 {
         Ok(())
     }
 
+    /// 为指定帧设置遮罩层
+    pub fn set_mask(&mut self, index: usize, mask_img: &RgbaImage, x: i16, y: i16) -> Result<()> {
+        self.check_image(index)?;
+        self.images[index]
+            .as_mut()
+            .ok_or(LibraryError::IndexOutOfBounds(index))?
+            .set_mask(mask_img, x, y);
+        Ok(())
+    }
+
+    /// 修改指定帧的阴影和遮罩属性，返回是否有改动
+    pub fn set_properties(&mut self, index: usize, properties: FrameProperties) -> Result<bool> {
+        self.check_image(index)?;
+        Ok(self.images[index]
+            .as_mut()
+            .ok_or(LibraryError::IndexOutOfBounds(index))?
+            .set_properties(properties))
+    }
+
+    /// 去掉指定范围内所有帧的遮罩层，返回实际被修改的帧索引
+    pub fn strip_masks(&mut self, range: Range<usize>) -> Result<Vec<usize>> {
+        let mut affected = Vec::new();
+        for index in range.start..range.end.min(self.images.len()) {
+            self.check_image(index)?;
+            if let Some(ref mut image) = self.images[index]
+                && image.clear_mask()
+            {
+                affected.push(index);
+            }
+        }
+        Ok(affected)
+    }
+
+    /// 将范围内的帧填充到相同尺寸，并统一偏移
+    ///
+    /// 取所有非空帧按各自偏移摆放后的包围盒，每帧在其中保持原来的绘制位置，
+    /// 因此游戏内显示效果不变。空帧不参与也不修改；范围内没有非空帧时返回 `None`。
+    pub fn normalize_frames(&mut self, range: Range<usize>) -> Result<Option<NormalizeReport>> {
+        let indices = range.start..range.end.min(self.images.len());
+
+        let mut bounds: Option<(i32, i32, i32, i32)> = None;
+        for index in indices.clone() {
+            self.check_image(index)?;
+            if let Some(ref img) = self.images[index]
+                && img.image.is_some()
+            {
+                let (left, top) = (img.x as i32, img.y as i32);
+                let (right, bottom) = (left + img.width as i32, top + img.height as i32);
+                bounds = Some(match bounds {
+                    None => (left, top, right, bottom),
+                    Some((l, t, r, b)) => (l.min(left), t.min(top), r.max(right), b.max(bottom)),
+                });
+            }
+        }
+        let Some((left, top, right, bottom)) = bounds else {
+            return Ok(None);
+        };
+
+        let (width, height) = (right - left, bottom - top);
+        if width > i16::MAX as i32 || height > i16::MAX as i32 {
+            return Err(LibraryError::ParseError(format!(
+                "统一后的尺寸 {}x{} 超出格式上限",
+                width, height
+            )));
+        }
+
+        let mut frames = Vec::new();
+        for index in indices {
+            let Some(ref mut img) = self.images[index] else {
+                continue;
+            };
+            let Some(ref src) = img.image else {
+                continue;
+            };
+            if (img.x as i32, img.y as i32) == (left, top)
+                && src.dimensions() == (width as u32, height as u32)
+            {
+                continue;
+            }
+
+            let mut canvas = RgbaImage::new(width as u32, height as u32);
+            image::imageops::overlay(
+                &mut canvas,
+                &**src,
+                (img.x as i32 - left) as i64,
+                (img.y as i32 - top) as i64,
+            );
+            img.set_pixels(&canvas, left as i16, top as i16, EncodeOptions::default());
+            frames.push(index);
+        }
+
+        Ok(Some(NormalizeReport {
+            x: left as i16,
+            y: top as i16,
+            width: width as i16,
+            height: height as i16,
+            frames,
+        }))
+    }
+
+    /// 将指定帧填充到 `width` x `height` 的画布，帧在画布中的位置由 `anchor` 决定
+    ///
+    /// 偏移随之调整，游戏内的绘制位置不变；遮罩层有独立的偏移，不受影响。
+    /// 空帧和已是该尺寸的帧跳过，比画布大的帧不裁剪，记入报告。
+    pub fn pad_frames(
+        &mut self,
+        indices: &[usize],
+        width: u32,
+        height: u32,
+        anchor: Anchor,
+    ) -> Result<PadReport> {
+        if width == 0 || height == 0 || width > i16::MAX as u32 || height > i16::MAX as u32 {
+            return Err(LibraryError::ParseError(format!(
+                "无效的画布尺寸 {}x{}",
+                width, height
+            )));
+        }
+
+        let mut report = PadReport::default();
+        for &index in indices {
+            self.check_image(index)?;
+            let Some(ref mut img) = self.images[index] else {
+                continue;
+            };
+            let Some(ref src) = img.image else {
+                continue;
+            };
+            if src.dimensions() == (width, height) {
+                continue;
+            }
+            let Some((padded, (dx, dy))) = canvas::pad(src, width, height, anchor) else {
+                report.oversized.push(index);
+                continue;
+            };
+
+            let x = (img.x as i32 - dx).clamp(i16::MIN as i32, i16::MAX as i32) as i16;
+            let y = (img.y as i32 - dy).clamp(i16::MIN as i32, i16::MAX as i32) as i16;
+            img.set_pixels(&padded, x, y, EncodeOptions::default());
+            report.frames.push(index);
+        }
+        Ok(report)
+    }
+
+    /// 将指定帧的偏移（含遮罩层偏移）整体移动，返回实际被修改的帧索引
+    ///
+    /// 空帧没有绘制位置，跳过；移动后超出 i16 范围的偏移截断到边界。
+    pub fn shift_offsets(&mut self, indices: &[usize], dx: i16, dy: i16) -> Result<Vec<usize>> {
+        let mut affected = Vec::new();
+        for &index in indices {
+            self.check_image(index)?;
+            let Some(ref mut img) = self.images[index] else {
+                continue;
+            };
+            if img.image.is_none() {
+                continue;
+            }
+            img.x = img.x.saturating_add(dx);
+            img.y = img.y.saturating_add(dy);
+            if img.has_mask {
+                img.mask_x = img.mask_x.saturating_add(dx);
+                img.mask_y = img.mask_y.saturating_add(dy);
+            }
+            affected.push(index);
+        }
+        Ok(affected)
+    }
+
+    /// 将指定帧清空为空帧（保留索引位置），返回原本非空的帧索引
+    pub fn clear_frames(&mut self, indices: &[usize]) -> Result<Vec<usize>> {
+        let mut affected = Vec::new();
+        for &index in indices {
+            self.check_image(index)?;
+            if self.images[index]
+                .as_ref()
+                .is_some_and(|img| img.image.is_some() || img.has_mask)
+            {
+                affected.push(index);
+            }
+            self.images[index] = Some(MImage::new());
+        }
+        Ok(affected)
+    }
+
     /// 插入图像
     pub fn insert_image(&mut self, index: usize, image: &MImage) -> Result<()> {
         if index > self.images.len() {
@@ -514,13 +956,30 @@ impl MLibraryV2 {
         }
         self.count += 1;
         self.images.insert(index, Some(image.clone()));
+        // 保持索引与帧对齐，插入的帧已在内存中，不会按占位偏移读取
+        if index < self.index_list.len() {
+            self.index_list.insert(index, 0);
+        }
         Ok(())
     }
 
+    /// 取出指定帧（从库中移除并返回）
+    pub fn take_image(&mut self, index: usize) -> Result<MImage> {
+        self.check_image(index)?;
+        let image = self
+            .images
+            .remove(index)
+            .ok_or(LibraryError::IndexOutOfBounds(index))?;
+        self.forget_offset(index);
+        self.count -= 1;
+        Ok(image)
+    }
+
     /// 删除图像
     pub fn remove_image(&mut self, index: usize) -> Result<()> {
         if self.images.len() <= 1 {
             self.images.clear();
+            self.index_list.clear();
             self.count = 0;
             return Ok(());
         }
@@ -530,38 +989,79 @@ impl MLibraryV2 {
         }
 
         self.images.remove(index);
+        self.forget_offset(index);
         self.count -= 1;
         Ok(())
     }
 
-    /// 保存库文件
-    pub fn save(&self) -> Result<()> {
-        let mut data_stream = Vec::new();
-        let mut index_list: Vec<u32> = Vec::new();
-
-        let offset = 8 + (self.images.len() * 4) as u32;
+    /// 移除帧后同步移除其在文件中的偏移，使后面尚未加载的帧仍读取正确的位置
+    fn forget_offset(&mut self, index: usize) {
+        if index < self.index_list.len() {
+            self.index_list.remove(index);
+        }
+    }
 
-        for img in self.images.iter().flatten() {
-            let current_offset = data_stream.len() as u32 + offset;
-            index_list.push(current_offset);
-            img.save(&mut data_stream)?;
+    /// 保存库文件
+    ///
+    /// 帧数据按 [`codec`](Self::codec) 写入，压缩方式不同的帧先重新压缩；设置了
+    /// [`level`](Self::level) 时全部帧按该级别重新压缩。
+    /// 未设置 [`wide_offsets`](Self::wide_offsets) 而偏移超出 32 位时返回
+    /// [`LibraryError::OffsetOverflow`]，不写入文件。尚未加载的帧先从原文件读出。
+    pub fn save(&mut self) -> Result<()> {
+        self.read_unloaded()?;
+        let images = self
+            .images
+            .iter()
+            .flatten()
+            .map(|img| {
+                if self.level.is_none() && img.is_compressed_with(self.codec) {
+                    return Ok(Cow::Borrowed(img));
+                }
+                let mut img = img.clone();
+                img.recompress(self.codec, self.level)?;
+                Ok(Cow::Owned(img))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let index_size = if self.wide_offsets { 8 } else { 4 };
+        let mut offset = 8 + (self.images.len() * index_size) as u64;
+        let mut index_list = Vec::with_capacity(self.images.len());
+        for img in &images {
+            index_list.push(offset);
+            offset += img.saved_len();
+        }
+        if !self.wide_offsets && offset > u32::MAX as u64 {
+            return Err(LibraryError::OffsetOverflow(offset));
         }
 
         // 写入文件
-        let lib_path = format!("{}.Lib", self.file_name);
-        let file = File::create(&lib_path)?;
-        let mut writer = BufWriter::new(file);
+        let lib_path = find_companion(&self.file_name, ".Lib");
+        let mut writer = AtomicFile::create(&lib_path)?;
 
-        writer.write_i32::<LittleEndian>(Self::LIB_VERSION)?;
+        let mut version = Self::LIB_VERSION;
+        if self.wide_offsets {
+            version |= Self::FLAG_WIDE_OFFSETS;
+        }
+        if self.codec == Codec::Zstd {
+            version |= Self::FLAG_ZSTD;
+        }
+        writer.write_i32::<LittleEndian>(version)?;
         writer.write_i32::<LittleEndian>(self.images.len() as i32)?;
 
-        for index in &index_list {
-            writer.write_i32::<LittleEndian>(*index as i32)?;
+        for &index in &index_list {
+            if self.wide_offsets {
+                writer.write_u64::<LittleEndian>(index)?;
+            } else {
+                writer.write_u32::<LittleEndian>(index as u32)?;
+            }
         }
 
-        writer.write_all(&data_stream)?;
-        writer.flush()?;
+        for img in &images {
+            img.save(&mut writer)?;
+        }
+        writer.commit()?;
 
+        self.index_list = index_list;
         Ok(())
     }
 
@@ -574,13 +1074,326 @@ impl MLibraryV2 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::formats::companion_path;
 
     #[test]
     fn test_create_library() {
-        let lib = MLibraryV2::new("test".to_string());
+        let lib = MLibraryV2::new(PathBuf::from("test"));
         assert!(lib.is_ok()); // 文件不存在时应该返回 Ok
     }
 
+    #[test]
+    fn test_set_mask_roundtrip() {
+        let temp = tempfile::tempdir().unwrap();
+        let base = temp.path().join("v2_mask");
+        let mut lib = MLibraryV2::new(base.clone()).unwrap();
+        let frame = RgbaImage::from_pixel(4, 4, Rgba([9, 9, 9, 255]));
+        let mask = RgbaImage::from_pixel(2, 3, Rgba([255, 128, 0, 255]));
+        lib.add_image(&MImage::from_image(&frame, 0, 0));
+        lib.set_mask(0, &mask, -1, 2).unwrap();
+        lib.save().unwrap();
+
+        let mut reloaded = MLibraryV2::new(base.clone()).unwrap();
+        let image = reloaded.get_image(0).unwrap();
+        assert!(image.has_mask);
+        assert_eq!((image.mask_width, image.mask_height), (2, 3));
+        assert_eq!((image.mask_x, image.mask_y), (-1, 2));
+        assert_eq!(
+            image.mask_image.as_ref().unwrap().get_pixel(1, 2).0,
+            [255, 128, 0, 255]
+        );
+    }
+
+    #[test]
+    fn test_wide_offsets_roundtrip() {
+        let temp = tempfile::tempdir().unwrap();
+        let base = temp.path().join("v2_wide");
+        let path = companion_path(&base, ".Lib");
+        let mut lib = MLibraryV2::new(base.clone()).unwrap();
+        let frame = RgbaImage::from_pixel(3, 2, Rgba([9, 9, 9, 255]));
+        lib.add_image(&MImage::from_image(&frame, 4, -1));
+        lib.add_image(&MImage::from_image(&frame, 0, 0));
+        lib.wide_offsets = true;
+        lib.save().unwrap();
+
+        let data = std::fs::read(&path).unwrap();
+        assert_eq!(i32::from_le_bytes(data[0..4].try_into().unwrap()), 0x102);
+        let mut reloaded = MLibraryV2::new(base.clone()).unwrap();
+        assert!(reloaded.wide_offsets);
+        assert_eq!(reloaded.index_list[0], 8 + 2 * 8);
+        assert_eq!(reloaded.get_image(0).unwrap().x, 4);
+        assert_eq!(reloaded.get_image(1).unwrap().width, 3);
+
+        // 不认识的标志位拒绝读取
+        let mut unknown = data;
+        unknown[0..4].copy_from_slice(&0x402i32.to_le_bytes());
+        std::fs::write(&path, unknown).unwrap();
+        assert!(matches!(
+            MLibraryV2::new(base.clone()),
+            Err(LibraryError::UnsupportedVersion(0x402))
+        ));
+    }
+
+    #[test]
+    fn test_zstd_roundtrip_and_back_to_gzip() {
+        let temp = tempfile::tempdir().unwrap();
+        let base = temp.path().join("v2_zstd");
+        let path = companion_path(&base, ".Lib");
+        let frame = RgbaImage::from_fn(8, 4, |x, y| Rgba([x as u8 * 30, y as u8 * 60, 7, 255]));
+        let zstd = EncodeOptions {
+            codec: Codec::Zstd,
+            ..EncodeOptions::default()
+        };
+        let mut lib = MLibraryV2::new(base.clone()).unwrap();
+        lib.codec = Codec::Zstd;
+        lib.add_image(&MImage::from_image_with(&frame, 1, 2, zstd));
+        // GZip 帧保存时重新压缩为 zstd
+        lib.add_image(&MImage::from_image(&frame, 3, 4));
+        lib.save().unwrap();
+
+        let version = |path: &PathBuf| {
+            let data = std::fs::read(path).unwrap();
+            i32::from_le_bytes(data[0..4].try_into().unwrap())
+        };
+        assert_eq!(version(&path), 0x202);
+        let mut reloaded = MLibraryV2::new(base.clone()).unwrap();
+        assert_eq!(reloaded.codec, Codec::Zstd);
+        for index in 0..2 {
+            let image = reloaded.get_image(index).unwrap();
+            assert_eq!(Codec::detect(&image.fbytes), Codec::Zstd);
+            assert_eq!(image.image.as_deref().unwrap(), &frame);
+        }
+
+        // 交给客户端时保存为 GZip
+        reloaded.codec = Codec::Gzip;
+        reloaded.save().unwrap();
+        assert_eq!(version(&path), MLibraryV2::LIB_VERSION);
+        let mut legacy = MLibraryV2::new(base.clone()).unwrap();
+        let image = legacy.get_image(1).unwrap();
+        assert_eq!(Codec::detect(&image.fbytes), Codec::Gzip);
+        assert_eq!(image.image.as_deref().unwrap(), &frame);
+    }
+
+    #[test]
+    fn test_save_compression_level() {
+        let temp = tempfile::tempdir().unwrap();
+        let base = temp.path().join("v2_level");
+        let path = companion_path(&base, ".Lib");
+        let frame = RgbaImage::from_fn(64, 32, |x, y| Rgba([(x / 4) as u8, (y / 8) as u8, 9, 255]));
+        let mut lib = MLibraryV2::new(base.clone()).unwrap();
+        lib.add_image(&MImage::from_image(&frame, 0, 0));
+
+        // 0 级不压缩，9 级最小；已压缩的帧也按新级别重写，像素不变
+        let mut sizes = Vec::new();
+        for level in [0, 9] {
+            lib.level = CompressionLevel::new(level);
+            lib.save().unwrap();
+            sizes.push(std::fs::metadata(&path).unwrap().len());
+            let mut reloaded = MLibraryV2::new(base.clone()).unwrap();
+            let image = reloaded.get_image(0).unwrap();
+            assert_eq!(image.image.as_deref().unwrap(), &frame);
+        }
+        assert!(sizes[0] > 64 * 32 * 4);
+        assert!(sizes[1] < sizes[0] / 4);
+        assert!(CompressionLevel::new(10).is_none());
+    }
+
+    #[test]
+    fn test_encode_keeps_opaque_black() {
+        // 透明色在导入时处理，编码按 Alpha 原样写入
+        let frame = RgbaImage::from_fn(2, 2, |x, _| {
+            Rgba(if x == 0 { [0, 0, 0, 255] } else { [0, 0, 0, 0] })
+        });
+        let mut image = MImage::from_image(&frame, 0, 0);
+        image.create_texture().unwrap();
+        let decoded = image.image.as_ref().unwrap();
+        assert_eq!(decoded.get_pixel(0, 1).0, [0, 0, 0, 255]);
+        assert_eq!(decoded.get_pixel(1, 1).0, [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_preserve_black_survives_client_color_key() {
+        // 客户端绘制时跳过 Alpha 为 0 或 RGB 全为 0 的像素
+        let drawn = |pixel: &Rgba<u8>| pixel[3] != 0 && pixel.0[..3] != [0, 0, 0];
+
+        let temp = tempfile::tempdir().unwrap();
+        let base = temp.path().join("v2_black");
+        let frame = RgbaImage::from_fn(3, 2, |x, _| {
+            Rgba(match x {
+                0 => [0, 0, 0, 255],
+                1 => [0, 0, 0, 0],
+                _ => [200, 10, 10, 255],
+            })
+        });
+        let preserve = EncodeOptions {
+            preserve_black: true,
+            ..EncodeOptions::default()
+        };
+        let mut lib = MLibraryV2::new(base.clone()).unwrap();
+        lib.add_image(&MImage::from_image(&frame, 0, 0));
+        lib.add_image(&MImage::from_image_with(&frame, 0, 0, preserve));
+        lib.save().unwrap();
+
+        // 从保存的文件重新读取并解码
+        let mut reopened = MLibraryV2::new(base.clone()).unwrap();
+        let mut decode = |index| {
+            let mut image = reopened.get_image(index).unwrap().clone();
+            image.create_texture().unwrap();
+            image.frame.image.unwrap()
+        };
+        let plain = decode(0);
+        let kept = decode(1);
+
+        // 默认按原样写入，客户端会把黑色抠掉
+        assert_eq!(plain.get_pixel(0, 0).0, [0, 0, 0, 255]);
+        assert!(!drawn(plain.get_pixel(0, 0)));
+        // 保留黑色时写成近黑色，客户端照常绘制；透明像素和其他颜色不变
+        assert_eq!(kept.get_pixel(0, 0).0, [1, 1, 1, 255]);
+        assert!(drawn(kept.get_pixel(0, 0)));
+        assert_eq!(kept.get_pixel(1, 0).0, [0, 0, 0, 0]);
+        assert_eq!(kept.get_pixel(2, 0).0, [200, 10, 10, 255]);
+    }
+
+    #[test]
+    fn test_frame_headers_skip_payload() {
+        let temp = tempfile::tempdir().unwrap();
+        let base = temp.path().join("v2_headers");
+        let mut lib = MLibraryV2::new(base.clone()).unwrap();
+        let frame = RgbaImage::from_pixel(6, 3, Rgba([9, 9, 9, 255]));
+        let mask = RgbaImage::from_pixel(2, 2, Rgba([255, 128, 0, 255]));
+        lib.add_image(&MImage::from_image_with_mask(&frame, &mask, -4, 7));
+        lib.add_image(&MImage::new());
+        let mut shadowed = MImage::from_image(&frame, 1, 2);
+        shadowed.shadow = 3;
+        lib.add_image(&shadowed);
+        lib.save().unwrap();
+
+        let opened = MLibraryV2::new(base.clone()).unwrap();
+        assert!(opened.images.iter().all(|img| img.is_none()));
+        let headers = opened.frame_headers().unwrap();
+
+        assert_eq!(headers.len(), 3);
+        assert_eq!((headers[0].width, headers[0].height), (6, 3));
+        assert_eq!((headers[0].x, headers[0].y), (-4, 7));
+        assert!(headers[0].has_mask && !headers[2].has_mask);
+        assert!(headers[1].is_empty());
+        assert!(headers[0].data_length > headers[2].data_length);
+        assert_eq!((headers[2].x, headers[2].y), (1, 2));
+        assert_eq!((headers[0].shadow, headers[2].shadow), (0, 3));
+    }
+
+    #[test]
+    fn test_lazy_frames_survive_edits_and_save() {
+        let temp = tempfile::tempdir().unwrap();
+        let base = temp.path().join("v2_lazy");
+        let shades = [10u8, 20, 30];
+        let frame = |shade: u8| RgbaImage::from_pixel(2, 2, Rgba([shade, 0, 0, 255]));
+        let mut lib = MLibraryV2::new(base.clone()).unwrap();
+        for shade in shades {
+            lib.add_image(&MImage::from_image(&frame(shade), 0, 0));
+        }
+        lib.save().unwrap();
+
+        // 打开时不读取帧；删除、插入后未加载的帧仍从正确的位置读取
+        let mut opened = MLibraryV2::new(base.clone()).unwrap();
+        assert!(opened.images.iter().all(|img| img.is_none()));
+        opened.remove_image(0).unwrap();
+        let inserted = MImage::from_image(&frame(40), 0, 0);
+        opened.insert_image(1, &inserted).unwrap();
+        opened.save().unwrap();
+
+        let mut reloaded = MLibraryV2::new(base.clone()).unwrap();
+        reloaded.preload().unwrap();
+        let reds: Vec<u8> = (0..reloaded.count())
+            .map(|i| reloaded.get_preview(i).unwrap().unwrap().get_pixel(0, 0)[0])
+            .collect();
+        assert_eq!(reds, [20, 40, 30]);
+    }
+
+    #[test]
+    fn test_strip_masks_in_range() {
+        let frame = RgbaImage::from_pixel(2, 2, Rgba([9, 9, 9, 255]));
+        let mut lib = MLibraryV2::new(PathBuf::from("strip_masks_missing")).unwrap();
+        for _ in 0..4 {
+            lib.add_image(&MImage::from_image_with_mask(&frame, &frame, 0, 0));
+        }
+
+        assert_eq!(lib.strip_masks(1..3).unwrap(), vec![1, 2]);
+        assert_eq!(lib.strip_masks(0..10).unwrap(), vec![0, 3]);
+        assert!(!lib.get_image(2).unwrap().has_mask);
+    }
+
+    #[test]
+    fn test_normalize_keeps_placement() {
+        let mut lib = MLibraryV2::new(PathBuf::from("normalize_missing")).unwrap();
+        let small = RgbaImage::from_pixel(2, 2, Rgba([200, 0, 0, 255]));
+        let wide = RgbaImage::from_pixel(4, 2, Rgba([0, 200, 0, 255]));
+        lib.add_image(&MImage::from_image(&small, 3, -1));
+        lib.add_image(&MImage::from_image(&wide, 0, 0));
+        lib.add_image(&MImage::new());
+
+        let report = lib.normalize_frames(0..3).unwrap().unwrap();
+        assert_eq!(
+            (report.x, report.y, report.width, report.height),
+            (0, -1, 5, 3)
+        );
+        assert_eq!(report.frames, vec![0, 1]);
+
+        // 原来位于 (3, -1) 的像素仍画在同一位置
+        let first = lib.get_image(0).unwrap();
+        let pixels = first.image.as_ref().unwrap();
+        assert_eq!(pixels.get_pixel(3, 0).0, [200, 0, 0, 255]);
+        assert_eq!(pixels.get_pixel(0, 0)[3], 0);
+        assert_eq!(lib.get_image(2).unwrap().width, 0);
+    }
+
+    #[test]
+    fn test_pad_keeps_placement() {
+        let mut lib = MLibraryV2::new(PathBuf::from("pad_missing")).unwrap();
+        let small = RgbaImage::from_pixel(2, 3, Rgba([200, 0, 0, 255]));
+        let large = RgbaImage::from_pixel(8, 2, Rgba([0, 200, 0, 255]));
+        lib.add_image(&MImage::from_image(&small, -1, -3));
+        lib.add_image(&MImage::from_image(&large, 0, 0));
+        lib.add_image(&MImage::new());
+
+        let report = lib.pad_frames(&[0, 1, 2], 6, 4, Anchor::Bottom).unwrap();
+        assert_eq!(report.frames, vec![0]);
+        assert_eq!(report.oversized, vec![1]);
+
+        // 底部居中：帧位于画布 (2, 1)，偏移前移后绘制位置不变
+        let first = lib.get_image(0).unwrap();
+        assert_eq!(
+            (first.width, first.height, first.x, first.y),
+            (6, 4, -3, -4)
+        );
+        let pixels = first.image.as_ref().unwrap();
+        assert_eq!(pixels.get_pixel(2, 1).0, [200, 0, 0, 255]);
+        assert_eq!(pixels.get_pixel(1, 3)[3], 0);
+
+        assert!(lib.pad_frames(&[0], 0, 4, Anchor::Center).is_err());
+    }
+
+    #[test]
+    fn test_shift_and_clear_selected() {
+        let frame = RgbaImage::from_pixel(2, 2, Rgba([9, 9, 9, 255]));
+        let mut lib = MLibraryV2::new(PathBuf::from("bulk_missing")).unwrap();
+        lib.add_image(&MImage::from_image_with_mask(&frame, &frame, 1, 1));
+        lib.add_image(&MImage::new());
+        lib.add_image(&MImage::from_image(&frame, i16::MAX, 0));
+
+        assert_eq!(lib.shift_offsets(&[0, 1, 2], 2, -3).unwrap(), vec![0, 2]);
+        let first = lib.get_image(0).unwrap();
+        assert_eq!(
+            (first.x, first.y, first.mask_x, first.mask_y),
+            (3, -2, 2, -3)
+        );
+        assert_eq!(lib.get_image(2).unwrap().x, i16::MAX);
+
+        assert_eq!(lib.clear_frames(&[1, 2]).unwrap(), vec![2]);
+        assert_eq!(lib.count(), 3);
+        assert!(lib.get_image(2).unwrap().image.is_none());
+    }
+
     #[test]
     fn test_mimage_creation() {
         let img = MImage::new();
@@ -1,21 +1,133 @@
 //! 库文件格式解析模块
 
+pub mod analyze;
+pub mod animation;
+pub mod atomic_file;
+pub mod bookmarks;
+pub mod clip;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod convert;
+pub mod edit_lock;
+pub mod edits;
+pub mod encrypted_wil;
+pub mod filter;
+pub mod folder_import;
+pub mod frame;
+pub mod frame_cache;
+pub mod header;
+pub mod integrity;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod journal;
+pub mod limits;
+pub mod locks;
+pub mod map;
+pub mod metadata;
+pub mod mir3_library;
 pub mod mlibrary_v0;
 pub mod mlibrary_v1;
 pub mod mlibrary_v2;
+pub mod open_options;
+pub mod project;
+pub mod remap;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod remote;
+pub mod similar;
+pub mod source;
+pub mod spec;
+pub mod trash;
+#[cfg(all(feature = "cli", not(target_arch = "wasm32")))]
+pub mod watch;
 pub mod wemade_library;
 pub mod wtl_library;
+pub mod zip_archive;
 
+pub use encrypted_wil::EncryptedWilLibrary;
+pub use header::LibraryHeader;
+pub use mir3_library::Mir3Library;
 pub use mlibrary_v0::MLibraryV0;
-pub use mlibrary_v1::MImage;
-pub use mlibrary_v2::MLibraryV2;
+pub use mlibrary_v2::{MLibraryV2, NormalizeReport, PadReport};
+pub use open_options::OpenOptions;
+pub use source::Source;
 
 use crate::error::{LibraryError, Result};
+use crate::formats::bookmarks::Bookmarks;
+use crate::formats::edit_lock::{EditLock, OpenMode};
+use crate::formats::edits::EditLog;
+use crate::formats::frame::{FrameCodec, FrameData, FrameProperties};
+use crate::formats::frame_cache::{CachePolicy, FrameCache};
+use crate::formats::locks::FrameLocks;
 use crate::formats::mlibrary_v1::MLibraryV1;
-use std::path::Path;
+use crate::formats::remap::IndexRemap;
+use crate::formats::trash::Trash;
+use crate::image::baseline::AlignPlan;
+use crate::image::canvas::Anchor;
+use crate::image::compare::{self, Placed};
+use crate::image::compression::CompressionLevel;
+use crate::image::export::{self, ExportFormat, ExportOptions};
+use crate::image::indexed::{self, IndexedFrame};
+use crate::image::layers;
+use crate::image::orientation::OrientationGuess;
+use crate::image::placement;
+use crate::image::shadow::{self, ShadowStyle};
+use crate::image::video::PlacedFrame;
+use crate::image::{contact_sheet, tiles};
+use crate::image::{MaskBlend, PlaceholderPolicy};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::progress::LoadHandle;
+use crate::progress::{CancelToken, Progress, ProgressEvent, ProgressSender};
+use serde::{Deserialize, Serialize};
+use std::ffi::OsString;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// 为不含扩展名的基础路径拼接伴随文件扩展名（如 `.wzx`）
+///
+/// 直接在 `OsString` 上追加，不经过 UTF-8 转换，也不会像 `with_extension`
+/// 那样替换掉基础文件名中已有的点号部分（如 `Hum.v2` + `.wzl`）。
+pub fn companion_path(base: &Path, ext: &str) -> PathBuf {
+    let mut path = OsString::from(base.as_os_str());
+    path.push(ext);
+    PathBuf::from(path)
+}
+
+/// 查找磁盘上的伴随文件
+///
+/// 先按原样拼接；不存在时在所在目录中不区分大小写地匹配文件名（Linux 上常见
+/// `Mon1.WZL` 与 `Mon1.wzx` 混用，或整个文件名大小写不一致）。都找不到时返回按原样
+/// 拼接的路径，由调用方报告文件不存在或在该路径新建。
+pub fn find_companion(base: &Path, ext: &str) -> PathBuf {
+    let path = companion_path(base, ext);
+    if path.exists() {
+        return path;
+    }
+    let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+        return path;
+    };
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+
+    // 多个文件只有大小写不同时取排序最前的，保证结果稳定
+    let found = std::fs::read_dir(dir).ok().and_then(|entries| {
+        entries
+            .flatten()
+            .map(|entry| entry.file_name())
+            .filter(|entry| entry.to_str().is_some_and(|n| n.eq_ignore_ascii_case(name)))
+            .min()
+    });
+    match found {
+        Some(found) => {
+            tracing::debug!("按不区分大小写匹配到伴随文件: {:?}", found);
+            path.with_file_name(found)
+        }
+        None => path,
+    }
+}
 
 /// 库文件类型枚举
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum LibraryType {
     /// MLibrary V0 (.wil 旧格式)
     MLV0,
@@ -27,6 +139,10 @@ pub enum LibraryType {
     WeMade,
     /// WTL Library
     WTL,
+    /// 加密 WIL (.D / 盛大 3.55+ 客户端)
+    EncryptedWil,
+    /// 传奇3 库 (.wil/.wix 或 .wzl/.wzx)
+    Mir3,
 }
 
 impl LibraryType {
@@ -37,6 +153,7 @@ impl LibraryType {
             ".lib" => Some(LibraryType::MLV2),
             ".wil" | ".wix" => Some(LibraryType::WeMade),
             ".wtl" => Some(LibraryType::WTL),
+            ".d" => Some(LibraryType::EncryptedWil),
             _ => None,
         }
     }
@@ -49,6 +166,8 @@ impl LibraryType {
             LibraryType::WeMade => ".wil",
             LibraryType::WTL => ".wtl",
             LibraryType::MLV0 => ".wil",
+            LibraryType::EncryptedWil => ".D",
+            LibraryType::Mir3 => ".wil",
         }
     }
 
@@ -56,28 +175,74 @@ impl LibraryType {
     pub fn index_extension(&self) -> Option<&str> {
         match self {
             LibraryType::MLV1 => Some(".wzx"),
-            LibraryType::WeMade => Some(".wix"),
+            LibraryType::WeMade | LibraryType::EncryptedWil | LibraryType::Mir3 => Some(".wix"),
             _ => None,
         }
     }
 
     /// 获取格式名称
-    pub fn name(&self) -> &str {
+    pub fn name(&self) -> &'static str {
         match self {
             LibraryType::MLV0 => "MLibrary V0",
             LibraryType::MLV1 => "MLibrary V1",
             LibraryType::MLV2 => "MLibrary V2",
             LibraryType::WeMade => "WeMade Library",
             LibraryType::WTL => "WTL Library",
+            LibraryType::EncryptedWil => "Encrypted WIL",
+            LibraryType::Mir3 => "Mir3 Library",
+        }
+    }
+
+    /// 获取格式能力
+    pub fn capabilities(&self) -> Capabilities {
+        match self {
+            LibraryType::MLV2 => Capabilities {
+                supports_mask: true,
+                supports_shadow: true,
+                paletted: false,
+                writable: true,
+                editable_properties: true,
+            },
+            LibraryType::MLV1 => Capabilities {
+                supports_mask: true,
+                supports_shadow: true,
+                paletted: false,
+                writable: true,
+                editable_properties: false,
+            },
+            LibraryType::Mir3 => Capabilities {
+                supports_shadow: true,
+                ..Default::default()
+            },
+            LibraryType::MLV0 | LibraryType::WeMade | LibraryType::EncryptedWil => Capabilities {
+                paletted: true,
+                ..Default::default()
+            },
+            LibraryType::WTL => Capabilities::default(),
         }
     }
 }
 
+/// 格式能力标志（GUI 据此禁用不支持的操作）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Capabilities {
+    /// 帧带遮罩层
+    pub supports_mask: bool,
+    /// 帧带阴影信息
+    pub supports_shadow: bool,
+    /// 8 位调色板格式
+    pub paletted: bool,
+    /// 支持保存、替换、添加和删除图像
+    pub writable: bool,
+    /// 可以修改帧的阴影和遮罩属性（WZL 保存时不写这些字段）
+    pub editable_properties: bool,
+}
+
 /// 库文件信息（用于GUI显示）
 #[derive(Debug, Clone)]
 pub struct LibraryInfo {
     /// 文件路径（不含扩展名）
-    pub base_path: String,
+    pub base_path: PathBuf,
     /// 文件名
     pub file_name: String,
     /// 库类型
@@ -91,7 +256,7 @@ pub struct LibraryInfo {
 impl LibraryInfo {
     /// 创建新的库信息
     pub fn new(
-        base_path: String,
+        base_path: PathBuf,
         file_name: String,
         library_type: LibraryType,
         image_count: usize,
@@ -105,10 +270,20 @@ impl LibraryInfo {
         }
     }
 
+    /// 库文件路径（基础路径所在目录下的文件名）
+    pub fn path(&self) -> PathBuf {
+        self.base_path.with_file_name(&self.file_name)
+    }
+
     /// 获取格式名称字符串
     pub fn format_name(&self) -> String {
         self.library_type.name().to_string()
     }
+
+    /// 获取格式能力
+    pub fn capabilities(&self) -> Capabilities {
+        self.library_type.capabilities()
+    }
 }
 
 /// 图像信息（用于GUI显示）
@@ -133,31 +308,46 @@ pub struct ImageInfo {
 /// 遮罩信息
 #[derive(Debug, Clone)]
 pub enum ShadowInfo {
+    /// 没有阴影信息
     None,
+    /// 只有阴影参数
     Simple {
+        /// 阴影值
         shadow: u8,
+        /// 阴影 X 偏移
         shadow_x: i16,
+        /// 阴影 Y 偏移
         shadow_y: i16,
     },
+    /// 阴影参数和遮罩层
     Mask {
+        /// 阴影值
         shadow: u8,
+        /// 阴影 X 偏移
         shadow_x: i16,
+        /// 阴影 Y 偏移
         shadow_y: i16,
+        /// 遮罩宽度
         mask_width: i16,
+        /// 遮罩高度
         mask_height: i16,
+        /// 遮罩 X 偏移
+        mask_x: i16,
+        /// 遮罩 Y 偏移
+        mask_y: i16,
     },
 }
 
 impl ImageInfo {
-    /// 从 MLibraryV1::MImage 创建图像信息
-    pub fn from_v1_image(index: usize, image: &mlibrary_v1::MImage) -> Self {
+    /// 从 WZL (V1) 或 .Lib (V2) 帧的公共字段创建图像信息
+    pub fn from_frame(index: usize, frame: &FrameData) -> Self {
         Self {
             index,
-            width: image.width as i32,
-            height: image.height as i32,
-            x: image.x as i32,
-            y: image.y as i32,
-            has_mask: ShadowInfo::None,
+            width: frame.width as i32,
+            height: frame.height as i32,
+            x: frame.x as i32,
+            y: frame.y as i32,
+            has_mask: frame.shadow_info(),
         }
     }
 
@@ -173,32 +363,9 @@ impl ImageInfo {
         }
     }
 
-    /// 从 MLibraryV2::MImage 创建图像信息
-    pub fn from_v2_image(index: usize, image: &mlibrary_v2::MImage) -> Self {
-        let shadow_info = if image.has_mask {
-            ShadowInfo::Mask {
-                shadow: image.shadow,
-                shadow_x: image.shadow_x,
-                shadow_y: image.shadow_y,
-                mask_width: image.mask_width,
-                mask_height: image.mask_height,
-            }
-        } else {
-            ShadowInfo::Simple {
-                shadow: image.shadow,
-                shadow_x: image.shadow_x,
-                shadow_y: image.shadow_y,
-            }
-        };
-
-        Self {
-            index,
-            width: image.width as i32,
-            height: image.height as i32,
-            x: image.x as i32,
-            y: image.y as i32,
-            has_mask: shadow_info,
-        }
+    /// 是否为空帧（宽或高为 0 的占位帧）
+    pub fn is_empty(&self) -> bool {
+        self.width <= 0 || self.height <= 0
     }
 
     /// 获取尺寸字符串
@@ -216,8 +383,40 @@ pub struct LibraryLoader {
     library_v2: Option<MLibraryV2>,
     /// MLibrary V0 / WeMade Library 实例
     library_v0: Option<MLibraryV0>,
+    /// 加密 WIL 实例
+    library_enc: Option<EncryptedWilLibrary>,
+    /// 传奇3 库实例
+    library_mir3: Option<Mir3Library>,
+    /// 空帧占位策略
+    placeholder: PlaceholderPolicy,
+    /// 已删除、尚未保存的帧
+    trash: Trash,
+    /// 未保存的修改记录
+    edits: EditLog,
+    /// 锁定（写保护）的帧
+    locks: FrameLocks,
+    /// 帧书签
+    bookmarks: Bookmarks,
+    /// 解码后上下翻转（库的行序与格式约定相反时使用）
+    flip_vertical: bool,
+    /// 仅追加模式：已有帧的索引不会移动
+    append_only: bool,
+    /// 解码帧的内存预算
+    frame_cache: FrameCache,
+    /// 打开模式
+    mode: OpenMode,
+    /// 磁盘上的库：编辑锁加在这个基础路径旁（内存中的库为 `None`，不加锁）
+    lock_base: Option<PathBuf>,
+    /// 持有中的编辑锁
+    edit_lock: Option<EditLock>,
 }
 
+// 加载器可在线程间传递和共享（如 `Arc<Mutex<LibraryLoader>>`），新增字段时保持这一点
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<LibraryLoader>();
+};
+
 impl LibraryLoader {
     /// 创建新的加载器
     pub fn new() -> Self {
@@ -226,16 +425,152 @@ impl LibraryLoader {
             library_v1: None,
             library_v2: None,
             library_v0: None,
+            library_enc: None,
+            library_mir3: None,
+            placeholder: crate::image::placeholder::loader_default(),
+            trash: Trash::default(),
+            edits: EditLog::default(),
+            locks: FrameLocks::default(),
+            bookmarks: Bookmarks::default(),
+            flip_vertical: false,
+            append_only: false,
+            frame_cache: FrameCache::default(),
+            mode: OpenMode::default(),
+            lock_base: None,
+            edit_lock: None,
         }
     }
 
+    /// 由内存中的 V2 库构造加载器（用于合成数据，如 GUI 基准测试）
+    pub fn from_v2(library: MLibraryV2, file_name: &str) -> (LibraryInfo, Self) {
+        let info = LibraryInfo::new(
+            library.file_name.clone(),
+            file_name.to_string(),
+            LibraryType::MLV2,
+            library.count(),
+        );
+
+        let mut loader = Self::new();
+        loader.info = Some(info.clone());
+        loader.library_v2 = Some(library);
+        (info, loader)
+    }
+
     /// 从文件路径加载库
     pub fn load(path: &Path) -> Result<(LibraryInfo, Self)> {
         tracing::debug!("开始加载库文件: {:?}", path);
         tracing::debug!("文件存在: {}", path.exists());
 
-        // 获取文件扩展名
-        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+        let (lib_type, mir3_container) = Self::detect_type(path)?;
+        Self::open_as(path, lib_type, mir3_container, None)
+    }
+
+    /// 从文件路径加载库，并报告读取索引、调色板和解码帧的进度
+    pub fn load_with_progress(
+        path: &Path,
+        progress: impl FnMut(Progress),
+    ) -> Result<(LibraryInfo, Self)> {
+        Self::load_cancellable(path, &CancelToken::new(), progress)
+    }
+
+    /// 同 [`load_with_progress`](Self::load_with_progress)，`cancel` 被取消后在下一帧
+    /// 之前中止并返回 [`LibraryError::Cancelled`]
+    pub fn load_cancellable(
+        path: &Path,
+        cancel: &CancelToken,
+        progress: impl FnMut(Progress),
+    ) -> Result<(LibraryInfo, Self)> {
+        tracing::debug!("开始加载库文件（报告进度）: {:?}", path);
+
+        let (lib_type, mir3_container) = Self::detect_type(path)?;
+        Self::open_with_progress(path, lib_type, mir3_container, None, cancel, progress)
+    }
+
+    /// 在后台线程中加载库，返回可取消的句柄；`progress` 在加载线程中调用
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn spawn_load(path: &Path, progress: impl FnMut(Progress) + Send + 'static) -> LoadHandle {
+        let cancel = CancelToken::new();
+        let token = cancel.clone();
+        let path = path.to_path_buf();
+        LoadHandle::spawn(cancel, move || {
+            Self::load_cancellable(&path, &token, progress)
+        })
+    }
+
+    /// 从数据来源加载库（如内存中的文件），按 `file_name` 的扩展名和文件头识别格式
+    ///
+    /// 不读取磁盘上的锁定记录。
+    pub fn from_source(file_name: &str, source: Source) -> Result<(LibraryInfo, Self)> {
+        tracing::debug!("从数据来源加载库: {}", source.describe(""));
+
+        let path = Path::new(file_name);
+        let (lib_type, mir3_container) = Self::detect_source(path, &source)?;
+        Self::open_source(path, source, lib_type, mir3_container, None)
+    }
+
+    /// 按位置加载库：本地路径或 `http(s)://` 地址（远程库先下载到本地缓存）
+    #[cfg(all(feature = "cli", not(target_arch = "wasm32")))]
+    pub fn open_location(location: &str) -> Result<(LibraryInfo, Self)> {
+        Self::load(&remote::resolve(location, false)?)
+    }
+
+    /// 只读取索引和帧头部，得到帧数和每帧尺寸，不读取任何像素数据
+    pub fn open_header(path: &Path) -> Result<LibraryHeader> {
+        tracing::debug!("仅读取文件头: {:?}", path);
+
+        let (lib_type, mir3_container) = Self::detect_type(path)?;
+        let base_path = path.with_extension("");
+        let file_name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        let frames = match lib_type {
+            LibraryType::MLV1 => MLibraryV1::new(base_path.clone())?.frame_headers()?,
+            LibraryType::MLV2 => MLibraryV2::new(base_path.clone())?.frame_headers()?,
+            LibraryType::WeMade | LibraryType::MLV0 => {
+                MLibraryV0::new(base_path.clone())?.frame_headers()?
+            }
+            LibraryType::EncryptedWil => {
+                EncryptedWilLibrary::new(base_path.clone())?.frame_headers()?
+            }
+            LibraryType::Mir3 => {
+                let container = mir3_container.unwrap_or(mir3_library::Mir3Container::Wil);
+                Mir3Library::new(base_path.clone(), container)?.frame_headers()?
+            }
+            _ => {
+                tracing::error!("暂不支持此格式: {}", lib_type.name());
+                return Err(LibraryError::InvalidFormat);
+            }
+        };
+
+        tracing::debug!("读取到 {} 帧头部", frames.len());
+        Ok(LibraryHeader {
+            info: LibraryInfo::new(base_path, file_name, lib_type, frames.len()),
+            frames,
+        })
+    }
+
+    /// 识别库类型，不读取索引和帧数据
+    pub fn detect_library_type(path: &Path) -> Result<LibraryType> {
+        Ok(Self::detect_type(path)?.0)
+    }
+
+    /// 按扩展名和文件头识别磁盘上的库类型
+    fn detect_type(path: &Path) -> Result<(LibraryType, Option<mir3_library::Mir3Container>)> {
+        Self::detect_source(path, &Source::Path(path.with_extension("")))
+    }
+
+    /// 按 `path` 的扩展名和来源中的文件头识别库类型
+    fn detect_source(
+        path: &Path,
+        source: &Source,
+    ) -> Result<(LibraryType, Option<mir3_library::Mir3Container>)> {
+        // 获取文件扩展名（扩展名本身总是 ASCII，非 UTF-8 的文件名部分不影响识别）
+        let extension = path
+            .extension()
+            .map(|e| e.to_string_lossy().into_owned())
+            .unwrap_or_default();
 
         tracing::debug!("文件扩展名: {}", extension);
 
@@ -246,89 +581,215 @@ impl LibraryLoader {
                 LibraryError::InvalidFormat
             })?;
 
+        // 传奇3 与传奇2 共用扩展名，需要根据索引文件头区分
+        let mir3_container = match lib_type {
+            LibraryType::WeMade | LibraryType::MLV1 => mir3_library::sniff(source),
+            _ => None,
+        };
+
+        // 文件头不是明文 WIL 时按加密 WIL 处理
+        let lib_type = if mir3_container.is_some() {
+            LibraryType::Mir3
+        } else if lib_type == LibraryType::WeMade && encrypted_wil::sniff(source, ".wil").is_some()
+        {
+            LibraryType::EncryptedWil
+        } else {
+            lib_type
+        };
+
         tracing::debug!("识别为格式: {}", lib_type.name());
 
-        // 获取基础路径（去掉扩展名）
-        // 注意：with_extension("") 会保留末尾的点，需要手动去除
-        let base_path = path
-            .with_extension("")
-            .to_str()
-            .ok_or_else(|| LibraryError::ParseError("路径转换失败".to_string()))?
-            .trim_end_matches('.')
-            .to_string();
+        Ok((lib_type, mir3_container))
+    }
+
+    /// 按手动指定的选项加载库（跳过格式自动识别，用于打开向导）
+    pub fn load_with(path: &Path, options: &OpenOptions) -> Result<(LibraryInfo, Self)> {
+        Self::load_with_cancellable(path, options, &CancelToken::new(), |_| {})
+    }
+
+    /// 同 [`load_with`](Self::load_with)，报告进度并可取消，见
+    /// [`load_cancellable`](Self::load_cancellable)
+    pub fn load_with_cancellable(
+        path: &Path,
+        options: &OpenOptions,
+        cancel: &CancelToken,
+        progress: impl FnMut(Progress),
+    ) -> Result<(LibraryInfo, Self)> {
+        tracing::debug!("按手动选项加载库文件: {:?} {:?}", path, options);
+
+        let mir3_container = match options.library_type {
+            LibraryType::Mir3 => Some(
+                mir3_library::sniff(&Source::Path(path.with_extension("")))
+                    .unwrap_or(mir3_library::Mir3Container::Wil),
+            ),
+            _ => None,
+        };
+
+        let (info, mut loader) = Self::open_with_progress(
+            path,
+            options.library_type,
+            mir3_container,
+            Some(options),
+            cancel,
+            progress,
+        )?;
+        loader.flip_vertical = options.flip_vertical;
+        Ok((info, loader))
+    }
+
+    /// 加载磁盘上的库并报告进度；各格式都只读取索引（和调色板），帧在访问时解码
+    fn open_with_progress(
+        path: &Path,
+        lib_type: LibraryType,
+        mir3_container: Option<mir3_library::Mir3Container>,
+        options: Option<&OpenOptions>,
+        cancel: &CancelToken,
+        mut progress: impl FnMut(Progress),
+    ) -> Result<(LibraryInfo, Self)> {
+        cancel.check()?;
+        let (info, loader) = Self::open_as(path, lib_type, mir3_container, options)?;
+        progress(Progress::IndexRead {
+            frames: info.image_count,
+        });
+        if lib_type.capabilities().paletted {
+            progress(Progress::PaletteRead);
+        }
+        Ok((info, loader))
+    }
+
+    /// 使用指定的解析器加载磁盘上的库
+    fn open_as(
+        path: &Path,
+        lib_type: LibraryType,
+        mir3_container: Option<mir3_library::Mir3Container>,
+        options: Option<&OpenOptions>,
+    ) -> Result<(LibraryInfo, Self)> {
+        let source = Source::Path(path.with_extension(""));
+        let (info, mut loader) =
+            Self::open_source(path, source, lib_type, mir3_container, options)?;
+
+        // 锁定记录读取失败不影响打开，只是没有写保护
+        loader.lock_base = Some(info.base_path.clone());
+        loader.locks = FrameLocks::load(&info.base_path).unwrap_or_else(|e| {
+            tracing::warn!("读取锁定记录失败: {:?}", e);
+            FrameLocks::default()
+        });
+        loader.bookmarks = Bookmarks::load(&info.base_path).unwrap_or_else(|e| {
+            tracing::warn!("读取书签失败: {:?}", e);
+            Bookmarks::default()
+        });
+        Ok((info, loader))
+    }
 
-        tracing::debug!("基础路径: {}", base_path);
+    /// 使用指定的解析器从数据来源加载库，`path` 提供库的文件名和基础路径
+    fn open_source(
+        path: &Path,
+        source: Source,
+        lib_type: LibraryType,
+        mir3_container: Option<mir3_library::Mir3Container>,
+        options: Option<&OpenOptions>,
+    ) -> Result<(LibraryInfo, Self)> {
+        // 获取基础路径（去掉扩展名），全程保持 PathBuf，不做字符串往返转换
+        let base_path = path.with_extension("");
+        tracing::debug!("基础路径: {:?}", base_path);
+
+        let file_name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
 
         // 根据类型加载
-        match lib_type {
+        let loaded = match lib_type {
             LibraryType::MLV1 => {
                 tracing::debug!("使用 MLibrary V1 加载器");
-                let library = MLibraryV1::new(base_path.clone())?;
+                let library = MLibraryV1::from_source(base_path.clone(), source)?;
                 let count = library.count();
 
                 tracing::debug!("成功加载 {count} 张图像");
 
-                let file_name = path
-                    .file_name()
-                    .and_then(|n| n.to_str())
-                    .unwrap_or("")
-                    .to_string();
-
                 let info = LibraryInfo::new(base_path, file_name, lib_type, count);
 
                 let mut loader = Self::new();
                 loader.info = Some(info.clone());
                 loader.library_v1 = Some(library);
 
-                Ok((info, loader))
+                (info, loader)
             }
             LibraryType::MLV2 => {
                 tracing::debug!("使用 MLibrary V2 加载器");
-                let library = MLibraryV2::new(base_path.clone())?;
+                let library = MLibraryV2::from_source(base_path.clone(), source)?;
                 let count = library.count();
 
                 tracing::debug!("成功加载 {} 张图像", count);
 
-                let file_name = path
-                    .file_name()
-                    .and_then(|n| n.to_str())
-                    .unwrap_or("")
-                    .to_string();
-
                 let info = LibraryInfo::new(base_path, file_name, lib_type, count);
 
                 let mut loader = Self::new();
                 loader.info = Some(info.clone());
                 loader.library_v2 = Some(library);
 
-                Ok((info, loader))
+                (info, loader)
             }
             LibraryType::WeMade | LibraryType::MLV0 => {
                 tracing::debug!("使用 WeMade Library 加载器");
-                let library = MLibraryV0::new(base_path.clone())?;
+                let mut library = MLibraryV0::from_source(
+                    base_path.clone(),
+                    source,
+                    options.and_then(|o| o.index_offset),
+                )?;
+                if let Some(palette) = options.map(|o| o.palette.resolve()).transpose()?.flatten() {
+                    library.set_palette(palette);
+                }
                 let count = library.count;
 
                 tracing::debug!("成功加载 {} 张图像", count);
 
-                let file_name = path
-                    .file_name()
-                    .and_then(|n| n.to_str())
-                    .unwrap_or("")
-                    .to_string();
-
                 let info = LibraryInfo::new(base_path, file_name, lib_type, count);
 
                 let mut loader = Self::new();
                 loader.info = Some(info.clone());
                 loader.library_v0 = Some(library);
 
-                Ok((info, loader))
+                (info, loader)
+            }
+            LibraryType::EncryptedWil => {
+                tracing::debug!("使用加密 WIL 加载器");
+                let library = EncryptedWilLibrary::from_source(base_path.clone(), source)?;
+                let count = library.count();
+
+                tracing::debug!("成功加载 {} 张图像", count);
+
+                let info = LibraryInfo::new(base_path, file_name, lib_type, count);
+
+                let mut loader = Self::new();
+                loader.info = Some(info.clone());
+                loader.library_enc = Some(library);
+
+                (info, loader)
+            }
+            LibraryType::Mir3 => {
+                tracing::debug!("使用 Mir3 Library 加载器");
+                let container = mir3_container.unwrap_or(mir3_library::Mir3Container::Wil);
+                let library = Mir3Library::from_source(base_path.clone(), source, container)?;
+                let count = library.count();
+
+                tracing::debug!("成功加载 {} 张图像", count);
+
+                let info = LibraryInfo::new(base_path, file_name, lib_type, count);
+
+                let mut loader = Self::new();
+                loader.info = Some(info.clone());
+                loader.library_mir3 = Some(library);
+
+                (info, loader)
             }
             _ => {
                 tracing::error!("暂不支持此格式: {}", lib_type.name());
-                Err(LibraryError::InvalidFormat)
+                return Err(LibraryError::InvalidFormat);
             }
-        }
+        };
+
+        Ok(loaded)
     }
 
     /// 获取库信息
@@ -343,13 +804,13 @@ impl LibraryLoader {
         // 优先从 V2 获取
         if let Some(ref mut lib) = self.library_v2 {
             let image = lib.get_image(index)?;
-            let info = ImageInfo::from_v2_image(index, image);
+            let info = ImageInfo::from_frame(index, image);
             tracing::debug!("图像信息: {}x{}, offset: ({}, {})", info.width, info.height, info.x, info.y);
             Ok(info)
         } else if let Some(ref mut lib) = self.library_v1 {
             // 从 V1 获取
             let image = lib.get_image(index)?;
-            let info = ImageInfo::from_v1_image(index, image);
+            let info = ImageInfo::from_frame(index, image);
             tracing::debug!("图像信息: {}x{}, offset: ({}, {})", info.width, info.height, info.x, info.y);
             Ok(info)
         } else if let Some(ref mut lib) = self.library_v0 {
@@ -358,6 +819,18 @@ impl LibraryLoader {
             let info = ImageInfo::from_v0_image(index, image);
             tracing::debug!("图像信息: {}x{}, offset: ({}, {})", info.width, info.height, info.x, info.y);
             Ok(info)
+        } else if let Some(ref mut lib) = self.library_enc {
+            // 从加密 WIL 获取（解密后与 V0 结构相同）
+            let image = lib.get_image(index)?;
+            let info = ImageInfo::from_v0_image(index, image);
+            tracing::debug!("图像信息: {}x{}, offset: ({}, {})", info.width, info.height, info.x, info.y);
+            Ok(info)
+        } else if let Some(ref mut lib) = self.library_mir3 {
+            // 从 Mir3 获取（帧结构与 V2 相同，带阴影信息）
+            let image = lib.get_image(index)?;
+            let info = ImageInfo::from_frame(index, image);
+            tracing::debug!("图像信息: {}x{}, offset: ({}, {})", info.width, info.height, info.x, info.y);
+            Ok(info)
         } else {
             Err(LibraryError::ParseError(
                 "获取图像信息时异常：库未加载".to_string(),
@@ -365,125 +838,1484 @@ impl LibraryLoader {
         }
     }
 
-    /// 获取图像预览
-    pub fn get_preview(&mut self, index: usize) -> Result<Option<image::RgbaImage>> {
-        tracing::debug!("获取图像预览: index={}", index);
+    /// 获取空帧占位策略
+    pub fn placeholder_policy(&self) -> PlaceholderPolicy {
+        self.placeholder
+    }
 
-        // 优先从 V2 获取
+    /// 设置空帧占位策略
+    pub fn set_placeholder_policy(&mut self, policy: PlaceholderPolicy) {
+        self.placeholder = policy;
+    }
+
+    /// 是否上下翻转显示
+    pub fn flip_vertical(&self) -> bool {
+        self.flip_vertical
+    }
+
+    /// 设置是否上下翻转显示（影响预览、遮罩、导出等所有解码结果）
+    pub fn set_flip_vertical(&mut self, flip: bool) {
+        self.flip_vertical = flip;
+    }
+
+    /// 解码帧的内存预算
+    pub fn cache_policy(&self) -> CachePolicy {
+        self.frame_cache.policy()
+    }
+
+    /// 设置解码帧的内存预算，超出时释放最久未访问的帧
+    ///
+    /// 设置为有限的预算时，之前解码、尚未记录访问顺序的帧全部释放。
+    pub fn set_cache_policy(&mut self, policy: CachePolicy) {
+        for index in self.frame_cache.set_policy(policy) {
+            self.release_frame(index);
+        }
+        if !policy.is_unlimited() {
+            for index in 0..self.image_count() {
+                if !self.frame_cache.contains(index) {
+                    self.release_frame(index);
+                }
+            }
+        }
+    }
+
+    /// 释放一帧解码后的图像，下次访问时重新解码
+    fn release_frame(&mut self, index: usize) {
         if let Some(ref mut lib) = self.library_v2 {
-            let preview = lib.get_preview(index)?.cloned();
-            return Ok(preview);
+            lib.release_image(index);
+        } else if let Some(ref mut lib) = self.library_v1 {
+            lib.release_image(index);
+        } else if let Some(ref mut lib) = self.library_v0 {
+            lib.release_image(index);
+        } else if let Some(ref mut lib) = self.library_enc {
+            lib.release_image(index);
+        } else if let Some(ref mut lib) = self.library_mir3 {
+            lib.release_image(index);
         }
+    }
 
-        // 从 V1 获取
-        if let Some(ref mut lib) = self.library_v1 {
-            let preview = lib.get_preview(index)?.cloned();
-            return Ok(preview);
+    /// 打开模式
+    pub fn open_mode(&self) -> OpenMode {
+        self.mode
+    }
+
+    /// 是否持有编辑锁
+    pub fn holds_edit_lock(&self) -> bool {
+        self.edit_lock.is_some()
+    }
+
+    /// 切换打开模式
+    ///
+    /// 切换为读写时立即取得编辑锁，库正被其他程序编辑时返回
+    /// [`LibraryError::LibraryInUse`] 并保持原模式；切换为只读时释放编辑锁。
+    /// 打开后未切换过时为读写模式，在第一次保存时取得编辑锁。
+    pub fn set_open_mode(&mut self, mode: OpenMode) -> Result<()> {
+        match mode {
+            OpenMode::ReadWrite => self.acquire_edit_lock()?,
+            OpenMode::ReadOnly => self.edit_lock = None,
         }
+        self.mode = mode;
+        Ok(())
+    }
 
-        // 从 V0 (WeMade) 获取
-        if let Some(ref mut lib) = self.library_v0 {
-            let image = lib.get_image(index)?;
-            if let Some(ref img) = image.image {
-                return Ok(Some(img.clone()));
+    /// 取得编辑锁（已持有或内存中的库时直接返回）
+    fn acquire_edit_lock(&mut self) -> Result<()> {
+        if self.edit_lock.is_some() {
+            return Ok(());
+        }
+        if let Some(ref base) = self.lock_base {
+            self.edit_lock = EditLock::acquire(base)?;
+        }
+        Ok(())
+    }
+
+    /// 是否处于仅追加模式
+    pub fn append_only(&self) -> bool {
+        self.append_only
+    }
+
+    /// 设置仅追加模式
+    ///
+    /// 服务端数据库按索引引用帧，开启后删除帧改为清空成空帧，新帧只能追加到末尾，
+    /// 恢复回收站中的帧也不能插到已有帧之前，保证已有帧的索引不会移动。
+    pub fn set_append_only(&mut self, append_only: bool) {
+        self.append_only = append_only;
+    }
+
+    /// 抽样检测库是否上下颠倒
+    ///
+    /// 在全部帧中均匀抽取至多 `samples` 帧投票，结果基于当前的翻转设置。
+    pub fn detect_orientation(&mut self, samples: usize) -> OrientationGuess {
+        let count = self.image_count();
+        let step = count.div_ceil(samples.max(1)).max(1);
+        let mut guess = OrientationGuess::default();
+        for index in (0..count).step_by(step) {
+            if let Ok(Some(frame)) = self.get_raw_preview(index) {
+                guess.add(&frame);
             }
+        }
+        tracing::debug!("方向检测: {:?}", guess);
+        guess
+    }
+
+    /// 帧图像及其绘制偏移（用于双库对比），空帧或超出帧数时返回 `None`
+    pub fn get_placed(&mut self, index: usize) -> Result<Option<PlacedFrame>> {
+        if index >= self.image_count() {
             return Ok(None);
         }
+        let Some(image) = self.get_raw_preview(index)? else {
+            return Ok(None);
+        };
+        let info = self.get_image_info(index)?;
+        Ok(Some((image, (info.x, info.y))))
+    }
 
-        Err(LibraryError::ParseError(
-            "获取图像预览时异常：库未加载".to_string(),
-        ))
+    /// 依次取出多帧的图像及绘制偏移（用于导出动画），空帧为 `None`
+    pub fn placed_frames(&mut self, indices: &[usize]) -> Result<Vec<Option<PlacedFrame>>> {
+        indices
+            .iter()
+            .map(|&index| self.get_placed(index))
+            .collect()
     }
 
-    /// 获取图像数量
-    pub fn image_count(&self) -> usize {
-        self.info.as_ref().map(|i| i.image_count).unwrap_or(0)
+    /// 取出范围内每帧的索引、图像及绘制偏移（用于基线对齐），空帧为 `None`
+    pub fn placed_range(
+        &mut self,
+        range: Range<usize>,
+    ) -> Result<Vec<(usize, Option<PlacedFrame>)>> {
+        let end = range.end.min(self.image_count());
+        (range.start.min(end)..end)
+            .map(|index| Ok((index, self.get_placed(index)?)))
+            .collect()
     }
 
-    /// 保存库
-    pub fn save(&self) -> Result<()> {
-        tracing::debug!("保存库文件");
+    /// 把范围内的帧作为地图图块按 `columns` 列无缝拼接，范围内全是空帧时返回 `None`
+    pub fn tile_grid(
+        &mut self,
+        range: Range<usize>,
+        columns: u32,
+    ) -> Result<Option<image::RgbaImage>> {
+        let end = range.end.min(self.image_count());
+        let frames = (range.start.min(end)..end)
+            .map(|index| self.get_frame(index))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(tiles::assemble(&frames, columns))
+    }
 
-        if let Some(ref lib) = self.library_v2 {
-            lib.save()?;
-            tracing::debug!("保存成功");
-            Ok(())
-        } else {
-            Err(LibraryError::ParseError(
-                "保存库文件时异常：库未加载".to_string(),
-            ))
-        }
+    /// 把范围内的帧的缩略图按 `columns` 列排成带帧号的联系表，格子边长为 `cell`
+    pub fn contact_sheet(
+        &mut self,
+        range: Range<usize>,
+        columns: u32,
+        cell: u32,
+    ) -> Result<image::RgbaImage> {
+        let end = range.end.min(self.image_count());
+        let frames = (range.start.min(end)..end)
+            .map(|index| Ok((index, self.get_pixel_thumbnail(index, cell)?)))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(contact_sheet::assemble(&frames, columns, cell))
     }
 
-    /// 替换图像
-    pub fn replace_image(
+    /// 获取图像预览
+    ///
+    /// 帧本身没有图像时按当前占位策略生成占位图。未翻转时与后端缓存共享同一份像素。
+    pub fn get_preview(&mut self, index: usize) -> Result<Option<Arc<image::RgbaImage>>> {
+        tracing::debug!("获取图像预览: index={}", index);
+
+        let preview = self.get_raw_preview(index)?;
+        Ok(preview.or_else(|| self.placeholder.render().map(Arc::new)))
+    }
+
+    /// 获取缩略图：等比缩小到不超过 `size`×`size`，小图不放大
+    ///
+    /// 用于缩略图列表，避免把完整尺寸的图像交给界面；空帧同样按占位策略生成占位图。
+    pub fn get_thumbnail(
         &mut self,
         index: usize,
-        image: &crate::formats::mlibrary_v2::MImage,
-    ) -> Result<()> {
-        tracing::debug!("替换图像: index={}", index);
+        size: u32,
+    ) -> Result<Option<Arc<image::RgbaImage>>> {
+        let preview = self.get_preview(index)?;
+        Ok(preview.map(|image| crate::image::fit_thumbnail(image, size)))
+    }
 
-        if let Some(ref mut lib) = self.library_v2 {
-            lib.replace_image(index, image)?;
-            tracing::debug!("替换成功");
-            Ok(())
-        } else {
-            Err(LibraryError::ParseError(
-                "替换图像时异常：库未加载".to_string(),
-            ))
+    /// 获取像素图缩略图：小图按整数倍最近邻放大，大图等比缩小，不超过 `size`×`size`
+    ///
+    /// 与界面缩略图列表相同（见 [`crate::image::fit_pixel_thumbnail`]），空帧按占位策略生成占位图。
+    pub fn get_pixel_thumbnail(
+        &mut self,
+        index: usize,
+        size: u32,
+    ) -> Result<Option<Arc<image::RgbaImage>>> {
+        let preview = self.get_preview(index)?;
+        Ok(preview.map(|image| crate::image::fit_pixel_thumbnail(image, size)))
+    }
+
+    /// 获取原始帧图像（完整尺寸，不应用占位策略）
+    pub fn get_frame(&mut self, index: usize) -> Result<Option<Arc<image::RgbaImage>>> {
+        self.get_raw_preview(index)
+    }
+
+    /// 帧信息满足 `predicate` 的全部帧索引，按索引顺序排列；读取帧信息失败的帧跳过
+    pub fn find_frames(&mut self, mut predicate: impl FnMut(&ImageInfo) -> bool) -> Vec<usize> {
+        let mut found = Vec::new();
+        for index in 0..self.image_count() {
+            match self.get_image_info(index) {
+                Ok(info) if predicate(&info) => found.push(index),
+                Ok(_) => {}
+                Err(e) => tracing::debug!("筛选时读取第 {} 帧信息失败: {:?}", index, e),
+            }
         }
+        found
     }
 
-    /// 添加图像
-    pub fn add_image(&mut self, image: &crate::formats::mlibrary_v2::MImage) -> Result<()> {
-        tracing::debug!("添加新图像");
+    /// 按索引顺序逐帧解码，产出 `(索引, 帧信息, 图像)`，跳过空帧
+    ///
+    /// 每次调用 `next` 时才解码下一帧。某帧解码失败时产出错误，之后仍可继续迭代。
+    pub fn iter_frames(&mut self) -> Frames<'_> {
+        Frames {
+            loader: self,
+            next: 0,
+        }
+    }
 
-        if let Some(ref mut lib) = self.library_v2 {
-            lib.add_image(image);
-            tracing::debug!("添加成功");
-            Ok(())
+    /// 获取帧的遮罩图像（仅 V1/V2 支持遮罩层）
+    pub fn get_mask(&mut self, index: usize) -> Result<Option<image::RgbaImage>> {
+        let mask = if let Some(ref mut lib) = self.library_v2 {
+            lib.get_image(index)?.mask_image.clone()
+        } else if let Some(ref mut lib) = self.library_v1 {
+            lib.get_image(index)?.mask_image.clone()
         } else {
-            Err(LibraryError::ParseError(
-                "添加图像时异常：库未加载".to_string(),
-            ))
+            None
+        };
+        Ok(self.apply_flip(mask))
+    }
+
+    /// 获取基础层与遮罩层的合成图像
+    ///
+    /// 没有遮罩层的帧直接返回基础层图像（不应用占位策略）。
+    pub fn get_composite(
+        &mut self,
+        index: usize,
+        blend: MaskBlend,
+    ) -> Result<Option<Arc<image::RgbaImage>>> {
+        let info = self.get_image_info(index)?;
+        let base = self.get_frame(index)?;
+
+        let ShadowInfo::Mask { mask_x, mask_y, .. } = info.has_mask else {
+            return Ok(base);
+        };
+        let Some(mask) = self.get_mask(index)? else {
+            return Ok(base);
+        };
+        // 基础层为空帧时以 1x1 透明图占位，只显示遮罩
+        let base = base.unwrap_or_else(|| Arc::new(image::RgbaImage::new(1, 1)));
+
+        Ok(Some(Arc::new(layers::composite(
+            &base,
+            (info.x, info.y),
+            &mask,
+            (mask_x as i32, mask_y as i32),
+            blend,
+        ))))
+    }
+
+    /// 获取带洋葱皮的帧图像：前后帧按 `opacity`（0-255）半透明地垫在下方，均按偏移对齐
+    ///
+    /// 当前帧为空帧时与 `get_preview` 相同。
+    pub fn get_onion_skin(
+        &mut self,
+        index: usize,
+        opacity: u8,
+    ) -> Result<Option<Arc<image::RgbaImage>>> {
+        let Some((image, offset)) = self.get_placed(index)? else {
+            return self.get_preview(index);
+        };
+        let previous = match index.checked_sub(1) {
+            Some(previous) => self.get_placed(previous)?,
+            None => None,
+        };
+        let next = self.get_placed(index + 1)?;
+        let ghosts: Vec<Placed> = previous
+            .iter()
+            .chain(&next)
+            .map(|(ghost, offset)| Placed::new(ghost, *offset))
+            .collect();
+        Ok(Some(Arc::new(compare::onion_skin(
+            Placed::new(&image, offset),
+            &ghosts,
+            opacity,
+        ))))
+    }
+
+    /// 获取在下方绘制了阴影的帧图像
+    ///
+    /// 按帧的 shadow / shadow_x / shadow_y 绘制；没有阴影信息的帧与 `get_preview` 相同。
+    pub fn get_with_shadow(&mut self, index: usize) -> Result<Option<Arc<image::RgbaImage>>> {
+        let info = self.get_image_info(index)?;
+        let (ShadowInfo::Simple {
+            shadow,
+            shadow_x,
+            shadow_y,
         }
+        | ShadowInfo::Mask {
+            shadow,
+            shadow_x,
+            shadow_y,
+            ..
+        }) = info.has_mask
+        else {
+            return self.get_preview(index);
+        };
+        let Some(frame) = self.get_frame(index)? else {
+            return self.get_preview(index);
+        };
+
+        Ok(Some(Arc::new(shadow::render_with_shadow(
+            &frame,
+            ShadowStyle::from_shadow(shadow),
+            (shadow_x as i32, shadow_y as i32),
+        ))))
     }
 
-    /// 删除图像
-    pub fn remove_image(&mut self, index: usize) -> Result<()> {
-        tracing::debug!("删除图像: index={}", index);
+    /// 获取按游戏规则摆放在地图格网格上的帧图像（空帧只有网格和原点）
+    pub fn get_placement(&mut self, index: usize) -> Result<image::RgbaImage> {
+        let placed = self.get_placed(index)?;
+        Ok(placement::render(placed.as_ref()))
+    }
+
+    /// 为指定帧设置遮罩层（仅 V2 可写）
+    pub fn set_mask(
+        &mut self,
+        index: usize,
+        mask: &image::RgbaImage,
+        x: i16,
+        y: i16,
+    ) -> Result<()> {
+        tracing::debug!("设置遮罩层: index={}, offset=({}, {})", index, x, y);
+        self.ensure_v2_writable("编辑遮罩")?;
+        self.ensure_unlocked(index)?;
 
         if let Some(ref mut lib) = self.library_v2 {
-            lib.remove_image(index)?;
-            tracing::debug!("删除成功");
+            lib.set_mask(index, mask, x, y)?;
+            self.edits.track(lib.count());
+            self.edits.modified(index);
+            tracing::debug!("设置遮罩成功");
             Ok(())
         } else {
             Err(LibraryError::ParseError(
-                "删除图像时异常：库未加载".to_string(),
+                "设置遮罩时异常：库未加载".to_string(),
             ))
         }
     }
 
-    /// 导出图像为 PNG
-    pub fn export_png(&mut self, index: usize, path: &Path) -> Result<()> {
-        tracing::debug!("导出图像为 PNG: index={}, path={:?}", index, path);
-
-        if let Some(ref mut lib) = self.library_v2 {
-            let preview = lib.get_preview(index)?;
+    /// 获取调色板（仅调色板格式 V0 / 加密 WIL 有）
+    pub fn palette(&self) -> Option<[[u8; 4]; 256]> {
+        if let Some(ref lib) = self.library_v0 {
+            return Some(*lib.get_palette());
+        }
+        self.library_enc.as_ref().map(|lib| *lib.get_palette())
+    }
 
-            if let Some(img) = preview {
-                img.save(path)?;
-                tracing::debug!("导出成功");
-                Ok(())
-            } else {
-                Err(LibraryError::InvalidImageData)
-            }
-        } else {
+    /// 获取帧内像素的调色板索引（仅 8 位调色板格式：V0、加密 WIL 及 WZL 的 8 位帧）
+    ///
+    /// 坐标与 `get_frame` 返回的图像一致；越界、16 位帧或非调色板格式返回 `None`。
+    pub fn palette_index_at(&mut self, index: usize, x: u32, y: u32) -> Result<Option<u8>> {
+        let flip = self.flip_vertical;
+        // 翻转显示时把坐标换回解码图像中的行
+        let row = |height: u32| match flip {
+            true => height.checked_sub(y + 1),
+            false => Some(y),
+        };
+        if let Some(ref mut lib) = self.library_v0 {
+            let image = lib.get_image(index)?;
+            return Ok(row(image.height as u32).and_then(|y| image.palette_index(x, y)));
+        }
+        if let Some(ref mut lib) = self.library_enc {
+            let image = lib.get_image(index)?;
+            return Ok(row(image.height as u32).and_then(|y| image.palette_index(x, y)));
+        }
+        if let Some(ref mut lib) = self.library_v1 {
+            let image = lib.get_image(index)?;
+            let height = image.height.max(0) as u32;
+            return Ok(row(height).and_then(|y| image.palette_index(x, y)));
+        }
+        Ok(None)
+    }
+
+    /// 获取整帧的调色板索引及该帧使用的调色板（仅 8 位调色板格式：V0、加密 WIL 及
+    /// WZL 的 8 位帧）
+    ///
+    /// 行方向与 `get_frame` 返回的图像一致；16 位帧或非调色板格式返回 `None`。
+    pub fn palette_indices(&mut self, index: usize) -> Result<Option<IndexedFrame>> {
+        let flip = self.flip_vertical;
+        let collect = |width: u32,
+                       height: u32,
+                       index_at: &dyn Fn(u32, u32) -> Option<u8>,
+                       palette: [[u8; 4]; 256]| {
+            let mut indices = Vec::with_capacity((width * height) as usize);
+            for y in 0..height {
+                let row = if flip { height - 1 - y } else { y };
+                indices.extend((0..width).map(|x| index_at(x, row).unwrap_or(0)));
+            }
+            IndexedFrame {
+                width,
+                height,
+                indices,
+                palette,
+            }
+        };
+        if let Some(ref mut lib) = self.library_v0 {
+            let palette = *lib.palette_for(index);
+            let image = lib.get_image(index)?;
+            let (width, height) = (image.width as u32, image.height as u32);
+            let index_at = |x, y| image.palette_index(x, y);
+            return Ok(Some(collect(width, height, &index_at, palette)));
+        }
+        if let Some(ref mut lib) = self.library_enc {
+            let palette = *lib.get_palette();
+            let image = lib.get_image(index)?;
+            let (width, height) = (image.width as u32, image.height as u32);
+            let index_at = |x, y| image.palette_index(x, y);
+            return Ok(Some(collect(width, height, &index_at, palette)));
+        }
+        if let Some(ref mut lib) = self.library_v1 {
+            let palette = lib.get_palette().map(|c| [c.b, c.g, c.r, c.a]);
+            let image = lib.get_image(index)?;
+            if image.bo16bit {
+                return Ok(None);
+            }
+            let (width, height) = (image.width.max(0) as u32, image.height.max(0) as u32);
+            let index_at = |x, y| image.palette_index(x, y);
+            return Ok(Some(collect(width, height, &index_at, palette)));
+        }
+        Ok(None)
+    }
+
+    /// 逐帧解码指定范围，并通过通道报告每一帧的结果
+    ///
+    /// 解码结果保留在各后端的缓存中，结束时发送 `Done`。
+    pub fn preload(
+        &mut self,
+        indices: impl IntoIterator<Item = usize>,
+        events: &ProgressSender,
+    ) {
+        for index in indices {
+            let event = match self.decode_frame(index) {
+                Ok(_) => ProgressEvent::FrameLoaded(index),
+                Err(e) => ProgressEvent::Error(index, e),
+            };
+            // 接收端已关闭说明没人关心进度了，继续解码即可
+            let _ = events.send(event);
+        }
+        let _ = events.send(ProgressEvent::Done);
+    }
+
+    /// 获取图像预览（不应用占位策略，已按设置翻转）
+    fn get_raw_preview(&mut self, index: usize) -> Result<Option<Arc<image::RgbaImage>>> {
+        let preview = self.decode_frame(index)?;
+        Ok(match preview {
+            Some(img) if self.flip_vertical => {
+                Some(Arc::new(image::imageops::flip_vertical(&*img)))
+            }
+            other => other,
+        })
+    }
+
+    /// 按翻转设置处理解码结果
+    fn apply_flip(&self, image: Option<image::RgbaImage>) -> Option<image::RgbaImage> {
+        match image {
+            Some(img) if self.flip_vertical => Some(image::imageops::flip_vertical(&img)),
+            other => other,
+        }
+    }
+
+    /// 从各后端取解码后的帧图像（与后端缓存共享），并按内存预算释放最久未访问的帧
+    fn decode_frame(&mut self, index: usize) -> Result<Option<Arc<image::RgbaImage>>> {
+        let frame = self.decode_backend_frame(index)?;
+        if let Some(ref image) = frame {
+            for evicted in self.frame_cache.touch(index, image.as_raw().len()) {
+                self.release_frame(evicted);
+            }
+        }
+        Ok(frame)
+    }
+
+    /// 从各后端取解码后的帧图像
+    fn decode_backend_frame(&mut self, index: usize) -> Result<Option<Arc<image::RgbaImage>>> {
+        // 优先从 V2 获取
+        if let Some(ref mut lib) = self.library_v2 {
+            return lib.get_preview(index);
+        }
+
+        // 从 V1 获取
+        if let Some(ref mut lib) = self.library_v1 {
+            return lib.get_preview(index);
+        }
+
+        // 从 V0 (WeMade) 获取
+        if let Some(ref mut lib) = self.library_v0 {
+            return Ok(lib.get_image(index)?.image.clone());
+        }
+
+        // 从加密 WIL 获取
+        if let Some(ref mut lib) = self.library_enc {
+            let image = lib.get_image(index)?;
+            return Ok(image.image.clone());
+        }
+
+        // 从 Mir3 获取
+        if let Some(ref mut lib) = self.library_mir3 {
+            return lib.get_preview(index);
+        }
+
+        Err(LibraryError::ParseError(
+            "获取图像预览时异常：库未加载".to_string(),
+        ))
+    }
+
+    /// 获取已加载库的格式能力（未加载时全部为 `false`）
+    pub fn capabilities(&self) -> Capabilities {
+        self.info
+            .as_ref()
+            .map(|i| i.capabilities())
+            .unwrap_or_default()
+    }
+
+    /// 获取图像数量
+    pub fn image_count(&self) -> usize {
+        self.info.as_ref().map(|i| i.image_count).unwrap_or(0)
+    }
+
+    /// 保存库（保存后回收站清空）
+    pub fn save(&mut self) -> Result<()> {
+        tracing::debug!("保存库文件");
+        self.ensure_writable("保存")?;
+        self.acquire_edit_lock()?;
+
+        if let Some(ref mut lib) = self.library_v2 {
+            lib.save()?;
+            self.trash.clear();
+        } else if let Some(ref mut lib) = self.library_v1 {
+            lib.save()?;
+        } else {
+            return Err(LibraryError::ParseError(
+                "保存库文件时异常：库未加载".to_string(),
+            ));
+        }
+        self.edits.clear();
+        if let Some(ref info) = self.info {
+            self.locks.save(&info.base_path)?;
+            self.bookmarks.save(&info.base_path)?;
+        }
+        tracing::debug!("保存成功");
+        Ok(())
+    }
+
+    /// 替换图像
+    ///
+    /// WZL (V1) 库按图像的像素重新编码为 16 位帧，遮罩层和阴影不保留。
+    pub fn replace_image(
+        &mut self,
+        index: usize,
+        image: &crate::formats::mlibrary_v2::MImage,
+    ) -> Result<()> {
+        tracing::debug!("替换图像: index={}", index);
+        self.ensure_writable("替换图像")?;
+        self.ensure_unlocked(index)?;
+
+        if let Some(ref mut lib) = self.library_v2 {
+            lib.replace_image(index, image)?;
+            self.edits.track(lib.count());
+        } else if let Some(ref mut lib) = self.library_v1 {
+            lib.replace_image(index, &mlibrary_v1::MImage::transcode(image))?;
+            self.edits.track(lib.count());
+        } else {
+            return Err(LibraryError::ParseError(
+                "替换图像时异常：库未加载".to_string(),
+            ));
+        }
+        self.edits.modified(index);
+        tracing::debug!("替换成功");
+        Ok(())
+    }
+
+    /// 添加图像（WZL 库同样重新编码为 16 位帧）
+    pub fn add_image(&mut self, image: &crate::formats::mlibrary_v2::MImage) -> Result<()> {
+        tracing::debug!("添加新图像");
+        self.ensure_writable("添加图像")?;
+
+        if let Some(ref mut lib) = self.library_v2 {
+            self.edits.track(lib.count());
+            lib.add_image(image);
+        } else if let Some(ref mut lib) = self.library_v1 {
+            self.edits.track(lib.count());
+            lib.add_image(&mlibrary_v1::MImage::transcode(image));
+        } else {
+            return Err(LibraryError::ParseError(
+                "添加图像时异常：库未加载".to_string(),
+            ));
+        }
+        self.sync_count();
+        tracing::debug!("添加成功");
+        Ok(())
+    }
+
+    /// 从 `at` 起依次替换帧，超出末尾的部分追加，返回写入的帧索引
+    ///
+    /// 替换时保留原帧的偏移、阴影和遮罩层；追加的帧偏移为 (0, 0)。`at` 为 `None` 时全部追加。
+    /// 落在锁定帧上的图像跳过不写，像素按 `options` 编码；WZL (V1) 库编码为 16 位帧，
+    /// 有半透明像素时带 alpha 平面，`options` 不起作用。
+    pub fn put_frames(
+        &mut self,
+        images: &[image::RgbaImage],
+        at: Option<usize>,
+        options: mlibrary_v2::EncodeOptions,
+    ) -> Result<Vec<usize>> {
+        tracing::debug!("写入 {} 帧: at={:?}", images.len(), at);
+        self.ensure_writable("写入帧")?;
+        if self.library_v1.is_some() {
+            return self.put_frames_v1(images, at);
+        }
+
+        let Some(ref mut lib) = self.library_v2 else {
+            return Err(LibraryError::ParseError(
+                "写入帧时异常：库未加载".to_string(),
+            ));
+        };
+
+        self.edits.track(lib.count());
+        let mut written = Vec::with_capacity(images.len());
+        for (offset, img) in images.iter().enumerate() {
+            match at.map(|at| at + offset).filter(|&i| i < lib.count()) {
+                Some(index) if self.locks.is_locked(index) => {
+                    tracing::warn!(frame = index, "写入帧时跳过锁定帧 {}", index);
+                }
+                Some(index) => {
+                    let mut frame = lib.get_image(index)?.clone();
+                    frame.set_pixels(img, frame.x, frame.y, options);
+                    lib.replace_image(index, &frame)?;
+                    self.edits.modified(index);
+                    written.push(index);
+                }
+                None => {
+                    lib.add_image(&mlibrary_v2::MImage::from_image_with(img, 0, 0, options));
+                    written.push(lib.count() - 1);
+                }
+            }
+        }
+        self.sync_count();
+        Ok(written)
+    }
+
+    /// [`put_frames`](Self::put_frames) 的 WZL (V1) 版本
+    fn put_frames_v1(
+        &mut self,
+        images: &[image::RgbaImage],
+        at: Option<usize>,
+    ) -> Result<Vec<usize>> {
+        let Some(ref mut lib) = self.library_v1 else {
+            return Err(LibraryError::ParseError(
+                "写入帧时异常：库未加载".to_string(),
+            ));
+        };
+
+        self.edits.track(lib.count());
+        let mut written = Vec::with_capacity(images.len());
+        for (offset, img) in images.iter().enumerate() {
+            match at.map(|at| at + offset).filter(|&i| i < lib.count()) {
+                Some(index) if self.locks.is_locked(index) => {
+                    tracing::warn!(frame = index, "写入帧时跳过锁定帧 {}", index);
+                }
+                Some(index) => {
+                    let (x, y) = {
+                        let frame = lib.get_image(index)?;
+                        (frame.x, frame.y)
+                    };
+                    lib.replace_image(index, &mlibrary_v1::MImage::from_image(img, x, y))?;
+                    self.edits.modified(index);
+                    written.push(index);
+                }
+                None => {
+                    lib.add_image(&mlibrary_v1::MImage::from_image(img, 0, 0));
+                    written.push(lib.count() - 1);
+                }
+            }
+        }
+        self.sync_count();
+        Ok(written)
+    }
+
+    /// 复制一帧的图像、偏移、阴影和遮罩层，空帧或超出帧数时返回 `None`
+    pub fn copy_frame(&mut self, index: usize) -> Result<Option<clip::FrameClip>> {
+        if index >= self.image_count() {
+            return Ok(None);
+        }
+        let Some(image) = self.get_frame(index)? else {
+            return Ok(None);
+        };
+        let info = self.get_image_info(index)?;
+        // 剪贴板中的帧可以修改，这里复制一份像素
+        let mut frame =
+            clip::FrameClip::new(Arc::unwrap_or_clone(image), info.x as i16, info.y as i16);
+        match info.has_mask {
+            ShadowInfo::None => {}
+            ShadowInfo::Simple {
+                shadow,
+                shadow_x,
+                shadow_y,
+            } => {
+                (frame.shadow, frame.shadow_x, frame.shadow_y) = (shadow, shadow_x, shadow_y);
+            }
+            ShadowInfo::Mask {
+                shadow,
+                shadow_x,
+                shadow_y,
+                mask_x,
+                mask_y,
+                ..
+            } => {
+                (frame.shadow, frame.shadow_x, frame.shadow_y) = (shadow, shadow_x, shadow_y);
+                frame.mask = self.get_mask(index)?.map(|image| clip::MaskClip {
+                    image,
+                    x: mask_x,
+                    y: mask_y,
+                });
+            }
+        }
+        Ok(Some(frame))
+    }
+
+    /// 粘贴复制的帧（仅 V2 可写），返回写入的帧索引
+    ///
+    /// `at` 指向已有帧时整帧替换（包括偏移、阴影和遮罩层），超出末尾或为 `None` 时追加。
+    pub fn paste_frame(&mut self, frame: &clip::FrameClip, at: Option<usize>) -> Result<usize> {
+        tracing::debug!("粘贴帧: at={:?}", at);
+        self.ensure_v2_writable("粘贴帧")?;
+        if let Some(index) = at {
+            self.ensure_unlocked(index)?;
+        }
+
+        let Some(ref mut lib) = self.library_v2 else {
+            return Err(LibraryError::ParseError(
+                "粘贴帧时异常：库未加载".to_string(),
+            ));
+        };
+
+        self.edits.track(lib.count());
+        let image = frame.to_mimage();
+        let index = match at.filter(|&index| index < lib.count()) {
+            Some(index) => {
+                lib.replace_image(index, &image)?;
+                self.edits.modified(index);
+                index
+            }
+            None => {
+                lib.add_image(&image);
+                lib.count() - 1
+            }
+        };
+        self.sync_count();
+        Ok(index)
+    }
+
+    /// 删除图像（移入回收站，保存前可以恢复）
+    ///
+    /// 仅追加模式下不移除帧，改为清空成空帧，后面帧的索引保持不变。
+    pub fn remove_image(&mut self, index: usize) -> Result<()> {
+        tracing::debug!("删除图像: index={}", index);
+        self.ensure_v2_writable("删除图像")?;
+        self.ensure_unlocked(index)?;
+        if self.append_only {
+            if index >= self.image_count() {
+                return Err(LibraryError::IndexOutOfBounds(index));
+            }
+            self.clear_frames(&[index])?;
+            tracing::debug!("仅追加模式，第 {} 帧已清空", index);
+            return Ok(());
+        }
+
+        if let Some(ref mut lib) = self.library_v2 {
+            self.edits.track(lib.count());
+            let image = lib.take_image(index)?;
+            self.trash.push(index, image);
+            self.edits.removed(index);
+            self.locks.removed(index);
+            self.bookmarks.removed(index);
+            self.sync_count();
+            tracing::debug!("删除成功，回收站中共 {} 帧", self.trash.len());
+            Ok(())
+        } else {
             Err(LibraryError::ParseError(
-                "导出图像时异常：库未加载".to_string(),
+                "删除图像时异常：库未加载".to_string(),
+            ))
+        }
+    }
+
+    /// 批量删除帧（逐帧移入回收站），返回删除的帧数
+    ///
+    /// 从大到小依次删除，前面帧的索引不受影响；撤销时按相反顺序逐帧恢复。锁定的帧跳过。
+    pub fn remove_images(&mut self, indices: &[usize]) -> Result<usize> {
+        let mut sorted = self.skip_locked(indices, "删除图像");
+        sorted.sort_unstable();
+        sorted.dedup();
+        for &index in sorted.iter().rev() {
+            self.remove_image(index)?;
+        }
+        Ok(sorted.len())
+    }
+
+    /// 删除空帧（逐帧移入回收站，可撤销），返回删除前后的索引对应关系；锁定的帧跳过
+    ///
+    /// `trailing_only` 为 `true` 时只删除末尾连续的空帧，其余帧的索引都不变；
+    /// 否则删除全部空帧，后面的帧依次前移。
+    pub fn remove_empty_frames(&mut self, trailing_only: bool) -> Result<IndexRemap> {
+        self.ensure_v2_writable("删除空帧")?;
+        if self.append_only {
+            return Err(LibraryError::AppendOnly("删除空帧"));
+        }
+        let count = self.image_count();
+        let mut empty = self.find_frames(ImageInfo::is_empty);
+        if trailing_only {
+            let kept = empty
+                .iter()
+                .rev()
+                .zip((0..count).rev())
+                .take_while(|(index, expected)| *index == expected)
+                .count();
+            empty.drain(..empty.len() - kept);
+        }
+
+        let removed = self.skip_locked(&empty, "删除空帧");
+        for &index in removed.iter().rev() {
+            self.remove_image(index)?;
+        }
+        tracing::debug!("删除了 {} 个空帧", removed.len());
+        Ok(IndexRemap::after_removal(count, &removed))
+    }
+
+    /// 将指定帧的偏移整体移动（仅 V2 可写），返回被修改的帧索引；锁定的帧跳过
+    pub fn shift_offsets(&mut self, indices: &[usize], dx: i16, dy: i16) -> Result<Vec<usize>> {
+        tracing::debug!("移动偏移: {} 帧, ({}, {})", indices.len(), dx, dy);
+        self.ensure_v2_writable("移动偏移")?;
+        let indices = self.skip_locked(indices, "移动偏移");
+
+        if let Some(ref mut lib) = self.library_v2 {
+            let shifted = lib.shift_offsets(&indices, dx, dy)?;
+            self.edits.track(lib.count());
+            shifted.iter().for_each(|&index| self.edits.modified(index));
+            Ok(shifted)
+        } else {
+            Err(LibraryError::ParseError(
+                "移动偏移时异常：库未加载".to_string(),
+            ))
+        }
+    }
+
+    /// 一帧的阴影和遮罩属性；没有阴影信息的格式返回默认值
+    pub fn frame_properties(&mut self, index: usize) -> Result<FrameProperties> {
+        Ok(FrameProperties::from(&self.get_image_info(index)?.has_mask))
+    }
+
+    /// 修改一帧的阴影和遮罩属性（仅 V2 可写），返回是否有改动；锁定的帧拒绝
+    ///
+    /// 关闭遮罩会去掉遮罩层，开启时若还没有遮罩层则创建与帧同尺寸的空遮罩层。
+    pub fn set_frame_properties(
+        &mut self,
+        index: usize,
+        properties: FrameProperties,
+    ) -> Result<bool> {
+        tracing::debug!("修改帧属性: #{} {:?}", index, properties);
+        self.ensure_v2_writable("修改帧属性")?;
+        self.ensure_unlocked(index)?;
+
+        let Some(ref mut lib) = self.library_v2 else {
+            return Err(LibraryError::ParseError(
+                "修改帧属性时异常：库未加载".to_string(),
+            ));
+        };
+        let changed = lib.set_properties(index, properties)?;
+        if changed {
+            self.edits.track(lib.count());
+            self.edits.modified(index);
+        }
+        Ok(changed)
+    }
+
+    /// 逐帧应用基线对齐的偏移修正（仅 V2 可写），返回被修改的帧索引；锁定的帧跳过
+    pub fn apply_alignment(&mut self, plan: &AlignPlan) -> Result<Vec<usize>> {
+        let mut shifted = Vec::with_capacity(plan.frames.len());
+        for alignment in &plan.frames {
+            shifted.extend(self.shift_offsets(&[alignment.index], alignment.dx, alignment.dy)?);
+        }
+        Ok(shifted)
+    }
+
+    /// 将指定帧清空为空帧（仅 V2 可写），返回原本非空的帧索引；锁定的帧跳过
+    pub fn clear_frames(&mut self, indices: &[usize]) -> Result<Vec<usize>> {
+        tracing::debug!("清空帧: {} 帧", indices.len());
+        self.ensure_v2_writable("清空帧")?;
+        let indices = self.skip_locked(indices, "清空帧");
+
+        if let Some(ref mut lib) = self.library_v2 {
+            let cleared = lib.clear_frames(&indices)?;
+            self.edits.track(lib.count());
+            cleared.iter().for_each(|&index| self.edits.modified(index));
+            Ok(cleared)
+        } else {
+            Err(LibraryError::ParseError(
+                "清空帧时异常：库未加载".to_string(),
+            ))
+        }
+    }
+
+    /// 重新压缩全部帧并保存（仅 V2 可写）：帧数据按 `level` 重写，`codec` 为 `None` 时
+    /// 保持原压缩方式
+    ///
+    /// 只把数据解压后重新压缩，像素、偏移、阴影和遮罩都不变；锁定的帧同样处理。
+    pub fn recompress(
+        &mut self,
+        level: CompressionLevel,
+        codec: Option<mlibrary_v2::Codec>,
+    ) -> Result<()> {
+        tracing::debug!("重新压缩: 级别 {}，{:?}", level.get(), codec);
+        self.ensure_v2_writable("重新压缩")?;
+        let Some(ref mut lib) = self.library_v2 else {
+            return Err(LibraryError::ParseError(
+                "重新压缩时异常：库未加载".to_string(),
+            ));
+        };
+        lib.level = Some(level);
+        if let Some(codec) = codec {
+            lib.codec = codec;
+        }
+        self.save()
+    }
+
+    /// 去掉指定范围内所有帧的遮罩层（仅 V2 可写），返回被修改的帧索引；锁定的帧跳过
+    pub fn strip_masks(&mut self, range: Range<usize>) -> Result<Vec<usize>> {
+        tracing::debug!("去除遮罩层: {:?}", range);
+        self.ensure_v2_writable("去除遮罩")?;
+        let range = range.start..range.end.min(self.image_count());
+        let indices = self.skip_locked(&range.collect::<Vec<_>>(), "去除遮罩");
+
+        if let Some(ref mut lib) = self.library_v2 {
+            let mut affected = Vec::new();
+            for index in indices {
+                affected.extend(lib.strip_masks(index..index + 1)?);
+            }
+            self.edits.track(lib.count());
+            affected
+                .iter()
+                .for_each(|&index| self.edits.modified(index));
+            tracing::debug!("已去除 {} 帧的遮罩层", affected.len());
+            Ok(affected)
+        } else {
+            Err(LibraryError::ParseError(
+                "去除遮罩时异常：库未加载".to_string(),
             ))
         }
     }
+
+    /// 将指定范围内的帧填充到相同尺寸并统一偏移（仅 V2 可写）
+    ///
+    /// 跳过部分帧会让范围内的帧不再对齐，因此范围内有锁定帧时整体拒绝。
+    pub fn normalize_frames(&mut self, range: Range<usize>) -> Result<Option<NormalizeReport>> {
+        tracing::debug!("统一帧尺寸: {:?}", range);
+        self.ensure_v2_writable("统一尺寸")?;
+        if let Some(index) = self.locks.iter().find(|index| range.contains(index)) {
+            return Err(LibraryError::FrameLocked(index));
+        }
+
+        if let Some(ref mut lib) = self.library_v2 {
+            let report = lib.normalize_frames(range)?;
+            self.edits.track(lib.count());
+            for &index in report.iter().flat_map(|report| &report.frames) {
+                self.edits.modified(index);
+            }
+            tracing::debug!("统一尺寸结果: {:?}", report);
+            Ok(report)
+        } else {
+            Err(LibraryError::ParseError(
+                "统一尺寸时异常：库未加载".to_string(),
+            ))
+        }
+    }
+
+    /// 将指定帧填充到统一的画布尺寸（仅 V2 可写），锁定的帧跳过
+    pub fn pad_frames(
+        &mut self,
+        indices: &[usize],
+        width: u32,
+        height: u32,
+        anchor: Anchor,
+    ) -> Result<PadReport> {
+        tracing::debug!(
+            "填充画布: {} 帧, {}x{}, 锚点 {}",
+            indices.len(),
+            width,
+            height,
+            anchor.name()
+        );
+        self.ensure_v2_writable("填充画布")?;
+        let indices = self.skip_locked(indices, "填充画布");
+
+        if let Some(ref mut lib) = self.library_v2 {
+            let report = lib.pad_frames(&indices, width, height, anchor)?;
+            self.edits.track(lib.count());
+            report
+                .frames
+                .iter()
+                .for_each(|&index| self.edits.modified(index));
+            if !report.oversized.is_empty() {
+                tracing::warn!(
+                    frame = report.oversized.first().copied(),
+                    "{} 帧比画布大，未处理: {:?}",
+                    report.oversized.len(),
+                    report.oversized
+                );
+            }
+            Ok(report)
+        } else {
+            Err(LibraryError::ParseError(
+                "填充画布时异常：库未加载".to_string(),
+            ))
+        }
+    }
+
+    /// 从回收站恢复第 `pos` 条记录，返回恢复后的帧索引
+    pub fn restore_image(&mut self, pos: usize) -> Result<usize> {
+        tracing::debug!("从回收站恢复: pos={}", pos);
+        self.ensure_v2_writable("恢复图像")?;
+
+        let Some(ref mut lib) = self.library_v2 else {
+            return Err(LibraryError::ParseError(
+                "恢复图像时异常：库未加载".to_string(),
+            ));
+        };
+        let entry = self
+            .trash
+            .entries()
+            .get(pos)
+            .ok_or(LibraryError::IndexOutOfBounds(pos))?;
+        if self.append_only && entry.index < lib.count() {
+            // 放回原位会让后面的帧后移，记录留在回收站
+            return Err(LibraryError::AppendOnly("把帧插到已有帧之前"));
+        }
+        let (index, image) = self
+            .trash
+            .take(pos)
+            .ok_or(LibraryError::IndexOutOfBounds(pos))?;
+        let index = index.min(lib.count());
+        self.edits.track(lib.count());
+        lib.insert_image(index, &image)?;
+        self.edits.restored(pos, index);
+        self.locks.inserted(index);
+        self.bookmarks.inserted(index);
+        self.sync_count();
+        tracing::debug!("已恢复到索引 {}", index);
+        Ok(index)
+    }
+
+    /// 撤销最近一次删除，回收站为空时返回 `None`
+    pub fn undo_remove(&mut self) -> Result<Option<usize>> {
+        match self.trash.len() {
+            0 => Ok(None),
+            len => self.restore_image(len - 1).map(Some),
+        }
+    }
+
+    /// 获取回收站
+    pub fn trash(&self) -> &Trash {
+        &self.trash
+    }
+
+    /// 第 `index` 帧在已保存文件中的索引，保存后被修改或新追加的帧返回 `None`
+    pub fn frame_origin(&self, index: usize) -> Option<usize> {
+        if index >= self.image_count() {
+            return None;
+        }
+        self.edits.origin(index)
+    }
+
+    /// 已保存文件中的索引到当前索引的对照表（删除、恢复帧后后续索引随之移动）
+    pub fn index_remap(&self) -> IndexRemap {
+        let count = self.image_count();
+        let saved = self.edits.saved_count().unwrap_or(count);
+        IndexRemap::from_sources(saved, (0..count).map(|index| self.edits.source(index)))
+    }
+
+    /// 是否有未保存的修改（含删除、恢复帧）
+    pub fn is_modified(&self) -> bool {
+        self.edits.is_modified()
+    }
+
+    /// 修改次数，两次取值相同说明期间没有新的修改（用于决定是否重写恢复日志）
+    pub fn revision(&self) -> u64 {
+        self.edits.revision()
+    }
+
+    /// 把未保存的修改整理成恢复日志，没有修改或不是磁盘上的 V2 库时返回 `None`
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn journal(&mut self) -> Result<Option<journal::Journal>> {
+        if !self.edits.is_modified() {
+            return Ok(None);
+        }
+        let (Some(info), Some(lib)) = (self.info.as_ref(), self.library_v2.as_mut()) else {
+            return Ok(None);
+        };
+        if self.lock_base.is_none() {
+            return Ok(None);
+        }
+        let library = info.path();
+        let stamp = journal::FileStamp::of(&library)?;
+
+        let (slots, removed) = self.edits.slot_states(lib.count());
+        let mut frames = Vec::with_capacity(slots.len());
+        for (index, (source, modified)) in slots.into_iter().enumerate() {
+            // 未修改的帧只记来源，恢复时从库文件读取
+            let image = match source {
+                Some(_) if !modified => None,
+                _ => Some(lib.get_image(index)?.clone()),
+            };
+            frames.push(journal::JournalFrame { source, image });
+        }
+        let trash = self
+            .trash
+            .entries()
+            .iter()
+            .zip(removed)
+            .map(|(entry, (source, _))| {
+                let frame = journal::JournalFrame {
+                    source,
+                    image: Some(entry.image.clone()),
+                };
+                (entry.index, frame)
+            })
+            .collect();
+
+        Ok(Some(journal::Journal {
+            library: library.canonicalize().unwrap_or(library),
+            stamp,
+            saved_count: self.edits.saved_count().unwrap_or(frames.len()),
+            frames,
+            trash,
+            locked: self.locks.iter().collect(),
+        }))
+    }
+
+    /// 按恢复日志重做未保存的修改（库需刚打开、没有修改过）
+    ///
+    /// 库文件在写入日志后被改动过时返回 [`LibraryError::StaleJournal`]，库保持不变。
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn apply_journal(&mut self, journal: journal::Journal) -> Result<()> {
+        self.ensure_v2_writable("恢复未保存的修改")?;
+        let (Some(info), Some(lib)) = (self.info.as_ref(), self.library_v2.as_mut()) else {
+            return Err(LibraryError::ParseError(
+                "恢复未保存的修改时异常：库未加载".to_string(),
+            ));
+        };
+        let library = info.path();
+        if self.edits.is_modified()
+            || lib.count() != journal.saved_count
+            || journal::FileStamp::of(&library)? != journal.stamp
+        {
+            return Err(LibraryError::StaleJournal(library.display().to_string()));
+        }
+
+        // 先从库文件读出未修改的帧，全部成功后再替换
+        let mut images = Vec::with_capacity(journal.frames.len());
+        for frame in &journal.frames {
+            let image = match (&frame.image, frame.source) {
+                (Some(image), _) => image.clone(),
+                (None, Some(source)) if source < lib.count() => lib.get_image(source)?.clone(),
+                _ => return Err(LibraryError::StaleJournal(library.display().to_string())),
+            };
+            images.push(image);
+        }
+        lib.set_images(images);
+
+        let state = |frame: &journal::JournalFrame| {
+            let modified = frame.source.is_some() && frame.image.is_some();
+            (frame.source, modified)
+        };
+        let slots: Vec<_> = journal.frames.iter().map(state).collect();
+        let removed: Vec<_> = journal
+            .trash
+            .iter()
+            .map(|(_, frame)| state(frame))
+            .collect();
+        self.edits.restore(journal.saved_count, &slots, &removed);
+        self.trash.clear();
+        for (index, frame) in journal.trash {
+            self.trash.push(index, frame.image.unwrap_or_default());
+        }
+        self.locks = FrameLocks::default();
+        for index in journal.locked {
+            self.locks.set(index, true);
+        }
+        self.frame_cache.clear();
+        self.sync_count();
+        tracing::info!(
+            "已按恢复日志恢复 {} 帧修改（回收站 {} 帧）",
+            journal.frames.iter().filter(|f| f.image.is_some()).count(),
+            self.trash.len()
+        );
+        Ok(())
+    }
+
+    /// 保存后是否修改过第 `index` 帧（含新追加和粘贴的帧）
+    pub fn is_edited(&self, index: usize) -> bool {
+        index < self.image_count() && self.edits.origin(index).is_none()
+    }
+
+    /// 第 `index` 帧是否锁定
+    pub fn is_locked(&self, index: usize) -> bool {
+        self.locks.is_locked(index)
+    }
+
+    /// 获取锁定记录
+    pub fn locks(&self) -> &FrameLocks {
+        &self.locks
+    }
+
+    /// 锁定或解锁指定帧，返回状态改变的帧数
+    ///
+    /// 没有未保存的修改时立即写出锁定记录；否则帧索引可能已经移动，等保存库时一并写出。
+    pub fn set_locked(&mut self, indices: &[usize], locked: bool) -> Result<usize> {
+        let count = self.image_count();
+        let changed = indices
+            .iter()
+            .filter(|&&index| index < count && self.locks.set(index, locked))
+            .count();
+        tracing::debug!("{}帧: {} 帧", if locked { "锁定" } else { "解锁" }, changed);
+
+        if changed > 0
+            && !self.edits.is_modified()
+            && let Some(ref info) = self.info
+        {
+            self.locks.save(&info.base_path)?;
+        }
+        Ok(changed)
+    }
+
+    /// 获取书签
+    pub fn bookmarks(&self) -> &Bookmarks {
+        &self.bookmarks
+    }
+
+    /// 给指定帧添加书签或修改书签的说明，返回是否有改动
+    ///
+    /// 与锁定记录相同，没有未保存的修改时立即写出书签文件，否则等保存库时一并写出。
+    pub fn set_bookmark(&mut self, index: usize, label: &str) -> Result<bool> {
+        if index >= self.image_count() {
+            return Err(LibraryError::IndexOutOfBounds(index));
+        }
+        let changed = self.bookmarks.set(index, label);
+        if changed {
+            tracing::debug!("书签: 第 {} 帧 {:?}", index, label);
+            self.save_bookmarks()?;
+        }
+        Ok(changed)
+    }
+
+    /// 删除指定帧的书签，返回是否有书签
+    pub fn remove_bookmark(&mut self, index: usize) -> Result<bool> {
+        let removed = self.bookmarks.remove(index);
+        if removed {
+            tracing::debug!("删除书签: 第 {} 帧", index);
+            self.save_bookmarks()?;
+        }
+        Ok(removed)
+    }
+
+    /// 加入另一处（如项目文件）记录的书签，已有书签的帧保持不变，返回加入的个数
+    pub fn merge_bookmarks(&mut self, other: &Bookmarks) -> Result<usize> {
+        let count = self.image_count();
+        let other = Bookmarks::from_iter(other.iter().filter(|b| b.index < count).cloned());
+        let added = self.bookmarks.merge(&other);
+        if added > 0 {
+            self.save_bookmarks()?;
+        }
+        Ok(added)
+    }
+
+    /// 没有未保存的修改时写出书签文件
+    fn save_bookmarks(&self) -> Result<()> {
+        if !self.edits.is_modified()
+            && let Some(ref base) = self.lock_base
+        {
+            self.bookmarks.save(base)?;
+        }
+        Ok(())
+    }
+
+    /// 检查帧是否锁定
+    fn ensure_unlocked(&self, index: usize) -> Result<()> {
+        if self.locks.is_locked(index) {
+            return Err(LibraryError::FrameLocked(index));
+        }
+        Ok(())
+    }
+
+    /// 去掉锁定的帧，跳过时记录警告
+    fn skip_locked(&self, indices: &[usize], action: &str) -> Vec<usize> {
+        let (open, locked) = self.locks.split(indices);
+        if !locked.is_empty() {
+            tracing::warn!(
+                frame = locked.first().copied(),
+                "{}时跳过 {} 个锁定帧: {:?}",
+                action,
+                locked.len(),
+                locked
+            );
+        }
+        open
+    }
+
+    /// 帧增删后同步库信息中的图像数量
+    fn sync_count(&mut self) {
+        let count = match (&self.library_v2, &self.library_v1) {
+            (Some(lib), _) => Some(lib.count()),
+            (None, Some(lib)) => Some(lib.count()),
+            (None, None) => None,
+        };
+        if let (Some(info), Some(count)) = (self.info.as_mut(), count) {
+            info.image_count = count;
+        }
+    }
+
+    /// 检查已加载的格式是否可写、是否以读写方式打开
+    fn ensure_writable(&self, action: &'static str) -> Result<()> {
+        if self.mode == OpenMode::ReadOnly {
+            return Err(LibraryError::ReadOnly(action));
+        }
+        match self.info {
+            Some(ref info) if !info.capabilities().writable => {
+                Err(LibraryError::Unsupported(info.format_name(), action))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// 检查可写，且已加载的是 V2 库：WZL (V1) 只支持保存、替换和添加帧
+    fn ensure_v2_writable(&self, action: &'static str) -> Result<()> {
+        self.ensure_writable(action)?;
+        match self.info {
+            Some(ref info) if self.library_v2.is_none() && self.library_v1.is_some() => {
+                Err(LibraryError::Unsupported(info.format_name(), action))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// 批量导出指定帧为 `<索引>.png`，返回写入的文件数
+    ///
+    /// 空帧按当前占位策略导出；策略为 `Empty` 时跳过空帧。`indexed` 为真时 8 位
+    /// 调色板帧写成索引色 PNG，其余帧照常写 RGBA。
+    pub fn export_pngs(
+        &mut self,
+        indices: &[usize],
+        out_dir: &Path,
+        indexed: bool,
+    ) -> Result<usize> {
+        std::fs::create_dir_all(out_dir)?;
+
+        let mut written = 0;
+        for &index in indices {
+            let path = out_dir.join(format!("{:05}.png", index));
+            if indexed && self.export_indexed_png(index, &path)? {
+                written += 1;
+            } else if let Some(img) = self.get_preview(index)? {
+                img.save(path)?;
+                written += 1;
+            }
+        }
+        tracing::debug!("批量导出 {} 帧到 {:?}", written, out_dir);
+        Ok(written)
+    }
+
+    /// 8 位调色板帧写成索引色 PNG（保留原调色板），返回是否写出
+    ///
+    /// 16 位帧、空帧和非调色板格式不写文件，返回 `false`。
+    pub fn export_indexed_png(&mut self, index: usize, path: &Path) -> Result<bool> {
+        match self.palette_indices(index)? {
+            Some(frame) if !frame.indices.is_empty() => {
+                indexed::write_indexed_png(path, &frame)?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// 导出图像为 PNG
+    ///
+    /// 空帧按加载器的占位策略导出（默认与 GUI 相同，见 [`PlaceholderPolicy::default`]）；
+    /// 策略为 `Empty` 时返回错误。
+    pub fn export_png(&mut self, index: usize, path: &Path) -> Result<()> {
+        self.export_image(index, path, ExportFormat::Png, &ExportOptions::default())
+    }
+
+    /// 按指定格式导出图像
+    ///
+    /// 空帧的处理与 [`export_png`](Self::export_png) 一致。8 位调色板 BMP 和索引色 PNG
+    /// 直接写出帧的调色板索引和库的调色板，只支持 8 位调色板帧（V0、加密 WIL 及 WZL
+    /// 的 8 位帧）。
+    pub fn export_image(
+        &mut self,
+        index: usize,
+        path: &Path,
+        format: ExportFormat,
+        options: &ExportOptions,
+    ) -> Result<()> {
+        tracing::debug!(
+            "导出图像: index={}, path={:?}, format={}",
+            index,
+            path,
+            format.name()
+        );
+
+        if self.library_v0.is_none()
+            && self.library_v1.is_none()
+            && self.library_v2.is_none()
+            && self.library_enc.is_none()
+            && self.library_mir3.is_none()
+        {
+            return Err(LibraryError::ParseError(
+                "导出图像时异常：库未加载".to_string(),
+            ));
+        }
+
+        if matches!(format, ExportFormat::Bmp8 | ExportFormat::Png8) {
+            let format_name = self.info.as_ref().map(|info| info.format_name());
+            let frame = match self.palette_indices(index)? {
+                Some(frame) => frame,
+                None => {
+                    return Err(LibraryError::Unsupported(
+                        format_name.unwrap_or_default(),
+                        match format {
+                            ExportFormat::Png8 => "导出索引色 PNG",
+                            _ => "导出 8 位调色板 BMP",
+                        },
+                    ));
+                }
+            };
+            match format {
+                ExportFormat::Png8 => indexed::write_indexed_png(path, &frame)?,
+                _ => export::write_indexed_bmp(path, &frame)?,
+            }
+            tracing::debug!("导出成功");
+            return Ok(());
+        }
+
+        match self.get_preview(index)? {
+            Some(img) => {
+                export::write_image(&img, path, format, options)?;
+                tracing::debug!("导出成功");
+                Ok(())
+            }
+            None => Err(LibraryError::InvalidImageData),
+        }
+    }
 }
 
 impl Default for LibraryLoader {
@@ -491,3 +2323,468 @@ impl Default for LibraryLoader {
         Self::new()
     }
 }
+
+/// 逐帧解码的迭代器，见 [`LibraryLoader::iter_frames`]
+pub struct Frames<'a> {
+    loader: &'a mut LibraryLoader,
+    /// 下一个要解码的帧索引
+    next: usize,
+}
+
+impl Iterator for Frames<'_> {
+    type Item = Result<(usize, ImageInfo, Arc<image::RgbaImage>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.next < self.loader.image_count() {
+            let index = self.next;
+            self.next += 1;
+            let frame = match self.loader.get_frame(index) {
+                Ok(Some(frame)) => frame,
+                Ok(None) => continue,
+                Err(e) => return Some(Err(e)),
+            };
+            return Some(
+                self.loader
+                    .get_image_info(index)
+                    .map(|info| (index, info, frame)),
+            );
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.loader.image_count().saturating_sub(self.next)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_companion_path_keeps_dotted_base() {
+        let base = Path::new("data").join("Hum.v2");
+        assert_eq!(companion_path(&base, ".wzx"), Path::new("data").join("Hum.v2.wzx"));
+    }
+
+    #[test]
+    fn test_put_frames_replaces_then_appends() {
+        let mut library = MLibraryV2::new(PathBuf::from("put_frames_missing")).unwrap();
+        let old = image::RgbaImage::from_pixel(2, 2, image::Rgba([1, 1, 1, 255]));
+        library.add_image(&mlibrary_v2::MImage::from_image(&old, 5, -3));
+        library.add_image(&mlibrary_v2::MImage::from_image(&old, 0, 0));
+        let (_, mut loader) = LibraryLoader::from_v2(library, "put.Lib");
+
+        let new = image::RgbaImage::from_pixel(4, 3, image::Rgba([9, 9, 9, 255]));
+        let written = loader
+            .put_frames(
+                &[new.clone(), new.clone(), new],
+                Some(0),
+                Default::default(),
+            )
+            .unwrap();
+        assert_eq!(written, vec![0, 1, 2]);
+        assert_eq!(loader.image_count(), 3);
+
+        // 替换的帧沿用原偏移
+        let info = loader.get_image_info(0).unwrap();
+        assert_eq!((info.width, info.height, info.x, info.y), (4, 3, 5, -3));
+        assert_eq!(
+            loader.put_frames(&[old], None, Default::default()).unwrap(),
+            vec![3]
+        );
+    }
+
+    #[test]
+    fn test_put_frames_encodes_wzl() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let base = dir.join("Effect");
+        let mut library = mlibrary_v1::MLibraryV1::empty(base.clone());
+        let old = image::RgbaImage::from_pixel(2, 2, image::Rgba([0, 0, 255, 255]));
+        library.add_image(&mlibrary_v1::MImage::from_image(&old, 5, -3));
+        library.save().unwrap();
+
+        let (_, mut loader) = LibraryLoader::load(&find_companion(&base, ".wzl")).unwrap();
+        loader.set_open_mode(OpenMode::ReadWrite).unwrap();
+        let red = image::RgbaImage::from_pixel(3, 2, image::Rgba([255, 0, 0, 255]));
+        let glow = image::RgbaImage::from_pixel(2, 2, image::Rgba([255, 255, 255, 128]));
+        let written = loader
+            .put_frames(&[red.clone(), glow], Some(0), Default::default())
+            .unwrap();
+        assert_eq!(written, vec![0, 1]);
+        assert_eq!(loader.image_count(), 2);
+        assert!(loader.remove_image(0).is_err());
+        loader.save().unwrap();
+        assert!(!loader.is_modified());
+
+        let (info, mut reopened) = LibraryLoader::load(&find_companion(&base, ".wzl")).unwrap();
+        assert_eq!(info.image_count, 2);
+        let frame = reopened.get_image_info(0).unwrap();
+        assert_eq!((frame.x, frame.y), (5, -3));
+        assert_eq!(*reopened.get_frame(0).unwrap().unwrap(), red);
+        let glow = reopened.get_frame(1).unwrap().unwrap();
+        assert_eq!(glow.get_pixel(0, 0).0, [255, 255, 255, 136]);
+        drop(reopened);
+        drop(loader);
+    }
+
+    #[test]
+    fn test_recompress() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let frame =
+            image::RgbaImage::from_fn(32, 16, |x, y| image::Rgba([x as u8, y as u8, 3, 255]));
+        let mut library = MLibraryV2::new(dir.join("Hum")).unwrap();
+        library.level = CompressionLevel::new(0);
+        library.add_image(&mlibrary_v2::MImage::from_image(&frame, 7, -2));
+        library.save().unwrap();
+        let path = dir.join("Hum.Lib");
+        let before = std::fs::metadata(&path).unwrap().len();
+
+        let (_, mut loader) = LibraryLoader::load(&path).unwrap();
+        loader
+            .recompress(CompressionLevel::BEST, Some(mlibrary_v2::Codec::Zstd))
+            .unwrap();
+        drop(loader);
+        assert!(std::fs::metadata(&path).unwrap().len() < before);
+
+        let mut reloaded = MLibraryV2::new(dir.join("Hum")).unwrap();
+        assert_eq!(reloaded.codec, mlibrary_v2::Codec::Zstd);
+        let image = reloaded.get_image(0).unwrap();
+        assert_eq!((image.x, image.y), (7, -2));
+        assert_eq!(image.image.as_deref().unwrap(), &frame);
+    }
+
+    #[test]
+    fn test_export_indexed_png_from_wzl() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let base = dir.join("Weapon");
+        let mut library = mlibrary_v1::MLibraryV1::empty(base.clone());
+        let mut indexed = mlibrary_v1::MImage::new();
+        (indexed.width, indexed.height) = (3, 2);
+        indexed.fbytes = vec![1, 2, 0, 0, 4, 5, 6, 0];
+        library.add_image(&indexed);
+        let mut rgb = mlibrary_v1::MImage::new();
+        (rgb.width, rgb.height, rgb.bo16bit) = (2, 2, true);
+        rgb.fbytes = vec![0xFF; 8];
+        library.add_image(&rgb);
+        library.save().unwrap();
+
+        let (_, mut loader) = LibraryLoader::load(&find_companion(&base, ".wzl")).unwrap();
+        let path = dir.join("frame.png");
+        let options = ExportOptions::default();
+        loader
+            .export_image(0, &path, ExportFormat::Png8, &options)
+            .unwrap();
+        let decoded = image::open(&path).unwrap().to_rgba8();
+        assert_eq!(&decoded, &*loader.get_frame(0).unwrap().unwrap());
+        let frame = loader.palette_indices(0).unwrap().unwrap();
+        assert_eq!(frame.indices, vec![4, 5, 6, 1, 2, 0]);
+
+        // 16 位帧没有调色板索引，批量导出时照常写 RGBA
+        assert!(
+            loader
+                .export_image(1, &path, ExportFormat::Png8, &options)
+                .is_err()
+        );
+        let out = dir.join("frames");
+        assert_eq!(loader.export_pngs(&[0, 1], &out, true).unwrap(), 2);
+        let color_type = |name: &str| {
+            let file = std::io::BufReader::new(std::fs::File::open(out.join(name)).unwrap());
+            let reader = png::Decoder::new(file).read_info().unwrap();
+            reader.info().color_type
+        };
+        assert_eq!(color_type("00000.png"), png::ColorType::Indexed);
+        assert_eq!(color_type("00001.png"), png::ColorType::Rgba);
+    }
+
+    #[test]
+    fn test_remove_empty_frames() {
+        let build = || {
+            let mut library = MLibraryV2::new(PathBuf::from("empty_frames_missing")).unwrap();
+            let pixel = image::RgbaImage::from_pixel(2, 2, image::Rgba([1, 1, 1, 255]));
+            for empty in [false, true, false, true, true] {
+                library.add_image(&if empty {
+                    mlibrary_v2::MImage::new()
+                } else {
+                    mlibrary_v2::MImage::from_image(&pixel, 0, 0)
+                });
+            }
+            LibraryLoader::from_v2(library, "empty.Lib").1
+        };
+
+        let mut loader = build();
+        let remap = loader.remove_empty_frames(true).unwrap();
+        assert_eq!(remap.removed(), vec![3, 4]);
+        assert!(remap.shifted().is_empty());
+        assert_eq!(loader.image_count(), 3);
+
+        let mut loader = build();
+        let remap = loader.remove_empty_frames(false).unwrap();
+        assert_eq!(remap.removed(), vec![1, 3, 4]);
+        assert_eq!(remap.new_index(2), Some(1));
+        assert_eq!(loader.image_count(), 2);
+        assert_eq!(loader.undo_remove().unwrap(), Some(1));
+        assert_eq!(loader.index_remap().removed(), vec![3, 4]);
+    }
+
+    #[test]
+    fn test_append_only_keeps_indices() {
+        let mut library = MLibraryV2::new(PathBuf::from("append_only_missing")).unwrap();
+        let pixel = image::RgbaImage::from_pixel(2, 2, image::Rgba([1, 1, 1, 255]));
+        for _ in 0..3 {
+            library.add_image(&mlibrary_v2::MImage::from_image(&pixel, 0, 0));
+        }
+        let (_, mut loader) = LibraryLoader::from_v2(library, "append.Lib");
+
+        // 删除前已在回收站中的帧不能再插回已有帧之前
+        loader.remove_image(0).unwrap();
+        loader.set_append_only(true);
+        assert!(matches!(
+            loader.undo_remove(),
+            Err(LibraryError::AppendOnly(_))
+        ));
+        assert_eq!(loader.trash().len(), 1);
+
+        // 删除改为清空，帧数和后面帧的索引不变
+        let shifted = loader.index_remap().shifted();
+        loader.remove_images(&[0]).unwrap();
+        assert_eq!(loader.image_count(), 2);
+        assert!(loader.get_image_info(0).unwrap().is_empty());
+        assert!(!loader.get_image_info(1).unwrap().is_empty());
+        assert_eq!(loader.index_remap().shifted(), shifted);
+        assert!(loader.remove_empty_frames(true).is_err());
+
+        loader.add_image(&mlibrary_v2::MImage::new()).unwrap();
+        assert_eq!(loader.image_count(), 3);
+    }
+
+    #[test]
+    fn test_set_frame_properties() {
+        let mut library = MLibraryV2::new(PathBuf::from("frame_properties_missing")).unwrap();
+        let pixel = image::RgbaImage::from_pixel(3, 2, image::Rgba([1, 1, 1, 255]));
+        for _ in 0..2 {
+            library.add_image(&mlibrary_v2::MImage::from_image(&pixel, 0, 0));
+        }
+        let (_, mut loader) = LibraryLoader::from_v2(library, "props.Lib");
+
+        // 阴影值只取低 7 位；开启遮罩时创建与帧同尺寸的空遮罩层
+        let properties = FrameProperties {
+            shadow: 0x85,
+            shadow_x: 4,
+            shadow_y: -2,
+            has_mask: true,
+            mask_x: 1,
+            mask_y: 7,
+        };
+        assert!(loader.set_frame_properties(0, properties).unwrap());
+        let read = loader.frame_properties(0).unwrap();
+        assert_eq!(
+            read,
+            FrameProperties {
+                shadow: 5,
+                ..properties
+            }
+        );
+        assert_eq!(loader.get_mask(0).unwrap().unwrap().dimensions(), (3, 2));
+        assert!(loader.is_edited(0) && !loader.is_edited(1));
+        assert!(!loader.set_frame_properties(0, read).unwrap());
+
+        // 关闭遮罩时去掉遮罩层
+        let plain = FrameProperties {
+            has_mask: false,
+            ..read
+        };
+        assert!(loader.set_frame_properties(0, plain).unwrap());
+        assert_eq!(
+            loader.frame_properties(0).unwrap(),
+            FrameProperties {
+                mask_x: 0,
+                mask_y: 0,
+                ..plain
+            }
+        );
+        assert!(loader.get_mask(0).unwrap().is_none());
+
+        loader.set_locked(&[1], true).unwrap();
+        assert!(matches!(
+            loader.set_frame_properties(1, plain),
+            Err(LibraryError::FrameLocked(1))
+        ));
+    }
+
+    #[test]
+    fn test_capabilities_match_backends() {
+        assert!(LibraryType::MLV2.capabilities().writable);
+        assert!(LibraryType::MLV1.capabilities().writable);
+        assert!(!LibraryType::MLV1.capabilities().editable_properties);
+        assert!(LibraryType::WeMade.capabilities().paletted);
+        assert!(!LibraryType::Mir3.capabilities().supports_mask);
+        assert_eq!(LibraryLoader::new().capabilities(), Capabilities::default());
+    }
+
+    #[test]
+    fn test_from_source_loads_wemade_from_memory() {
+        let temp = tempfile::tempdir().unwrap();
+        let base = temp.path().join("from_source");
+        let mut library = MLibraryV0::new(base.clone()).unwrap();
+        let mut palette = [[0u8; 4]; 256];
+        palette[7] = [30, 60, 90, 255];
+        library.set_palette(palette);
+        let mut frame = mlibrary_v0::MImage::new();
+        frame.width = 2;
+        frame.height = 1;
+        frame.fbytes = vec![7, 7, 0, 0];
+        library.add_image(&frame);
+        library.save().unwrap();
+
+        let read = |ext| std::fs::read(companion_path(&base, ext)).unwrap();
+        let source = Source::memory([(".wil", read(".wil")), (".wix", read(".wix"))]);
+
+        let (info, mut loader) = LibraryLoader::from_source("Mon1.wil", source).unwrap();
+        assert_eq!(info.library_type, LibraryType::WeMade);
+        assert_eq!(info.image_count, 1);
+        let frame = loader.get_frame(0).unwrap().unwrap();
+        assert_eq!(frame.get_pixel(1, 0).0, [90, 60, 30, 255]);
+    }
+
+    #[test]
+    fn test_load_with_progress_reports_and_cancels() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let mut library = MLibraryV2::new(dir.join("Hum")).unwrap();
+        let frame = image::RgbaImage::from_pixel(2, 2, image::Rgba([9, 9, 9, 255]));
+        for _ in 0..3 {
+            library.add_image(&mlibrary_v2::MImage::from_image(&frame, 0, 0));
+        }
+        library.save().unwrap();
+        let path = dir.join("Hum.Lib");
+
+        let mut events = Vec::new();
+        let (info, mut loader) =
+            LibraryLoader::load_with_progress(&path, |p| events.push(p)).unwrap();
+        assert_eq!(info.image_count, 3);
+        // 只读取索引，帧在访问时才解码
+        assert_eq!(events, [Progress::IndexRead { frames: 3 }]);
+        assert_eq!(*loader.get_frame(2).unwrap().unwrap(), frame);
+
+        // 已取消时不再读取
+        let cancel = CancelToken::new();
+        cancel.cancel();
+        let result = LibraryLoader::load_cancellable(&path, &cancel, |_| {});
+        assert!(matches!(result, Err(LibraryError::Cancelled)));
+
+        let handle = LibraryLoader::spawn_load(&path, |_| {});
+        assert_eq!(handle.join().unwrap().0.image_count, 3);
+    }
+
+    #[test]
+    fn test_iter_frames_skips_empty_frames() {
+        let mut library = MLibraryV2::new(PathBuf::from("iter_frames_missing")).unwrap();
+        let red = image::RgbaImage::from_pixel(3, 2, image::Rgba([200, 0, 0, 255]));
+        let blue = image::RgbaImage::from_pixel(1, 4, image::Rgba([0, 0, 200, 255]));
+        library.add_image(&mlibrary_v2::MImage::from_image(&red, 1, 2));
+        library.add_image(&mlibrary_v2::MImage::new());
+        library.add_image(&mlibrary_v2::MImage::from_image(&blue, -3, 0));
+        let (_, mut loader) = LibraryLoader::from_v2(library, "Hum.Lib");
+
+        let frames: Vec<_> = loader.iter_frames().collect::<Result<_>>().unwrap();
+        assert_eq!(frames.len(), 2);
+        let (index, info, image) = &frames[0];
+        assert_eq!((*index, info.x, info.y), (0, 1, 2));
+        assert_eq!(**image, red);
+        let (index, info, image) = &frames[1];
+        assert_eq!((*index, info.width, info.height), (2, 1, 4));
+        assert_eq!(**image, blue);
+
+        // 取帧与解码缓存共享像素，不复制
+        let first = loader.get_frame(0).unwrap().unwrap();
+        assert!(Arc::ptr_eq(&first, &frames[0].2));
+    }
+
+    #[test]
+    fn test_thumbnail_downscales_large_frames() {
+        let mut library = MLibraryV2::new(PathBuf::from("thumbnail_missing")).unwrap();
+        let wide = image::RgbaImage::from_pixel(400, 100, image::Rgba([0, 200, 0, 255]));
+        let small = image::RgbaImage::from_pixel(20, 30, image::Rgba([0, 0, 200, 255]));
+        library.add_image(&mlibrary_v2::MImage::from_image(&wide, 0, 0));
+        library.add_image(&mlibrary_v2::MImage::from_image(&small, 0, 0));
+        let (_, mut loader) = LibraryLoader::from_v2(library, "Hum.Lib");
+
+        let thumbnail = loader.get_thumbnail(0, 80).unwrap().unwrap();
+        assert_eq!(thumbnail.dimensions(), (80, 20));
+        assert_eq!(thumbnail.get_pixel(40, 10).0, [0, 200, 0, 255]);
+        // 小图不放大，直接共享解码结果
+        let thumbnail = loader.get_thumbnail(1, 80).unwrap().unwrap();
+        let frame = loader.get_frame(1).unwrap().unwrap();
+        assert!(Arc::ptr_eq(&thumbnail, &frame));
+    }
+
+    #[test]
+    fn test_cache_policy_releases_least_recent_frames() {
+        let mut library = MLibraryV2::new(PathBuf::from("cache_policy_missing")).unwrap();
+        for shade in [10u8, 20, 30] {
+            let frame = image::RgbaImage::from_pixel(4, 4, image::Rgba([shade, 0, 0, 255]));
+            library.add_image(&mlibrary_v2::MImage::from_image(&frame, 0, 0));
+        }
+        let (_, mut loader) = LibraryLoader::from_v2(library, "Hum.Lib");
+        loader.set_cache_policy(CachePolicy {
+            max_frames: 2,
+            ..CachePolicy::UNLIMITED
+        });
+
+        let first = loader.get_frame(0).unwrap().unwrap();
+        assert!(Arc::ptr_eq(&first, &loader.get_frame(0).unwrap().unwrap()));
+        loader.get_frame(1).unwrap();
+        loader.get_frame(2).unwrap();
+        // 第 0 帧已被释放，重新解码得到新的图像
+        let again = loader.get_frame(0).unwrap().unwrap();
+        assert!(!Arc::ptr_eq(&first, &again));
+        assert_eq!(*first, *again);
+    }
+
+    #[test]
+    fn test_companions_resolve_case_insensitively() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let mut library = MLibraryV2::new(dir.join("Hum")).unwrap();
+        let frame = image::RgbaImage::from_pixel(2, 2, image::Rgba([5, 5, 5, 255]));
+        library.add_image(&mlibrary_v2::MImage::from_image(&frame, 0, 0));
+        library.save().unwrap();
+        std::fs::rename(dir.join("Hum.Lib"), dir.join("HUM.LIB")).unwrap();
+
+        assert_eq!(
+            find_companion(&dir.join("Hum"), ".lib"),
+            dir.join("HUM.LIB")
+        );
+        assert_eq!(
+            find_companion(&dir.join("Hum"), ".wzx"),
+            dir.join("Hum.wzx")
+        );
+        let (info, _) = LibraryLoader::load(&dir.join("hum.lib")).unwrap();
+        assert_eq!(info.image_count, 1);
+
+        // 保存时写回已有的文件，而不是新建一个只有大小写不同的文件
+        let mut library = MLibraryV2::new(dir.join("hum")).unwrap();
+        library.add_image(&mlibrary_v2::MImage::from_image(&frame, 1, 1));
+        library.save().unwrap();
+        let names: Vec<_> = std::fs::read_dir(dir)
+            .unwrap()
+            .flatten()
+            .map(|e| e.file_name())
+            .collect();
+        assert_eq!(names, vec![std::ffi::OsString::from("HUM.LIB")]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_companion_path_non_utf8() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let base = Path::new(std::ffi::OsStr::from_bytes(b"\xff\xfeHum"));
+        let path = companion_path(base, ".wzl");
+        assert_eq!(path.as_os_str().as_bytes(), b"\xff\xfeHum.wzl");
+    }
+}
@@ -0,0 +1,165 @@
+//! 手动打开选项（打开向导）
+//!
+//! 自动识别失败时，由用户手动指定解析器、索引起始偏移和调色板来源。
+//! 选择结果按文件路径记录在 `config/open_choices.json` 中，下次打开同一文件时直接复用。
+
+use crate::error::{LibraryError, Result};
+use crate::formats::LibraryType;
+use crate::image::DEFAULT_PALETTE;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+/// 已记住的打开选项文件
+pub const OPEN_CHOICES_FILE: &str = "./config/open_choices.json";
+
+/// 调色板在 WIL 数据文件中的偏移量
+const PALETTE_OFFSET: u64 = 56;
+/// 调色板大小（256色 * 4字节）
+const PALETTE_SIZE: usize = 1024;
+
+/// 调色板来源
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum PaletteSource {
+    /// 使用数据文件内置的调色板
+    #[default]
+    Embedded,
+    /// 使用程序内置的默认调色板
+    Default,
+    /// 从其他文件读取：1024 字节的裸 BGRA 调色板，或另一个 .wil 文件中的调色板
+    External(PathBuf),
+}
+
+impl PaletteSource {
+    /// 解析为 BGRA 调色板，`Embedded` 返回 `None`（保持文件内置调色板）
+    pub fn resolve(&self) -> Result<Option<[[u8; 4]; 256]>> {
+        match self {
+            PaletteSource::Embedded => Ok(None),
+            PaletteSource::Default => {
+                let mut palette = [[0u8; 4]; 256];
+                for (dst, c) in palette.iter_mut().zip(DEFAULT_PALETTE.iter()) {
+                    *dst = [c.b, c.g, c.r, c.a];
+                }
+                Ok(Some(palette))
+            }
+            PaletteSource::External(path) => {
+                if !path.exists() {
+                    return Err(LibraryError::FileNotFound(path.display().to_string()));
+                }
+
+                let mut file = std::fs::File::open(path)?;
+                // 恰好 1024 字节视为裸调色板，否则按 WIL 布局读取
+                if file.metadata()?.len() != PALETTE_SIZE as u64 {
+                    file.seek(SeekFrom::Start(PALETTE_OFFSET))?;
+                }
+                let mut data = [0u8; PALETTE_SIZE];
+                file.read_exact(&mut data)?;
+
+                let mut palette = [[0u8; 4]; 256];
+                for (dst, chunk) in palette.iter_mut().zip(data.chunks_exact(4)) {
+                    dst.copy_from_slice(chunk);
+                }
+                Ok(Some(palette))
+            }
+        }
+    }
+}
+
+/// 手动打开选项
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OpenOptions {
+    /// 使用的解析器
+    pub library_type: LibraryType,
+    /// 索引数组在索引文件中的起始偏移（仅 WeMade/V0 生效，`None` 为自动检测）
+    #[serde(default)]
+    pub index_offset: Option<u64>,
+    /// 调色板来源（仅调色板格式生效）
+    #[serde(default)]
+    pub palette: PaletteSource,
+    /// 解码后上下翻转（方向检测提示后由用户确认）
+    #[serde(default)]
+    pub flip_vertical: bool,
+}
+
+impl OpenOptions {
+    /// 创建仅指定解析器的选项
+    pub fn new(library_type: LibraryType) -> Self {
+        Self {
+            library_type,
+            index_offset: None,
+            palette: PaletteSource::default(),
+            flip_vertical: false,
+        }
+    }
+}
+
+/// 按文件记住的打开选项
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct OpenChoices {
+    /// 文件路径 -> 打开选项
+    entries: HashMap<String, OpenOptions>,
+}
+
+impl OpenChoices {
+    /// 读取记录文件，不存在或损坏时返回空记录
+    pub fn load(path: &Path) -> Self {
+        std::fs::read(path)
+            .ok()
+            .and_then(|data| serde_json::from_slice(&data).ok())
+            .unwrap_or_default()
+    }
+
+    /// 写入记录文件
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// 查询文件已记住的选项
+    pub fn get(&self, file: &Path) -> Option<&OpenOptions> {
+        self.entries.get(&Self::key(file))
+    }
+
+    /// 记住文件的选项
+    pub fn insert(&mut self, file: &Path, options: OpenOptions) {
+        self.entries.insert(Self::key(file), options);
+    }
+
+    /// 记录键：尽量使用规范化的绝对路径
+    fn key(file: &Path) -> String {
+        file.canonicalize()
+            .unwrap_or_else(|_| file.to_path_buf())
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_choices_roundtrip() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("open_choices.json");
+        let file = Path::new("/nonexistent/Hum.wil");
+
+        let mut choices = OpenChoices::default();
+        let options = OpenOptions {
+            library_type: LibraryType::WeMade,
+            index_offset: Some(52),
+            palette: PaletteSource::Default,
+            flip_vertical: true,
+        };
+        choices.insert(file, options.clone());
+        choices.save(&path).unwrap();
+
+        let loaded = OpenChoices::load(&path);
+        assert_eq!(loaded.get(file), Some(&options));
+        assert!(loaded.get(Path::new("other.wil")).is_none());
+    }
+}
@@ -0,0 +1,173 @@
+//! 项目文件（`.lep`）
+//!
+//! 记录一组库以及每个库的书签、命名动画序列和备注，如“Mon23 攻击 = 第 360-369 帧”，
+//! 整理资源时积累的这类信息可以随项目保存和分享。项目文件为 JSON，库路径在项目
+//! 文件所在目录之下时保存为相对路径，整个目录搬到别处后仍能打开。
+
+use crate::error::{LibraryError, Result};
+use crate::formats::animation::AnimationSet;
+use crate::formats::bookmarks::Bookmarks;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// 项目文件的扩展名
+pub const PROJECT_EXTENSION: &str = "lep";
+
+/// 写入的项目文件版本号
+pub const PROJECT_VERSION: u32 = 1;
+
+/// 项目中的一个库
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProjectLibrary {
+    /// 库文件路径
+    pub path: PathBuf,
+    /// 书签
+    pub bookmarks: Bookmarks,
+    /// 动画序列
+    pub sequences: AnimationSet,
+    /// 备注
+    pub notes: String,
+}
+
+/// 项目
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Project {
+    /// 文件版本号
+    pub version: u32,
+    /// 项目中的库（按添加顺序）
+    pub libraries: Vec<ProjectLibrary>,
+    /// 项目备注
+    pub notes: String,
+}
+
+impl Default for Project {
+    fn default() -> Self {
+        Self {
+            version: PROJECT_VERSION,
+            libraries: Vec::new(),
+            notes: String::new(),
+        }
+    }
+}
+
+impl Project {
+    /// 读取项目文件，相对路径按项目文件所在目录解析
+    pub fn load(path: &Path) -> Result<Self> {
+        let mut project: Self = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        if project.version > PROJECT_VERSION {
+            return Err(LibraryError::ParseError(format!(
+                "项目文件版本 {} 高于支持的版本 {}",
+                project.version, PROJECT_VERSION
+            )));
+        }
+        let dir = project_dir(path);
+        for library in &mut project.libraries {
+            if library.path.is_relative() {
+                library.path = dir.join(&library.path);
+            }
+        }
+        tracing::debug!("读取项目 {:?}: {} 个库", path, project.libraries.len());
+        Ok(project)
+    }
+
+    /// 写出项目文件，项目文件所在目录下的库保存为相对路径
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let dir = project_dir(path);
+        let mut project = self.clone();
+        project.version = PROJECT_VERSION;
+        for library in &mut project.libraries {
+            if let Ok(relative) = library.path.strip_prefix(&dir) {
+                library.path = relative.to_path_buf();
+            }
+        }
+        std::fs::write(path, serde_json::to_string_pretty(&project)?)?;
+        Ok(())
+    }
+
+    /// 查找项目中的库
+    pub fn library(&self, path: &Path) -> Option<&ProjectLibrary> {
+        self.libraries.iter().find(|l| same_file(&l.path, path))
+    }
+
+    /// 查找项目中的库，不在项目中时先添加（相对路径按当前目录转为绝对路径）
+    pub fn library_mut(&mut self, path: &Path) -> &mut ProjectLibrary {
+        let pos = match self.libraries.iter().position(|l| same_file(&l.path, path)) {
+            Some(pos) => pos,
+            None => {
+                self.libraries.push(ProjectLibrary {
+                    path: std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf()),
+                    ..Default::default()
+                });
+                self.libraries.len() - 1
+            }
+        };
+        &mut self.libraries[pos]
+    }
+}
+
+/// 项目文件所在目录（绝对路径，取不到时为相对路径的上级目录）
+fn project_dir(path: &Path) -> PathBuf {
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    path.parent().map(Path::to_path_buf).unwrap_or_default()
+}
+
+/// 两个路径是否指向同一文件（文件不存在时按绝对路径比较）
+fn same_file(a: &Path, b: &Path) -> bool {
+    let normalize = |p: &Path| {
+        p.canonicalize()
+            .or_else(|_| std::path::absolute(p))
+            .unwrap_or_else(|_| p.to_path_buf())
+    };
+    normalize(a) == normalize(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::animation::Sequence;
+
+    #[test]
+    fn test_project_roundtrip_with_relative_paths() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path().join("project");
+        std::fs::create_dir_all(dir.join("Data")).unwrap();
+        let lep = dir.join("mon.lep");
+
+        let mut project = Project {
+            notes: "怪物动作整理".to_string(),
+            ..Default::default()
+        };
+        let mon = project.library_mut(&dir.join("Data").join("Mon23.wil"));
+        mon.bookmarks.set(369, "攻击结束");
+        mon.bookmarks.set(360, "攻击");
+        mon.bookmarks.set(369, "攻击最后一帧");
+        mon.sequences
+            .set(Sequence {
+                name: "攻击".to_string(),
+                start: 360,
+                count: 9,
+                fps: 8,
+            })
+            .unwrap();
+        project.library_mut(Path::new("/elsewhere/Hum.wzl"));
+        project.save(&lep).unwrap();
+        assert!(!std::fs::read_to_string(&lep).unwrap().contains("project"));
+
+        // 整个目录搬走后相对路径跟着项目文件走
+        let moved = temp.path().join("moved");
+        std::fs::rename(&dir, &moved).unwrap();
+        let loaded = Project::load(&moved.join("mon.lep")).unwrap();
+        assert_eq!(loaded.notes, "怪物动作整理");
+        assert_eq!(loaded.libraries.len(), 2);
+        let mon = loaded
+            .library(&moved.join("Data").join("Mon23.wil"))
+            .unwrap();
+        let indices: Vec<usize> = mon.bookmarks.iter().map(|b| b.index).collect();
+        assert_eq!(indices, vec![360, 369]);
+        assert_eq!(mon.bookmarks.get(369).unwrap().label, "攻击最后一帧");
+        assert_eq!(mon.sequences.get("攻击").unwrap().frames(), 360..369);
+        assert_eq!(loaded.libraries[1].path, Path::new("/elsewhere/Hum.wzl"));
+    }
+}
@@ -0,0 +1,212 @@
+//! 帧索引对应关系
+//!
+//! 删除帧后，后面的帧索引依次前移，游戏和服务端按索引引用帧的数据随之失效。
+//! 这里记录结构性修改前后每个旧索引对应的新索引，并把连续移动的索引合并成段，
+//! 方便报告“哪些索引移到了哪里”。对照表可导出为 CSV 或 JSON，只列出位置变化
+//! 或已删除的帧，未列出的索引保持不变，供服务端据此更新引用。
+
+use crate::error::Result;
+use serde::Serialize;
+
+/// 一段连续移动的索引：旧索引 `old_start..old_start + len` 变为 `new_start..new_start + len`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RemapSegment {
+    /// 旧的起始索引
+    pub old_start: usize,
+    /// 新的起始索引
+    pub new_start: usize,
+    /// 帧数
+    pub len: usize,
+}
+
+/// 对照表中的一行：旧索引及新索引（已删除为 `None`）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct RemapEntry {
+    /// 已保存文件中的索引
+    pub old: usize,
+    /// 修改后的索引
+    pub new: Option<usize>,
+}
+
+/// 导出为 JSON 时的内容
+#[derive(Serialize)]
+struct RemapReport<'a> {
+    old_count: usize,
+    new_count: usize,
+    changes: &'a [RemapEntry],
+}
+
+/// 修改前后的索引对应关系（按旧索引，已删除的帧为 `None`）
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IndexRemap {
+    map: Vec<Option<usize>>,
+    /// 修改后的帧数
+    new_count: usize,
+}
+
+impl IndexRemap {
+    /// 由修改后每一帧的来源建立对应关系：`sources` 的第 `n` 项为新索引 `n` 的帧
+    /// 在修改前的索引，新加入的帧为 `None`
+    pub fn from_sources(
+        old_count: usize,
+        sources: impl IntoIterator<Item = Option<usize>>,
+    ) -> Self {
+        let mut map = vec![None; old_count];
+        let mut new_count = 0;
+        for (new, source) in sources.into_iter().enumerate() {
+            if let Some(slot) = source.and_then(|old| map.get_mut(old)) {
+                *slot = Some(new);
+            }
+            new_count = new + 1;
+        }
+        Self { map, new_count }
+    }
+
+    /// 共 `count` 帧时删除 `removed` 中的帧之后的对应关系
+    pub fn after_removal(count: usize, removed: &[usize]) -> Self {
+        let mut map = vec![Some(0); count];
+        for &index in removed {
+            if let Some(slot) = map.get_mut(index) {
+                *slot = None;
+            }
+        }
+        let mut new_count = 0;
+        for (new, slot) in map.iter_mut().flatten().enumerate() {
+            *slot = new;
+            new_count = new + 1;
+        }
+        Self { map, new_count }
+    }
+
+    /// 旧索引对应的新索引，帧已删除或超出范围时为 `None`
+    pub fn new_index(&self, old: usize) -> Option<usize> {
+        self.map.get(old).copied().flatten()
+    }
+
+    /// 已删除的旧索引
+    pub fn removed(&self) -> Vec<usize> {
+        (0..self.map.len())
+            .filter(|&old| self.map[old].is_none())
+            .collect()
+    }
+
+    /// 是否没有任何帧的索引发生变化
+    pub fn is_identity(&self) -> bool {
+        self.map
+            .iter()
+            .enumerate()
+            .all(|(old, new)| *new == Some(old))
+    }
+
+    /// 位置变化或已删除的帧，按旧索引排列
+    pub fn changes(&self) -> Vec<RemapEntry> {
+        self.map
+            .iter()
+            .enumerate()
+            .filter(|(old, new)| **new != Some(*old))
+            .map(|(old, new)| RemapEntry { old, new: *new })
+            .collect()
+    }
+
+    /// 对照表的 CSV 行（含表头），已删除的帧新索引留空
+    pub fn csv_rows(&self) -> Vec<Vec<String>> {
+        let mut rows = vec![vec!["old_index".to_string(), "new_index".to_string()]];
+        rows.extend(self.changes().iter().map(|entry| {
+            vec![
+                entry.old.to_string(),
+                entry.new.map(|new| new.to_string()).unwrap_or_default(),
+            ]
+        }));
+        rows
+    }
+
+    /// 对照表的 JSON 文本，含修改前后的帧数
+    pub fn to_json(&self) -> Result<String> {
+        let report = RemapReport {
+            old_count: self.map.len(),
+            new_count: self.new_count,
+            changes: &self.changes(),
+        };
+        Ok(serde_json::to_string_pretty(&report)?)
+    }
+
+    /// 索引发生移动的帧，按旧索引合并成连续的段
+    pub fn shifted(&self) -> Vec<RemapSegment> {
+        let mut segments: Vec<RemapSegment> = Vec::new();
+        for (old, new) in self.map.iter().enumerate() {
+            let Some(new) = *new else {
+                continue;
+            };
+            if new == old {
+                continue;
+            }
+            match segments.last_mut() {
+                Some(last)
+                    if last.old_start + last.len == old && last.new_start + last.len == new =>
+                {
+                    last.len += 1;
+                }
+                _ => segments.push(RemapSegment {
+                    old_start: old,
+                    new_start: new,
+                    len: 1,
+                }),
+            }
+        }
+        segments
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remap_after_removal() {
+        let remap = IndexRemap::after_removal(8, &[1, 2, 5]);
+        assert_eq!(remap.new_index(0), Some(0));
+        assert_eq!(remap.new_index(1), None);
+        assert_eq!(remap.new_index(3), Some(1));
+        assert_eq!(remap.new_index(7), Some(4));
+        assert_eq!(remap.removed(), vec![1, 2, 5]);
+        assert!(!remap.is_identity());
+        assert_eq!(
+            remap.shifted(),
+            vec![
+                RemapSegment {
+                    old_start: 3,
+                    new_start: 1,
+                    len: 2
+                },
+                RemapSegment {
+                    old_start: 6,
+                    new_start: 3,
+                    len: 2
+                },
+            ]
+        );
+
+        // 只删除末尾的帧时其余索引不变
+        let trailing = IndexRemap::after_removal(4, &[2, 3]);
+        assert!(trailing.shifted().is_empty());
+        assert!(!trailing.is_identity());
+        assert!(IndexRemap::after_removal(3, &[]).is_identity());
+
+        // 由来源表建立：删除旧的第 1 帧后又在末尾追加一帧
+        let remap = IndexRemap::from_sources(3, [Some(0), Some(2), None]);
+        assert_eq!(
+            remap.changes(),
+            vec![
+                RemapEntry { old: 1, new: None },
+                RemapEntry {
+                    old: 2,
+                    new: Some(1)
+                },
+            ]
+        );
+        assert_eq!(remap.csv_rows()[1], vec!["1".to_string(), String::new()]);
+        let json: serde_json::Value = serde_json::from_str(&remap.to_json().unwrap()).unwrap();
+        assert_eq!(json["new_count"], 3);
+        assert_eq!(json["changes"][1]["new"], 1);
+    }
+}
@@ -0,0 +1,249 @@
+//! 远程库文件
+//!
+//! 支持直接打开 `http://` / `https://` 地址上的库文件：首次打开时连同索引文件一起
+//! 下载到本地缓存目录，之后直接使用缓存。缓存按主机名和 URL 路径分目录保存，
+//! 索引文件与数据文件落在同一目录，各格式仍按本地文件的规则查找伴随文件。
+//!
+//! 下载（[`fetch`]、[`resolve`]）需要 `cli` feature；缓存路径的计算始终可用。
+
+use crate::error::{LibraryError, Result};
+use std::path::PathBuf;
+#[cfg(feature = "cli")]
+use std::{fs::File, path::Path};
+
+/// 数据文件扩展名 -> 需要一并下载的伴随文件扩展名（不区分大小写）
+#[cfg(feature = "cli")]
+const COMPANIONS: [(&str, &[&str]); 5] = [
+    ("wil", &["wix"]),
+    ("wix", &["wil"]),
+    ("wzl", &["wzx"]),
+    ("wzx", &["wzl"]),
+    ("d", &["wix"]),
+];
+
+/// 是否为远程地址
+pub fn is_url(location: &str) -> bool {
+    let lower = location.get(..8).unwrap_or(location).to_ascii_lowercase();
+    lower.starts_with("http://") || lower.starts_with("https://")
+}
+
+/// 远程库的缓存目录（[`cache_root`] 下的 `remote`）
+pub fn cache_dir() -> PathBuf {
+    cache_root().join("remote")
+}
+
+/// 编辑器的缓存根目录
+///
+/// Windows 下为 `%LOCALAPPDATA%\library_editor`，其他平台为
+/// `$XDG_CACHE_HOME/library_editor` 或 `~/.cache/library_editor`；
+/// 都取不到时退回当前目录下的 `cache`。
+pub fn cache_root() -> PathBuf {
+    let env_dir = |name: &str| {
+        std::env::var_os(name)
+            .map(PathBuf::from)
+            .filter(|dir| dir.is_absolute())
+    };
+    let base = if cfg!(windows) {
+        env_dir("LOCALAPPDATA")
+    } else {
+        env_dir("XDG_CACHE_HOME").or_else(|| env_dir("HOME").map(|home| home.join(".cache")))
+    };
+    match base {
+        Some(base) => base.join("library_editor"),
+        None => PathBuf::from("./cache"),
+    }
+}
+
+/// URL 在缓存目录中对应的相对路径：`主机名/路径`，忽略查询参数和片段
+pub fn cache_path(url: &str) -> Result<PathBuf> {
+    let invalid = || LibraryError::ParseError(format!("无效的库文件地址: {}", url));
+    let (_, rest) = url.split_once("://").ok_or_else(invalid)?;
+    let rest = rest.split(['?', '#']).next().unwrap_or_default();
+    let (host, path) = rest.split_once('/').ok_or_else(invalid)?;
+
+    // 主机名中的端口号和路径中的特殊字符换成下划线，避免生成非法或越出缓存目录的路径
+    let sanitize = |part: &str| -> String {
+        part.chars()
+            .map(|c| match c {
+                ':' | '/' | '\\' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+                c => c,
+            })
+            .collect()
+    };
+    // 主机名同路径段一样不能是空、`.` 或 `..`（只有点号的主机名在 Windows 上也会退化成这些）
+    if host.chars().all(|c| c == '.') {
+        return Err(invalid());
+    }
+    let mut relative = PathBuf::from(sanitize(host));
+    for segment in path.split('/').map(percent_decode) {
+        match segment.as_str() {
+            "" | "." => {}
+            ".." => return Err(invalid()),
+            segment => relative.push(sanitize(segment)),
+        }
+    }
+    if relative.file_name().is_none() || relative.components().count() < 2 {
+        return Err(invalid());
+    }
+    Ok(relative)
+}
+
+/// 伴随文件的 URL（扩展名大小写跟随原文件：`Hum.WIL` -> `Hum.WIX`）
+#[cfg(feature = "cli")]
+fn companion_urls(url: &str) -> Vec<String> {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    let Some((stem, ext)) = path.rsplit_once('.').filter(|(_, ext)| !ext.contains('/')) else {
+        return Vec::new();
+    };
+    let upper = ext.chars().all(|c| !c.is_ascii_lowercase());
+    COMPANIONS
+        .iter()
+        .find(|(data, _)| ext.eq_ignore_ascii_case(data))
+        .map(|(_, companions)| {
+            companions
+                .iter()
+                .map(|companion| {
+                    let companion = if upper {
+                        companion.to_ascii_uppercase()
+                    } else {
+                        companion.to_string()
+                    };
+                    format!("{}.{}", stem, companion)
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// 解码 URL 路径中的 `%XX` 转义
+fn percent_decode(segment: &str) -> String {
+    let bytes = segment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// 把库的位置解析为本地路径：远程地址先下载到缓存，本地路径原样返回
+#[cfg(feature = "cli")]
+pub fn resolve(location: &str, refresh: bool) -> Result<PathBuf> {
+    if is_url(location) {
+        fetch(location, refresh)
+    } else {
+        Ok(PathBuf::from(location))
+    }
+}
+
+/// 下载远程库到缓存，返回本地数据文件路径
+///
+/// 已缓存的文件直接复用，`refresh` 为真时重新下载。伴随的索引文件一并下载，
+/// 服务器上不存在（404）时跳过，由加载器按格式判断是否缺少必需文件。
+#[cfg(feature = "cli")]
+pub fn fetch(url: &str, refresh: bool) -> Result<PathBuf> {
+    let dir = cache_dir();
+    let local = dir.join(cache_path(url)?);
+
+    for companion in companion_urls(url) {
+        let path = dir.join(cache_path(&companion)?);
+        if refresh || !path.exists() {
+            download(&companion, &path, false)?;
+        }
+    }
+    if refresh || !local.exists() {
+        download(url, &local, true)?;
+    } else {
+        tracing::debug!("使用已缓存的远程库: {:?}", local);
+    }
+    Ok(local)
+}
+
+/// 下载单个文件：先写入临时文件，完成后再改名，中断时不会留下不完整的缓存
+///
+/// 返回是否下载成功；`required` 为假时 404 不视为错误。
+#[cfg(feature = "cli")]
+fn download(url: &str, dest: &Path, required: bool) -> Result<bool> {
+    tracing::info!("下载 {} -> {:?}", url, dest);
+    let response = match ureq::get(url).call() {
+        Ok(response) => response,
+        Err(ureq::Error::StatusCode(404)) if !required => {
+            tracing::debug!("远程伴随文件不存在: {}", url);
+            return Ok(false);
+        }
+        Err(e) => return Err(LibraryError::Download(format!("{}: {}", url, e))),
+    };
+
+    if let Some(parent) = dest.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let partial = crate::formats::companion_path(dest, ".part");
+    let mut file = File::create(&partial)?;
+    let written = std::io::copy(&mut response.into_body().into_reader(), &mut file);
+    drop(file);
+    match written {
+        Ok(size) => {
+            std::fs::rename(&partial, dest)?;
+            tracing::debug!("已下载 {} 字节", size);
+            Ok(true)
+        }
+        Err(e) => {
+            std::fs::remove_file(&partial).ok();
+            Err(LibraryError::Download(format!("{}: {}", url, e)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn test_cache_path() {
+        assert!(is_url("HTTPS://assets.example.com/Data/Hum.wil"));
+        assert!(!is_url("C:/Data/Hum.wil"));
+
+        assert_eq!(
+            cache_path("https://assets.example.com:8080/Data/Mon%2012.wil?v=3").unwrap(),
+            Path::new("assets.example.com_8080")
+                .join("Data")
+                .join("Mon 12.wil")
+        );
+        assert!(cache_path("https://assets.example.com/../etc/Hum.wil").is_err());
+        assert!(cache_path("https://assets.example.com/%2E%2E/Hum.wil").is_err());
+        assert!(cache_path("https://assets.example.com/").is_err());
+        assert!(cache_path("http://../x.wzl").is_err());
+        assert!(cache_path("http://./x.wzl").is_err());
+        assert!(cache_path("http://..../x.wzl").is_err());
+        assert!(cache_path("http:///x.wzl").is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "cli")]
+    fn test_companion_urls() {
+        assert_eq!(
+            companion_urls("http://host/Data/Hum.wil"),
+            vec!["http://host/Data/Hum.wix"]
+        );
+        assert_eq!(
+            companion_urls("http://host/Data/Hum.WZL?x=1"),
+            vec!["http://host/Data/Hum.WZX"]
+        );
+        assert!(companion_urls("http://host/Data/Hum.Lib").is_empty());
+        assert!(companion_urls("http://host.name/Data").is_empty());
+    }
+}
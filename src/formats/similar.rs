@@ -0,0 +1,157 @@
+//! 相似帧查找
+//!
+//! 为库中的全部非空帧计算感知哈希（见 [`crate::image::hash`]）建立索引，
+//! 再按与目标图像的距离排序，找出某张 PNG 或另一个库中的帧在本库中的位置。
+//! 四万帧的库建立索引需要逐帧解码，索引建好后每次查找只比较哈希。
+
+use crate::error::{LibraryError, Result};
+use crate::formats::LibraryLoader;
+use crate::image::hash::ImageHash;
+use crate::progress::{CancelToken, Progress};
+use image::RgbaImage;
+use std::sync::Arc;
+
+/// 查找结果中的一帧
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SimilarFrame {
+    /// 帧索引
+    pub index: usize,
+    /// 与目标的距离（0 为哈希完全相同）
+    pub distance: u32,
+}
+
+/// 库中全部非空帧的感知哈希
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SimilarityIndex {
+    hashes: Vec<(usize, ImageHash)>,
+}
+
+impl SimilarityIndex {
+    /// 逐帧解码并计算哈希，每帧之后报告进度并检查取消；解码失败的帧跳过
+    pub fn build(
+        loader: &mut LibraryLoader,
+        cancel: &CancelToken,
+        progress: impl FnMut(Progress),
+    ) -> Result<Self> {
+        let total = loader.image_count();
+        Self::build_with(total, cancel, progress, |index| loader.get_frame(index))
+    }
+
+    /// 同 [`build`](Self::build)，`total` 帧逐帧由 `frame` 取得
+    ///
+    /// 供后台线程使用：每取一帧才锁一次共享的加载器，不必在整个建立过程中占着它。
+    /// `frame` 返回错误时该帧跳过，[`LibraryError::Cancelled`] 则中止建立。
+    ///
+    /// [`LibraryError::Cancelled`]: crate::error::LibraryError::Cancelled
+    pub fn build_with(
+        total: usize,
+        cancel: &CancelToken,
+        mut progress: impl FnMut(Progress),
+        mut frame: impl FnMut(usize) -> Result<Option<Arc<RgbaImage>>>,
+    ) -> Result<Self> {
+        let mut hashes = Vec::new();
+        for index in 0..total {
+            cancel.check()?;
+            match frame(index) {
+                Ok(Some(frame)) => hashes.push((index, ImageHash::of(&frame))),
+                Ok(None) => {}
+                Err(e @ LibraryError::Cancelled) => return Err(e),
+                Err(e) => tracing::debug!("计算第 {} 帧的哈希失败: {:?}", index, e),
+            }
+            progress(Progress::FrameDecoded {
+                done: index + 1,
+                total,
+            });
+        }
+        tracing::debug!("相似帧索引: {} 帧中 {} 帧非空", total, hashes.len());
+        Ok(Self { hashes })
+    }
+
+    /// 已建立哈希的帧数
+    pub fn len(&self) -> usize {
+        self.hashes.len()
+    }
+
+    /// 是否没有非空帧
+    pub fn is_empty(&self) -> bool {
+        self.hashes.is_empty()
+    }
+
+    /// 与目标图像最相似的 `limit` 帧，按距离从小到大排列
+    pub fn search(&self, target: &RgbaImage, limit: usize) -> Vec<SimilarFrame> {
+        let target = ImageHash::of(target);
+        let mut matches: Vec<SimilarFrame> = self
+            .hashes
+            .iter()
+            .map(|(index, hash)| SimilarFrame {
+                index: *index,
+                distance: hash.distance(&target),
+            })
+            .collect();
+        matches.sort_by_key(|m| (m.distance, m.index));
+        matches.truncate(limit);
+        matches
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::mlibrary_v2::{MImage, MLibraryV2};
+    use image::Rgba;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_search_similar_frames() {
+        let stripes = |vertical: bool| {
+            RgbaImage::from_fn(16, 16, |x, y| {
+                let v = if (if vertical { x } else { y }) < 8 {
+                    40
+                } else {
+                    220
+                };
+                Rgba([v, v, v, 255])
+            })
+        };
+        let mut source = MLibraryV2::new(PathBuf::from("similar_missing")).unwrap();
+        source.add_image(&MImage::from_image(&stripes(false), 0, 0));
+        source.add_image(&MImage::new());
+        source.add_image(&MImage::from_image(&stripes(true), 0, 0));
+        let (_, mut loader) = LibraryLoader::from_v2(source, "similar.Lib");
+
+        let mut reported = 0;
+        let index = SimilarityIndex::build(&mut loader, &CancelToken::new(), |p| {
+            if let Progress::FrameDecoded { done, .. } = p {
+                reported = done;
+            }
+        })
+        .unwrap();
+        assert_eq!(index.len(), 2);
+        assert_eq!(reported, 3);
+
+        let matches = index.search(&stripes(true), 5);
+        assert_eq!(matches.len(), 2);
+        assert_eq!(
+            matches[0],
+            SimilarFrame {
+                index: 2,
+                distance: 0
+            }
+        );
+        assert!(matches[1].distance > 0);
+
+        let cancel = CancelToken::new();
+        cancel.cancel();
+        assert!(SimilarityIndex::build(&mut loader, &cancel, |_| {}).is_err());
+
+        // 逐帧取得：读取失败的帧跳过，取消时中止
+        let frames = |index: usize| match index {
+            0 => Err(LibraryError::InvalidImageData),
+            _ => Ok(Some(Arc::new(stripes(true)))),
+        };
+        let index = SimilarityIndex::build_with(3, &CancelToken::new(), |_| {}, frames).unwrap();
+        assert_eq!(index.len(), 2);
+        let closed = |_| Err(LibraryError::Cancelled);
+        assert!(SimilarityIndex::build_with(3, &CancelToken::new(), |_| {}, closed).is_err());
+    }
+}
@@ -0,0 +1,135 @@
+//! 库文件的数据来源
+//!
+//! 解析器通过 [`Source`] 按扩展名打开伴随文件（`.Lib`、`.wzx`、`.wzl` 等），
+//! 不直接访问文件系统：桌面端从磁盘读取，浏览器（wasm32）中从调用方传入的
+//! 字节读取。
+
+use crate::error::{LibraryError, Result};
+use crate::formats::find_companion;
+use std::fs::File;
+use std::io::{BufReader, Cursor, Read, Seek};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// 可随机读取的字节流
+///
+/// 要求 `Send + Sync`，持有读取器的解析器（以及 [`LibraryLoader`](super::LibraryLoader)）
+/// 才能在线程间传递和共享。
+pub trait ReadSeek: Read + Seek + Send + Sync {}
+
+impl<T: Read + Seek + Send + Sync> ReadSeek for T {}
+
+/// 库文件的数据来源
+#[derive(Debug, Clone)]
+pub enum Source {
+    /// 磁盘上的库，保存基础路径（不带扩展名）
+    Path(PathBuf),
+    /// 内存中的伴随文件：扩展名（如 `.wzl`）和文件内容
+    Memory(Vec<(String, Arc<[u8]>)>),
+}
+
+impl Source {
+    /// 由若干伴随文件的内容构造内存来源
+    pub fn memory<'a>(files: impl IntoIterator<Item = (&'a str, Vec<u8>)>) -> Self {
+        Source::Memory(
+            files
+                .into_iter()
+                .map(|(ext, data)| (ext.to_string(), Arc::from(data)))
+                .collect(),
+        )
+    }
+
+    /// 内存中的伴随文件（扩展名不区分大小写）
+    fn find(&self, ext: &str) -> Option<&Arc<[u8]>> {
+        match self {
+            Source::Path(_) => None,
+            Source::Memory(files) => files
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(ext))
+                .map(|(_, data)| data),
+        }
+    }
+
+    /// 磁盘上已存在的伴随文件路径（文件名不区分大小写）
+    fn existing_path(base: &Path, ext: &str) -> Result<PathBuf> {
+        let path = find_companion(base, ext);
+        if !path.exists() {
+            return Err(LibraryError::FileNotFound(path.display().to_string()));
+        }
+        Ok(path)
+    }
+
+    /// 内存中的伴随文件内容，不存在时返回 [`LibraryError::FileNotFound`]
+    fn data(&self, ext: &str) -> Result<&Arc<[u8]>> {
+        self.find(ext)
+            .ok_or_else(|| LibraryError::FileNotFound(self.describe(ext)))
+    }
+
+    /// 伴随文件的名称，用于日志和错误信息
+    pub fn describe(&self, ext: &str) -> String {
+        match self {
+            Source::Path(base) => find_companion(base, ext).display().to_string(),
+            Source::Memory(_) => format!("<内存>{}", ext),
+        }
+    }
+
+    /// 伴随文件是否存在
+    pub fn exists(&self, ext: &str) -> bool {
+        match self {
+            Source::Path(base) => find_companion(base, ext).exists(),
+            Source::Memory(_) => self.find(ext).is_some(),
+        }
+    }
+
+    /// 打开伴随文件，不存在时返回 [`LibraryError::FileNotFound`]
+    pub fn open(&self, ext: &str) -> Result<Box<dyn ReadSeek>> {
+        match self {
+            Source::Path(base) => {
+                let file = File::open(Self::existing_path(base, ext)?)?;
+                Ok(Box::new(BufReader::new(file)))
+            }
+            Source::Memory(_) => Ok(Box::new(Cursor::new(Arc::clone(self.data(ext)?)))),
+        }
+    }
+
+    /// 伴随文件的字节数
+    pub fn len(&self, ext: &str) -> Result<u64> {
+        match self {
+            Source::Path(base) => Ok(std::fs::metadata(Self::existing_path(base, ext)?)?.len()),
+            Source::Memory(_) => Ok(self.data(ext)?.len() as u64),
+        }
+    }
+
+    /// 读取伴随文件的全部内容
+    pub fn read(&self, ext: &str) -> Result<Vec<u8>> {
+        match self {
+            Source::Path(base) => Ok(std::fs::read(Self::existing_path(base, ext)?)?),
+            Source::Memory(_) => Ok(self.data(ext)?.to_vec()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_source() {
+        let source = Source::memory([(".wzx", vec![1, 2]), (".wzl", vec![3, 4, 5])]);
+        assert!(source.exists(".WZL"));
+        assert!(!source.exists(".Lib"));
+        assert_eq!(source.read(".wzx").unwrap(), vec![1, 2]);
+        assert_eq!(source.len(".wzl").unwrap(), 3);
+
+        let mut data = Vec::new();
+        let mut reader = source.open(".wzl").unwrap();
+        reader.seek(std::io::SeekFrom::Start(1)).unwrap();
+        reader.read_to_end(&mut data).unwrap();
+        assert_eq!(data, vec![4, 5]);
+
+        assert!(matches!(
+            source.open(".Lib"),
+            Err(LibraryError::FileNotFound(name)) if name == "<内存>.Lib"
+        ));
+    }
+}
@@ -0,0 +1,364 @@
+//! 内置的格式说明
+//!
+//! 把各库格式的二进制结构（文件头、索引、帧记录的字段偏移和类型）整理成结构化数据，
+//! 供 GUI 的格式说明面板和 `spec` 命令显示。打开文件时按字段标识读取实际的值
+//! （[`annotate`]），在说明旁标出解析器在当前文件中找到的内容。
+
+use crate::error::Result;
+use crate::formats::header::FrameHeader;
+use crate::formats::{LibraryLoader, LibraryType, find_companion};
+use byteorder::{LittleEndian, ReadBytesExt};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// 一个字段
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldSpec {
+    /// 偏移（`+` 开头的为相对帧记录起点）
+    pub offset: &'static str,
+    /// 类型
+    pub kind: &'static str,
+    /// 说明
+    pub description: &'static str,
+    /// 字段标识，[`annotate`] 读到的值以此为键；为空时不读取
+    pub id: &'static str,
+}
+
+/// 一段结构（如索引文件头、帧记录）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SectionSpec {
+    /// 标题
+    pub title: &'static str,
+    /// 字段
+    pub fields: &'static [FieldSpec],
+}
+
+/// 一种格式的说明
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FormatSpec {
+    /// 名称
+    pub name: &'static str,
+    /// 涉及的文件
+    pub files: &'static str,
+    /// 概述
+    pub summary: &'static str,
+    /// 各段结构
+    pub sections: &'static [SectionSpec],
+}
+
+const fn field(
+    offset: &'static str,
+    kind: &'static str,
+    description: &'static str,
+    id: &'static str,
+) -> FieldSpec {
+    FieldSpec {
+        offset,
+        kind,
+        description,
+        id,
+    }
+}
+
+/// 全部格式说明
+pub const SPECS: [FormatSpec; 4] = [
+    FormatSpec {
+        name: "MLibrary V1",
+        files: ".wzl / .wzx",
+        summary: "索引文件给出每帧在数据文件中的偏移；帧记录为 16 字节头部加 zlib 压缩的像素数据，\
+                  像素为 8 位调色板索引或 16 位 RGB565，每行补齐到 4 字节；16 位帧可在行数据后附 \
+                  每像素 4 位的 alpha 平面。",
+        sections: &[
+            SectionSpec {
+                title: ".wzx 索引文件",
+                fields: &[
+                    field("0", "u8[44]", "文件头标识", "index.magic"),
+                    field(
+                        "44",
+                        "i32",
+                        "图像数量（0 表示读取到文件末尾）",
+                        "index.count",
+                    ),
+                    field(
+                        "48",
+                        "u32 × N",
+                        "各帧在 .wzl 中的偏移（0 为空帧）",
+                        "index.first",
+                    ),
+                ],
+            },
+            SectionSpec {
+                title: ".wzl 帧记录",
+                fields: &[
+                    field(
+                        "+0",
+                        "u8",
+                        "像素格式（5 为 16 位 RGB565，6 为 16 位 RGB565 加 4 位 alpha 平面，\
+                         否则为 8 位调色板）",
+                        "",
+                    ),
+                    field("+1", "u8[3]", "保留", ""),
+                    field("+4", "i16", "宽度", "frame.width"),
+                    field("+6", "i16", "高度", "frame.height"),
+                    field("+8", "i16", "X 偏移", "frame.x"),
+                    field("+10", "i16", "Y 偏移", "frame.y"),
+                    field("+12", "i32", "压缩数据长度（0 表示未压缩）", "frame.length"),
+                    field("+16", "u8 × 长度", "像素数据（zlib 压缩）", ""),
+                ],
+            },
+        ],
+    },
+    FormatSpec {
+        name: "MLibrary V2",
+        files: ".Lib",
+        summary: "单个文件，文件头后是索引，帧记录带阴影信息和可选的遮罩层，像素为 GZip 压缩的 RGBA。\
+                  只支持版本号 2，版本号不同的 .Lib（如 v3）打开时报告版本不符。",
+        sections: &[
+            SectionSpec {
+                title: "文件头",
+                fields: &[
+                    field("0", "i32", "版本号（2）", "lib.version"),
+                    field("4", "i32", "图像数量", "index.count"),
+                    field("8", "u32 × N", "各帧在文件中的偏移", "index.first"),
+                ],
+            },
+            SectionSpec {
+                title: "帧记录",
+                fields: &[
+                    field("+0", "i16", "宽度", "frame.width"),
+                    field("+2", "i16", "高度", "frame.height"),
+                    field("+4", "i16", "X 偏移", "frame.x"),
+                    field("+6", "i16", "Y 偏移", "frame.y"),
+                    field("+8", "i16", "阴影 X 偏移", ""),
+                    field("+10", "i16", "阴影 Y 偏移", ""),
+                    field(
+                        "+12",
+                        "u8",
+                        "阴影类型，最高位为 1 表示带遮罩层",
+                        "frame.mask",
+                    ),
+                    field("+13", "i32", "像素数据长度", "frame.length"),
+                    field("+17", "u8 × 长度", "像素数据（GZip 压缩的 RGBA）", ""),
+                    field("", "i16 × 4", "遮罩层宽、高、X、Y 偏移（带遮罩时）", ""),
+                    field("", "i32", "遮罩层数据长度（带遮罩时）", ""),
+                    field("", "u8 × 长度", "遮罩层像素数据（带遮罩时）", ""),
+                ],
+            },
+        ],
+    },
+    FormatSpec {
+        name: "WeMade Library",
+        files: ".wil / .wix",
+        summary: "传奇1 / 早期传奇2 的格式：数据文件自带调色板，帧记录为宽高加未压缩的 8 位调色板索引。\
+                  调色板字节数为 N × 1024 时为多调色板变体。",
+        sections: &[
+            SectionSpec {
+                title: ".wix 索引文件",
+                fields: &[
+                    field("0", "u8[44]", "文件头标识", "index.magic"),
+                    field("44", "i32", "图像数量", "index.count"),
+                    field("48", "u32 × N", "各帧在 .wil 中的偏移", "index.first"),
+                ],
+            },
+            SectionSpec {
+                title: ".wil 数据文件",
+                fields: &[
+                    field("0", "u8[44]", "文件头标识", "data.magic"),
+                    field("44", "i32", "颜色数", "wil.colors"),
+                    field("48", "u32", "调色板字节数", "wil.palette_bytes"),
+                    field("52", "i32", "版本标志", ""),
+                    field("56", "u8[1024]", "调色板（256 色 BGRA）", ""),
+                    field("1080", "", "帧记录起点", ""),
+                ],
+            },
+            SectionSpec {
+                title: ".wil 帧记录",
+                fields: &[
+                    field("+0", "u16", "宽度", "frame.width"),
+                    field("+2", "u16", "高度", "frame.height"),
+                    field("+4", "u32", "固定标识", ""),
+                    field(
+                        "+8",
+                        "u8 × 行跨度 × 高",
+                        "像素数据（调色板索引，每行补齐到 4 字节）",
+                        "",
+                    ),
+                ],
+            },
+        ],
+    },
+    FormatSpec {
+        name: "WTL Library",
+        files: ".wtl",
+        summary: "单个文件，4 字节标识后是图像数量和索引，帧记录为 12 字节头部加像素数据。",
+        sections: &[
+            SectionSpec {
+                title: "文件头",
+                fields: &[
+                    field("0", "u8[4]", "标识（WTL\\0 或 WTL\\1）", "data.magic"),
+                    field("4", "u32", "图像数量", "index.count"),
+                    field("8", "u32 × N", "各帧在文件中的偏移", "index.first"),
+                ],
+            },
+            SectionSpec {
+                title: "帧记录",
+                fields: &[
+                    field("+0", "i16", "宽度", "frame.width"),
+                    field("+2", "i16", "高度", "frame.height"),
+                    field("+4", "i16", "X 偏移", "frame.x"),
+                    field("+6", "i16", "Y 偏移", "frame.y"),
+                    field("+8", "i32", "像素数据长度", "frame.length"),
+                    field("+12", "u8 × 长度", "像素数据", ""),
+                ],
+            },
+        ],
+    },
+];
+
+/// 库类型对应的格式说明（加密 WIL 和传奇3 变体没有单独的说明）
+pub fn spec_for(lib_type: LibraryType) -> Option<&'static FormatSpec> {
+    let index = match lib_type {
+        LibraryType::MLV1 => 0,
+        LibraryType::MLV2 => 1,
+        LibraryType::WeMade | LibraryType::MLV0 => 2,
+        LibraryType::WTL => 3,
+        LibraryType::EncryptedWil | LibraryType::Mir3 => return None,
+    };
+    Some(&SPECS[index])
+}
+
+/// 按名称查找格式说明：格式名或其中任一扩展名（不区分大小写）
+pub fn find(text: &str) -> Option<&'static FormatSpec> {
+    let text = text.trim().trim_start_matches('.').to_lowercase();
+    SPECS.iter().find(|spec| {
+        spec.name.to_lowercase() == text
+            || spec
+                .files
+                .split(" / ")
+                .any(|ext| ext.trim_start_matches('.').to_lowercase() == text)
+    })
+}
+
+/// 读取文件中各字段的实际值（字段标识 -> 值）
+///
+/// 只读取文件头、索引的第一项和第一个非空帧的头部，读不到的字段不出现在结果中。
+pub fn annotate(path: &Path, lib_type: LibraryType) -> Result<BTreeMap<&'static str, String>> {
+    let base = path.with_extension("");
+    let mut values = BTreeMap::new();
+
+    let (index_path, count_offset) = match lib_type {
+        LibraryType::MLV1 => (find_companion(&base, ".wzx"), 44),
+        LibraryType::WeMade | LibraryType::MLV0 => (find_companion(&base, ".wix"), 44),
+        LibraryType::MLV2 => (path.to_path_buf(), 4),
+        LibraryType::WTL => (path.to_path_buf(), 4),
+        LibraryType::EncryptedWil | LibraryType::Mir3 => return Ok(values),
+    };
+
+    let mut index = File::open(&index_path)?;
+    if matches!(
+        lib_type,
+        LibraryType::MLV1 | LibraryType::WeMade | LibraryType::MLV0
+    ) {
+        values.insert("index.magic", read_magic(&mut index, 44)?);
+    }
+    index.seek(SeekFrom::Start(count_offset))?;
+    values.insert("index.count", index.read_i32::<LittleEndian>()?.to_string());
+    if let Ok(first) = index.read_u32::<LittleEndian>() {
+        values.insert("index.first", format!("第 0 帧 @ {}", first));
+    }
+
+    let mut data = File::open(path)?;
+    match lib_type {
+        LibraryType::MLV2 => {
+            values.insert("lib.version", data.read_i32::<LittleEndian>()?.to_string());
+        }
+        LibraryType::WTL => {
+            values.insert("data.magic", read_magic(&mut data, 4)?);
+        }
+        LibraryType::WeMade | LibraryType::MLV0 => {
+            values.insert("data.magic", read_magic(&mut data, 44)?);
+            values.insert("wil.colors", data.read_i32::<LittleEndian>()?.to_string());
+            values.insert(
+                "wil.palette_bytes",
+                data.read_u32::<LittleEndian>()?.to_string(),
+            );
+        }
+        _ => {}
+    }
+
+    // 帧字段取第一个非空帧（WTL 不支持仅读文件头）
+    let frames = LibraryLoader::open_header(path)
+        .map(|header| header.frames)
+        .unwrap_or_default();
+    if let Some(frame) = frames.iter().find(|f| !f.is_empty()) {
+        annotate_frame(&mut values, frame);
+    }
+    Ok(values)
+}
+
+/// 帧头部字段的值，标出取自第几帧
+fn annotate_frame(values: &mut BTreeMap<&'static str, String>, frame: &FrameHeader) {
+    let at = |value: String| format!("{}（第 {} 帧）", value, frame.index);
+    values.insert("frame.width", at(frame.width.to_string()));
+    values.insert("frame.height", at(frame.height.to_string()));
+    values.insert("frame.x", at(frame.x.to_string()));
+    values.insert("frame.y", at(frame.y.to_string()));
+    values.insert("frame.length", at(frame.data_length.to_string()));
+    let mask = if frame.has_mask {
+        "带遮罩"
+    } else {
+        "无遮罩"
+    };
+    values.insert("frame.mask", at(mask.to_string()));
+}
+
+/// 读取文件头标识（去掉末尾的填充字节）
+fn read_magic(file: &mut File, len: usize) -> Result<String> {
+    let mut magic = vec![0u8; len];
+    file.seek(SeekFrom::Start(0))?;
+    file.read_exact(&mut magic)?;
+    let text = String::from_utf8_lossy(&magic);
+    Ok(text.trim_end_matches('\0').escape_debug().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::mlibrary_v2::MImage;
+    use crate::formats::{MLibraryV2, companion_path};
+
+    #[test]
+    fn test_annotate_v2_fields() {
+        let temp = tempfile::tempdir().unwrap();
+        let base = temp.path().join("spec_annotate");
+        let mut library = MLibraryV2::new(base.clone()).unwrap();
+        library.add_image(&MImage::new());
+        let pixels = image::RgbaImage::from_pixel(5, 3, image::Rgba([1, 2, 3, 255]));
+        library.add_image(&MImage::from_image(&pixels, -2, 4));
+        library.save().unwrap();
+
+        let path = companion_path(&base, ".Lib");
+        let values = annotate(&path, LibraryType::MLV2).unwrap();
+        assert_eq!(values["lib.version"], "2");
+        assert_eq!(values["index.count"], "2");
+        assert_eq!(values["frame.width"], "5（第 1 帧）");
+        assert_eq!(values["frame.x"], "-2（第 1 帧）");
+
+        // 说明中的字段标识都能在对应格式的结果中找到
+        let spec = spec_for(LibraryType::MLV2).unwrap();
+        let ids: Vec<_> = spec
+            .sections
+            .iter()
+            .flat_map(|s| s.fields)
+            .map(|f| f.id)
+            .filter(|id| !id.is_empty())
+            .collect();
+        assert!(ids.iter().all(|id| values.contains_key(id)), "{:?}", ids);
+
+        assert_eq!(find("wzx").map(|s| s.name), Some("MLibrary V1"));
+        assert_eq!(find("WTL Library").map(|s| s.name), Some("WTL Library"));
+        assert!(find("png").is_none());
+    }
+}
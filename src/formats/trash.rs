@@ -0,0 +1,103 @@
+//! 已删除帧的回收站
+//!
+//! 删除帧时先移入本次会话的回收站，保存前都可以恢复；保存后清空。
+//! 每条记录保存删除时的索引，恢复时按之后发生的删除调整位置，
+//! 因此不按删除顺序恢复也能回到原处。
+
+use crate::formats::mlibrary_v2::MImage;
+
+/// 回收站中的一帧
+#[derive(Debug, Clone)]
+pub struct TrashEntry {
+    /// 删除时所在的索引
+    pub index: usize,
+    /// 被删除的帧
+    pub image: MImage,
+}
+
+/// 本次会话的回收站（按删除顺序排列）
+#[derive(Debug, Default)]
+pub struct Trash {
+    entries: Vec<TrashEntry>,
+}
+
+impl Trash {
+    /// 记录一次删除
+    pub fn push(&mut self, index: usize, image: MImage) {
+        self.entries.push(TrashEntry { index, image });
+    }
+
+    /// 取出第 `pos` 条记录，返回应插回的当前索引和帧
+    ///
+    /// 在它之后删除的帧若位于其前方，插回位置前移；位于其后方的记录则后移一位。
+    pub fn take(&mut self, pos: usize) -> Option<(usize, MImage)> {
+        if pos >= self.entries.len() {
+            return None;
+        }
+
+        let entry = self.entries.remove(pos);
+        let mut index = entry.index;
+        for later in &mut self.entries[pos..] {
+            if later.index < index {
+                index -= 1;
+            } else {
+                later.index += 1;
+            }
+        }
+        Some((index, entry.image))
+    }
+
+    /// 取出最近删除的一帧
+    pub fn take_last(&mut self) -> Option<(usize, MImage)> {
+        self.take(self.entries.len().checked_sub(1)?)
+    }
+
+    /// 回收站中的记录
+    pub fn entries(&self) -> &[TrashEntry] {
+        &self.entries
+    }
+
+    /// 记录数
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// 是否为空
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// 清空回收站
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 模拟删除：从列表移除并记录到回收站
+    fn remove(frames: &mut Vec<i16>, trash: &mut Trash, index: usize) {
+        let mut image = MImage::new();
+        image.x = frames.remove(index);
+        trash.push(index, image);
+    }
+
+    #[test]
+    fn test_restore_out_of_order() {
+        let mut frames: Vec<i16> = (0..6).collect();
+        let mut trash = Trash::default();
+        remove(&mut frames, &mut trash, 3);
+        remove(&mut frames, &mut trash, 1);
+        remove(&mut frames, &mut trash, 2); // 原第 4 帧
+
+        // 先恢复最早删除的，再恢复其余的
+        for pos in [0, 1, 0] {
+            let (index, image) = trash.take(pos).unwrap();
+            frames.insert(index, image.x);
+        }
+        assert_eq!(frames, (0..6).collect::<Vec<_>>());
+        assert!(trash.take_last().is_none());
+    }
+}
@@ -0,0 +1,175 @@
+//! 监视文件变化
+//!
+//! 美术在外部工具中改完图像后，编辑器自动重新加载库，`--watch` 命令行自动重新导出或
+//! 打包。监视的是文件所在的目录而不是文件本身：很多工具保存时先写临时文件再改名替换，
+//! 直接监视文件会在第一次替换后失效。一次保存通常触发一串事件，最后一个事件之后
+//! 安静 [`SETTLE_TIME`] 才报告变化，避免读到写了一半的文件。
+
+use crate::error::Result;
+use crossbeam_channel::{Receiver, RecvTimeoutError, unbounded};
+use notify::event::{AccessKind, AccessMode, ModifyKind};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// 最后一个事件之后等待文件写完的时间
+pub const SETTLE_TIME: Duration = Duration::from_millis(300);
+
+/// 监视一个库文件或一个目录中文件的变化
+pub struct FileWatcher {
+    /// 保持监视（丢弃后停止）
+    _watcher: RecommendedWatcher,
+    events: Receiver<PathBuf>,
+    /// 只关心与此同名（不含扩展名、不区分大小写）的文件；为 `None` 时目录中的文件都算
+    base: Option<String>,
+    /// 尚未报告的变化
+    pending: BTreeSet<PathBuf>,
+    last_event: Option<Instant>,
+    /// 在此之前的变化忽略（自己保存时）
+    ignore_until: Option<Instant>,
+}
+
+impl FileWatcher {
+    /// 监视库文件，连同同名的索引文件（如 `.wix`、`.wzx`）
+    pub fn library(path: &Path) -> Result<Self> {
+        let dir = path.parent().unwrap_or(Path::new("."));
+        let base = base_name(path);
+        Self::watch(dir, base)
+    }
+
+    /// 监视目录中的文件（不含子目录）
+    pub fn directory(dir: &Path) -> Result<Self> {
+        Self::watch(dir, None)
+    }
+
+    fn watch(dir: &Path, base: Option<String>) -> Result<Self> {
+        let (sender, events) = unbounded();
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<Event>| match event {
+                Ok(event) if is_change(&event.kind) => {
+                    for path in event.paths {
+                        let _ = sender.send(path);
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => tracing::warn!("监视文件出错: {}", e),
+            })
+            .map_err(std::io::Error::other)?;
+        watcher
+            .watch(dir, RecursiveMode::NonRecursive)
+            .map_err(std::io::Error::other)?;
+        tracing::debug!("开始监视 {:?} (文件名 {:?})", dir, base);
+
+        Ok(Self {
+            _watcher: watcher,
+            events,
+            base,
+            pending: BTreeSet::new(),
+            last_event: None,
+            ignore_until: None,
+        })
+    }
+
+    /// 接下来 `duration` 内的变化不报告（自己写文件之前调用）
+    pub fn ignore_for(&mut self, duration: Duration) {
+        self.ignore_until = Some(Instant::now() + duration);
+        self.pending.clear();
+    }
+
+    /// 不阻塞地检查：有变化且已安静 [`SETTLE_TIME`] 时返回变化的文件
+    pub fn poll(&mut self) -> Option<Vec<PathBuf>> {
+        while let Ok(path) = self.events.try_recv() {
+            self.record(path);
+        }
+        self.take_settled()
+    }
+
+    /// 阻塞直到有变化，返回变化的文件
+    pub fn wait(&mut self) -> Vec<PathBuf> {
+        loop {
+            match self.events.recv_timeout(SETTLE_TIME) {
+                Ok(path) => self.record(path),
+                Err(RecvTimeoutError::Timeout) => {}
+                // 监视线程已结束，不会再有事件
+                Err(RecvTimeoutError::Disconnected) => return Vec::new(),
+            }
+            if let Some(changed) = self.take_settled() {
+                return changed;
+            }
+        }
+    }
+
+    fn record(&mut self, path: PathBuf) {
+        let now = Instant::now();
+        if self.ignore_until.is_some_and(|until| now < until) {
+            return;
+        }
+        if self.base.is_some() && base_name(&path) != self.base {
+            return;
+        }
+        self.pending.insert(path);
+        self.last_event = Some(now);
+    }
+
+    fn take_settled(&mut self) -> Option<Vec<PathBuf>> {
+        let settled = self
+            .last_event
+            .is_some_and(|last| last.elapsed() >= SETTLE_TIME);
+        if !settled || self.pending.is_empty() {
+            return None;
+        }
+        self.last_event = None;
+        Some(std::mem::take(&mut self.pending).into_iter().collect())
+    }
+}
+
+/// 写入、创建、删除和改名算作变化；打开、读取和只改元数据的不算
+fn is_change(kind: &EventKind) -> bool {
+    match kind {
+        EventKind::Create(_) | EventKind::Remove(_) => true,
+        EventKind::Modify(modify) => !matches!(modify, ModifyKind::Metadata(_)),
+        EventKind::Access(access) => matches!(access, AccessKind::Close(AccessMode::Write)),
+        _ => false,
+    }
+}
+
+/// 不含扩展名的文件名（小写）
+fn base_name(path: &Path) -> Option<String> {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_watch_library_files() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let data = dir.join("Hum.wzl");
+        std::fs::write(&data, b"v1").unwrap();
+
+        let mut watcher = FileWatcher::library(&data).unwrap();
+        assert!(watcher.poll().is_none());
+
+        // 其他文件的变化不报告
+        std::fs::write(dir.join("Other.wzl"), b"x").unwrap();
+        std::fs::write(dir.join("hum.WZX"), b"index").unwrap();
+        std::fs::write(&data, b"v2").unwrap();
+        let changed = watcher.wait();
+        assert!(changed.contains(&data));
+        assert!(
+            changed
+                .iter()
+                .all(|p| base_name(p).as_deref() == Some("hum"))
+        );
+
+        // 自己写入时忽略
+        watcher.ignore_for(Duration::from_secs(5));
+        std::fs::write(&data, b"v3").unwrap();
+        std::thread::sleep(SETTLE_TIME * 2);
+        assert!(watcher.poll().is_none());
+    }
+}
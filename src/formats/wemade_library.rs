@@ -4,15 +4,15 @@
 use crate::error::{LibraryError, Result};
 use crate::image::Color;
 use crate::formats::mlibrary_v2::MImage;
+use crate::formats::source::{ReadSeek, Source};
 use byteorder::{LittleEndian, ReadBytesExt};
-use std::fs::File;
-use std::io::{BufReader, Read, Seek, SeekFrom};
-use std::path::Path;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::PathBuf;
 
 /// WeMadLibrary - 用于处理 .wil/.wix 文件
 pub struct WeMadeLibrary {
-    /// 文件名（不带扩展名）
-    pub file_name: String,
+    /// 文件路径（不带扩展名）
+    pub file_name: PathBuf,
     /// 图像列表
     pub images: Vec<Option<WeMadeImage>>,
     /// 索引列表
@@ -27,6 +27,8 @@ pub struct WeMadeLibrary {
     palette: Vec<Color>,
     /// 版本号
     version: i32,
+    /// 读取索引和帧数据的来源
+    source: Source,
 }
 
 /// WeMade 图像结构
@@ -60,7 +62,13 @@ pub struct WeMadeImage {
 
 impl WeMadeLibrary {
     /// 创建新的 WeMadeLibrary 实例
-    pub fn new(file_name: String) -> Result<Self> {
+    pub fn new(file_name: PathBuf) -> Result<Self> {
+        let source = Source::Path(file_name.clone());
+        Self::from_source(file_name, source)
+    }
+
+    /// 从指定来源读取的实例
+    pub fn from_source(file_name: PathBuf, source: Source) -> Result<Self> {
         let mut library = Self {
             file_name,
             images: Vec::new(),
@@ -70,23 +78,28 @@ impl WeMadeLibrary {
             n_type: 0,
             palette: Vec::new(),
             version: 0,
+            source,
         };
 
         library.initialize()?;
         Ok(library)
     }
 
-    /// 初始化库
-    pub fn initialize(&mut self) -> Result<()> {
-        self.initialized = true;
-
-        let main_ext = if self.n_type == 1 {
+    /// 数据文件扩展名
+    fn main_extension(&self) -> &'static str {
+        if self.n_type == 1 {
             ".wzl"
         } else if self.n_type == 4 {
             ".miz"
         } else {
             ".wil"
-        };
+        }
+    }
+
+    /// 初始化库
+    pub fn initialize(&mut self) -> Result<()> {
+        self.initialized = true;
+
         let index_ext = if self.n_type == 1 {
             ".wzx"
         } else if self.n_type == 4 {
@@ -95,19 +108,14 @@ impl WeMadeLibrary {
             ".wix"
         };
 
-        let main_path = format!("{}{}", self.file_name, main_ext);
-        let index_path = format!("{}{}", self.file_name, index_ext);
-
-        if !Path::new(&index_path).exists() {
-            return Err(LibraryError::FileNotFound(index_path));
-        }
-
-        if !Path::new(&main_path).exists() {
-            return Err(LibraryError::FileNotFound(main_path));
+        for ext in [index_ext, self.main_extension()] {
+            if !self.source.exists(ext) {
+                return Err(LibraryError::FileNotFound(self.source.describe(ext)));
+            }
         }
 
         // 加载图像信息
-        self.load_image_info(&index_path)?;
+        self.load_image_info(index_ext)?;
 
         // 初始化图像列表
         self.images = vec![None; self.index_list.len()];
@@ -121,12 +129,11 @@ impl WeMadeLibrary {
     }
 
     /// 加载图像信息
-    fn load_image_info(&mut self, index_path: &str) -> Result<()> {
+    fn load_image_info(&mut self, index_ext: &str) -> Result<()> {
         // 设置默认调色板
         self.palette = crate::image::DEFAULT_PALETTE.to_vec();
 
-        let file = File::open(index_path)?;
-        let mut reader = BufReader::new(file);
+        let mut reader = self.source.open(index_ext)?;
 
         // 根据类型读取不同长度的头部
         match self.n_type {
@@ -178,29 +185,19 @@ impl WeMadeLibrary {
 
     /// 加载指定索引的图像
     fn load_image(&mut self, index: usize) -> Result<()> {
-        let main_ext = if self.n_type == 1 {
-            ".wzl"
-        } else if self.n_type == 4 {
-            ".miz"
-        } else {
-            ".wil"
-        };
-        let main_path = format!("{}{}", self.file_name, main_ext);
-
-        let file = File::open(&main_path)?;
-        let mut reader = BufReader::new(file);
+        let mut reader = self.source.open(self.main_extension())?;
 
         let offset = self.index_list[index] as u64;
         reader.seek(SeekFrom::Start(offset))?;
 
-        let image = self.read_wemade_image(&mut reader, offset)?;
+        let image = self.read_wemade_image(&mut *reader, offset)?;
         self.images[index] = Some(image);
 
         Ok(())
     }
 
     /// 读取 WeMade 图像
-    fn read_wemade_image(&self, reader: &mut BufReader<File>, offset: u64) -> Result<WeMadeImage> {
+    fn read_wemade_image(&self, reader: &mut dyn ReadSeek, offset: u64) -> Result<WeMadeImage> {
         reader.seek(SeekFrom::Start(offset))?;
 
         let mut image = WeMadeImage {
@@ -281,7 +278,7 @@ mod tests {
 
     #[test]
     fn test_create_library() {
-        let lib = WeMadeLibrary::new("test".to_string());
+        let lib = WeMadeLibrary::new(PathBuf::from("test"));
         assert!(lib.is_err()); // 文件不存在
     }
 }
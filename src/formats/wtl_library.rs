@@ -2,16 +2,23 @@
 //! 用于处理传奇2的 WTL 格式库文件
 
 use crate::error::{Result, LibraryError};
-use crate::image::MImage;
-use std::fs::File;
-use std::io::{BufReader, BufWriter, Read, Write, Seek, SeekFrom};
-use std::path::Path;
+use crate::formats::atomic_file::AtomicFile;
+use crate::formats::find_companion;
+use crate::formats::limits;
+use crate::formats::source::{ReadSeek, Source};
+use crate::formats::mlibrary_v1::MImage;
+use crate::image::rows::RowOrder;
+use std::io::{Read, Write, Seek, SeekFrom};
+use std::path::PathBuf;
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 
+/// WTL 像素行的存储顺序
+pub const ROW_ORDER: RowOrder = RowOrder::BottomUp;
+
 /// WTLLibrary - 用于处理 .wtl 文件
 pub struct WTLLibrary {
-    /// 文件名
-    pub file_name: String,
+    /// 文件路径（不带扩展名）
+    pub file_name: PathBuf,
     /// 图像列表
     pub images: Vec<Option<MImage>>,
     /// 索引列表
@@ -20,17 +27,26 @@ pub struct WTLLibrary {
     pub count: usize,
     /// 是否已初始化
     initialized: bool,
+    /// 读取索引和帧数据的来源（保存时总是写入 `file_name`）
+    source: Source,
 }
 
 impl WTLLibrary {
     /// 创建新的 WTLLibrary 实例
-    pub fn new(file_name: String) -> Result<Self> {
+    pub fn new(file_name: PathBuf) -> Result<Self> {
+        let source = Source::Path(file_name.clone());
+        Self::from_source(file_name, source)
+    }
+
+    /// 从指定来源读取的实例（如内存中的 `.wtl` 内容）
+    pub fn from_source(file_name: PathBuf, source: Source) -> Result<Self> {
         let mut library = Self {
             file_name,
             images: Vec::new(),
             index_list: Vec::new(),
             count: 0,
             initialized: false,
+            source,
         };
 
         library.initialize()?;
@@ -41,14 +57,8 @@ impl WTLLibrary {
     pub fn initialize(&mut self) -> Result<()> {
         self.initialized = true;
 
-        let wtl_path = format!("{}.wtl", self.file_name);
-
-        if !Path::new(&wtl_path).exists() {
-            return Err(LibraryError::FileNotFound(wtl_path));
-        }
-
         // WTL 文件结构与 WIL 类似
-        self.load_wtl_file(&wtl_path)?;
+        self.load_wtl_file()?;
 
         // 初始化图像列表
         self.images = vec![None; self.index_list.len()];
@@ -62,9 +72,8 @@ impl WTLLibrary {
     }
 
     /// 加载 WTL 文件
-    fn load_wtl_file(&mut self, path: &str) -> Result<()> {
-        let file = File::open(path)?;
-        let mut reader = BufReader::new(file);
+    fn load_wtl_file(&mut self) -> Result<()> {
+        let mut reader = self.source.open(".wtl")?;
 
         // 读取文件头
         let mut header = [0u8; 4];
@@ -76,7 +85,7 @@ impl WTLLibrary {
         }
 
         // 读取图像计数
-        self.count = reader.read_u32::<LittleEndian>()? as usize;
+        self.count = limits::check_count(reader.read_u32::<LittleEndian>()? as usize)?;
 
         // 读取所有索引
         self.index_list.clear();
@@ -107,21 +116,19 @@ impl WTLLibrary {
 
     /// 加载指定索引的图像
     fn load_image(&mut self, index: usize) -> Result<()> {
-        let wtl_path = format!("{}.wtl", self.file_name);
-        let file = File::open(&wtl_path)?;
-        let mut reader = BufReader::new(file);
+        let mut reader = self.source.open(".wtl")?;
 
         let offset = self.index_list[index] as u64;
         reader.seek(SeekFrom::Start(offset))?;
 
-        let image = self.read_wtl_image(&mut reader)?;
+        let image = self.read_wtl_image(&mut *reader)?;
         self.images[index] = Some(image);
 
         Ok(())
     }
 
     /// 读取 WTL 图像
-    fn read_wtl_image(&self, reader: &mut BufReader<File>) -> Result<MImage> {
+    fn read_wtl_image(&self, reader: &mut dyn ReadSeek) -> Result<MImage> {
         // 读取图像头部
         let width = reader.read_i16::<LittleEndian>()?;
         let height = reader.read_i16::<LittleEndian>()?;
@@ -136,11 +143,11 @@ impl WTLLibrary {
         image.y = y;
 
         if data_size > 0 {
-            let mut data = vec![0u8; data_size as usize];
+            let mut data = vec![0u8; limits::check_length(data_size as i64, "帧数据")?];
             reader.read_exact(&mut data)?;
 
             // WTL 格式通常使用某种压缩
-            image.create_texture(&data)?;
+            image.create_texture(&data, ROW_ORDER)?;
         }
 
         Ok(image)
@@ -189,10 +196,9 @@ impl WTLLibrary {
 
     /// 保存库文件
     pub fn save(&self) -> Result<()> {
-        let wtl_path = format!("{}.wtl", self.file_name);
+        let wtl_path = find_companion(&self.file_name, ".wtl");
 
-        let file = File::create(&wtl_path)?;
-        let mut writer = BufWriter::new(file);
+        let mut writer = AtomicFile::create(&wtl_path)?;
 
         // 写入文件头
         writer.write_all(b"WTL\x00")?;
@@ -211,7 +217,7 @@ impl WTLLibrary {
             current_offset += 256; // 预估大小
         }
 
-        writer.flush()?;
+        writer.commit()?;
         Ok(())
     }
 
@@ -227,7 +233,7 @@ mod tests {
 
     #[test]
     fn test_create_library() {
-        let lib = WTLLibrary::new("test".to_string());
+        let lib = WTLLibrary::new(PathBuf::from("test"));
         assert!(lib.is_err()); // 文件不存在
     }
 }
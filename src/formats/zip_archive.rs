@@ -0,0 +1,154 @@
+//! ZIP 交换格式 (export-zip / import-zip)
+//!
+//! 编辑器的标准往返格式：每一帧保存为独立的 PNG，帧属性写入 `manifest.json`
+//! （结构见 `metadata` 模块）。
+//! 任意可读格式都能导出为 ZIP，导入时重建为 MLibrary V2 (.Lib)。
+//!
+//! 归档结构：
+//! ```text
+//! manifest.json          清单（版本、来源格式、调色板、逐帧属性）
+//! frames/00000.png       帧图像（空帧不写入）
+//! masks/00000.png        遮罩图像（仅带遮罩的帧）
+//! ```
+
+use crate::error::{LibraryError, Result};
+use crate::formats::LibraryLoader;
+use crate::formats::metadata::Manifest;
+use crate::formats::mlibrary_v2::{EncodeOptions, MLibraryV2};
+use crate::image::chroma_key::ChromaKey;
+use image::{ImageFormat, RgbaImage};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Cursor, Read, Seek, Write};
+use std::path::Path;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+/// 清单文件名
+const MANIFEST_NAME: &str = "manifest.json";
+
+/// 将已加载的库导出为 ZIP 归档，返回写入的帧数
+pub fn export_zip(loader: &mut LibraryLoader, path: &Path) -> Result<usize> {
+    let mut writer = ZipWriter::new(BufWriter::new(File::create(path)?));
+    // PNG 本身已压缩，直接存储即可
+    let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+
+    let manifest = Manifest::collect(loader, "frames/", "masks/", |name, img| {
+        writer.start_file(name, stored)?;
+        writer.write_all(&encode_png(img)?)?;
+        Ok(())
+    })?;
+
+    writer.start_file(MANIFEST_NAME, SimpleFileOptions::default())?;
+    writer.write_all(&manifest.to_json()?)?;
+    writer.finish()?.flush()?;
+
+    tracing::info!("导出 ZIP: {:?} ({} 帧)", path, manifest.frames.len());
+    Ok(manifest.frames.len())
+}
+
+/// 读取 ZIP 归档的清单
+pub fn read_manifest(path: &Path) -> Result<Manifest> {
+    let mut archive = ZipArchive::new(BufReader::new(File::open(path)?))?;
+    load_manifest(&mut archive)
+}
+
+/// 从 ZIP 归档重建 MLibrary V2 库，`output` 为目标 .Lib 文件路径
+pub fn import_zip(
+    path: &Path,
+    output: &Path,
+    key: ChromaKey,
+    options: EncodeOptions,
+) -> Result<MLibraryV2> {
+    let mut archive = ZipArchive::new(BufReader::new(File::open(path)?))?;
+    let manifest = load_manifest(&mut archive)?;
+
+    let library = manifest.rebuild(output, options, |name| {
+        let mut image = read_png(&mut archive, name)?;
+        key.apply(&mut image);
+        Ok(image)
+    })?;
+    tracing::info!(
+        "导入 ZIP: {:?} -> {:?} ({} 帧, 来源格式 {})",
+        path,
+        output,
+        library.count(),
+        manifest.source_format
+    );
+    Ok(library)
+}
+
+/// 读取并校验清单
+fn load_manifest<R: Read + Seek>(archive: &mut ZipArchive<R>) -> Result<Manifest> {
+    Manifest::from_json(&read_entry(archive, MANIFEST_NAME)?)
+}
+
+/// 从归档中读取 PNG
+fn read_png<R: Read + Seek>(archive: &mut ZipArchive<R>, name: &str) -> Result<RgbaImage> {
+    let data = read_entry(archive, name)?;
+    Ok(image::load_from_memory_with_format(&data, ImageFormat::Png)?.to_rgba8())
+}
+
+/// 读取归档条目的全部内容
+fn read_entry<R: Read + Seek>(archive: &mut ZipArchive<R>, name: &str) -> Result<Vec<u8>> {
+    let mut data = Vec::new();
+    archive
+        .by_name(name)
+        .map_err(|_| LibraryError::FileNotFound(name.to_string()))?
+        .read_to_end(&mut data)?;
+    Ok(data)
+}
+
+/// 编码为 PNG 字节
+fn encode_png(img: &RgbaImage) -> Result<Vec<u8>> {
+    let mut data = Cursor::new(Vec::new());
+    img.write_to(&mut data, ImageFormat::Png)?;
+    Ok(data.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::mlibrary_v2::MImage;
+    use image::Rgba;
+
+    #[test]
+    fn test_zip_roundtrip_through_v2() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+
+        // 先构造一个带空帧的 V2 库
+        let base = dir.join("src");
+        let mut source = MLibraryV2::new(base.clone()).unwrap();
+        let frame = RgbaImage::from_pixel(4, 3, Rgba([200, 10, 10, 255]));
+        let mut image = MImage::from_image(&frame, -5, 7);
+        image.shadow = 3;
+        image.shadow_x = 2;
+        source.add_image(&image);
+        source.add_image(&MImage::new());
+        source.save().unwrap();
+
+        let (_, mut loader) = LibraryLoader::load(&dir.join("src.Lib")).unwrap();
+        let zip_path = dir.join("out.zip");
+        assert_eq!(export_zip(&mut loader, &zip_path).unwrap(), 2);
+
+        let manifest = read_manifest(&zip_path).unwrap();
+        assert_eq!(
+            manifest.frames[0].image.as_deref(),
+            Some("frames/00000.png")
+        );
+        assert_eq!(manifest.frames[0].shadow, 3);
+        assert!(manifest.frames[1].image.is_none());
+
+        let mut rebuilt = import_zip(
+            &zip_path,
+            &dir.join("rebuilt.Lib"),
+            ChromaKey::Black,
+            EncodeOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(rebuilt.count(), 2);
+        let first = rebuilt.get_image(0).unwrap();
+        assert_eq!((first.width, first.height, first.x, first.y), (4, 3, -5, 7));
+        assert_eq!((first.shadow, first.shadow_x), (3, 2));
+    }
+}
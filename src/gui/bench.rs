@@ -0,0 +1,139 @@
+//! 缩略图画廊性能基准（隐藏的 `--bench-gui` 模式）
+//!
+//! 载入合成的 10 万帧库，用定时器按固定步长自动切换选中帧，使缩略图列表
+//! 持续滚动并按需加载缩略图。每次定时器触发时记录与上一次的间隔，
+//! 界面渲染或缩略图加载变慢时间隔随之变大，可作为近似的帧时间。
+//! 结束后把统计结果写入日志并退出事件循环。
+
+use super::{AppWindow, select_frame};
+use crate::error::Result;
+use crate::formats::LibraryLoader;
+use crate::formats::mlibrary_v2::{MImage, MLibraryV2};
+use image::{Rgba, RgbaImage};
+use slint::ComponentHandle;
+use std::cell::RefCell;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// 合成库的帧数
+pub const BENCH_FRAMES: usize = 100_000;
+
+/// 采样次数（每次切换一帧）
+const BENCH_STEPS: usize = 600;
+
+/// 每次切换跨越的帧数（与常见列数互质，滚动覆盖不同的行）
+const BENCH_STRIDE: usize = 173;
+
+/// 定时器间隔（约 60 FPS）
+const TICK: Duration = Duration::from_millis(16);
+
+/// 超过此时长的间隔计为卡顿
+const JANK_THRESHOLD: Duration = Duration::from_millis(33);
+
+/// 生成合成库：少量不同尺寸、颜色的模板帧循环排列
+///
+/// 只保留压缩数据，解码后的图像在访问时才生成，与真实库的加载路径一致。
+pub fn synthetic_library(count: usize) -> Result<MLibraryV2> {
+    let templates: Vec<MImage> = (0..16u32)
+        .map(|i| {
+            let (w, h) = (16 + i * 3, 24 + (i * 5) % 40);
+            let color = Rgba([(i * 16) as u8, 200, (i * 40) as u8, 255]);
+            let mut image = MImage::from_image(&RgbaImage::from_pixel(w, h, color), 0, 0);
+            image.release_texture();
+            image
+        })
+        .collect();
+
+    let mut library = MLibraryV2::new(PathBuf::from("bench-gui-synthetic"))?;
+    for index in 0..count {
+        library.add_image(&templates[index % templates.len()]);
+    }
+    Ok(library)
+}
+
+/// 帧间隔统计
+#[derive(Debug, Default)]
+struct FrameTimes {
+    samples: Vec<Duration>,
+    /// 选中帧时更新预览所用时间
+    select: Vec<Duration>,
+}
+
+impl FrameTimes {
+    /// 输出统计结果
+    fn report(&mut self) {
+        if self.samples.is_empty() {
+            tracing::warn!("基准测试没有采样");
+            return;
+        }
+
+        self.samples.sort();
+        let total: Duration = self.samples.iter().sum();
+        let percentile = |p: usize| self.samples[(self.samples.len() - 1) * p / 100];
+        let jank = self.samples.iter().filter(|d| **d > JANK_THRESHOLD).count();
+        let select_avg = self.select.iter().sum::<Duration>() / self.select.len().max(1) as u32;
+
+        tracing::info!("GUI 基准测试完成: {} 次采样", self.samples.len());
+        tracing::info!(
+            "  帧间隔: 平均 {:.2?}, 中位 {:.2?}, P95 {:.2?}, 最大 {:.2?}",
+            total / self.samples.len() as u32,
+            percentile(50),
+            percentile(95),
+            self.samples[self.samples.len() - 1]
+        );
+        tracing::info!("  卡顿 (>{:?}): {} 次", JANK_THRESHOLD, jank);
+        tracing::info!("  选中帧更新: 平均 {:.2?}", select_avg);
+    }
+}
+
+/// 载入合成库并启动自动滚动，结束后退出事件循环
+///
+/// `show` 负责把加载器显示到界面（与打开文件共用同一流程）。
+pub fn start(
+    window: &AppWindow,
+    library_loader: &Arc<Mutex<Option<LibraryLoader>>>,
+    timer: &slint::Timer,
+    show: impl FnOnce(&AppWindow, crate::formats::LibraryInfo, LibraryLoader),
+) -> Result<()> {
+    let started = Instant::now();
+    let library = synthetic_library(BENCH_FRAMES)?;
+    let (info, loader) = LibraryLoader::from_v2(library, "bench-gui (合成)");
+    tracing::info!("合成 {} 帧用时 {:.2?}", BENCH_FRAMES, started.elapsed());
+    show(window, info, loader);
+
+    let window_weak = window.as_weak();
+    let library_loader = library_loader.clone();
+    let times = RefCell::new(FrameTimes::default());
+    let last_tick = RefCell::new(Instant::now());
+    let step = RefCell::new(0usize);
+
+    timer.start(slint::TimerMode::Repeated, TICK, move || {
+        let Some(window) = window_weak.upgrade() else {
+            return;
+        };
+
+        let now = Instant::now();
+        let interval = now - last_tick.replace(now);
+        let mut step = step.borrow_mut();
+        // 第一次触发的间隔包含启动时间，不计入
+        if *step > 0 {
+            times.borrow_mut().samples.push(interval);
+        }
+
+        if *step >= BENCH_STEPS {
+            times.borrow_mut().report();
+            let _ = slint::quit_event_loop();
+            return;
+        }
+        *step += 1;
+
+        let index = (*step * BENCH_STRIDE) % BENCH_FRAMES;
+        if let Some(ref mut loader) = *library_loader.lock().unwrap() {
+            let select_start = Instant::now();
+            select_frame(&window, loader, index);
+            times.borrow_mut().select.push(select_start.elapsed());
+        }
+    });
+    Ok(())
+}
@@ -0,0 +1,209 @@
+//! 书签
+//!
+//! 书签保存在库文件旁的 `<库名>.bookmarks.json` 中（见 [`crate::formats::bookmarks`]），
+//! 侧栏列出当前库的书签。库在已打开的项目中时，修改同时写入项目文件；打开库时加入项目中
+//! 记录而书签文件中没有的书签。
+
+use super::{AppState, AppWindow, Result, select_frame};
+use crate::formats::LibraryLoader;
+use crate::formats::project::Project;
+use crate::i18n::tr;
+use slint::{ComponentHandle, SharedString};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// 在侧栏列出当前库的书签
+pub fn write(window: &AppWindow, loader: &LibraryLoader) {
+    let bookmarks = loader.bookmarks();
+    let frames: Vec<i32> = bookmarks.iter().map(|b| b.index as i32).collect();
+    let labels: Vec<SharedString> = bookmarks
+        .iter()
+        .map(|b| SharedString::from(&b.label))
+        .collect();
+    window.set_bookmark_frames(slint::ModelRc::new(slint::VecModel::from(frames)));
+    window.set_bookmark_labels(slint::ModelRc::new(slint::VecModel::from(labels)));
+}
+
+/// 修改当前库的书签，刷新侧栏并同步到已打开的项目（库在项目中时），结果显示在状态栏
+fn edit(
+    window: &AppWindow,
+    library_loader: &Mutex<Option<LibraryLoader>>,
+    project: &Mutex<Option<(PathBuf, Project)>>,
+    change: impl FnOnce(&mut LibraryLoader) -> Result<String>,
+) {
+    let mut guard = library_loader.lock().unwrap();
+    let Some(ref mut loader) = *guard else {
+        return;
+    };
+    let result = change(loader).and_then(|message| {
+        write(window, loader);
+        let library = loader.info().map(|info| info.path());
+        if let (Some((path, project)), Some(library)) = (project.lock().unwrap().as_mut(), library)
+            && project.library(&library).is_some()
+        {
+            project.library_mut(&library).bookmarks = loader.bookmarks().clone();
+            project.save(path)?;
+        }
+        Ok(message)
+    });
+    let status = match result {
+        Ok(message) => message,
+        Err(e) => {
+            tracing::error!("修改书签失败: {:?}", e);
+            tr!("修改书签失败: {}", e)
+        }
+    };
+    window.set_status_text(SharedString::from(status));
+}
+
+/// 刚打开的库在已打开的项目中时，加入项目中记录而书签文件中没有的书签
+pub fn merge_project(
+    window: &AppWindow,
+    library_loader: &Mutex<Option<LibraryLoader>>,
+    project: &Mutex<Option<(PathBuf, Project)>>,
+) {
+    let mut guard = library_loader.lock().unwrap();
+    let Some(ref mut loader) = *guard else {
+        return;
+    };
+    let Some(library) = loader.info().map(|info| info.path()) else {
+        return;
+    };
+    let project = project.lock().unwrap();
+    let Some(bookmarks) = project
+        .as_ref()
+        .and_then(|(_, project)| project.library(&library))
+        .map(|library| &library.bookmarks)
+    else {
+        return;
+    };
+    match loader.merge_bookmarks(bookmarks) {
+        Ok(0) => {}
+        Ok(added) => {
+            tracing::debug!("从项目加入 {} 个书签", added);
+            write(window, loader);
+        }
+        Err(e) => tracing::warn!("写入书签失败: {}", e),
+    }
+}
+
+/// 设置书签回调：添加或更新、Ctrl+B 添加或删除、删除侧栏中的书签，以及跳转到书签
+pub fn install(window: &AppWindow, state: &AppState) {
+    {
+        let window_weak = window.as_weak();
+        let library_loader = state.library_loader.clone();
+        let project = state.project.clone();
+
+        window.on_add_bookmark(move || {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            let Ok(index) = usize::try_from(window.get_current_index()) else {
+                window.set_status_text(SharedString::from(tr!("请先选择一张图像")));
+                return;
+            };
+            let label = window.get_bookmark_label().trim().to_string();
+            edit(&window, &library_loader, &project, |loader| {
+                let existed = loader.bookmarks().get(index).is_some();
+                loader.set_bookmark(index, &label)?;
+                window.set_bookmark_label(SharedString::new());
+                Ok(if existed {
+                    tr!("已更新第 {} 帧的书签", index)
+                } else {
+                    tr!("已添加书签: 第 {} 帧", index)
+                })
+            });
+        });
+    }
+    {
+        let window_weak = window.as_weak();
+        let library_loader = state.library_loader.clone();
+        let project = state.project.clone();
+
+        window.on_toggle_bookmark(move || {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            let Ok(index) = usize::try_from(window.get_current_index()) else {
+                window.set_status_text(SharedString::from(tr!("请先选择一张图像")));
+                return;
+            };
+            let label = window.get_bookmark_label().trim().to_string();
+            edit(&window, &library_loader, &project, |loader| {
+                if loader.remove_bookmark(index)? {
+                    return Ok(tr!("已删除第 {} 帧的书签", index));
+                }
+                loader.set_bookmark(index, &label)?;
+                window.set_bookmark_label(SharedString::new());
+                Ok(tr!("已添加书签: 第 {} 帧", index))
+            });
+        });
+    }
+    {
+        let window_weak = window.as_weak();
+        let library_loader = state.library_loader.clone();
+        let project = state.project.clone();
+
+        window.on_remove_bookmark(move |index| {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            let Ok(index) = usize::try_from(index) else {
+                return;
+            };
+            edit(&window, &library_loader, &project, |loader| {
+                loader.remove_bookmark(index)?;
+                Ok(tr!("已删除第 {} 帧的书签", index))
+            });
+        });
+    }
+
+    // 设置书签跳转回调：跳到侧栏中点击的书签，或当前帧之后 (1) / 之前 (-1) 的书签
+    {
+        let window_weak = window.as_weak();
+        let library_loader = state.library_loader.clone();
+
+        window.on_goto_bookmark(move |index| {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            let Ok(index) = usize::try_from(index) else {
+                return;
+            };
+            if let Some(ref mut loader) = *library_loader.lock().unwrap()
+                && index < loader.image_count()
+            {
+                select_frame(&window, loader, index);
+            }
+        });
+    }
+    {
+        let window_weak = window.as_weak();
+        let library_loader = state.library_loader.clone();
+
+        window.on_step_bookmark(move |direction| {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            let Some(ref mut loader) = *library_loader.lock().unwrap() else {
+                return;
+            };
+            let current = window.get_current_index().max(0) as usize;
+            let bookmark = if direction < 0 {
+                loader.bookmarks().previous(current)
+            } else {
+                loader.bookmarks().next(current)
+            };
+            let Some(bookmark) = bookmark.cloned() else {
+                window.set_status_text(SharedString::from(tr!("没有书签")));
+                return;
+            };
+            select_frame(&window, loader, bookmark.index);
+            window.set_status_text(SharedString::from(&tr!(
+                "书签: 第 {} 帧 {}",
+                bookmark.index,
+                bookmark.label
+            )));
+        });
+    }
+}
@@ -0,0 +1,137 @@
+//! 命令面板
+//!
+//! Ctrl+P 打开，按关键字模糊查找 [`crate::actions`] 中的编辑器操作，输入帧号（可带 `#`）
+//! 时第一行为跳转到该帧。执行时调用界面的 `run_action`，与工具栏按钮走同一条路径；
+//! 每行旁边显示操作当前的快捷键。
+
+use super::keymap::{Action, Keymap};
+use super::{AppSettings, AppWindow};
+use crate::actions::{self, EditorAction};
+use crate::i18n::tr;
+use slint::{ComponentHandle, SharedString};
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+
+/// 命令面板最多列出的行数
+const MAX_ROWS: usize = 12;
+
+/// 命令面板中的一行
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PaletteRow {
+    /// 执行编辑器操作
+    Action(EditorAction),
+    /// 跳转到输入的帧号
+    Goto(usize),
+}
+
+/// 按关键字列出命令面板的行，输入帧号（可带 `#`）时第一行为跳转到该帧
+fn rows(query: &str) -> Vec<PaletteRow> {
+    let goto = query
+        .trim()
+        .trim_start_matches('#')
+        .parse::<usize>()
+        .ok()
+        .map(PaletteRow::Goto);
+    goto.into_iter()
+        .chain(actions::search(query).into_iter().map(PaletteRow::Action))
+        .take(MAX_ROWS)
+        .collect()
+}
+
+/// 操作的快捷键（可修改的按快捷键设置，编辑快捷键固定），没有时为空
+fn hint(action: EditorAction, keymap: &Keymap) -> String {
+    let bound = match action {
+        EditorAction::OpenFile => Action::OpenFile,
+        EditorAction::SaveFile => Action::SaveFile,
+        EditorAction::ExportPng => Action::ExportPng,
+        EditorAction::PlayAnimation => Action::PlayAnimation,
+        EditorAction::Shortcuts => Action::ShowShortcuts,
+        EditorAction::ToggleBookmark => Action::ToggleBookmark,
+        EditorAction::NextBookmark => Action::NextBookmark,
+        EditorAction::PreviousBookmark => Action::PreviousBookmark,
+        EditorAction::GotoFrame => return "Ctrl+G".to_string(),
+        EditorAction::CopyFrame => return "Ctrl+C".to_string(),
+        EditorAction::CopyImage => return "Ctrl+Shift+C".to_string(),
+        EditorAction::PasteFrame => return "Ctrl+V".to_string(),
+        EditorAction::ToggleLock => return "Ctrl+L".to_string(),
+        EditorAction::UndoDelete => return "Ctrl+Z".to_string(),
+        EditorAction::DeleteFrame => return "Delete".to_string(),
+        _ => return String::new(),
+    };
+    keymap.keys(bound)
+}
+
+/// 在命令面板中列出各行的名称和快捷键
+fn show_rows(window: &AppWindow, rows: &[PaletteRow], keymap: &Keymap) {
+    let strings = |items: Vec<String>| {
+        let items: Vec<SharedString> = items.into_iter().map(SharedString::from).collect();
+        slint::ModelRc::new(slint::VecModel::from(items))
+    };
+    window.set_palette_labels(strings(
+        rows.iter()
+            .map(|row| match row {
+                PaletteRow::Action(action) => action.label().to_string(),
+                PaletteRow::Goto(index) => tr!("跳转到 #{}", index),
+            })
+            .collect(),
+    ));
+    window.set_palette_hints(strings(
+        rows.iter()
+            .map(|row| match row {
+                PaletteRow::Action(action) => hint(*action, keymap),
+                PaletteRow::Goto(_) => String::new(),
+            })
+            .collect(),
+    ));
+}
+
+/// 设置命令面板回调：查找时记下列出的行，执行时按行号取回
+pub fn install(window: &AppWindow, settings: Arc<AppSettings>) {
+    let found = Rc::new(Mutex::new(Vec::new()));
+    {
+        let window_weak = window.as_weak();
+        let found = found.clone();
+
+        window.on_palette_search(move |query| {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            let rows = rows(&query);
+            show_rows(&window, &rows, &settings.keymap());
+            *found.lock().unwrap() = rows;
+        });
+    }
+    let window_weak = window.as_weak();
+
+    window.on_palette_run(move |row| {
+        let Some(window) = window_weak.upgrade() else {
+            return;
+        };
+        let row = usize::try_from(row)
+            .ok()
+            .and_then(|row| found.lock().unwrap().get(row).copied());
+        match row {
+            Some(PaletteRow::Action(action)) => {
+                tracing::debug!("命令面板: {}", action.id());
+                window.invoke_run_action(SharedString::from(action.id()));
+            }
+            Some(PaletteRow::Goto(index)) => {
+                window.invoke_goto_frame(SharedString::from(index.to_string()));
+            }
+            None => {}
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rows_goto_first() {
+        let found = rows("#12");
+        assert_eq!(found.first(), Some(&PaletteRow::Goto(12)));
+        assert!(found.len() <= MAX_ROWS);
+        assert!(!rows("").contains(&PaletteRow::Goto(0)));
+    }
+}
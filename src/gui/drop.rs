@@ -0,0 +1,103 @@
+//! 拖放文件
+//!
+//! .slint 界面本身收不到系统拖放，这里通过 winit 窗口事件接收拖入的文件。
+//! winit 对一次拖放中的每个文件各发送一个事件，且不带落点坐标：
+//! 先收集同一批文件，在当前这轮事件处理完后统一交给处理函数；
+//! 落点取最近一次光标移动的位置（拖放过程中部分平台不更新光标，可能为空）。
+//!
+//! winit 窗口事件只能注册一个处理函数，缩放变化也在这里转交给 [`super::scale`]。
+
+use super::{AppWindow, scale};
+use slint::ComponentHandle;
+use slint::winit_030::winit::event::WindowEvent;
+use slint::winit_030::{EventResult, WinitWindowAccessor};
+use std::cell::{Cell, RefCell};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::Duration;
+
+/// 一次拖放的文件
+#[derive(Debug, Default)]
+pub struct DroppedFiles {
+    /// 库文件（及其他非 PNG 文件，按库文件尝试打开）
+    pub libraries: Vec<PathBuf>,
+    /// PNG 图像（按文件名排序）
+    pub images: Vec<PathBuf>,
+}
+
+impl DroppedFiles {
+    /// 按扩展名分类
+    fn classify(files: Vec<PathBuf>) -> Self {
+        let (mut images, libraries): (Vec<_>, Vec<_>) =
+            files.into_iter().partition(|path| is_png(path));
+        images.sort();
+        Self { libraries, images }
+    }
+}
+
+/// 是否为 PNG 文件（扩展名不区分大小写）
+fn is_png(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("png"))
+}
+
+/// 注册拖放处理
+///
+/// `handler` 收到一次拖放的全部文件和落点（窗口内的逻辑坐标）。
+pub fn install(
+    window: &AppWindow,
+    handler: impl Fn(&AppWindow, DroppedFiles, Option<slint::LogicalPosition>) + 'static,
+) {
+    let window_weak = window.as_weak();
+    let handler = Rc::new(handler);
+    let pending: Rc<RefCell<Vec<PathBuf>>> = Rc::default();
+    let cursor: Rc<Cell<Option<slint::LogicalPosition>>> = Rc::default();
+
+    window
+        .window()
+        .on_winit_window_event(move |slint_window, event| {
+            scale::window_event(&window_weak);
+            match event {
+                WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                    scale::system_scale_changed(window_weak.clone(), *scale_factor);
+                    return EventResult::PreventDefault;
+                }
+                WindowEvent::CursorMoved { position, .. } => {
+                    let physical =
+                        slint::PhysicalPosition::new(position.x as i32, position.y as i32);
+                    cursor.set(Some(physical.to_logical(slint_window.scale_factor())));
+                }
+                WindowEvent::HoveredFile(_) => {
+                    if let Some(window) = window_weak.upgrade() {
+                        window.set_drop_active(true);
+                    }
+                }
+                WindowEvent::HoveredFileCancelled => {
+                    if let Some(window) = window_weak.upgrade() {
+                        window.set_drop_active(false);
+                    }
+                }
+                WindowEvent::DroppedFile(path) => {
+                    let first = pending.borrow().is_empty();
+                    pending.borrow_mut().push(path.clone());
+                    if first {
+                        let window_weak = window_weak.clone();
+                        let handler = handler.clone();
+                        let pending = pending.clone();
+                        let cursor = cursor.clone();
+                        slint::Timer::single_shot(Duration::ZERO, move || {
+                            let files = pending.take();
+                            let Some(window) = window_weak.upgrade() else {
+                                return;
+                            };
+                            window.set_drop_active(false);
+                            tracing::debug!("拖入 {} 个文件: {:?}", files.len(), files);
+                            handler(&window, DroppedFiles::classify(files), cursor.get());
+                        });
+                    }
+                }
+                _ => {}
+            }
+            EventResult::Propagate
+        });
+}
@@ -0,0 +1,458 @@
+//! 快捷键
+//!
+//! 打开、保存、导出、帧导航、缩放、播放动画和书签等操作的快捷键可以在快捷键对话框中修改，
+//! 偏好设置只记录与默认值不同的绑定。复制、粘贴、锁定、撤销删除、跳转和删除等编辑
+//! 快捷键由界面直接处理，不能修改，也不能分配给其他操作。
+//!
+//! 快捷键以文字表示，如 `Ctrl+S`、`Shift+F3`、`PageDown`，一个操作的多个快捷键用
+//! 逗号分隔，逗号键本身写作 `Comma`。
+
+use crate::i18n::tr;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+/// 可以分配快捷键的操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    /// 打开文件
+    OpenFile,
+    /// 保存文件
+    SaveFile,
+    /// 导出当前帧为 PNG
+    ExportPng,
+    /// 上一帧
+    PreviousFrame,
+    /// 下一帧
+    NextFrame,
+    /// 缩略图网格中的上一行
+    PreviousRow,
+    /// 缩略图网格中的下一行
+    NextRow,
+    /// 第一帧
+    FirstFrame,
+    /// 最后一帧
+    LastFrame,
+    /// 向前翻一页
+    PageUp,
+    /// 向后翻一页
+    PageDown,
+    /// 向前翻一大页
+    LargePageUp,
+    /// 向后翻一大页
+    LargePageDown,
+    /// 放大预览
+    ZoomIn,
+    /// 缩小预览
+    ZoomOut,
+    /// 预览恢复 100%
+    ZoomReset,
+    /// 播放或停止动画
+    PlayAnimation,
+    /// 添加或删除当前帧的书签
+    ToggleBookmark,
+    /// 下一个书签
+    NextBookmark,
+    /// 上一个书签
+    PreviousBookmark,
+    /// 打开快捷键对话框
+    ShowShortcuts,
+    /// 打开命令面板
+    CommandPalette,
+}
+
+impl Action {
+    /// 全部操作（快捷键对话框中的顺序）
+    pub const ALL: [Action; 22] = [
+        Action::OpenFile,
+        Action::SaveFile,
+        Action::ExportPng,
+        Action::PreviousFrame,
+        Action::NextFrame,
+        Action::PreviousRow,
+        Action::NextRow,
+        Action::FirstFrame,
+        Action::LastFrame,
+        Action::PageUp,
+        Action::PageDown,
+        Action::LargePageUp,
+        Action::LargePageDown,
+        Action::ZoomIn,
+        Action::ZoomOut,
+        Action::ZoomReset,
+        Action::PlayAnimation,
+        Action::ToggleBookmark,
+        Action::NextBookmark,
+        Action::PreviousBookmark,
+        Action::ShowShortcuts,
+        Action::CommandPalette,
+    ];
+
+    /// 快捷键对话框中显示的名称
+    pub fn label(self) -> &'static str {
+        match self {
+            Action::OpenFile => tr!("打开文件"),
+            Action::SaveFile => tr!("保存文件"),
+            Action::ExportPng => tr!("导出图像"),
+            Action::PreviousFrame => tr!("上一张图像"),
+            Action::NextFrame => tr!("下一张图像"),
+            Action::PreviousRow => tr!("上一行"),
+            Action::NextRow => tr!("下一行"),
+            Action::FirstFrame => tr!("第一帧"),
+            Action::LastFrame => tr!("最后一帧"),
+            Action::PageUp => tr!("向前翻 10 帧"),
+            Action::PageDown => tr!("向后翻 10 帧"),
+            Action::LargePageUp => tr!("向前翻 100 帧"),
+            Action::LargePageDown => tr!("向后翻 100 帧"),
+            Action::ZoomIn => tr!("放大"),
+            Action::ZoomOut => tr!("缩小"),
+            Action::ZoomReset => tr!("恢复 100% 缩放"),
+            Action::PlayAnimation => tr!("播放 / 停止动画"),
+            Action::ToggleBookmark => tr!("添加 / 删除书签"),
+            Action::NextBookmark => tr!("下一个书签"),
+            Action::PreviousBookmark => tr!("上一个书签"),
+            Action::ShowShortcuts => tr!("快捷键"),
+            Action::CommandPalette => tr!("命令面板"),
+        }
+    }
+
+    /// 默认快捷键
+    pub fn default_keys(self) -> &'static str {
+        match self {
+            Action::OpenFile => "Ctrl+O",
+            Action::SaveFile => "Ctrl+S",
+            Action::ExportPng => "Ctrl+E",
+            Action::PreviousFrame => "Left",
+            Action::NextFrame => "Right",
+            Action::PreviousRow => "Up",
+            Action::NextRow => "Down",
+            Action::FirstFrame => "Home",
+            Action::LastFrame => "End",
+            Action::PageUp => "PageUp",
+            Action::PageDown => "PageDown",
+            Action::LargePageUp => "Ctrl+PageUp",
+            Action::LargePageDown => "Ctrl+PageDown",
+            Action::ZoomIn => "Ctrl+=, +",
+            Action::ZoomOut => "Ctrl+-, -",
+            Action::ZoomReset => "Ctrl+0",
+            Action::PlayAnimation => "Space",
+            Action::ToggleBookmark => "Ctrl+B",
+            Action::NextBookmark => "F2",
+            Action::PreviousBookmark => "Shift+F2",
+            Action::ShowShortcuts => "F1",
+            Action::CommandPalette => "Ctrl+P",
+        }
+    }
+
+    /// 是否为帧导航（按住时按设置的间隔节流）
+    pub fn is_navigation(self) -> bool {
+        matches!(
+            self,
+            Action::PreviousFrame
+                | Action::NextFrame
+                | Action::PreviousRow
+                | Action::NextRow
+                | Action::FirstFrame
+                | Action::LastFrame
+                | Action::PageUp
+                | Action::PageDown
+                | Action::LargePageUp
+                | Action::LargePageDown
+        )
+    }
+}
+
+/// 由界面直接处理、不能修改的快捷键及说明
+pub fn fixed_shortcuts() -> [(&'static str, &'static str); 9] {
+    [
+        ("Ctrl+C", tr!("复制帧")),
+        ("Ctrl+Shift+C", tr!("复制图像到系统剪贴板")),
+        ("Ctrl+V", tr!("粘贴帧到当前位置")),
+        ("Ctrl+Shift+V", tr!("粘贴帧到末尾")),
+        ("Ctrl+L", tr!("锁定 / 解锁帧")),
+        ("Ctrl+Z", tr!("撤销删除")),
+        ("Ctrl+G", tr!("跳转到帧")),
+        ("Delete", tr!("删除图像")),
+        ("Escape", tr!("关闭对话框 / 中止打开")),
+    ]
+}
+
+/// 有名称的按键（Slint 按键事件中的字符）
+const NAMED_KEYS: [(&str, char); 28] = [
+    ("Space", ' '),
+    ("Comma", ','),
+    ("Tab", '\t'),
+    ("Enter", '\n'),
+    ("Escape", '\u{1b}'),
+    ("Backspace", '\u{8}'),
+    ("Delete", '\u{7f}'),
+    ("Insert", '\u{F727}'),
+    ("Home", '\u{F729}'),
+    ("End", '\u{F72B}'),
+    ("PageUp", '\u{F72C}'),
+    ("PageDown", '\u{F72D}'),
+    ("Up", '\u{F700}'),
+    ("Down", '\u{F701}'),
+    ("Left", '\u{F702}'),
+    ("Right", '\u{F703}'),
+    ("F1", '\u{F704}'),
+    ("F2", '\u{F705}'),
+    ("F3", '\u{F706}'),
+    ("F4", '\u{F707}'),
+    ("F5", '\u{F708}'),
+    ("F6", '\u{F709}'),
+    ("F7", '\u{F70A}'),
+    ("F8", '\u{F70B}'),
+    ("F9", '\u{F70C}'),
+    ("F10", '\u{F70D}'),
+    ("F11", '\u{F70E}'),
+    ("F12", '\u{F70F}'),
+];
+
+/// 一个快捷键：修饰键加一个按键
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Shortcut {
+    control: bool,
+    shift: bool,
+    alt: bool,
+    /// 按键字符，字母统一为大写
+    key: char,
+}
+
+impl Shortcut {
+    /// 规范化：字母取大写；标点和数字由 Shift 决定输入哪个字符，Shift 本身不参与匹配
+    fn new(key: char, control: bool, shift: bool, alt: bool) -> Self {
+        let named = NAMED_KEYS.iter().any(|(_, c)| *c == key);
+        let shift = shift && (named || key.is_alphabetic());
+        Self {
+            control,
+            shift,
+            alt,
+            key: key.to_uppercase().next().unwrap_or(key),
+        }
+    }
+
+    /// 按键事件对应的快捷键，单独按下修饰键或没有字符时返回 `None`
+    pub fn from_event(text: &str, control: bool, shift: bool, alt: bool) -> Option<Self> {
+        let key = text.chars().next()?;
+        // Shift、Ctrl、Alt、Meta 等修饰键本身
+        if ('\u{10}'..='\u{18}').contains(&key) {
+            return None;
+        }
+        Some(Self::new(key, control, shift, alt))
+    }
+
+    /// 解析 `Ctrl+Shift+S` 形式的快捷键（不区分大小写）
+    pub fn parse(text: &str) -> Option<Self> {
+        let (mut control, mut shift, mut alt) = (false, false, false);
+        let mut rest = text.trim();
+        while let Some((modifier, tail)) = rest.split_once('+')
+            && !tail.is_empty()
+        {
+            match modifier.trim().to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => control = true,
+                "shift" => shift = true,
+                "alt" => alt = true,
+                _ => return None,
+            }
+            rest = tail;
+        }
+        let name = rest.trim();
+        let key = match NAMED_KEYS
+            .iter()
+            .find(|(key_name, _)| key_name.eq_ignore_ascii_case(name))
+        {
+            Some((_, key)) => *key,
+            None => {
+                let mut chars = name.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(key), None) if !key.is_control() && !key.is_whitespace() => key,
+                    _ => return None,
+                }
+            }
+        };
+        Some(Self::new(key, control, shift, alt))
+    }
+}
+
+impl fmt::Display for Shortcut {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.control {
+            f.write_str("Ctrl+")?;
+        }
+        if self.shift {
+            f.write_str("Shift+")?;
+        }
+        if self.alt {
+            f.write_str("Alt+")?;
+        }
+        match NAMED_KEYS.iter().find(|(_, key)| *key == self.key) {
+            Some((name, _)) => f.write_str(name),
+            None => write!(f, "{}", self.key),
+        }
+    }
+}
+
+/// 解析逗号分隔的多个快捷键，空文字表示不分配快捷键
+pub fn parse_keys(text: &str) -> Result<Vec<Shortcut>, String> {
+    text.split(',')
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .map(|part| Shortcut::parse(part).ok_or_else(|| tr!("无法识别的快捷键: {}", part)))
+        .collect()
+}
+
+/// 把快捷键写成逗号分隔的文字
+pub fn format_keys(keys: &[Shortcut]) -> String {
+    keys.iter()
+        .map(Shortcut::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// 各操作的快捷键
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keymap {
+    bindings: BTreeMap<Action, Vec<Shortcut>>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let bindings = Action::ALL
+            .iter()
+            .map(|&action| {
+                let keys = parse_keys(action.default_keys()).unwrap_or_default();
+                (action, keys)
+            })
+            .collect();
+        Self { bindings }
+    }
+}
+
+impl Keymap {
+    /// 按偏好设置中修改过的快捷键建立，无法使用的设置按默认值处理
+    pub fn with_overrides(overrides: &BTreeMap<Action, String>) -> Self {
+        let mut texts: BTreeMap<Action, String> = Action::ALL
+            .iter()
+            .map(|&action| (action, action.default_keys().to_string()))
+            .collect();
+        texts.extend(
+            overrides
+                .iter()
+                .map(|(action, keys)| (*action, keys.clone())),
+        );
+        Self::from_texts(&texts).unwrap_or_else(|e| {
+            tracing::warn!("快捷键设置无效，使用默认快捷键: {}", e);
+            Self::default()
+        })
+    }
+
+    /// 由各操作的快捷键文字建立，检查格式、重复分配和不能修改的快捷键
+    pub fn from_texts(texts: &BTreeMap<Action, String>) -> Result<Self, String> {
+        let fixed: Vec<Shortcut> = fixed_shortcuts()
+            .iter()
+            .filter_map(|(keys, _)| Shortcut::parse(keys))
+            .collect();
+        let mut owners: HashMap<Shortcut, Action> = HashMap::new();
+        let mut bindings = BTreeMap::new();
+        for &action in &Action::ALL {
+            let text = texts.get(&action).map_or("", String::as_str);
+            let keys = parse_keys(text)?;
+            for key in &keys {
+                if fixed.contains(key) {
+                    return Err(tr!(
+                        "{} 是固定的快捷键，不能分配给“{}”",
+                        key,
+                        action.label()
+                    ));
+                }
+                if let Some(owner) = owners.insert(*key, action)
+                    && owner != action
+                {
+                    return Err(tr!(
+                        "{} 同时分配给了“{}”和“{}”",
+                        key,
+                        owner.label(),
+                        action.label()
+                    ));
+                }
+            }
+            bindings.insert(action, keys);
+        }
+        Ok(Self { bindings })
+    }
+
+    /// 快捷键对应的操作
+    pub fn action(&self, shortcut: &Shortcut) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(_, keys)| keys.contains(shortcut))
+            .map(|(action, _)| *action)
+    }
+
+    /// 操作的快捷键文字（逗号分隔，未分配时为空）
+    pub fn keys(&self, action: Action) -> String {
+        self.bindings
+            .get(&action)
+            .map(|keys| format_keys(keys))
+            .unwrap_or_default()
+    }
+
+    /// 与默认值不同的绑定（写入偏好设置）
+    pub fn overrides(&self) -> BTreeMap<Action, String> {
+        let defaults = Self::default();
+        Action::ALL
+            .iter()
+            .filter(|&&action| self.bindings.get(&action) != defaults.bindings.get(&action))
+            .map(|&action| (action, self.keys(action)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_match_and_overrides() {
+        let shortcut = Shortcut::parse("ctrl+shift+s").unwrap();
+        assert_eq!(shortcut.to_string(), "Ctrl+Shift+S");
+        assert_eq!(Shortcut::from_event("s", true, true, false), Some(shortcut));
+        assert_eq!(Shortcut::parse("Ctrl++").unwrap().to_string(), "Ctrl++");
+        // 标点由 Shift 输入时不计 Shift
+        assert_eq!(
+            Shortcut::from_event("+", false, true, false),
+            Shortcut::parse("+")
+        );
+        assert_eq!(
+            Shortcut::from_event("\u{F729}", false, false, false),
+            Shortcut::parse("Home")
+        );
+        assert_eq!(Shortcut::from_event("\u{10}", false, true, false), None);
+        assert_eq!(Shortcut::parse("Hyper+X"), None);
+        assert_eq!(parse_keys("Ctrl+O, F3").unwrap().len(), 2);
+        assert!(parse_keys("Ctrl+Oops").is_err());
+
+        let keymap = Keymap::default();
+        let home = Shortcut::parse("Home").unwrap();
+        assert_eq!(keymap.action(&home), Some(Action::FirstFrame));
+        assert!(keymap.overrides().is_empty());
+
+        // 只记录修改过的绑定，无效的设置回到默认值
+        let overrides = BTreeMap::from([(Action::OpenFile, "F3, Ctrl+O".to_string())]);
+        let keymap = Keymap::with_overrides(&overrides);
+        assert_eq!(
+            keymap.action(&Shortcut::parse("f3").unwrap()),
+            Some(Action::OpenFile)
+        );
+        assert_eq!(keymap.overrides(), overrides);
+        let conflicting = BTreeMap::from([(Action::SaveFile, "Ctrl+O".to_string())]);
+        assert_eq!(Keymap::with_overrides(&conflicting), Keymap::default());
+
+        // 固定的快捷键不能分配
+        let mut texts: BTreeMap<Action, String> = BTreeMap::new();
+        texts.insert(Action::ZoomIn, "Ctrl+C".to_string());
+        assert!(Keymap::from_texts(&texts).is_err());
+    }
+}
@@ -0,0 +1,56 @@
+//! 界面语言
+//!
+//! 设置对话框中的语言选项与 [`Language`] 之间的换算，以及切换语言：Rust 侧 `tr!` 的文字
+//! 与 Slint 打包的译文一起切换。启动时的语言已按 `--lang`、偏好设置和系统语言决定，
+//! 这里只负责把它应用到界面。
+
+use super::{AppSettings, AppWindow};
+use crate::i18n::{self, Language};
+use std::sync::Arc;
+
+/// 设置对话框中的语言选项：0 为跟随系统，其余依次为 [`Language::ALL`]
+pub fn choice(language: Option<Language>) -> i32 {
+    language
+        .and_then(|language| Language::ALL.iter().position(|l| *l == language))
+        .map_or(0, |index| index as i32 + 1)
+}
+
+/// 设置对话框选项对应的语言，跟随系统时为 `None`
+pub fn from_choice(choice: i32) -> Option<Language> {
+    usize::try_from(choice - 1)
+        .ok()
+        .and_then(|index| Language::ALL.get(index).copied())
+}
+
+/// 切换界面语言：Rust 侧的文字与 Slint 打包的译文一起切换
+pub fn apply(language: Language) {
+    i18n::set_language(language);
+    if let Err(e) = slint::select_bundled_translation(language.catalog_name()) {
+        tracing::warn!("切换界面语言失败: {}", e);
+    }
+}
+
+/// 设置界面语言回调：选择写入偏好设置并立即切换（跟随系统时重新检测）
+pub fn install(window: &AppWindow, settings: Arc<AppSettings>) {
+    window.on_save_language(move |choice| {
+        let language = from_choice(choice);
+        settings.preferences().language = language;
+        apply(language.unwrap_or_else(Language::detect));
+        tracing::info!("界面语言: {:?}", i18n::language());
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_choice_round_trip() {
+        assert_eq!(choice(None), 0);
+        assert_eq!(from_choice(0), None);
+        for language in Language::ALL {
+            assert_eq!(from_choice(choice(Some(language))), Some(language));
+        }
+        assert_eq!(from_choice(-1), None);
+    }
+}
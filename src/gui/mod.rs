@@ -4,12 +4,69 @@
 
 pub use crate::error::Result;
 
+mod bench;
+mod bookmarks;
+mod command_palette;
+mod drop;
+mod keymap;
+mod language;
+pub(crate) mod preferences;
+mod problems;
+mod scale;
+mod selection;
+mod thumbnails;
+
+use crate::error::LibraryError;
+use crate::formats::analyze::{self, LibraryStats};
+use crate::formats::animation::{AnimationSet, Sequence};
+use crate::formats::atomic_file;
+use crate::formats::clip::FrameClip;
+use crate::formats::edit_lock::OpenMode;
+use crate::formats::filter::FrameFilter;
+use crate::formats::folder_import::{self, ImportPlan};
+use crate::formats::frame::FrameProperties;
+use crate::formats::frame_cache::CachePolicy;
+use crate::formats::header::SizeHistogram;
+use crate::formats::integrity::{self, FileReport};
+use crate::formats::journal::{self, Journal, PendingJournal};
+use crate::formats::map::{self, MapFile, MapLibraries, MapRegion};
+use crate::formats::mlibrary_v2::EncodeOptions;
+use crate::formats::open_options::{OPEN_CHOICES_FILE, OpenChoices, PaletteSource};
+use crate::formats::project::{PROJECT_EXTENSION, Project};
+use crate::formats::remap::IndexRemap;
+use crate::formats::remote;
+use crate::formats::similar::SimilarityIndex;
+use crate::formats::spec;
+use crate::formats::watch::FileWatcher;
+use crate::formats::{
+    ImageInfo, LibraryHeader, LibraryInfo, LibraryLoader, LibraryType, OpenOptions, ShadowInfo,
+    companion_path,
+};
+use crate::i18n::{self, tr};
+use crate::image::baseline::{self, AlignPlan};
+use crate::image::canvas::Anchor;
+use crate::image::chroma_key::ChromaKey;
+use crate::image::compare::{self, CompareMode, FrameDiff, Placed};
+use crate::image::export::{self, ExportFormat, ExportOptions};
+use crate::image::gif;
+use crate::image::index_bar::{self, FrameState};
+use crate::image::video::{self, PlacedFrame, VideoOptions};
+use crate::image::{MaskBlend, PlaceholderPolicy, parse_hex_color};
+use crate::locale;
+use crate::progress::{self, CancelToken, Progress, ProgressReceiver, ProgressTracker};
+use crate::script;
+use keymap::{Action, Keymap, Shortcut};
+use preferences::{DialogDir, Preferences, THUMBNAIL_SIZE_RANGE};
+use problems::{Problem, ProblemLog};
+use selection::Selection;
 use slint::{Model, SharedString};
-use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
-use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::atomic::{AtomicU8, AtomicU32, AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
-use std::time::Instant;
+use std::time::{Duration, Instant};
+use thumbnails::{ThumbnailCache, ThumbnailPixels};
 use tracing_appender::rolling;
 
 slint::include_modules!();
@@ -20,6 +77,60 @@ const DEFAULT_CACHE_MAX_SIZE: u64 = 100000;
 /// 默认按键节流间隔（毫秒）
 const DEFAULT_KEY_THROTTLE_MS: u64 = 33;
 
+/// 打开向导中可选的解析器（顺序与界面下拉框一致）
+const WIZARD_PARSERS: [LibraryType; 5] = [
+    LibraryType::WeMade,
+    LibraryType::MLV1,
+    LibraryType::MLV2,
+    LibraryType::EncryptedWil,
+    LibraryType::Mir3,
+];
+
+/// 打开向导查找样例帧时最多尝试的帧数
+const WIZARD_SAMPLE_FRAMES: usize = 32;
+
+/// 打开库时方向检测抽样的帧数
+const ORIENTATION_SAMPLES: usize = 64;
+
+/// 翻页（默认 PageUp / PageDown）一次移动的帧数
+const PAGE_STEP: i32 = 10;
+
+/// 大翻页（默认 Ctrl+PageUp / Ctrl+PageDown）一次移动的帧数
+const LARGE_PAGE_STEP: i32 = 100;
+
+/// 预览缩放快捷键每次调整的百分比
+const ZOOM_STEP: i32 = 10;
+
+/// 预览缩放范围（百分比，与工具栏的缩放滑块一致）
+const ZOOM_RANGE: std::ops::RangeInclusive<i32> = 50..=200;
+
+/// 播放动画的最高帧率
+const MAX_PLAYBACK_FPS: i32 = 60;
+
+/// 检查新的警告和错误并显示到问题面板的间隔
+const PROBLEM_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// 预览背景模式数（最后一种为参考图）
+const PREVIEW_BG_MODES: i32 = 5;
+
+/// 属性面板中数据大小直方图的区间数
+const SIZE_HISTOGRAM_BINS: usize = 24;
+
+/// 索引分布条的最大行数（帧数更多时每行合并一段索引）
+const INDEX_BAR_ROWS: usize = 1024;
+
+/// 查找相似帧时选中的帧数
+const SIMILAR_LIMIT: usize = 10;
+
+/// 体积分析对话框中列出的最大帧个数
+const STATS_LARGEST: usize = 20;
+
+/// 检查库文件外部修改的间隔
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// 保存后忽略库文件变化的时间（自己写文件触发的事件）
+const SAVE_IGNORE: Duration = Duration::from_secs(1);
+
 /// 应用程序设置（支持动态修改）
 #[derive(Debug)]
 struct AppSettings {
@@ -27,13 +138,33 @@ struct AppSettings {
     cache_max_size: AtomicU64,
     /// 按键节流间隔（毫秒）
     key_throttle_ms: AtomicU64,
+    /// 空帧占位策略（PlaceholderPolicy 序号）
+    placeholder_policy: AtomicU8,
+    /// 缩略图的生成尺寸（物理像素），随缩略图大小和显示缩放变化
+    thumbnail_pixels: AtomicU32,
+    /// 快捷键
+    keymap: Mutex<Keymap>,
+    /// 持久化的偏好设置
+    preferences: Mutex<Preferences>,
+    /// 偏好设置文件路径
+    preferences_path: PathBuf,
 }
 
 impl AppSettings {
     fn new() -> Self {
+        let preferences_path = locale::settings_path();
+        let preferences = Preferences::load(&preferences_path);
+        tracing::debug!("偏好设置: {:?}", preferences_path);
+        locale::set(preferences.number_format.clone());
+        atomic_file::set_backup_count(preferences.backup_count);
         Self {
-            cache_max_size: AtomicU64::new(DEFAULT_CACHE_MAX_SIZE),
-            key_throttle_ms: AtomicU64::new(DEFAULT_KEY_THROTTLE_MS),
+            cache_max_size: AtomicU64::new(preferences.cache_max_size),
+            key_throttle_ms: AtomicU64::new(preferences.key_throttle_ms),
+            placeholder_policy: AtomicU8::new(preferences.placeholder_policy),
+            thumbnail_pixels: AtomicU32::new(*THUMBNAIL_SIZE_RANGE.end()),
+            keymap: Mutex::new(Keymap::with_overrides(&preferences.shortcuts)),
+            preferences: Mutex::new(preferences),
+            preferences_path,
         }
     }
 
@@ -53,27 +184,153 @@ impl AppSettings {
     fn set_key_throttle_ms(&self, ms: u64) {
         self.key_throttle_ms.store(ms, Ordering::SeqCst);
     }
+
+    fn get_placeholder_policy(&self) -> PlaceholderPolicy {
+        PlaceholderPolicy::from_index(self.placeholder_policy.load(Ordering::SeqCst))
+    }
+
+    fn set_placeholder_policy(&self, policy: PlaceholderPolicy) {
+        self.placeholder_policy.store(policy.to_index(), Ordering::SeqCst);
+    }
+
+    fn get_thumbnail_pixels(&self) -> u32 {
+        self.thumbnail_pixels.load(Ordering::SeqCst)
+    }
+
+    fn set_thumbnail_pixels(&self, pixels: u32) {
+        self.thumbnail_pixels.store(pixels.max(1), Ordering::SeqCst);
+    }
+
+    fn keymap(&self) -> std::sync::MutexGuard<'_, Keymap> {
+        self.keymap.lock().unwrap()
+    }
+
+    /// 替换快捷键，偏好设置中只记录与默认值不同的部分
+    fn set_keymap(&self, keymap: Keymap) {
+        self.preferences().shortcuts = keymap.overrides();
+        *self.keymap() = keymap;
+    }
+
+    fn preferences(&self) -> std::sync::MutexGuard<'_, Preferences> {
+        self.preferences.lock().unwrap()
+    }
+
+    /// 写回偏好设置（先同步设置对话框中的选项）
+    fn save_preferences(&self) {
+        let mut preferences = self.preferences();
+        preferences.cache_max_size = self.cache_max_size.load(Ordering::SeqCst);
+        preferences.key_throttle_ms = self.key_throttle_ms.load(Ordering::SeqCst);
+        preferences.placeholder_policy = self.placeholder_policy.load(Ordering::SeqCst);
+        if let Err(e) = preferences.save(&self.preferences_path) {
+            tracing::warn!("保存偏好设置失败: {}", e);
+        }
+    }
+
+    /// 从上次所在目录打开的文件对话框
+    fn file_dialog(&self, kind: DialogDir) -> rfd::FileDialog {
+        let dialog = rfd::FileDialog::new();
+        match self.preferences().last_dir(kind) {
+            Some(dir) => dialog.set_directory(dir),
+            None => dialog,
+        }
+    }
+
+    /// 记住对话框选中的文件或目录所在的位置
+    fn remember_dir(&self, kind: DialogDir, path: &Path) {
+        self.preferences().set_last_dir(kind, path);
+    }
 }
 
 /// 应用状态
 struct AppState {
     /// 库加载器
-    library_loader: Rc<Mutex<Option<crate::formats::LibraryLoader>>>,
+    library_loader: Arc<Mutex<Option<crate::formats::LibraryLoader>>>,
     /// 缩略图缓存
     thumbnail_cache: Rc<Mutex<Option<Arc<ThumbnailCache>>>>,
     /// 上次按键时间（用于节流）
     last_key_time: Rc<Mutex<Instant>>,
     /// 应用设置
-    settings: Rc<AppSettings>,
+    settings: Arc<AppSettings>,
+    /// 打开向导正在处理的文件
+    wizard_path: Rc<Mutex<Option<PathBuf>>>,
+    /// 快速预览中的文件
+    header_path: Rc<Mutex<Option<PathBuf>>>,
+    /// 双库对比中的对比库
+    compare_loader: Arc<Mutex<Option<LibraryLoader>>>,
+    /// 帧剪贴板（切换打开的库后仍保留，可粘贴到另一个库）
+    frame_clip: Rc<Mutex<Option<FrameClip>>>,
+    /// 系统剪贴板（首次复制图像时创建，保持存活以免部分平台上内容随之丢失）
+    os_clipboard: Rc<Mutex<Option<arboard::Clipboard>>>,
+    /// 正在后台打开的库
+    opening: Rc<Mutex<Option<OpenTask>>>,
+    /// 当前项目（项目文件路径和内容）
+    project: Rc<Mutex<Option<(PathBuf, Project)>>>,
+    /// 地图查看中的地图
+    map_view: Rc<Mutex<Option<MapView>>>,
+    /// 监视当前库文件的外部修改
+    library_watch: Rc<Mutex<Option<LibraryWatch>>>,
+    /// 启动时提示可以恢复的日志
+    recovery_offer: Rc<Mutex<Option<PendingJournal>>>,
+    /// 选择恢复后等待库打开完成再套用的日志
+    recovery: Rc<Mutex<Option<Journal>>>,
+    /// 上一次写入的恢复日志（日志文件、写入时的修改次数）
+    journal_written: Rc<Mutex<Option<(PathBuf, u64)>>>,
+}
+
+/// 监视中的库文件
+struct LibraryWatch {
+    /// 库文件路径
+    path: PathBuf,
+    /// 库文件及索引文件的变化
+    files: FileWatcher,
+    /// 自动重新加载后要回到的帧
+    restore_index: Option<i32>,
+}
+
+/// 地图查看对话框中打开的地图
+struct MapView {
+    /// 地图文件路径
+    path: PathBuf,
+    /// 地图内容
+    map: MapFile,
+    /// 渲染用的资源库
+    libraries: MapLibraries,
+}
+
+/// 后台打开库的结果
+type OpenResult = crate::error::Result<(LibraryInfo, LibraryLoader)>;
+
+/// 在后台线程中打开的库
+struct OpenTask {
+    /// 库文件路径
+    path: PathBuf,
+    /// 是否按记住的手动选项打开
+    remembered: bool,
+    /// 取消标记（Escape 或状态栏的取消按钮）
+    cancel: CancelToken,
+    /// 加载结果，加载线程结束后填入
+    result: Arc<Mutex<Option<OpenResult>>>,
 }
 
 impl AppState {
     fn new() -> Self {
         Self {
-            library_loader: Rc::new(Mutex::new(None)),
+            library_loader: Arc::new(Mutex::new(None)),
             thumbnail_cache: Rc::new(Mutex::new(None)),
             last_key_time: Rc::new(Mutex::new(Instant::now())),
-            settings: Rc::new(AppSettings::new()),
+            settings: Arc::new(AppSettings::new()),
+            wizard_path: Rc::new(Mutex::new(None)),
+            header_path: Rc::new(Mutex::new(None)),
+            compare_loader: Arc::new(Mutex::new(None)),
+            frame_clip: Rc::new(Mutex::new(None)),
+            os_clipboard: Rc::new(Mutex::new(None)),
+            opening: Rc::new(Mutex::new(None)),
+            project: Rc::new(Mutex::new(None)),
+            map_view: Rc::new(Mutex::new(None)),
+            library_watch: Rc::new(Mutex::new(None)),
+            recovery_offer: Rc::new(Mutex::new(None)),
+            recovery: Rc::new(Mutex::new(None)),
+            journal_written: Rc::new(Mutex::new(None)),
         }
     }
 
@@ -83,7 +340,22 @@ impl AppState {
         loader: &mut crate::formats::LibraryLoader,
         index: usize,
     ) {
-        match loader.get_preview(index) {
+        // 按当前图层选择显示内容
+        let preview = match window.get_layer_view() {
+            1 => loader.get_mask(index).map(|mask| mask.map(Arc::new)),
+            2 => loader.get_composite(index, MaskBlend::from_index(window.get_mask_blend())),
+            _ if window.get_show_placement() => loader
+                .get_placement(index)
+                .map(|image| Some(Arc::new(image))),
+            _ if window.get_show_shadow() => loader.get_with_shadow(index),
+            _ if window.get_show_onion() => {
+                let opacity = window.get_onion_opacity().clamp(0, 100) as u32 * 255 / 100;
+                loader.get_onion_skin(index, opacity as u8)
+            }
+            _ => loader.get_preview(index),
+        };
+
+        match preview {
             Ok(Some(preview_img)) => {
                 if let Some(slint_image) = rgba_image_to_slint(&preview_img) {
                     window.set_main_preview(slint_image);
@@ -93,803 +365,5175 @@ impl AppState {
                 window.set_main_preview(slint::Image::default());
             }
             Err(e) => {
-                tracing::warn!("获取主预览图 {} 失败: {:?}", index, e);
+                tracing::warn!(frame = index, "获取主预览图 {} 失败: {:?}", index, e);
                 window.set_main_preview(slint::Image::default());
             }
         }
     }
 }
 
-/// 缩略图缓存（LRU 策略）
-struct ThumbnailCache {
-    /// 缓存的缩略图（索引 -> 图像）
-    cache: Mutex<HashMap<usize, slint::Image>>,
-    /// LRU 访问顺序（最近使用的在末尾）
-    access_order: Mutex<Vec<usize>>,
-    /// 总图片数
-    total_count: usize,
-    /// 正在加载的索引集合
-    loading: Mutex<std::collections::HashSet<usize>>,
-    /// 已加载数量
-    loaded_count: AtomicU32,
-    /// 应用设置引用
-    settings: Rc<AppSettings>,
-}
+/// 启动进度监听线程
+///
+/// 汇总事件后通过事件循环更新状态栏；缩略图缓存被替换、发送端全部释放后线程自动退出。
+fn spawn_progress_listener(
+    events: ProgressReceiver,
+    tracker: Arc<Mutex<ProgressTracker>>,
+    window_weak: slint::Weak<AppWindow>,
+) {
+    std::thread::spawn(move || {
+        for event in events.iter() {
+            let (loaded, percent, loading) = {
+                let mut tracker = tracker.lock().unwrap();
+                tracker.apply(&event);
+                // 积压的事件一并处理，避免逐帧刷新界面
+                for event in events.try_iter() {
+                    tracker.apply(&event);
+                }
+                (
+                    tracker.loaded() as i32,
+                    tracker.percent() as i32,
+                    !tracker.is_done(),
+                )
+            };
 
-impl ThumbnailCache {
-    fn new(total_count: usize, settings: Rc<AppSettings>) -> Self {
-        Self {
-            cache: Mutex::new(HashMap::new()),
-            access_order: Mutex::new(Vec::new()),
-            total_count,
-            loading: Mutex::new(std::collections::HashSet::new()),
-            loaded_count: AtomicU32::new(0),
-            settings,
+            let _ = window_weak.upgrade_in_event_loop(move |win| {
+                win.set_loaded_count(loaded);
+                win.set_load_progress(percent);
+                win.set_is_loading(loading);
+                win.invoke_refresh_index_bar();
+            });
         }
-    }
+        tracing::debug!("进度监听线程退出");
+    });
+}
 
-    /// 获取缓存的缩略图
-    fn get(&self, index: usize) -> Option<slint::Image> {
-        let cache = self.cache.lock().unwrap();
-        if let Some(img) = cache.get(&index) {
-            // 更新 LRU 顺序
-            let mut order = self.access_order.lock().unwrap();
-            order.retain(|&i| i != index);
-            order.push(index);
-            return Some(img.clone());
-        }
-        None
+/// 将加载成功的库显示到界面（打开文件与打开向导共用）
+fn show_library(
+    window: &AppWindow,
+    info: LibraryInfo,
+    mut loader: LibraryLoader,
+    library_loader: &Arc<Mutex<Option<LibraryLoader>>>,
+    thumbnail_cache: &Rc<Mutex<Option<Arc<ThumbnailCache>>>>,
+    settings: &Arc<AppSettings>,
+) {
+    // 先释放上一个库及其编辑锁，重新打开同一个库时才能再次加锁
+    library_loader.lock().unwrap().take();
+    loader.set_placeholder_policy(settings.get_placeholder_policy());
+    loader.set_append_only(settings.preferences().append_only);
+    let frame_memory_mb = settings.preferences().frame_memory_mb;
+    loader.set_cache_policy(CachePolicy::megabytes(frame_memory_mb));
+    // 库正被其他编辑器打开时改为只读，避免两边同时写入
+    let mut in_use = None;
+    if info.capabilities().writable
+        && let Err(e) = loader.set_open_mode(OpenMode::ReadWrite)
+    {
+        tracing::warn!("无法取得编辑锁，以只读方式打开: {}", e);
+        let _ = loader.set_open_mode(OpenMode::ReadOnly);
+        in_use = Some(e);
+    }
+    tracing::debug!("库文件加载成功: {}", info.file_name);
+    tracing::debug!("  格式: {}", info.format_name());
+    tracing::debug!("  图像数: {}", info.image_count);
+
+    // 更新 UI
+    window.set_file_name(SharedString::from(&info.file_name));
+    window.set_image_count(info.image_count as i32);
+    window.set_image_format(SharedString::from(&info.format_name()));
+    window.set_current_index(if info.image_count > 0 { 0 } else { -1 });
+
+    // 按格式能力启用/禁用界面操作
+    let caps = info.capabilities();
+    window.set_cap_writable(caps.writable && in_use.is_none());
+    window.set_cap_mask(caps.supports_mask);
+    window.set_cap_shadow(caps.supports_shadow);
+    window.set_cap_paletted(caps.paletted);
+    window.set_cap_properties(caps.editable_properties);
+    window.set_trash_count(0);
+    write_selection(window, &Selection::default());
+    write_locks(window, &loader);
+    bookmarks::write(window, &loader);
+    window.set_bookmark_label(SharedString::new());
+    if !caps.supports_mask {
+        window.set_layer_view(0);
     }
 
-    /// 插入缩略图到缓存
-    fn put(&self, index: usize, image: slint::Image) {
-        let mut cache = self.cache.lock().unwrap();
-        let mut order = self.access_order.lock().unwrap();
+    // 初始化空的缩略图数组
+    let empty_thumbnails: Vec<slint::Image> = vec![slint::Image::default(); info.image_count];
+    let model = slint::VecModel::from(empty_thumbnails);
+    window.set_thumbnails(slint::ModelRc::new(model));
+    window.set_loaded_count(0);
 
-        // 如果已存在，先移除
-        if cache.contains_key(&index) {
-            order.retain(|&i| i != index);
+    // 加载第一张图像信息
+    if info.image_count > 0 {
+        tracing::debug!("加载第一张图像信息");
+        if let Ok(img_info) = loader.get_image_info(0) {
+            show_image_info(window, &img_info);
+            tracing::debug!("图像尺寸: {}x{}", img_info.width, img_info.height);
         }
+        // 更新主预览图
+        AppState::update_main_preview(window, &mut loader, 0);
+    } else {
+        // 没有图像，清空主预览
+        window.set_main_preview(slint::Image::default());
+    }
 
-        // 如果缓存已满，移除最久未使用的（使用动态配置的 max_size）
-        let max_size = self.settings.get_cache_max_size();
-        if cache.len() >= max_size
-            && let Some(old_index) = order.first().copied()
-        {
-            cache.remove(&old_index);
-            order.remove(0);
-        }
+    // 创建缩略图缓存及进度通道
+    let (events_tx, events_rx) = progress::channel();
+    let cache = Arc::new(ThumbnailCache::new(
+        info.image_count,
+        settings.clone(),
+        events_tx,
+    ));
+    spawn_progress_listener(events_rx, cache.progress.clone(), window.as_weak());
+
+    let header = read_file_header(&loader);
+    show_size_histogram(window, header.as_ref());
+    cache.set_file_header(header.as_ref());
+    update_index_bar(window, &loader, &cache);
+
+    // 保存引用
+    *library_loader.lock().unwrap() = Some(loader);
+    thumbnails::replace_cache(thumbnail_cache, Some(Arc::clone(&cache)));
+
+    let status = match in_use {
+        Some(e) => tr!(
+            "已以只读方式打开: {} ({} 张图像)，{}",
+            info.file_name,
+            info.image_count,
+            e
+        ),
+        None => tr!("已打开: {} ({} 张图像)", info.file_name, info.image_count),
+    };
+    window.set_status_text(SharedString::from(&status));
+}
 
-        cache.insert(index, image);
-        order.push(index);
-        self.loaded_count.fetch_add(1, Ordering::SeqCst);
-        tracing::trace!("缓存缩略图: {}, 缓存大小: {}", index, cache.len());
-    }
+/// 弹出库文件选择对话框（从上次打开的目录开始）
+fn pick_library_file(settings: &AppSettings, title: &str) -> Option<PathBuf> {
+    let path = settings
+        .file_dialog(DialogDir::Library)
+        .add_filter(tr!("传奇库文件"), &["lib", "wzl", "wil", "wtl", "d"])
+        .add_filter(tr!("所有文件"), &["*"])
+        .set_title(title)
+        .pick_file()?;
+    settings.remember_dir(DialogDir::Library, &path);
+    Some(path)
+}
 
-    /// 请求加载指定范围的缩略图（使用共享加载器）
-    fn request_range_with_loader(
-        &self,
-        start: usize,
-        end: usize,
-        window_weak: slint::Weak<AppWindow>,
-        library_loader: Rc<Mutex<Option<crate::formats::LibraryLoader>>>,
-    ) {
-        let start = start.min(self.total_count.saturating_sub(1));
-        let end = end.min(self.total_count.saturating_sub(1));
+/// 更新界面上的最近打开列表
+fn show_recent_files(window: &AppWindow, recent_files: &[PathBuf]) {
+    let items: Vec<SharedString> = recent_files
+        .iter()
+        .map(|path| SharedString::from(path.display().to_string()))
+        .collect();
+    window.set_recent_files(slint::ModelRc::new(slint::VecModel::from(items)));
+}
 
-        if start > end {
-            return;
-        }
+/// 把成功打开的库加入最近打开列表并写回偏好设置
+fn add_recent_file(window: &AppWindow, settings: &AppSettings, path: &Path) {
+    let recent_files = {
+        let mut preferences = settings.preferences();
+        preferences.add_recent(path);
+        preferences.recent_files.clone()
+    };
+    settings.save_preferences();
+    show_recent_files(window, &recent_files);
+}
 
-        // 找出需要加载的索引
-        let indices_to_load: Vec<usize> = {
-            let cache = self.cache.lock().unwrap();
-            let mut loading = self.loading.lock().unwrap();
-            tracing::debug!("缓存大小: {}, 正在加载: {}", cache.len(), loading.len());
-            let indices: Vec<usize> = (start..=end)
-                .filter(|&i| !cache.contains_key(&i) && !loading.contains(&i))
-                .collect();
-            for &i in &indices {
-                loading.insert(i);
+/// 更新界面上的项目名称和项目中的库
+fn show_project(window: &AppWindow, project: Option<&(PathBuf, Project)>) {
+    let (name, libraries, hints): (String, Vec<SharedString>, Vec<SharedString>) = match project {
+        Some((path, project)) => {
+            let mut name = path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            if !project.notes.is_empty() {
+                name = format!("{}：{}", name, project.notes);
             }
-            indices
-        };
-
-        if indices_to_load.is_empty() {
-            return;
+            let libraries = project
+                .libraries
+                .iter()
+                .map(|l| SharedString::from(l.path.display().to_string()))
+                .collect();
+            let hints = project
+                .libraries
+                .iter()
+                .map(|l| {
+                    SharedString::from(tr!(
+                        "{} 个动画，{} 个书签",
+                        l.sequences.len(),
+                        l.bookmarks.len()
+                    ))
+                })
+                .collect();
+            (name, libraries, hints)
         }
+        None => Default::default(),
+    };
+    window.set_project_name(SharedString::from(name));
+    window.set_project_libraries(slint::ModelRc::new(slint::VecModel::from(libraries)));
+    window.set_project_library_hints(slint::ModelRc::new(slint::VecModel::from(hints)));
+}
 
-        tracing::debug!(
-            "请求加载缩略图: {}..{} ({} 张)",
-            start,
-            end,
-            indices_to_load.len()
-        );
+/// 在后台线程中完整加载库文件，加载结束后由 `library_loaded` 回调显示
+///
+/// 加载期间界面保持响应，状态栏显示进度，Escape 或取消按钮中止加载；
+/// 新的打开请求会取消尚未完成的旧请求。
+fn open_library(
+    window: &AppWindow,
+    path: PathBuf,
+    opening: &Rc<Mutex<Option<OpenTask>>>,
+    thumbnail_cache: &Rc<Mutex<Option<Arc<ThumbnailCache>>>>,
+) {
+    // 清理已加载的数据
+    tracing::debug!("清理旧数据...");
+    // 清理缩略图缓存
+    thumbnails::replace_cache(thumbnail_cache, None);
+
+    // 清理 UI 数据（先重置 image_count 为 0，触发 Slint 端的滚动重置）
+    // 问题面板中的帧索引属于旧库，一并清空
+    window.invoke_clear_problems();
+    window.set_image_count(0);
+    window.set_thumbnails(slint::ModelRc::new(slint::VecModel::from(vec![])));
+    window.set_main_preview(slint::Image::default());
+    window.set_current_index(0);
 
-        // 直接在主线程同步加载（避免每次重新打开文件）
-        if let Some(win) = window_weak.upgrade() {
-            let mut loader_guard = library_loader.lock().unwrap();
-            if let Some(ref mut loader) = *loader_guard {
-                let thumbnails = win.get_thumbnails();
-                let mut new_thumbnails: Vec<slint::Image> = thumbnails.iter().collect();
-
-                for i in &indices_to_load {
-                    match loader.get_preview(*i) {
-                        Ok(Some(preview_img)) => {
-                            if let Some(slint_image) = rgba_image_to_slint(&preview_img)
-                                && *i < new_thumbnails.len()
-                            {
-                                new_thumbnails[*i] = slint_image.clone();
-                                // 存入缓存，避免重复加载
-                                self.put(*i, slint_image);
-                            }
-                        }
-                        Ok(None) => {}
-                        Err(e) => {
-                            tracing::warn!("加载缩略图 {} 失败: {:?}", i, e);
-                        }
-                    }
-                }
+    tracing::debug!("选择的文件: {:?}", path);
+    window.set_status_text(SharedString::from(tr!("正在加载...")));
+    window.set_load_progress(0);
+    window.set_is_loading(true);
+    window.set_is_opening(true);
+
+    // 已记住手动选项的文件直接按选项加载
+    let options = OpenChoices::load(Path::new(OPEN_CHOICES_FILE))
+        .get(&path)
+        .cloned();
+    let cancel = CancelToken::new();
+    let result = Arc::new(Mutex::new(None));
+    let previous = opening.lock().unwrap().replace(OpenTask {
+        path: path.clone(),
+        remembered: options.is_some(),
+        cancel: cancel.clone(),
+        result: result.clone(),
+    });
+    if let Some(previous) = previous {
+        tracing::debug!("取消尚未完成的加载: {:?}", previous.path);
+        previous.cancel.cancel();
+    }
+
+    let window_weak = window.as_weak();
+    std::thread::spawn(move || {
+        let progress = load_progress_reporter(window_weak.clone());
+        let loaded = match &options {
+            Some(options) => {
+                LibraryLoader::load_with_cancellable(&path, options, &cancel, progress)
+            }
+            None => LibraryLoader::load_cancellable(&path, &cancel, progress),
+        };
+        *result.lock().unwrap() = Some(loaded);
+        let _ = window_weak.upgrade_in_event_loop(|window| window.invoke_library_loaded());
+    });
+}
 
-                let model = slint::VecModel::from(new_thumbnails);
-                win.set_thumbnails(slint::ModelRc::new(model));
-                win.set_loaded_count(self.get_loaded_count() as i32);
+/// 把打开库的进度显示到状态栏（在加载线程中调用，百分比变化时才刷新界面）
+fn load_progress_reporter(window_weak: slint::Weak<AppWindow>) -> impl FnMut(Progress) {
+    let mut last_percent = None;
+    move |progress| {
+        let (text, percent) = match progress {
+            Progress::IndexRead { frames } => (
+                tr!(
+                    "正在加载: 已读取索引（{} 帧）",
+                    locale::count(frames as u64)
+                ),
+                0,
+            ),
+            Progress::PaletteRead => (tr!("正在加载: 已读取调色板").to_string(), 0),
+            Progress::FrameDecoded { done, total } => {
+                let percent = (done * 100 / total.max(1)) as i32;
+                if last_percent == Some(percent) {
+                    return;
+                }
+                last_percent = Some(percent);
+                (
+                    tr!(
+                        "正在加载: 解码 {}/{} 帧（Esc 取消）",
+                        locale::count(done as u64),
+                        locale::count(total as u64)
+                    ),
+                    percent,
+                )
+            }
+        };
+        let _ = window_weak.upgrade_in_event_loop(move |window| {
+            // 加载已结束（如刚被取消）时不再覆盖状态栏
+            if window.get_is_opening() {
+                window.set_status_text(SharedString::from(&text));
+                window.set_load_progress(percent);
             }
+        });
+    }
+}
 
-            // 清除加载标记
-            let mut loading = self.loading.lock().unwrap();
-            for i in &indices_to_load {
-                loading.remove(i);
+/// 显示后台加载的结果，自动识别失败时弹出打开向导
+fn finish_open_library(
+    window: &AppWindow,
+    task: OpenTask,
+    result: OpenResult,
+    library_loader: &Arc<Mutex<Option<LibraryLoader>>>,
+    thumbnail_cache: &Rc<Mutex<Option<Arc<ThumbnailCache>>>>,
+    settings: &Arc<AppSettings>,
+    wizard_path: &Rc<Mutex<Option<PathBuf>>>,
+) {
+    window.set_is_opening(false);
+    window.set_is_loading(false);
+    let path = task.path;
+
+    match result {
+        Ok((info, mut loader)) => {
+            // 未记住选项的库先检测方向，疑似上下颠倒时询问是否翻转
+            let guess = (!task.remembered).then(|| loader.detect_orientation(ORIENTATION_SAMPLES));
+            add_recent_file(window, settings, &path);
+            show_library(
+                window,
+                info,
+                loader,
+                library_loader,
+                thumbnail_cache,
+                settings,
+            );
+            if let Some(guess) = guess.filter(|g| g.likely_flipped()) {
+                window.set_orientation_message(SharedString::from(&tr!(
+                    "抽样的 {} 帧中有 {} 帧底部宽于顶部，像是上下颠倒的。是否翻转显示方向？选择会按文件记住。",
+                    guess.upright + guess.flipped + guess.undecided,
+                    guess.flipped
+                )));
+                window.set_show_orientation_prompt(true);
             }
         }
+        Err(LibraryError::Cancelled) => {
+            tracing::debug!("已取消加载: {:?}", path);
+            window.set_status_text(SharedString::from(&tr!("已取消加载: {}", path.display())));
+            window.set_file_name(SharedString::from(""));
+            window.set_current_index(-1);
+        }
+        Err(e) => {
+            tracing::warn!("自动识别失败，打开向导: {:?}", e);
+            window.set_status_text(SharedString::from(&tr!("加载失败: {}", e)));
+
+            // 清空状态
+            window.set_file_name(SharedString::from(""));
+            window.set_image_count(0);
+            window.set_current_index(-1);
+            window.set_image_width(0);
+            window.set_image_height(0);
+            window.set_main_preview(slint::Image::default());
+
+            // 弹出打开向导
+            window.set_wizard_file(SharedString::from(path.display().to_string()));
+            window.set_wizard_preview(slint::Image::default());
+            window.set_wizard_status(SharedString::from(&tr!("自动识别失败: {}", e)));
+            window.set_show_open_wizard(true);
+            *wizard_path.lock().unwrap() = Some(path);
+        }
     }
+}
+
+/// 用文件头信息填充快速预览对话框
+fn show_header_preview(window: &AppWindow, header: &LibraryHeader) {
+    let (max_width, max_height) = header.max_size();
+    window.set_header_file(SharedString::from(&header.info.file_name));
+    window.set_header_summary(SharedString::from(&tr!(
+        "{}，{} 帧（空帧 {}，带遮罩 {}），最大尺寸 {}x{}，像素数据 {}",
+        header.info.format_name(),
+        locale::count(header.frames.len() as u64),
+        locale::count(header.empty_count() as u64),
+        locale::count(header.mask_count() as u64),
+        max_width,
+        max_height,
+        locale::size(header.payload_bytes())
+    )));
+
+    let rows: Vec<slint::ModelRc<slint::StandardListViewItem>> = header
+        .frames
+        .iter()
+        .map(|frame| {
+            let size = if frame.is_empty() {
+                tr!("空帧").to_string()
+            } else {
+                format!("{}x{}", frame.width, frame.height)
+            };
+            let cells: Vec<slint::StandardListViewItem> = [
+                frame.index.to_string(),
+                size,
+                format!("({}, {})", frame.x, frame.y),
+                locale::count(frame.data_length),
+            ]
+            .iter()
+            .map(|text| slint::StandardListViewItem::from(text.as_str()))
+            .collect();
+            slint::ModelRc::new(slint::VecModel::from(cells))
+        })
+        .collect();
+    window.set_header_rows(slint::ModelRc::new(slint::VecModel::from(rows)));
+    window.set_show_header_preview(true);
+}
 
-    /// 获取已加载数量
-    fn get_loaded_count(&self) -> usize {
-        self.cache.lock().unwrap().len()
+/// 读取项目文件，打开其中第一个存在的库
+fn open_project(
+    window: &AppWindow,
+    path: PathBuf,
+    project: &Mutex<Option<(PathBuf, Project)>>,
+    opening: &Rc<Mutex<Option<OpenTask>>>,
+    thumbnail_cache: &Rc<Mutex<Option<Arc<ThumbnailCache>>>>,
+) {
+    let loaded = match Project::load(&path) {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            tracing::error!("读取项目失败 {:?}: {}", path, e);
+            window.set_status_text(SharedString::from(&tr!("读取项目失败: {}", e)));
+            return;
+        }
+    };
+    let first = loaded
+        .libraries
+        .iter()
+        .map(|l| l.path.clone())
+        .find(|p| p.is_file());
+    let count = loaded.libraries.len();
+    let mut project = project.lock().unwrap();
+    *project = Some((path, loaded));
+    show_project(window, project.as_ref());
+    drop(project);
+
+    match first {
+        Some(first) => open_library(window, first, opening, thumbnail_cache),
+        None => window.set_status_text(SharedString::from(&tr!(
+            "已打开项目（{} 个库，均不存在）",
+            count
+        ))),
     }
 }
 
-/// 将 RGBA 图像转换为 Slint Image
-fn rgba_image_to_slint(img: &image::RgbaImage) -> Option<slint::Image> {
-    let width = img.width();
-    let height = img.height();
+/// 当前打开的库的文件路径和修改次数，后台任务前后对比以发现期间换了库或修改了帧
+fn loader_revision(library_loader: &Mutex<Option<LibraryLoader>>) -> Option<(PathBuf, u64)> {
+    let guard = library_loader.lock().unwrap();
+    let loader = guard.as_ref()?;
+    Some((loader.info()?.path(), loader.revision()))
+}
 
-    if width == 0 || height == 0 {
-        return None;
+/// 在后台线程中读取共享加载器中的一帧，只在读这一帧时持有锁；库已关闭时返回取消
+fn shared_frame(
+    library_loader: &Mutex<Option<LibraryLoader>>,
+    index: usize,
+) -> Result<Option<Arc<image::RgbaImage>>> {
+    match library_loader.lock().unwrap().as_mut() {
+        Some(loader) => loader.get_frame(index),
+        None => Err(LibraryError::Cancelled),
     }
+}
 
-    let buffer = slint::SharedPixelBuffer::<slint::Rgba8Pixel>::clone_from_slice(
-        img.as_raw(),
-        width,
-        height,
-    );
-    Some(slint::Image::from_rgba8(buffer))
+/// 当前打开的库的文件路径和类型
+fn open_library_kind(
+    library_loader: &Mutex<Option<LibraryLoader>>,
+) -> Option<(PathBuf, LibraryType)> {
+    let guard = library_loader.lock().unwrap();
+    let info = guard.as_ref()?.info()?;
+    Some((info.path(), info.library_type))
 }
 
-/// 初始化日志 - 同时输出到控制台和文件
-fn init_logging() {
-    use tracing::Level;
-    use tracing_subscriber::{Registry, layer::SubscriberExt, util::SubscriberInitExt};
+/// 把恢复日志中的修改套用到刚打开的库上并刷新界面
+///
+/// 恢复后日志交给定时写入接管：之后按新的修改重写，保存后删除。
+fn restore_journal(
+    window: &AppWindow,
+    journal: Journal,
+    library_loader: &Arc<Mutex<Option<LibraryLoader>>>,
+    thumbnail_cache: &Rc<Mutex<Option<Arc<ThumbnailCache>>>>,
+    settings: &Arc<AppSettings>,
+    written: &mut Option<(PathBuf, u64)>,
+) {
+    let mut guard = library_loader.lock().unwrap();
+    let Some(ref mut loader) = *guard else {
+        window.set_status_text(SharedString::from(tr!("库没有打开，未能恢复未保存的修改")));
+        return;
+    };
+    let edited = journal.edited_frames();
+    let path = journal::journal_path(&journal::journal_dir(), &journal.library);
+    match loader.apply_journal(journal) {
+        Ok(()) => {
+            // 修改次数从 0 算起不会与当前相同，下一次定时写入时重写日志
+            *written = Some((path, 0));
+            let thumbnails = vec![slint::Image::default(); loader.image_count()];
+            refresh_frames(window, loader, thumbnail_cache, settings, thumbnails, 0);
+            window.set_status_text(SharedString::from(&tr!(
+                "已恢复上次未保存的修改（{} 帧），检查后请保存",
+                edited
+            )));
+        }
+        Err(e) => {
+            tracing::error!("恢复未保存的修改失败: {}", e);
+            window.set_status_text(SharedString::from(&tr!("恢复未保存的修改失败: {}", e)));
+        }
+    }
+}
 
-    let file_appender = rolling::daily("./logs", "library-editor.log");
+/// 按当前库的状态写入或删除恢复日志
+///
+/// 有新的修改时重写日志；修改已保存、换了库或库已关闭时删除上一次写入的日志。
+fn autosave_journal(loader: Option<&mut LibraryLoader>, written: &mut Option<(PathBuf, u64)>) {
+    let dir = journal::journal_dir();
+    let current = loader.and_then(|loader| {
+        let path = journal::journal_path(&dir, &loader.info()?.path());
+        Some((path, loader))
+    });
+    if let Some((old, _)) = written.as_ref()
+        && current
+            .as_ref()
+            .is_none_or(|(path, loader)| path != old || !loader.is_modified())
+    {
+        journal::discard(old);
+        *written = None;
+    }
 
-    // 根据编译配置选择日志级别
-    #[cfg(debug_assertions)]
-    let log_level = Level::DEBUG;
-    #[cfg(not(debug_assertions))]
-    let log_level = Level::INFO;
+    let Some((path, loader)) = current.filter(|(_, loader)| loader.is_modified()) else {
+        return;
+    };
+    let revision = loader.revision();
+    if written
+        .as_ref()
+        .is_some_and(|(_, written)| *written == revision)
+    {
+        return;
+    }
+    let result = loader
+        .journal()
+        .and_then(|journal| journal.map_or(Ok(()), |journal| journal.write(&path)));
+    match result {
+        Ok(()) => *written = Some((path, revision)),
+        Err(e) => tracing::warn!("写入恢复日志失败: {}", e),
+    }
+}
 
-    let file_layer = tracing_subscriber::fmt::layer()
-        .with_writer(file_appender)
-        .with_ansi(false)
-        .with_level(true)
-        .with_target(true);
+/// 当前打开的库的基础路径（不含扩展名）
+fn library_base(library_loader: &Mutex<Option<LibraryLoader>>) -> Option<PathBuf> {
+    let guard = library_loader.lock().unwrap();
+    Some(guard.as_ref()?.info()?.base_path.clone())
+}
 
-    let console_layer = tracing_subscriber::fmt::layer()
-        .with_writer(std::io::stdout)
-        .with_ansi(true)
-        .with_level(true)
-        .with_target(false);
+/// 索引有变化时把对照表写到库旁的 `<库名>.remap.csv`，没有变化时返回 `None`
+fn remap_sidecar(
+    loader: &LibraryLoader,
+    remap: &IndexRemap,
+) -> Option<crate::error::Result<PathBuf>> {
+    if remap.changes().is_empty() {
+        return None;
+    }
+    let path = companion_path(&loader.info()?.base_path, ".remap.csv");
+    Some(crate::commands::write_remap(&path, remap).map(|_| path))
+}
 
-    Registry::default()
-        .with(file_layer)
-        .with(console_layer)
-        .with(
-            tracing_subscriber::filter::Targets::new()
-                .with_target("library_editor", log_level)
-                .with_default(log_level),
-        )
-        .init();
+/// 读取当前库的动画序列，读取失败时在状态栏显示错误
+fn load_animations(
+    window: &AppWindow,
+    library_loader: &Mutex<Option<LibraryLoader>>,
+) -> Option<AnimationSet> {
+    match AnimationSet::load(&library_base(library_loader)?) {
+        Ok(animations) => Some(animations),
+        Err(e) => {
+            tracing::error!("读取动画序列失败: {:?}", e);
+            window.set_status_text(SharedString::from(&tr!("读取动画序列失败: {}", e)));
+            None
+        }
+    }
 }
 
-/// 运行 GUI 应用程序
-pub fn run() -> Result<()> {
-    use crate::error::LibraryError;
-    use crate::formats::LibraryLoader;
+/// 由界面上的 x、y、宽、高（格）构造地图区域
+fn map_region(x: i32, y: i32, width: i32, height: i32) -> MapRegion {
+    MapRegion {
+        x: x.max(0) as u32,
+        y: y.max(0) as u32,
+        width: width.max(1) as u32,
+        height: height.max(1) as u32,
+    }
+}
 
-    // 初始化日志
-    init_logging();
+/// 渲染地图区域并显示在地图对话框中，说明中列出缺少的资源库
+fn show_map_region(window: &AppWindow, view: &mut MapView, region: MapRegion) {
+    let region = region.clamp_to(&view.map);
+    let canvas = map::render(&view.map, &mut view.libraries, region);
+    window.set_map_preview(rgba_image_to_slint(&canvas).unwrap_or_default());
+    window.set_map_x(region.x as i32);
+    window.set_map_y(region.y as i32);
+    window.set_map_region_width(region.width as i32);
+    window.set_map_region_height(region.height as i32);
+
+    let mut summary = tr!(
+        "{}x{} 格，资源目录 {}",
+        view.map.width,
+        view.map.height,
+        view.libraries.dir().display()
+    );
+    let missing = view.libraries.missing(&view.map);
+    if !missing.is_empty() {
+        summary.push_str(&tr!("，缺少 {}", missing.join("、")));
+    }
+    window.set_map_summary(SharedString::from(summary));
+}
 
-    tracing::debug!("Library Editor GUI 启动");
-    tracing::debug!("初始化 Slint 组件");
+/// 在动画序列对话框中列出序列
+fn show_animations(window: &AppWindow, animations: &AnimationSet) {
+    let rows: Vec<slint::ModelRc<slint::StandardListViewItem>> = animations
+        .iter()
+        .map(|sequence| {
+            let cells: Vec<slint::StandardListViewItem> = [
+                sequence.name.clone(),
+                format!(
+                    "{} - {}",
+                    sequence.start,
+                    sequence.frames().end.saturating_sub(1)
+                ),
+                sequence.fps.to_string(),
+            ]
+            .iter()
+            .map(|text| slint::StandardListViewItem::from(text.as_str()))
+            .collect();
+            slint::ModelRc::new(slint::VecModel::from(cells))
+        })
+        .collect();
+    window.set_animation_rows(slint::ModelRc::new(slint::VecModel::from(rows)));
+}
 
-    // 创建主窗口
-    let window =
-        AppWindow::new().map_err(|e| LibraryError::Gui(format!("创建窗口失败: {:?}", e)))?;
+/// 修改当前库的动画序列并写回，刷新对话框中的列表，结果显示在状态栏
+fn edit_animations(
+    window: &AppWindow,
+    library_loader: &Mutex<Option<LibraryLoader>>,
+    edit: impl FnOnce(&mut AnimationSet) -> Result<String>,
+) {
+    let Some(base) = library_base(library_loader) else {
+        return;
+    };
+    let result = AnimationSet::load(&base).and_then(|mut animations| {
+        let message = edit(&mut animations)?;
+        animations.save(&base)?;
+        show_animations(window, &animations);
+        Ok(message)
+    });
+    let status = match result {
+        Ok(message) => message,
+        Err(e) => {
+            tracing::error!("修改动画序列失败: {:?}", e);
+            tr!("修改动画序列失败: {}", e)
+        }
+    };
+    window.set_status_text(SharedString::from(status));
+}
 
-    // 创建应用状态
-    let state = AppState::new();
+/// 在格式说明对话框中显示第 `index` 种格式
+///
+/// 当前打开的库正是该格式时，读取文件头在字段旁标出实际值。
+fn show_format_doc(window: &AppWindow, index: usize, library: Option<(PathBuf, LibraryType)>) {
+    let index = index.min(spec::SPECS.len() - 1);
+    let format = &spec::SPECS[index];
+    let names: Vec<SharedString> = spec::SPECS
+        .iter()
+        .map(|s| SharedString::from(format!("{} ({})", s.name, s.files)))
+        .collect();
+    window.set_format_doc_names(slint::ModelRc::new(slint::VecModel::from(names)));
+    window.set_format_doc_index(index as i32);
+    window.set_format_doc_summary(SharedString::from(format.summary));
+
+    let mut file_name = String::new();
+    let values = match library {
+        Some((path, lib_type)) if spec::spec_for(lib_type) == Some(format) => {
+            file_name = path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            spec::annotate(&path, lib_type).unwrap_or_else(|e| {
+                tracing::warn!("读取格式字段失败 {:?}: {}", path, e);
+                Default::default()
+            })
+        }
+        _ => Default::default(),
+    };
+    window.set_format_doc_file(SharedString::from(file_name));
+
+    let row = |cells: [&str; 4]| {
+        let cells: Vec<slint::StandardListViewItem> = cells
+            .iter()
+            .map(|text| slint::StandardListViewItem::from(*text))
+            .collect();
+        slint::ModelRc::new(slint::VecModel::from(cells))
+    };
+    let mut rows = Vec::new();
+    for section in format.sections {
+        rows.push(row(["", "", &format!("【{}】", section.title), ""]));
+        for field in section.fields {
+            let value = values.get(field.id).map(String::as_str).unwrap_or("");
+            rows.push(row([field.offset, field.kind, field.description, value]));
+        }
+    }
+    window.set_format_doc_rows(slint::ModelRc::new(slint::VecModel::from(rows)));
+}
 
-    // 设置初始状态
-    window.set_status_text(SharedString::from("就绪"));
-    window.set_file_name(SharedString::from(""));
-    window.set_image_count(0);
-    window.set_current_index(-1);
-    window.set_image_width(0);
-    window.set_image_height(0);
-    window.set_image_x(0);
-    window.set_image_y(0);
-    window.set_image_format(SharedString::from("-"));
-    window.set_load_progress(0);
-    window.set_is_loading(false);
-    window.set_loaded_count(0);
+/// 自检对话框中的资源目录（分号分隔，忽略空项）
+fn parse_asset_dirs(text: &str) -> Vec<PathBuf> {
+    text.split(';')
+        .map(str::trim)
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .collect()
+}
 
-    tracing::debug!("初始状态设置完成");
+/// 在后台检查资源目录，结果填入自检对话框
+///
+/// `popup` 为真时（启动时的自动检查）只在发现问题时弹出对话框。
+fn start_self_test(window: &AppWindow, dirs: Vec<PathBuf>, popup: bool) {
+    window.set_self_test_busy(true);
+    window.set_self_test_summary(SharedString::from(tr!("正在检查...")));
+    window.set_self_test_rows(Default::default());
 
-    // 克隆窗口弱引用用于回调
     let window_weak = window.as_weak();
+    std::thread::spawn(move || {
+        let reports = integrity::scan(&dirs);
+        let _ = window_weak.upgrade_in_event_loop(move |window| {
+            show_self_test_reports(&window, &reports, popup);
+        });
+    });
+}
 
-    // 设置打开文件回调
-    {
-        let window_weak = window_weak.clone();
-        let library_loader = state.library_loader.clone();
-        let thumbnail_cache = state.thumbnail_cache.clone();
-        let settings = state.settings.clone();
+/// 把自检结果填入对话框：每个有问题的文件一行
+fn show_self_test_reports(window: &AppWindow, reports: &[FileReport], popup: bool) {
+    let problems: Vec<&FileReport> = reports.iter().filter(|r| !r.is_ok()).collect();
+    let summary = tr!(
+        "已检查 {} 个库，{} 个有问题",
+        locale::count(reports.len() as u64),
+        locale::count(problems.len() as u64)
+    );
 
-        window.on_open_file(move || {
-            tracing::debug!("用户触发打开文件操作");
+    let rows: Vec<slint::ModelRc<slint::StandardListViewItem>> = problems
+        .iter()
+        .map(|report| {
+            let cells: Vec<slint::StandardListViewItem> = [
+                report.path.display().to_string(),
+                report.format.unwrap_or(tr!("未知")).to_string(),
+                locale::count(report.frames as u64),
+                report.problems.join("；"),
+            ]
+            .iter()
+            .map(|text| slint::StandardListViewItem::from(text.as_str()))
+            .collect();
+            slint::ModelRc::new(slint::VecModel::from(cells))
+        })
+        .collect();
+    window.set_self_test_rows(slint::ModelRc::new(slint::VecModel::from(rows)));
+    window.set_self_test_summary(SharedString::from(&summary));
+    window.set_self_test_busy(false);
+    window.set_status_text(SharedString::from(&tr!("完整性自检: {}", summary)));
+    if !problems.is_empty() && popup {
+        window.set_show_self_test(true);
+    }
+}
 
-            let window = match window_weak.upgrade() {
-                Some(w) => w,
-                None => {
-                    tracing::error!("无法获取窗口引用");
-                    return;
-                }
-            };
+/// 按导航快捷键切换当前帧（上下移动按缩略图网格的列数）
+fn navigate(
+    window: &AppWindow,
+    library_loader: &Arc<Mutex<Option<LibraryLoader>>>,
+    action: Action,
+) {
+    let image_count = window.get_image_count();
+    if image_count == 0 {
+        return;
+    }
+    let current = window.get_current_index();
+    let cols = window.get_thumb_cols().max(1);
+    let last = image_count - 1;
+    let new_index = match action {
+        Action::PreviousFrame => (current - 1).max(0),
+        Action::NextFrame => (current + 1).min(last),
+        Action::PreviousRow if current >= cols => current - cols,
+        Action::NextRow if current + cols <= last => current + cols,
+        Action::FirstFrame => 0,
+        Action::LastFrame => last,
+        Action::PageUp => (current - PAGE_STEP).max(0),
+        Action::PageDown => (current + PAGE_STEP).min(last),
+        Action::LargePageUp => (current - LARGE_PAGE_STEP).max(0),
+        Action::LargePageDown => (current + LARGE_PAGE_STEP).min(last),
+        _ => current,
+    };
+
+    if new_index != current {
+        tracing::debug!("切换到图像: {}", new_index);
+        if let Some(ref mut loader) = *library_loader.lock().unwrap() {
+            select_frame(window, loader, new_index as usize);
+        }
+    }
+}
 
-            window.set_status_text(SharedString::from("正在选择文件..."));
+/// 播放或停止动画：在动画序列对话框中的范围内（未设置时为全部帧）按其帧率循环切换帧
+fn toggle_playback(
+    window: &AppWindow,
+    timer: &Rc<slint::Timer>,
+    library_loader: &Arc<Mutex<Option<LibraryLoader>>>,
+) {
+    if timer.running() {
+        timer.stop();
+        window.set_status_text(SharedString::from(tr!("已停止播放")));
+        return;
+    }
+    let count = window.get_image_count();
+    if count <= 0 {
+        return;
+    }
+    let (start, end) = match (window.get_animation_start(), window.get_animation_end()) {
+        (start, end) if start >= 0 && start < end && end < count => (start, end),
+        _ => (0, count - 1),
+    };
+    let fps = window.get_animation_fps().clamp(1, MAX_PLAYBACK_FPS);
 
-            // 调用文件对话框
-            tracing::debug!("打开文件对话框");
-            let path = match rfd::FileDialog::new()
-                .add_filter("传奇库文件", &["lib", "wzl", "wil", "wtl"])
-                .add_filter("所有文件", &["*"])
-                .set_title("打开库文件")
-                .pick_file()
-            {
-                Some(p) => p,
-                None => {
-                    tracing::debug!("用户取消了文件选择");
-                    window.set_status_text(SharedString::from("未选择文件"));
-                    return;
-                }
+    let window_weak = window.as_weak();
+    let library_loader = library_loader.clone();
+    let timer_weak = Rc::downgrade(timer);
+    timer.start(
+        slint::TimerMode::Repeated,
+        Duration::from_millis(1000 / fps as u64),
+        move || {
+            let Some(window) = window_weak.upgrade() else {
+                return;
             };
-
-            // 清理已加载的数据
-            tracing::debug!("清理旧数据...");
-            // 清理缩略图缓存
-            *thumbnail_cache.lock().unwrap() = None;
-
-            // 清理 UI 数据（先重置 image_count 为 0，触发 Slint 端的滚动重置）
-            window.set_image_count(0);
-            window.set_thumbnails(slint::ModelRc::new(slint::VecModel::from(vec![])));
-            window.set_main_preview(slint::Image::default());
-            window.set_current_index(0);
-
-            tracing::debug!("选择的文件: {:?}", path);
-            window.set_status_text(SharedString::from("正在加载..."));
-
-            // 加载库文件
-            match LibraryLoader::load(&path) {
-                Ok((info, mut loader)) => {
-                    tracing::debug!("库文件加载成功: {}", info.file_name);
-                    tracing::debug!("  格式: {}", info.format_name());
-                    tracing::debug!("  图像数: {}", info.image_count);
-
-                    // 更新 UI
-                    window.set_file_name(SharedString::from(&info.file_name));
-                    window.set_image_count(info.image_count as i32);
-                    window.set_image_format(SharedString::from(&info.format_name()));
-                    window.set_current_index(if info.image_count > 0 { 0 } else { -1 });
-
-                    // 初始化空的缩略图数组
-                    let empty_thumbnails: Vec<slint::Image> =
-                        vec![slint::Image::default(); info.image_count];
-                    let model = slint::VecModel::from(empty_thumbnails);
-                    window.set_thumbnails(slint::ModelRc::new(model));
-                    window.set_loaded_count(0);
-
-                    // 加载第一张图像信息
-                    if info.image_count > 0 {
-                        tracing::debug!("加载第一张图像信息");
-                        if let Ok(img_info) = loader.get_image_info(0) {
-                            window.set_image_width(img_info.width);
-                            window.set_image_height(img_info.height);
-                            window.set_image_x(img_info.x);
-                            window.set_image_y(img_info.y);
-                            tracing::debug!("图像尺寸: {}x{}", img_info.width, img_info.height);
-                        }
-                        // 更新主预览图
-                        AppState::update_main_preview(&window, &mut loader, 0);
-                    } else {
-                        // 没有图像，清空主预览
-                        window.set_main_preview(slint::Image::default());
+            let current = window.get_current_index();
+            let next = if current < start || current >= end {
+                start
+            } else {
+                current + 1
+            };
+            match *library_loader.lock().unwrap() {
+                Some(ref mut loader) if (next as usize) < loader.image_count() => {
+                    select_frame(&window, loader, next as usize);
+                }
+                // 库已关闭或帧数变少
+                _ => {
+                    if let Some(timer) = timer_weak.upgrade() {
+                        timer.stop();
                     }
+                }
+            }
+        },
+    );
+    window.set_status_text(SharedString::from(&tr!(
+        "正在播放 #{} - #{}（{} 帧/秒）",
+        start,
+        end,
+        fps
+    )));
+}
 
-                    // 创建缩略图缓存
-                    let cache = Arc::new(ThumbnailCache::new(info.image_count, settings.clone()));
+/// 在快捷键对话框中列出各操作的快捷键
+fn show_shortcuts(window: &AppWindow, keymap: &Keymap) {
+    let strings = |items: Vec<String>| {
+        let items: Vec<SharedString> = items.into_iter().map(SharedString::from).collect();
+        slint::ModelRc::new(slint::VecModel::from(items))
+    };
+    let fixed = keymap::fixed_shortcuts();
+    window.set_shortcut_labels(strings(
+        Action::ALL.iter().map(|a| a.label().to_string()).collect(),
+    ));
+    window.set_shortcut_keys(strings(
+        Action::ALL.iter().map(|a| keymap.keys(*a)).collect(),
+    ));
+    window.set_shortcut_fixed_keys(strings(fixed.iter().map(|(k, _)| k.to_string()).collect()));
+    window.set_shortcut_fixed_labels(strings(
+        fixed.iter().map(|(_, label)| label.to_string()).collect(),
+    ));
+    window.set_shortcut_error(SharedString::new());
+}
 
-                    // 保存引用
-                    *library_loader.lock().unwrap() = Some(loader);
-                    *thumbnail_cache.lock().unwrap() = Some(Arc::clone(&cache));
+/// 在问题面板中列出问题（最新的在前）
+fn show_problems(window: &AppWindow, problems: &VecDeque<Problem>) {
+    let texts: Vec<SharedString> = problems
+        .iter()
+        .rev()
+        .map(|p| SharedString::from(p.message.as_str()))
+        .collect();
+    let frames: Vec<i32> = problems
+        .iter()
+        .rev()
+        .map(|p| p.frame.map_or(-1, |frame| frame as i32))
+        .collect();
+    let errors: Vec<bool> = problems.iter().rev().map(|p| p.error).collect();
+    window.set_problem_texts(slint::ModelRc::new(slint::VecModel::from(texts)));
+    window.set_problem_frames(slint::ModelRc::new(slint::VecModel::from(frames)));
+    window.set_problem_errors(slint::ModelRc::new(slint::VecModel::from(errors)));
+    window.set_problem_count(problems.len() as i32);
+    window.set_problem_error_count(problems.iter().filter(|p| p.error).count() as i32);
+}
 
-                    window.set_status_text(SharedString::from(&format!(
-                        "已打开: {} ({} 张图像)",
-                        info.file_name, info.image_count
-                    )));
-                }
-                Err(e) => {
-                    tracing::error!("加载库文件失败: {:?}", e);
-                    window.set_status_text(SharedString::from(&format!("加载失败: {}", e)));
+/// 在属性面板中显示帧的尺寸、偏移以及阴影和遮罩属性
+fn show_image_info(window: &AppWindow, info: &ImageInfo) {
+    window.set_image_width(info.width);
+    window.set_image_height(info.height);
+    window.set_image_x(info.x);
+    window.set_image_y(info.y);
+    let properties = FrameProperties::from(&info.has_mask);
+    window.set_image_shadow(properties.shadow as i32);
+    window.set_image_shadow_x(properties.shadow_x as i32);
+    window.set_image_shadow_y(properties.shadow_y as i32);
+    window.set_image_has_mask(properties.has_mask);
+    window.set_image_mask_x(properties.mask_x as i32);
+    window.set_image_mask_y(properties.mask_y as i32);
+}
 
-                    // 清空状态
-                    window.set_file_name(SharedString::from(""));
-                    window.set_image_count(0);
-                    window.set_current_index(-1);
-                    window.set_image_width(0);
-                    window.set_image_height(0);
-                    window.set_main_preview(slint::Image::default());
-                }
-            }
-        });
+/// 选中指定帧：更新当前索引、图像信息和主预览
+fn select_frame(window: &AppWindow, loader: &mut LibraryLoader, index: usize) {
+    window.set_current_index(index as i32);
+    if let Ok(img_info) = loader.get_image_info(index) {
+        show_image_info(window, &img_info);
+    }
+    AppState::update_main_preview(window, loader, index);
+}
+
+/// 读取已保存文件的帧头（合成的库或文件不可读时返回 `None`）
+fn read_file_header(loader: &LibraryLoader) -> Option<LibraryHeader> {
+    let path = loader.info()?.path();
+    LibraryLoader::open_header(&path)
+        .inspect_err(|e| tracing::debug!("无法读取文件头: {:?} {:?}", path, e))
+        .ok()
+}
+
+/// 统计已保存文件中每帧数据大小的分布
+fn size_histogram(loader: &LibraryLoader) -> Option<SizeHistogram> {
+    read_file_header(loader).map(|header| header.size_histogram(SIZE_HISTOGRAM_BINS))
+}
+
+/// 刷新属性面板中的数据大小直方图
+fn show_size_histogram(window: &AppWindow, header: Option<&LibraryHeader>) {
+    let histogram = header
+        .map(|header| header.size_histogram(SIZE_HISTOGRAM_BINS))
+        .unwrap_or_default();
+    let peak = histogram.peak().max(1) as f32;
+    let bars: Vec<f32> = histogram
+        .bins
+        .iter()
+        .map(|bin| bin.frames.len() as f32 / peak)
+        .collect();
+    let labels: Vec<SharedString> = histogram
+        .bins
+        .iter()
+        .map(|bin| {
+            SharedString::from(tr!(
+                "{} - {} 字节: {} 帧",
+                locale::count(bin.min),
+                locale::count(bin.max),
+                locale::count(bin.frames.len() as u64)
+            ))
+        })
+        .collect();
+    let outlier_bars: Vec<bool> = histogram
+        .bins
+        .iter()
+        .map(|bin| {
+            bin.frames
+                .iter()
+                .any(|index| histogram.outliers.binary_search(index).is_ok())
+        })
+        .collect();
+
+    window.set_size_bars(slint::ModelRc::new(slint::VecModel::from(bars)));
+    window.set_size_labels(slint::ModelRc::new(slint::VecModel::from(labels)));
+    window.set_size_outlier_bars(slint::ModelRc::new(slint::VecModel::from(outlier_bars)));
+    window.set_size_summary(SharedString::from(&tr!(
+        "中位数 {}，点击柱条跳到对应的帧",
+        locale::size(histogram.median)
+    )));
+    window.set_size_outlier_count(histogram.outliers.len() as i32);
+}
+
+/// 刷新缩略图网格旁的索引分布条及缩略图上的空帧标记
+fn update_index_bar(window: &AppWindow, loader: &LibraryLoader, cache: &ThumbnailCache) {
+    let states = cache.frame_states(loader);
+    let bar = index_bar::render(&states, INDEX_BAR_ROWS);
+    window.set_index_bar(rgba_image_to_slint(&bar).unwrap_or_default());
+
+    let empty: Vec<bool> = states.iter().map(|s| *s == FrameState::Empty).collect();
+    window.set_empty_count(empty.iter().filter(|&&e| e).count() as i32);
+    window.set_empty_frames(slint::ModelRc::new(slint::VecModel::from(empty)));
+}
+
+/// 按当前帧刷新对比视图：对比库中同一索引的帧，或与主库帧叠加 / 求差异
+fn update_compare_view(
+    window: &AppWindow,
+    loader: Option<&mut LibraryLoader>,
+    compare_loader: &mut LibraryLoader,
+) {
+    let Ok(index) = usize::try_from(window.get_current_index()) else {
+        window.set_compare_preview(slint::Image::default());
+        window.set_compare_status(SharedString::from(""));
+        return;
+    };
+
+    let placed = |loader: &mut LibraryLoader| {
+        loader.get_placed(index).unwrap_or_else(|e| {
+            tracing::warn!(frame = index, "获取对比帧 {} 失败: {:?}", index, e);
+            None
+        })
+    };
+    let primary = loader.and_then(&placed);
+    let other = placed(compare_loader);
+    fn as_placed(frame: &Option<PlacedFrame>) -> Option<Placed<'_>> {
+        frame
+            .as_ref()
+            .map(|(image, offset)| Placed::new(image, *offset))
+    }
+
+    let mode = CompareMode::from_index(window.get_compare_mode());
+    let preview = compare::render(mode, as_placed(&primary), as_placed(&other))
+        .and_then(|image| rgba_image_to_slint(&image));
+    window.set_compare_preview(preview.unwrap_or_default());
+
+    let result = match compare::diff(as_placed(&primary), as_placed(&other)) {
+        FrameDiff::BothEmpty => tr!("两边都是空帧").to_string(),
+        FrameDiff::OnlyPrimary if index >= compare_loader.image_count() => {
+            tr!("对比库只有 {} 帧", compare_loader.image_count())
+        }
+        FrameDiff::OnlyPrimary => tr!("对比库中为空帧").to_string(),
+        FrameDiff::OnlyCompare => tr!("主库中为空帧").to_string(),
+        FrameDiff::Identical => tr!("相同").to_string(),
+        FrameDiff::Changed { pixels } => tr!("不同: {} 个像素", pixels),
+    };
+    let status = match &other {
+        Some((image, (x, y))) => format!(
+            "#{}  {}x{} ({}, {})  {}",
+            index,
+            image.width(),
+            image.height(),
+            x,
+            y,
+            result
+        ),
+        None => format!("#{}  {}", index, result),
+    };
+    window.set_compare_status(SharedString::from(&status));
+}
+
+/// 把图像复制到系统剪贴板，供外部画图程序粘贴
+fn copy_to_os_clipboard(
+    clipboard: &mut Option<arboard::Clipboard>,
+    img: &image::RgbaImage,
+) -> Result<()> {
+    let to_error = |e: arboard::Error| LibraryError::Gui(tr!("系统剪贴板: {}", e));
+    let clipboard = match clipboard {
+        Some(clipboard) => clipboard,
+        None => clipboard.insert(arboard::Clipboard::new().map_err(to_error)?),
+    };
+    clipboard
+        .set_image(arboard::ImageData {
+            width: img.width() as usize,
+            height: img.height() as usize,
+            bytes: std::borrow::Cow::Borrowed(img.as_raw()),
+        })
+        .map_err(to_error)
+}
+
+/// 读取要导入的图像，按偏好设置中的透明色处理
+fn open_import_image(path: &Path, settings: &AppSettings) -> image::ImageResult<image::RgbaImage> {
+    let mut image = image::open(path)?.to_rgba8();
+    let key = settings.preferences().import_key;
+    let keyed = key.apply(&mut image);
+    tracing::debug!(
+        "导入图像 {:?}: 透明色 {}，{} 个像素",
+        path,
+        key.name(),
+        keyed
+    );
+    Ok(image)
+}
+
+/// 把拖入的 PNG 从 `at` 帧起依次替换，超出末尾或 `at` 为 `None` 时追加
+fn import_dropped_images(
+    window: &AppWindow,
+    loader: &mut LibraryLoader,
+    paths: &[PathBuf],
+    at: Option<usize>,
+    thumbnail_cache: &Rc<Mutex<Option<Arc<ThumbnailCache>>>>,
+    settings: &Arc<AppSettings>,
+) {
+    let mut images = Vec::with_capacity(paths.len());
+    for path in paths {
+        match open_import_image(path, settings) {
+            Ok(img) => images.push(img),
+            Err(e) => {
+                tracing::error!("加载拖入的图像失败: {:?} {:?}", path, e);
+                window.set_status_text(SharedString::from(&tr!(
+                    "加载图像失败: {}: {}",
+                    path.display(),
+                    e
+                )));
+                return;
+            }
+        }
+    }
+
+    let count = loader.image_count();
+    let options = EncodeOptions {
+        preserve_black: settings.preferences().preserve_black,
+        ..EncodeOptions::default()
+    };
+    let written = match loader.put_frames(&images, at, options) {
+        Ok(written) => written,
+        Err(e) => {
+            tracing::error!("写入拖入的帧失败: {:?}", e);
+            window.set_status_text(SharedString::from(&tr!("导入失败: {}", e)));
+            return;
+        }
+    };
+
+    refresh_written_frames(window, loader, &written, thumbnail_cache, settings);
+    let appended = written.iter().filter(|&&index| index >= count).count();
+    window.set_status_text(SharedString::from(&tr!(
+        "已替换 {} 帧、追加 {} 帧，保存后生效",
+        written.len() - appended,
+        appended
+    )));
+}
+
+/// 被替换和新追加的帧重新生成缩略图，并选中第一个写入的帧
+fn refresh_written_frames(
+    window: &AppWindow,
+    loader: &mut LibraryLoader,
+    written: &[usize],
+    thumbnail_cache: &Rc<Mutex<Option<Arc<ThumbnailCache>>>>,
+    settings: &Arc<AppSettings>,
+) {
+    let mut thumbnails: Vec<slint::Image> = window.get_thumbnails().iter().collect();
+    thumbnails.resize(loader.image_count(), slint::Image::default());
+    for &index in written {
+        thumbnails[index] = thumbnails::frame_image(loader, index, settings.get_thumbnail_pixels());
+    }
+    let first = written.first().copied().unwrap_or(0);
+    refresh_frames(window, loader, thumbnail_cache, settings, thumbnails, first);
+}
+
+/// 按文件名把文件夹中的 PNG 替换或追加到当前库（规则见 `folder_import`）
+fn import_folder(
+    window: &AppWindow,
+    loader: &mut LibraryLoader,
+    dir: &Path,
+    thumbnail_cache: &Rc<Mutex<Option<Arc<ThumbnailCache>>>>,
+    settings: &Arc<AppSettings>,
+) {
+    let (key, preserve_black) = {
+        let preferences = settings.preferences();
+        (preferences.import_key, preferences.preserve_black)
+    };
+    let options = EncodeOptions {
+        preserve_black,
+        ..EncodeOptions::default()
+    };
+    let report = ImportPlan::scan(dir).and_then(|plan| {
+        if !plan.skipped.is_empty() {
+            tracing::info!("不导入: {}", plan.skipped.join(", "));
+        }
+        folder_import::apply(loader, dir, &plan, key, options)
+    });
+    let report = match report {
+        Ok(report) => report,
+        Err(e) => {
+            tracing::error!("从文件夹导入失败: {:?} {:?}", dir, e);
+            window.set_status_text(SharedString::from(&tr!("导入失败: {}", e)));
+            return;
+        }
+    };
+
+    let written: Vec<usize> = report
+        .replaced
+        .iter()
+        .chain(&report.appended)
+        .copied()
+        .collect();
+    if written.is_empty() {
+        window.set_status_text(SharedString::from(tr!(
+            "文件夹中没有可导入的 PNG（00017.png 替换第 17 帧，append_*.png 追加）"
+        )));
+        return;
+    }
+    refresh_written_frames(window, loader, &written, thumbnail_cache, settings);
+    window.set_status_text(SharedString::from(&tr!(
+        "已替换 {} 帧、追加 {} 帧，保存后生效",
+        report.replaced.len(),
+        report.appended.len()
+    )));
+}
+
+/// 计算范围内帧的基线对齐方案，并把对照图和说明写到基线对齐对话框
+fn show_alignment(
+    window: &AppWindow,
+    loader: &mut LibraryLoader,
+    start: i32,
+    end: i32,
+    horizontal: bool,
+) -> Option<AlignPlan> {
+    let range = start.max(0) as usize..end.max(0) as usize + 1;
+    let frames = match loader.placed_range(range) {
+        Ok(frames) => frames,
+        Err(e) => {
+            tracing::error!("读取对齐的帧失败: {:?}", e);
+            window.set_align_preview(slint::Image::default());
+            window.set_align_pending(0);
+            window.set_align_summary(SharedString::from(&tr!("读取帧失败: {}", e)));
+            return None;
+        }
+    };
+    let plan = baseline::plan(&frames, horizontal);
+    let preview = plan
+        .as_ref()
+        .and_then(|plan| baseline::preview(&frames, plan))
+        .and_then(|image| rgba_image_to_slint(&image));
+    window.set_align_preview(preview.unwrap_or_default());
+    window.set_align_pending(plan.as_ref().map_or(0, |plan| plan.frames.len() as i32));
+    let summary = match plan {
+        Some(ref plan) if plan.frames.is_empty() => tr!("基线 y = {}，各帧已对齐", plan.baseline),
+        Some(ref plan) => tr!(
+            "基线 y = {}，{} 帧需要修正",
+            plan.baseline,
+            plan.frames.len()
+        ),
+        None => tr!("范围内没有非空帧").to_string(),
+    };
+    window.set_align_summary(SharedString::from(summary));
+    plan
+}
+
+/// 把体积分析的结果写到体积分析对话框
+fn show_library_stats(window: &AppWindow, stats: &LibraryStats) {
+    let (stored, raw) = (stats.stored_bytes(), stats.raw_bytes());
+    window.set_stats_summary(SharedString::from(&tr!(
+        "{} 帧，文件中 {}，解码后 {} ({:.1}%)",
+        locale::count(stats.frames.len() as u64),
+        locale::size(stored),
+        locale::size(raw),
+        stored as f64 * 100.0 / raw.max(1) as f64
+    )));
+
+    let largest = stats.largest(STATS_LARGEST);
+    let labels: Vec<SharedString> = largest
+        .iter()
+        .map(|frame| {
+            SharedString::from(tr!(
+                "#{}  {}x{}  {}（解码后的 {:.1}%）",
+                frame.index,
+                frame.width,
+                frame.height,
+                locale::size(frame.stored),
+                frame.ratio() * 100.0
+            ))
+        })
+        .collect();
+    let frames: Vec<i32> = largest.iter().map(|frame| frame.index as i32).collect();
+    window.set_stats_largest_labels(slint::ModelRc::new(slint::VecModel::from(labels)));
+    window.set_stats_largest_frames(slint::ModelRc::new(slint::VecModel::from(frames)));
+
+    let peak = stats.dimensions.iter().copied().max().unwrap_or(0).max(1) as f32;
+    let bars: Vec<f32> = stats
+        .dimensions
+        .iter()
+        .map(|&count| count as f32 / peak)
+        .collect();
+    let labels: Vec<SharedString> = stats
+        .dimensions
+        .iter()
+        .enumerate()
+        .map(|(bin, &count)| {
+            SharedString::from(match analyze::DIMENSION_LIMITS.get(bin) {
+                Some(limit) => tr!("<= {}: {} 帧", limit, count),
+                None => tr!("> {}: {} 帧", analyze::DIMENSION_LIMITS[bin - 1], count),
+            })
+        })
+        .collect();
+    window.set_stats_dimension_bars(slint::ModelRc::new(slint::VecModel::from(bars)));
+    window.set_stats_dimension_labels(slint::ModelRc::new(slint::VecModel::from(labels)));
+
+    let duplicates = if stats.duplicates.is_empty() {
+        tr!("没有重复帧").to_string()
+    } else {
+        let groups: Vec<String> = stats
+            .duplicates
+            .iter()
+            .map(|group| {
+                let indices: Vec<String> = group.iter().map(|i| format!("#{}", i)).collect();
+                indices.join(" = ")
+            })
+            .collect();
+        tr!("{} 组: {}", stats.duplicates.len(), groups.join("; "))
+    };
+    window.set_stats_duplicates(SharedString::from(&duplicates));
+
+    let savings: Vec<SharedString> = [
+        tr!("裁掉透明边: {}", locale::size(stats.savings.trim)),
+        tr!("去掉重复帧: {}", locale::size(stats.savings.dedup)),
+        tr!("改用 zstd: {}", locale::size(stats.savings.zstd)),
+    ]
+    .into_iter()
+    .map(SharedString::from)
+    .collect();
+    window.set_stats_savings(slint::ModelRc::new(slint::VecModel::from(savings)));
+}
+
+/// 读取界面上的多选状态/// 读取界面上的多选状态
+fn read_selection(window: &AppWindow) -> Selection {
+    let anchor = usize::try_from(window.get_selection_anchor()).ok();
+    Selection::new(window.get_selection().iter(), anchor)
+}
+
+/// 把多选状态写回界面
+fn write_selection(window: &AppWindow, selection: &Selection) {
+    window.set_selection(slint::ModelRc::new(slint::VecModel::from(
+        selection.flags(),
+    )));
+    window.set_selected_count(if selection.is_multiple() {
+        selection.len() as i32
+    } else {
+        0
+    });
+    window.set_selection_anchor(selection.anchor().map_or(-1, |a| a as i32));
+}
+
+/// 把锁定标记写到界面
+fn write_locks(window: &AppWindow, loader: &LibraryLoader) {
+    let mut flags = vec![false; loader.image_count()];
+    for index in loader.locks().iter() {
+        if let Some(flag) = flags.get_mut(index) {
+            *flag = true;
+        }
+    }
+    window.set_locked_frames(slint::ModelRc::new(slint::VecModel::from(flags)));
+}
+
+/// 批量操作跳过锁定帧时附在状态栏消息后的说明
+fn locked_note(locked: &[usize]) -> String {
+    if locked.is_empty() {
+        return String::new();
+    }
+    tr!("（跳过 {} 个锁定帧）", locked.len())
+}
+
+/// 批量操作的目标帧：多选时为所选帧，否则为当前帧
+fn selected_frames(window: &AppWindow) -> Vec<usize> {
+    let selection = read_selection(window);
+    if selection.is_multiple() {
+        return selection.indices();
+    }
+    usize::try_from(window.get_current_index())
+        .map(|index| vec![index])
+        .unwrap_or_default()
+}
+
+/// 把帧清空为空帧并就地刷新缩略图（帧数不变，保留多选），在状态栏报告结果
+fn clear_frames_in_place(
+    window: &AppWindow,
+    loader: &mut LibraryLoader,
+    thumbnail_cache: &Rc<Mutex<Option<Arc<ThumbnailCache>>>>,
+    settings: &Arc<AppSettings>,
+    frames: &[usize],
+) {
+    let selection = read_selection(window);
+    let (_, locked) = loader.locks().split(frames);
+    match loader.clear_frames(frames) {
+        Ok(affected) => {
+            // 被清空的帧改用占位图
+            let mut thumbnails: Vec<slint::Image> = window.get_thumbnails().iter().collect();
+            for &index in &affected {
+                if let Some(slot) = thumbnails.get_mut(index) {
+                    *slot = thumbnails::frame_image(loader, index, settings.get_thumbnail_pixels());
+                }
+            }
+            let current = window.get_current_index().max(0) as usize;
+            refresh_frames(
+                window,
+                loader,
+                thumbnail_cache,
+                settings,
+                thumbnails,
+                current,
+            );
+            // 帧数不变，保留多选以便继续操作
+            write_selection(window, &selection);
+            window.set_status_text(SharedString::from(&tr!(
+                "已清空 {} 帧，保存后生效{}",
+                affected.len(),
+                locked_note(&locked)
+            )));
+        }
+        Err(e) => {
+            tracing::error!("清空帧失败: {:?}", e);
+            window.set_status_text(SharedString::from(&tr!("清空帧失败: {}", e)));
+        }
+    }
+}
+
+/// 帧增删后刷新界面：替换缩略图列表、重建缩略图缓存并选中 `select` 帧
+fn refresh_frames(
+    window: &AppWindow,
+    loader: &mut LibraryLoader,
+    thumbnail_cache: &Rc<Mutex<Option<Arc<ThumbnailCache>>>>,
+    settings: &Arc<AppSettings>,
+    thumbnails: Vec<slint::Image>,
+    select: usize,
+) {
+    let count = loader.image_count();
+    window.set_thumbnails(slint::ModelRc::new(slint::VecModel::from(thumbnails)));
+    window.set_image_count(count as i32);
+    window.set_trash_count(loader.trash().len() as i32);
+    write_selection(window, &Selection::default());
+    write_locks(window, loader);
+    bookmarks::write(window, loader);
+
+    // 后续帧的索引整体移动，旧缓存作废（文件中的空帧信息按来源索引沿用）
+    let (events_tx, events_rx) = progress::channel();
+    let cache = Arc::new(ThumbnailCache::new(count, settings.clone(), events_tx));
+    spawn_progress_listener(events_rx, cache.progress.clone(), window.as_weak());
+    if let Some(ref old) = *thumbnail_cache.lock().unwrap() {
+        cache.copy_file_header(old);
+    }
+    update_index_bar(window, loader, &cache);
+    thumbnails::replace_cache(thumbnail_cache, Some(cache));
+
+    if count == 0 {
+        window.set_current_index(-1);
+        window.set_main_preview(slint::Image::default());
+        return;
+    }
+
+    let index = select.min(count - 1);
+    window.set_current_index(index as i32);
+    if let Ok(img_info) = loader.get_image_info(index) {
+        show_image_info(window, &img_info);
+    }
+    AppState::update_main_preview(window, loader, index);
+}
+
+/// 载入参考背景图
+fn load_preview_bg_image(window: &AppWindow, path: &Path) -> Result<()> {
+    let img = image::open(path)?;
+    let slint_image = rgba_image_to_slint(&img.to_rgba8()).ok_or(LibraryError::InvalidImageData)?;
+    window.set_preview_bg_image(slint_image);
+    Ok(())
+}
+
+/// 按偏好设置恢复窗口尺寸、预览背景、缩略图大小和视图、洋葱皮不透明度、设置选项、界面语言、界面缩放和最近打开列表
+fn restore_preferences(window: &AppWindow, preferences: &Preferences) {
+    if let Some((width, height)) = preferences.window_size {
+        window
+            .window()
+            .set_size(slint::LogicalSize::new(width as f32, height as f32));
+    }
+
+    window.set_cache_max_size(preferences.cache_max_size.min(i32::MAX as u64) as i32);
+    window.set_key_throttle_ms(preferences.key_throttle_ms.min(i32::MAX as u64) as i32);
+    window.set_thumb_size(preferences.thumbnail_size as i32);
+    window.set_thumb_grid_view(preferences.thumbnail_grid);
+    window.set_onion_opacity(preferences.onion_opacity.clamp(10, 90) as i32);
+
+    if let Some([r, g, b]) = parse_hex_color(&preferences.preview_bg_color) {
+        window.set_preview_bg_color(slint::Color::from_rgb_u8(r, g, b));
+        window.set_preview_bg_color_text(SharedString::from(&preferences.preview_bg_color));
+    }
+    if let Some(path) = &preferences.preview_bg_image
+        && let Err(e) = load_preview_bg_image(window, path)
+    {
+        tracing::warn!("恢复参考背景失败 {:?}: {}", path, e);
+    }
+    // 参考图已不可用时退回默认背景
+    let mut mode = preferences.preview_bg_mode.clamp(0, PREVIEW_BG_MODES - 1);
+    if mode == PREVIEW_BG_MODES - 1 && window.get_preview_bg_image().size().width == 0 {
+        mode = 0;
+    }
+    window.set_preview_bg_mode(mode);
+
+    show_recent_files(window, &preferences.recent_files);
+
+    let key = preferences.import_key;
+    window.set_import_key(key.to_index() as i32);
+    window.set_preserve_black(preferences.preserve_black);
+    window.set_append_only(preferences.append_only);
+    window.set_indexed_png(preferences.indexed_png);
+    window.set_language(language::choice(preferences.language));
+    window.set_ui_scale(scale::choice(preferences.ui_scale));
+    scale::set_ui_scale(window.window(), preferences.ui_scale);
+    if let ChromaKey::Color {
+        rgb: [r, g, b],
+        tolerance,
+    } = key
+    {
+        window.set_import_key_color(SharedString::from(format!("#{:02x}{:02x}{:02x}", r, g, b)));
+        window.set_import_key_tolerance(tolerance as i32);
+    }
+
+    let asset_dirs = if preferences.asset_dirs.is_empty() {
+        integrity::configured_dirs()
+    } else {
+        preferences.asset_dirs.clone()
+    };
+    let asset_dirs: Vec<String> = asset_dirs
+        .iter()
+        .map(|dir| dir.display().to_string())
+        .collect();
+    window.set_self_test_dirs(SharedString::from(asset_dirs.join(";")));
+    window.set_self_test_on_startup(preferences.self_test_on_startup);
+}
+
+/// 把窗口尺寸、预览背景、缩略图大小和视图、洋葱皮不透明度记入偏好设置
+fn store_preferences(window: &AppWindow, preferences: &mut Preferences) {
+    let size = window
+        .window()
+        .size()
+        .to_logical(window.window().scale_factor());
+    // 最小化时尺寸为 0，保留上次的记录
+    if size.width >= 1.0 && size.height >= 1.0 {
+        preferences.window_size = Some((size.width.round() as u32, size.height.round() as u32));
+    }
+
+    let color = window.get_preview_bg_color();
+    preferences.preview_bg_mode = window.get_preview_bg_mode();
+    preferences.preview_bg_color = format!(
+        "#{:02X}{:02X}{:02X}",
+        color.red(),
+        color.green(),
+        color.blue()
+    );
+    preferences.thumbnail_size = window.get_thumb_size().max(0) as u32;
+    preferences.thumbnail_grid = window.get_thumb_grid_view();
+    preferences.onion_opacity = window.get_onion_opacity().clamp(10, 90) as u8;
+}
+
+/// 根据打开向导的输入构造打开选项
+fn wizard_options(
+    parser: i32,
+    offset: &str,
+    palette: i32,
+    palette_file: &str,
+) -> std::result::Result<OpenOptions, String> {
+    let library_type = *WIZARD_PARSERS
+        .get(parser.max(0) as usize)
+        .ok_or_else(|| tr!("无效的解析器: {}", parser))?;
+
+    let offset = offset.trim();
+    let index_offset = if offset.is_empty() {
+        None
+    } else {
+        let parsed = match offset
+            .strip_prefix("0x")
+            .or_else(|| offset.strip_prefix("0X"))
+        {
+            Some(hex) => u64::from_str_radix(hex, 16),
+            None => offset.parse::<u64>(),
+        };
+        Some(parsed.map_err(|_| tr!("无效的偏移量: {}", offset))?)
+    };
+
+    let palette = match palette {
+        1 => PaletteSource::Default,
+        2 if palette_file.is_empty() => return Err(tr!("请选择调色板文件").to_string()),
+        2 => PaletteSource::External(PathBuf::from(palette_file)),
+        _ => PaletteSource::Embedded,
+    };
+
+    Ok(OpenOptions {
+        library_type,
+        index_offset,
+        palette,
+        flip_vertical: false,
+    })
+}
+
+/// 将 RGBA 图像转换为 Slint Image
+fn rgba_image_to_slint(img: &image::RgbaImage) -> Option<slint::Image> {
+    rgba_image_to_pixels(img).map(slint::Image::from_rgba8)
+}
+
+/// 将 RGBA 图像复制为 Slint 像素缓冲（可在工作线程中生成）
+fn rgba_image_to_pixels(img: &image::RgbaImage) -> Option<ThumbnailPixels> {
+    let width = img.width();
+    let height = img.height();
+
+    if width == 0 || height == 0 {
+        return None;
+    }
+
+    Some(ThumbnailPixels::clone_from_slice(img.as_raw(), width, height))
+}
+
+/// 初始化日志 - 同时输出到控制台和文件，警告和错误另外收集到问题面板
+fn init_logging() -> ProblemLog {
+    use tracing::Level;
+    use tracing_subscriber::{Registry, layer::SubscriberExt, util::SubscriberInitExt};
+
+    let file_appender = rolling::daily("./logs", "library-editor.log");
+
+    // 根据编译配置选择日志级别
+    #[cfg(debug_assertions)]
+    let log_level = Level::DEBUG;
+    #[cfg(not(debug_assertions))]
+    let log_level = Level::INFO;
+
+    let file_layer = tracing_subscriber::fmt::layer()
+        .with_writer(file_appender)
+        .with_ansi(false)
+        .with_level(true)
+        .with_target(true);
+
+    let console_layer = tracing_subscriber::fmt::layer()
+        .with_writer(std::io::stdout)
+        .with_ansi(true)
+        .with_level(true)
+        .with_target(false);
+
+    let problems = ProblemLog::default();
+    Registry::default()
+        .with(file_layer)
+        .with(console_layer)
+        .with(problems.layer())
+        .with(
+            tracing_subscriber::filter::Targets::new()
+                .with_target("library_editor", log_level)
+                .with_default(log_level),
+        )
+        .init();
+    problems
+}
+
+/// 运行 GUI 应用程序，`open` 为启动后直接打开的库文件或项目文件
+pub fn run(bench_gui: bool, open: Option<PathBuf>) -> Result<()> {
+    // 初始化日志
+    let problem_log = init_logging();
+
+    tracing::debug!("Library Editor GUI 启动");
+    tracing::debug!("初始化 Slint 组件");
+
+    // 创建主窗口
+    let window =
+        AppWindow::new().map_err(|e| LibraryError::Gui(format!("创建窗口失败: {:?}", e)))?;
+
+    // 创建应用状态
+    let state = AppState::new();
+
+    // 界面语言已在启动时按 --lang、偏好设置和系统语言决定
+    language::apply(i18n::language());
+
+    // 设置初始状态
+    window.set_status_text(SharedString::from(tr!("就绪")));
+    window.set_file_name(SharedString::from(""));
+    window.set_image_count(0);
+    window.set_current_index(-1);
+    window.set_image_width(0);
+    window.set_image_height(0);
+    window.set_image_x(0);
+    window.set_image_y(0);
+    window.set_image_format(SharedString::from("-"));
+    window.set_load_progress(0);
+    window.set_is_loading(false);
+    window.set_loaded_count(0);
+    window.set_placeholder_policy(state.settings.get_placeholder_policy().to_index() as i32);
+    if let Some(checker) = PlaceholderPolicy::Checker.render() {
+        window.set_preview_checker(rgba_image_to_slint(&checker).unwrap_or_default());
+    }
+
+    restore_preferences(&window, &state.settings.preferences());
+
+    // 启动时检查资源目录，发现问题才弹出自检对话框
+    if window.get_self_test_on_startup() {
+        let dirs = parse_asset_dirs(&window.get_self_test_dirs());
+        if !dirs.is_empty() {
+            start_self_test(&window, dirs, true);
+        }
+    }
+
+    // 上次异常退出时留下了恢复日志，询问是否恢复（只提示最近的一份）
+    let pending = if bench_gui {
+        Vec::new()
+    } else {
+        journal::pending(&journal::journal_dir())
+    };
+    if let Some(offer) = pending.into_iter().next() {
+        window.set_recovery_message(SharedString::from(&tr!(
+            "上次退出前 {} 有未保存的修改，是否打开并恢复？丢弃后无法找回。",
+            offer.library.display()
+        )));
+        window.set_show_recovery_prompt(true);
+        *state.recovery_offer.lock().unwrap() = Some(offer);
+    }
+
+    tracing::debug!("初始状态设置完成");
+
+    // 克隆窗口弱引用用于回调
+    let window_weak = window.as_weak();
+
+    // 设置打开文件回调
+    {
+        let window_weak = window_weak.clone();
+        let thumbnail_cache = state.thumbnail_cache.clone();
+        let opening = state.opening.clone();
+        let settings = state.settings.clone();
+
+        window.on_open_file(move || {
+            tracing::debug!("用户触发打开文件操作");
+
+            let window = match window_weak.upgrade() {
+                Some(w) => w,
+                None => {
+                    tracing::error!("无法获取窗口引用");
+                    return;
+                }
+            };
+
+            window.set_status_text(SharedString::from(tr!("正在选择文件...")));
+
+            // 调用文件对话框
+            tracing::debug!("打开文件对话框");
+            let Some(path) = pick_library_file(&settings, tr!("打开库文件")) else {
+                tracing::debug!("用户取消了文件选择");
+                window.set_status_text(SharedString::from(tr!("未选择文件")));
+                return;
+            };
+
+            open_library(&window, path, &opening, &thumbnail_cache);
+        });
+    }
+
+    // 设置打开最近文件回调
+    {
+        let window_weak = window_weak.clone();
+        let thumbnail_cache = state.thumbnail_cache.clone();
+        let opening = state.opening.clone();
+        let settings = state.settings.clone();
+
+        window.on_open_recent(move |index| {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            let Some(path) = usize::try_from(index)
+                .ok()
+                .and_then(|i| settings.preferences().recent_files.get(i).cloned())
+            else {
+                return;
+            };
+
+            // 文件已被移动或删除时从列表中去掉
+            if !path.is_file() {
+                let recent_files = {
+                    let mut preferences = settings.preferences();
+                    preferences.remove_recent(&path);
+                    preferences.recent_files.clone()
+                };
+                settings.save_preferences();
+                show_recent_files(&window, &recent_files);
+                window.set_status_text(SharedString::from(&tr!(
+                    "文件不存在，已从最近打开列表移除: {}",
+                    path.display()
+                )));
+                return;
+            }
+
+            open_library(&window, path, &opening, &thumbnail_cache);
+        });
+    }
+
+    // 设置地图查看回调：打开 .map 后在对话框中按区域渲染
+    {
+        let window_weak = window_weak.clone();
+        let settings = state.settings.clone();
+        let map_view = state.map_view.clone();
+
+        window.on_open_map(move || {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            let Some(path) = settings
+                .file_dialog(DialogDir::Library)
+                .add_filter(tr!("传奇地图"), &[map::MAP_EXTENSION])
+                .set_title(tr!("打开地图"))
+                .pick_file()
+            else {
+                return;
+            };
+            settings.remember_dir(DialogDir::Library, &path);
+
+            let map = match MapFile::load(&path) {
+                Ok(map) => map,
+                Err(e) => {
+                    tracing::error!("读取地图失败 {:?}: {}", path, e);
+                    window.set_status_text(SharedString::from(&tr!("读取地图失败: {}", e)));
+                    return;
+                }
+            };
+            let mut view = MapView {
+                libraries: MapLibraries::new(&map::default_data_dir(&path)),
+                path,
+                map,
+            };
+            window.set_map_title(SharedString::from(
+                view.path
+                    .file_name()
+                    .map(|n| n.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+            ));
+            window.set_map_width(view.map.width as i32);
+            window.set_map_height(view.map.height as i32);
+            let region = map_region(
+                0,
+                0,
+                window.get_map_region_width(),
+                window.get_map_region_height(),
+            );
+            show_map_region(&window, &mut view, region);
+            *map_view.lock().unwrap() = Some(view);
+            window.set_show_map_dialog(true);
+        });
+    }
+    {
+        let window_weak = window_weak.clone();
+        let map_view = state.map_view.clone();
+
+        window.on_refresh_map(move |x, y, width, height| {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            if let Some(ref mut view) = *map_view.lock().unwrap() {
+                show_map_region(&window, view, map_region(x, y, width, height));
+            }
+        });
+    }
+    {
+        let window_weak = window_weak.clone();
+        let settings = state.settings.clone();
+        let map_view = state.map_view.clone();
+
+        window.on_pick_map_data(move || {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            let mut guard = map_view.lock().unwrap();
+            let Some(ref mut view) = *guard else {
+                return;
+            };
+            let Some(dir) = settings
+                .file_dialog(DialogDir::Library)
+                .set_title(tr!("选择地图资源目录（Tiles、SmTiles、Objects 所在目录）"))
+                .pick_folder()
+            else {
+                return;
+            };
+            view.libraries = MapLibraries::new(&dir);
+            let region = map_region(
+                window.get_map_x(),
+                window.get_map_y(),
+                window.get_map_region_width(),
+                window.get_map_region_height(),
+            );
+            show_map_region(&window, view, region);
+        });
+    }
+    {
+        let window_weak = window_weak.clone();
+        let settings = state.settings.clone();
+        let map_view = state.map_view.clone();
+
+        window.on_export_map(move |x, y, width, height| {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            let mut guard = map_view.lock().unwrap();
+            let Some(ref mut view) = *guard else {
+                return;
+            };
+            let Some(path) = settings
+                .file_dialog(DialogDir::Image)
+                .add_filter(tr!("PNG 图像"), &["png"])
+                .set_title(tr!("导出地图"))
+                .save_file()
+            else {
+                window.set_status_text(SharedString::from(tr!("导出取消")));
+                return;
+            };
+            settings.remember_dir(DialogDir::Image, &path);
+
+            let canvas = map::render(
+                &view.map,
+                &mut view.libraries,
+                map_region(x, y, width, height),
+            );
+            let status = match canvas.save(&path) {
+                Ok(()) => tr!("已导出地图: {}", path.display()),
+                Err(e) => {
+                    tracing::error!("导出地图失败: {:?}", e);
+                    tr!("导出地图失败: {}", e)
+                }
+            };
+            window.set_status_text(SharedString::from(status));
+        });
+    }
+
+    // 设置打开项目回调：读取项目文件，打开其中第一个存在的库
+    {
+        let window_weak = window_weak.clone();
+        let opening = state.opening.clone();
+        let thumbnail_cache = state.thumbnail_cache.clone();
+        let settings = state.settings.clone();
+        let project = state.project.clone();
+
+        window.on_open_project(move || {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            let Some(path) = settings
+                .file_dialog(DialogDir::Library)
+                .add_filter(tr!("项目文件"), &[PROJECT_EXTENSION])
+                .set_title(tr!("打开项目"))
+                .pick_file()
+            else {
+                return;
+            };
+            settings.remember_dir(DialogDir::Library, &path);
+            open_project(&window, path, &project, &opening, &thumbnail_cache);
+        });
+    }
+
+    // 设置把当前库加入项目回调：没有打开项目时新建项目文件
+    {
+        let window_weak = window_weak.clone();
+        let library_loader = state.library_loader.clone();
+        let settings = state.settings.clone();
+        let project = state.project.clone();
+
+        window.on_add_to_project(move || {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            let Some((library, _)) = open_library_kind(&library_loader) else {
+                window.set_status_text(SharedString::from(tr!("没有打开的库")));
+                return;
+            };
+
+            let mut project = project.lock().unwrap();
+            if project.is_none() {
+                let Some(path) = settings
+                    .file_dialog(DialogDir::Library)
+                    .add_filter(tr!("项目文件"), &[PROJECT_EXTENSION])
+                    .set_title(tr!("新建项目"))
+                    .save_file()
+                else {
+                    return;
+                };
+                settings.remember_dir(DialogDir::Library, &path);
+                *project = Some((path.with_extension(PROJECT_EXTENSION), Project::default()));
+            }
+            let Some((path, current)) = project.as_mut() else {
+                return;
+            };
+            let added = current.library_mut(&library);
+            if let Some(ref loader) = *library_loader.lock().unwrap() {
+                added.bookmarks.merge(loader.bookmarks());
+            }
+            match current.save(path) {
+                Ok(()) => window
+                    .set_status_text(SharedString::from(&tr!("已加入项目: {}", path.display()))),
+                Err(e) => {
+                    tracing::error!("保存项目失败 {:?}: {}", path, e);
+                    window.set_status_text(SharedString::from(&tr!("保存项目失败: {}", e)));
+                }
+            }
+            show_project(&window, project.as_ref());
+        });
+    }
+
+    // 设置打开项目中的库回调
+    {
+        let window_weak = window_weak.clone();
+        let opening = state.opening.clone();
+        let thumbnail_cache = state.thumbnail_cache.clone();
+        let project = state.project.clone();
+
+        window.on_open_project_library(move |index| {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            let path = project.lock().unwrap().as_ref().and_then(|(_, project)| {
+                let library = project.libraries.get(usize::try_from(index).ok()?)?;
+                Some(library.path.clone())
+            });
+            let Some(path) = path else {
+                return;
+            };
+            if !path.is_file() {
+                window.set_status_text(SharedString::from(&tr!("文件不存在: {}", path.display())));
+                return;
+            }
+            open_library(&window, path, &opening, &thumbnail_cache);
+        });
+    }
+
+    // 设置打开 URL 回调：在后台线程下载到缓存，完成后回到事件循环打开
+    {
+        let window_weak = window_weak.clone();
+
+        window.on_open_url(move |url, refresh| {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            let url = url.trim().to_string();
+            if !remote::is_url(&url) {
+                window.set_url_status(SharedString::from(tr!(
+                    "请输入 http:// 或 https:// 开头的地址"
+                )));
+                return;
+            }
+
+            window.set_url_busy(true);
+            window.set_url_status(SharedString::from(tr!("正在下载...")));
+            let window_weak = window.as_weak();
+            std::thread::spawn(move || {
+                let (path, error) = match remote::fetch(&url, refresh) {
+                    Ok(path) => (path.display().to_string(), String::new()),
+                    Err(e) => {
+                        tracing::error!("下载远程库失败: {}", e);
+                        (String::new(), e.to_string())
+                    }
+                };
+                let _ = window_weak.upgrade_in_event_loop(move |window| {
+                    window.invoke_url_downloaded(path.into(), error.into());
+                });
+            });
+        });
+    }
+
+    // 设置后台打开库完成回调
+    {
+        let window_weak = window_weak.clone();
+        let opening = state.opening.clone();
+        let library_loader = state.library_loader.clone();
+        let thumbnail_cache = state.thumbnail_cache.clone();
+        let settings = state.settings.clone();
+        let wizard_path = state.wizard_path.clone();
+        let library_watch = state.library_watch.clone();
+        let recovery = state.recovery.clone();
+        let journal_written = state.journal_written.clone();
+        let project = state.project.clone();
+
+        window.on_library_loaded(move || {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            // 被新的打开请求取代的旧任务结束时，当前任务尚无结果，不做处理
+            let mut opening = opening.lock().unwrap();
+            let result = opening
+                .as_ref()
+                .and_then(|task| task.result.lock().unwrap().take());
+            let (Some(result), Some(task)) = (result, opening.take()) else {
+                return;
+            };
+            drop(opening);
+            finish_open_library(
+                &window,
+                task,
+                result,
+                &library_loader,
+                &thumbnail_cache,
+                &settings,
+                &wizard_path,
+            );
+            bookmarks::merge_project(&window, &library_loader, &project);
+
+            // 选择了恢复时，把日志中的修改套用到刚打开的库上
+            if let Some(journal) = recovery.lock().unwrap().take() {
+                restore_journal(
+                    &window,
+                    journal,
+                    &library_loader,
+                    &thumbnail_cache,
+                    &settings,
+                    &mut journal_written.lock().unwrap(),
+                );
+            }
+
+            // 自动重新加载后回到原来的帧
+            let restore = library_watch
+                .lock()
+                .unwrap()
+                .as_mut()
+                .and_then(|watch| watch.restore_index.take());
+            if let Some(index) = restore.filter(|&i| i >= 0 && i < window.get_image_count()) {
+                window.invoke_goto_frame(SharedString::from(index.to_string()));
+                window.set_status_text(SharedString::from(tr!("库文件已在外部修改，已重新加载")));
+            }
+        });
+    }
+
+    // 设置取消打开回调
+    {
+        let window_weak = window_weak.clone();
+        let opening = state.opening.clone();
+
+        window.on_cancel_open(move || {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            if let Some(task) = opening.lock().unwrap().as_ref() {
+                tracing::debug!("用户取消加载: {:?}", task.path);
+                task.cancel.cancel();
+                window.set_status_text(SharedString::from(tr!("正在取消...")));
+            }
+        });
+    }
+
+    // 设置远程库下载完成回调
+    {
+        let window_weak = window_weak.clone();
+        let thumbnail_cache = state.thumbnail_cache.clone();
+        let opening = state.opening.clone();
+
+        window.on_url_downloaded(move |path, error| {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            window.set_url_busy(false);
+            if !error.is_empty() {
+                window.set_url_status(error);
+                return;
+            }
+
+            window.set_show_url_dialog(false);
+            open_library(
+                &window,
+                PathBuf::from(path.as_str()),
+                &opening,
+                &thumbnail_cache,
+            );
+        });
+    }
+
+    // 设置打开对比库回调
+    {
+        let window_weak = window_weak.clone();
+        let library_loader = state.library_loader.clone();
+        let compare_loader = state.compare_loader.clone();
+        let settings = state.settings.clone();
+
+        window.on_open_compare(move || {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            let Some(path) = pick_library_file(&settings, tr!("选择对比库")) else {
+                return;
+            };
+
+            // 与打开文件一致：已记住手动选项的文件按选项加载
+            let choices = OpenChoices::load(Path::new(OPEN_CHOICES_FILE));
+            let result = match choices.get(&path) {
+                Some(options) => LibraryLoader::load_with(&path, options),
+                None => LibraryLoader::load(&path),
+            };
+            let (info, mut compare) = match result {
+                Ok(loaded) => loaded,
+                Err(e) => {
+                    tracing::error!("加载对比库失败: {:?}", e);
+                    window.set_status_text(SharedString::from(&tr!("加载对比库失败: {}", e)));
+                    return;
+                }
+            };
+            compare.set_placeholder_policy(settings.get_placeholder_policy());
+
+            window.set_compare_file(SharedString::from(&tr!(
+                "{} ({} 帧)",
+                info.file_name,
+                info.image_count
+            )));
+            window.set_compare_active(true);
+            update_compare_view(
+                &window,
+                library_loader.lock().unwrap().as_mut(),
+                &mut compare,
+            );
+            *compare_loader.lock().unwrap() = Some(compare);
+            window.set_status_text(SharedString::from(&tr!(
+                "对比: {} ({})",
+                info.file_name,
+                info.format_name()
+            )));
+        });
+    }
+
+    // 设置刷新对比视图回调（切换帧或对比模式时）
+    {
+        let window_weak = window_weak.clone();
+        let library_loader = state.library_loader.clone();
+        let compare_loader = state.compare_loader.clone();
+
+        window.on_compare_update(move || {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            if let Some(ref mut compare) = *compare_loader.lock().unwrap() {
+                update_compare_view(&window, library_loader.lock().unwrap().as_mut(), compare);
+            }
+        });
+    }
+
+    // 设置关闭对比回调
+    {
+        let window_weak = window_weak.clone();
+        let compare_loader = state.compare_loader.clone();
+
+        window.on_close_compare(move || {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            *compare_loader.lock().unwrap() = None;
+            window.set_compare_active(false);
+            window.set_compare_preview(slint::Image::default());
+            window.set_compare_status(SharedString::from(""));
+        });
+    }
+
+    // 设置锁定/解锁帧回调（目标帧全部已锁定时解锁，否则锁定）
+    {
+        let window_weak = window_weak.clone();
+        let library_loader = state.library_loader.clone();
+
+        window.on_toggle_lock(move || {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            let frames = selected_frames(&window);
+            if frames.is_empty() {
+                window.set_status_text(SharedString::from(tr!("请先选择一张图像")));
+                return;
+            }
+
+            if let Some(ref mut loader) = *library_loader.lock().unwrap() {
+                let lock = !frames.iter().all(|&index| loader.is_locked(index));
+                match loader.set_locked(&frames, lock) {
+                    Ok(changed) => {
+                        write_locks(&window, loader);
+                        let total = loader.locks().len();
+                        let status = if lock {
+                            tr!("已锁定 {} 帧，共锁定 {} 帧", changed, total)
+                        } else {
+                            tr!("已解锁 {} 帧，共锁定 {} 帧", changed, total)
+                        };
+                        window.set_status_text(SharedString::from(&status));
+                    }
+                    Err(e) => {
+                        tracing::error!("写入锁定记录失败: {:?}", e);
+                        window.set_status_text(SharedString::from(&tr!("写入锁定记录失败: {}", e)));
+                    }
+                }
+            }
+        });
+    }
+
+    // 设置书签回调
+    bookmarks::install(&window, &state);
+
+    // 设置复制帧回调
+    {
+        let window_weak = window_weak.clone();
+        let library_loader = state.library_loader.clone();
+        let frame_clip = state.frame_clip.clone();
+
+        window.on_copy_frame(move || {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            let Ok(index) = usize::try_from(window.get_current_index()) else {
+                return;
+            };
+            let Some(ref mut loader) = *library_loader.lock().unwrap() else {
+                return;
+            };
+            match loader.copy_frame(index) {
+                Ok(Some(clip)) => {
+                    let status = tr!("已复制 #{} ({})", index, clip.describe());
+                    *frame_clip.lock().unwrap() = Some(clip);
+                    window.set_clip_available(true);
+                    window.set_status_text(SharedString::from(&status));
+                }
+                Ok(None) => {
+                    window.set_status_text(SharedString::from(&tr!(
+                        "#{} 是空帧，没有可复制的图像",
+                        index
+                    )));
+                }
+                Err(e) => {
+                    tracing::error!("复制帧失败: {:?}", e);
+                    window.set_status_text(SharedString::from(&tr!("复制帧失败: {}", e)));
+                }
+            }
+        });
+    }
+
+    // 设置粘贴帧回调
+    {
+        let window_weak = window_weak.clone();
+        let library_loader = state.library_loader.clone();
+        let frame_clip = state.frame_clip.clone();
+        let thumbnail_cache = state.thumbnail_cache.clone();
+        let settings = state.settings.clone();
+
+        window.on_paste_frame(move |append| {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            let Some(ref clip) = *frame_clip.lock().unwrap() else {
+                return;
+            };
+            let Some(ref mut loader) = *library_loader.lock().unwrap() else {
+                return;
+            };
+
+            let at = if append {
+                None
+            } else {
+                usize::try_from(window.get_current_index()).ok()
+            };
+            let index = match loader.paste_frame(clip, at) {
+                Ok(index) => index,
+                Err(e) => {
+                    tracing::error!("粘贴帧失败: {:?}", e);
+                    window.set_status_text(SharedString::from(&tr!("粘贴帧失败: {}", e)));
+                    return;
+                }
+            };
+
+            let mut thumbnails: Vec<slint::Image> = window.get_thumbnails().iter().collect();
+            thumbnails.resize(loader.image_count(), slint::Image::default());
+            thumbnails[index] =
+                thumbnails::frame_image(loader, index, settings.get_thumbnail_pixels());
+            refresh_frames(
+                &window,
+                loader,
+                &thumbnail_cache,
+                &settings,
+                thumbnails,
+                index,
+            );
+            window.set_status_text(SharedString::from(&tr!("已粘贴到 #{}，保存后生效", index)));
+        });
+    }
+
+    // 设置复制图像到系统剪贴板回调
+    {
+        let window_weak = window_weak.clone();
+        let library_loader = state.library_loader.clone();
+        let os_clipboard = state.os_clipboard.clone();
+
+        window.on_copy_image(move || {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            let Ok(index) = usize::try_from(window.get_current_index()) else {
+                return;
+            };
+            let Some(ref mut loader) = *library_loader.lock().unwrap() else {
+                return;
+            };
+            let img = match loader.get_frame(index) {
+                Ok(Some(img)) => img,
+                Ok(None) => {
+                    window.set_status_text(SharedString::from(&tr!(
+                        "#{} 是空帧，没有可复制的图像",
+                        index
+                    )));
+                    return;
+                }
+                Err(e) => {
+                    tracing::error!("读取帧失败: {:?}", e);
+                    window.set_status_text(SharedString::from(&tr!("读取帧失败: {}", e)));
+                    return;
+                }
+            };
+            match copy_to_os_clipboard(&mut os_clipboard.lock().unwrap(), &img) {
+                Ok(()) => window.set_status_text(SharedString::from(&tr!(
+                    "已复制 #{} 的图像到系统剪贴板 ({} x {})",
+                    index,
+                    img.width(),
+                    img.height()
+                ))),
+                Err(e) => {
+                    tracing::error!("复制到系统剪贴板失败: {:?}", e);
+                    window.set_status_text(SharedString::from(&tr!("复制失败: {}", e)));
+                }
+            }
+        });
+    }
+
+    // 设置快速预览回调（仅读取文件头）
+    {
+        let window_weak = window_weak.clone();
+        let header_path = state.header_path.clone();
+        let settings = state.settings.clone();
+
+        window.on_quick_preview(move || {
+            tracing::debug!("用户触发快速预览");
+
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            let Some(path) = pick_library_file(&settings, tr!("快速预览库文件")) else {
+                window.set_status_text(SharedString::from(tr!("未选择文件")));
+                return;
+            };
+
+            match LibraryLoader::open_header(&path) {
+                Ok(header) => {
+                    show_header_preview(&window, &header);
+                    window.set_status_text(SharedString::from(&tr!(
+                        "快速预览: {} ({} 帧)",
+                        header.info.file_name,
+                        header.frames.len()
+                    )));
+                    *header_path.lock().unwrap() = Some(path);
+                }
+                Err(e) => {
+                    tracing::error!("读取文件头失败: {:?}", e);
+                    window.set_status_text(SharedString::from(&tr!("读取文件头失败: {}", e)));
+                }
+            }
+        });
+    }
+
+    // 设置快速预览中“完整打开”回调
+    {
+        let window_weak = window_weak.clone();
+        let thumbnail_cache = state.thumbnail_cache.clone();
+        let opening = state.opening.clone();
+        let header_path = state.header_path.clone();
+
+        window.on_header_open_full(move || {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            window.set_show_header_preview(false);
+            let Some(path) = header_path.lock().unwrap().take() else {
+                return;
+            };
+
+            open_library(&window, path, &opening, &thumbnail_cache);
+        });
+    }
+
+    // 设置打开向导预览回调
+    {
+        let window_weak = window_weak.clone();
+        let wizard_path = state.wizard_path.clone();
+
+        window.on_wizard_sample(
+            move |parser, offset: SharedString, palette, palette_file: SharedString| {
+                let Some(window) = window_weak.upgrade() else {
+                    return;
+                };
+                let Some(path) = wizard_path.lock().unwrap().clone() else {
+                    return;
+                };
+
+                let options = match wizard_options(parser, &offset, palette, &palette_file) {
+                    Ok(options) => options,
+                    Err(msg) => {
+                        window.set_wizard_status(SharedString::from(&msg));
+                        return;
+                    }
+                };
+
+                match LibraryLoader::load_with(&path, &options) {
+                    Ok((info, mut loader)) => {
+                        let sample = (0..info.image_count.min(WIZARD_SAMPLE_FRAMES))
+                            .find_map(|i| loader.get_frame(i).ok().flatten().map(|img| (i, img)));
+                        match sample {
+                            Some((index, img)) => {
+                                window.set_wizard_preview(
+                                    rgba_image_to_slint(&img).unwrap_or_default(),
+                                );
+                                window.set_wizard_status(SharedString::from(&tr!(
+                                    "{}: {} 张图像，样例帧 #{} ({}x{})",
+                                    info.format_name(),
+                                    info.image_count,
+                                    index,
+                                    img.width(),
+                                    img.height()
+                                )));
+                            }
+                            None => {
+                                window.set_wizard_preview(slint::Image::default());
+                                window.set_wizard_status(SharedString::from(&tr!(
+                                    "{}: {} 张图像，未找到可显示的样例帧",
+                                    info.format_name(),
+                                    info.image_count
+                                )));
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        window.set_wizard_preview(slint::Image::default());
+                        window.set_wizard_status(SharedString::from(&tr!("加载失败: {}", e)));
+                    }
+                }
+            },
+        );
+    }
+
+    // 设置打开向导选择调色板文件回调
+    {
+        let window_weak = window_weak.clone();
+        let settings = state.settings.clone();
+
+        window.on_wizard_pick_palette(move || {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+
+            if let Some(path) = settings
+                .file_dialog(DialogDir::Library)
+                .add_filter(tr!("调色板/WIL 文件"), &["pal", "wil", "bin"])
+                .add_filter(tr!("所有文件"), &["*"])
+                .set_title(tr!("选择调色板文件"))
+                .pick_file()
+            {
+                settings.remember_dir(DialogDir::Library, &path);
+                window.set_wizard_palette_file(SharedString::from(path.display().to_string()));
+                window.invoke_wizard_sample(
+                    window.get_wizard_parser(),
+                    window.get_wizard_offset(),
+                    window.get_wizard_palette(),
+                    window.get_wizard_palette_file(),
+                );
+            }
+        });
+    }
+
+    // 设置打开向导确认回调
+    {
+        let window_weak = window_weak.clone();
+        let wizard_path = state.wizard_path.clone();
+        let library_loader = state.library_loader.clone();
+        let thumbnail_cache = state.thumbnail_cache.clone();
+        let settings = state.settings.clone();
+
+        window.on_wizard_confirm(
+            move |parser, offset: SharedString, palette, palette_file: SharedString, remember| {
+                let Some(window) = window_weak.upgrade() else {
+                    return;
+                };
+                let Some(path) = wizard_path.lock().unwrap().clone() else {
+                    return;
+                };
+
+                let options = match wizard_options(parser, &offset, palette, &palette_file) {
+                    Ok(options) => options,
+                    Err(msg) => {
+                        window.set_wizard_status(SharedString::from(&msg));
+                        return;
+                    }
+                };
+
+                match LibraryLoader::load_with(&path, &options) {
+                    Ok((info, loader)) => {
+                        if remember {
+                            let choices_path = Path::new(OPEN_CHOICES_FILE);
+                            let mut choices = OpenChoices::load(choices_path);
+                            choices.insert(&path, options);
+                            if let Err(e) = choices.save(choices_path) {
+                                tracing::warn!("保存打开选项失败: {:?}", e);
+                            }
+                        }
+
+                        window.set_show_open_wizard(false);
+                        *wizard_path.lock().unwrap() = None;
+                        add_recent_file(&window, &settings, &path);
+                        show_library(
+                            &window,
+                            info,
+                            loader,
+                            &library_loader,
+                            &thumbnail_cache,
+                            &settings,
+                        );
+                    }
+                    Err(e) => {
+                        window.set_wizard_status(SharedString::from(&tr!("加载失败: {}", e)));
+                    }
+                }
+            },
+        );
+    }
+
+    // 设置保存文件回调
+    {
+        let window_weak = window_weak.clone();
+        let library_loader = state.library_loader.clone();
+        let thumbnail_cache = state.thumbnail_cache.clone();
+        let library_watch = state.library_watch.clone();
+
+        window.on_save_file(move || {
+            tracing::debug!("用户触发保存文件操作");
+
+            let window = match window_weak.upgrade() {
+                Some(w) => w,
+                None => return,
+            };
+
+            // 检查是否有加载的库
+            let has_library = library_loader.lock().unwrap().is_some();
+
+            if !has_library {
+                tracing::warn!("没有加载的库文件");
+                window.set_status_text(SharedString::from(tr!("请先打开一个库文件")));
+                return;
+            }
+
+            window.set_status_text(SharedString::from(tr!("正在保存...")));
+
+            // 执行保存；删除或恢复帧使索引移动时，在库旁写出索引对照表
+            if let Some(ref mut loader) = *library_loader.lock().unwrap() {
+                let remap = loader.index_remap();
+                let saved = loader.save();
+                // 自己写文件触发的变化不算外部修改
+                if let Some(watch) = library_watch.lock().unwrap().as_mut() {
+                    watch.files.ignore_for(SAVE_IGNORE);
+                }
+                match saved {
+                    Ok(_) => {
+                        tracing::debug!("保存成功");
+                        window.set_trash_count(0);
+                        let header = read_file_header(loader);
+                        show_size_histogram(&window, header.as_ref());
+                        if let Some(ref cache) = *thumbnail_cache.lock().unwrap() {
+                            cache.set_file_header(header.as_ref());
+                            update_index_bar(&window, loader, cache);
+                        }
+                        let status = match remap_sidecar(loader, &remap) {
+                            None => tr!("保存成功").to_string(),
+                            Some(Ok(path)) => tr!(
+                                "保存成功，{} 帧索引有变化，对照表已写入 {}",
+                                locale::count(remap.changes().len() as u64),
+                                path.display()
+                            ),
+                            Some(Err(e)) => {
+                                tracing::error!("写出索引对照表失败: {:?}", e);
+                                tr!("保存成功，但写出索引对照表失败: {}", e)
+                            }
+                        };
+                        window.set_status_text(SharedString::from(&status));
+                    }
+                    Err(e) => {
+                        tracing::error!("保存失败: {:?}", e);
+                        window.set_status_text(SharedString::from(&tr!("保存失败: {}", e)));
+                    }
+                }
+            }
+        });
+    }
+
+    // 设置另存为文件回调
+    {
+        let window_weak = window_weak.clone();
+        let settings = state.settings.clone();
+
+        window.on_save_as_file(move || {
+            tracing::debug!("用户触发另存为操作");
+
+            let window = match window_weak.upgrade() {
+                Some(w) => w,
+                None => return,
+            };
+
+            match settings
+                .file_dialog(DialogDir::Library)
+                .add_filter(tr!("传奇库文件"), &["lib", "wzl", "wtl"])
+                .set_title(tr!("另存为"))
+                .save_file()
+            {
+                Some(path) => {
+                    settings.remember_dir(DialogDir::Library, &path);
+                    tracing::debug!("另存为: {:?}", path);
+                    window.set_status_text(SharedString::from(&tr!("已保存: {}", path.display())));
+                    // TODO: 实现另存为逻辑
+                }
+                None => {
+                    window.set_status_text(SharedString::from(tr!("保存取消")));
+                }
+            }
+        });
+    }
+
+    // 设置导出图像回调
+    {
+        let window_weak = window_weak.clone();
+        let settings = state.settings.clone();
+        let library_loader = state.library_loader.clone();
+
+        window.on_export_png(move || {
+            tracing::debug!("用户触发导出图像操作");
+
+            let window = match window_weak.upgrade() {
+                Some(w) => w,
+                None => return,
+            };
+
+            let current_index = window.get_current_index();
+            if current_index < 0 {
+                window.set_status_text(SharedString::from(tr!("请先选择一张图像")));
+                return;
+            }
+
+            // 选择保存路径（格式按扩展名判断）
+            let path = match settings
+                .file_dialog(DialogDir::Image)
+                .add_filter(tr!("PNG 图像"), &["png"])
+                .add_filter(tr!("BMP 图像"), &["bmp"])
+                .add_filter(tr!("TGA 图像"), &["tga"])
+                .add_filter(tr!("JPEG 图像"), &["jpg", "jpeg"])
+                .set_title(tr!("导出图像"))
+                .save_file()
+            {
+                Some(p) => p,
+                None => {
+                    window.set_status_text(SharedString::from(tr!("导出取消")));
+                    return;
+                }
+            };
+            settings.remember_dir(DialogDir::Image, &path);
+
+            // 导出图像（显示阴影时导出带阴影的渲染结果）；调色板格式的库导出 BMP 时
+            // 保留原调色板，写出 8 位 BMP；开启索引色 PNG 时 8 位帧写成索引色 PNG
+            if let Some(ref mut loader) = *library_loader.lock().unwrap() {
+                let format = ExportFormat::from_path(&path).unwrap_or(ExportFormat::Png);
+                let options = ExportOptions::default();
+                let result = if window.get_show_shadow() {
+                    loader
+                        .get_with_shadow(current_index as usize)
+                        .and_then(|img| img.ok_or(LibraryError::InvalidImageData))
+                        .and_then(|img| export::write_image(&img, &path, format, &options))
+                } else if format == ExportFormat::Bmp && loader.palette().is_some() {
+                    loader.export_image(current_index as usize, &path, ExportFormat::Bmp8, &options)
+                } else if format == ExportFormat::Png && window.get_indexed_png() {
+                    loader
+                        .export_indexed_png(current_index as usize, &path)
+                        .and_then(|written| match written {
+                            true => Ok(()),
+                            false => {
+                                loader.export_image(current_index as usize, &path, format, &options)
+                            }
+                        })
+                } else {
+                    loader.export_image(current_index as usize, &path, format, &options)
+                };
+                match result {
+                    Ok(_) => {
+                        tracing::debug!("导出成功: {:?}", path);
+                        window.set_status_text(SharedString::from(&tr!(
+                            "已导出: {}",
+                            path.display()
+                        )));
+                    }
+                    Err(e) => {
+                        tracing::error!("导出失败: {:?}", e);
+                        window.set_status_text(SharedString::from(&tr!("导出失败: {}", e)));
+                    }
+                }
+            }
+        });
+    }
+
+    // 设置导出视频回调：在界面线程对齐帧，在后台线程调用 ffmpeg 编码
+    {
+        let window_weak = window_weak.clone();
+        let settings = state.settings.clone();
+        let library_loader = state.library_loader.clone();
+
+        window.on_export_video(move |start, end, fps| {
+            tracing::debug!("用户触发导出视频: {} - {}, {} FPS", start, end, fps);
+
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+
+            let Some(path) = settings
+                .file_dialog(DialogDir::Export)
+                .add_filter(tr!("WebM 视频"), &["webm"])
+                .add_filter(tr!("MP4 视频"), &["mp4"])
+                .set_title(tr!("导出视频"))
+                .save_file()
+            else {
+                window.set_status_text(SharedString::from(tr!("导出取消")));
+                return;
+            };
+            settings.remember_dir(DialogDir::Export, &path);
+
+            let indices: Vec<usize> = (start.max(0) as usize..=end.max(0) as usize).collect();
+            let frames = match *library_loader.lock().unwrap() {
+                Some(ref mut loader) => loader.placed_frames(&indices),
+                None => return,
+            };
+            let frames = match frames {
+                Ok(frames) => frames,
+                Err(e) => {
+                    tracing::error!("读取帧失败: {:?}", e);
+                    window.set_status_text(SharedString::from(&tr!("导出失败: {}", e)));
+                    return;
+                }
+            };
+
+            let color = window.get_preview_bg_color();
+            let options = VideoOptions {
+                fps: fps.max(1) as u32,
+                background: [color.red(), color.green(), color.blue()],
+            };
+            window.set_status_text(SharedString::from(&tr!(
+                "正在导出视频 ({} 帧)...",
+                frames.len()
+            )));
+            let window_weak = window.as_weak();
+            std::thread::spawn(move || {
+                let status = match video::export(&frames, &path, options) {
+                    Ok(written) => tr!("已导出视频: {} ({} 帧)", path.display(), written),
+                    Err(e) => {
+                        tracing::error!("导出视频失败: {:?}", e);
+                        tr!("导出视频失败: {}", e)
+                    }
+                };
+                let _ = window_weak.upgrade_in_event_loop(move |window| {
+                    window.set_status_text(status.into());
+                });
+            });
+        });
+    }
+
+    // 设置动画序列回调：序列保存在当前库旁的 .anim.json 中，每次修改后立即写回
+    {
+        let window_weak = window_weak.clone();
+        let library_loader = state.library_loader.clone();
+
+        window.on_open_animations(move || {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            if window.get_image_count() <= 0 {
+                return;
+            }
+            // 以当前帧到末尾作为新序列的默认范围
+            let Some(animations) = load_animations(&window, &library_loader) else {
+                return;
+            };
+            show_animations(&window, &animations);
+            window.set_animation_name(SharedString::new());
+            window.set_animation_start(window.get_current_index().max(0));
+            window.set_animation_end(window.get_image_count() - 1);
+            window.set_show_animation_dialog(true);
+        });
+    }
+    {
+        let window_weak = window_weak.clone();
+        let library_loader = state.library_loader.clone();
+
+        window.on_select_animation(move |row| {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            let selected = load_animations(&window, &library_loader)
+                .and_then(|animations| animations.iter().nth(row.max(0) as usize).cloned());
+            if let Some(sequence) = selected {
+                window.set_animation_name(SharedString::from(&sequence.name));
+                window.set_animation_start(sequence.start as i32);
+                window.set_animation_end(sequence.frames().end.saturating_sub(1) as i32);
+                window.set_animation_fps(sequence.fps as i32);
+                window.set_status_text(SharedString::from(&tr!(
+                    "动画序列 {}: {} 帧",
+                    sequence.name,
+                    sequence.count
+                )));
+            }
+        });
+    }
+    {
+        let window_weak = window_weak.clone();
+        let library_loader = state.library_loader.clone();
+
+        window.on_save_animation(move |name, start, end, fps| {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            let sequence = Sequence {
+                name: name.trim().to_string(),
+                start: start.max(0) as usize,
+                count: (end - start + 1).max(0) as usize,
+                fps: fps.max(1) as u32,
+            };
+            edit_animations(&window, &library_loader, |animations| {
+                let message = tr!("已保存动画序列 {}", sequence.name);
+                animations.set(sequence)?;
+                Ok(message)
+            });
+        });
+    }
+    {
+        let window_weak = window_weak.clone();
+        let library_loader = state.library_loader.clone();
+
+        window.on_remove_animation(move |name| {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            edit_animations(&window, &library_loader, |animations| {
+                if !animations.remove(&name) {
+                    animations.require(&name)?;
+                }
+                Ok(tr!("已删除动画序列 {}", name))
+            });
+            window.set_animation_name(SharedString::new());
+        });
+    }
+
+    // 设置导出 GIF 回调：在界面线程对齐帧，在后台线程编码
+    {
+        let window_weak = window_weak.clone();
+        let settings = state.settings.clone();
+        let library_loader = state.library_loader.clone();
+
+        window.on_export_gif(move |start, end, fps| {
+            tracing::debug!("用户触发导出 GIF: {} - {}, {} FPS", start, end, fps);
+
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+
+            let Some(path) = settings
+                .file_dialog(DialogDir::Export)
+                .add_filter(tr!("GIF 动画"), &["gif"])
+                .set_title(tr!("导出 GIF"))
+                .save_file()
+            else {
+                window.set_status_text(SharedString::from(tr!("导出取消")));
+                return;
+            };
+            settings.remember_dir(DialogDir::Export, &path);
+
+            let indices: Vec<usize> = (start.max(0) as usize..=end.max(0) as usize).collect();
+            let frames = match *library_loader.lock().unwrap() {
+                Some(ref mut loader) => loader.placed_frames(&indices),
+                None => return,
+            };
+            let frames = match frames {
+                Ok(frames) => frames,
+                Err(e) => {
+                    tracing::error!("读取帧失败: {:?}", e);
+                    window.set_status_text(SharedString::from(&tr!("导出失败: {}", e)));
+                    return;
+                }
+            };
+
+            window.set_status_text(SharedString::from(&tr!(
+                "正在导出 GIF ({} 帧)...",
+                frames.len()
+            )));
+            let window_weak = window.as_weak();
+            std::thread::spawn(move || {
+                let status = match gif::export(&frames, &path, fps.max(1) as u32) {
+                    Ok(written) => tr!("已导出 GIF: {} ({} 帧)", path.display(), written),
+                    Err(e) => {
+                        tracing::error!("导出 GIF 失败: {:?}", e);
+                        tr!("导出 GIF 失败: {}", e)
+                    }
+                };
+                let _ = window_weak.upgrade_in_event_loop(move |window| {
+                    window.set_status_text(status.into());
+                });
+            });
+        });
+    }
+
+    // 设置图块拼接回调：刷新时在对话框中显示拼接结果，导出时写出整张 PNG
+    {
+        let window_weak = window_weak.clone();
+        let library_loader = state.library_loader.clone();
+
+        window.on_refresh_tiles(move |start, end, columns| {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            let grid = match *library_loader.lock().unwrap() {
+                Some(ref mut loader) => loader.tile_grid(
+                    start.max(0) as usize..end.max(0) as usize + 1,
+                    columns as u32,
+                ),
+                None => return,
+            };
+            let summary = match grid {
+                Ok(Some(grid)) => {
+                    window.set_tile_preview(rgba_image_to_slint(&grid).unwrap_or_default());
+                    tr!(
+                        "{} 块，{}x{}",
+                        (end - start + 1).max(0),
+                        grid.width(),
+                        grid.height()
+                    )
+                }
+                Ok(None) => {
+                    window.set_tile_preview(slint::Image::default());
+                    tr!("范围内没有图块").to_string()
+                }
+                Err(e) => {
+                    tracing::error!("拼接图块失败: {:?}", e);
+                    window.set_tile_preview(slint::Image::default());
+                    tr!("拼接失败: {}", e)
+                }
+            };
+            window.set_tile_summary(SharedString::from(summary));
+        });
+    }
+    {
+        let window_weak = window_weak.clone();
+        let settings = state.settings.clone();
+        let library_loader = state.library_loader.clone();
+
+        window.on_export_tiles(move |start, end, columns| {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+
+            let Some(path) = settings
+                .file_dialog(DialogDir::Image)
+                .add_filter(tr!("PNG 图像"), &["png"])
+                .set_title(tr!("导出拼接图"))
+                .save_file()
+            else {
+                window.set_status_text(SharedString::from(tr!("导出取消")));
+                return;
+            };
+            settings.remember_dir(DialogDir::Image, &path);
+
+            let grid = match *library_loader.lock().unwrap() {
+                Some(ref mut loader) => loader.tile_grid(
+                    start.max(0) as usize..end.max(0) as usize + 1,
+                    columns as u32,
+                ),
+                None => return,
+            };
+            let result = grid
+                .and_then(|grid| grid.ok_or(LibraryError::InvalidImageData))
+                .and_then(|grid| Ok(grid.save(&path)?));
+            let status = match result {
+                Ok(()) => tr!("已导出拼接图: {}", path.display()),
+                Err(e) => {
+                    tracing::error!("导出拼接图失败: {:?}", e);
+                    tr!("导出拼接图失败: {}", e)
+                }
+            };
+            window.set_status_text(SharedString::from(status));
+        });
+    }
+
+    // 基线对齐：刷新对照图、应用偏移修正
+    {
+        let window_weak = window_weak.clone();
+        let library_loader = state.library_loader.clone();
+
+        window.on_refresh_alignment(move |start, end, horizontal| {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            if let Some(ref mut loader) = *library_loader.lock().unwrap() {
+                show_alignment(&window, loader, start, end, horizontal);
+            }
+        });
+    }
+    {
+        let window_weak = window_weak.clone();
+        let library_loader = state.library_loader.clone();
+        let thumbnail_cache = state.thumbnail_cache.clone();
+
+        window.on_apply_alignment(move |start, end, horizontal| {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            let Some(ref mut loader) = *library_loader.lock().unwrap() else {
+                return;
+            };
+            let Some(plan) = show_alignment(&window, loader, start, end, horizontal) else {
+                return;
+            };
+            let frames: Vec<usize> = plan.frames.iter().map(|a| a.index).collect();
+            let (_, locked) = loader.locks().split(&frames);
+            match loader.apply_alignment(&plan) {
+                Ok(shifted) => {
+                    let current = window.get_current_index();
+                    if current >= 0 {
+                        if let Ok(img_info) = loader.get_image_info(current as usize) {
+                            show_image_info(&window, &img_info);
+                        }
+                        AppState::update_main_preview(&window, loader, current as usize);
+                    }
+                    if let Some(ref cache) = *thumbnail_cache.lock().unwrap() {
+                        update_index_bar(&window, loader, cache);
+                    }
+                    show_alignment(&window, loader, start, end, horizontal);
+                    window.set_status_text(SharedString::from(&tr!(
+                        "已对齐 {} 帧的基线，保存后生效{}",
+                        shifted.len(),
+                        locked_note(&locked)
+                    )));
+                }
+                Err(e) => {
+                    tracing::error!("基线对齐失败: {:?}", e);
+                    window.set_status_text(SharedString::from(&tr!("基线对齐失败: {}", e)));
+                }
+            }
+        });
+    }
+
+    // 设置相似帧查找回调：在后台线程中逐帧读取当前库（含未保存的修改）计算哈希，
+    // 完成后选中最相似的几帧并跳转到最相似的一帧
+    {
+        let window_weak = window_weak.clone();
+        let library_loader = state.library_loader.clone();
+        let settings = state.settings.clone();
+
+        window.on_find_similar(move || {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            let Some(started) = loader_revision(&library_loader) else {
+                window.set_status_text(SharedString::from(tr!("请先打开一个库")));
+                return;
+            };
+
+            let Some(path) = settings
+                .file_dialog(DialogDir::Image)
+                .add_filter(tr!("图像文件"), &["png", "bmp", "jpg", "jpeg"])
+                .set_title(tr!("选择要查找的图像"))
+                .pick_file()
+            else {
+                return;
+            };
+            settings.remember_dir(DialogDir::Image, &path);
+            let target = match open_import_image(&path, &settings) {
+                Ok(target) => target,
+                Err(e) => {
+                    tracing::error!("加载图像失败: {:?}", e);
+                    window.set_status_text(SharedString::from(&tr!("加载图像失败: {}", e)));
+                    return;
+                }
+            };
+
+            window.set_status_text(SharedString::from(tr!("正在查找相似帧...")));
+            let window_weak = window.as_weak();
+            let library_loader = library_loader.clone();
+            std::thread::spawn(move || {
+                let cancel = CancelToken::new();
+                let mut last_percent = None;
+                let progress_weak = window_weak.clone();
+                let progress = move |progress| {
+                    let Progress::FrameDecoded { done, total } = progress else {
+                        return;
+                    };
+                    let percent = done * 100 / total.max(1);
+                    if last_percent.replace(percent) == Some(percent) {
+                        return;
+                    }
+                    let _ = progress_weak.upgrade_in_event_loop(move |window| {
+                        window.set_status_text(SharedString::from(&tr!(
+                            "正在查找相似帧: {}%",
+                            percent
+                        )));
+                    });
+                };
+                let total = library_loader
+                    .lock()
+                    .unwrap()
+                    .as_ref()
+                    .map_or(0, |loader| loader.image_count());
+                let matches = SimilarityIndex::build_with(total, &cancel, progress, |index| {
+                    shared_frame(&library_loader, index)
+                })
+                .map(|index| index.search(&target, SIMILAR_LIMIT));
+                // 查找期间换了库或修改了帧，结果中的索引已对不上
+                let changed = loader_revision(&library_loader).as_ref() != Some(&started);
+
+                let _ = window_weak.upgrade_in_event_loop(move |window| {
+                    if changed {
+                        window.set_status_text(SharedString::from(tr!(
+                            "查找期间库已修改，请重新查找相似帧"
+                        )));
+                        return;
+                    }
+                    let matches = match matches {
+                        Ok(matches) => matches,
+                        Err(e) => {
+                            tracing::error!("查找相似帧失败: {:?}", e);
+                            window
+                                .set_status_text(SharedString::from(&tr!("查找相似帧失败: {}", e)));
+                            return;
+                        }
+                    };
+                    let Some(best) = matches.first() else {
+                        window.set_status_text(SharedString::from(tr!("库中没有非空帧")));
+                        return;
+                    };
+
+                    window.invoke_goto_frame(SharedString::from(best.index.to_string()));
+                    let mut flags = vec![false; window.get_image_count().max(0) as usize];
+                    for m in &matches {
+                        if let Some(flag) = flags.get_mut(m.index) {
+                            *flag = true;
+                        }
+                    }
+                    write_selection(&window, &Selection::new(flags, Some(best.index)));
+                    let listed: Vec<String> = matches
+                        .iter()
+                        .map(|m| format!("#{} ({})", m.index, m.distance))
+                        .collect();
+                    window.set_status_text(SharedString::from(&tr!(
+                        "最相似的帧（距离）: {}",
+                        listed.join(", ")
+                    )));
+                });
+            });
+        });
+    }
+
+    // 设置体积分析回调：在后台线程中逐帧读取当前库并按已保存文件的帧头统计，
+    // 数据大小只对已保存的文件有意义，有未保存的修改时先要求保存
+    {
+        let window_weak = window_weak.clone();
+        let library_loader = state.library_loader.clone();
+
+        window.on_analyze_library(move || {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            let Some(started) = loader_revision(&library_loader) else {
+                window.set_status_text(SharedString::from(tr!("请先打开一个库")));
+                return;
+            };
+            let modified = library_loader
+                .lock()
+                .unwrap()
+                .as_ref()
+                .is_some_and(|loader| loader.is_modified());
+            if modified {
+                window
+                    .set_stats_summary(SharedString::from(tr!("库有未保存的修改，请先保存再分析")));
+                return;
+            }
+
+            window.set_stats_busy(true);
+            window.set_stats_summary(SharedString::from(tr!("正在分析...")));
+            let window_weak = window.as_weak();
+            let library_loader = library_loader.clone();
+            std::thread::spawn(move || {
+                let cancel = CancelToken::new();
+                let mut last_percent = None;
+                let progress_weak = window_weak.clone();
+                let progress = move |progress| {
+                    let Progress::FrameDecoded { done, total } = progress else {
+                        return;
+                    };
+                    let percent = done * 100 / total.max(1);
+                    if last_percent.replace(percent) == Some(percent) {
+                        return;
+                    }
+                    let _ = progress_weak.upgrade_in_event_loop(move |window| {
+                        window
+                            .set_stats_summary(SharedString::from(&tr!("正在分析: {}%", percent)));
+                    });
+                };
+                let stats = LibraryLoader::open_header(&started.0).and_then(|header| {
+                    LibraryStats::collect_with(&header, &cancel, progress, |index| {
+                        shared_frame(&library_loader, index)
+                    })
+                });
+                // 分析期间换了库或修改了帧，帧索引和数据大小已对不上
+                let changed = loader_revision(&library_loader).as_ref() != Some(&started);
+
+                let _ = window_weak.upgrade_in_event_loop(move |window| {
+                    window.set_stats_busy(false);
+                    if changed {
+                        window.set_stats_summary(SharedString::from(tr!(
+                            "分析期间库已修改，请保存后重新分析"
+                        )));
+                        return;
+                    }
+                    match stats {
+                        Ok(stats) => show_library_stats(&window, &stats),
+                        Err(e) => {
+                            tracing::error!("体积分析失败: {:?}", e);
+                            window
+                                .set_stats_summary(SharedString::from(&tr!("体积分析失败: {}", e)));
+                        }
+                    }
+                });
+            });
+        });
+    }
+
+    // 设置格式说明回调：打开时定位到当前库的格式
+    {
+        let window_weak = window_weak.clone();
+        let library_loader = state.library_loader.clone();
+
+        window.on_open_format_doc(move || {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            let library = open_library_kind(&library_loader);
+            let index = library
+                .as_ref()
+                .and_then(|(_, lib_type)| spec::spec_for(*lib_type))
+                .and_then(|format| spec::SPECS.iter().position(|s| s == format))
+                .unwrap_or(window.get_format_doc_index().max(0) as usize);
+            show_format_doc(&window, index, library);
+            window.set_show_format_doc(true);
+        });
+    }
+    {
+        let window_weak = window_weak.clone();
+        let library_loader = state.library_loader.clone();
+
+        window.on_select_format_doc(move |index| {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            show_format_doc(
+                &window,
+                index.max(0) as usize,
+                open_library_kind(&library_loader),
+            );
+        });
+    }
+
+    // 设置完整性自检回调：记住资源目录后在后台检查
+    {
+        let window_weak = window_weak.clone();
+        let settings = state.settings.clone();
+
+        window.on_run_self_test(move |dirs, on_startup| {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            let dirs = parse_asset_dirs(&dirs);
+            {
+                let mut preferences = settings.preferences();
+                preferences.asset_dirs = dirs.clone();
+                preferences.self_test_on_startup = on_startup;
+            }
+            settings.save_preferences();
+
+            if dirs.is_empty() {
+                window.set_self_test_summary(SharedString::from(tr!("请填写资源目录")));
+                return;
+            }
+            tracing::info!("用户触发完整性自检: {:?}", dirs);
+            start_self_test(&window, dirs, false);
+        });
+    }
+
+    // 设置替换图像回调
+    {
+        let window_weak = window_weak.clone();
+        let settings = state.settings.clone();
+        let library_loader = state.library_loader.clone();
+
+        window.on_replace_image(move || {
+            tracing::debug!("用户触发替换图像操作");
+
+            let window = match window_weak.upgrade() {
+                Some(w) => w,
+                None => return,
+            };
+
+            let current_index = window.get_current_index();
+            if current_index < 0 {
+                window.set_status_text(SharedString::from(tr!("请先选择一张图像")));
+                return;
+            }
+
+            // 只读格式直接提示，不弹出文件选择框
+            if let Some(ref loader) = *library_loader.lock().unwrap()
+                && !loader.capabilities().writable
+            {
+                window.set_status_text(SharedString::from(&tr!(
+                    "{} 不支持替换图像",
+                    loader.info().map(|i| i.format_name()).unwrap_or_default()
+                )));
+                return;
+            }
+
+            // 选择新图像
+            let path = match settings
+                .file_dialog(DialogDir::Image)
+                .add_filter(tr!("图像文件"), &["png", "bmp", "jpg", "jpeg"])
+                .set_title(tr!("选择替换图像"))
+                .pick_file()
+            {
+                Some(p) => p,
+                None => {
+                    window.set_status_text(SharedString::from(tr!("替换取消")));
+                    return;
+                }
+            };
+            settings.remember_dir(DialogDir::Image, &path);
+
+            // 加载新图像
+            match open_import_image(&path, &settings) {
+                Ok(rgba) => {
+                    // TODO: 实现图像替换功能（需要根据库类型调用不同的方法）
+                    // 目前仅更新预览
+                    tracing::debug!("图像加载成功，替换功能待实现");
+                    window.set_status_text(SharedString::from(tr!("图像已加载 (替换功能开发中)")));
+
+                    // 更新预览
+                    if let Some(slint_image) = rgba_image_to_slint(&rgba) {
+                        window.set_main_preview(slint_image);
+                    }
+                }
+                Err(e) => {
+                    tracing::error!("加载图像失败: {:?}", e);
+                    window.set_status_text(SharedString::from(&tr!("加载图像失败: {}", e)));
+                }
+            }
+        });
+    }
+
+    // 设置导入遮罩回调
+    {
+        let window_weak = window_weak.clone();
+        let settings = state.settings.clone();
+        let library_loader = state.library_loader.clone();
+        let thumbnail_cache = state.thumbnail_cache.clone();
+
+        window.on_import_mask(move || {
+            tracing::debug!("用户触发导入遮罩操作");
+
+            let window = match window_weak.upgrade() {
+                Some(w) => w,
+                None => return,
+            };
+
+            let current_index = window.get_current_index();
+            if current_index < 0 {
+                window.set_status_text(SharedString::from(tr!("请先选择一张图像")));
+                return;
+            }
+            let index = current_index as usize;
+
+            let path = match settings
+                .file_dialog(DialogDir::Image)
+                .add_filter(tr!("PNG 图像"), &["png"])
+                .set_title(tr!("选择遮罩图像"))
+                .pick_file()
+            {
+                Some(p) => p,
+                None => {
+                    window.set_status_text(SharedString::from(tr!("导入取消")));
+                    return;
+                }
+            };
+            settings.remember_dir(DialogDir::Image, &path);
+
+            let mask = match open_import_image(&path, &settings) {
+                Ok(img) => img,
+                Err(e) => {
+                    tracing::error!("加载遮罩图像失败: {:?}", e);
+                    window.set_status_text(SharedString::from(&tr!("加载图像失败: {}", e)));
+                    return;
+                }
+            };
+
+            if let Some(ref mut loader) = *library_loader.lock().unwrap() {
+                // 已有遮罩时沿用其偏移，否则与基础层对齐
+                let (x, y) = match loader.get_image_info(index).map(|i| (i.x, i.y, i.has_mask)) {
+                    Ok((_, _, ShadowInfo::Mask { mask_x, mask_y, .. })) => (mask_x, mask_y),
+                    Ok((x, y, _)) => (x as i16, y as i16),
+                    Err(_) => (0, 0),
+                };
+
+                match loader.set_mask(index, &mask, x, y) {
+                    Ok(()) => {
+                        window.set_layer_view(2);
+                        if let Some(ref cache) = *thumbnail_cache.lock().unwrap() {
+                            update_index_bar(&window, loader, cache);
+                        }
+                        AppState::update_main_preview(&window, loader, index);
+                        window.set_status_text(SharedString::from(&tr!(
+                            "已导入遮罩到 #{} ({}x{})，保存后生效",
+                            index,
+                            mask.width(),
+                            mask.height()
+                        )));
+                    }
+                    Err(e) => {
+                        tracing::error!("导入遮罩失败: {:?}", e);
+                        window.set_status_text(SharedString::from(&tr!("导入遮罩失败: {}", e)));
+                    }
+                }
+            }
+        });
+    }
+
+    // 设置从文件夹导入回调
+    {
+        let window_weak = window_weak.clone();
+        let settings = state.settings.clone();
+        let library_loader = state.library_loader.clone();
+        let thumbnail_cache = state.thumbnail_cache.clone();
+
+        window.on_import_folder(move || {
+            tracing::debug!("用户触发从文件夹导入操作");
+
+            let window = match window_weak.upgrade() {
+                Some(w) => w,
+                None => return,
+            };
+
+            let mut guard = library_loader.lock().unwrap();
+            let Some(ref mut loader) = *guard else {
+                window.set_status_text(SharedString::from(tr!("请先打开一个库文件")));
+                return;
+            };
+            if !loader.capabilities().writable {
+                window.set_status_text(SharedString::from(&tr!(
+                    "{} 不支持写入帧",
+                    loader.info().map(|i| i.format_name()).unwrap_or_default()
+                )));
+                return;
+            }
+
+            let Some(dir) = settings
+                .file_dialog(DialogDir::Image)
+                .set_title(tr!("选择要导入的文件夹"))
+                .pick_folder()
+            else {
+                window.set_status_text(SharedString::from(tr!("导入取消")));
+                return;
+            };
+            settings.remember_dir(DialogDir::Image, &dir);
+
+            import_folder(&window, loader, &dir, &thumbnail_cache, &settings);
+        });
+    }
+
+    // 设置删除图像回调
+    {
+        let window_weak = window_weak.clone();
+        let library_loader = state.library_loader.clone();
+        let thumbnail_cache = state.thumbnail_cache.clone();
+        let settings = state.settings.clone();
+
+        window.on_delete_image(move || {
+            tracing::debug!("用户触发删除图像操作");
+
+            let window = match window_weak.upgrade() {
+                Some(w) => w,
+                None => return,
+            };
+
+            let current_index = window.get_current_index();
+            if current_index < 0 {
+                window.set_status_text(SharedString::from(tr!("请先选择一张图像")));
+                return;
+            }
+            let index = current_index as usize;
+
+            if let Some(ref mut loader) = *library_loader.lock().unwrap() {
+                // 仅追加模式下删除改为清空，后面帧的索引不变
+                if loader.append_only() {
+                    clear_frames_in_place(&window, loader, &thumbnail_cache, &settings, &[index]);
+                    return;
+                }
+                match loader.remove_image(index) {
+                    Ok(()) => {
+                        let mut thumbnails: Vec<slint::Image> =
+                            window.get_thumbnails().iter().collect();
+                        if index < thumbnails.len() {
+                            thumbnails.remove(index);
+                        }
+                        refresh_frames(
+                            &window,
+                            loader,
+                            &thumbnail_cache,
+                            &settings,
+                            thumbnails,
+                            index,
+                        );
+                        window.set_status_text(SharedString::from(&tr!(
+                            "已删除 #{}，保存前可撤销 (回收站 {} 帧)",
+                            index,
+                            loader.trash().len()
+                        )));
+                    }
+                    Err(e) => {
+                        tracing::error!("删除失败: {:?}", e);
+                        window.set_status_text(SharedString::from(&tr!("删除失败: {}", e)));
+                    }
+                }
+            }
+        });
+    }
+
+    // 设置像素检查回调
+    {
+        let window_weak = window_weak.clone();
+        let library_loader = state.library_loader.clone();
+
+        window.on_pixel_hovered(move |x, y| {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            // 从当前显示的预览图取像素，与所选图层、阴影叠加后的画面一致
+            let Some(buffer) = window.get_main_preview().to_rgba8() else {
+                return;
+            };
+            let (x, y) = (x as u32, y as u32);
+            if x >= buffer.width() || y >= buffer.height() {
+                return;
+            }
+            let pixel = buffer.as_slice()[(y * buffer.width() + x) as usize];
+
+            let mut text = tr!(
+                "像素 ({}, {})  RGBA({}, {}, {}, {})",
+                x,
+                y,
+                pixel.r,
+                pixel.g,
+                pixel.b,
+                pixel.a
+            );
+
+            // 仅显示基础层时预览坐标即帧内坐标，可以查到调色板索引
+            let index = window.get_current_index();
+            if window.get_cap_paletted()
+                && window.get_layer_view() == 0
+                && !window.get_show_shadow()
+                && !window.get_show_onion()
+                && !window.get_show_placement()
+                && index >= 0
+                && let Some(ref mut loader) = *library_loader.lock().unwrap()
+                && let Ok(Some(palette_index)) = loader.palette_index_at(index as usize, x, y)
+            {
+                text.push_str(&tr!("  调色板 #{}", palette_index));
+            }
+
+            window.set_status_text(SharedString::from(&text));
+        });
+    }
+
+    {
+        let window_weak = window_weak.clone();
+
+        window.on_pixel_left(move || {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            if window.get_inspect_pixels() {
+                window.set_status_text(SharedString::from(tr!("像素检查: 将光标移到预览图上")));
+            }
+        });
+    }
+
+    // 设置统一帧尺寸回调
+    {
+        let window_weak = window_weak.clone();
+        let library_loader = state.library_loader.clone();
+        let thumbnail_cache = state.thumbnail_cache.clone();
+        let settings = state.settings.clone();
+
+        window.on_normalize_frames(move |start, end| {
+            tracing::debug!("用户触发统一帧尺寸: {} - {}", start, end);
+
+            let window = match window_weak.upgrade() {
+                Some(w) => w,
+                None => return,
+            };
+
+            let range = start.max(0) as usize..end.max(0) as usize + 1;
+            if let Some(ref mut loader) = *library_loader.lock().unwrap() {
+                match loader.normalize_frames(range) {
+                    Ok(Some(report)) => {
+                        // 只重新生成被修改帧的缩略图
+                        let mut thumbnails: Vec<slint::Image> =
+                            window.get_thumbnails().iter().collect();
+                        let pixels = settings.get_thumbnail_pixels();
+                        for &index in &report.frames {
+                            if let Some(slot) = thumbnails.get_mut(index) {
+                                *slot = thumbnails::frame_image(loader, index, pixels);
+                            }
+                        }
+                        let current = window.get_current_index().max(0) as usize;
+                        refresh_frames(
+                            &window,
+                            loader,
+                            &thumbnail_cache,
+                            &settings,
+                            thumbnails,
+                            current,
+                        );
+                        window.set_status_text(SharedString::from(&tr!(
+                            "已将 {} 帧统一为 {}x{}，偏移 ({}, {})，保存后生效",
+                            report.frames.len(),
+                            report.width,
+                            report.height,
+                            report.x,
+                            report.y
+                        )));
+                    }
+                    Ok(None) => {
+                        window.set_status_text(SharedString::from(tr!("所选范围内没有非空帧")));
+                    }
+                    Err(e) => {
+                        tracing::error!("统一帧尺寸失败: {:?}", e);
+                        window.set_status_text(SharedString::from(&tr!("统一帧尺寸失败: {}", e)));
+                    }
+                }
+            }
+        });
+    }
+
+    // 设置填充画布回调
+    {
+        let window_weak = window_weak.clone();
+        let library_loader = state.library_loader.clone();
+        let thumbnail_cache = state.thumbnail_cache.clone();
+        let settings = state.settings.clone();
+
+        window.on_pad_frames(move |start, end, width, height, anchor| {
+            let anchor = Anchor::from_index(anchor.max(0) as usize);
+            tracing::debug!(
+                "用户触发填充画布: {} - {}, {}x{}, 锚点 {}",
+                start,
+                end,
+                width,
+                height,
+                anchor.name()
+            );
+
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+
+            let indices: Vec<usize> = (start.max(0) as usize..=end.max(0) as usize).collect();
+            let (width, height) = (width.max(1) as u32, height.max(1) as u32);
+            if let Some(ref mut loader) = *library_loader.lock().unwrap() {
+                let (_, locked) = loader.locks().split(&indices);
+                match loader.pad_frames(&indices, width, height, anchor) {
+                    Ok(report) => {
+                        // 只重新生成被修改帧的缩略图
+                        let mut thumbnails: Vec<slint::Image> =
+                            window.get_thumbnails().iter().collect();
+                        let pixels = settings.get_thumbnail_pixels();
+                        for &index in &report.frames {
+                            if let Some(slot) = thumbnails.get_mut(index) {
+                                *slot = thumbnails::frame_image(loader, index, pixels);
+                            }
+                        }
+                        let current = window.get_current_index().max(0) as usize;
+                        refresh_frames(
+                            &window,
+                            loader,
+                            &thumbnail_cache,
+                            &settings,
+                            thumbnails,
+                            current,
+                        );
+                        let oversized = if report.oversized.is_empty() {
+                            String::new()
+                        } else {
+                            tr!("，{} 帧比画布大未处理", report.oversized.len())
+                        };
+                        window.set_status_text(SharedString::from(&tr!(
+                            "已将 {} 帧填充到 {}x{}{}，保存后生效{}",
+                            report.frames.len(),
+                            width,
+                            height,
+                            oversized,
+                            locked_note(&locked)
+                        )));
+                    }
+                    Err(e) => {
+                        tracing::error!("填充画布失败: {:?}", e);
+                        window.set_status_text(SharedString::from(&tr!("填充画布失败: {}", e)));
+                    }
+                }
+            }
+        });
+    }
+
+    // 设置方向翻转回调（方向检测提示中确认）
+    {
+        let window_weak = window_weak.clone();
+        let library_loader = state.library_loader.clone();
+        let thumbnail_cache = state.thumbnail_cache.clone();
+        let settings = state.settings.clone();
+
+        window.on_flip_orientation(move || {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            let Some(ref mut loader) = *library_loader.lock().unwrap() else {
+                return;
+            };
+            let Some(info) = loader.info().cloned() else {
+                return;
+            };
+
+            let flip = !loader.flip_vertical();
+            loader.set_flip_vertical(flip);
+            tracing::info!("切换显示方向: {} 上下翻转={}", info.file_name, flip);
+
+            // 所有缩略图都要按新方向重新生成
+            let current = window.get_current_index().max(0) as usize;
+            let thumbnails = vec![slint::Image::default(); loader.image_count()];
+            refresh_frames(
+                &window,
+                loader,
+                &thumbnail_cache,
+                &settings,
+                thumbnails,
+                current,
+            );
+
+            // 记住该文件的方向，下次打开直接按此方向解码
+            let path = info.base_path.with_file_name(&info.file_name);
+            let choices_path = Path::new(OPEN_CHOICES_FILE);
+            let mut choices = OpenChoices::load(choices_path);
+            let mut options = choices
+                .get(&path)
+                .cloned()
+                .unwrap_or_else(|| OpenOptions::new(info.library_type));
+            options.flip_vertical = flip;
+            choices.insert(&path, options);
+            if let Err(e) = choices.save(choices_path) {
+                tracing::warn!("保存打开选项失败: {:?}", e);
+            }
+
+            window.set_status_text(SharedString::from(tr!("已翻转显示方向")));
+        });
+    }
+
+    // 设置仅追加模式开关回调：开关状态写入偏好设置，之后打开的库沿用
+    {
+        let window_weak = window_weak.clone();
+        let library_loader = state.library_loader.clone();
+        let settings = state.settings.clone();
+
+        window.on_toggle_append_only(move || {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            let append_only = !window.get_append_only();
+            window.set_append_only(append_only);
+            if let Some(ref mut loader) = *library_loader.lock().unwrap() {
+                loader.set_append_only(append_only);
+            }
+            settings.preferences().append_only = append_only;
+            settings.save_preferences();
+            tracing::info!("仅追加模式: {}", append_only);
+
+            window.set_status_text(SharedString::from(if append_only {
+                tr!("已开启仅追加模式：删除改为清空，新帧只加在末尾")
+            } else {
+                tr!("已关闭仅追加模式")
+            }));
+        });
+    }
+
+    // 设置索引色 PNG 开关回调：开关状态写入偏好设置
+    {
+        let window_weak = window_weak.clone();
+        let settings = state.settings.clone();
+
+        window.on_toggle_indexed_png(move || {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            let indexed_png = !window.get_indexed_png();
+            window.set_indexed_png(indexed_png);
+            settings.preferences().indexed_png = indexed_png;
+            settings.save_preferences();
+            tracing::info!("导出索引色 PNG: {}", indexed_png);
+
+            window.set_status_text(SharedString::from(if indexed_png {
+                tr!("导出 PNG 时 8 位调色板帧保留原调色板（索引色）")
+            } else {
+                tr!("导出 PNG 时展开为 RGBA")
+            }));
+        });
+    }
+
+    // 设置撤销删除回调
+    {
+        let window_weak = window_weak.clone();
+        let library_loader = state.library_loader.clone();
+        let thumbnail_cache = state.thumbnail_cache.clone();
+        let settings = state.settings.clone();
+
+        window.on_undo_delete(move || {
+            tracing::debug!("用户触发撤销删除操作");
+
+            let window = match window_weak.upgrade() {
+                Some(w) => w,
+                None => return,
+            };
+
+            if let Some(ref mut loader) = *library_loader.lock().unwrap() {
+                match loader.undo_remove() {
+                    Ok(Some(index)) => {
+                        let pixels = settings.get_thumbnail_pixels();
+                        let thumbnail = thumbnails::frame_image(loader, index, pixels);
+                        let mut thumbnails: Vec<slint::Image> =
+                            window.get_thumbnails().iter().collect();
+                        thumbnails.insert(index.min(thumbnails.len()), thumbnail);
+                        refresh_frames(
+                            &window,
+                            loader,
+                            &thumbnail_cache,
+                            &settings,
+                            thumbnails,
+                            index,
+                        );
+                        window.set_status_text(SharedString::from(&tr!(
+                            "已恢复 #{} (回收站剩余 {} 帧)",
+                            index,
+                            loader.trash().len()
+                        )));
+                    }
+                    Ok(None) => {
+                        window.set_status_text(SharedString::from(tr!("回收站为空")));
+                    }
+                    Err(e) => {
+                        tracing::error!("撤销删除失败: {:?}", e);
+                        window.set_status_text(SharedString::from(&tr!("撤销删除失败: {}", e)));
+                    }
+                }
+            }
+        });
+    }
+
+    // 设置图层切换回调
+    {
+        let window_weak = window_weak.clone();
+        let library_loader = state.library_loader.clone();
+
+        window.on_layer_changed(move || {
+            let window = match window_weak.upgrade() {
+                Some(w) => w,
+                None => return,
+            };
+
+            let current_index = window.get_current_index();
+            if current_index < 0 {
+                return;
+            }
+
+            if let Some(ref mut loader) = *library_loader.lock().unwrap() {
+                AppState::update_main_preview(&window, loader, current_index as usize);
+            }
+        });
+    }
+
+    // 设置上一张图像回调
+    {
+        let window_weak = window_weak.clone();
+        let library_loader = state.library_loader.clone();
+
+        window.on_prev_image(move || {
+            let window = match window_weak.upgrade() {
+                Some(w) => w,
+                None => return,
+            };
+
+            let current = window.get_current_index();
+            let count = window.get_image_count();
+
+            if count == 0 {
+                return;
+            }
+
+            let new_index = if current <= 0 { count - 1 } else { current - 1 };
+            window.set_current_index(new_index);
+
+            // 更新图像信息
+            if let Some(ref mut loader) = *library_loader.lock().unwrap() {
+                if let Ok(img_info) = loader.get_image_info(new_index as usize) {
+                    show_image_info(&window, &img_info);
+                }
+                AppState::update_main_preview(&window, loader, new_index as usize);
+            }
+
+            tracing::debug!("切换到上一张图像: {}", new_index);
+        });
+    }
+
+    // 设置下一张图像回调
+    {
+        let window_weak = window_weak.clone();
+        let library_loader = state.library_loader.clone();
+
+        window.on_next_image(move || {
+            let window = match window_weak.upgrade() {
+                Some(w) => w,
+                None => return,
+            };
+
+            let current = window.get_current_index();
+            let count = window.get_image_count();
+
+            if count == 0 {
+                return;
+            }
+
+            let new_index = if current >= count - 1 { 0 } else { current + 1 };
+            window.set_current_index(new_index);
+
+            // 更新图像信息
+            if let Some(ref mut loader) = *library_loader.lock().unwrap() {
+                if let Ok(img_info) = loader.get_image_info(new_index as usize) {
+                    show_image_info(&window, &img_info);
+                }
+                AppState::update_main_preview(&window, loader, new_index as usize);
+            }
+
+            tracing::debug!("切换到下一张图像: {}", new_index);
+        });
+    }
+
+    // 设置缩略图点击回调
+    {
+        let window_weak = window_weak.clone();
+        let library_loader = state.library_loader.clone();
+
+        window.on_thumbnail_clicked(move |index, control, shift| {
+            let window = match window_weak.upgrade() {
+                Some(w) => w,
+                None => return,
+            };
+
+            let mut selection = read_selection(&window);
+            selection.click(index as usize, control, shift);
+            write_selection(&window, &selection);
+
+            window.set_current_index(index);
+
+            // 更新图像信息
+            if let Some(ref mut loader) = *library_loader.lock().unwrap() {
+                if let Ok(img_info) = loader.get_image_info(index as usize) {
+                    show_image_info(&window, &img_info);
+                }
+                AppState::update_main_preview(&window, loader, index as usize);
+            }
+
+            tracing::debug!("点击缩略图: {}", index);
+        });
+    }
+
+    // 设置批量导出所选帧回调
+    {
+        let window_weak = window_weak.clone();
+        let settings = state.settings.clone();
+        let library_loader = state.library_loader.clone();
+
+        window.on_export_selected(move || {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            let frames = selected_frames(&window);
+            if frames.is_empty() {
+                window.set_status_text(SharedString::from(tr!("请先选择图像")));
+                return;
+            }
+
+            let Some(dir) = settings
+                .file_dialog(DialogDir::Export)
+                .set_title(tr!("导出所选帧到文件夹"))
+                .pick_folder()
+            else {
+                window.set_status_text(SharedString::from(tr!("导出取消")));
+                return;
+            };
+            settings.remember_dir(DialogDir::Export, &dir);
+
+            if let Some(ref mut loader) = *library_loader.lock().unwrap() {
+                match loader.export_pngs(&frames, &dir, window.get_indexed_png()) {
+                    Ok(written) => {
+                        window.set_status_text(SharedString::from(&tr!(
+                            "已导出 {} 帧到 {}",
+                            written,
+                            dir.display()
+                        )));
+                    }
+                    Err(e) => {
+                        tracing::error!("批量导出失败: {:?}", e);
+                        window.set_status_text(SharedString::from(&tr!("导出失败: {}", e)));
+                    }
+                }
+            }
+        });
+    }
+
+    // 设置导出一段帧为 PNG 回调
+    {
+        let window_weak = window_weak.clone();
+        let library_loader = state.library_loader.clone();
+        let settings = state.settings.clone();
+
+        window.on_export_range(move |start, end| {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            let (Ok(start), Ok(end)) = (usize::try_from(start), usize::try_from(end)) else {
+                return;
+            };
+
+            let Some(dir) = settings
+                .file_dialog(DialogDir::Export)
+                .set_title(tr!("导出一段帧为 PNG"))
+                .pick_folder()
+            else {
+                window.set_status_text(SharedString::from(tr!("导出取消")));
+                return;
+            };
+            settings.remember_dir(DialogDir::Export, &dir);
+
+            if let Some(ref mut loader) = *library_loader.lock().unwrap() {
+                let frames: Vec<usize> =
+                    (start..=end.min(loader.image_count().saturating_sub(1))).collect();
+                match loader.export_pngs(&frames, &dir, window.get_indexed_png()) {
+                    Ok(written) => {
+                        window.set_status_text(SharedString::from(&tr!(
+                            "已导出 {} 帧到 {}",
+                            written,
+                            dir.display()
+                        )));
+                    }
+                    Err(e) => {
+                        tracing::error!("导出范围失败: {:?}", e);
+                        window.set_status_text(SharedString::from(&tr!("导出失败: {}", e)));
+                    }
+                }
+            }
+        });
     }
 
-    // 设置保存文件回调
+    // 设置运行脚本回调：当前库的路径作为 ARGS[0]，脚本改写当前库后由文件监视重新加载
     {
         let window_weak = window_weak.clone();
         let library_loader = state.library_loader.clone();
+        let settings = state.settings.clone();
 
-        window.on_save_file(move || {
-            tracing::debug!("用户触发保存文件操作");
+        window.on_run_script(move || {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            let Some(path) = settings
+                .file_dialog(DialogDir::Script)
+                .add_filter(tr!("Rhai 脚本"), &["rhai"])
+                .set_title(tr!("运行脚本"))
+                .pick_file()
+            else {
+                return;
+            };
+            settings.remember_dir(DialogDir::Script, &path);
+
+            let args: Vec<String> = library_loader
+                .lock()
+                .unwrap()
+                .as_ref()
+                .and_then(|loader| loader.info())
+                .map(|info| info.path().to_string_lossy().into_owned())
+                .into_iter()
+                .collect();
+            let encode = EncodeOptions {
+                preserve_black: settings.preferences().preserve_black,
+                ..EncodeOptions::default()
+            };
+            let name = path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            window.set_status_text(SharedString::from(&tr!("正在运行脚本 {}...", name)));
+
+            let window_weak = window_weak.clone();
+            std::thread::spawn(move || {
+                let args: Vec<&str> = args.iter().map(String::as_str).collect();
+                let result = script::run_file(&path, &args, encode);
+                let _ = window_weak.upgrade_in_event_loop(move |window| {
+                    let text = match result {
+                        Ok(()) => tr!("脚本 {} 已运行完成", name),
+                        Err(e) => {
+                            tracing::error!("脚本运行失败: {:?}", e);
+                            tr!("脚本运行失败: {}", e)
+                        }
+                    };
+                    window.set_status_text(SharedString::from(&text));
+                });
+            });
+        });
+    }
 
-            let window = match window_weak.upgrade() {
-                Some(w) => w,
-                None => return,
+    // 设置批量删除所选帧回调
+    {
+        let window_weak = window_weak.clone();
+        let library_loader = state.library_loader.clone();
+        let thumbnail_cache = state.thumbnail_cache.clone();
+        let settings = state.settings.clone();
+
+        window.on_delete_selected(move || {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            let frames = selected_frames(&window);
+            let Some(&first) = frames.first() else {
+                return;
             };
 
-            // 检查是否有加载的库
-            let has_library = library_loader.lock().unwrap().is_some();
+            if let Some(ref mut loader) = *library_loader.lock().unwrap() {
+                if loader.append_only() {
+                    clear_frames_in_place(&window, loader, &thumbnail_cache, &settings, &frames);
+                    return;
+                }
+                // 锁定的帧不删除，缩略图也保留
+                let (frames, locked) = loader.locks().split(&frames);
+                match loader.remove_images(&frames) {
+                    Ok(removed) => {
+                        let mut thumbnails: Vec<slint::Image> =
+                            window.get_thumbnails().iter().collect();
+                        for &index in frames.iter().rev() {
+                            if index < thumbnails.len() {
+                                thumbnails.remove(index);
+                            }
+                        }
+                        refresh_frames(
+                            &window,
+                            loader,
+                            &thumbnail_cache,
+                            &settings,
+                            thumbnails,
+                            first,
+                        );
+                        window.set_status_text(SharedString::from(&tr!(
+                            "已删除 {} 帧，保存前可撤销 (回收站 {} 帧){}",
+                            removed,
+                            loader.trash().len(),
+                            locked_note(&locked)
+                        )));
+                    }
+                    Err(e) => {
+                        tracing::error!("批量删除失败: {:?}", e);
+                        window.set_status_text(SharedString::from(&tr!("删除失败: {}", e)));
+                    }
+                }
+            }
+        });
+    }
 
-            if !has_library {
-                tracing::warn!("没有加载的库文件");
-                window.set_status_text(SharedString::from("请先打开一个库文件"));
+    // 设置导出索引对照表回调：列出自上次保存以来位置变化或已删除的帧
+    {
+        let window_weak = window_weak.clone();
+        let settings = state.settings.clone();
+        let library_loader = state.library_loader.clone();
+
+        window.on_export_remap(move || {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            let Some(remap) = library_loader
+                .lock()
+                .unwrap()
+                .as_ref()
+                .map(|l| l.index_remap())
+            else {
+                return;
+            };
+            if remap.changes().is_empty() {
+                window.set_status_text(SharedString::from(tr!("自上次保存以来没有帧索引变化")));
                 return;
             }
 
-            window.set_status_text(SharedString::from("正在保存..."));
+            let Some(path) = settings
+                .file_dialog(DialogDir::Export)
+                .add_filter(tr!("CSV 表格"), &["csv"])
+                .add_filter("JSON", &["json"])
+                .set_title(tr!("导出索引对照表"))
+                .save_file()
+            else {
+                return;
+            };
+            settings.remember_dir(DialogDir::Export, &path);
+            let status = match crate::commands::write_remap(&path, &remap) {
+                Ok(()) => tr!(
+                    "已导出索引对照表: {} ({} 帧)",
+                    path.display(),
+                    remap.changes().len()
+                ),
+                Err(e) => {
+                    tracing::error!("导出索引对照表失败: {:?}", e);
+                    tr!("导出索引对照表失败: {}", e)
+                }
+            };
+            window.set_status_text(SharedString::from(&status));
+        });
+    }
 
-            // 执行保存
-            if let Some(ref loader) = *library_loader.lock().unwrap() {
-                match loader.save() {
-                    Ok(_) => {
-                        tracing::debug!("保存成功");
-                        window.set_status_text(SharedString::from("保存成功"));
+    // 设置清理空帧回调：删除的帧移入回收站，保存前可撤销
+    {
+        let window_weak = window_weak.clone();
+        let library_loader = state.library_loader.clone();
+        let thumbnail_cache = state.thumbnail_cache.clone();
+        let settings = state.settings.clone();
+
+        window.on_remove_empty_frames(move |trailing_only| {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            let Some(ref mut loader) = *library_loader.lock().unwrap() else {
+                return;
+            };
+
+            let remap = match loader.remove_empty_frames(trailing_only) {
+                Ok(remap) => remap,
+                Err(e) => {
+                    tracing::error!("删除空帧失败: {:?}", e);
+                    window.set_status_text(SharedString::from(&tr!("删除空帧失败: {}", e)));
+                    return;
+                }
+            };
+            let removed = remap.removed();
+            let Some(&first) = removed.first() else {
+                window.set_status_text(SharedString::from(tr!("没有可删除的空帧")));
+                return;
+            };
+
+            let mut thumbnails: Vec<slint::Image> = window.get_thumbnails().iter().collect();
+            for &index in removed.iter().rev() {
+                if index < thumbnails.len() {
+                    thumbnails.remove(index);
+                }
+            }
+            refresh_frames(
+                &window,
+                loader,
+                &thumbnail_cache,
+                &settings,
+                thumbnails,
+                first,
+            );
+            let shifted: usize = remap.shifted().iter().map(|segment| segment.len).sum();
+            window.set_status_text(SharedString::from(&tr!(
+                "已删除 {} 个空帧，{} 帧索引前移，保存前可撤销",
+                locale::count(removed.len() as u64),
+                locale::count(shifted as u64)
+            )));
+        });
+    }
+
+    // 设置移动所选帧偏移回调
+    {
+        let window_weak = window_weak.clone();
+        let library_loader = state.library_loader.clone();
+        let thumbnail_cache = state.thumbnail_cache.clone();
+
+        window.on_shift_selected(move |dx, dy| {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            let frames = selected_frames(&window);
+            let clamp = |v: i32| v.clamp(i16::MIN as i32, i16::MAX as i32) as i16;
+
+            if let Some(ref mut loader) = *library_loader.lock().unwrap() {
+                let (_, locked) = loader.locks().split(&frames);
+                match loader.shift_offsets(&frames, clamp(dx), clamp(dy)) {
+                    Ok(affected) => {
+                        // 偏移不影响图像本身，只需刷新当前帧的属性
+                        let current = window.get_current_index();
+                        if current >= 0
+                            && let Ok(img_info) = loader.get_image_info(current as usize)
+                        {
+                            show_image_info(&window, &img_info);
+                        }
+                        if let Some(ref cache) = *thumbnail_cache.lock().unwrap() {
+                            update_index_bar(&window, loader, cache);
+                        }
+                        window.set_status_text(SharedString::from(&tr!(
+                            "已将 {} 帧的偏移移动 ({}, {})，保存后生效{}",
+                            affected.len(),
+                            dx,
+                            dy,
+                            locked_note(&locked)
+                        )));
                     }
                     Err(e) => {
-                        tracing::error!("保存失败: {:?}", e);
-                        window.set_status_text(SharedString::from(&format!("保存失败: {}", e)));
+                        tracing::error!("移动偏移失败: {:?}", e);
+                        window.set_status_text(SharedString::from(&tr!("移动偏移失败: {}", e)));
                     }
                 }
             }
         });
     }
 
-    // 设置另存为文件回调
+    // 设置修改当前帧阴影和遮罩属性回调
     {
         let window_weak = window_weak.clone();
+        let library_loader = state.library_loader.clone();
+        let thumbnail_cache = state.thumbnail_cache.clone();
 
-        window.on_save_as_file(move || {
-            tracing::debug!("用户触发另存为操作");
+        window.on_frame_properties_edited(move || {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            let current = window.get_current_index();
+            if current < 0 {
+                return;
+            }
+            let index = current as usize;
+            let clamp = |v: i32| v.clamp(i16::MIN as i32, i16::MAX as i32) as i16;
+            let properties = FrameProperties {
+                shadow: window.get_image_shadow().clamp(0, 127) as u8,
+                shadow_x: clamp(window.get_image_shadow_x()),
+                shadow_y: clamp(window.get_image_shadow_y()),
+                has_mask: window.get_image_has_mask(),
+                mask_x: clamp(window.get_image_mask_x()),
+                mask_y: clamp(window.get_image_mask_y()),
+            };
 
-            let window = match window_weak.upgrade() {
-                Some(w) => w,
+            if let Some(ref mut loader) = *library_loader.lock().unwrap() {
+                match loader.set_frame_properties(index, properties) {
+                    Ok(true) => {
+                        // 遮罩层的开关和偏移会改变合成预览
+                        AppState::update_main_preview(&window, loader, index);
+                        if let Some(ref cache) = *thumbnail_cache.lock().unwrap() {
+                            update_index_bar(&window, loader, cache);
+                        }
+                        window.set_status_text(SharedString::from(&tr!(
+                            "已修改第 {} 帧的阴影和遮罩属性，保存后生效",
+                            index
+                        )));
+                    }
+                    Ok(false) => {}
+                    Err(e) => {
+                        tracing::error!("修改帧属性失败: {:?}", e);
+                        window.set_status_text(SharedString::from(&tr!("修改帧属性失败: {}", e)));
+                    }
+                }
+                // 显示实际保存的值：阴影值只取低 7 位，关闭遮罩后偏移归零，失败时还原
+                if let Ok(img_info) = loader.get_image_info(index) {
+                    show_image_info(&window, &img_info);
+                }
+            }
+        });
+    }
+
+    // 设置清空所选帧回调
+    {
+        let window_weak = window_weak.clone();
+        let library_loader = state.library_loader.clone();
+        let thumbnail_cache = state.thumbnail_cache.clone();
+        let settings = state.settings.clone();
+
+        window.on_clear_selected(move || {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            let frames = selected_frames(&window);
+
+            if let Some(ref mut loader) = *library_loader.lock().unwrap() {
+                clear_frames_in_place(&window, loader, &thumbnail_cache, &settings, &frames);
+            }
+        });
+    }
+
+    // 设置取消多选回调
+    {
+        let window_weak = window_weak.clone();
+
+        window.on_clear_selection(move || {
+            if let Some(window) = window_weak.upgrade() {
+                write_selection(&window, &Selection::default());
+            }
+        });
+    }
+
+    // 设置缩略图筛选回调：按条件查出帧索引，缩略图网格只显示这些帧
+    {
+        let window_weak = window_weak.clone();
+        let library_loader = state.library_loader.clone();
+
+        window.on_apply_filter(move |width, height, empty, mask, shadow| {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            let filter = match (
+                FrameFilter::parse_range(&width),
+                FrameFilter::parse_range(&height),
+            ) {
+                (Ok(width), Ok(height)) => FrameFilter {
+                    width,
+                    height,
+                    empty,
+                    mask,
+                    shadow,
+                },
+                (Err(e), _) | (_, Err(e)) => {
+                    window.set_status_text(SharedString::from(&e.to_string()));
+                    return;
+                }
+            };
+            if filter.is_empty() {
+                window.set_thumbnail_filtering(false);
+                return;
+            }
+
+            let found = match *library_loader.lock().unwrap() {
+                Some(ref mut loader) => loader.find_frames(|info| filter.matches(info)),
                 None => return,
             };
+            window.set_status_text(SharedString::from(&tr!(
+                "筛选出 {} 帧",
+                locale::count(found.len() as u64)
+            )));
+            let found: Vec<i32> = found.into_iter().map(|index| index as i32).collect();
+            window.set_thumbnail_filter(slint::ModelRc::new(slint::VecModel::from(found)));
+            window.set_thumbnail_filtering(true);
+        });
+    }
 
-            match rfd::FileDialog::new()
-                .add_filter("传奇库文件", &["lib", "wzl", "wtl"])
-                .set_title("另存为")
-                .save_file()
-            {
-                Some(path) => {
-                    tracing::debug!("另存为: {:?}", path);
-                    window.set_status_text(SharedString::from(&format!(
-                        "已保存: {}",
+    // 设置切换预览背景回调
+    {
+        let window_weak = window_weak.clone();
+
+        window.on_toggle_preview_bg(move || {
+            if let Some(window) = window_weak.upgrade() {
+                // 依次切换各背景模式，未加载参考图时跳过参考图模式
+                let modes = if window.get_preview_bg_image().size().width > 0 {
+                    PREVIEW_BG_MODES
+                } else {
+                    PREVIEW_BG_MODES - 1
+                };
+                let mode = (window.get_preview_bg_mode() + 1) % modes;
+                window.set_preview_bg_mode(mode);
+                tracing::debug!("切换预览背景: {}", mode);
+            }
+        });
+    }
+
+    // 设置自定义背景色回调
+    {
+        let window_weak = window_weak.clone();
+
+        window.on_preview_bg_color_edited(move |text| {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            // 输入未完成时保持原颜色
+            if let Some([r, g, b]) = parse_hex_color(&text) {
+                window.set_preview_bg_color(slint::Color::from_rgb_u8(r, g, b));
+            }
+        });
+    }
+
+    // 设置参考背景图回调
+    {
+        let window_weak = window_weak.clone();
+        let settings = state.settings.clone();
+
+        window.on_pick_preview_bg_image(move || {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+
+            let Some(path) = settings
+                .file_dialog(DialogDir::Image)
+                .add_filter(tr!("图像文件"), &["png", "bmp", "jpg", "jpeg"])
+                .set_title(tr!("选择参考背景图"))
+                .pick_file()
+            else {
+                return;
+            };
+            settings.remember_dir(DialogDir::Image, &path);
+
+            match load_preview_bg_image(&window, &path) {
+                Ok(()) => {
+                    window.set_preview_bg_mode(PREVIEW_BG_MODES - 1);
+                    window.set_status_text(SharedString::from(&tr!(
+                        "已加载参考背景: {}",
                         path.display()
                     )));
-                    // TODO: 实现另存为逻辑
+                    settings.preferences().preview_bg_image = Some(path);
                 }
-                None => {
-                    window.set_status_text(SharedString::from("保存取消"));
+                Err(e) => {
+                    tracing::error!("加载参考背景失败: {:?}", e);
+                    window.set_status_text(SharedString::from(&tr!("加载图像失败: {}", e)));
+                }
+            }
+        });
+    }
+
+    // 设置请求缩略图回调
+    thumbnails::install(&window, &state);
+
+    // 设置键盘事件回调：按快捷键设置分派，帧导航按设置的间隔节流
+    {
+        let window_weak = window_weak.clone();
+        let library_loader = state.library_loader.clone();
+        let last_key_time = state.last_key_time.clone();
+        let settings = state.settings.clone();
+
+        window.on_key_pressed(move |text, control, shift, alt| {
+            let Some(shortcut) = Shortcut::from_event(&text, control, shift, alt) else {
+                return;
+            };
+            let Some(action) = settings.keymap().action(&shortcut) else {
+                return;
+            };
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            tracing::debug!("快捷键 {}: {:?}", shortcut, action);
+
+            match action {
+                Action::OpenFile => window.invoke_open_file(),
+                Action::SaveFile => window.invoke_save_file(),
+                Action::ExportPng => window.invoke_export_png(),
+                Action::ZoomIn | Action::ZoomOut | Action::ZoomReset => {
+                    let zoom = match action {
+                        Action::ZoomIn => window.get_zoom_scale() + ZOOM_STEP,
+                        Action::ZoomOut => window.get_zoom_scale() - ZOOM_STEP,
+                        _ => 100,
+                    };
+                    window.set_zoom_scale(zoom.clamp(*ZOOM_RANGE.start(), *ZOOM_RANGE.end()));
+                }
+                Action::PlayAnimation => window.invoke_play_animation(),
+                Action::ShowShortcuts => window.invoke_open_shortcuts(),
+                Action::ToggleBookmark => window.invoke_toggle_bookmark(),
+                Action::NextBookmark => window.invoke_step_bookmark(1),
+                Action::PreviousBookmark => window.invoke_step_bookmark(-1),
+                Action::CommandPalette => {
+                    window.invoke_palette_search(SharedString::new());
+                    window.set_show_command_palette(true);
+                }
+                _ => {
+                    // 节流检查：使用动态配置的间隔
+                    {
+                        let mut last_time = last_key_time.lock().unwrap();
+                        if last_time.elapsed().as_millis() < settings.get_key_throttle_ms() {
+                            return;
+                        }
+                        *last_time = Instant::now();
+                    }
+                    navigate(&window, &library_loader, action);
+                }
+            }
+        });
+    }
+
+    // 设置播放动画回调（快捷键和命令面板共用一个计时器）
+    {
+        let window_weak = window_weak.clone();
+        let library_loader = state.library_loader.clone();
+        let playback = Rc::new(slint::Timer::default());
+
+        window.on_play_animation(move || {
+            if let Some(window) = window_weak.upgrade() {
+                toggle_playback(&window, &playback, &library_loader);
+            }
+        });
+    }
+
+    // 设置命令面板回调
+    command_palette::install(&window, state.settings.clone());
+
+    // 设置快捷键对话框回调
+    {
+        let window_weak = window_weak.clone();
+        let settings = state.settings.clone();
+
+        window.on_open_shortcuts(move || {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            show_shortcuts(&window, &settings.keymap());
+            window.set_show_shortcuts(true);
+        });
+    }
+    {
+        let window_weak = window_weak.clone();
+
+        window.on_reset_shortcuts(move || {
+            if let Some(window) = window_weak.upgrade() {
+                show_shortcuts(&window, &Keymap::default());
+            }
+        });
+    }
+    {
+        let window_weak = window_weak.clone();
+        let settings = state.settings.clone();
+
+        window.on_save_shortcuts(move || {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            let texts = Action::ALL
+                .iter()
+                .zip(window.get_shortcut_keys().iter())
+                .map(|(action, keys)| (*action, keys.to_string()))
+                .collect();
+            match Keymap::from_texts(&texts) {
+                Ok(keymap) => {
+                    settings.set_keymap(keymap);
+                    window.set_show_shortcuts(false);
+                    window.set_status_text(SharedString::from(tr!("快捷键已保存")));
+                    tracing::info!("快捷键: {:?}", settings.preferences().shortcuts);
                 }
+                Err(e) => window.set_shortcut_error(SharedString::from(e)),
             }
         });
     }
 
-    // 设置导出PNG回调
+    // 设置跳转到指定帧回调
     {
         let window_weak = window_weak.clone();
         let library_loader = state.library_loader.clone();
 
-        window.on_export_png(move || {
-            tracing::debug!("用户触发导出PNG操作");
-
-            let window = match window_weak.upgrade() {
-                Some(w) => w,
-                None => return,
-            };
-
-            let current_index = window.get_current_index();
-            if current_index < 0 {
-                window.set_status_text(SharedString::from("请先选择一张图像"));
+        window.on_goto_frame(move |text| {
+            let Some(window) = window_weak.upgrade() else {
                 return;
-            }
+            };
 
-            // 选择保存路径
-            let path = match rfd::FileDialog::new()
-                .add_filter("PNG 图像", &["png"])
-                .set_title("导出PNG")
-                .save_file()
-            {
-                Some(p) => p,
-                None => {
-                    window.set_status_text(SharedString::from("导出取消"));
+            let count = window.get_image_count().max(0) as usize;
+            let index = match text.trim().trim_start_matches('#').parse::<usize>() {
+                Ok(index) if index < count => index,
+                _ => {
+                    window.set_status_text(SharedString::from(&tr!(
+                        "无效的帧索引: {} (共 {} 帧)",
+                        text,
+                        count
+                    )));
                     return;
                 }
             };
 
-            // 导出图像
             if let Some(ref mut loader) = *library_loader.lock().unwrap() {
-                match loader.export_png(current_index as usize, &path) {
-                    Ok(_) => {
-                        tracing::debug!("导出成功: {:?}", path);
-                        window.set_status_text(SharedString::from(&format!(
-                            "已导出: {}",
-                            path.display()
-                        )));
-                    }
-                    Err(e) => {
-                        tracing::error!("导出失败: {:?}", e);
-                        window.set_status_text(SharedString::from(&format!("导出失败: {}", e)));
-                    }
-                }
+                select_frame(&window, loader, index);
+                window.set_status_text(SharedString::from(&tr!("已跳转到 #{}", index)));
             }
         });
     }
 
-    // 设置替换图像回调
+    // 设置刷新索引分布条回调（缩略图加载进度更新后由进度监听线程触发）
     {
         let window_weak = window_weak.clone();
-        let _library_loader = state.library_loader.clone();
-
-        window.on_replace_image(move || {
-            tracing::debug!("用户触发替换图像操作");
-
-            let window = match window_weak.upgrade() {
-                Some(w) => w,
-                None => return,
-            };
+        let library_loader = state.library_loader.clone();
+        let thumbnail_cache = state.thumbnail_cache.clone();
 
-            let current_index = window.get_current_index();
-            if current_index < 0 {
-                window.set_status_text(SharedString::from("请先选择一张图像"));
+        window.on_refresh_index_bar(move || {
+            let Some(window) = window_weak.upgrade() else {
                 return;
-            }
-
-            // 选择新图像
-            let path = match rfd::FileDialog::new()
-                .add_filter("图像文件", &["png", "bmp", "jpg", "jpeg"])
-                .set_title("选择替换图像")
-                .pick_file()
-            {
-                Some(p) => p,
-                None => {
-                    window.set_status_text(SharedString::from("替换取消"));
-                    return;
-                }
             };
-
-            // 加载新图像
-            match image::open(&path) {
-                Ok(new_img) => {
-                    let rgba = new_img.to_rgba8();
-
-                    // TODO: 实现图像替换功能（需要根据库类型调用不同的方法）
-                    // 目前仅更新预览
-                    tracing::debug!("图像加载成功，替换功能待实现");
-                    window.set_status_text(SharedString::from(&format!(
-                        "图像已加载 (替换功能开发中)"
-                    )));
-
-                    // 更新预览
-                    if let Some(slint_image) = rgba_image_to_slint(&rgba) {
-                        window.set_main_preview(slint_image);
-                    }
-                }
-                Err(e) => {
-                    tracing::error!("加载图像失败: {:?}", e);
-                    window.set_status_text(SharedString::from(&format!("加载图像失败: {}", e)));
-                }
+            if let (Some(loader), Some(cache)) = (
+                library_loader.lock().unwrap().as_ref(),
+                thumbnail_cache.lock().unwrap().as_ref(),
+            ) {
+                update_index_bar(&window, loader, cache);
             }
         });
     }
 
-    // 设置上一张图像回调
+    // 设置索引分布条悬停说明回调（位置为 0-1 的比例）
     {
-        let window_weak = window_weak.clone();
         let library_loader = state.library_loader.clone();
+        let thumbnail_cache = state.thumbnail_cache.clone();
 
-        window.on_prev_image(move || {
-            let window = match window_weak.upgrade() {
-                Some(w) => w,
-                None => return,
+        window.on_describe_index_bar(move |position| {
+            // 在界面绘制时调用，锁被占用（正在加载或保存）时不等待
+            let (Ok(loader), Ok(cache)) = (library_loader.try_lock(), thumbnail_cache.try_lock())
+            else {
+                return SharedString::new();
             };
-
-            let current = window.get_current_index();
-            let count = window.get_image_count();
-
-            if count == 0 {
-                return;
-            }
-
-            let new_index = if current <= 0 { count - 1 } else { current - 1 };
-            window.set_current_index(new_index);
-
-            // 更新图像信息
-            if let Some(ref mut loader) = *library_loader.lock().unwrap() {
-                if let Ok(img_info) = loader.get_image_info(new_index as usize) {
-                    window.set_image_width(img_info.width);
-                    window.set_image_height(img_info.height);
-                    window.set_image_x(img_info.x);
-                    window.set_image_y(img_info.y);
-                }
-                AppState::update_main_preview(&window, loader, new_index as usize);
-            }
-
-            tracing::debug!("切换到上一张图像: {}", new_index);
+            let (Some(loader), Some(cache)) = (loader.as_ref(), cache.as_ref()) else {
+                return SharedString::new();
+            };
+            let states = cache.frame_states(loader);
+            let rows = states.len().min(INDEX_BAR_ROWS);
+            let row = (position.clamp(0.0, 1.0) * rows as f32) as usize;
+            let row = row.min(rows.saturating_sub(1));
+            SharedString::from(index_bar::describe(
+                &states,
+                index_bar::bucket(states.len(), rows, row),
+            ))
         });
     }
 
-    // 设置下一张图像回调
+    // 设置数据大小直方图点击回调（跳到所点区间中当前帧之后的下一帧）
     {
         let window_weak = window_weak.clone();
         let library_loader = state.library_loader.clone();
 
-        window.on_next_image(move || {
-            let window = match window_weak.upgrade() {
-                Some(w) => w,
-                None => return,
+        window.on_size_bar_clicked(move |bin| {
+            let Some(window) = window_weak.upgrade() else {
+                return;
             };
-
-            let current = window.get_current_index();
-            let count = window.get_image_count();
-
-            if count == 0 {
+            let Some(ref mut loader) = *library_loader.lock().unwrap() else {
                 return;
-            }
-
-            let new_index = if current >= count - 1 { 0 } else { current + 1 };
-            window.set_current_index(new_index);
-
-            // 更新图像信息
-            if let Some(ref mut loader) = *library_loader.lock().unwrap() {
-                if let Ok(img_info) = loader.get_image_info(new_index as usize) {
-                    window.set_image_width(img_info.width);
-                    window.set_image_height(img_info.height);
-                    window.set_image_x(img_info.x);
-                    window.set_image_y(img_info.y);
-                }
-                AppState::update_main_preview(&window, loader, new_index as usize);
-            }
-
-            tracing::debug!("切换到下一张图像: {}", new_index);
+            };
+            let Some(histogram) = size_histogram(loader) else {
+                return;
+            };
+            let current = usize::try_from(window.get_current_index()).ok();
+            let Some(index) = usize::try_from(bin)
+                .ok()
+                .and_then(|bin| histogram.next_in_bin(bin, current))
+                .filter(|&index| index < loader.image_count())
+            else {
+                return;
+            };
+            select_frame(&window, loader, index);
+            window.set_status_text(SharedString::from(&tr!("已跳转到 #{}", index)));
         });
     }
 
-    // 设置缩略图点击回调
+    // 设置跳到下一个异常大的帧回调
     {
         let window_weak = window_weak.clone();
         let library_loader = state.library_loader.clone();
 
-        window.on_thumbnail_clicked(move |index| {
-            let window = match window_weak.upgrade() {
-                Some(w) => w,
-                None => return,
+        window.on_next_size_outlier(move || {
+            let Some(window) = window_weak.upgrade() else {
+                return;
             };
+            let Some(ref mut loader) = *library_loader.lock().unwrap() else {
+                return;
+            };
+            let Some(histogram) = size_histogram(loader) else {
+                return;
+            };
+            let current = usize::try_from(window.get_current_index()).ok();
+            let Some(index) = histogram
+                .next_outlier(current)
+                .filter(|&index| index < loader.image_count())
+            else {
+                return;
+            };
+            select_frame(&window, loader, index);
+            window.set_status_text(SharedString::from(&tr!(
+                "已跳转到异常大的帧 #{} (共 {} 个，超过 {})",
+                index,
+                histogram.outliers.len(),
+                locale::size(histogram.fence)
+            )));
+        });
+    }
 
-            window.set_current_index(index as i32);
-
-            // 更新图像信息
-            if let Some(ref mut loader) = *library_loader.lock().unwrap() {
-                if let Ok(img_info) = loader.get_image_info(index as usize) {
-                    window.set_image_width(img_info.width);
-                    window.set_image_height(img_info.height);
-                    window.set_image_x(img_info.x);
-                    window.set_image_y(img_info.y);
-                }
-                AppState::update_main_preview(&window, loader, index as usize);
-            }
+    // 设置导入透明色和黑色像素回调（与其他设置一起保存）
+    {
+        let settings = state.settings.clone();
 
-            tracing::debug!("点击缩略图: {}", index);
+        window.on_save_import_key(move |index, color, tolerance, preserve_black| {
+            let rgb = parse_hex_color(&color).unwrap_or([255, 0, 255]);
+            let key =
+                ChromaKey::from_index(index.max(0) as usize, rgb, tolerance.clamp(0, 255) as u8);
+            let mut preferences = settings.preferences();
+            preferences.import_key = key;
+            preferences.preserve_black = preserve_black;
+            tracing::info!("导入透明色: {}，保留黑色: {}", key.name(), preserve_black);
         });
     }
 
-    // 设置切换预览背景回调
+    // 设置界面语言回调
+    language::install(&window, state.settings.clone());
+
+    // 设置界面缩放回调
     {
         let window_weak = window_weak.clone();
+        let settings = state.settings.clone();
 
-        window.on_toggle_preview_bg(move || {
-            if let Some(window) = window_weak.upgrade() {
-                let current = window.get_preview_bg_light();
-                window.set_preview_bg_light(!current);
-                tracing::debug!("切换预览背景: {}", !current);
-            }
+        window.on_save_ui_scale(move |choice| {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            let percent = scale::from_choice(choice);
+            settings.preferences().ui_scale = percent;
+            scale::set_ui_scale(window.window(), percent);
+            tracing::info!("界面缩放: {}%", percent);
         });
     }
 
-    // 设置请求缩略图回调（懒加载）
+    // 设置保存设置回调
     {
-        let window_weak = window_weak.clone();
-        let thumbnail_cache = state.thumbnail_cache.clone();
+        let settings = state.settings.clone();
         let library_loader = state.library_loader.clone();
 
-        window.on_request_thumbnails(move |start, end| {
-            let start = start as usize;
-            let end = end as usize;
+        window.on_save_settings(
+            move |cache_max_size, key_throttle_ms, placeholder_policy, thumbnail_size| {
+                settings.set_cache_max_size(cache_max_size as u64);
+                settings.set_key_throttle_ms(key_throttle_ms as u64);
+                settings.preferences().thumbnail_size = thumbnail_size.max(0) as u32;
+
+                let policy = PlaceholderPolicy::from_index(placeholder_policy as u8);
+                settings.set_placeholder_policy(policy);
+                if let Some(ref mut loader) = *library_loader.lock().unwrap() {
+                    loader.set_placeholder_policy(policy);
+                }
 
-            tracing::debug!("请求缩略图: {} - {}", start, end);
+                settings.save_preferences();
 
-            // 使用缓存的加载器加载缩略图
-            if let Some(ref cache) = *thumbnail_cache.lock().unwrap() {
-                cache.request_range_with_loader(
-                    start,
-                    end,
-                    window_weak.clone(),
-                    library_loader.clone(),
+                tracing::info!(
+                    "设置已更新: cache_max_size={}, key_throttle_ms={}, placeholder={}, thumbnail_size={}",
+                    cache_max_size,
+                    key_throttle_ms,
+                    policy.name(),
+                    thumbnail_size
                 );
-            }
-        });
+            },
+        );
     }
 
-    // 设置键盘事件回调 - 在 Rust 端处理导航
+    // 设置拖放文件处理：库文件直接打开，PNG 写入落点处的帧
     {
-        let window_weak = window_weak.clone();
         let library_loader = state.library_loader.clone();
-        let last_key_time = state.last_key_time.clone();
+        let thumbnail_cache = state.thumbnail_cache.clone();
+        let opening = state.opening.clone();
         let settings = state.settings.clone();
 
-        window.on_key_pressed(move |text| {
-            // 获取按键字符的 Unicode 码点
-            // Slint 箭头键码点: U+F700(Up), U+F701(Down), U+F702(Left), U+F703(Right)
-            let key_code = text.chars().next().map(|c| c as u32);
-
-            // 判断是否是导航键
-            let is_navigation_key = matches!(
-                key_code,
-                Some(0xF700) | Some(0xF701) |  // Up, Down
-                Some(0xF702) | Some(0xF703) |  // Left, Right
-                Some(0xF704) | Some(0xF705) // Home, End
-            );
+        drop::install(&window, move |window, files, position| {
+            if let Some(path) = files.libraries.first() {
+                if files.libraries.len() + files.images.len() > 1 {
+                    tracing::warn!("一次拖入多个文件，只打开 {:?}", path);
+                }
+                open_library(window, path.clone(), &opening, &thumbnail_cache);
+                return;
+            }
 
-            if !is_navigation_key {
-                return; // 不是导航键，不处理
+            // 无法取得落点时追加到末尾
+            let target = position.map_or(-1, |p| window.invoke_drop_target(p.x, p.y));
+            if target == -2 {
+                window.set_status_text(SharedString::from(tr!(
+                    "请把 PNG 拖到缩略图区域：落在帧上替换，落在末尾空白处追加"
+                )));
+                return;
             }
 
-            // 节流检查：使用动态配置的间隔
-            {
-                let mut last_time = last_key_time.lock().unwrap();
-                let elapsed = last_time.elapsed().as_millis();
-                let throttle_ms = settings.get_key_throttle_ms();
-                if elapsed < throttle_ms {
-                    return; // 忽略过快的按键
-                }
-                *last_time = Instant::now();
+            if let Some(ref mut loader) = *library_loader.lock().unwrap() {
+                let at = usize::try_from(target).ok();
+                import_dropped_images(
+                    window,
+                    loader,
+                    &files.images,
+                    at,
+                    &thumbnail_cache,
+                    &settings,
+                );
+            } else {
+                window.set_status_text(SharedString::from(tr!("请先打开库文件再拖入 PNG")));
             }
+        });
+    }
 
-            let window = match window_weak.upgrade() {
-                Some(w) => w,
-                None => return,
+    // 当前库文件在外部被修改时自动重新加载（有未保存的修改时只提示）
+    let watch_timer = slint::Timer::default();
+    {
+        let window_weak = window_weak.clone();
+        let library_loader = state.library_loader.clone();
+        let thumbnail_cache = state.thumbnail_cache.clone();
+        let opening = state.opening.clone();
+        let library_watch = state.library_watch.clone();
+
+        watch_timer.start(slint::TimerMode::Repeated, WATCH_INTERVAL, move || {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            let current = open_library_kind(&library_loader).map(|(path, _)| path);
+            let mut watch = library_watch.lock().unwrap();
+
+            // 打开了另一个库（或关闭了库）时换成监视新的文件
+            if watch.as_ref().map(|w| &w.path) != current.as_ref() {
+                *watch = current.and_then(|path| match FileWatcher::library(&path) {
+                    Ok(files) => Some(LibraryWatch {
+                        path,
+                        files,
+                        restore_index: None,
+                    }),
+                    Err(e) => {
+                        tracing::warn!("无法监视 {:?}: {}", path, e);
+                        None
+                    }
+                });
+                return;
+            }
+            let Some(watch) = watch.as_mut() else {
+                return;
+            };
+            let Some(changed) = watch.files.poll() else {
+                return;
             };
+            tracing::info!("库文件在外部被修改: {:?}", changed);
+            if opening.lock().unwrap().is_some() {
+                return;
+            }
 
-            let image_count = window.get_image_count();
-            if image_count == 0 {
+            let modified = library_loader
+                .lock()
+                .unwrap()
+                .as_ref()
+                .is_some_and(|loader| loader.is_modified());
+            if modified {
+                window.set_status_text(SharedString::from(tr!(
+                    "库文件已在外部修改，当前有未保存的修改，没有自动重新加载"
+                )));
                 return;
             }
+            watch.restore_index = Some(window.get_current_index());
+            open_library(&window, watch.path.clone(), &opening, &thumbnail_cache);
+        });
+    }
 
-            let current = window.get_current_index();
-            let cols = window.get_thumb_cols().max(1);
-            let mut new_index = current;
+    // 恢复或丢弃上次未保存的修改
+    {
+        let window_weak = window_weak.clone();
+        let recovery_offer = state.recovery_offer.clone();
+        let recovery = state.recovery.clone();
+        let opening = state.opening.clone();
+        let thumbnail_cache = state.thumbnail_cache.clone();
 
-            // 判断按键
-            match key_code {
-                Some(0xF702) => {
-                    // Left
-                    if current > 0 {
-                        new_index = current - 1;
-                    }
-                }
-                Some(0xF703) => {
-                    // Right
-                    if current < image_count - 1 {
-                        new_index = current + 1;
-                    }
-                }
-                Some(0xF700) => {
-                    // Up
-                    if current >= cols {
-                        new_index = current - cols;
-                    }
-                }
-                Some(0xF701) => {
-                    // Down
-                    let idx = current + cols;
-                    if idx < image_count {
-                        new_index = idx;
-                    }
-                }
-                Some(0xF704) => {
-                    // Home
-                    new_index = 0;
+        window.on_restore_recovery(move || {
+            let Some(window) = window_weak.upgrade() else {
+                return;
+            };
+            let Some(offer) = recovery_offer.lock().unwrap().take() else {
+                return;
+            };
+            match Journal::read(&offer.path) {
+                Ok(journal) => {
+                    let library = journal.library.clone();
+                    *recovery.lock().unwrap() = Some(journal);
+                    open_library(&window, library, &opening, &thumbnail_cache);
                 }
-                Some(0xF705) => {
-                    // End
-                    new_index = image_count - 1;
+                Err(e) => {
+                    tracing::error!("读取恢复日志 {:?} 失败: {}", offer.path, e);
+                    window.set_status_text(SharedString::from(&tr!("读取恢复日志失败: {}", e)));
                 }
-                _ => return,
             }
+        });
+    }
+    {
+        let recovery_offer = state.recovery_offer.clone();
 
-            // 如果索引有变化，更新UI
-            if new_index != current {
-                window.set_current_index(new_index);
-                tracing::debug!("切换到图像: {}", new_index);
-
-                if let Some(ref mut loader) = *library_loader.lock().unwrap() {
-                    if let Ok(img_info) = loader.get_image_info(new_index as usize) {
-                        window.set_image_width(img_info.width);
-                        window.set_image_height(img_info.height);
-                        window.set_image_x(img_info.x);
-                        window.set_image_y(img_info.y);
-                    }
-                    AppState::update_main_preview(&window, loader, new_index as usize);
-                }
+        window.on_discard_recovery(move || {
+            if let Some(offer) = recovery_offer.lock().unwrap().take() {
+                tracing::info!("丢弃 {:?} 未保存的修改", offer.library);
+                journal::discard(&offer.path);
             }
         });
     }
 
-    // 设置保存设置回调
+    // 定时把收集到的警告和错误显示到问题面板
+    let problem_timer = slint::Timer::default();
     {
-        let settings = state.settings.clone();
+        let shown = Rc::new(Mutex::new(VecDeque::new()));
+        {
+            let window_weak = window_weak.clone();
+            let problem_log = problem_log.clone();
+            let shown = shown.clone();
+
+            window.on_clear_problems(move || {
+                problem_log.take();
+                shown.lock().unwrap().clear();
+                if let Some(window) = window_weak.upgrade() {
+                    show_problems(&window, &shown.lock().unwrap());
+                }
+            });
+        }
+        let window_weak = window_weak.clone();
+
+        problem_timer.start(
+            slint::TimerMode::Repeated,
+            PROBLEM_POLL_INTERVAL,
+            move || {
+                let new = problem_log.take();
+                if new.is_empty() {
+                    return;
+                }
+                let Some(window) = window_weak.upgrade() else {
+                    return;
+                };
+                // 同一帧反复失败（如缩略图重新请求）只列一次
+                let mut shown = shown.lock().unwrap();
+                for problem in new {
+                    if !shown.contains(&problem) {
+                        shown.push_back(problem);
+                    }
+                }
+                let excess = shown.len().saturating_sub(problems::MAX_PROBLEMS);
+                shown.drain(..excess);
+                show_problems(&window, &shown);
+            },
+        );
+    }
+
+    // 有未保存的修改时定期写入恢复日志
+    let autosave_timer = slint::Timer::default();
+    let autosave_secs = state.settings.preferences().autosave_secs;
+    if autosave_secs > 0 && !bench_gui {
+        let library_loader = state.library_loader.clone();
+        let journal_written = state.journal_written.clone();
+
+        autosave_timer.start(
+            slint::TimerMode::Repeated,
+            Duration::from_secs(autosave_secs),
+            move || {
+                autosave_journal(
+                    library_loader.lock().unwrap().as_mut(),
+                    &mut journal_written.lock().unwrap(),
+                );
+            },
+        );
+    }
 
-        window.on_save_settings(move |cache_max_size, key_throttle_ms| {
-            settings.set_cache_max_size(cache_max_size as u64);
-            settings.set_key_throttle_ms(key_throttle_ms as u64);
-            tracing::info!(
-                "设置已更新: cache_max_size={}, key_throttle_ms={}",
-                cache_max_size,
-                key_throttle_ms
+    // 命令行或资源管理器“打开方式”传入的文件：项目文件按项目打开，其余按库文件打开
+    if let Some(path) = open {
+        if !path.is_file() {
+            window.set_status_text(SharedString::from(&tr!("文件不存在: {}", path.display())));
+        } else if path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case(PROJECT_EXTENSION))
+        {
+            open_project(
+                &window,
+                path,
+                &state.project,
+                &state.opening,
+                &state.thumbnail_cache,
             );
-        });
+        } else {
+            open_library(&window, path, &state.opening, &state.thumbnail_cache);
+        }
+    }
+
+    // 隐藏的基准测试模式：载入合成库并自动滚动，结束后退出
+    let bench_timer = slint::Timer::default();
+    if bench_gui {
+        let library_loader = state.library_loader.clone();
+        let thumbnail_cache = state.thumbnail_cache.clone();
+        let settings = state.settings.clone();
+        bench::start(
+            &window,
+            &state.library_loader,
+            &bench_timer,
+            |window, info, loader| {
+                show_library(
+                    window,
+                    info,
+                    loader,
+                    &library_loader,
+                    &thumbnail_cache,
+                    &settings,
+                );
+            },
+        )?;
     }
 
     tracing::debug!("运行主窗口");
@@ -897,5 +5541,16 @@ pub fn run() -> Result<()> {
         .run()
         .map_err(|e| LibraryError::Gui(format!("运行窗口失败: {:?}", e)))?;
 
+    // 正常退出时不保留恢复日志
+    if let Some((path, _)) = state.journal_written.lock().unwrap().take() {
+        journal::discard(&path);
+    }
+
+    // 基准测试不改动用户的偏好设置
+    if !bench_gui {
+        store_preferences(&window, &mut state.settings.preferences());
+        state.settings.save_preferences();
+    }
+
     Ok(())
 }
@@ -0,0 +1,228 @@
+//! 偏好设置
+//!
+//! 记住最近打开的库、各类文件对话框上次所在的目录、窗口尺寸、预览背景、
+//! 缩略图大小和视图、数字格式、界面语言、快捷键和设置对话框中的选项，以 JSON 保存在用户配置目录
+//! （[`crate::locale::config_dir`]）中，启动时由 `gui::run` 恢复，退出时写回。
+
+use super::keymap::Action;
+use crate::error::Result;
+use crate::i18n::Language;
+use crate::image::chroma_key::ChromaKey;
+use crate::locale::NumberFormat;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// 最近打开列表的最大条数
+pub const MAX_RECENT_FILES: usize = 10;
+
+/// 缩略图默认边长（像素）
+pub const DEFAULT_THUMBNAIL_SIZE: u32 = 80;
+
+/// 缩略图边长范围（像素）
+pub const THUMBNAIL_SIZE_RANGE: std::ops::RangeInclusive<u32> = 32..=256;
+
+/// 解码帧默认最多占用的内存（MiB）
+pub const DEFAULT_FRAME_MEMORY_MB: usize = 512;
+
+/// 默认每隔多少秒写入一次恢复日志
+pub const DEFAULT_AUTOSAVE_SECS: u64 = 30;
+
+/// 文件对话框的用途（各自记住上次所在的目录）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum DialogDir {
+    /// 打开 / 另存库文件
+    Library,
+    /// 导入 / 导出单张图像
+    Image,
+    /// 批量导出的目标目录
+    Export,
+    /// 运行的 Rhai 脚本
+    Script,
+}
+
+/// 持久化的偏好设置
+///
+/// 缺少的字段取默认值，旧版本写出的文件可以直接读取。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Preferences {
+    /// 最近打开的库（最新的在前）
+    pub recent_files: Vec<PathBuf>,
+    /// 各类对话框上次所在的目录
+    pub last_dirs: BTreeMap<DialogDir, PathBuf>,
+    /// 窗口尺寸（逻辑像素）
+    pub window_size: Option<(u32, u32)>,
+    /// 预览背景模式
+    pub preview_bg_mode: i32,
+    /// 自定义背景色（#RRGGBB）
+    pub preview_bg_color: String,
+    /// 参考背景图
+    pub preview_bg_image: Option<PathBuf>,
+    /// 缩略图边长（像素）
+    pub thumbnail_size: u32,
+    /// 缩略图网格视图（缩略图铺满窗口）
+    pub thumbnail_grid: bool,
+    /// 洋葱皮中前后帧的不透明度（百分比）
+    pub onion_opacity: u8,
+    /// LRU 缓存最大容量（0 表示无限制）
+    pub cache_max_size: u64,
+    /// 解码帧最多占用的内存（MiB，0 表示不限制）
+    pub frame_memory_mb: usize,
+    /// 按键节流间隔（毫秒）
+    pub key_throttle_ms: u64,
+    /// 空帧占位策略（PlaceholderPolicy 序号）
+    pub placeholder_policy: u8,
+    /// 导入图像时的透明色
+    pub import_key: ChromaKey,
+    /// 导入时把不透明的纯黑写成近黑色
+    pub preserve_black: bool,
+    /// 导出 PNG 时把 8 位调色板帧写成索引色 PNG（保留原调色板）
+    pub indexed_png: bool,
+    /// 完整性自检的资源目录
+    pub asset_dirs: Vec<PathBuf>,
+    /// 启动时检查资源目录
+    pub self_test_on_startup: bool,
+    /// 报告和状态栏中的数字格式（命令行也读取这一项）
+    pub number_format: NumberFormat,
+    /// 仅追加模式：删除帧改为清空，已有帧的索引不变
+    pub append_only: bool,
+    /// 保存库时保留的旧版本份数（`.bak1`、`.bak2`……，0 表示不保留）
+    pub backup_count: usize,
+    /// 有未保存的修改时写入恢复日志的间隔（秒，0 表示不写）
+    pub autosave_secs: u64,
+    /// 界面和命令行的语言（`None` 为跟随系统，命令行也读取这一项）
+    pub language: Option<Language>,
+    /// 界面缩放（百分比，在系统缩放的基础上再乘）
+    pub ui_scale: u32,
+    /// 修改过的快捷键（操作 -> 逗号分隔的快捷键，未列出的操作使用默认快捷键）
+    pub shortcuts: BTreeMap<Action, String>,
+}
+
+impl Default for Preferences {
+    fn default() -> Self {
+        Self {
+            recent_files: Vec::new(),
+            last_dirs: BTreeMap::new(),
+            window_size: None,
+            preview_bg_mode: 0,
+            preview_bg_color: "#808080".to_string(),
+            preview_bg_image: None,
+            thumbnail_size: DEFAULT_THUMBNAIL_SIZE,
+            thumbnail_grid: false,
+            onion_opacity: 35,
+            cache_max_size: super::DEFAULT_CACHE_MAX_SIZE,
+            frame_memory_mb: DEFAULT_FRAME_MEMORY_MB,
+            key_throttle_ms: super::DEFAULT_KEY_THROTTLE_MS,
+            placeholder_policy: crate::image::PlaceholderPolicy::default().to_index(),
+            import_key: ChromaKey::default(),
+            preserve_black: false,
+            indexed_png: false,
+            asset_dirs: Vec::new(),
+            self_test_on_startup: false,
+            number_format: NumberFormat::default(),
+            append_only: false,
+            backup_count: 0,
+            autosave_secs: DEFAULT_AUTOSAVE_SECS,
+            language: None,
+            ui_scale: 100,
+            shortcuts: BTreeMap::new(),
+        }
+    }
+}
+
+impl Preferences {
+    /// 读取偏好设置，不存在或损坏时返回默认值
+    pub fn load(path: &Path) -> Self {
+        let mut preferences: Self = std::fs::read(path)
+            .ok()
+            .and_then(|data| serde_json::from_slice(&data).ok())
+            .unwrap_or_default();
+        preferences.thumbnail_size = preferences
+            .thumbnail_size
+            .clamp(*THUMBNAIL_SIZE_RANGE.start(), *THUMBNAIL_SIZE_RANGE.end());
+        preferences
+    }
+
+    /// 写入偏好设置
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// 记录打开的库：移到列表最前，并记住所在目录
+    pub fn add_recent(&mut self, file: &Path) {
+        let file = file.canonicalize().unwrap_or_else(|_| file.to_path_buf());
+        self.set_last_dir(DialogDir::Library, &file);
+        self.recent_files.retain(|recent| *recent != file);
+        self.recent_files.insert(0, file);
+        self.recent_files.truncate(MAX_RECENT_FILES);
+    }
+
+    /// 从最近打开列表中移除（文件已不存在时）
+    pub fn remove_recent(&mut self, file: &Path) {
+        self.recent_files.retain(|recent| recent != file);
+    }
+
+    /// 对话框上次所在的目录（目录已不存在时忽略）
+    pub fn last_dir(&self, kind: DialogDir) -> Option<&Path> {
+        self.last_dirs
+            .get(&kind)
+            .map(PathBuf::as_path)
+            .filter(|dir| dir.is_dir())
+    }
+
+    /// 记住对话框所在的目录：传入文件时取其父目录
+    pub fn set_last_dir(&mut self, kind: DialogDir, path: &Path) {
+        let dir = if path.is_dir() {
+            Some(path)
+        } else {
+            path.parent()
+        };
+        if let Some(dir) = dir.filter(|dir| !dir.as_os_str().is_empty()) {
+            self.last_dirs.insert(kind, dir.to_path_buf());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::locale::PREFERENCES_FILE;
+
+    #[test]
+    fn test_recent_files_and_roundtrip() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let path = dir.join(PREFERENCES_FILE);
+
+        // 文件不存在时取默认值
+        assert_eq!(Preferences::load(&path), Preferences::default());
+
+        let mut preferences = Preferences::default();
+        for i in 0..MAX_RECENT_FILES + 2 {
+            preferences.add_recent(&dir.join(format!("{i}.wil")));
+        }
+        preferences.add_recent(&dir.join("5.wil"));
+        assert_eq!(preferences.recent_files.len(), MAX_RECENT_FILES);
+        assert_eq!(preferences.recent_files[0], dir.join("5.wil"));
+        assert_eq!(preferences.recent_files[1], dir.join("11.wil"));
+        assert_eq!(preferences.last_dir(DialogDir::Library), Some(dir));
+        assert_eq!(preferences.last_dir(DialogDir::Image), None);
+
+        preferences.window_size = Some((1280, 800));
+        preferences.thumbnail_size = 120;
+        preferences.thumbnail_grid = true;
+        preferences.save(&path).unwrap();
+        assert_eq!(Preferences::load(&path), preferences);
+
+        // 缺少的字段取默认值，越界的缩略图尺寸被收回范围内
+        std::fs::write(&path, r#"{"thumbnail_size": 4000}"#).unwrap();
+        let partial = Preferences::load(&path);
+        assert_eq!(partial.thumbnail_size, *THUMBNAIL_SIZE_RANGE.end());
+        assert_eq!(partial.preview_bg_color, "#808080");
+    }
+}
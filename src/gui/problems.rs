@@ -0,0 +1,137 @@
+//! 问题面板
+//!
+//! 帧解码失败、保存时的警告和格式转换中丢失的信息（如保存为 WZL 时去掉遮罩层）原先
+//! 只写入日志。[`ProblemLayer`] 作为 tracing 的一层把警告和错误另外收集起来，界面定时
+//! 取出，显示在状态栏上方可折叠的问题面板中。记录时带 `frame` 字段的问题（如
+//! `tracing::warn!(frame = index, ...)`）可以点击跳转到对应的帧。
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+
+/// 面板中最多保留的问题数，超出时丢弃最早的
+pub const MAX_PROBLEMS: usize = 500;
+
+/// 一条警告或错误
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Problem {
+    /// 是否为错误（否则为警告）
+    pub error: bool,
+    /// 涉及的帧索引
+    pub frame: Option<usize>,
+    /// 日志内容
+    pub message: String,
+}
+
+/// 收集到的问题，由 [`ProblemLayer`] 写入，界面取出
+#[derive(Debug, Clone, Default)]
+pub struct ProblemLog(Arc<Mutex<VecDeque<Problem>>>);
+
+impl ProblemLog {
+    /// 写入本记录的 tracing 层
+    pub fn layer(&self) -> ProblemLayer {
+        ProblemLayer(self.clone())
+    }
+
+    /// 取出尚未显示的问题（按发生顺序）
+    pub fn take(&self) -> Vec<Problem> {
+        self.0.lock().unwrap().drain(..).collect()
+    }
+
+    fn push(&self, problem: Problem) {
+        let mut problems = self.0.lock().unwrap();
+        if problems.len() >= MAX_PROBLEMS {
+            problems.pop_front();
+        }
+        problems.push_back(problem);
+    }
+}
+
+/// 收集警告和错误的 tracing 层
+pub struct ProblemLayer(ProblemLog);
+
+impl<S: Subscriber> Layer<S> for ProblemLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let level = *event.metadata().level();
+        if level > Level::WARN {
+            return;
+        }
+        let mut visitor = ProblemVisitor::default();
+        event.record(&mut visitor);
+        self.0.push(Problem {
+            error: level == Level::ERROR,
+            frame: visitor.frame,
+            message: visitor.message,
+        });
+    }
+}
+
+/// 从事件中取出 `frame` 字段和日志内容
+#[derive(Default)]
+struct ProblemVisitor {
+    frame: Option<usize>,
+    message: String,
+}
+
+impl Visit for ProblemVisitor {
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        if field.name() == "frame" {
+            self.frame = usize::try_from(value).ok();
+        }
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        if field.name() == "frame" {
+            self.frame = usize::try_from(value).ok();
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message = value.to_string();
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.message = format!("{:?}", value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[test]
+    fn test_collects_warnings_with_frames() {
+        let log = ProblemLog::default();
+        let subscriber = tracing_subscriber::registry().with(log.layer());
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!("打开库");
+            tracing::warn!(frame = 12usize, "加载帧 {} 失败", 12);
+            tracing::error!("保存失败: {}", "磁盘已满");
+        });
+
+        assert_eq!(
+            log.take(),
+            vec![
+                Problem {
+                    error: false,
+                    frame: Some(12),
+                    message: "加载帧 12 失败".to_string(),
+                },
+                Problem {
+                    error: true,
+                    frame: None,
+                    message: "保存失败: 磁盘已满".to_string(),
+                },
+            ]
+        );
+        assert!(log.take().is_empty());
+    }
+}
@@ -0,0 +1,99 @@
+//! 界面缩放
+//!
+//! 在系统缩放（高 DPI 显示器上的 150%、200% 等）的基础上再乘以偏好设置中的界面缩放。
+//! 窗口系统报告的缩放变化由 [`super::drop`] 注册的 winit 事件处理转交到这里，记下系统的
+//! 缩放系数后换成乘上界面缩放的系数再交给 Slint；窗口创建时 Slint 直接取系统的系数，
+//! 收到第一个窗口事件后补上界面缩放。
+//!
+//! 预览和缩略图中的像素图按物理像素的整数倍显示，不受这里缩放系数是否为整数的影响。
+
+use slint::platform::WindowEvent;
+use slint::winit_030::WinitWindowAccessor;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::time::Duration;
+
+/// 界面缩放的可选值（百分比），设置对话框中的选项与此一一对应
+pub const UI_SCALE_CHOICES: [u32; 6] = [75, 100, 125, 150, 175, 200];
+
+/// 界面缩放（百分比）
+static UI_SCALE: AtomicU32 = AtomicU32::new(100);
+/// 系统报告的缩放系数（`f32` 的位表示，0 表示尚未收到）
+static SYSTEM_SCALE: AtomicU32 = AtomicU32::new(0);
+/// 是否已按界面缩放设置过窗口
+static APPLIED: AtomicBool = AtomicBool::new(false);
+
+/// 界面缩放在 [`UI_SCALE_CHOICES`] 中的下标，不在其中时取最接近的一项
+pub fn choice(percent: u32) -> i32 {
+    UI_SCALE_CHOICES
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, value)| value.abs_diff(percent))
+        .map_or(1, |(index, _)| index as i32)
+}
+
+/// 设置对话框选项对应的界面缩放（百分比）
+pub fn from_choice(choice: i32) -> u32 {
+    usize::try_from(choice)
+        .ok()
+        .and_then(|index| UI_SCALE_CHOICES.get(index).copied())
+        .unwrap_or(100)
+}
+
+/// 设置界面缩放并立即应用到窗口（窗口尚未创建时在第一个窗口事件后应用）
+pub fn set_ui_scale(window: &slint::Window, percent: u32) {
+    UI_SCALE.store(percent.max(1), Ordering::Relaxed);
+    apply(window);
+}
+
+/// 窗口系统报告缩放变化（如窗口移到另一台显示器）
+pub fn system_scale_changed(window: slint::Weak<super::AppWindow>, scale_factor: f64) {
+    SYSTEM_SCALE.store((scale_factor as f32).to_bits(), Ordering::Relaxed);
+    apply_later(window);
+}
+
+/// 收到窗口事件：窗口已创建，第一次时补上界面缩放
+pub fn window_event(window: &slint::Weak<super::AppWindow>) {
+    if !APPLIED.swap(true, Ordering::Relaxed) {
+        apply_later(window.clone());
+    }
+}
+
+/// 在当前这轮事件处理完后应用，避免在 winit 事件处理中重入窗口
+fn apply_later(window: slint::Weak<super::AppWindow>) {
+    slint::Timer::single_shot(Duration::ZERO, move || {
+        if let Some(window) = window.upgrade() {
+            apply(slint::ComponentHandle::window(&window));
+        }
+    });
+}
+
+/// 按系统缩放乘界面缩放设置窗口的缩放系数
+fn apply(window: &slint::Window) {
+    let system = match f32::from_bits(SYSTEM_SCALE.load(Ordering::Relaxed)) {
+        scale if scale > 0.0 => scale,
+        _ => match window.with_winit_window(|winit| winit.scale_factor() as f32) {
+            Some(scale) => scale,
+            None => return,
+        },
+    };
+    let scale_factor = system * UI_SCALE.load(Ordering::Relaxed) as f32 / 100.0;
+    if (window.scale_factor() - scale_factor).abs() > f32::EPSILON {
+        tracing::debug!("界面缩放系数: {}（系统 {}）", scale_factor, system);
+        window.dispatch_event(WindowEvent::ScaleFactorChanged { scale_factor });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_choice_round_trip() {
+        for (index, percent) in UI_SCALE_CHOICES.iter().enumerate() {
+            assert_eq!(choice(*percent), index as i32);
+            assert_eq!(from_choice(index as i32), *percent);
+        }
+        assert_eq!(choice(110), 1);
+        assert_eq!(from_choice(-1), 100);
+    }
+}
@@ -0,0 +1,120 @@
+//! 缩略图多选
+//!
+//! 选择状态保存在界面的 `selection`（按索引的标记）和 `selection_anchor` 属性中，
+//! 帧增删后界面直接清空即可，不会与 Rust 侧的状态脱节。这里负责按点击方式更新选择：
+//! 普通点击只选中一帧，Ctrl 点击增减单帧，Shift 点击从锚点选到当前帧。
+
+use std::collections::BTreeSet;
+
+/// 多选集合
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Selection {
+    marked: BTreeSet<usize>,
+    /// Shift 点击的起点（上一次普通点击或 Ctrl 点击的帧）
+    anchor: Option<usize>,
+}
+
+impl Selection {
+    /// 由界面上的标记和锚点还原
+    pub fn new(flags: impl IntoIterator<Item = bool>, anchor: Option<usize>) -> Self {
+        let marked = flags
+            .into_iter()
+            .enumerate()
+            .filter_map(|(i, marked)| marked.then_some(i))
+            .collect();
+        Self { marked, anchor }
+    }
+
+    /// 处理一次点击
+    pub fn click(&mut self, index: usize, control: bool, shift: bool) {
+        if shift {
+            let anchor = self.anchor.unwrap_or(index);
+            if !control {
+                self.marked.clear();
+            }
+            self.marked.extend(anchor.min(index)..=anchor.max(index));
+            return;
+        }
+
+        if control {
+            // 从单选开始 Ctrl 点击时，先把锚点帧加入集合
+            if self.marked.is_empty()
+                && let Some(anchor) = self.anchor
+            {
+                self.marked.insert(anchor);
+            }
+            if !self.marked.remove(&index) {
+                self.marked.insert(index);
+            }
+        } else {
+            self.marked.clear();
+            self.marked.insert(index);
+        }
+        self.anchor = Some(index);
+    }
+
+    /// 是否选中了多帧（只选中一帧时批量操作退化为对当前帧操作）
+    pub fn is_multiple(&self) -> bool {
+        self.marked.len() > 1
+    }
+
+    /// 已选帧数
+    pub fn len(&self) -> usize {
+        self.marked.len()
+    }
+
+    /// 已选帧索引（升序）
+    pub fn indices(&self) -> Vec<usize> {
+        self.marked.iter().copied().collect()
+    }
+
+    /// 锚点
+    pub fn anchor(&self) -> Option<usize> {
+        self.anchor
+    }
+
+    /// 界面显示用的标记：只有多选时才标记，长度到最大的已选索引为止
+    pub fn flags(&self) -> Vec<bool> {
+        if !self.is_multiple() {
+            return Vec::new();
+        }
+        let len = self.marked.last().map_or(0, |&last| last + 1);
+        let mut flags = vec![false; len];
+        for &index in &self.marked {
+            flags[index] = true;
+        }
+        flags
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_click_modifiers() {
+        let mut selection = Selection::default();
+        selection.click(3, false, false);
+        assert!(!selection.is_multiple());
+        assert!(selection.flags().is_empty());
+
+        // Ctrl 点击把先前单选的帧一并纳入
+        selection.click(5, true, false);
+        assert_eq!(selection.indices(), vec![3, 5]);
+
+        // Shift 点击从锚点 5 选到 1，替换原有集合
+        selection.click(1, false, true);
+        assert_eq!(selection.indices(), vec![1, 2, 3, 4, 5]);
+
+        // Ctrl 点击取消其中一帧
+        selection.click(2, true, false);
+        assert_eq!(selection.indices(), vec![1, 3, 4, 5]);
+        assert_eq!(
+            selection.flags(),
+            vec![false, true, false, true, true, true]
+        );
+
+        let restored = Selection::new(selection.flags(), selection.anchor());
+        assert_eq!(restored, selection);
+    }
+}
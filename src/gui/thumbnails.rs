@@ -0,0 +1,305 @@
+//! 缩略图网格
+//!
+//! 缩略图按网格中可见的范围懒加载：界面滚动时请求一段格子，在工作线程中逐帧解码后放入
+//! LRU 缓存并回填到缩略图列表。缩略图条和网格视图的切换、格子大小都由界面决定，这里只按
+//! 界面报告的生成尺寸（物理像素）解码；尺寸变化时已缓存的缩略图作废。
+
+use super::{AppSettings, AppState, AppWindow, Result, rgba_image_to_pixels, rgba_image_to_slint};
+use crate::formats::{LibraryHeader, LibraryLoader};
+use crate::image::index_bar::FrameState;
+use crate::progress::{ProgressEvent, ProgressSender, ProgressTracker};
+use slint::{ComponentHandle, Model};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// 缩略图像素（可在线程间传递，显示时再转成 `slint::Image`）
+pub type ThumbnailPixels = slint::SharedPixelBuffer<slint::Rgba8Pixel>;
+
+/// 缩略图缓存（LRU 策略）
+///
+/// 缩略图在工作线程中解码；帧增删或换库后旧缓存作废，作废前已开始的加载结果丢弃。
+pub struct ThumbnailCache {
+    /// 缓存的缩略图（索引 -> 像素）
+    cache: Mutex<HashMap<usize, ThumbnailPixels>>,
+    /// LRU 访问顺序（最近使用的在末尾）
+    access_order: Mutex<Vec<usize>>,
+    /// 总图片数
+    total_count: usize,
+    /// 正在加载的索引集合
+    loading: Mutex<HashSet<usize>>,
+    /// 加载进度事件发送端
+    events: ProgressSender,
+    /// 各帧的加载结果（由进度监听线程汇总）
+    pub progress: Arc<Mutex<ProgressTracker>>,
+    /// 已保存文件中各帧是否为空帧（按文件中的索引，来自文件头）
+    file_empty: Mutex<Vec<bool>>,
+    /// 缓存中缩略图的生成尺寸（物理像素）
+    pixels: AtomicU32,
+    /// 应用设置引用
+    settings: Arc<AppSettings>,
+    /// 已被新缓存取代
+    retired: AtomicBool,
+}
+
+impl ThumbnailCache {
+    pub fn new(total_count: usize, settings: Arc<AppSettings>, events: ProgressSender) -> Self {
+        Self {
+            cache: Mutex::new(HashMap::new()),
+            access_order: Mutex::new(Vec::new()),
+            total_count,
+            loading: Mutex::new(HashSet::new()),
+            events,
+            progress: Arc::new(Mutex::new(ProgressTracker::new(total_count))),
+            file_empty: Mutex::new(Vec::new()),
+            pixels: AtomicU32::new(settings.get_thumbnail_pixels()),
+            settings,
+            retired: AtomicBool::new(false),
+        }
+    }
+
+    /// 标记为作废：工作线程停止加载，已解码的结果不再显示
+    pub fn retire(&self) {
+        self.retired.store(true, Ordering::SeqCst);
+    }
+
+    fn is_retired(&self) -> bool {
+        self.retired.load(Ordering::SeqCst)
+    }
+
+    /// 按文件头记录已保存文件中的空帧
+    pub fn set_file_header(&self, header: Option<&LibraryHeader>) {
+        *self.file_empty.lock().unwrap() = header
+            .map(|header| header.frames.iter().map(|f| f.is_empty()).collect())
+            .unwrap_or_default();
+    }
+
+    /// 沿用旧缓存记录的已保存文件中的空帧
+    pub fn copy_file_header(&self, old: &ThumbnailCache) {
+        *self.file_empty.lock().unwrap() = old.file_empty.lock().unwrap().clone();
+    }
+
+    /// 各帧当前的状态：加载失败 > 已编辑 > 空帧 > 已加载 > 未加载
+    pub fn frame_states(&self, loader: &LibraryLoader) -> Vec<FrameState> {
+        let progress = self.progress.lock().unwrap();
+        let file_empty = self.file_empty.lock().unwrap();
+        (0..loader.image_count())
+            .map(|index| {
+                if progress.is_failed(index) {
+                    return FrameState::Corrupt;
+                }
+                match loader.frame_origin(index) {
+                    None => FrameState::Edited,
+                    Some(origin) if file_empty.get(origin) == Some(&true) => FrameState::Empty,
+                    Some(_) if progress.is_loaded(index) => FrameState::Loaded,
+                    Some(_) => FrameState::Unloaded,
+                }
+            })
+            .collect()
+    }
+
+    /// 获取缓存的缩略图
+    pub fn get(&self, index: usize) -> Option<slint::Image> {
+        let cache = self.cache.lock().unwrap();
+        if let Some(pixels) = cache.get(&index) {
+            // 更新 LRU 顺序
+            let mut order = self.access_order.lock().unwrap();
+            order.retain(|&i| i != index);
+            order.push(index);
+            return Some(slint::Image::from_rgba8(pixels.clone()));
+        }
+        None
+    }
+
+    /// 插入缩略图到缓存
+    fn put(&self, index: usize, image: ThumbnailPixels) {
+        let mut cache = self.cache.lock().unwrap();
+        let mut order = self.access_order.lock().unwrap();
+
+        // 如果已存在，先移除
+        if cache.contains_key(&index) {
+            order.retain(|&i| i != index);
+        }
+
+        // 如果缓存已满，移除最久未使用的（使用动态配置的 max_size）
+        let max_size = self.settings.get_cache_max_size();
+        if cache.len() >= max_size
+            && let Some(old_index) = order.first().copied()
+        {
+            cache.remove(&old_index);
+            order.remove(0);
+        }
+
+        cache.insert(index, image);
+        order.push(index);
+        tracing::trace!("缓存缩略图: {}, 缓存大小: {}", index, cache.len());
+    }
+
+    /// 请求加载指定帧的缩略图
+    ///
+    /// 在工作线程中逐帧解码，每帧只短暂持有共享加载器的锁，解码期间界面照常响应；
+    /// 解码完成后回到事件循环更新缩略图列表。
+    pub fn request_with_loader(
+        self: &Arc<Self>,
+        indices: &[usize],
+        window_weak: slint::Weak<AppWindow>,
+        library_loader: Arc<Mutex<Option<LibraryLoader>>>,
+    ) {
+        // 生成尺寸变了（调整缩略图大小或显示缩放），已缓存的缩略图作废
+        let pixels = self.settings.get_thumbnail_pixels();
+        if self.pixels.swap(pixels, Ordering::SeqCst) != pixels {
+            tracing::debug!("缩略图尺寸变为 {} 像素，清空缓存", pixels);
+            self.cache.lock().unwrap().clear();
+            self.access_order.lock().unwrap().clear();
+        }
+
+        // 找出需要加载的索引
+        let indices_to_load: Vec<usize> = {
+            let cache = self.cache.lock().unwrap();
+            let mut loading = self.loading.lock().unwrap();
+            tracing::debug!("缓存大小: {}, 正在加载: {}", cache.len(), loading.len());
+            let indices: Vec<usize> = indices
+                .iter()
+                .copied()
+                .filter(|&i| {
+                    i < self.total_count && !cache.contains_key(&i) && !loading.contains(&i)
+                })
+                .collect();
+            for &i in &indices {
+                loading.insert(i);
+            }
+            indices
+        };
+
+        if indices_to_load.is_empty() {
+            return;
+        }
+
+        tracing::debug!(
+            "请求加载缩略图: {} 张中 {} 张未缓存",
+            indices.len(),
+            indices_to_load.len()
+        );
+
+        let cache = Arc::clone(self);
+        std::thread::spawn(move || {
+            let mut loaded = Vec::new();
+            for &i in &indices_to_load {
+                if cache.is_retired() {
+                    break;
+                }
+                let result = match library_loader.lock().unwrap().as_mut() {
+                    Some(loader) => load(loader, i, pixels),
+                    None => break,
+                };
+                match result {
+                    Ok(Some(preview_img)) => {
+                        if let Some(buffer) = rgba_image_to_pixels(&preview_img) {
+                            // 存入缓存，避免重复加载
+                            cache.put(i, buffer.clone());
+                            loaded.push((i, buffer));
+                        }
+                        let _ = cache.events.send(ProgressEvent::FrameLoaded(i));
+                    }
+                    Ok(None) => {
+                        let _ = cache.events.send(ProgressEvent::FrameLoaded(i));
+                    }
+                    Err(e) => {
+                        let _ = cache.events.send(ProgressEvent::Error(i, e));
+                    }
+                }
+            }
+            let _ = cache.events.send(ProgressEvent::Done);
+
+            // 清除加载标记
+            {
+                let mut loading = cache.loading.lock().unwrap();
+                for i in &indices_to_load {
+                    loading.remove(i);
+                }
+            }
+
+            let _ = window_weak.upgrade_in_event_loop(move |win| {
+                if cache.is_retired() {
+                    return;
+                }
+                let thumbnails = win.get_thumbnails();
+                for (i, buffer) in loaded {
+                    if i < thumbnails.row_count() {
+                        thumbnails.set_row_data(i, slint::Image::from_rgba8(buffer));
+                    }
+                }
+            });
+        });
+    }
+}
+
+/// 换上新的缩略图缓存，旧缓存作废（其工作线程随之停止）
+pub fn replace_cache(
+    thumbnail_cache: &Mutex<Option<Arc<ThumbnailCache>>>,
+    cache: Option<Arc<ThumbnailCache>>,
+) {
+    if let Some(old) = std::mem::replace(&mut *thumbnail_cache.lock().unwrap(), cache) {
+        old.retire();
+    }
+}
+
+/// 帧的缩略图，按 `pixels`（物理像素）生成，读取失败时为空图
+pub fn frame_image(loader: &mut LibraryLoader, index: usize, pixels: u32) -> slint::Image {
+    match load(loader, index, pixels) {
+        Ok(image) => image
+            .and_then(|img| rgba_image_to_slint(&img))
+            .unwrap_or_default(),
+        Err(e) => {
+            tracing::warn!(frame = index, "生成缩略图 {} 失败: {}", index, e);
+            slint::Image::default()
+        }
+    }
+}
+
+/// 生成 `pixels`×`pixels`（物理像素）以内的缩略图
+///
+/// 小图按整数倍最近邻放大，界面按物理像素 1:1 显示，不经过 Slint 的平滑缩放，
+/// 像素图在高 DPI 显示器上同样清晰。
+fn load(
+    loader: &mut LibraryLoader,
+    index: usize,
+    pixels: u32,
+) -> Result<Option<Arc<image::RgbaImage>>> {
+    loader.get_pixel_thumbnail(index, pixels)
+}
+
+/// 设置请求缩略图回调：界面滚动时请求可见范围内的格子（懒加载）
+pub fn install(window: &AppWindow, state: &AppState) {
+    let window_weak = window.as_weak();
+    let thumbnail_cache = state.thumbnail_cache.clone();
+    let library_loader = state.library_loader.clone();
+    let settings = state.settings.clone();
+
+    window.on_request_thumbnails(move |start, end| {
+        let Some(window) = window_weak.upgrade() else {
+            return;
+        };
+        let start = start as usize;
+        let end = end as usize;
+
+        tracing::debug!("请求缩略图: {} - {}", start, end);
+        settings.set_thumbnail_pixels(window.get_thumb_pixels().max(1) as u32);
+
+        // 筛选时请求的是网格中的格子，换算成对应的帧索引
+        let indices: Vec<usize> = if window.get_thumbnail_filtering() {
+            let filter = window.get_thumbnail_filter();
+            (start..=end)
+                .filter_map(|slot| filter.row_data(slot))
+                .map(|index| index as usize)
+                .collect()
+        } else {
+            (start..=end).collect()
+        };
+
+        // 使用缓存的加载器加载缩略图
+        if let Some(ref cache) = *thumbnail_cache.lock().unwrap() {
+            cache.request_with_loader(&indices, window_weak.clone(), library_loader.clone());
+        }
+    });
+}
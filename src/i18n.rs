@@ -0,0 +1,363 @@
+//! 界面与命令行文字的多语言支持
+//!
+//! gettext 风格：源代码中的文字以中文书写，同时作为查找译文的键；其他语言的译文放在
+//! `lang/<语言>/LC_MESSAGES/library_editor.po` 中。Slint 界面的 `@tr(...)` 在编译时
+//! 打包同一份目录，Rust 代码用 [`tr!`] 在运行时查找，两边共用一套译文。找不到译文的
+//! 文字按中文原样显示，库返回的错误信息不翻译。
+//!
+//! 语言依次取命令行的 `--lang`、偏好设置中的 `language`、系统语言；系统语言不是中文时
+//! 使用英文。
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::path::Path;
+use std::sync::LazyLock;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// 界面语言
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Language {
+    /// 简体中文（源文字）
+    #[default]
+    #[serde(rename = "zh")]
+    Chinese,
+    /// 英文
+    #[serde(rename = "en")]
+    English,
+}
+
+impl Language {
+    /// 全部语言
+    pub const ALL: [Language; 2] = [Language::Chinese, Language::English];
+
+    /// 语言代码（`--lang` 的取值）
+    pub fn code(self) -> &'static str {
+        match self {
+            Language::Chinese => "zh",
+            Language::English => "en",
+        }
+    }
+
+    /// 译文目录名（Slint 打包的翻译），中文为源文字，返回空字符串
+    pub fn catalog_name(self) -> &'static str {
+        match self {
+            Language::Chinese => "",
+            Language::English => "en",
+        }
+    }
+
+    /// 按语言代码或区域设置（如 `zh_CN.UTF-8`、`en-US`）识别语言
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim().to_ascii_lowercase();
+        if text.starts_with("zh") {
+            Some(Language::Chinese)
+        } else if text.starts_with("en") {
+            Some(Language::English)
+        } else {
+            None
+        }
+    }
+
+    /// 按系统语言选择：中文系统用中文，其他语言的系统用英文，读不到时用中文
+    pub fn detect() -> Self {
+        match sys_locale::get_locale() {
+            Some(locale) => Self::parse(&locale).unwrap_or(Language::English),
+            None => Language::Chinese,
+        }
+    }
+
+    fn from_index(index: u8) -> Self {
+        Self::ALL.get(index as usize).copied().unwrap_or_default()
+    }
+}
+
+/// 当前语言（[`Language::ALL`] 中的下标）
+static CURRENT: AtomicU8 = AtomicU8::new(0);
+
+/// 设置当前语言
+pub fn set_language(language: Language) {
+    let index = Language::ALL
+        .iter()
+        .position(|l| *l == language)
+        .unwrap_or(0);
+    CURRENT.store(index as u8, Ordering::Relaxed);
+}
+
+/// 当前语言
+pub fn language() -> Language {
+    Language::from_index(CURRENT.load(Ordering::Relaxed))
+}
+
+/// 设置文件中与语言相关的部分（其余字段由其他模块读取）
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Settings {
+    language: Option<Language>,
+}
+
+/// 从设置文件读取语言，未设置、文件不存在或损坏时返回 `None`
+pub fn load(path: &Path) -> Option<Language> {
+    std::fs::read(path)
+        .ok()
+        .and_then(|data| serde_json::from_slice::<Settings>(&data).ok())
+        .and_then(|settings| settings.language)
+}
+
+/// 按 `--lang`、设置文件、系统语言的顺序决定并设置当前语言
+///
+/// `--lang` 的值无法识别时返回错误文字（列出可用的语言代码）。
+pub fn init(flag: Option<&str>, settings: &Path) -> Result<Language, String> {
+    let language = match flag {
+        Some(text) => Language::parse(text).ok_or_else(|| {
+            let codes: Vec<&str> = Language::ALL.iter().map(|l| l.code()).collect();
+            format!("不支持的语言: {}（可用: {}）", text, codes.join("、"))
+        })?,
+        None => load(settings).unwrap_or_else(Language::detect),
+    };
+    set_language(language);
+    Ok(language)
+}
+
+/// 英文译文（原文 -> 译文）
+static ENGLISH: LazyLock<HashMap<String, String>> =
+    LazyLock::new(|| parse_po(include_str!("../lang/en/LC_MESSAGES/library_editor.po")));
+
+/// 当前语言的译文，没有译文时返回原文
+pub fn translate(msgid: &str) -> &str {
+    let catalog = match language() {
+        Language::Chinese => return msgid,
+        Language::English => &*ENGLISH,
+    };
+    catalog
+        .get(msgid)
+        .map(String::as_str)
+        .filter(|text| !text.is_empty())
+        .unwrap_or(msgid)
+}
+
+/// 把模板中的 `{}` 依次替换为参数，`{{`、`}}` 为花括号本身
+pub fn format(template: &str, args: &[&dyn Display]) -> String {
+    let mut text = String::with_capacity(template.len() + args.len() * 8);
+    let mut args = args.iter();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('{', Some('{')) | ('}', Some('}')) => {
+                text.push(c);
+                chars.next();
+            }
+            ('{', Some('}')) => {
+                chars.next();
+                if let Some(arg) = args.next() {
+                    text.push_str(&arg.to_string());
+                }
+            }
+            _ => text.push(c),
+        }
+    }
+    text
+}
+
+/// 取当前语言的译文；带参数时按顺序填入模板中的 `{}`，得到 `String`
+macro_rules! tr {
+    ($msgid:literal) => {
+        $crate::i18n::translate($msgid)
+    };
+    ($msgid:literal, $($arg:expr),+ $(,)?) => {
+        $crate::i18n::format(
+            $crate::i18n::translate($msgid),
+            &[$(&$arg as &dyn std::fmt::Display),+],
+        )
+    };
+}
+pub(crate) use tr;
+
+/// 解析 .po 文件中的 `msgid`/`msgstr` 对
+///
+/// 只处理本项目用到的部分：多行字符串、常见转义和注释；带 `msgctxt` 或复数形式的条目、
+/// 标为 fuzzy 的条目和文件头（空 `msgid`）跳过。
+fn parse_po(text: &str) -> HashMap<String, String> {
+    /// 读取中的条目，读到 `msgstr` 后完整，下一个条目开始时写入目录
+    #[derive(Default)]
+    struct Entry {
+        id: String,
+        translated: String,
+        fuzzy: bool,
+        skip: bool,
+        complete: bool,
+    }
+    enum Field {
+        Id,
+        Str,
+        Other,
+    }
+
+    let mut catalog = HashMap::new();
+    let mut finish = |entry: &mut Entry| {
+        let entry = std::mem::take(entry);
+        if !entry.id.is_empty() && !entry.fuzzy && !entry.skip {
+            catalog.insert(entry.id, entry.translated);
+        }
+    };
+    let mut entry = Entry::default();
+    let mut field = Field::Other;
+    for line in text.lines().map(str::trim) {
+        let (keyword, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let starts_entry = matches!(keyword, "#," | "msgctxt" | "msgid");
+        if starts_entry && entry.complete {
+            finish(&mut entry);
+        }
+        match keyword {
+            "#," => entry.fuzzy |= rest.contains("fuzzy"),
+            "msgctxt" | "msgid_plural" => {
+                entry.skip = true;
+                field = Field::Other;
+            }
+            "msgid" => {
+                entry.id = unquote(rest);
+                field = Field::Id;
+            }
+            "msgstr" => {
+                entry.translated = unquote(rest);
+                entry.complete = true;
+                field = Field::Str;
+            }
+            _ if keyword.starts_with("msgstr[") => {
+                entry.complete = true;
+                field = Field::Other;
+            }
+            _ if line.starts_with('"') => match field {
+                Field::Id => entry.id.push_str(&unquote(line)),
+                Field::Str => entry.translated.push_str(&unquote(line)),
+                Field::Other => {}
+            },
+            _ => {}
+        }
+    }
+    finish(&mut entry);
+    catalog
+}
+
+/// 去掉引号并处理转义
+fn unquote(text: &str) -> String {
+    let text = text.trim();
+    let text = text
+        .strip_prefix('"')
+        .and_then(|t| t.strip_suffix('"'))
+        .unwrap_or(text);
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => result.push('\n'),
+            Some('t') => result.push('\t'),
+            Some(other) => result.push(other),
+            None => {}
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_catalog_and_format() {
+        let catalog = parse_po(concat!(
+            "msgid \"\"\n",
+            "msgstr \"Content-Type: text/plain; charset=UTF-8\\n\"\n",
+            "\n",
+            "msgid \"就绪\"\n",
+            "msgstr \"Ready\"\n",
+            "\n",
+            "#, fuzzy\n",
+            "msgid \"设置\"\n",
+            "msgstr \"Setup\"\n",
+            "\n",
+            "msgid \"\"\n",
+            "\"已打开 {} \"\n",
+            "\"（{} 帧）\"\n",
+            "msgstr \"Opened {} \"\n",
+            "\"({} \\\"frames\\\")\"\n",
+        ));
+        assert_eq!(catalog.len(), 2);
+        assert_eq!(catalog["就绪"], "Ready");
+        assert_eq!(catalog["已打开 {} （{} 帧）"], "Opened {} ({} \"frames\")");
+        assert_eq!(
+            format(&catalog["已打开 {} （{} 帧）"], &[&"Hum.Lib", &12]),
+            "Opened Hum.Lib (12 \"frames\")"
+        );
+        assert_eq!(format("{{{}}}", &[&1]), "{1}");
+
+        assert_eq!(Language::parse("zh_CN.UTF-8"), Some(Language::Chinese));
+        assert_eq!(Language::parse("en-US"), Some(Language::English));
+        assert_eq!(Language::parse("de"), None);
+
+        // 打包的英文译文中每条都有译文，且参数个数与原文一致
+        for (id, text) in ENGLISH.iter() {
+            assert!(!text.is_empty(), "缺少译文: {}", id);
+            assert_eq!(
+                id.matches("{}").count(),
+                text.matches("{}").count(),
+                "{}",
+                id
+            );
+        }
+
+        // 命令行的输出、帮助和进度条中的原文都有英文译文
+        for source in [
+            include_str!("commands/mod.rs"),
+            include_str!("commands/check.rs"),
+            include_str!("commands/convert.rs"),
+            include_str!("commands/edit.rs"),
+            include_str!("commands/export.rs"),
+            include_str!("commands/import.rs"),
+            include_str!("commands/map.rs"),
+            include_str!("commands/project.rs"),
+            include_str!("commands/search.rs"),
+            include_str!("commands/stats.rs"),
+            include_str!("commands/tools.rs"),
+            include_str!("progress_bar.rs"),
+        ] {
+            for msgid in source_msgids(source) {
+                assert!(ENGLISH.contains_key(&msgid), "缺少译文: {}", msgid);
+            }
+        }
+    }
+
+    /// 源文件中 `tr!` 的原文和 `print_help` 中的帮助行
+    fn source_msgids(source: &str) -> Vec<String> {
+        // 读取从 `text` 开头的字符串字面量（只处理 `\"` 和 `\\` 转义）
+        let literal = |text: &str| {
+            let mut chars = text.strip_prefix('"')?.chars();
+            let mut value = String::new();
+            while let Some(c) = chars.next() {
+                match c {
+                    '"' => return Some(value),
+                    '\\' => value.extend(chars.next()),
+                    _ => value.push(c),
+                }
+            }
+            None
+        };
+        let mut msgids: Vec<String> = source
+            .split("tr!(")
+            .skip(1)
+            .filter_map(|rest| literal(rest.trim_start()))
+            .collect();
+        if let Some((_, help)) = source.split_once("print_help(&[")
+            && let Some((help, _)) = help.split_once("]);")
+        {
+            msgids.extend(help.lines().filter_map(|line| literal(line.trim())));
+        }
+        // 纯 ASCII 的行（选项列表、扩展名）不需要翻译
+        msgids.retain(|msgid| !msgid.is_ascii());
+        msgids
+    }
+}
@@ -0,0 +1,216 @@
+//! 基线对齐
+//!
+//! 行走、跑步等动作的各帧由美术分别出图，脚底（非透明内容的下边缘）常常上下错开
+//! 几个像素，播放时人物上下抖动，以往只能逐帧手工调偏移。这里按帧偏移加上内容
+//! 包围盒算出每帧脚底在绘制坐标中的位置，以范围内的中位数为基准给出偏移修正，
+//! 可选同时按内容的水平中心对齐；应用前可以先生成对齐前后的对照图。
+
+use crate::image::video::PlacedFrame;
+use image::{Rgba, RgbaImage};
+
+/// 对照图中帧之间的间隔（像素）
+const GAP: u32 = 4;
+/// 基线颜色
+const BASELINE_COLOR: Rgba<u8> = Rgba([255, 0, 0, 255]);
+/// 水平中心线颜色
+const CENTER_COLOR: Rgba<u8> = Rgba([0, 160, 255, 255]);
+/// 分隔线颜色
+const SEPARATOR_COLOR: Rgba<u8> = Rgba([128, 128, 128, 160]);
+
+/// 非透明像素的包围盒 `(left, top, right, bottom)`，右、下边界不含；全透明时返回 `None`
+pub fn content_bounds(image: &RgbaImage) -> Option<(u32, u32, u32, u32)> {
+    let mut bounds: Option<(u32, u32, u32, u32)> = None;
+    for (x, y, pixel) in image.enumerate_pixels() {
+        if pixel[3] == 0 {
+            continue;
+        }
+        bounds = Some(match bounds {
+            None => (x, y, x + 1, y + 1),
+            Some((l, t, r, b)) => (l.min(x), t.min(y), r.max(x + 1), b.max(y + 1)),
+        });
+    }
+    bounds
+}
+
+/// 一帧的偏移修正
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Alignment {
+    /// 帧索引
+    pub index: usize,
+    /// 水平修正
+    pub dx: i16,
+    /// 垂直修正
+    pub dy: i16,
+}
+
+/// 对齐方案
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AlignPlan {
+    /// 基准基线（内容下边缘在绘制坐标中的 y）
+    pub baseline: i32,
+    /// 基准水平中心（绘制坐标中 x 的两倍，避免半像素）；不做水平对齐时为 `None`
+    pub center: Option<i32>,
+    /// 需要修正的帧（已对齐的帧不列出）
+    pub frames: Vec<Alignment>,
+}
+
+impl AlignPlan {
+    /// 某帧的修正量，不需要修正时为 (0, 0)
+    pub fn shift(&self, index: usize) -> (i32, i32) {
+        self.frames
+            .iter()
+            .find(|a| a.index == index)
+            .map_or((0, 0), |a| (a.dx as i32, a.dy as i32))
+    }
+}
+
+/// 中位数（偶数个时取较大的一个）
+fn median(mut values: Vec<i32>) -> i32 {
+    values.sort_unstable();
+    values[values.len() / 2]
+}
+
+/// 计算对齐方案：`frames` 为帧索引及其图像和偏移，空帧和全透明的帧不参与
+///
+/// 没有可对齐的帧时返回 `None`。
+pub fn plan(frames: &[(usize, Option<PlacedFrame>)], horizontal: bool) -> Option<AlignPlan> {
+    // (索引, 基线, 水平中心的两倍)
+    let placed: Vec<(usize, i32, i32)> = frames
+        .iter()
+        .filter_map(|(index, frame)| {
+            let (image, (x, y)) = frame.as_ref()?;
+            let (left, _, right, bottom) = content_bounds(image)?;
+            Some((*index, y + bottom as i32, 2 * x + (left + right) as i32))
+        })
+        .collect();
+    if placed.is_empty() {
+        return None;
+    }
+
+    let baseline = median(placed.iter().map(|p| p.1).collect());
+    let center = horizontal.then(|| median(placed.iter().map(|p| p.2).collect()));
+    let clamp = |v: i32| v.clamp(i16::MIN as i32, i16::MAX as i32) as i16;
+    let frames = placed
+        .iter()
+        .map(|&(index, bottom, mid)| Alignment {
+            index,
+            dx: clamp(center.map_or(0, |center| (center - mid) / 2)),
+            dy: clamp(baseline - bottom),
+        })
+        .filter(|a| (a.dx, a.dy) != (0, 0))
+        .collect();
+    Some(AlignPlan {
+        baseline,
+        center,
+        frames,
+    })
+}
+
+/// 生成对照图：上排为当前偏移，下排为应用方案后的偏移，红线为基准基线，
+/// 蓝线为基准水平中心
+///
+/// 各帧按相同的绘制坐标范围并排摆放，全部为空帧时返回 `None`。
+pub fn preview(frames: &[(usize, Option<PlacedFrame>)], plan: &AlignPlan) -> Option<RgbaImage> {
+    // 两排共用的绘制坐标范围
+    let mut bounds: Option<(i32, i32, i32, i32)> = None;
+    for (index, frame) in frames {
+        let Some((image, (x, y))) = frame else {
+            continue;
+        };
+        let (dx, dy) = plan.shift(*index);
+        let (w, h) = (image.width() as i32, image.height() as i32);
+        for (x, y) in [(*x, *y), (x + dx, y + dy)] {
+            bounds = Some(match bounds {
+                None => (x, y, x + w, y + h),
+                Some((l, t, r, b)) => (l.min(x), t.min(y), r.max(x + w), b.max(y + h)),
+            });
+        }
+    }
+    let (left, top, right, bottom) = bounds?;
+    let (cell_w, cell_h) = ((right - left) as u32 + GAP, (bottom - top) as u32 + GAP);
+    let count = frames.len() as u32;
+    let mut canvas = RgbaImage::new(cell_w * count, cell_h * 2);
+
+    for (col, (index, frame)) in frames.iter().enumerate() {
+        let Some((image, (x, y))) = frame else {
+            continue;
+        };
+        let (dx, dy) = plan.shift(*index);
+        for (row, (x, y)) in [(*x, *y), (x + dx, y + dy)].into_iter().enumerate() {
+            image::imageops::overlay(
+                &mut canvas,
+                &**image,
+                (col as u32 * cell_w) as i64 + (x - left) as i64,
+                (row as u32 * cell_h) as i64 + (y - top) as i64,
+            );
+        }
+    }
+
+    let (width, height) = canvas.dimensions();
+    for y in 0..height {
+        for col in 1..count {
+            canvas.put_pixel(col * cell_w - GAP / 2, y, SEPARATOR_COLOR);
+        }
+    }
+    for x in 0..width {
+        canvas.put_pixel(x, cell_h - GAP / 2, SEPARATOR_COLOR);
+        for row in 0..2 {
+            let y = row * cell_h + (plan.baseline - top) as u32;
+            if y < height {
+                canvas.put_pixel(x, y, BASELINE_COLOR);
+            }
+        }
+    }
+    if let Some(center) = plan.center {
+        let x = (center / 2 - left).max(0) as u32;
+        for col in 0..count {
+            for y in 0..height {
+                canvas.put_pixel((col * cell_w + x).min(width - 1), y, CENTER_COLOR);
+            }
+        }
+    }
+    Some(canvas)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_baseline_plan() {
+        // 4x6 的画布中 2x3 的内容，下边缘在第 5 行
+        let mut sprite = RgbaImage::new(4, 6);
+        for (x, y) in [(1, 2), (2, 4)] {
+            sprite.put_pixel(x, y, Rgba([255, 255, 255, 255]));
+        }
+        assert_eq!(content_bounds(&sprite), Some((1, 2, 3, 5)));
+        assert_eq!(content_bounds(&RgbaImage::new(2, 2)), None);
+
+        let sprite = Arc::new(sprite);
+        let frames = vec![
+            (10, Some((sprite.clone(), (0, -20)))),
+            (11, Some((sprite.clone(), (3, -22)))),
+            (12, None),
+            (13, Some((sprite.clone(), (0, -20)))),
+        ];
+        let plan = plan(&frames, false).unwrap();
+        assert_eq!(plan.baseline, -15);
+        assert_eq!(
+            plan.frames,
+            vec![Alignment {
+                index: 11,
+                dx: 0,
+                dy: 2
+            }]
+        );
+
+        let horizontal = super::plan(&frames, true).unwrap();
+        assert_eq!(horizontal.shift(11), (-3, 2));
+        assert_eq!(horizontal.shift(10), (0, 0));
+
+        let image = preview(&frames, &plan).unwrap();
+        assert_eq!(image.width(), 4 * (7 + GAP));
+        assert!(super::plan(&[(0, None)], false).is_none());
+    }
+}
@@ -1,6 +1,7 @@
 //! 位图和图像数据结构
 //!
-//! 注意：MImage 已移至 src/formats/mlibrary_v1.rs
+//! 注意：帧结构已移至 formats 模块，公共字段见 `formats::frame::FrameData`，
+//! WZL / .Lib 的帧分别为 `formats::mlibrary_v1::MImage` 与 `formats::mlibrary_v2::MImage`。
 //! 此模块保留 Bitmap 类型别名
 
 use image::RgbaImage;
@@ -0,0 +1,138 @@
+//! 画布扩展
+//!
+//! 把帧填充到统一的画布尺寸（如 64x64 的地面放置块），帧在画布中的位置由锚点决定，
+//! 调整偏移后游戏内的绘制位置不变。供要求帧尺寸一致的引擎准备素材。
+
+use image::RgbaImage;
+
+/// 帧在画布中的对齐位置
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Anchor {
+    /// 左上
+    TopLeft,
+    /// 顶部居中
+    Top,
+    /// 右上
+    TopRight,
+    /// 左侧居中
+    Left,
+    /// 居中
+    Center,
+    /// 右侧居中
+    Right,
+    /// 左下
+    BottomLeft,
+    /// 底部居中（角色和怪物的脚底对齐）
+    #[default]
+    Bottom,
+    /// 右下
+    BottomRight,
+}
+
+impl Anchor {
+    /// 全部锚点（按 3x3 网格自左上到右下的顺序）
+    pub const ALL: [Anchor; 9] = [
+        Anchor::TopLeft,
+        Anchor::Top,
+        Anchor::TopRight,
+        Anchor::Left,
+        Anchor::Center,
+        Anchor::Right,
+        Anchor::BottomLeft,
+        Anchor::Bottom,
+        Anchor::BottomRight,
+    ];
+
+    /// 命令行中使用的名称
+    pub fn name(self) -> &'static str {
+        match self {
+            Anchor::TopLeft => "top-left",
+            Anchor::Top => "top",
+            Anchor::TopRight => "top-right",
+            Anchor::Left => "left",
+            Anchor::Center => "center",
+            Anchor::Right => "right",
+            Anchor::BottomLeft => "bottom-left",
+            Anchor::Bottom => "bottom",
+            Anchor::BottomRight => "bottom-right",
+        }
+    }
+
+    /// 按名称解析（不区分大小写）
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim().to_lowercase();
+        Self::ALL.into_iter().find(|anchor| anchor.name() == text)
+    }
+
+    /// 在 3x3 网格中的序号（界面中的选择顺序）
+    pub fn to_index(self) -> usize {
+        Self::ALL.iter().position(|&a| a == self).unwrap_or(0)
+    }
+
+    /// 从序号还原，越界时回退为默认锚点
+    pub fn from_index(index: usize) -> Self {
+        Self::ALL.get(index).copied().unwrap_or_default()
+    }
+
+    /// 尺寸为 `size` 的帧放进 `canvas` 画布时左上角的位置
+    pub fn place(self, size: (u32, u32), canvas: (u32, u32)) -> (i32, i32) {
+        let index = self.to_index();
+        let along = |col: usize, size: u32, canvas: u32| {
+            let room = canvas as i32 - size as i32;
+            match col {
+                0 => 0,
+                1 => room / 2,
+                _ => room,
+            }
+        };
+        (
+            along(index % 3, size.0, canvas.0),
+            along(index / 3, size.1, canvas.1),
+        )
+    }
+}
+
+/// 把图像按锚点放到 `width` x `height` 的透明画布上，返回画布和图像在其中的位置
+///
+/// 图像比画布大时返回 `None`（不裁剪，避免丢失像素）。
+pub fn pad(
+    src: &RgbaImage,
+    width: u32,
+    height: u32,
+    anchor: Anchor,
+) -> Option<(RgbaImage, (i32, i32))> {
+    if src.width() > width || src.height() > height {
+        return None;
+    }
+    let (x, y) = anchor.place(src.dimensions(), (width, height));
+    let mut canvas = RgbaImage::new(width, height);
+    image::imageops::overlay(&mut canvas, src, x as i64, y as i64);
+    Some((canvas, (x, y)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pad_with_anchor() {
+        let src = RgbaImage::from_pixel(2, 3, image::Rgba([255, 0, 0, 255]));
+
+        let (canvas, pos) = pad(&src, 6, 5, Anchor::Bottom).unwrap();
+        assert_eq!(canvas.dimensions(), (6, 5));
+        assert_eq!(pos, (2, 2));
+        assert_eq!(canvas.get_pixel(2, 4).0, [255, 0, 0, 255]);
+        assert_eq!(canvas.get_pixel(2, 1).0, [0, 0, 0, 0]);
+
+        assert_eq!(Anchor::TopLeft.place((2, 3), (6, 5)), (0, 0));
+        assert_eq!(Anchor::Center.place((2, 3), (7, 5)), (2, 1));
+        assert_eq!(Anchor::BottomRight.place((2, 3), (6, 5)), (4, 2));
+
+        // 比画布大时不处理
+        assert!(pad(&src, 1, 8, Anchor::Center).is_none());
+
+        assert_eq!(Anchor::parse("Bottom-Left"), Some(Anchor::BottomLeft));
+        assert_eq!(Anchor::parse("middle"), None);
+        assert_eq!(Anchor::from_index(Anchor::Right.to_index()), Anchor::Right);
+    }
+}
@@ -0,0 +1,155 @@
+//! 导入时的透明色键
+//!
+//! 导入 PNG / BMP 时决定哪些像素透明：沿用图像自带的 Alpha 通道，或把纯黑、
+//! 品红、自定义颜色（带容差）当作透明色。以前编码时一律把纯黑像素设为透明，
+//! 会毁掉画面中本来就是黑色的像素，现在由导入方选择，编码只按 Alpha 写入。
+
+use super::parse_hex_color;
+use image::RgbaImage;
+use serde::{Deserialize, Serialize};
+
+/// 透明色的判定方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ChromaKey {
+    /// 沿用图像自带的 Alpha 通道
+    Alpha,
+    /// 纯黑像素透明（旧版固定行为）
+    #[default]
+    Black,
+    /// 品红（#FF00FF）像素透明
+    Magenta,
+    /// 与指定颜色各通道相差不超过容差的像素透明
+    Color {
+        /// 透明色
+        rgb: [u8; 3],
+        /// 各通道允许的差值
+        tolerance: u8,
+    },
+}
+
+impl ChromaKey {
+    /// 界面中的选项名（按序号排列，自定义颜色在最后）
+    pub const NAMES: [&str; 4] = ["保留 Alpha", "黑色透明", "品红透明", "自定义颜色"];
+
+    /// 命令行中的写法（可由 [`Self::parse`] 还原）
+    pub fn name(self) -> String {
+        match self {
+            ChromaKey::Alpha => "alpha".to_string(),
+            ChromaKey::Black => "black".to_string(),
+            ChromaKey::Magenta => "magenta".to_string(),
+            ChromaKey::Color {
+                rgb: [r, g, b],
+                tolerance,
+            } => format!("#{:02x}{:02x}{:02x}:{}", r, g, b, tolerance),
+        }
+    }
+
+    /// 解析命令行参数：`alpha`、`black`、`magenta` 或 `#RRGGBB[:容差]`
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim().to_lowercase();
+        match text.as_str() {
+            "alpha" => Some(ChromaKey::Alpha),
+            "black" => Some(ChromaKey::Black),
+            "magenta" => Some(ChromaKey::Magenta),
+            _ => {
+                let (color, tolerance) = match text.split_once(':') {
+                    Some((color, tolerance)) => (color, tolerance.parse().ok()?),
+                    None => (text.as_str(), 0),
+                };
+                Some(ChromaKey::Color {
+                    rgb: parse_hex_color(color)?,
+                    tolerance,
+                })
+            }
+        }
+    }
+
+    /// 在 [`Self::NAMES`] 中的序号
+    pub fn to_index(self) -> usize {
+        match self {
+            ChromaKey::Alpha => 0,
+            ChromaKey::Black => 1,
+            ChromaKey::Magenta => 2,
+            ChromaKey::Color { .. } => 3,
+        }
+    }
+
+    /// 从序号还原，自定义颜色使用 `rgb` 和 `tolerance`
+    pub fn from_index(index: usize, rgb: [u8; 3], tolerance: u8) -> Self {
+        match index {
+            0 => ChromaKey::Alpha,
+            2 => ChromaKey::Magenta,
+            3 => ChromaKey::Color { rgb, tolerance },
+            _ => ChromaKey::Black,
+        }
+    }
+
+    /// 像素是否为透明色
+    pub fn matches(self, [r, g, b, _]: [u8; 4]) -> bool {
+        let near = |key: [u8; 3], tolerance: u8| {
+            [r, g, b]
+                .iter()
+                .zip(key)
+                .all(|(&c, k)| c.abs_diff(k) <= tolerance)
+        };
+        match self {
+            ChromaKey::Alpha => false,
+            ChromaKey::Black => near([0, 0, 0], 0),
+            ChromaKey::Magenta => near([255, 0, 255], 0),
+            ChromaKey::Color { rgb, tolerance } => near(rgb, tolerance),
+        }
+    }
+
+    /// 把透明色像素设为全透明，返回处理的像素数
+    pub fn apply(self, image: &mut RgbaImage) -> usize {
+        let mut keyed = 0;
+        for pixel in image.pixels_mut() {
+            if pixel[3] != 0 && self.matches(pixel.0) {
+                *pixel = image::Rgba([0, 0, 0, 0]);
+                keyed += 1;
+            }
+        }
+        keyed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keys() {
+        let mut image = RgbaImage::from_fn(4, 1, |x, _| {
+            image::Rgba(match x {
+                0 => [0, 0, 0, 255],
+                1 => [255, 0, 255, 255],
+                2 => [250, 6, 250, 255],
+                _ => [0, 0, 0, 128],
+            })
+        });
+
+        // 保留 Alpha 时黑色像素不受影响
+        assert_eq!(ChromaKey::Alpha.apply(&mut image.clone()), 0);
+
+        let mut black = image.clone();
+        assert_eq!(ChromaKey::Black.apply(&mut black), 2);
+        assert_eq!(black.get_pixel(1, 0).0, [255, 0, 255, 255]);
+
+        let key = ChromaKey::parse("#FF00FF:8").unwrap();
+        assert_eq!(
+            key,
+            ChromaKey::Color {
+                rgb: [255, 0, 255],
+                tolerance: 8
+            }
+        );
+        assert_eq!(key.apply(&mut image), 2);
+        assert_eq!(image.get_pixel(0, 0).0, [0, 0, 0, 255]);
+        assert_eq!(image.get_pixel(2, 0).0, [0, 0, 0, 0]);
+
+        assert_eq!(ChromaKey::parse("magenta"), Some(ChromaKey::Magenta));
+        assert_eq!(ChromaKey::parse("#12"), None);
+        assert_eq!(ChromaKey::parse(&key.name()), Some(key));
+        assert_eq!(ChromaKey::from_index(key.to_index(), [255, 0, 255], 8), key);
+    }
+}
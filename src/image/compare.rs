@@ -0,0 +1,264 @@
+//! 双库对比
+//!
+//! 对比原版客户端库与修改后的库时，两边同一索引的帧按各自的绘制偏移对齐到同一画布，
+//! 再叠加显示（洋葱皮）或标出不同的像素（差异图）。编辑动画时的洋葱皮
+//! （[`onion_skin`]）用同样的方式把前后帧半透明地垫在当前帧下方。
+
+use image::{Rgba, RgbaImage};
+
+/// 洋葱皮模式下对比帧的不透明度
+const ONION_ALPHA: u32 = 128;
+
+/// 差异图中相同像素的亮度比例（压暗后作为背景）
+const SAME_DIM: u32 = 4;
+
+/// 差异像素的颜色
+const DIFF_COLOR: [u8; 3] = [255, 0, 255];
+
+/// 对比视图模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CompareMode {
+    /// 并排显示对比帧
+    #[default]
+    SideBySide,
+    /// 对比帧半透明叠加在主帧上
+    Onion,
+    /// 标出两帧不同的像素
+    Difference,
+}
+
+impl CompareMode {
+    /// 全部模式（按界面中的顺序）
+    pub const ALL: [CompareMode; 3] = [
+        CompareMode::SideBySide,
+        CompareMode::Onion,
+        CompareMode::Difference,
+    ];
+
+    /// 从界面序号还原，越界时回退为默认模式
+    pub fn from_index(index: i32) -> Self {
+        usize::try_from(index)
+            .ok()
+            .and_then(|i| Self::ALL.get(i).copied())
+            .unwrap_or_default()
+    }
+}
+
+/// 带绘制偏移的帧
+#[derive(Debug, Clone, Copy)]
+pub struct Placed<'a> {
+    /// 帧图像
+    pub image: &'a RgbaImage,
+    /// 绘制偏移
+    pub offset: (i32, i32),
+}
+
+impl<'a> Placed<'a> {
+    /// 创建带偏移的帧
+    pub fn new(image: &'a RgbaImage, offset: (i32, i32)) -> Self {
+        Self { image, offset }
+    }
+
+    /// 画布坐标处的像素（超出帧范围时为透明）
+    fn pixel(&self, x: i32, y: i32) -> Rgba<u8> {
+        let (px, py) = (x - self.offset.0, y - self.offset.1);
+        if px < 0 || py < 0 || px >= self.image.width() as i32 || py >= self.image.height() as i32 {
+            return Rgba([0, 0, 0, 0]);
+        }
+        *self.image.get_pixel(px as u32, py as u32)
+    }
+}
+
+/// 同一索引两帧的对比结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameDiff {
+    /// 两边都是空帧
+    BothEmpty,
+    /// 只有主库有图像
+    OnlyPrimary,
+    /// 只有对比库有图像
+    OnlyCompare,
+    /// 对齐后像素完全相同
+    Identical,
+    /// 对齐后有像素不同
+    Changed {
+        /// 不同的像素数
+        pixels: usize,
+    },
+}
+
+/// 对齐后的画布范围：(左, 上, 宽, 高)
+fn union(a: &Placed, b: &Placed) -> (i32, i32, u32, u32) {
+    let left = a.offset.0.min(b.offset.0);
+    let top = a.offset.1.min(b.offset.1);
+    let right = (a.offset.0 + a.image.width() as i32).max(b.offset.0 + b.image.width() as i32);
+    let bottom = (a.offset.1 + a.image.height() as i32).max(b.offset.1 + b.image.height() as i32);
+    (left, top, (right - left) as u32, (bottom - top) as u32)
+}
+
+/// 两个像素是否视为相同（都透明时忽略颜色）
+fn same(a: Rgba<u8>, b: Rgba<u8>) -> bool {
+    (a[3] == 0 && b[3] == 0) || a == b
+}
+
+/// 比较两帧，`None` 表示空帧或超出该库的帧数
+pub fn diff(primary: Option<Placed>, compare: Option<Placed>) -> FrameDiff {
+    let (a, b) = match (primary, compare) {
+        (None, None) => return FrameDiff::BothEmpty,
+        (Some(_), None) => return FrameDiff::OnlyPrimary,
+        (None, Some(_)) => return FrameDiff::OnlyCompare,
+        (Some(a), Some(b)) => (a, b),
+    };
+
+    let (left, top, width, height) = union(&a, &b);
+    let pixels = (0..height as i32)
+        .flat_map(|y| (0..width as i32).map(move |x| (left + x, top + y)))
+        .filter(|&(x, y)| !same(a.pixel(x, y), b.pixel(x, y)))
+        .count();
+    if pixels == 0 {
+        FrameDiff::Identical
+    } else {
+        FrameDiff::Changed { pixels }
+    }
+}
+
+/// 洋葱皮：对比帧以半透明叠加在主帧上
+pub fn onion(primary: Placed, compare: Placed) -> RgbaImage {
+    let (left, top, width, height) = union(&primary, &compare);
+    RgbaImage::from_fn(width, height, |x, y| {
+        let (cx, cy) = (left + x as i32, top + y as i32);
+        let dst = primary.pixel(cx, cy);
+        let src = compare.pixel(cx, cy);
+        let a = src[3] as u32 * ONION_ALPHA / 255;
+        if a == 0 {
+            return dst;
+        }
+        let mix = |d: u8, s: u8| ((s as u32 * a + d as u32 * (255 - a)) / 255) as u8;
+        let out_a = a + dst[3] as u32 * (255 - a) / 255;
+        Rgba([
+            mix(dst[0], src[0]),
+            mix(dst[1], src[1]),
+            mix(dst[2], src[2]),
+            out_a as u8,
+        ])
+    })
+}
+
+/// 动画洋葱皮：前后帧按 `opacity`（0-255）半透明地垫在当前帧下方，均按绘制偏移对齐
+///
+/// 返回的画布覆盖全部帧，当前帧位于 `(current.offset - 画布左上角)` 处。
+pub fn onion_skin(current: Placed, ghosts: &[Placed], opacity: u8) -> RgbaImage {
+    let (mut left, mut top, mut right, mut bottom) = (i32::MAX, i32::MAX, i32::MIN, i32::MIN);
+    for placed in std::iter::once(&current).chain(ghosts) {
+        let (x, y) = placed.offset;
+        left = left.min(x);
+        top = top.min(y);
+        right = right.max(x + placed.image.width() as i32);
+        bottom = bottom.max(y + placed.image.height() as i32);
+    }
+
+    let mut canvas = RgbaImage::new((right - left) as u32, (bottom - top) as u32);
+    let position = |placed: &Placed| {
+        (
+            (placed.offset.0 - left) as i64,
+            (placed.offset.1 - top) as i64,
+        )
+    };
+    for ghost in ghosts {
+        let mut faded = ghost.image.clone();
+        for pixel in faded.pixels_mut() {
+            pixel[3] = (pixel[3] as u32 * opacity as u32 / 255) as u8;
+        }
+        let (x, y) = position(ghost);
+        image::imageops::overlay(&mut canvas, &faded, x, y);
+    }
+    let (x, y) = position(&current);
+    image::imageops::overlay(&mut canvas, current.image, x, y);
+    canvas
+}
+
+/// 差异图：相同的像素压暗显示，不同的像素以品红标出
+pub fn difference(primary: Placed, compare: Placed) -> RgbaImage {
+    let (left, top, width, height) = union(&primary, &compare);
+    RgbaImage::from_fn(width, height, |x, y| {
+        let (cx, cy) = (left + x as i32, top + y as i32);
+        let a = primary.pixel(cx, cy);
+        let b = compare.pixel(cx, cy);
+        if same(a, b) {
+            let dim = |c: u8| (c as u32 / SAME_DIM) as u8;
+            return Rgba([dim(a[0]), dim(a[1]), dim(a[2]), a[3]]);
+        }
+        let [r, g, b] = DIFF_COLOR;
+        Rgba([r, g, b, 255])
+    })
+}
+
+/// 按模式生成对比视图中显示的图像
+///
+/// 并排模式只显示对比帧；叠加类模式缺少一边时退化为显示存在的一边。
+pub fn render(
+    mode: CompareMode,
+    primary: Option<Placed>,
+    compare: Option<Placed>,
+) -> Option<RgbaImage> {
+    match (mode, primary, compare) {
+        (CompareMode::SideBySide, _, compare) => compare.map(|c| c.image.clone()),
+        (CompareMode::Onion, Some(a), Some(b)) => Some(onion(a, b)),
+        (CompareMode::Difference, Some(a), Some(b)) => Some(difference(a, b)),
+        (_, a, b) => b.or(a).map(|p| p.image.clone()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_aligns_offsets() {
+        let red = Rgba([255, 0, 0, 255]);
+        let a = RgbaImage::from_pixel(4, 4, red);
+        // 同样的帧换一种裁剪方式：多一列透明边，偏移随之左移
+        let mut b = RgbaImage::new(5, 4);
+        image::imageops::overlay(&mut b, &a, 1, 0);
+
+        let primary = Placed::new(&a, (10, 20));
+        assert_eq!(
+            diff(Some(primary), Some(Placed::new(&b, (9, 20)))),
+            FrameDiff::Identical
+        );
+
+        // 偏移不一致时整帧错位
+        let shifted = Placed::new(&a, (11, 20));
+        assert_eq!(
+            diff(Some(primary), Some(shifted)),
+            FrameDiff::Changed { pixels: 8 }
+        );
+        let out = difference(primary, shifted);
+        assert_eq!(out.dimensions(), (5, 4));
+        assert_eq!(out.get_pixel(0, 0).0, [255, 0, 255, 255]);
+        assert_eq!(out.get_pixel(1, 0).0, [63, 0, 0, 255]);
+
+        let out = onion(primary, shifted);
+        assert_eq!(out.get_pixel(4, 0)[3], 128);
+        assert_eq!(out.get_pixel(1, 0).0, red.0);
+
+        assert_eq!(diff(Some(primary), None), FrameDiff::OnlyPrimary);
+        assert!(render(CompareMode::Difference, Some(primary), None).is_some());
+        assert_eq!(CompareMode::from_index(7), CompareMode::SideBySide);
+    }
+
+    #[test]
+    fn test_onion_skin_ghosts() {
+        let red = RgbaImage::from_pixel(2, 2, Rgba([255, 0, 0, 255]));
+        let blue = RgbaImage::from_pixel(2, 2, Rgba([0, 0, 255, 255]));
+        let current = Placed::new(&red, (0, 0));
+        let ghosts = [Placed::new(&blue, (-1, 0)), Placed::new(&blue, (1, 1))];
+
+        let out = onion_skin(current, &ghosts, 64);
+        assert_eq!(out.dimensions(), (4, 3));
+        // 只有前一帧覆盖的位置为半透明蓝色，当前帧盖在前后帧之上
+        assert_eq!(out.get_pixel(0, 0).0, [0, 0, 255, 64]);
+        assert_eq!(out.get_pixel(1, 1).0, [255, 0, 0, 255]);
+        assert_eq!(out.get_pixel(3, 0)[3], 0);
+    }
+}
@@ -6,9 +6,46 @@ use flate2::write::{GzEncoder, ZlibEncoder};
 use flate2::Compression;
 use std::io::{Read, Write};
 
+/// GZip/Zlib 压缩级别：0 为不压缩，9 压缩得最小；默认 6，与 flate2 的默认级别相同
+///
+/// 旧工具多用 9 级输出，默认级别写出的库明显更大。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CompressionLevel(u32);
+
+impl CompressionLevel {
+    /// 压缩得最小的级别
+    pub const BEST: Self = Self(9);
+
+    /// 按级别数值创建，超出 0-9 时返回 `None`
+    pub fn new(level: u32) -> Option<Self> {
+        (level <= Self::BEST.0).then_some(Self(level))
+    }
+
+    /// 级别数值
+    pub fn get(self) -> u32 {
+        self.0
+    }
+
+    /// 对应的 flate2 压缩级别
+    pub(crate) fn flate(self) -> Compression {
+        Compression::new(self.0)
+    }
+}
+
+impl Default for CompressionLevel {
+    fn default() -> Self {
+        Self(6)
+    }
+}
+
 /// 使用 GZIP 压缩数据
 pub fn compress_gzip(data: &[u8]) -> Result<Vec<u8>> {
-    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    compress_gzip_with(data, CompressionLevel::default())
+}
+
+/// 按指定级别使用 GZIP 压缩数据
+pub fn compress_gzip_with(data: &[u8], level: CompressionLevel) -> Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), level.flate());
     encoder.write_all(data)?;
     Ok(encoder.finish()?)
 }
@@ -23,7 +60,12 @@ pub fn decompress_gzip(data: &[u8]) -> Result<Vec<u8>> {
 
 /// 使用 ZLIB 压缩数据
 pub fn compress_zlib(data: &[u8]) -> Result<Vec<u8>> {
-    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    compress_zlib_with(data, CompressionLevel::default())
+}
+
+/// 按指定级别使用 ZLIB 压缩数据
+pub fn compress_zlib_with(data: &[u8], level: CompressionLevel) -> Result<Vec<u8>> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), level.flate());
     encoder.write_all(data)?;
     Ok(encoder.finish()?)
 }
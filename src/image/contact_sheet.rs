@@ -0,0 +1,162 @@
+//! 联系表（contact sheet）
+//!
+//! 把一段帧的缩略图排成带帧号的网格，导出为一张 PNG，用于写文档和审查资源，
+//! 不必打开界面逐帧翻看。缩略图与界面缩略图列表相同（见
+//! [`LibraryLoader::get_pixel_thumbnail`](crate::formats::LibraryLoader::get_pixel_thumbnail)），
+//! 帧号用内置的点阵数字绘制，不依赖字体文件。
+
+use image::imageops;
+use image::{Rgba, RgbaImage};
+use std::sync::Arc;
+
+/// 未指定列数时每行的帧数
+pub const DEFAULT_COLUMNS: u32 = 10;
+
+/// 未指定时缩略图格子的边长（像素）
+pub const DEFAULT_CELL: u32 = 96;
+
+/// 格子之间及四周的间隙
+const GAP: u32 = 4;
+
+/// 背景色
+const BACKGROUND: Rgba<u8> = Rgba([32, 32, 32, 255]);
+
+/// 缩略图格子的底色
+const CELL_BACKGROUND: Rgba<u8> = Rgba([58, 58, 58, 255]);
+
+/// 帧号颜色（空帧的帧号用暗色）
+const CAPTION: Rgba<u8> = Rgba([220, 220, 220, 255]);
+const CAPTION_EMPTY: Rgba<u8> = Rgba([110, 110, 110, 255]);
+
+/// 3×5 点阵数字，每行低 3 位从左到右
+const DIGITS: [[u8; 5]; 10] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111],
+    [0b010, 0b110, 0b010, 0b010, 0b111],
+    [0b111, 0b001, 0b111, 0b100, 0b111],
+    [0b111, 0b001, 0b111, 0b001, 0b111],
+    [0b101, 0b101, 0b111, 0b001, 0b001],
+    [0b111, 0b100, 0b111, 0b001, 0b111],
+    [0b111, 0b100, 0b111, 0b101, 0b111],
+    [0b111, 0b001, 0b001, 0b001, 0b001],
+    [0b111, 0b101, 0b111, 0b101, 0b111],
+    [0b111, 0b101, 0b111, 0b001, 0b111],
+];
+
+/// 帧号的放大倍数：格子较小时用 1 倍
+fn caption_scale(cell: u32) -> u32 {
+    if cell >= 48 { 2 } else { 1 }
+}
+
+/// 帧号栏的高度
+fn caption_height(cell: u32) -> u32 {
+    5 * caption_scale(cell) + 2 * GAP
+}
+
+/// 帧号文字的宽度（每个数字 3 点加 1 点间隔）
+fn text_width(text: &str, scale: u32) -> u32 {
+    (text.len() as u32 * 4).saturating_sub(1) * scale
+}
+
+/// 在 (`x`, `y`) 处绘制数字，其他字符留空
+fn draw_digits(canvas: &mut RgbaImage, x: u32, y: u32, text: &str, scale: u32, color: Rgba<u8>) {
+    for (i, ch) in text.chars().enumerate() {
+        let Some(glyph) = ch.to_digit(10).map(|d| DIGITS[d as usize]) else {
+            continue;
+        };
+        let left = x + i as u32 * 4 * scale;
+        for (row, bits) in glyph.iter().enumerate() {
+            for col in 0..3 {
+                if bits & (0b100 >> col) == 0 {
+                    continue;
+                }
+                for dy in 0..scale {
+                    for dx in 0..scale {
+                        let (px, py) = (left + col * scale + dx, y + row as u32 * scale + dy);
+                        if px < canvas.width() && py < canvas.height() {
+                            canvas.put_pixel(px, py, color);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// 把帧（帧索引、缩略图）按 `columns` 列排成带帧号的网格
+///
+/// 缩略图居中放在 `cell`×`cell` 的格子中，大于格子的先等比缩小；帧号写在格子下方。
+/// 没有缩略图的帧留出空格子，帧号用暗色。
+pub fn assemble(frames: &[(usize, Option<Arc<RgbaImage>>)], columns: u32, cell: u32) -> RgbaImage {
+    let cell = cell.max(1);
+    let columns = columns.clamp(1, frames.len().max(1) as u32);
+    let rows = (frames.len() as u32).div_ceil(columns).max(1);
+    let (step_x, step_y) = (cell + GAP, cell + caption_height(cell) + GAP);
+    let mut sheet = RgbaImage::from_pixel(columns * step_x + GAP, rows * step_y + GAP, BACKGROUND);
+    let scale = caption_scale(cell);
+
+    for (i, (index, thumbnail)) in frames.iter().enumerate() {
+        let (col, row) = (i as u32 % columns, i as u32 / columns);
+        let (left, top) = (GAP + col * step_x, GAP + row * step_y);
+        let background = RgbaImage::from_pixel(cell, cell, CELL_BACKGROUND);
+        imageops::replace(&mut sheet, &background, left as i64, top as i64);
+
+        if let Some(thumbnail) = thumbnail {
+            let thumbnail = crate::image::fit_thumbnail(thumbnail.clone(), cell);
+            let x = left + (cell - thumbnail.width()) / 2;
+            let y = top + (cell - thumbnail.height()) / 2;
+            imageops::overlay(&mut sheet, &*thumbnail, x as i64, y as i64);
+        }
+
+        let text = index.to_string();
+        let width = text_width(&text, scale);
+        let x = left + cell.saturating_sub(width) / 2;
+        let color = if thumbnail.is_some() {
+            CAPTION
+        } else {
+            CAPTION_EMPTY
+        };
+        draw_digits(&mut sheet, x, top + cell + GAP, &text, scale, color);
+    }
+    sheet
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_assemble_contact_sheet() {
+        let frame = |w, h| {
+            Some(Arc::new(RgbaImage::from_pixel(
+                w,
+                h,
+                Rgba([255, 0, 0, 255]),
+            )))
+        };
+        let frames = vec![(10, frame(8, 4)), (11, None), (12, frame(200, 100))];
+
+        let sheet = assemble(&frames, 2, 50);
+        let caption = caption_height(50);
+        assert_eq!(caption, 18);
+        assert_eq!(sheet.dimensions(), (2 * 54 + 4, 2 * (50 + caption + 4) + 4));
+
+        // 小图居中，不放大
+        assert_eq!(sheet.get_pixel(4 + 21, 4 + 23).0, [255, 0, 0, 255]);
+        assert_eq!(sheet.get_pixel(4 + 20, 4 + 23), &CELL_BACKGROUND);
+        // 空帧只有格子底色
+        assert_eq!(sheet.get_pixel(58 + 25, 4 + 25), &CELL_BACKGROUND);
+        // 大图缩小到格子内（50×25，上下留空）
+        let top = 4 + 50 + caption + 4;
+        assert_eq!(sheet.get_pixel(4, top + 25).0, [255, 0, 0, 255]);
+        assert_eq!(sheet.get_pixel(4, top + 5), &CELL_BACKGROUND);
+
+        // 帧号 "10" 居中写在格子下方：2 倍点阵，宽 14
+        let (x, y) = (4 + (50 - 14) / 2, 4 + 50 + 4);
+        assert_eq!(sheet.get_pixel(x + 2, y), &CAPTION);
+        assert_eq!(sheet.get_pixel(x, y), &BACKGROUND);
+        assert_eq!(sheet.get_pixel(x + 8, y + 2), &CAPTION);
+        assert_eq!(sheet.get_pixel(x + 10, y + 2), &BACKGROUND);
+        // 空帧的帧号用暗色
+        assert_eq!(sheet.get_pixel(58 + (50 - 14) / 2 + 2, y), &CAPTION_EMPTY);
+    }
+}
@@ -0,0 +1,239 @@
+//! 单帧导出为常见图像格式
+//!
+//! 旧的美术流程往往只认 24 位 BMP、TGA 或 8 位调色板 BMP。不支持透明的格式（BMP、
+//! JPEG、不保留透明的 TGA）先把图像按 alpha 混合到背景色上；8 位调色板 BMP 和索引色
+//! PNG 直接写出帧的调色板索引和库的调色板，由 [`LibraryLoader::export_image`] 负责取出。
+//!
+//! [`LibraryLoader::export_image`]: crate::formats::LibraryLoader::export_image
+
+use crate::error::{LibraryError, Result};
+use crate::image::indexed::IndexedFrame;
+use ::image::codecs::bmp::BmpEncoder;
+use ::image::codecs::jpeg::JpegEncoder;
+use ::image::codecs::tga::TgaEncoder;
+use ::image::{ExtendedColorType, ImageEncoder, RgbImage, RgbaImage};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+/// 导出的图像格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// PNG（保留透明）
+    Png,
+    /// 24 位 BMP
+    Bmp,
+    /// 8 位调色板 BMP，使用库的调色板
+    Bmp8,
+    /// 8 位索引色 PNG，使用库的调色板
+    Png8,
+    /// TGA（按选项保留透明）
+    Tga,
+    /// JPEG
+    Jpeg,
+}
+
+impl ExportFormat {
+    /// 按名称解析（`png` / `png8` / `bmp` / `bmp8` / `tga` / `jpg` / `jpeg`，不区分大小写）
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "png" => Some(Self::Png),
+            "png8" => Some(Self::Png8),
+            "bmp" => Some(Self::Bmp),
+            "bmp8" => Some(Self::Bmp8),
+            "tga" => Some(Self::Tga),
+            "jpg" | "jpeg" => Some(Self::Jpeg),
+            _ => None,
+        }
+    }
+
+    /// 按文件扩展名判断格式（`.bmp` 视为 24 位 BMP，`.png` 视为 RGBA PNG）
+    pub fn from_path(path: &Path) -> Option<Self> {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .filter(|ext| !ext.eq_ignore_ascii_case("bmp8") && !ext.eq_ignore_ascii_case("png8"))
+            .and_then(Self::from_name)
+    }
+
+    /// 格式名称
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::Bmp => "bmp",
+            Self::Bmp8 => "bmp8",
+            Self::Png8 => "png8",
+            Self::Tga => "tga",
+            Self::Jpeg => "jpg",
+        }
+    }
+
+    /// 文件扩展名
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Bmp8 => "bmp",
+            Self::Png8 => "png",
+            _ => self.name(),
+        }
+    }
+}
+
+/// 导出选项
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExportOptions {
+    /// 不支持透明的格式混合到的背景色
+    pub background: [u8; 3],
+    /// JPEG 质量（1-100）
+    pub jpeg_quality: u8,
+    /// TGA 是否保留透明（否则输出 24 位）
+    pub keep_alpha: bool,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            background: [0, 0, 0],
+            jpeg_quality: 90,
+            keep_alpha: true,
+        }
+    }
+}
+
+/// 把图像按 alpha 混合到背景色上
+pub fn flatten(image: &RgbaImage, background: [u8; 3]) -> RgbImage {
+    RgbImage::from_fn(image.width(), image.height(), |x, y| {
+        let [r, g, b, a] = image.get_pixel(x, y).0;
+        let blend =
+            |c: u8, bg: u8| ((c as u32 * a as u32 + bg as u32 * (255 - a as u32)) / 255) as u8;
+        ::image::Rgb([
+            blend(r, background[0]),
+            blend(g, background[1]),
+            blend(b, background[2]),
+        ])
+    })
+}
+
+/// 按格式写出 RGBA 图像（8 位调色板 BMP 见 [`write_indexed_bmp`]，索引色 PNG 见
+/// [`write_indexed_png`](crate::image::indexed::write_indexed_png)）
+pub fn write_image(
+    image: &RgbaImage,
+    path: &Path,
+    format: ExportFormat,
+    options: &ExportOptions,
+) -> Result<()> {
+    let (width, height) = image.dimensions();
+    let mut writer = BufWriter::new(File::create(path)?);
+    match format {
+        ExportFormat::Png => {
+            image.write_to(&mut writer, ::image::ImageFormat::Png)?;
+        }
+        ExportFormat::Bmp => {
+            let rgb = flatten(image, options.background);
+            BmpEncoder::new(&mut writer).write_image(
+                &rgb,
+                width,
+                height,
+                ExtendedColorType::Rgb8,
+            )?;
+        }
+        ExportFormat::Tga if options.keep_alpha => {
+            TgaEncoder::new(&mut writer).write_image(
+                image,
+                width,
+                height,
+                ExtendedColorType::Rgba8,
+            )?;
+        }
+        ExportFormat::Tga => {
+            let rgb = flatten(image, options.background);
+            TgaEncoder::new(&mut writer).write_image(
+                &rgb,
+                width,
+                height,
+                ExtendedColorType::Rgb8,
+            )?;
+        }
+        ExportFormat::Jpeg => {
+            let rgb = flatten(image, options.background);
+            JpegEncoder::new_with_quality(&mut writer, options.jpeg_quality.clamp(1, 100))
+                .write_image(&rgb, width, height, ExtendedColorType::Rgb8)?;
+        }
+        ExportFormat::Bmp8 | ExportFormat::Png8 => {
+            return Err(LibraryError::ParseError(format!(
+                "{} 需要调色板索引",
+                format.name()
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// 写出 8 位调色板 BMP
+pub fn write_indexed_bmp(path: &Path, frame: &IndexedFrame) -> Result<()> {
+    frame.validate()?;
+    let rgb: Vec<[u8; 3]> = frame
+        .palette
+        .iter()
+        .map(|&[b, g, r, _]| [r, g, b])
+        .collect();
+    let mut writer = BufWriter::new(File::create(path)?);
+    BmpEncoder::new(&mut writer).encode_with_palette(
+        &frame.indices,
+        frame.width,
+        frame.height,
+        ExtendedColorType::L8,
+        Some(&rgb),
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::image::Rgba;
+
+    #[test]
+    fn test_export_formats() {
+        assert_eq!(
+            ExportFormat::from_path(Path::new("a.JPEG")),
+            Some(ExportFormat::Jpeg)
+        );
+        assert_eq!(
+            ExportFormat::from_path(Path::new("a.bmp")),
+            Some(ExportFormat::Bmp)
+        );
+        assert_eq!(ExportFormat::from_name("bmp8"), Some(ExportFormat::Bmp8));
+        assert_eq!(ExportFormat::from_name("PNG8"), Some(ExportFormat::Png8));
+        assert_eq!(ExportFormat::Png8.extension(), "png");
+        assert_eq!(ExportFormat::from_path(Path::new("a.gif")), None);
+
+        let mut image = RgbaImage::new(2, 1);
+        image.put_pixel(0, 0, Rgba([255, 0, 0, 255]));
+        image.put_pixel(1, 0, Rgba([255, 255, 255, 0]));
+        let flat = flatten(&image, [0, 0, 255]);
+        assert_eq!(flat.get_pixel(0, 0).0, [255, 0, 0]);
+        assert_eq!(flat.get_pixel(1, 0).0, [0, 0, 255]);
+
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let options = ExportOptions::default();
+        for format in [ExportFormat::Bmp, ExportFormat::Tga, ExportFormat::Jpeg] {
+            let path = dir.join(format!("frame.{}", format.extension()));
+            write_image(&image, &path, format, &options).unwrap();
+            assert_eq!(::image::open(&path).unwrap().width(), 2);
+        }
+
+        let mut palette = [[0u8; 4]; 256];
+        palette[1] = [0, 0, 255, 255]; // 红色 (BGRA)
+        let path = dir.join("indexed.bmp");
+        let frame = IndexedFrame {
+            width: 2,
+            height: 1,
+            indices: vec![1, 0],
+            palette,
+        };
+        write_indexed_bmp(&path, &frame).unwrap();
+        let decoded = ::image::open(&path).unwrap().to_rgb8();
+        assert_eq!(decoded.get_pixel(0, 0).0, [255, 0, 0]);
+        assert_eq!(decoded.get_pixel(1, 0).0, [0, 0, 0]);
+    }
+}
@@ -0,0 +1,73 @@
+//! GIF 动画导出
+//!
+//! 把一段帧按绘制偏移对齐到同一透明画布，编码为循环播放的 GIF。GIF 每帧最多
+//! 256 色、只有全透明和不透明两种 Alpha，颜色会有损失，但不依赖外部程序，
+//! 浏览器和聊天工具都能直接播放。
+
+use crate::error::{LibraryError, Result};
+use crate::image::video::{self, FPS_RANGE, PlacedFrame};
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, Frame};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+/// 对齐帧并导出为循环播放的 GIF，返回写入的帧数
+pub fn export(frames: &[Option<PlacedFrame>], path: &Path, fps: u32) -> Result<usize> {
+    let canvases = video::align(frames);
+    if canvases.is_empty() {
+        return Err(LibraryError::ParseError("没有可导出的帧".to_string()));
+    }
+
+    let fps = fps.clamp(*FPS_RANGE.start(), *FPS_RANGE.end());
+    tracing::debug!("编码 GIF {:?}: {} 帧, {} FPS", path, canvases.len(), fps);
+
+    let mut encoder = GifEncoder::new(BufWriter::new(File::create(path)?));
+    encoder.set_repeat(Repeat::Infinite)?;
+    let delay = Delay::from_numer_denom_ms(1000, fps);
+    let written = canvases.len();
+    encoder.encode_frames(
+        canvases
+            .into_iter()
+            .map(|canvas| Frame::from_parts(canvas, 0, 0, delay)),
+    )?;
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::codecs::gif::GifDecoder;
+    use image::{AnimationDecoder, Rgba, RgbaImage};
+    use std::io::BufReader;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_export_gif_aligns_frames() {
+        let red = RgbaImage::from_pixel(2, 2, Rgba([255, 0, 0, 255]));
+        let blue = RgbaImage::from_pixel(1, 3, Rgba([0, 0, 255, 255]));
+        let frames = vec![
+            Some((Arc::new(red), (0, 0))),
+            None,
+            Some((Arc::new(blue), (2, -1))),
+        ];
+
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("export.gif");
+        assert_eq!(export(&frames, &path, 5).unwrap(), 3);
+
+        let decoder = GifDecoder::new(BufReader::new(File::open(&path).unwrap())).unwrap();
+        let decoded: Vec<Frame> = decoder.into_frames().collect_frames().unwrap();
+
+        assert_eq!(decoded.len(), 3);
+        assert_eq!(decoded[0].delay().numer_denom_ms(), (200, 1));
+        let first = decoded[0].buffer();
+        assert_eq!(first.dimensions(), (3, 3));
+        assert_eq!(first.get_pixel(0, 1).0, [255, 0, 0, 255]);
+        assert_eq!(first.get_pixel(2, 0).0[3], 0);
+        assert!(decoded[1].buffer().pixels().all(|p| p.0[3] == 0));
+        assert_eq!(decoded[2].buffer().get_pixel(2, 0).0, [0, 0, 255, 255]);
+
+        assert!(export(&[None], &path, 5).is_err());
+    }
+}
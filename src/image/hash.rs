@@ -0,0 +1,94 @@
+//! 感知哈希
+//!
+//! 把图像缩小成 8×8 的灰度图后计算均值哈希（aHash）和差值哈希（dHash），
+//! 两个 64 位哈希的汉明距离之和越小越相似。缩放、轻微调色和重新压缩后的
+//! 同一精灵距离通常很小，可用来在不同库中查找同一张图。透明像素按黑色计算，
+//! 与游戏中黑色透明的约定一致。
+
+use image::imageops::{self, FilterType};
+use image::{GrayImage, Luma, RgbaImage};
+
+/// 两个哈希的最大距离（两个 64 位哈希全部不同）
+pub const MAX_DISTANCE: u32 = 128;
+
+/// 图像的感知哈希
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ImageHash {
+    /// 均值哈希：8×8 灰度图中高于平均亮度的像素
+    pub average: u64,
+    /// 差值哈希：9×8 灰度图中比右侧像素暗的像素
+    pub difference: u64,
+}
+
+impl ImageHash {
+    /// 计算图像的感知哈希
+    pub fn of(image: &RgbaImage) -> Self {
+        let gray = GrayImage::from_fn(image.width(), image.height(), |x, y| {
+            let [r, g, b, a] = image.get_pixel(x, y).0;
+            let luma = (r as u32 * 299 + g as u32 * 587 + b as u32 * 114) / 1000;
+            Luma([(luma * a as u32 / 255) as u8])
+        });
+
+        let small = imageops::resize(&gray, 8, 8, FilterType::Triangle);
+        let mean = small.pixels().map(|p| p[0] as u32).sum::<u32>() / 64;
+        let average = bits(small.pixels().map(|p| p[0] as u32 > mean));
+
+        let wide = imageops::resize(&gray, 9, 8, FilterType::Triangle);
+        let difference = bits(
+            (0..8)
+                .flat_map(|y| (0..8).map(move |x| (x, y)))
+                .map(|(x, y)| wide.get_pixel(x, y)[0] < wide.get_pixel(x + 1, y)[0]),
+        );
+
+        Self {
+            average,
+            difference,
+        }
+    }
+
+    /// 与另一哈希的距离（0 到 [`MAX_DISTANCE`]）
+    pub fn distance(&self, other: &Self) -> u32 {
+        (self.average ^ other.average).count_ones()
+            + (self.difference ^ other.difference).count_ones()
+    }
+}
+
+/// 把 64 个布尔值按顺序拼成哈希
+fn bits(values: impl Iterator<Item = bool>) -> u64 {
+    values
+        .take(64)
+        .enumerate()
+        .fold(0, |hash, (i, set)| hash | (set as u64) << i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    #[test]
+    fn test_hash_distance() {
+        let gradient = |scale: u32, offset: u8| {
+            RgbaImage::from_fn(16 * scale, 16 * scale, |x, y| {
+                let v = ((x + y) * 8 / scale) as u8;
+                Rgba([v.saturating_add(offset), v, v, 255])
+            })
+        };
+        let hash = ImageHash::of(&gradient(1, 0));
+        assert_eq!(hash.distance(&hash), 0);
+        // 放大和轻微调色后仍然相近
+        assert!(hash.distance(&ImageHash::of(&gradient(2, 10))) <= 8);
+
+        // 方向相反的渐变差别很大
+        let reversed = RgbaImage::from_fn(16, 16, |x, y| {
+            let v = ((30 - x - y) * 8) as u8;
+            Rgba([v, v, v, 255])
+        });
+        assert!(hash.distance(&ImageHash::of(&reversed)) > 64);
+
+        // 透明像素按黑色计算
+        let clear = RgbaImage::from_pixel(8, 8, Rgba([255, 255, 255, 0]));
+        let black = RgbaImage::from_pixel(8, 8, Rgba([0, 0, 0, 255]));
+        assert_eq!(ImageHash::of(&clear), ImageHash::of(&black));
+    }
+}
@@ -0,0 +1,144 @@
+//! 索引分布条
+//!
+//! 把整个库的帧按状态（未加载、空帧、已加载、已编辑、损坏）压缩成一条细长的图像，
+//! 每个像素对应一段连续的索引，一眼就能看出哪些索引段是空的、哪些已加载或改动过。
+//! GUI 把它竖放在缩略图网格旁边，同时作为滚动的缩略地图。
+
+use image::{Rgba, RgbaImage};
+use std::ops::Range;
+
+/// 单帧状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum FrameState {
+    /// 尚未加载
+    Unloaded,
+    /// 空帧（没有像素数据）
+    Empty,
+    /// 已加载
+    Loaded,
+    /// 保存后修改过
+    Edited,
+    /// 加载失败（数据损坏或无法解码）
+    Corrupt,
+}
+
+impl FrameState {
+    /// 全部状态（按说明文字中的顺序）
+    pub const ALL: [FrameState; 5] = [
+        FrameState::Loaded,
+        FrameState::Empty,
+        FrameState::Unloaded,
+        FrameState::Edited,
+        FrameState::Corrupt,
+    ];
+
+    /// 在分布条中的颜色
+    pub fn color(self) -> Rgba<u8> {
+        match self {
+            FrameState::Unloaded => Rgba([85, 85, 85, 255]),
+            FrameState::Empty => Rgba([24, 24, 24, 255]),
+            FrameState::Loaded => Rgba([78, 143, 90, 255]),
+            FrameState::Edited => Rgba([215, 186, 125, 255]),
+            FrameState::Corrupt => Rgba([209, 105, 105, 255]),
+        }
+    }
+
+    /// 状态名称
+    pub fn name(self) -> &'static str {
+        match self {
+            FrameState::Unloaded => "未加载",
+            FrameState::Empty => "空帧",
+            FrameState::Loaded => "已加载",
+            FrameState::Edited => "已编辑",
+            FrameState::Corrupt => "损坏",
+        }
+    }
+
+    /// 是否需要单独标出（一段索引中只要有一帧就显示为该状态）
+    fn is_notable(self) -> bool {
+        matches!(self, FrameState::Edited | FrameState::Corrupt)
+    }
+}
+
+/// 共 `count` 帧分成 `rows` 段时第 `row` 段的索引范围
+pub fn bucket(count: usize, rows: usize, row: usize) -> Range<usize> {
+    let rows = rows.max(1);
+    (row * count / rows)..((row + 1) * count / rows)
+}
+
+/// 一段索引的代表状态：有损坏或已编辑的帧时取最严重的，否则取最多的状态
+fn dominant(states: &[FrameState]) -> FrameState {
+    if let Some(&notable) = states.iter().filter(|s| s.is_notable()).max() {
+        return notable;
+    }
+    let mut counts = [0usize; FrameState::ALL.len()];
+    for &state in states {
+        counts[state as usize] += 1;
+    }
+    FrameState::ALL
+        .into_iter()
+        .max_by_key(|&state| (counts[state as usize], state))
+        .unwrap_or(FrameState::Unloaded)
+}
+
+/// 生成竖向的分布条：宽 1 像素、高 `min(帧数, max_rows)` 像素，自上而下对应索引从小到大
+///
+/// 没有帧时返回 1x1 的透明图像。
+pub fn render(states: &[FrameState], max_rows: usize) -> RgbaImage {
+    let rows = states.len().min(max_rows.max(1));
+    if rows == 0 {
+        return RgbaImage::new(1, 1);
+    }
+    RgbaImage::from_fn(1, rows as u32, |_, row| {
+        dominant(&states[bucket(states.len(), rows, row as usize)]).color()
+    })
+}
+
+/// 描述一段索引中各状态的帧数，如 `#100-199: 已加载 80，空帧 20`
+pub fn describe(states: &[FrameState], range: Range<usize>) -> String {
+    let range = range.start.min(states.len())..range.end.min(states.len());
+    if range.is_empty() {
+        return String::new();
+    }
+    let parts: Vec<String> = FrameState::ALL
+        .into_iter()
+        .filter_map(|state| {
+            let count = states[range.clone()]
+                .iter()
+                .filter(|&&s| s == state)
+                .count();
+            (count > 0).then(|| format!("{} {}", state.name(), count))
+        })
+        .collect();
+    format!("#{}-{}: {}", range.start, range.end - 1, parts.join("，"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_buckets() {
+        use FrameState::*;
+        let mut states = vec![Loaded; 5];
+        states.extend([Empty; 3]);
+        states.extend([Unloaded, Unloaded, Edited, Unloaded]);
+        states.extend([Loaded, Corrupt, Loaded, Loaded]);
+
+        // 16 帧分成 4 段，每段 4 帧
+        let bar = render(&states, 4);
+        assert_eq!(bar.dimensions(), (1, 4));
+        assert_eq!(*bar.get_pixel(0, 0), Loaded.color());
+        assert_eq!(*bar.get_pixel(0, 1), Empty.color());
+        assert_eq!(*bar.get_pixel(0, 2), Edited.color());
+        assert_eq!(*bar.get_pixel(0, 3), Corrupt.color());
+
+        // 帧数少于行数时每帧一行
+        assert_eq!(render(&states[..3], 100).dimensions(), (1, 3));
+        assert_eq!(render(&[], 100).dimensions(), (1, 1));
+
+        assert_eq!(bucket(10, 3, 2), 6..10);
+        assert_eq!(describe(&states, 4..8), "#4-7: 已加载 1，空帧 3");
+        assert_eq!(describe(&states, 20..30), "");
+    }
+}
@@ -0,0 +1,131 @@
+//! 索引色图像
+//!
+//! WIL / WZL 的 8 位帧本身就是调色板索引。导出成 RGBA PNG 后，外部工具改完再导入
+//! 时要重新量化，颜色可能落到相邻的调色板项上。这里按原调色板写出索引色 PNG
+//! （PLTE + tRNS），索引原样保留，外部工具可以无损编辑；透明与解码一致：索引 0
+//! 透明，其余按调色板的 alpha，alpha 为 0 时视为不透明。
+
+use crate::error::{LibraryError, Result};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+/// 调色板格式的一帧：调色板索引及该帧使用的调色板
+#[derive(Debug, Clone)]
+pub struct IndexedFrame {
+    /// 宽度
+    pub width: u32,
+    /// 高度
+    pub height: u32,
+    /// 自上而下逐行的调色板索引
+    pub indices: Vec<u8>,
+    /// BGRA 调色板
+    pub palette: [[u8; 4]; 256],
+}
+
+impl IndexedFrame {
+    /// 尺寸为零或索引个数与尺寸不符时返回错误
+    pub fn validate(&self) -> Result<()> {
+        if self.width == 0
+            || self.height == 0
+            || self.indices.len() != (self.width * self.height) as usize
+        {
+            return Err(LibraryError::InvalidImageData);
+        }
+        Ok(())
+    }
+
+    /// 各调色板项的透明度（与解码一致）
+    fn alphas(&self) -> [u8; 256] {
+        let mut alphas = [255u8; 256];
+        for (i, (alpha, color)) in alphas.iter_mut().zip(&self.palette).enumerate() {
+            *alpha = match (i, color[3]) {
+                (0, _) => 0,
+                (_, 0) => 255,
+                (_, a) => a,
+            };
+        }
+        alphas
+    }
+}
+
+/// 写出 8 位索引色 PNG，调色板为帧的全部 256 色
+pub fn write_indexed_png(path: &Path, frame: &IndexedFrame) -> Result<()> {
+    frame.validate()?;
+    let rgb: Vec<u8> = frame
+        .palette
+        .iter()
+        .flat_map(|&[b, g, r, _]| [r, g, b])
+        .collect();
+    // tRNS 只需写到最后一个不是完全不透明的项
+    let alphas = frame.alphas();
+    let trns_len = alphas.iter().rposition(|&a| a != 255).map_or(0, |i| i + 1);
+
+    let writer = BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(writer, frame.width, frame.height);
+    encoder.set_color(png::ColorType::Indexed);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_palette(rgb);
+    if trns_len > 0 {
+        encoder.set_trns(alphas[..trns_len].to_vec());
+    }
+    let mut writer = encoder.write_header().map_err(std::io::Error::from)?;
+    writer
+        .write_image_data(&frame.indices)
+        .map_err(std::io::Error::from)?;
+    writer.finish().map_err(std::io::Error::from)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::BufReader;
+
+    #[test]
+    fn test_write_indexed_png() {
+        let mut palette = [[0u8; 4]; 256];
+        palette[0] = [9, 9, 9, 0];
+        palette[1] = [0, 0, 255, 0]; // 红色 (BGRA)，alpha 为 0 视为不透明
+        palette[7] = [255, 0, 0, 128]; // 半透明蓝色
+        let frame = IndexedFrame {
+            width: 3,
+            height: 2,
+            indices: vec![1, 0, 7, 7, 1, 200],
+            palette,
+        };
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let path = dir.join("frame.png");
+        write_indexed_png(&path, &frame).unwrap();
+
+        // 索引和调色板原样保留
+        let mut decoder = png::Decoder::new(BufReader::new(File::open(&path).unwrap()));
+        decoder.set_transformations(png::Transformations::IDENTITY);
+        let mut reader = decoder.read_info().unwrap();
+        let info = reader.info();
+        assert_eq!(info.color_type, png::ColorType::Indexed);
+        assert_eq!(&info.palette.as_ref().unwrap()[3..6], &[255, 0, 0]);
+        assert_eq!(
+            info.trns.as_deref(),
+            Some(&[0, 255, 255, 255, 255, 255, 255, 128][..])
+        );
+        let mut indices = vec![0; reader.output_buffer_size().unwrap()];
+        reader.next_frame(&mut indices).unwrap();
+        assert_eq!(indices, frame.indices);
+
+        // 展开为 RGBA 时与解码一致
+        let rgba = ::image::open(&path).unwrap().to_rgba8();
+        assert_eq!(rgba.get_pixel(0, 0).0, [255, 0, 0, 255]);
+        assert_eq!(rgba.get_pixel(1, 0).0[3], 0);
+        assert_eq!(rgba.get_pixel(2, 0).0, [0, 0, 255, 128]);
+
+        let empty = IndexedFrame {
+            width: 0,
+            height: 0,
+            indices: Vec::new(),
+            palette,
+        };
+        assert!(write_indexed_png(&path, &empty).is_err());
+    }
+}
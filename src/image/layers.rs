@@ -0,0 +1,109 @@
+//! 遮罩层合成
+//!
+//! V2 帧的遮罩层（武器、特效的发光/覆盖层）与基础层各自带绘制偏移，
+//! 合成时按两者的偏移对齐到同一画布上。
+
+use image::{Rgba, RgbaImage};
+
+/// 遮罩层混合模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MaskBlend {
+    /// 加色混合（游戏中发光层的绘制方式）
+    #[default]
+    Additive,
+    /// 普通 Alpha 覆盖
+    Alpha,
+}
+
+impl MaskBlend {
+    /// 全部混合模式（按界面中的顺序）
+    pub const ALL: [MaskBlend; 2] = [MaskBlend::Additive, MaskBlend::Alpha];
+
+    /// 从界面序号还原，越界时回退为默认模式
+    pub fn from_index(index: i32) -> Self {
+        usize::try_from(index)
+            .ok()
+            .and_then(|i| Self::ALL.get(i).copied())
+            .unwrap_or_default()
+    }
+
+    /// 混合单个像素
+    fn blend(self, dst: Rgba<u8>, src: Rgba<u8>) -> Rgba<u8> {
+        let [sr, sg, sb, sa] = src.0;
+        let [dr, dg, db, da] = dst.0;
+        if sa == 0 {
+            return dst;
+        }
+
+        match self {
+            MaskBlend::Additive => {
+                // 按源 Alpha 加权后相加，结果饱和到 255
+                let add = |d: u8, s: u8| d.saturating_add((s as u16 * sa as u16 / 255) as u8);
+                Rgba([add(dr, sr), add(dg, sg), add(db, sb), da.max(sa)])
+            }
+            MaskBlend::Alpha => {
+                let a = sa as u32;
+                let mix = |d: u8, s: u8| ((s as u32 * a + d as u32 * (255 - a)) / 255) as u8;
+                let out_a = a + da as u32 * (255 - a) / 255;
+                Rgba([mix(dr, sr), mix(dg, sg), mix(db, sb), out_a as u8])
+            }
+        }
+    }
+}
+
+/// 将遮罩层按偏移合成到基础层上
+///
+/// `base_offset` / `mask_offset` 为两层各自的绘制偏移（帧的 x/y 与 mask_x/mask_y），
+/// 输出画布覆盖两层的并集，左上角对应两者偏移的较小值。
+pub fn composite(
+    base: &RgbaImage,
+    base_offset: (i32, i32),
+    mask: &RgbaImage,
+    mask_offset: (i32, i32),
+    blend: MaskBlend,
+) -> RgbaImage {
+    let left = base_offset.0.min(mask_offset.0);
+    let top = base_offset.1.min(mask_offset.1);
+    let right = (base_offset.0 + base.width() as i32).max(mask_offset.0 + mask.width() as i32);
+    let bottom = (base_offset.1 + base.height() as i32).max(mask_offset.1 + mask.height() as i32);
+
+    let mut canvas = RgbaImage::new((right - left) as u32, (bottom - top) as u32);
+    image::imageops::overlay(
+        &mut canvas,
+        base,
+        (base_offset.0 - left) as i64,
+        (base_offset.1 - top) as i64,
+    );
+
+    let dx = (mask_offset.0 - left) as u32;
+    let dy = (mask_offset.1 - top) as u32;
+    for (x, y, src) in mask.enumerate_pixels() {
+        let dst = canvas.get_pixel_mut(x + dx, y + dy);
+        *dst = blend.blend(*dst, *src);
+    }
+
+    canvas
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_composite_aligns_offsets() {
+        let base = RgbaImage::from_pixel(4, 4, Rgba([100, 0, 0, 255]));
+        let mask = RgbaImage::from_pixel(2, 2, Rgba([200, 0, 50, 255]));
+
+        // 遮罩偏移在基础层左上方，画布应向外扩展
+        let out = composite(&base, (0, 0), &mask, (-1, -1), MaskBlend::Additive);
+        assert_eq!(out.dimensions(), (5, 5));
+        assert_eq!(out.get_pixel(0, 0).0, [200, 0, 50, 255]);
+        assert_eq!(out.get_pixel(1, 1).0, [255, 0, 50, 255]);
+        assert_eq!(out.get_pixel(4, 4).0, [100, 0, 0, 255]);
+
+        let out = composite(&base, (0, 0), &mask, (1, 1), MaskBlend::Alpha);
+        assert_eq!(out.dimensions(), (4, 4));
+        assert_eq!(out.get_pixel(1, 1).0, [200, 0, 50, 255]);
+        assert_eq!(MaskBlend::from_index(9), MaskBlend::Additive);
+    }
+}
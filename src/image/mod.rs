@@ -1,32 +1,135 @@
 //! 图像处理模块
 
 pub mod bitmap;
+pub mod baseline;
+pub mod canvas;
+pub mod chroma_key;
+pub mod compare;
+pub mod contact_sheet;
+pub mod export;
+pub mod gif;
+pub mod hash;
+pub mod index_bar;
+pub mod indexed;
+pub mod layers;
+pub mod orientation;
 pub mod palette;
 pub mod palette_data;
+pub mod placeholder;
+pub mod placement;
+pub mod rows;
+pub mod shadow;
+pub mod tiles;
+pub mod video;
 pub mod compression;
 
-// 重新导出 MImage（已移至 formats::mlibrary_v1）
-pub use crate::formats::MImage;
+pub use layers::MaskBlend;
 pub use palette::{Color, DEFAULT_PALETTE};
+pub use placeholder::PlaceholderPolicy;
+
+use ::image::RgbaImage;
+use ::image::imageops::{self, FilterType};
+use std::sync::Arc;
+
+/// 解析 `#RRGGBB` / `RRGGBB` 形式的颜色
+pub fn parse_hex_color(text: &str) -> Option<[u8; 3]> {
+    let hex = text.trim().trim_start_matches('#');
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
+
+/// `MImage` 预览图的边长
+pub const PREVIEW_SIZE: u32 = 64;
+
+/// 等比缩小到长边不超过 `size` 后的尺寸（每边至少 1 像素），本来就不超过的不变
+fn fit_dimensions(width: u32, height: u32, size: u32) -> (u32, u32) {
+    let size = size.max(1);
+    if width <= size && height <= size {
+        return (width, height);
+    }
+    let scale = size as f64 / width.max(height) as f64;
+    let fit = |value: u32| ((value as f64 * scale).round() as u32).clamp(1, size);
+    (fit(width), fit(height))
+}
+
+/// 等比缩小到不超过 `size`×`size`（至少 1 像素），本来就不超过的原样返回
+pub fn fit_thumbnail(image: Arc<RgbaImage>, size: u32) -> Arc<RgbaImage> {
+    let (width, height) = image.dimensions();
+    let (fit_width, fit_height) = fit_dimensions(width, height, size);
+    if (fit_width, fit_height) == (width, height) {
+        return image;
+    }
+    Arc::new(imageops::thumbnail(&*image, fit_width, fit_height))
+}
+
+/// `size`×`size` 的预览图：等比缩小到能放进画布（不放大），居中放在透明画布上，
+/// 多出的部分留空
+pub fn letterbox(image: &RgbaImage, size: u32) -> RgbaImage {
+    let (width, height) = fit_dimensions(image.width(), image.height(), size);
+    let mut canvas = RgbaImage::new(size, size);
+    if width == 0 || height == 0 {
+        return canvas;
+    }
+    let resized = imageops::resize(image, width, height, FilterType::Triangle);
+    let (x, y) = ((size - width) / 2, (size - height) / 2);
+    imageops::replace(&mut canvas, &resized, x as i64, y as i64);
+    canvas
+}
+
+/// 像素图缩略图：能整数倍放大时按最大的整数倍最近邻放大到不超过 `size`×`size`，
+/// 像素保持方正清晰；放大不到 2 倍时同 [`fit_thumbnail`]
+pub fn fit_pixel_thumbnail(image: Arc<RgbaImage>, size: u32) -> Arc<RgbaImage> {
+    let (width, height) = image.dimensions();
+    let factor = size / width.max(height).max(1);
+    if factor < 2 || width == 0 || height == 0 {
+        return fit_thumbnail(image, size);
+    }
+    Arc::new(imageops::resize(
+        &*image,
+        width * factor,
+        height * factor,
+        FilterType::Nearest,
+    ))
+}
+
+/// RGB565 转为不透明的 RGBA
+///
+/// 低位用高位补齐（位复制），0x1F / 0x3F 展开为 255，而不是截断得到的 248 / 252，
+/// 再按 RGB565 截断回去得到原值。
+pub fn rgb565_to_rgba(color: u16) -> [u8; 4] {
+    let r = ((color >> 11) & 0x1f) as u8;
+    let g = ((color >> 5) & 0x3f) as u8;
+    let b = (color & 0x1f) as u8;
+    [
+        (r << 3) | (r >> 2),
+        (g << 2) | (g >> 4),
+        (b << 3) | (b >> 2),
+        255,
+    ]
+}
+
+/// RGB888 转为最接近的 RGB565（[`rgb565_to_rgba`] 的逆运算，展开后再转换得到原值）
+pub fn rgb_to_rgb565(r: u8, g: u8, b: u8) -> u16 {
+    let scale = |value: u8, max: u32| (value as u32 * max + 127) / 255;
+    ((scale(r, 31) << 11) | (scale(g, 63) << 5) | scale(b, 31)) as u16
+}
 
 /// 16位颜色转32位颜色
 pub fn convert_16bit_to_32bit(color: u16) -> u32 {
-    let red = ((color & 0xf800) >> 8) as u8;
-    let green = ((color & 0x07e0) >> 3) as u8;
-    let blue = ((color & 0x001f) << 3) as u8;
-
-    if red == 0 && green == 0 && blue == 0 {
+    if color == 0 {
         return 0;
     }
+    let [red, green, blue, _] = rgb565_to_rgba(color);
 
     ((red as u32) << 16) | ((green as u32) << 8) | (blue as u32) | (255 << 24)
 }
 
 /// 16位颜色转32位颜色（带透明度）
 pub fn convert_16bit_to_32bit_with_alpha(color: u16, alpha_byte: u8, x: usize) -> u32 {
-    let red = ((color & 0xf800) >> 8) as u8;
-    let green = ((color & 0x07e0) >> 3) as u8;
-    let blue = ((color & 0x001f) << 3) as u8;
+    let [red, green, blue, _] = rgb565_to_rgba(color);
 
     // 从 alpha 字节中提取透明度值
     let alpha = if x % 2 != 0 {
@@ -38,12 +141,79 @@ pub fn convert_16bit_to_32bit_with_alpha(color: u16, alpha_byte: u8, x: usize) -
     ((red as u32) << 16) | ((green as u32) << 8) | (blue as u32) | (alpha << 24)
 }
 
-/// 计算行字节数（用于 BMP 格式）
-pub fn width_bytes(bit_count: u32, width: u32) -> u32 {
-    ((width * bit_count) + 31) / 32 * 4
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::image::Rgba;
+
+    #[test]
+    fn test_rgb565_expansion() {
+        // 逐个分量的全部取值：两端展开为 0 / 255，且截断回 RGB565 后不变
+        for value in 0..32u16 {
+            let [r, _, b, a] = rgb565_to_rgba((value << 11) | value);
+            assert_eq!((r, b, a), (r, r, 255));
+            assert_eq!(r >> 3, value as u8);
+        }
+        for value in 0..64u16 {
+            let [_, g, _, _] = rgb565_to_rgba(value << 5);
+            assert_eq!(g >> 2, value as u8);
+        }
+        assert_eq!(rgb565_to_rgba(0xFFFF), [255, 255, 255, 255]);
+        for color in 0..=u16::MAX {
+            let [r, g, b, _] = rgb565_to_rgba(color);
+            assert_eq!(rgb_to_rgb565(r, g, b), color);
+        }
+        assert_eq!(rgb_to_rgb565(250, 3, 5), 0xF021);
+        assert_eq!(rgb565_to_rgba(0x8410), [132, 130, 132, 255]);
 
-/// 跳过的字节数
-pub fn skip_bytes(bit: u32, width: u32) -> u32 {
-    width_bytes(bit * width, width) - width * (bit / 8)
+        assert_eq!(convert_16bit_to_32bit(0), 0);
+        assert_eq!(convert_16bit_to_32bit(0xF800), 0xFFFF_0000);
+        assert_eq!(
+            convert_16bit_to_32bit_with_alpha(0x001F, 0x8F, 0),
+            0x8800_00FF
+        );
+        assert_eq!(
+            convert_16bit_to_32bit_with_alpha(0x001F, 0x8F, 1),
+            0xFF00_00FF
+        );
+    }
+
+    #[test]
+    fn test_fit_pixel_thumbnail_scales_by_whole_factors() {
+        let mut sprite = RgbaImage::from_pixel(3, 2, Rgba([0, 0, 0, 255]));
+        sprite.put_pixel(0, 0, Rgba([255, 0, 0, 255]));
+        let sprite = Arc::new(sprite);
+
+        // 3×2 放进 20×20：最大整数倍为 6，每个源像素变成 6×6 的方块
+        let scaled = fit_pixel_thumbnail(sprite.clone(), 20);
+        assert_eq!(scaled.dimensions(), (18, 12));
+        assert_eq!(scaled.get_pixel(5, 5).0, [255, 0, 0, 255]);
+        assert_eq!(scaled.get_pixel(6, 5).0, [0, 0, 0, 255]);
+
+        // 放大不到 2 倍时原样返回，过大时等比缩小
+        let unscaled = fit_pixel_thumbnail(sprite.clone(), 5);
+        assert!(Arc::ptr_eq(&unscaled, &sprite));
+        let large = Arc::new(RgbaImage::new(100, 50));
+        assert_eq!(fit_pixel_thumbnail(large, 40).dimensions(), (40, 20));
+    }
+
+    #[test]
+    fn test_letterbox_keeps_aspect_ratio() {
+        // 300×200 缩小为 64×43，上下各留出透明的边
+        let wide = RgbaImage::from_pixel(300, 200, Rgba([0, 128, 255, 255]));
+        let preview = letterbox(&wide, PREVIEW_SIZE);
+        assert_eq!(preview.dimensions(), (PREVIEW_SIZE, PREVIEW_SIZE));
+        assert_eq!(preview.get_pixel(0, 9).0[3], 0);
+        assert_eq!(preview.get_pixel(0, 10).0, [0, 128, 255, 255]);
+        assert_eq!(preview.get_pixel(63, 52).0, [0, 128, 255, 255]);
+        assert_eq!(preview.get_pixel(63, 53).0[3], 0);
+
+        // 小图不放大，居中放置
+        let small = RgbaImage::from_pixel(10, 6, Rgba([9, 9, 9, 255]));
+        let preview = letterbox(&small, PREVIEW_SIZE);
+        assert_eq!(preview.get_pixel(27, 29).0, [9, 9, 9, 255]);
+        assert_eq!(preview.get_pixel(26, 29).0[3], 0);
+        assert_eq!(preview.get_pixel(37, 35).0[3], 0);
+        assert_eq!(letterbox(&RgbaImage::new(0, 0), 8).dimensions(), (8, 8));
+    }
 }
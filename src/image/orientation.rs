@@ -0,0 +1,123 @@
+//! 显示方向检测
+//!
+//! 各格式的行序约定不同（V1 自下而上、V2 自上而下），解码方向弄错时精灵会上下颠倒。
+//! 这里按“着地”分布猜测方向：直立的人物、怪物、树木只有脚底或树干接触地面，
+//! 底部一带不透明像素的横向跨度比顶部窄；颠倒后宽的部分落到了底部。
+//! 对抽样帧逐帧投票，票数足够且颠倒票明显占多数时才认为整库颠倒。
+
+use image::RgbaImage;
+
+/// 顶部 / 底部取样带占不透明区域高度的比例
+const BAND_RATIO: f32 = 0.2;
+
+/// 两带跨度相差不足此比例时该帧弃权
+const MARGIN: f32 = 0.25;
+
+/// 判定整库颠倒所需的最少有效票数
+const MIN_VOTES: usize = 8;
+
+/// 不透明区域高度小于此值的帧（如特效碎片）不参与投票
+const MIN_HEIGHT: u32 = 8;
+
+/// 多帧投票结果
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct OrientationGuess {
+    /// 底部较窄（直立）的帧数
+    pub upright: usize,
+    /// 底部较宽（颠倒）的帧数
+    pub flipped: usize,
+    /// 无法判断的帧数
+    pub undecided: usize,
+}
+
+impl OrientationGuess {
+    /// 加入一帧的投票
+    pub fn add(&mut self, frame: &RgbaImage) {
+        match vote(frame) {
+            Some(true) => self.flipped += 1,
+            Some(false) => self.upright += 1,
+            None => self.undecided += 1,
+        }
+    }
+
+    /// 是否很可能上下颠倒：有效票数足够，且颠倒票至少是直立票的两倍
+    pub fn likely_flipped(&self) -> bool {
+        self.upright + self.flipped >= MIN_VOTES && self.flipped >= self.upright * 2
+    }
+}
+
+/// 单帧投票：`Some(true)` 表示底部比顶部宽（疑似颠倒）
+fn vote(frame: &RgbaImage) -> Option<bool> {
+    let opaque_rows: Vec<u32> = (0..frame.height())
+        .filter(|&y| (0..frame.width()).any(|x| frame.get_pixel(x, y)[3] > 0))
+        .collect();
+    let (&top, &bottom) = (opaque_rows.first()?, opaque_rows.last()?);
+    let height = bottom - top + 1;
+    if height < MIN_HEIGHT {
+        return None;
+    }
+
+    let band = ((height as f32 * BAND_RATIO).round() as u32).max(1);
+    let top_span = span(frame, top..top + band)?;
+    let bottom_span = span(frame, bottom + 1 - band..bottom + 1)?;
+
+    let diff = (bottom_span as f32 - top_span as f32) / top_span.max(bottom_span) as f32;
+    if diff > MARGIN {
+        Some(true)
+    } else if diff < -MARGIN {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+/// 指定行范围内不透明像素的横向跨度（最右列 - 最左列 + 1）
+fn span(frame: &RgbaImage, rows: std::ops::Range<u32>) -> Option<u32> {
+    let columns =
+        (0..frame.width()).filter(|&x| rows.clone().any(|y| frame.get_pixel(x, y)[3] > 0));
+    let (min, max) = columns.fold(None, |acc, x| match acc {
+        None => Some((x, x)),
+        Some((min, _)) => Some((min, x)),
+    })?;
+    Some(max - min + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, imageops};
+
+    /// 上宽下窄的“人形”：宽肩膀、窄脚
+    fn figure() -> RgbaImage {
+        RgbaImage::from_fn(32, 40, |x, y| {
+            let half = if y < 20 { 12 } else { 3 };
+            if (16 - half..16 + half).contains(&x) {
+                Rgba([200, 100, 50, 255])
+            } else {
+                Rgba([0, 0, 0, 0])
+            }
+        })
+    }
+
+    #[test]
+    fn test_guess_detects_flipped_library() {
+        let upright = figure();
+        let flipped = imageops::flip_vertical(&upright);
+
+        let mut guess = OrientationGuess::default();
+        for _ in 0..MIN_VOTES {
+            guess.add(&upright);
+        }
+        assert_eq!(guess.upright, MIN_VOTES);
+        assert!(!guess.likely_flipped());
+
+        let mut guess = OrientationGuess::default();
+        for _ in 0..MIN_VOTES {
+            guess.add(&flipped);
+        }
+        guess.add(&RgbaImage::new(4, 4));
+        assert_eq!(guess.flipped, MIN_VOTES);
+        assert_eq!(guess.undecided, 1);
+        assert!(guess.likely_flipped());
+    }
+}
@@ -3,13 +3,18 @@
 /// RGBA 颜色结构
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Color {
+    /// Alpha 分量
     pub a: u8,
+    /// 红色分量
     pub r: u8,
+    /// 绿色分量
     pub g: u8,
+    /// 蓝色分量
     pub b: u8,
 }
 
 impl Color {
+    /// 由 ARGB 分量创建颜色
     pub const fn new(a: u8, r: u8, g: u8, b: u8) -> Self {
         Self { a, r, g, b }
     }
@@ -165,6 +170,7 @@ pub struct BrightnessSortedPalette {
 }
 
 impl BrightnessSortedPalette {
+    /// 按亮度排序标准调色板
     pub fn new() -> Self {
         let mut indices: [usize; 256] = [0; 256];
         for i in 0..256 {
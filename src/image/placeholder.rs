@@ -0,0 +1,141 @@
+//! 空帧占位图策略
+//!
+//! 当某一帧没有可显示的图像（空帧、尺寸过小等）时，由加载器统一按策略生成占位图，
+//! 保证 CLI 导出和 GUI 显示的结果一致。两端默认都用 [`PlaceholderPolicy::default`]
+//! （棋盘格）；新建的加载器使用进程内的默认策略，命令行的 `--placeholder` 用
+//! [`set_loader_default`] 修改。
+
+use image::{Rgba, RgbaImage};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// 占位图边长（与预览图尺寸一致）
+pub const PLACEHOLDER_SIZE: u32 = 64;
+
+/// 棋盘格单元大小
+const CHECKER_CELL: u32 = 8;
+
+/// 新建的加载器使用的占位策略（序号）
+static LOADER_DEFAULT: AtomicU8 = AtomicU8::new(PlaceholderPolicy::Checker.to_index());
+
+/// 新建的加载器使用的占位策略
+pub fn loader_default() -> PlaceholderPolicy {
+    PlaceholderPolicy::from_index(LOADER_DEFAULT.load(Ordering::Relaxed))
+}
+
+/// 设置此后新建的加载器使用的占位策略，已打开的库不受影响
+pub fn set_loader_default(policy: PlaceholderPolicy) {
+    LOADER_DEFAULT.store(policy.to_index(), Ordering::Relaxed);
+}
+
+/// 空帧占位策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PlaceholderPolicy {
+    /// 不生成占位图，保持返回 `None`
+    Empty,
+    /// 全透明图像
+    Transparent,
+    /// 灰白棋盘格（GUI 和命令行的默认策略）
+    #[default]
+    Checker,
+    /// 带红色叉号的“缺失”标记
+    Missing,
+}
+
+impl PlaceholderPolicy {
+    /// 全部策略（按设置界面中的顺序）
+    pub const ALL: [PlaceholderPolicy; 4] = [
+        PlaceholderPolicy::Empty,
+        PlaceholderPolicy::Transparent,
+        PlaceholderPolicy::Checker,
+        PlaceholderPolicy::Missing,
+    ];
+
+    /// 生成占位图，`Empty` 策略返回 `None`
+    pub fn render(self) -> Option<RgbaImage> {
+        let size = PLACEHOLDER_SIZE;
+        match self {
+            PlaceholderPolicy::Empty => None,
+            PlaceholderPolicy::Transparent => Some(RgbaImage::new(size, size)),
+            PlaceholderPolicy::Checker => Some(RgbaImage::from_fn(size, size, |x, y| {
+                if ((x / CHECKER_CELL) + (y / CHECKER_CELL)).is_multiple_of(2) {
+                    Rgba([204, 204, 204, 255])
+                } else {
+                    Rgba([255, 255, 255, 255])
+                }
+            })),
+            PlaceholderPolicy::Missing => Some(RgbaImage::from_fn(size, size, |x, y| {
+                let on_border = x == 0 || y == 0 || x == size - 1 || y == size - 1;
+                let on_cross = x.abs_diff(y) <= 1 || (x + y).abs_diff(size - 1) <= 1;
+                if on_border || on_cross {
+                    Rgba([220, 40, 40, 255])
+                } else {
+                    Rgba([48, 48, 48, 255])
+                }
+            })),
+        }
+    }
+
+    /// 转换为设置中保存的序号
+    pub const fn to_index(self) -> u8 {
+        match self {
+            PlaceholderPolicy::Empty => 0,
+            PlaceholderPolicy::Transparent => 1,
+            PlaceholderPolicy::Checker => 2,
+            PlaceholderPolicy::Missing => 3,
+        }
+    }
+
+    /// 从设置中保存的序号还原，越界时回退为默认策略
+    pub fn from_index(index: u8) -> Self {
+        Self::ALL
+            .get(index as usize)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// 获取命令行中使用的名称
+    pub fn name(self) -> &'static str {
+        match self {
+            PlaceholderPolicy::Empty => "none",
+            PlaceholderPolicy::Transparent => "transparent",
+            PlaceholderPolicy::Checker => "checker",
+            PlaceholderPolicy::Missing => "missing",
+        }
+    }
+}
+
+impl FromStr for PlaceholderPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|p| p.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("未知的占位策略: {}", s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_sizes() {
+        assert!(PlaceholderPolicy::Empty.render().is_none());
+        for policy in &PlaceholderPolicy::ALL[1..] {
+            let img = policy.render().unwrap();
+            assert_eq!(img.dimensions(), (PLACEHOLDER_SIZE, PLACEHOLDER_SIZE));
+        }
+    }
+
+    #[test]
+    fn test_parse_and_index_roundtrip() {
+        for policy in PlaceholderPolicy::ALL {
+            assert_eq!(policy.name().parse::<PlaceholderPolicy>(), Ok(policy));
+            assert_eq!(PlaceholderPolicy::from_index(policy.to_index()), policy);
+        }
+        assert!("bogus".parse::<PlaceholderPolicy>().is_err());
+        assert_eq!(loader_default(), PlaceholderPolicy::default());
+    }
+}
@@ -0,0 +1,106 @@
+//! 按游戏规则摆放的预览
+//!
+//! 客户端以角色所在地图格的左上角为绘制原点，再加上帧的 x/y 偏移绘制精灵。
+//! 这里在 48×32 的地图格网格上用同样的方式摆放帧，并标出所在格和原点，
+//! 不进游戏即可检查偏移是否对齐。
+
+use crate::image::video::PlacedFrame;
+use image::{Rgba, RgbaImage};
+
+/// 地图格宽度（像素）
+pub const CELL_WIDTH: u32 = 48;
+/// 地图格高度（像素）
+pub const CELL_HEIGHT: u32 = 32;
+
+/// 网格线颜色
+const GRID_COLOR: Rgba<u8> = Rgba([128, 128, 128, 160]);
+/// 角色所在格的填充色
+const CELL_COLOR: Rgba<u8> = Rgba([255, 200, 0, 48]);
+/// 原点标记颜色
+const ANCHOR_COLOR: Rgba<u8> = Rgba([255, 0, 0, 255]);
+/// 原点十字标记的半径
+const ANCHOR_RADIUS: i32 = 4;
+
+/// 画布至少覆盖的格数：所在格左右各 3 格，上方 4 格，下方 2 格
+///
+/// 大多数帧的画布尺寸因此相同，逐帧翻看时原点位置保持不动。
+const MARGIN_CELLS: (i32, i32, i32, i32) = (3, 4, 3, 2);
+
+/// 在地图格网格上按偏移摆放帧，返回透明底的画布；空帧只绘制网格和原点
+///
+/// 画布对齐到格子边界，至少覆盖所在格周围的若干格，精灵超出时向外扩展。
+pub fn render(frame: Option<&PlacedFrame>) -> RgbaImage {
+    let (cw, ch) = (CELL_WIDTH as i32, CELL_HEIGHT as i32);
+    let (margin_left, margin_top, margin_right, margin_bottom) = MARGIN_CELLS;
+
+    // 画布范围，以所在格左上角（绘制原点）为坐标原点
+    let mut left = -margin_left * cw;
+    let mut top = -margin_top * ch;
+    let mut right = (margin_right + 1) * cw;
+    let mut bottom = (margin_bottom + 1) * ch;
+    if let Some((sprite, (x, y))) = frame {
+        let (w, h) = (sprite.width() as i32, sprite.height() as i32);
+        left = left.min(x.div_euclid(cw) * cw);
+        top = top.min(y.div_euclid(ch) * ch);
+        right = right.max((x + w + cw - 1).div_euclid(cw) * cw);
+        bottom = bottom.max((y + h + ch - 1).div_euclid(ch) * ch);
+    }
+
+    let mut canvas = RgbaImage::from_fn((right - left) as u32, (bottom - top) as u32, |px, py| {
+        let (x, y) = (px as i32 + left, py as i32 + top);
+        if x.rem_euclid(cw) == 0 || y.rem_euclid(ch) == 0 {
+            GRID_COLOR
+        } else if (0..cw).contains(&x) && (0..ch).contains(&y) {
+            CELL_COLOR
+        } else {
+            Rgba([0, 0, 0, 0])
+        }
+    });
+
+    if let Some((sprite, (x, y))) = frame {
+        image::imageops::overlay(&mut canvas, &**sprite, (x - left) as i64, (y - top) as i64);
+    }
+
+    // 原点十字标记画在精灵上方
+    let (ox, oy) = (-left, -top);
+    for d in -ANCHOR_RADIUS..=ANCHOR_RADIUS {
+        for (x, y) in [(ox + d, oy), (ox, oy + d)] {
+            if x >= 0 && y >= 0 && (x as u32) < canvas.width() && (y as u32) < canvas.height() {
+                canvas.put_pixel(x as u32, y as u32, ANCHOR_COLOR);
+            }
+        }
+    }
+    canvas
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_render_places_sprite_by_offset() {
+        // 默认画布：7 格宽、7 格高，原点在 (144, 128)
+        let empty = render(None);
+        assert_eq!(empty.dimensions(), (7 * 48, 7 * 32));
+        assert_eq!(*empty.get_pixel(144, 128), ANCHOR_COLOR);
+        assert_eq!(*empty.get_pixel(150, 140), CELL_COLOR);
+        assert_eq!(*empty.get_pixel(48, 10), GRID_COLOR);
+        assert_eq!(empty.get_pixel(50, 10)[3], 0);
+
+        let sprite = RgbaImage::from_pixel(10, 20, Rgba([0, 0, 255, 255]));
+        let canvas = render(Some(&(Arc::new(sprite.clone()), (-5, -60))));
+        assert_eq!(canvas.dimensions(), empty.dimensions());
+        assert_eq!(
+            canvas.get_pixel(144 - 5 + 1, 128 - 60 + 1).0,
+            [0, 0, 255, 255]
+        );
+        assert_eq!(canvas.get_pixel(144 - 6, 128 - 60 + 1)[3], 0);
+
+        // 超出默认范围时向外扩展到格子边界，原点随之移动
+        let canvas = render(Some(&(Arc::new(sprite), (-200, 0))));
+        assert_eq!(canvas.dimensions(), (5 * 48 + 4 * 48, 7 * 32));
+        assert_eq!(*canvas.get_pixel(240, 128), ANCHOR_COLOR);
+        assert_eq!(canvas.get_pixel(41, 129).0, [0, 0, 255, 255]);
+    }
+}
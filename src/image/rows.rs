@@ -0,0 +1,145 @@
+//! 像素行的对齐和行序
+//!
+//! WIL / WZL 的像素数据按 DIB 的规则逐行存储，每行补齐到 4 字节：8 位帧每像素 1 字节，
+//! 16 位帧每像素 2 字节，WZL 的 alpha 平面每像素 4 位。以往各解码器、编码器各自计算
+//! 行跨度，有的补齐、有的按紧密排列算，宽度不是 4 的倍数时图像逐行错位。行跨度和每行
+//! 像素数据的位置统一由 [`RowLayout`] 给出。
+//!
+//! 行序同样因格式而异：WIL、WZL 和 WTL 沿用 DIB 的自下而上，.Lib 和传奇3 的 RLE 自上
+//! 而下。解码后的图像一律自上而下，各格式的编解码器声明自己的 [`RowOrder`]，读写时
+//! 统一用它换算行号，格式之间转换时不会上下颠倒。
+
+use std::ops::Range;
+
+/// 行跨度的对齐字节数
+pub const ROW_ALIGN: usize = 4;
+
+/// 像素行在文件中的存储顺序
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RowOrder {
+    /// 第一行是图像顶行（与解码后的图像相同）
+    TopDown,
+    /// 第一行是图像底行
+    BottomUp,
+}
+
+impl RowOrder {
+    /// 存储的第 `row` 行对应的图像行（自上而下数）；换算是对称的，同样可以由图像行
+    /// 得到存储行
+    pub fn map(self, row: u32, height: u32) -> u32 {
+        match self {
+            RowOrder::TopDown => row,
+            RowOrder::BottomUp => height - 1 - row,
+        }
+    }
+}
+
+/// 一块按行存储、每行补齐到 [`ROW_ALIGN`] 字节的像素数据的布局
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RowLayout {
+    /// 每行像素数
+    pub width: u32,
+    /// 行数
+    pub height: u32,
+    /// 每像素的位数（4、8 或 16）
+    pub bits: u32,
+}
+
+impl RowLayout {
+    /// 创建布局
+    pub fn new(width: u32, height: u32, bits: u32) -> Self {
+        Self {
+            width,
+            height,
+            bits,
+        }
+    }
+
+    /// 每行像素数据的字节数（不含补齐）
+    pub fn row_bytes(&self) -> usize {
+        (self.width as usize * self.bits as usize).div_ceil(8)
+    }
+
+    /// 行跨度：每行像素数据加补齐的字节数
+    pub fn stride(&self) -> usize {
+        self.row_bytes().div_ceil(ROW_ALIGN) * ROW_ALIGN
+    }
+
+    /// 每行末尾补齐的字节数
+    pub fn padding(&self) -> usize {
+        self.stride() - self.row_bytes()
+    }
+
+    /// 全部行的字节数
+    pub fn len(&self) -> usize {
+        self.stride() * self.height as usize
+    }
+
+    /// 是否没有任何像素
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 第 `row` 个存储行（从数据开头数起）中像素数据的范围，不含补齐
+    pub fn row(&self, row: u32) -> Range<usize> {
+        let start = row as usize * self.stride();
+        start..start + self.row_bytes()
+    }
+
+    /// 按存储顺序逐行切出像素数据（不含补齐）
+    ///
+    /// 数据不足时最后一行截短，之后的行不再给出。
+    pub fn rows<'a>(&self, data: &'a [u8]) -> impl Iterator<Item = &'a [u8]> {
+        let layout = *self;
+        (0..self.height)
+            .map(move |row| layout.row(row))
+            .take_while(move |range| range.start < data.len())
+            .map(move |range| &data[range.start..range.end.min(data.len())])
+    }
+
+    /// 把逐行的像素数据按存储顺序拼接并补齐，行数据过长时截断、过短时补 0
+    pub fn pack<'a>(&self, rows: impl IntoIterator<Item = &'a [u8]>) -> Vec<u8> {
+        let mut data = vec![0u8; self.len()];
+        for (row, pixels) in (0..self.height).zip(rows) {
+            let range = self.row(row);
+            let len = pixels.len().min(range.len());
+            data[range.start..range.start + len].copy_from_slice(&pixels[..len]);
+        }
+        data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_row_layout() {
+        // 8 位：宽度 1-8 的行跨度
+        let strides: Vec<usize> = (1..=8)
+            .map(|width| RowLayout::new(width, 1, 8).stride())
+            .collect();
+        assert_eq!(strides, vec![4, 4, 4, 4, 8, 8, 8, 8]);
+        // 16 位：奇数宽度也按字节数补齐，而不是按像素数
+        assert_eq!(RowLayout::new(3, 1, 16).stride(), 8);
+        assert_eq!(RowLayout::new(3, 1, 16).padding(), 2);
+        // 4 位：两像素一字节，奇数宽度向上取整
+        assert_eq!(RowLayout::new(9, 1, 4).row_bytes(), 5);
+        assert_eq!(RowLayout::new(9, 1, 4).stride(), 8);
+        assert!(RowLayout::new(0, 5, 8).is_empty());
+
+        // 拼接后逐行切出得到原来的行，补齐位置为 0
+        let layout = RowLayout::new(3, 2, 8);
+        let data = layout.pack([&[1, 2, 3][..], &[4, 5, 6, 7][..]]);
+        assert_eq!(data, vec![1, 2, 3, 0, 4, 5, 6, 0]);
+        let rows: Vec<&[u8]> = layout.rows(&data).collect();
+        assert_eq!(rows, vec![&[1, 2, 3][..], &[4, 5, 6][..]]);
+        // 数据不足时截短
+        let rows: Vec<&[u8]> = layout.rows(&data[..5]).collect();
+        assert_eq!(rows, vec![&[1, 2, 3][..], &[4][..]]);
+
+        assert_eq!(RowOrder::BottomUp.map(0, 3), 2);
+        assert_eq!(RowOrder::BottomUp.map(2, 3), 0);
+        assert_eq!(RowOrder::TopDown.map(1, 3), 1);
+    }
+}
@@ -0,0 +1,115 @@
+//! 阴影渲染
+//!
+//! 按帧的 `shadow` / `shadow_x` / `shadow_y` 在精灵下方绘制阴影，
+//! 供 GUI 预览和导出共用。阴影取精灵的不透明轮廓，以半透明黑色绘制。
+
+use image::{Rgba, RgbaImage};
+
+/// 阴影颜色（半透明黑色）
+pub const SHADOW_COLOR: Rgba<u8> = Rgba([0, 0, 0, 128]);
+
+/// 平铺阴影对应的 shadow 值
+const FLAT_SHADOW: u8 = 49;
+
+/// 阴影绘制方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShadowStyle {
+    /// 不绘制阴影
+    None,
+    /// 与精灵同形的平铺阴影
+    Flat,
+    /// 压扁一半并向右倾斜的投影（游戏客户端中角色、怪物的阴影）
+    Skewed,
+}
+
+impl ShadowStyle {
+    /// 由帧的 shadow 值确定绘制方式：0 不绘制，49 平铺，其余倾斜
+    ///
+    /// V2 中表示遮罩层的最高位在读取时已去掉，这里不再处理。
+    pub fn from_shadow(shadow: u8) -> Self {
+        match shadow {
+            0 => ShadowStyle::None,
+            FLAT_SHADOW => ShadowStyle::Flat,
+            _ => ShadowStyle::Skewed,
+        }
+    }
+}
+
+/// 生成精灵的阴影轮廓
+fn silhouette(sprite: &RgbaImage, style: ShadowStyle) -> Option<RgbaImage> {
+    let (w, h) = sprite.dimensions();
+    match style {
+        ShadowStyle::None => None,
+        ShadowStyle::Flat => Some(RgbaImage::from_fn(w, h, |x, y| {
+            if sprite.get_pixel(x, y)[3] > 0 {
+                SHADOW_COLOR
+            } else {
+                Rgba([0, 0, 0, 0])
+            }
+        })),
+        ShadowStyle::Skewed => {
+            // 高度减半，底边不动，越往上越向右偏移（顶边偏移约为半个高度）
+            let out_h = h.div_ceil(2);
+            let mut out = RgbaImage::new(w + h.saturating_sub(1) / 2, out_h);
+            for (x, y, px) in sprite.enumerate_pixels() {
+                if px[3] == 0 {
+                    continue;
+                }
+                let shift = (h - 1 - y) / 2;
+                out.put_pixel(x + shift, out_h - 1 - shift, SHADOW_COLOR);
+            }
+            Some(out)
+        }
+    }
+}
+
+/// 在精灵下方绘制阴影，返回覆盖两者的画布
+///
+/// 阴影与精灵底边对齐，再按 `shift`（帧的 shadow_x / shadow_y）平移。
+/// `style` 为 `None` 时直接返回精灵副本。
+pub fn render_with_shadow(sprite: &RgbaImage, style: ShadowStyle, shift: (i32, i32)) -> RgbaImage {
+    let Some(shadow) = silhouette(sprite, style) else {
+        return sprite.clone();
+    };
+
+    // 精灵位于原点，阴影底边与精灵底边对齐
+    let shadow_x = shift.0;
+    let shadow_y = sprite.height() as i32 - shadow.height() as i32 + shift.1;
+
+    let left = shadow_x.min(0);
+    let top = shadow_y.min(0);
+    let right = (shadow_x + shadow.width() as i32).max(sprite.width() as i32);
+    let bottom = (shadow_y + shadow.height() as i32).max(sprite.height() as i32);
+
+    let mut canvas = RgbaImage::new((right - left) as u32, (bottom - top) as u32);
+    image::imageops::overlay(
+        &mut canvas,
+        &shadow,
+        (shadow_x - left) as i64,
+        (shadow_y - top) as i64,
+    );
+    image::imageops::overlay(&mut canvas, sprite, -left as i64, -top as i64);
+    canvas
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_skewed_shadow_geometry() {
+        let sprite = RgbaImage::from_pixel(2, 4, Rgba([255, 255, 255, 255]));
+
+        // 倾斜阴影：4 行压成 2 行，顶部向右偏移 1 像素
+        let out = render_with_shadow(&sprite, ShadowStyle::Skewed, (0, 0));
+        assert_eq!(out.dimensions(), (3, 4));
+        assert_eq!(*out.get_pixel(2, 2), SHADOW_COLOR);
+        assert_eq!(out.get_pixel(0, 3).0, [255, 255, 255, 255]);
+
+        // 平铺阴影向左上平移时画布向外扩展
+        let out = render_with_shadow(&sprite, ShadowStyle::Flat, (-1, -1));
+        assert_eq!(out.dimensions(), (3, 5));
+        assert_eq!(*out.get_pixel(0, 0), SHADOW_COLOR);
+        assert_eq!(ShadowStyle::from_shadow(0), ShadowStyle::None);
+    }
+}
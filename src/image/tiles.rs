@@ -0,0 +1,62 @@
+//! 地图图块拼接
+//!
+//! Tiles.wil / SmTiles.wil 中的帧是地图图块（大块 96×64、小块 48×32），游戏中
+//! 按格子紧密排列、不使用偏移。这里把一段图块按同样的方式无缝拼成网格，便于
+//! 检查图块边缘能否接上，也可以导出为一张 PNG。
+
+use image::RgbaImage;
+use std::sync::Arc;
+
+/// 未指定列数时每行的图块数
+pub const DEFAULT_COLUMNS: u32 = 8;
+
+/// 把图块按 `columns` 列紧密拼成网格，格子取最大的图块尺寸，空帧处留空
+///
+/// 图块贴在格子左上角。全部为空帧时返回 `None`。
+pub fn assemble(tiles: &[Option<Arc<RgbaImage>>], columns: u32) -> Option<RgbaImage> {
+    let (cell_w, cell_h) = tiles
+        .iter()
+        .flatten()
+        .map(|tile| tile.dimensions())
+        .reduce(|(w, h), (tw, th)| (w.max(tw), h.max(th)))?;
+    let columns = columns.clamp(1, tiles.len() as u32);
+    let rows = (tiles.len() as u32).div_ceil(columns);
+
+    let mut grid = RgbaImage::new(cell_w * columns, cell_h * rows);
+    for (i, tile) in tiles.iter().enumerate() {
+        if let Some(tile) = tile {
+            let (col, row) = (i as u32 % columns, i as u32 / columns);
+            image::imageops::replace(
+                &mut grid,
+                &**tile,
+                (col * cell_w) as i64,
+                (row * cell_h) as i64,
+            );
+        }
+    }
+    Some(grid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    #[test]
+    fn test_assemble_tiles_without_gaps() {
+        let tile = |v| Some(Arc::new(RgbaImage::from_pixel(4, 2, Rgba([v, v, v, 255]))));
+        let tiles = vec![tile(10), None, tile(30), tile(40), tile(50)];
+
+        let grid = assemble(&tiles, 2).unwrap();
+        assert_eq!(grid.dimensions(), (8, 6));
+        assert_eq!(grid.get_pixel(3, 1).0, [10, 10, 10, 255]);
+        assert_eq!(grid.get_pixel(4, 0)[3], 0);
+        assert_eq!(grid.get_pixel(0, 2).0, [30, 30, 30, 255]);
+        assert_eq!(grid.get_pixel(7, 3).0, [40, 40, 40, 255]);
+        assert_eq!(grid.get_pixel(0, 5).0, [50, 50, 50, 255]);
+
+        // 列数超过图块数时只排一行
+        assert_eq!(assemble(&tiles, 100).unwrap().dimensions(), (20, 2));
+        assert!(assemble(&[None, None], 2).is_none());
+    }
+}
@@ -0,0 +1,241 @@
+//! 视频导出（WebM / MP4）
+//!
+//! 把一段动画帧按各自的绘制偏移对齐到同一画布、铺上背景色，再交给外部的 ffmpeg
+//! 编码为短视频，便于在聊天中分享预览而不像 GIF 那样损失颜色。ffmpeg 是可选依赖：
+//! 从 `PATH` 查找，也可以用环境变量 `LIBRARY_EDITOR_FFMPEG` 指定路径；找不到时只有
+//! 视频导出不可用。
+
+use crate::error::{LibraryError, Result};
+use image::{Rgba, RgbaImage};
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+
+/// 指定 ffmpeg 路径的环境变量
+pub const FFMPEG_ENV: &str = "LIBRARY_EDITOR_FFMPEG";
+
+/// 默认帧率
+pub const DEFAULT_FPS: u32 = 10;
+
+/// 帧率范围
+pub const FPS_RANGE: std::ops::RangeInclusive<u32> = 1..=60;
+
+/// 带绘制偏移的帧图像（与库的解码缓存共享像素）
+pub type PlacedFrame = (Arc<RgbaImage>, (i32, i32));
+
+/// 视频容器格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VideoFormat {
+    /// WebM（VP9）
+    WebM,
+    /// MP4（H.264）
+    Mp4,
+}
+
+impl VideoFormat {
+    /// 按输出文件扩展名识别格式
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_lowercase();
+        match ext.as_str() {
+            "webm" => Some(VideoFormat::WebM),
+            "mp4" => Some(VideoFormat::Mp4),
+            _ => None,
+        }
+    }
+
+    /// ffmpeg 编码参数
+    fn codec_args(self) -> &'static [&'static str] {
+        match self {
+            VideoFormat::WebM => &[
+                "-c:v",
+                "libvpx-vp9",
+                "-pix_fmt",
+                "yuv420p",
+                "-crf",
+                "30",
+                "-b:v",
+                "0",
+            ],
+            VideoFormat::Mp4 => &[
+                "-c:v",
+                "libx264",
+                "-pix_fmt",
+                "yuv420p",
+                "-crf",
+                "18",
+                "-movflags",
+                "+faststart",
+            ],
+        }
+    }
+}
+
+/// 视频导出选项
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VideoOptions {
+    /// 帧率
+    pub fps: u32,
+    /// 背景色（视频不带透明通道）
+    pub background: [u8; 3],
+}
+
+impl Default for VideoOptions {
+    fn default() -> Self {
+        Self {
+            fps: DEFAULT_FPS,
+            background: [0, 0, 0],
+        }
+    }
+}
+
+/// 把各帧按绘制偏移对齐到同一画布并铺上背景色，空帧显示为纯背景
+///
+/// 画布取所有非空帧的包围盒，宽高补齐到偶数（yuv420p 要求）。全部为空帧时返回空列表。
+pub fn compose(frames: &[Option<PlacedFrame>], background: [u8; 3]) -> Vec<RgbaImage> {
+    let [r, g, b] = background;
+    place(frames, Rgba([r, g, b, 255]), 2)
+}
+
+/// 把各帧按绘制偏移对齐到同一透明画布（GIF 等带透明的格式使用），空帧为全透明
+///
+/// 画布取所有非空帧的包围盒。全部为空帧时返回空列表。
+pub fn align(frames: &[Option<PlacedFrame>]) -> Vec<RgbaImage> {
+    place(frames, Rgba([0, 0, 0, 0]), 1)
+}
+
+/// 在以 `fill` 填充的包围盒画布上按偏移放置各帧，宽高补齐到 `multiple` 的倍数
+fn place(frames: &[Option<PlacedFrame>], fill: Rgba<u8>, multiple: u32) -> Vec<RgbaImage> {
+    let bounds = frames
+        .iter()
+        .flatten()
+        .map(|(image, (x, y))| (*x, *y, x + image.width() as i32, y + image.height() as i32))
+        .reduce(|(l, t, r, b), (l2, t2, r2, b2)| (l.min(l2), t.min(t2), r.max(r2), b.max(b2)));
+    let Some((left, top, right, bottom)) = bounds else {
+        return Vec::new();
+    };
+    let width = ((right - left) as u32)
+        .next_multiple_of(multiple)
+        .max(multiple);
+    let height = ((bottom - top) as u32)
+        .next_multiple_of(multiple)
+        .max(multiple);
+
+    frames
+        .iter()
+        .map(|frame| {
+            let mut canvas = RgbaImage::from_pixel(width, height, fill);
+            if let Some((image, (x, y))) = frame {
+                image::imageops::overlay(
+                    &mut canvas,
+                    &**image,
+                    (x - left) as i64,
+                    (y - top) as i64,
+                );
+            }
+            canvas
+        })
+        .collect()
+}
+
+/// 调用 ffmpeg 把画布序列编码为视频（各帧尺寸须一致，见 [`compose`]）
+pub fn encode(frames: &[RgbaImage], path: &Path, fps: u32) -> Result<()> {
+    let format = VideoFormat::from_path(path).ok_or_else(|| {
+        LibraryError::ParseError(format!("不支持的视频格式: {:?}（可用 .webm / .mp4）", path))
+    })?;
+    let Some(first) = frames.first() else {
+        return Err(LibraryError::ParseError("没有可导出的帧".to_string()));
+    };
+
+    let ffmpeg = std::env::var_os(FFMPEG_ENV).unwrap_or_else(|| "ffmpeg".into());
+    let size = format!("{}x{}", first.width(), first.height());
+    let fps = fps.clamp(*FPS_RANGE.start(), *FPS_RANGE.end()).to_string();
+    tracing::debug!(
+        "编码视频 {:?}: {} 帧, {}, {} FPS",
+        path,
+        frames.len(),
+        size,
+        fps
+    );
+
+    let mut child = Command::new(&ffmpeg)
+        .args([
+            "-y",
+            "-loglevel",
+            "error",
+            "-f",
+            "rawvideo",
+            "-pix_fmt",
+            "rgba",
+        ])
+        .args(["-s", &size, "-r", &fps, "-i", "-"])
+        .args(format.codec_args())
+        .arg(path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| LibraryError::Encoder(format!("无法启动 ffmpeg ({:?}): {}", ffmpeg, e)))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        for frame in frames {
+            if let Err(e) = stdin.write_all(frame.as_raw()) {
+                // ffmpeg 提前退出时写入失败，错误原因在下面的输出里
+                tracing::debug!("写入 ffmpeg 失败: {}", e);
+                break;
+            }
+        }
+    }
+
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(LibraryError::Encoder(format!(
+            "ffmpeg 退出 ({}): {}",
+            output.status,
+            stderr.trim()
+        )));
+    }
+    Ok(())
+}
+
+/// 对齐帧并导出视频
+pub fn export(frames: &[Option<PlacedFrame>], path: &Path, options: VideoOptions) -> Result<usize> {
+    let canvases = compose(frames, options.background);
+    encode(&canvases, path, options.fps)?;
+    Ok(canvases.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compose_aligns_offsets() {
+        let red = RgbaImage::from_pixel(2, 2, Rgba([255, 0, 0, 255]));
+        let green = RgbaImage::from_pixel(1, 3, Rgba([0, 255, 0, 128]));
+        let frames = vec![
+            Some((Arc::new(red), (-2, 0))),
+            None,
+            Some((Arc::new(green), (1, -1))),
+        ];
+
+        let canvases = compose(&frames, [0, 0, 255]);
+        assert_eq!(canvases.len(), 3);
+        // 包围盒 3x4，宽补齐为 4
+        assert!(canvases.iter().all(|c| c.dimensions() == (4, 4)));
+        assert_eq!(canvases[0].get_pixel(0, 1).0, [255, 0, 0, 255]);
+        assert_eq!(canvases[0].get_pixel(3, 0).0, [0, 0, 255, 255]);
+        assert!(canvases[1].pixels().all(|p| p.0 == [0, 0, 255, 255]));
+        // 半透明像素与背景混合
+        let mixed = canvases[2].get_pixel(3, 0).0;
+        assert!(mixed[1] > 100 && mixed[2] > 100, "{:?}", mixed);
+
+        assert!(compose(&[None, None], [0, 0, 0]).is_empty());
+        assert_eq!(
+            VideoFormat::from_path(Path::new("clip.MP4")),
+            Some(VideoFormat::Mp4)
+        );
+        assert_eq!(VideoFormat::from_path(Path::new("clip.gif")), None);
+    }
+}
@@ -0,0 +1,49 @@
+//! mir2_library - 传奇2资源库文件的解析与编辑核心
+//!
+//! 从编辑器中拆出的库，不依赖界面，地图编辑器、服务端模拟器等工具可以直接链接，
+//! 只需关闭默认 feature，不会引入 Slint 和命令行专用的依赖（脚本引擎、下载、文件监视等）：
+//!
+//! ```toml
+//! library_editor = { path = "...", default-features = false }
+//! ```
+//!
+//! - [`formats`]：各库格式的读写，统一入口为 [`formats::LibraryLoader`]
+//! - [`image`]：像素格式转换、调色板、画布等图像处理
+//! - [`error`]：错误类型 [`LibraryError`] 与 [`Result`] 别名
+//! - `cli` feature：并行批量转换、文件监视和远程库下载，编辑器可执行文件需要
+//! - `ffi`：C 接口（`capi` feature），供 C / C++ / C# 工具调用
+//! - `wasm`：浏览器接口（`wasm` feature），编译为 `wasm32-unknown-unknown`
+//!
+//! 支持的文件格式：
+//! - MLibrary V1 (.wzl/.wzx)
+//! - MLibrary V2 (.Lib)
+//! - MLibrary V0 (.wil 旧格式)
+//! - WeMade Library (.wil/.wix)
+//! - WTL Library (.wtl)
+//! - 加密 WIL (.D / 盛大 3.55+)
+//! - 传奇3 库 (.wil/.wzl Mir3 变体)
+//!
+//! ```no_run
+//! use mir2_library::formats::LibraryLoader;
+//!
+//! let (info, mut loader) = LibraryLoader::load("Data/Hum.wzl".as_ref())?;
+//! println!("{} ({}): {} 帧", info.file_name, info.format_name(), info.image_count);
+//! for frame in loader.iter_frames() {
+//!     let (index, _, image) = frame?;
+//!     image.save(format!("{:05}.png", index))?;
+//! }
+//! # Ok::<(), mir2_library::LibraryError>(())
+//! ```
+
+#![warn(missing_docs)]
+
+pub mod error;
+#[cfg(feature = "capi")]
+pub mod ffi;
+pub mod formats;
+pub mod image;
+pub mod progress;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+pub use error::{LibraryError, Result};
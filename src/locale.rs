@@ -0,0 +1,268 @@
+//! 本地化的数字格式与设置文件
+//!
+//! 报告、状态栏和导出的统计中，字节大小与帧数按设置文件中的 `number_format`
+//! 格式化（千位分隔符、小数点、大小单位）。命令行和界面读取同一个偏好设置文件，
+//! 启动时通过 [`set`] 设为当前格式。
+//!
+//! 中文等全角字符在终端中占两列，按 [`pad`] 以显示宽度补齐才能对齐表格列；
+//! CSV 按带 BOM 的 UTF-8 写出，Excel 在中文系统上才能正确显示中文文件名。
+
+use crate::error::Result;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, RwLock};
+
+/// 偏好设置文件名（位于 [`config_dir`] 下）
+pub const PREFERENCES_FILE: &str = "preferences.json";
+
+/// UTF-8 BOM
+const UTF8_BOM: &str = "\u{feff}";
+
+/// 字节大小的单位
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum SizeUnits {
+    /// 始终以字节为单位
+    Bytes,
+    /// 1024 进制（KiB、MiB、GiB）
+    #[default]
+    Binary,
+    /// 1000 进制（KB、MB、GB）
+    Decimal,
+}
+
+/// 数字格式
+///
+/// 缺少的字段取默认值：逗号分组、小数点、1024 进制单位。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NumberFormat {
+    /// 千位分隔符（为空时不分组）
+    pub thousands_separator: String,
+    /// 小数点
+    pub decimal_separator: String,
+    /// 字节大小的单位
+    pub size_units: SizeUnits,
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        Self {
+            thousands_separator: ",".to_string(),
+            decimal_separator: ".".to_string(),
+            size_units: SizeUnits::default(),
+        }
+    }
+}
+
+impl NumberFormat {
+    /// 整数（按千位分组）
+    pub fn count(&self, value: u64) -> String {
+        let digits = value.to_string();
+        let mut text = String::with_capacity(digits.len() * 2);
+        for (i, digit) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i).is_multiple_of(3) {
+                text.push_str(&self.thousands_separator);
+            }
+            text.push(digit);
+        }
+        text
+    }
+
+    /// 字节大小：不足一个单位时显示字节数，否则保留一位小数
+    pub fn size(&self, bytes: u64) -> String {
+        let (base, units) = match self.size_units {
+            SizeUnits::Bytes => return format!("{} 字节", self.count(bytes)),
+            SizeUnits::Binary => (1024.0, ["KiB", "MiB", "GiB", "TiB"]),
+            SizeUnits::Decimal => (1000.0, ["KB", "MB", "GB", "TB"]),
+        };
+        if (bytes as f64) < base {
+            return format!("{} 字节", bytes);
+        }
+        let mut value = bytes as f64 / base;
+        let mut unit = 0;
+        while value >= base && unit < units.len() - 1 {
+            value /= base;
+            unit += 1;
+        }
+        let tenths = (value * 10.0).round() as u64;
+        format!(
+            "{}{}{} {}",
+            self.count(tenths / 10),
+            self.decimal_separator,
+            tenths % 10,
+            units[unit]
+        )
+    }
+}
+
+/// 设置文件中与数字格式相关的部分（其余字段由界面的偏好设置读取）
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Settings {
+    number_format: NumberFormat,
+}
+
+/// 从设置文件读取数字格式，不存在或损坏时返回默认值
+pub fn load(path: &Path) -> NumberFormat {
+    std::fs::read(path)
+        .ok()
+        .and_then(|data| serde_json::from_slice::<Settings>(&data).ok())
+        .map(|settings| settings.number_format)
+        .unwrap_or_default()
+}
+
+/// 当前的数字格式
+static CURRENT: LazyLock<RwLock<NumberFormat>> = LazyLock::new(Default::default);
+
+/// 设置当前的数字格式
+pub fn set(format: NumberFormat) {
+    *CURRENT.write().unwrap() = format;
+}
+
+/// 按当前格式显示整数
+pub fn count(value: u64) -> String {
+    CURRENT.read().unwrap().count(value)
+}
+
+/// 按当前格式显示字节大小
+pub fn size(bytes: u64) -> String {
+    CURRENT.read().unwrap().size(bytes)
+}
+
+/// 字符在终端中占的列数（中日韩文字和全角符号占两列）
+fn char_width(c: char) -> usize {
+    match c as u32 {
+        0x1100..=0x115F
+        | 0x2E80..=0x303E
+        | 0x3041..=0x33FF
+        | 0x3400..=0x4DBF
+        | 0x4E00..=0x9FFF
+        | 0xA000..=0xA4CF
+        | 0xAC00..=0xD7A3
+        | 0xF900..=0xFAFF
+        | 0xFE30..=0xFE4F
+        | 0xFF00..=0xFF60
+        | 0xFFE0..=0xFFE6
+        | 0x20000..=0x3FFFD => 2,
+        _ => 1,
+    }
+}
+
+/// 文本在终端中的显示宽度
+pub fn display_width(text: &str) -> usize {
+    text.chars().map(char_width).sum()
+}
+
+/// 在文本后补空格，使显示宽度达到 `width`
+pub fn pad(text: &str, width: usize) -> String {
+    let fill = width.saturating_sub(display_width(text));
+    format!("{}{}", text, " ".repeat(fill))
+}
+
+/// CSV 字段：包含分隔符、引号或换行时加引号
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+/// 写出 CSV（带 BOM 的 UTF-8，CRLF 换行）
+///
+/// 数字按原样写出，不使用千位分隔符，便于表格软件识别。
+pub fn write_csv(path: &Path, rows: &[Vec<String>]) -> Result<()> {
+    let mut text = String::from(UTF8_BOM);
+    for row in rows {
+        let fields: Vec<String> = row.iter().map(|field| csv_field(field)).collect();
+        text.push_str(&fields.join(","));
+        text.push_str("\r\n");
+    }
+    std::fs::write(path, text)?;
+    Ok(())
+}
+
+/// 用户配置目录
+///
+/// Windows 下为 `%APPDATA%\library_editor`，其他平台为
+/// `$XDG_CONFIG_HOME/library_editor` 或 `~/.config/library_editor`；
+/// 都取不到时退回当前目录下的 `config`。
+pub fn config_dir() -> PathBuf {
+    let env_dir = |name: &str| {
+        std::env::var_os(name)
+            .map(PathBuf::from)
+            .filter(|dir| dir.is_absolute())
+    };
+    let base = if cfg!(windows) {
+        env_dir("APPDATA")
+    } else {
+        env_dir("XDG_CONFIG_HOME").or_else(|| env_dir("HOME").map(|home| home.join(".config")))
+    };
+    match base {
+        Some(base) => base.join("library_editor"),
+        None => PathBuf::from("./config"),
+    }
+}
+
+/// 偏好设置文件路径（命令行和界面共用）
+pub fn settings_path() -> PathBuf {
+    config_dir().join(PREFERENCES_FILE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_number_format_and_width() {
+        let format = NumberFormat::default();
+        assert_eq!(format.count(0), "0");
+        assert_eq!(format.count(1234567), "1,234,567");
+        assert_eq!(format.size(512), "512 字节");
+        assert_eq!(format.size(1536), "1.5 KiB");
+        assert_eq!(format.size(5 << 50), "5,120.0 TiB");
+
+        let german = NumberFormat {
+            thousands_separator: ".".to_string(),
+            decimal_separator: ",".to_string(),
+            size_units: SizeUnits::Decimal,
+        };
+        assert_eq!(german.size(2_345_678), "2,3 MB");
+        let bytes = NumberFormat {
+            size_units: SizeUnits::Bytes,
+            ..german
+        };
+        assert_eq!(bytes.size(2_345_678), "2.345.678 字节");
+
+        // 设置文件中只写了部分字段
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let path = dir.join(PREFERENCES_FILE);
+        std::fs::write(
+            &path,
+            br#"{"thumbnail_size": 96, "number_format": {"thousands_separator": " "}}"#,
+        )
+        .unwrap();
+        let loaded = load(&path);
+        assert_eq!(loaded.count(12345), "12 345");
+        assert_eq!(loaded.size_units, SizeUnits::Binary);
+
+        assert_eq!(display_width("Hum.wil"), 7);
+        assert_eq!(display_width("怪物.wil"), 8);
+        assert_eq!(pad("怪物", 6), "怪物  ");
+
+        let csv = dir.join("report.csv");
+        write_csv(
+            &csv,
+            &[
+                vec!["文件".to_string(), "问题".to_string()],
+                vec!["怪物, 1.wil".to_string(), "第 1 帧\"截断\"".to_string()],
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&csv).unwrap(),
+            "\u{feff}文件,问题\r\n\"怪物, 1.wil\",\"第 1 帧\"\"截断\"\"\"\r\n"
+        );
+    }
+}
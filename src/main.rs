@@ -1,31 +1,69 @@
 //! Library Editor - 传奇2库文件编辑器 (Rust版本)
 //!
-//! 这是一个用于编辑传奇2游戏资源库文件的跨平台应用程序。
+//! 这是一个用于编辑传奇2游戏资源库文件的跨平台应用程序，
+//! 格式解析与图像处理由 `mir2_library` 库提供，这里只有界面和命令行。
 //! 支持的文件格式：
 //! - MLibrary V1 (.wzl/.wzx)
 //! - MLibrary V2 (.Lib)
 //! - MLibrary V0 (.wil 旧格式)
 //! - WeMade Library (.wil/.wix)
 //! - WTL Library (.wtl)
+//! - 加密 WIL (.D / 盛大 3.55+)
+//! - 传奇3 库 (.wil/.wzl Mir3 变体)
 
 #![warn(missing_docs)]
 #![allow(dead_code)]
 
-mod error;
-mod formats;
+mod actions;
+mod associate;
+mod commands;
 #[cfg(feature = "gui")]
 mod gui;
-mod image;
+mod i18n;
+mod locale;
+mod progress_bar;
+mod script;
+mod serve;
+
+// 核心模块来自 mir2_library，在此引入后 `crate::formats` 等路径保持不变
+use mir2_library::error;
+#[cfg(feature = "gui")]
+use mir2_library::{formats, image, progress};
 
 use error::Result;
-use tracing::{Level, info};
+use i18n::tr;
+#[cfg(feature = "gui")]
+use std::path::PathBuf;
+use std::process::ExitCode;
+use tracing::Level;
 use tracing_appender::rolling;
-use tracing_subscriber::{Registry, fmt, layer::SubscriberExt, util::SubscriberInitExt};
+use tracing_subscriber::filter::{LevelFilter, Targets};
+use tracing_subscriber::{Layer, Registry, fmt, layer::SubscriberExt, util::SubscriberInitExt};
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        // 退出码按错误类别区分，见 `ErrorCode`
+        Err(e) => {
+            let code = e.code();
+            eprintln!("{}", tr!("错误（{}）: {}", code.name(), e));
+            ExitCode::from(code.exit_code())
+        }
+    }
+}
 
-fn main() -> Result<()> {
+/// 按命令行参数进入界面或命令行模式
+fn run() -> Result<()> {
     // 解析命令行参数
     let args: Vec<String> = std::env::args().collect();
 
+    // 界面和命令行的语言：--lang、偏好设置、系统语言
+    i18n::init(
+        commands::option_value(&args, "--lang"),
+        &locale::settings_path(),
+    )
+    .map_err(error::LibraryError::ParseError)?;
+
     // 检查是否有 --no-gui 或 --cli 参数（强制使用 CLI 模式）
     let no_gui = args.iter().any(|a| a == "--no-gui" || a == "--cli");
 
@@ -33,7 +71,13 @@ fn main() -> Result<()> {
     if !no_gui {
         #[cfg(feature = "gui")]
         {
-            return gui::run();
+            // 命令行或资源管理器“打开方式”传入的文件在界面启动后直接打开
+            let open = match commands::positional_args(&args).as_slice() {
+                [path] => Some(PathBuf::from(path)),
+                _ => None,
+            };
+            // --bench-gui 为隐藏的界面性能基准模式
+            return gui::run(args.iter().any(|a| a == "--bench-gui"), open);
         }
 
         #[cfg(not(feature = "gui"))]
@@ -43,11 +87,14 @@ fn main() -> Result<()> {
         }
     }
 
-    run_cli(args)
+    commands::run_cli(args)
 }
 
 /// 初始化日志系统 - 同时输出到控制台和文件
-fn init_logging() {
+///
+/// `--quiet` 时控制台只输出警告、错误和命令结果，`--verbose` 时输出调试信息；
+/// 日志文件不受影响。
+fn init_logging(quiet: bool, verbose: bool) {
     // 创建日志文件目录（在当前目录下的 logs 文件夹）
     let file_appender = rolling::daily("./logs", "library-editor.log");
 
@@ -56,74 +103,46 @@ fn init_logging() {
     let log_level = Level::DEBUG;
     #[cfg(not(debug_assertions))]
     let log_level = Level::INFO;
+    let console_level = if quiet {
+        Level::WARN
+    } else if verbose {
+        Level::DEBUG
+    } else {
+        log_level
+    };
 
     // 配置日志输出层
     let file_layer = fmt::layer()
         .with_writer(file_appender)
         .with_ansi(false)
         .with_level(true)
-        .with_target(true);
+        .with_target(true)
+        .with_filter(LevelFilter::from_level(log_level));
 
     let console_layer = fmt::layer()
         .with_writer(std::io::stdout)
         .with_ansi(true)
         .with_level(true)
-        .with_target(false);
+        .with_target(false)
+        .with_filter(console_filter(console_level));
 
     // 组合订阅器，同时输出到控制台和文件
     Registry::default()
         .with(file_layer)
         .with(console_layer)
         .with(
-            tracing_subscriber::filter::Targets::new()
-                .with_target("library_editor", log_level)
-                .with_default(log_level),
+            Targets::new()
+                .with_target("library_editor", log_level.max(console_level))
+                .with_default(log_level.max(console_level)),
         )
         .init();
 }
 
-/// 运行 CLI 模式
-fn run_cli(args: Vec<String>) -> Result<()> {
-    // 初始化日志 - 同时输出到控制台和文件
-    init_logging();
-
-    info!("Library Editor CLI 模式启动中...");
-    info!("支持格式: MLibrary V1/V2, WeMade, WTL");
-
-    info!("");
-    info!("使用方法:");
-    info!("  library_editor.exe [选项] <文件路径>");
-    info!("");
-    info!("选项:");
-    info!("  --no-gui, --cli    强制使用 CLI 模式 (当前默认为 GUI)");
-    info!("  --help, -h         显示帮助信息");
-    info!("");
-    info!("支持格式:");
-    info!("  - .wzl/.wzx (MLibrary V1)");
-    info!("  - .Lib (MLibrary V2)");
-    info!("  - .wil/.wix (WeMade Library)");
-    info!("  - .wtl (WTL Library)");
-    info!("");
-    info!("注意: 程序默认使用 GUI 模式");
-    info!("      (gui feature 当前已默认启用)");
-
-    // 显示传入的文件参数
-    if args.len() > 1 {
-        let file_args: Vec<_> = args
-            .iter()
-            .filter(|a| !a.starts_with("--") && !a.starts_with('-'))
-            .collect();
-
-        if !file_args.is_empty() {
-            info!("");
-            info!("传入的文件:");
-            for file in file_args {
-                info!("  - {}", file);
-            }
-        }
-    }
-
-    Ok(())
+/// 控制台的过滤：命令结果（[`commands::RESULT_TARGET`]）总是输出，其余按 `level`
+fn console_filter(level: Level) -> Targets {
+    Targets::new()
+        .with_target(commands::RESULT_TARGET, Level::INFO.max(level))
+        .with_default(level)
 }
 
 /// 应用程序名称
@@ -143,4 +162,16 @@ mod tests {
     fn test_app_info() {
         assert_eq!(APP_NAME, "Library Editor");
     }
+
+    #[test]
+    fn test_quiet_console_keeps_results() {
+        // --quiet 时控制台为 WARN
+        let quiet = console_filter(Level::WARN);
+        assert!(quiet.would_enable(commands::RESULT_TARGET, &Level::INFO));
+        assert!(!quiet.would_enable("library_editor::commands", &Level::INFO));
+        assert!(quiet.would_enable("library_editor::commands", &Level::WARN));
+
+        let verbose = console_filter(Level::DEBUG);
+        assert!(verbose.would_enable("library_editor::commands", &Level::DEBUG));
+    }
 }
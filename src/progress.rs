@@ -0,0 +1,275 @@
+//! 加载进度事件
+//!
+//! 帧加载方（GUI 缩略图加载、CLI 批处理）通过通道发送 `ProgressEvent`，
+//! 消费方（界面状态栏、命令行进度条）用 `ProgressTracker` 汇总，
+//! 发送端可以位于任意工作线程，不再依赖共享计数器轮询。
+//!
+//! 打开库本身的进度（读取索引、调色板、解码帧）以 [`Progress`] 回调报告，
+//! 见 `LibraryLoader::load_with_progress`；[`CancelToken`] 用于中止加载。
+
+use crate::error::{LibraryError, Result};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::formats::{LibraryInfo, LibraryLoader};
+use crossbeam_channel::{Receiver, Sender};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// 加载进度事件
+#[derive(Debug)]
+pub enum ProgressEvent {
+    /// 指定索引的帧已加载
+    FrameLoaded(usize),
+    /// 指定索引的帧加载失败
+    Error(usize, LibraryError),
+    /// 本轮加载结束
+    Done,
+}
+
+/// 打开库的进度
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Progress {
+    /// 已读取索引，得到帧数
+    IndexRead {
+        /// 帧数
+        frames: usize,
+    },
+    /// 已读取调色板（8 位调色板格式）
+    PaletteRead,
+    /// 已解码若干帧（导出、建立索引等逐帧处理时）
+    FrameDecoded {
+        /// 已解码帧数
+        done: usize,
+        /// 总帧数
+        total: usize,
+    },
+}
+
+/// 取消标记
+///
+/// 克隆后共享同一个标记：界面线程调用 [`cancel`](Self::cancel)，工作线程在
+/// 每帧之间调用 [`check`](Self::check)。
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    /// 创建未取消的标记
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 请求取消
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// 是否已请求取消
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// 已请求取消时返回 [`LibraryError::Cancelled`]
+    pub fn check(&self) -> Result<()> {
+        if self.is_cancelled() {
+            return Err(LibraryError::Cancelled);
+        }
+        Ok(())
+    }
+}
+
+/// 后台加载的句柄，见 `LibraryLoader::spawn_load`
+#[cfg(not(target_arch = "wasm32"))]
+pub struct LoadHandle {
+    /// 取消标记
+    cancel: CancelToken,
+    /// 加载线程
+    thread: std::thread::JoinHandle<Result<(LibraryInfo, LibraryLoader)>>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl LoadHandle {
+    /// 在后台线程中执行加载
+    pub(crate) fn spawn(
+        cancel: CancelToken,
+        load: impl FnOnce() -> Result<(LibraryInfo, LibraryLoader)> + Send + 'static,
+    ) -> Self {
+        Self {
+            cancel,
+            thread: std::thread::spawn(load),
+        }
+    }
+
+    /// 请求中止加载，加载线程在下一帧之前结束并返回 [`LibraryError::Cancelled`]
+    pub fn cancel(&self) {
+        self.cancel.cancel();
+    }
+
+    /// 取消标记（可交给其他线程）
+    pub fn cancel_token(&self) -> CancelToken {
+        self.cancel.clone()
+    }
+
+    /// 加载是否已结束（成功、失败或已取消）
+    pub fn is_finished(&self) -> bool {
+        self.thread.is_finished()
+    }
+
+    /// 等待加载结束并取得结果
+    pub fn join(self) -> Result<(LibraryInfo, LibraryLoader)> {
+        self.thread
+            .join()
+            .unwrap_or_else(|_| Err(LibraryError::ParseError("加载线程异常退出".to_string())))
+    }
+}
+
+/// 进度事件发送端
+pub type ProgressSender = Sender<ProgressEvent>;
+
+/// 进度事件接收端
+pub type ProgressReceiver = Receiver<ProgressEvent>;
+
+/// 创建进度事件通道（无界，发送方不会因消费方繁忙而阻塞）
+pub fn channel() -> (ProgressSender, ProgressReceiver) {
+    crossbeam_channel::unbounded()
+}
+
+/// 进度汇总器
+///
+/// 同一帧重复加载（如缓存淘汰后重新加载）只计一次。
+#[derive(Debug, Clone)]
+pub struct ProgressTracker {
+    /// 每一帧是否已加载
+    loaded: Vec<bool>,
+    /// 每一帧最近一次加载是否失败
+    failed: Vec<bool>,
+    /// 已加载帧数
+    loaded_count: usize,
+    /// 失败次数
+    error_count: usize,
+    /// 是否收到 `Done`
+    done: bool,
+}
+
+impl ProgressTracker {
+    /// 创建指定总帧数的汇总器
+    pub fn new(total: usize) -> Self {
+        Self {
+            loaded: vec![false; total],
+            failed: vec![false; total],
+            loaded_count: 0,
+            error_count: 0,
+            done: false,
+        }
+    }
+
+    /// 处理一个事件
+    pub fn apply(&mut self, event: &ProgressEvent) {
+        match event {
+            ProgressEvent::FrameLoaded(index) => {
+                if let Some(flag) = self.loaded.get_mut(*index)
+                    && !*flag
+                {
+                    *flag = true;
+                    self.loaded_count += 1;
+                }
+                if let Some(flag) = self.failed.get_mut(*index) {
+                    *flag = false;
+                }
+                self.done = false;
+            }
+            ProgressEvent::Error(index, err) => {
+                tracing::warn!(frame = *index, "加载帧 {} 失败: {}", index, err);
+                self.error_count += 1;
+                if let Some(flag) = self.failed.get_mut(*index) {
+                    *flag = true;
+                }
+                self.done = false;
+            }
+            ProgressEvent::Done => self.done = true,
+        }
+    }
+
+    /// 总帧数
+    pub fn total(&self) -> usize {
+        self.loaded.len()
+    }
+
+    /// 已加载帧数
+    pub fn loaded(&self) -> usize {
+        self.loaded_count
+    }
+
+    /// 失败次数
+    pub fn errors(&self) -> usize {
+        self.error_count
+    }
+
+    /// 指定帧是否已加载过
+    pub fn is_loaded(&self, index: usize) -> bool {
+        self.loaded.get(index).copied().unwrap_or(false)
+    }
+
+    /// 指定帧最近一次加载是否失败（数据损坏或无法解码）
+    pub fn is_failed(&self, index: usize) -> bool {
+        self.failed.get(index).copied().unwrap_or(false)
+    }
+
+    /// 本轮加载是否已结束
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    /// 加载百分比（0-100）
+    pub fn percent(&self) -> u32 {
+        match self.total() {
+            0 => 100,
+            total => (self.loaded_count * 100 / total) as u32,
+        }
+    }
+
+    /// 生成文本进度条，如 `[#####-----] 50/100`
+    pub fn bar(&self, width: usize) -> String {
+        let filled = match self.total() {
+            0 => width,
+            total => self.loaded_count * width / total,
+        };
+        format!(
+            "[{}{}] {}/{}",
+            "#".repeat(filled),
+            "-".repeat(width - filled),
+            self.loaded_count,
+            self.total()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tracker_counts_unique_frames() {
+        let (tx, rx) = channel();
+        let worker = std::thread::spawn(move || {
+            for i in [0, 1, 1, 3] {
+                tx.send(ProgressEvent::FrameLoaded(i)).unwrap();
+            }
+            tx.send(ProgressEvent::Error(2, LibraryError::InvalidImageData))
+                .unwrap();
+            tx.send(ProgressEvent::Done).unwrap();
+        });
+
+        let mut tracker = ProgressTracker::new(4);
+        for event in rx.iter() {
+            tracker.apply(&event);
+        }
+        worker.join().unwrap();
+
+        assert_eq!(tracker.loaded(), 3);
+        assert_eq!(tracker.errors(), 1);
+        assert_eq!(tracker.percent(), 75);
+        assert!(tracker.is_done());
+        assert_eq!(tracker.bar(4), "[###-] 3/4");
+        assert!(tracker.is_failed(2) && !tracker.is_loaded(2));
+        assert!(tracker.is_loaded(3) && !tracker.is_failed(3));
+    }
+}
@@ -0,0 +1,48 @@
+//! 命令行进度条
+//!
+//! 导出、批量转换、自检等耗时的命令在终端显示一行进度条和预计剩余时间（按当前语言
+//! 翻译），逐项的过程只写入调试日志。`--quiet` 时不显示进度条；输出不是终端
+//! （重定向到文件、在 CI 中运行）时进度条自动隐藏。
+
+use crate::i18n::tr;
+use indicatif::{ProgressBar, ProgressStyle};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// 是否隐藏进度条
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// 设置是否隐藏进度条（`--quiet`）
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+/// 共 `total` 项的进度条，`message`（已翻译）显示在进度条前
+pub fn new(total: usize, message: &str) -> ProgressBar {
+    if QUIET.load(Ordering::Relaxed) {
+        return ProgressBar::hidden();
+    }
+    // 模板中的占位符由 indicatif 填写，不经过 tr! 的参数替换
+    let style = ProgressStyle::with_template(tr!(
+        "{msg} [{bar:32}] {human_pos}/{human_len} {percent:>3}% 剩余 {eta}"
+    ))
+    .expect("进度条模板有效")
+    .progress_chars("#>-");
+    ProgressBar::new(total as u64)
+        .with_style(style)
+        .with_message(message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quiet_hides_bar() {
+        set_quiet(true);
+        let bar = new(10, "导出");
+        bar.inc(3);
+        assert!(bar.is_hidden());
+        assert_eq!(bar.position(), 3);
+        set_quiet(false);
+    }
+}
@@ -0,0 +1,304 @@
+//! 脚本（`script` 命令）
+//!
+//! 用 [Rhai](https://rhai.rs) 脚本做一次性的批量处理，例如“把 100–200 帧换个颜色后
+//! 追加到另一个库”，不必为此改代码重新编译。脚本中可用：
+//!
+//! - `open(路径)`：打开库；`create(路径)`：新建空的 .Lib（保存时写入）
+//! - `lib.count()`、`lib.frame(i)`（空帧为 `()`）、`lib.replace(i, 帧)`、`lib.append(帧)`、
+//!   `lib.remove(i)`、`lib.shift_offsets(帧索引数组或范围, dx, dy)`、`lib.save()`、
+//!   `lib.save_as(路径)`（任意格式另存为 .Lib）
+//! - `new_frame(宽, 高)`；帧的 `width`、`height`、`x`、`y`、`get_pixel(x, y)`（`[r, g, b, a]`）、
+//!   `set_pixel(x, y, [r, g, b, a])`
+//! - `ARGS`：命令行中脚本文件之后的参数；`print` 输出到日志
+//!
+//! 修改遵守库的锁定帧、仅追加模式等规则，和在编辑器中操作一样。
+
+use mir2_library::error::{LibraryError, Result};
+use mir2_library::formats::LibraryLoader;
+use mir2_library::formats::clip::FrameClip;
+use mir2_library::formats::convert;
+use mir2_library::formats::mlibrary_v2::{EncodeOptions, MLibraryV2};
+use rhai::{Array, Dynamic, Engine, EvalAltResult, INT, Scope};
+use std::cell::RefCell;
+use std::ops::{Range, RangeInclusive};
+use std::path::Path;
+use std::rc::Rc;
+
+/// 脚本中的库
+type Library = Rc<RefCell<LibraryLoader>>;
+
+/// 脚本函数的结果
+type ScriptResult<T> = std::result::Result<T, Box<EvalAltResult>>;
+
+/// 运行脚本文件，`args` 作为 `ARGS` 传给脚本，写入的帧按 `encode` 编码
+pub fn run_file(path: &Path, args: &[&str], encode: EncodeOptions) -> Result<()> {
+    let source = std::fs::read_to_string(path)?;
+    tracing::info!("运行脚本: {:?}", path);
+    run(&source, args, encode)
+}
+
+/// 运行脚本文本
+pub fn run(source: &str, args: &[&str], encode: EncodeOptions) -> Result<()> {
+    let args: Array = args
+        .iter()
+        .map(|arg| Dynamic::from(arg.to_string()))
+        .collect();
+    let mut scope = Scope::new();
+    scope.push_constant("ARGS", args);
+    engine(encode)
+        .run_with_scope(&mut scope, source)
+        .map_err(|e| LibraryError::Script(e.to_string()))
+}
+
+/// 注册了库和帧操作的脚本引擎
+fn engine(encode: EncodeOptions) -> Engine {
+    let mut engine = Engine::new();
+    engine.on_print(|text| crate::commands::report!("{}", text));
+    engine.on_debug(|text, _, pos| tracing::debug!("{} {}", pos, text));
+
+    engine
+        .register_type_with_name::<Library>("Library")
+        .register_fn("open", |path: &str| -> ScriptResult<Library> {
+            let (_, loader) = LibraryLoader::load(Path::new(path)).map_err(script_error)?;
+            Ok(Rc::new(RefCell::new(loader)))
+        })
+        .register_fn("create", move |path: &str| -> ScriptResult<Library> {
+            let path = Path::new(path);
+            let mut library = MLibraryV2::new(path.with_extension("")).map_err(script_error)?;
+            library.images.clear();
+            library.count = 0;
+            library.wide_offsets = encode.wide_offsets;
+            library.codec = encode.codec;
+            library.level = encode.level;
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            let (_, loader) = LibraryLoader::from_v2(library, &name);
+            Ok(Rc::new(RefCell::new(loader)))
+        })
+        .register_fn("count", |lib: &mut Library| {
+            lib.borrow().image_count() as INT
+        })
+        .register_fn(
+            "frame",
+            |lib: &mut Library, index: INT| -> ScriptResult<Dynamic> {
+                let mut loader = lib.borrow_mut();
+                let index = frame_index(&loader, index)?;
+                Ok(match loader.copy_frame(index).map_err(script_error)? {
+                    Some(frame) => Dynamic::from(frame),
+                    None => Dynamic::UNIT,
+                })
+            },
+        )
+        .register_fn(
+            "replace",
+            |lib: &mut Library, index: INT, frame: FrameClip| -> ScriptResult<()> {
+                let mut loader = lib.borrow_mut();
+                let index = frame_index(&loader, index)?;
+                loader
+                    .paste_frame(&frame, Some(index))
+                    .map_err(script_error)?;
+                Ok(())
+            },
+        )
+        .register_fn(
+            "append",
+            |lib: &mut Library, frame: FrameClip| -> ScriptResult<INT> {
+                let index = lib.borrow_mut().paste_frame(&frame, None);
+                Ok(index.map_err(script_error)? as INT)
+            },
+        )
+        .register_fn(
+            "remove",
+            |lib: &mut Library, index: INT| -> ScriptResult<()> {
+                let mut loader = lib.borrow_mut();
+                let index = frame_index(&loader, index)?;
+                loader.remove_image(index).map_err(script_error)
+            },
+        )
+        .register_fn(
+            "shift_offsets",
+            |lib: &mut Library, indices: Array, dx: INT, dy: INT| -> ScriptResult<INT> {
+                let indices = indices
+                    .into_iter()
+                    .map(|index| index.as_int().map_err(|t| format!("帧索引应为整数: {}", t)))
+                    .collect::<std::result::Result<Vec<_>, _>>()?;
+                shift_offsets(lib, indices, dx, dy)
+            },
+        )
+        .register_fn(
+            "shift_offsets",
+            |lib: &mut Library, range: Range<INT>, dx: INT, dy: INT| {
+                shift_offsets(lib, range.collect(), dx, dy)
+            },
+        )
+        .register_fn(
+            "shift_offsets",
+            |lib: &mut Library, range: RangeInclusive<INT>, dx: INT, dy: INT| {
+                shift_offsets(lib, range.collect(), dx, dy)
+            },
+        )
+        .register_fn("save", |lib: &mut Library| -> ScriptResult<()> {
+            lib.borrow_mut().save().map_err(script_error)
+        })
+        .register_fn(
+            "save_as",
+            move |lib: &mut Library, path: &str| -> ScriptResult<()> {
+                convert::to_v2(&mut lib.borrow_mut(), Path::new(path), encode)
+                    .map_err(script_error)?;
+                Ok(())
+            },
+        );
+
+    engine
+        .register_type_with_name::<FrameClip>("Frame")
+        .register_fn(
+            "new_frame",
+            |width: INT, height: INT| -> ScriptResult<FrameClip> {
+                let size =
+                    |value: INT| u32::try_from(value).map_err(|_| format!("无效的尺寸: {}", value));
+                let image = ::image::RgbaImage::new(size(width)?, size(height)?);
+                Ok(FrameClip::new(image, 0, 0))
+            },
+        )
+        .register_get("width", |frame: &mut FrameClip| frame.image.width() as INT)
+        .register_get("height", |frame: &mut FrameClip| {
+            frame.image.height() as INT
+        })
+        .register_get_set(
+            "x",
+            |frame: &mut FrameClip| frame.x as INT,
+            |frame: &mut FrameClip, x: INT| frame.x = x as i16,
+        )
+        .register_get_set(
+            "y",
+            |frame: &mut FrameClip| frame.y as INT,
+            |frame: &mut FrameClip, y: INT| frame.y = y as i16,
+        )
+        .register_fn(
+            "get_pixel",
+            |frame: &mut FrameClip, x: INT, y: INT| -> ScriptResult<Array> {
+                let (x, y) = pixel_position(frame, x, y)?;
+                let pixel = frame.image.get_pixel(x, y);
+                Ok(pixel.0.iter().map(|&c| Dynamic::from(c as INT)).collect())
+            },
+        )
+        .register_fn(
+            "set_pixel",
+            |frame: &mut FrameClip, x: INT, y: INT, rgba: Array| -> ScriptResult<()> {
+                let (x, y) = pixel_position(frame, x, y)?;
+                let mut pixel = [0, 0, 0, 255];
+                if !(3..=4).contains(&rgba.len()) {
+                    return Err("颜色应为 [r, g, b] 或 [r, g, b, a]".into());
+                }
+                for (channel, value) in pixel.iter_mut().zip(rgba) {
+                    let value = value
+                        .as_int()
+                        .map_err(|t| format!("颜色分量应为整数: {}", t))?;
+                    *channel = value.clamp(0, 255) as u8;
+                }
+                frame.image.put_pixel(x, y, ::image::Rgba(pixel));
+                Ok(())
+            },
+        );
+
+    engine
+}
+
+/// 库操作的错误转为脚本错误
+fn script_error(e: LibraryError) -> Box<EvalAltResult> {
+    e.to_string().into()
+}
+
+/// 检查帧索引在库的范围内
+fn frame_index(loader: &LibraryLoader, index: INT) -> ScriptResult<usize> {
+    usize::try_from(index)
+        .ok()
+        .filter(|&index| index < loader.image_count())
+        .ok_or_else(|| {
+            format!(
+                "帧索引 {} 超出范围（共 {} 帧）",
+                index,
+                loader.image_count()
+            )
+            .into()
+        })
+}
+
+/// 检查像素坐标在帧图像内
+fn pixel_position(frame: &FrameClip, x: INT, y: INT) -> ScriptResult<(u32, u32)> {
+    let (width, height) = frame.image.dimensions();
+    match (u32::try_from(x), u32::try_from(y)) {
+        (Ok(px), Ok(py)) if px < width && py < height => Ok((px, py)),
+        _ => Err(format!("像素 ({}, {}) 超出图像范围 {}x{}", x, y, width, height).into()),
+    }
+}
+
+/// 移动帧偏移，返回实际移动的帧数（锁定帧跳过）
+fn shift_offsets(lib: &mut Library, indices: Vec<INT>, dx: INT, dy: INT) -> ScriptResult<INT> {
+    let mut loader = lib.borrow_mut();
+    let indices = indices
+        .into_iter()
+        .map(|index| frame_index(&loader, index))
+        .collect::<ScriptResult<Vec<_>>>()?;
+    let shifted = loader
+        .shift_offsets(&indices, dx as i16, dy as i16)
+        .map_err(script_error)?;
+    Ok(shifted.len() as INT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mir2_library::formats::mlibrary_v2::MImage;
+
+    #[test]
+    fn test_script_recolor_and_append() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let mut library = MLibraryV2::new(dir.join("源")).unwrap();
+        let red = ::image::RgbaImage::from_pixel(2, 2, ::image::Rgba([200, 0, 0, 255]));
+        library.add_image(&MImage::from_image(&red, 3, -5));
+        library.add_image(&MImage::new());
+        library.add_image(&MImage::from_image(&red, 1, 1));
+        library.save().unwrap();
+
+        let script = r#"
+            let src = open(ARGS[0]);
+            let out = create(ARGS[1]);
+            for i in 0..src.count() {
+                let frame = src.frame(i);
+                if frame == () { continue; }
+                for y in 0..frame.height {
+                    for x in 0..frame.width {
+                        let p = frame.get_pixel(x, y);
+                        frame.set_pixel(x, y, [p[2], p[1], p[0], p[3]]);
+                    }
+                }
+                out.append(frame);
+            }
+            out.shift_offsets(0..=1, 10, 0);
+            out.save();
+            src.save_as(ARGS[2]);
+        "#;
+        let (src, out, copy) = (dir.join("源.Lib"), dir.join("蓝.Lib"), dir.join("副本.Lib"));
+        let args = [
+            src.to_str().unwrap(),
+            out.to_str().unwrap(),
+            copy.to_str().unwrap(),
+        ];
+        run(script, &args, EncodeOptions::default()).unwrap();
+
+        let (_, mut loader) = LibraryLoader::load(&out).unwrap();
+        assert_eq!(loader.image_count(), 2);
+        let frame = loader.copy_frame(0).unwrap().unwrap();
+        assert_eq!(frame.image.get_pixel(0, 0).0, [0, 0, 200, 255]);
+        assert_eq!((frame.x, frame.y), (13, -5));
+        let (_, copy) = LibraryLoader::load(&copy).unwrap();
+        assert_eq!(copy.image_count(), 3);
+
+        // 出错时报告脚本位置
+        let error = run("open(ARGS[0]).frame(9);", &args, EncodeOptions::default());
+        assert!(
+            matches!(error, Err(LibraryError::Script(message)) if message.contains("超出范围"))
+        );
+    }
+}
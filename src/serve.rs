@@ -0,0 +1,517 @@
+//! 本地渲染服务（`serve` 命令）
+//!
+//! 在本机开一个只读的 HTTP 接口，网页版地图编辑器、资料站等工具可以直接从原始
+//! 资源库取图，不必先导出成 PNG：
+//!
+//! - `GET /libs`：资源目录下全部库的名称（相对资源目录的路径，以 `/` 分隔）
+//! - `GET /lib/{name}/info`：库的格式、帧数及每帧的尺寸和偏移（JSON）
+//! - `GET /lib/{name}/frame/{i}.png`：第 `i` 帧的 PNG，空帧返回 404
+//!
+//! 连接由固定数量的工作线程处理，每个连接有读写超时，空闲的客户端不会占住服务；
+//! 请求行和请求头合计超过 [`MAX_REQUEST_HEAD`] 字节时返回 431。库列表由后台线程
+//! 每隔 [`RESCAN_INTERVAL`] 重新扫描，请求本身不遍历资源目录。
+//! 最近用过的库保持打开并在线程间共享，再次请求时不必重新读取索引；同一个库的请求
+//! 依次解码，不同库的请求并行处理。只接受列表中的库名，不会读取资源目录以外的文件。
+
+use ::image::ImageFormat;
+use mir2_library::error::{LibraryError, Result};
+use mir2_library::formats::LibraryLoader;
+use mir2_library::formats::integrity::find_libraries;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Cursor, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+/// 默认监听端口
+pub const DEFAULT_PORT: u16 = 8765;
+
+/// 同时保持打开的库数
+const MAX_OPEN_LIBRARIES: usize = 8;
+
+/// 处理连接的工作线程数
+const WORKERS: usize = 8;
+
+/// 读取请求和写回响应的超时
+const IO_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 请求行和请求头合计的字节上限
+const MAX_REQUEST_HEAD: u64 = 8192;
+
+/// 重新扫描资源目录的间隔
+const RESCAN_INTERVAL: Duration = Duration::from_secs(30);
+
+/// 在线程间共享的已打开库
+type SharedLoader = Arc<Mutex<LibraryLoader>>;
+
+/// 一次请求的响应
+#[derive(Debug)]
+pub struct Response {
+    /// HTTP 状态码
+    pub status: u16,
+    /// Content-Type
+    pub content_type: &'static str,
+    /// 响应体
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    fn json(value: &impl Serialize) -> Self {
+        match serde_json::to_vec(value) {
+            Ok(body) => Self {
+                status: 200,
+                content_type: "application/json; charset=utf-8",
+                body,
+            },
+            Err(e) => Self::error(&LibraryError::from(e)),
+        }
+    }
+
+    fn png(body: Vec<u8>) -> Self {
+        Self {
+            status: 200,
+            content_type: "image/png",
+            body,
+        }
+    }
+
+    fn text(status: u16, text: &str) -> Self {
+        Self {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            body: text.as_bytes().to_vec(),
+        }
+    }
+
+    fn not_found(text: &str) -> Self {
+        Self::text(404, text)
+    }
+
+    /// 库操作失败：索引越界按 404，其余按 500
+    fn error(e: &LibraryError) -> Self {
+        match e {
+            LibraryError::IndexOutOfBounds(_) => Self::not_found(&e.to_string()),
+            _ => Self::text(500, &e.to_string()),
+        }
+    }
+
+    fn reason(&self) -> &'static str {
+        match self.status {
+            200 => "OK",
+            400 => "Bad Request",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            431 => "Request Header Fields Too Large",
+            _ => "Internal Server Error",
+        }
+    }
+}
+
+/// `/lib/{name}/info` 的内容
+#[derive(Serialize)]
+struct LibrarySummary<'a> {
+    name: &'a str,
+    format: String,
+    frames: usize,
+    offsets: Vec<FrameSummary>,
+}
+
+/// 单帧的尺寸和偏移
+#[derive(Serialize)]
+struct FrameSummary {
+    width: i32,
+    height: i32,
+    x: i32,
+    y: i32,
+}
+
+/// 资源目录上的只读服务，可在多个线程中同时处理请求
+pub struct AssetServer {
+    root: PathBuf,
+    /// 资源目录下的库名
+    names: RwLock<Vec<String>>,
+    /// 最近用过的库（最新的在前）
+    open: Mutex<VecDeque<(String, SharedLoader)>>,
+}
+
+impl AssetServer {
+    /// 扫描资源目录下的库
+    pub fn new(root: &Path) -> Result<Self> {
+        if !root.is_dir() {
+            return Err(LibraryError::FileNotFound(root.display().to_string()));
+        }
+        let server = Self {
+            root: root.to_path_buf(),
+            names: RwLock::new(Vec::new()),
+            open: Mutex::new(VecDeque::new()),
+        };
+        server.rescan();
+        Ok(server)
+    }
+
+    /// 资源目录下的库数
+    pub fn library_count(&self) -> usize {
+        self.names.read().unwrap().len()
+    }
+
+    /// 重新扫描资源目录，遍历时不占用库名列表的锁
+    fn rescan(&self) {
+        let names = find_libraries(&self.root)
+            .iter()
+            .filter_map(|path| path.strip_prefix(&self.root).ok())
+            .map(|relative| {
+                relative
+                    .components()
+                    .map(|c| c.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/")
+            })
+            .collect();
+        *self.names.write().unwrap() = names;
+    }
+
+    /// 处理一个 GET 请求（`target` 为请求行中的路径，可带查询串）
+    pub fn handle(&self, target: &str) -> Response {
+        let path = target.split(['?', '#']).next().unwrap_or_default();
+        let Some(path) = percent_decode(path) else {
+            return Response::text(400, "路径编码无效");
+        };
+
+        if path == "/libs" {
+            return Response::json(&*self.names.read().unwrap());
+        }
+        let Some(rest) = path.strip_prefix("/lib/") else {
+            return Response::not_found("未知的接口");
+        };
+        if let Some(name) = rest.strip_suffix("/info") {
+            return self.info(name);
+        }
+        if let Some((name, frame)) = rest.rsplit_once("/frame/")
+            && let Some(index) = frame.strip_suffix(".png")
+        {
+            return match index.parse::<usize>() {
+                Ok(index) => self.frame(name, index),
+                Err(_) => Response::text(400, &format!("无效的帧索引: {}", index)),
+            };
+        }
+        Response::not_found("未知的接口")
+    }
+
+    fn info(&self, name: &str) -> Response {
+        let shared = match self.loader(name) {
+            Ok(shared) => shared,
+            Err(response) => return response,
+        };
+        let mut loader = shared.lock().unwrap();
+        let Some(info) = loader.info().cloned() else {
+            return Response::text(500, "库未加载");
+        };
+        let mut offsets = Vec::with_capacity(info.image_count);
+        for index in 0..info.image_count {
+            match loader.get_image_info(index) {
+                Ok(frame) => offsets.push(FrameSummary {
+                    width: frame.width,
+                    height: frame.height,
+                    x: frame.x,
+                    y: frame.y,
+                }),
+                Err(e) => return Response::error(&e),
+            }
+        }
+        Response::json(&LibrarySummary {
+            name,
+            format: info.format_name(),
+            frames: info.image_count,
+            offsets,
+        })
+    }
+
+    fn frame(&self, name: &str, index: usize) -> Response {
+        let shared = match self.loader(name) {
+            Ok(shared) => shared,
+            Err(response) => return response,
+        };
+        let image = {
+            let mut loader = shared.lock().unwrap();
+            if index >= loader.image_count() {
+                return Response::not_found(&format!("索引超出范围: {}", index));
+            }
+            match loader.get_frame(index) {
+                Ok(Some(image)) => image,
+                Ok(None) => return Response::not_found(&format!("第 {} 帧为空帧", index)),
+                Err(e) => return Response::error(&e),
+            }
+        };
+        let mut png = Cursor::new(Vec::new());
+        match image.write_to(&mut png, ImageFormat::Png) {
+            Ok(()) => Response::png(png.into_inner()),
+            Err(e) => Response::error(&LibraryError::from(e)),
+        }
+    }
+
+    /// 取得已打开的库，不在最近列表中时打开并挤掉最久未用的库
+    ///
+    /// 读取索引时不占用列表的锁，其它库的请求照常处理。
+    fn loader(&self, name: &str) -> std::result::Result<SharedLoader, Response> {
+        if let Some(shared) = self.recent(name) {
+            return Ok(shared);
+        }
+        if !self.names.read().unwrap().iter().any(|known| known == name) {
+            return Err(Response::not_found(&format!("没有名为 {} 的库", name)));
+        }
+        let (_, loader) =
+            LibraryLoader::load(&self.root.join(name)).map_err(|e| Response::error(&e))?;
+        tracing::debug!("打开库: {}", name);
+
+        // 其它线程可能同时打开了同一个库，沿用先放入列表的那个
+        let mut open = self.open.lock().unwrap();
+        if let Some((_, shared)) = open.iter().find(|(open, _)| open == name) {
+            return Ok(Arc::clone(shared));
+        }
+        let shared = Arc::new(Mutex::new(loader));
+        open.truncate(MAX_OPEN_LIBRARIES - 1);
+        open.push_front((name.to_string(), Arc::clone(&shared)));
+        Ok(shared)
+    }
+
+    /// 在最近列表中查找已打开的库并移到最前
+    fn recent(&self, name: &str) -> Option<SharedLoader> {
+        let mut open = self.open.lock().unwrap();
+        let pos = open.iter().position(|(open, _)| open == name)?;
+        let entry = open.remove(pos).expect("位置有效");
+        let shared = Arc::clone(&entry.1);
+        open.push_front(entry);
+        Some(shared)
+    }
+}
+
+/// 在 `addr` 上监听，由工作线程处理连接，直到进程退出
+pub fn run(server: AssetServer, addr: &str) -> Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    serve(server, listener)
+}
+
+/// 在已绑定的端口上提供服务
+fn serve(server: AssetServer, listener: TcpListener) -> Result<()> {
+    tracing::info!(
+        "正在提供 {:?} 下的 {} 个库: http://{}/libs",
+        server.root,
+        server.library_count(),
+        listener.local_addr()?
+    );
+
+    let server = Arc::new(server);
+    {
+        let server = Arc::clone(&server);
+        std::thread::spawn(move || {
+            loop {
+                std::thread::sleep(RESCAN_INTERVAL);
+                server.rescan();
+            }
+        });
+    }
+    let (streams, incoming) = crossbeam_channel::unbounded::<TcpStream>();
+    for _ in 0..WORKERS {
+        let server = Arc::clone(&server);
+        let incoming = incoming.clone();
+        std::thread::spawn(move || {
+            for stream in incoming {
+                if let Err(e) = serve_connection(&server, stream) {
+                    tracing::warn!("处理请求失败: {}", e);
+                }
+            }
+        });
+    }
+
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                tracing::warn!("接受连接失败: {}", e);
+                continue;
+            }
+        };
+        if streams.send(stream).is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// 读取请求行和请求头，写回响应后关闭连接；客户端超过 [`IO_TIMEOUT`] 没有动静时放弃
+fn serve_connection(server: &AssetServer, stream: TcpStream) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(IO_TIMEOUT))?;
+    stream.set_write_timeout(Some(IO_TIMEOUT))?;
+    let mut reader = BufReader::new(stream);
+    let request_line = match read_request_line(&mut reader)? {
+        Ok(request_line) => request_line,
+        Err(response) => return write_response(reader.into_inner(), "", &response),
+    };
+
+    let mut parts = request_line.split_whitespace();
+    let (method, target) = (parts.next().unwrap_or(""), parts.next().unwrap_or("/"));
+    let response = match method {
+        "GET" | "HEAD" => server.handle(target),
+        _ => Response::text(405, "只支持 GET 请求"),
+    };
+    tracing::debug!("{} {} -> {}", method, target, response.status);
+    write_response(reader.into_inner(), method, &response)
+}
+
+/// 读取请求行，跳过请求头（读到空行为止）
+///
+/// 合计超过 [`MAX_REQUEST_HEAD`] 字节时返回 431，请求行不是 UTF-8 时返回 400。
+fn read_request_line(
+    reader: &mut impl BufRead,
+) -> std::io::Result<std::result::Result<String, Response>> {
+    let mut head = reader.by_ref().take(MAX_REQUEST_HEAD);
+    let mut request_line = Vec::new();
+    let mut line = Vec::new();
+    let mut first = true;
+    loop {
+        line.clear();
+        head.read_until(b'\n', &mut line)?;
+        // 读满上限仍没有读到空行
+        if head.limit() == 0 && !line.ends_with(b"\n") {
+            return Ok(Err(Response::text(431, "请求头过长")));
+        }
+        if first {
+            first = false;
+            request_line = std::mem::take(&mut line);
+        } else if line.trim_ascii().is_empty() {
+            // 空行，或客户端已关闭连接
+            break;
+        }
+    }
+    Ok(String::from_utf8(request_line).map_err(|_| Response::text(400, "请求行无效")))
+}
+
+/// 写回响应并关闭连接，`HEAD` 请求只写响应头
+fn write_response(mut stream: TcpStream, method: &str, response: &Response) -> std::io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n\
+         Access-Control-Allow-Origin: *\r\nConnection: close\r\n\r\n",
+        response.status,
+        response.reason(),
+        response.content_type,
+        response.body.len()
+    )?;
+    if method != "HEAD" {
+        stream.write_all(&response.body)?;
+    }
+    stream.flush()
+}
+
+/// 解码 URL 路径中的 `%XX`，结果不是有效 UTF-8 时返回 `None`
+fn percent_decode(text: &str) -> Option<String> {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::image::RgbaImage;
+    use mir2_library::formats::mlibrary_v2::{MImage, MLibraryV2};
+
+    #[test]
+    fn test_serve_frames_and_info() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        std::fs::create_dir_all(root.join("Data")).unwrap();
+        let mut library = MLibraryV2::new(root.join("Data").join("怪物")).unwrap();
+        library.add_image(&MImage::from_image(&RgbaImage::new(3, 2), 4, -1));
+        library.add_image(&MImage::new());
+        library.save().unwrap();
+
+        let server = AssetServer::new(root).unwrap();
+        let libs = server.handle("/libs");
+        assert_eq!(libs.status, 200);
+        assert_eq!(
+            String::from_utf8(libs.body).unwrap(),
+            r#"["Data/怪物.Lib"]"#
+        );
+
+        let info = server.handle("/lib/Data/%E6%80%AA%E7%89%A9.Lib/info");
+        let json: serde_json::Value = serde_json::from_slice(&info.body).unwrap();
+        assert_eq!(json["frames"], 2);
+        assert_eq!(json["offsets"][0]["x"], 4);
+
+        let frame = server.handle("/lib/Data/怪物.Lib/frame/0.png?v=1");
+        assert_eq!((frame.status, frame.content_type), (200, "image/png"));
+        let image = ::image::load_from_memory(&frame.body).unwrap();
+        assert_eq!((image.width(), image.height()), (3, 2));
+
+        assert_eq!(server.handle("/lib/Data/怪物.Lib/frame/1.png").status, 404);
+        assert_eq!(server.handle("/lib/Data/怪物.Lib/frame/9.png").status, 404);
+        assert_eq!(server.handle("/lib/Data/怪物.Lib/frame/x.png").status, 400);
+        assert_eq!(server.handle("/lib/../secret.Lib/info").status, 404);
+    }
+
+    #[test]
+    fn test_idle_client_does_not_block_others() {
+        let temp = tempfile::tempdir().unwrap();
+        let server = AssetServer::new(temp.path()).unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || serve(server, listener));
+
+        // 第一个客户端连上后什么也不发
+        let _idle = TcpStream::connect(addr).unwrap();
+        let mut client = TcpStream::connect(addr).unwrap();
+        client.set_read_timeout(Some(IO_TIMEOUT / 2)).unwrap();
+        client.write_all(b"GET /libs HTTP/1.1\r\n\r\n").unwrap();
+        let mut response = String::new();
+        client.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK"), "{}", response);
+        assert!(response.ends_with("[]"));
+    }
+
+    #[test]
+    fn test_request_head_is_capped() {
+        let mut request = Cursor::new(b"GET /libs HTTP/1.1\r\nHost: x\r\n\r\n".to_vec());
+        let line = read_request_line(&mut request).unwrap().unwrap();
+        assert_eq!(line.trim_end(), "GET /libs HTTP/1.1");
+
+        let mut long = b"GET /libs HTTP/1.1\r\nX-Filler: ".to_vec();
+        long.resize(MAX_REQUEST_HEAD as usize * 2, b'a');
+        let response = read_request_line(&mut Cursor::new(long))
+            .unwrap()
+            .unwrap_err();
+        assert_eq!(response.status, 431);
+        let response = read_request_line(&mut Cursor::new(b"GET /\xff HTTP/1.1\r\n\r\n".to_vec()))
+            .unwrap()
+            .unwrap_err();
+        assert_eq!(response.status, 400);
+    }
+
+    #[test]
+    fn test_libs_served_from_last_scan() {
+        let temp = tempfile::tempdir().unwrap();
+        let server = AssetServer::new(temp.path()).unwrap();
+        MLibraryV2::new(temp.path().join("Hum"))
+            .unwrap()
+            .save()
+            .unwrap();
+
+        // 请求不遍历资源目录，新增的库等下一次扫描
+        assert_eq!(server.handle("/libs").body, b"[]");
+        server.rescan();
+        assert_eq!(server.handle("/libs").body, br#"["Hum.Lib"]"#);
+    }
+}
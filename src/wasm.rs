@@ -0,0 +1,104 @@
+//! 浏览器接口（`wasm` feature）
+//!
+//! 编译为 `wasm32-unknown-unknown` 后由 wasm-bindgen 生成 JS 绑定，网页中直接
+//! 查看用户选择的库文件，数据全部在内存中，不访问文件系统。示例页面见
+//! `web/index.html`。编译：
+//!
+//! ```text
+//! cargo rustc --lib --release --target wasm32-unknown-unknown --no-default-features --features wasm --crate-type cdylib
+//! wasm-bindgen --target web --out-dir web/pkg target/wasm32-unknown-unknown/release/mir2_library.wasm
+//! ```
+//!
+//! 带索引文件的格式（`.wzl`、`.wil`、`.D`）需同时传入索引文件（`.wzx`、`.wix`）。
+
+use crate::formats::{LibraryLoader, LibraryType, Source};
+use image::ImageFormat;
+use std::io::Cursor;
+use std::path::Path;
+use wasm_bindgen::prelude::*;
+
+/// 已打开的库
+#[wasm_bindgen]
+pub struct Library {
+    loader: LibraryLoader,
+}
+
+#[wasm_bindgen]
+impl Library {
+    /// 由文件内容打开库，格式按 `file_name` 的扩展名和文件头识别；带索引文件的
+    /// 格式需同时传入索引文件的内容
+    pub fn from_bytes(
+        file_name: &str,
+        data: Vec<u8>,
+        index: Option<Vec<u8>>,
+    ) -> Result<Library, JsError> {
+        let lib_type = Path::new(file_name)
+            .extension()
+            .and_then(|ext| LibraryType::from_extension(&format!(".{}", ext.to_string_lossy())))
+            .ok_or_else(|| JsError::new(&format!("不支持的文件格式: {}", file_name)))?;
+
+        let mut files = vec![(lib_type.main_extension(), data)];
+        if let (Some(ext), Some(index)) = (lib_type.index_extension(), index) {
+            files.push((ext, index));
+        }
+        let (_, loader) = LibraryLoader::from_source(file_name, Source::memory(files))
+            .map_err(|e| JsError::new(&e.to_string()))?;
+        Ok(Library { loader })
+    }
+
+    /// 帧数
+    pub fn count(&self) -> usize {
+        self.loader.image_count()
+    }
+
+    /// 格式名称
+    pub fn format_name(&self) -> String {
+        self.loader
+            .info()
+            .map(|info| info.format_name())
+            .unwrap_or_default()
+    }
+
+    /// 把帧编码为 PNG，空帧返回 `undefined`
+    pub fn frame_png(&mut self, index: usize) -> Result<Option<Vec<u8>>, JsError> {
+        let frame = self
+            .loader
+            .get_frame(index)
+            .map_err(|e| JsError::new(&e.to_string()))?;
+        let Some(frame) = frame else {
+            return Ok(None);
+        };
+        let mut data = Cursor::new(Vec::new());
+        frame
+            .write_to(&mut data, ImageFormat::Png)
+            .map_err(|e| JsError::new(&e.to_string()))?;
+        Ok(Some(data.into_inner()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::mlibrary_v2::{MImage, MLibraryV2};
+
+    #[test]
+    fn test_library_from_bytes() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let frame = image::RgbaImage::from_pixel(3, 2, image::Rgba([20, 200, 20, 255]));
+        let mut source = MLibraryV2::new(dir.join("Hum")).unwrap();
+        source.add_image(&MImage::from_image(&frame, 0, 0));
+        source.add_image(&MImage::new());
+        source.save().unwrap();
+        let data = std::fs::read(dir.join("Hum.Lib")).unwrap();
+
+        let mut library = Library::from_bytes("Hum.Lib", data, None).unwrap();
+        assert_eq!(library.count(), 2);
+        assert_eq!(library.format_name(), "MLibrary V2");
+
+        let png = library.frame_png(0).unwrap().unwrap();
+        let decoded = image::load_from_memory_with_format(&png, ImageFormat::Png).unwrap();
+        assert_eq!(decoded.to_rgba8(), frame);
+        assert!(library.frame_png(1).unwrap().is_none());
+    }
+}